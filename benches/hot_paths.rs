@@ -0,0 +1,112 @@
+use std::rc::Rc;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use veronica::config::config;
+use veronica::core::backtesting;
+use veronica::crawler::{finmind, stocklist};
+use veronica::dataview::view::{self, Transform};
+use veronica::storage::backend::{self, BackendOp};
+use veronica::strategy::{schema, strategy};
+
+const STOCK_ID: &str = "2330";
+
+fn get_bench_path(name: &str) -> String {
+    std::env::temp_dir()
+        .join(format!("veronica-bench-{}-{}", name, std::process::id()))
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+fn get_start_date() -> chrono::NaiveDate {
+    chrono::NaiveDate::from_ymd_opt(2020, 1, 1).unwrap()
+}
+
+fn get_end_date() -> chrono::NaiveDate {
+    chrono::NaiveDate::from_ymd_opt(2021, 12, 31).unwrap()
+}
+
+/// Two years of synthetic weekday records oscillating around an upward drift.
+fn prepare_backend(db_path: &str) -> Rc<backend::SledBackend> {
+    let backend_op = Rc::new(backend::SledBackend::new(db_path).unwrap());
+    let mut records = Vec::new();
+    let mut date = get_start_date();
+    let mut day = 0;
+
+    while date <= get_end_date() {
+        if chrono::Datelike::weekday(&date).number_from_monday() <= 5 {
+            let close = 100.0 + day as f64 * 0.1 + (day as f64 / 10.0).sin() * 5.0;
+
+            records.push((
+                STOCK_ID.to_owned(),
+                schema::RawData {
+                    open: close - 0.5,
+                    high: close + 1.0,
+                    low: close - 1.0,
+                    close,
+                    spread: 0.5,
+                    date,
+                    trading_volume: 1000000,
+                    trading_money: (close * 1000000.0) as u64,
+                },
+            ));
+            day += 1;
+        }
+        date = date.succ_opt().unwrap();
+    }
+    backend_op.batch_insert(&records).unwrap();
+    backend_op
+}
+
+fn bench_hot_paths(c: &mut Criterion) {
+    let db_path = get_bench_path("db");
+    let backend_op = prepare_backend(&db_path);
+    let year_start_date = chrono::NaiveDate::from_ymd_opt(2021, 1, 1).unwrap();
+    let records = backend_op
+        .query_by_range(STOCK_ID, year_start_date, get_end_date())
+        .unwrap();
+
+    c.bench_function("query_by_range (1 year)", |b| {
+        b.iter(|| {
+            backend_op
+                .query_by_range(STOCK_ID, year_start_date, get_end_date())
+                .unwrap()
+        })
+    });
+    c.bench_function("BollingerBandView::transform (1 year)", |b| {
+        b.iter(|| view::BollingerBandView::transform(&records).unwrap())
+    });
+
+    let config = config::Config {
+        db_path: db_path.to_owned(),
+        portfolio_path: get_bench_path("portfolio"),
+        ..Default::default()
+    };
+    let crawler = Rc::new(stocklist::StockListCrawler::new(
+        Rc::new(finmind::Finmind::new("")),
+        vec![STOCK_ID.to_owned()],
+    ));
+    let mut group = c.benchmark_group("backtest");
+
+    group.sample_size(10);
+    group.bench_function("single stock (1 year)", |b| {
+        b.iter(|| {
+            let mut backtesting = backtesting::Backtesting::new(
+                config.clone(),
+                crawler.clone(),
+                backend_op.clone(),
+                strategy::Strategies::BollingerBand,
+            );
+
+            backtesting.run(year_start_date, get_end_date());
+        })
+    });
+    group.finish();
+
+    std::fs::remove_dir_all(&db_path).ok();
+    std::fs::remove_dir_all(&config.portfolio_path).ok();
+}
+
+criterion_group!(benches, bench_hot_paths);
+criterion_main!(benches);
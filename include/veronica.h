@@ -0,0 +1,38 @@
+#ifndef VERONICA_H
+#define VERONICA_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define FFI_OK 0
+/* A pointer is null, a date cannot be read or the strategy id is unknown. */
+#define FFI_INVALID_ARGUMENT -1
+/* The strategy failed to score the series. */
+#define FFI_STRATEGY_ERROR -2
+
+/* Parallel arrays of `len` daily bars, oldest first. Dates are YYYYMMDD. */
+typedef struct {
+    const int32_t *dates;
+    const double *open;
+    const double *high;
+    const double *low;
+    const double *close;
+    const uint64_t *volume;
+    size_t len;
+} FfiSeries;
+
+typedef struct {
+    /* 0: Bollinger band */
+    uint32_t strategy;
+    int32_t assess_date;
+} FfiScoreParams;
+
+typedef struct {
+    /* Above zero is a buy signal. */
+    int64_t point;
+    uint64_t trading_volume;
+} FfiScore;
+
+int32_t score_stock(const FfiSeries *series, const FfiScoreParams *params, FfiScore *score);
+
+#endif
@@ -0,0 +1,163 @@
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::storage::backend;
+use crate::strategy::schema;
+
+pub const SPREAD_AUDIT_FILENAME: &str = "spread_audit.yaml";
+/// Prices are quoted to the cent, so smaller differences are rounding.
+pub const SPREAD_TOLERANCE: f64 = 0.005;
+/// Calendar days between consecutive records beyond which a gap is more likely missing data
+/// than a weekend or a short holiday.
+pub const MAX_MARKET_GAP_DAYS: i64 = 4;
+
+#[derive(Debug)]
+pub enum Error {
+    Backend(backend::Error),
+}
+
+impl From<backend::Error> for Error {
+    fn from(err: backend::Error) -> Error {
+        Error::Backend(err)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MismatchCause {
+    /// The previous record is the prior trading day, so one of the closes was revised.
+    Revision,
+    /// The previous record is further back than a market gap, so days are probably missing.
+    MissingDays,
+}
+
+/// A record whose stored spread differs from the change since the previous stored close.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpreadMismatch {
+    pub stock_id: String,
+    pub date: chrono::NaiveDate,
+    pub prev_date: chrono::NaiveDate,
+    pub stored: f64,
+    pub expected: f64,
+    pub cause: MismatchCause,
+}
+
+/// Compares the stored spread of every record of `records`, sorted by date, with the change
+/// from the previous close. The first record has nothing to compare with and is skipped.
+pub fn audit(stock_id: &str, records: &[schema::RawData]) -> Vec<SpreadMismatch> {
+    let mut mismatches = Vec::new();
+
+    for index in 1..records.len() {
+        let prev = &records[index - 1];
+        let record = &records[index];
+        let expected = record.close - prev.close;
+
+        if (record.spread - expected).abs() <= SPREAD_TOLERANCE {
+            continue;
+        }
+        let cause = if (record.date - prev.date).num_days() > MAX_MARKET_GAP_DAYS {
+            MismatchCause::MissingDays
+        } else {
+            MismatchCause::Revision
+        };
+
+        mismatches.push(SpreadMismatch {
+            stock_id: stock_id.to_owned(),
+            date: record.date,
+            prev_date: prev.date,
+            stored: record.spread,
+            expected,
+            cause,
+        });
+    }
+    mismatches
+}
+
+pub struct SpreadAudit {
+    pub backend_op: Rc<dyn backend::BackendOp>,
+}
+
+impl SpreadAudit {
+    pub fn new(backend_op: Rc<dyn backend::BackendOp>) -> Self {
+        SpreadAudit { backend_op }
+    }
+
+    /// Audits the stored records of every stock, rewriting the mismatched spreads with the
+    /// recomputed ones when `correct` is set.
+    pub fn run(&self, stock_ids: &[String], correct: bool) -> Result<Vec<SpreadMismatch>, Error> {
+        let mut mismatches = Vec::new();
+
+        for stock_id in stock_ids {
+            let mut records = self.backend_op.query_all(stock_id)?;
+
+            records.sort_by_key(|record| record.date);
+
+            let stock_mismatches = audit(stock_id, &records);
+
+            if correct && !stock_mismatches.is_empty() {
+                let corrected: Vec<(String, schema::RawData)> = stock_mismatches
+                    .iter()
+                    .filter_map(|mismatch| {
+                        records
+                            .iter()
+                            .find(|record| record.date == mismatch.date)
+                            .map(|record| {
+                                let mut record = record.clone();
+
+                                record.spread = mismatch.expected;
+                                (stock_id.to_owned(), record)
+                            })
+                    })
+                    .collect();
+
+                self.backend_op.batch_insert(&corrected)?;
+            }
+            mismatches.extend(stock_mismatches);
+        }
+        Ok(mismatches)
+    }
+}
+
+#[cfg(test)]
+mod audit_test {
+    use std::rc::Rc;
+
+    use crate::storage::backend::BackendOp;
+    use crate::storage::memory;
+    use crate::testkit::generator;
+
+    use super::{MismatchCause, SpreadAudit};
+
+    #[test]
+    fn spread_audit_check() {
+        let backend_op = Rc::new(memory::MemoryBackend::new());
+        let start_date = chrono::NaiveDate::from_ymd_opt(2022, 1, 3).unwrap();
+        let mut records = generator::SeriesBuilder::new(start_date, 100.0)
+            .trend(12, 1.0)
+            .build();
+
+        records[3].spread += 1.0;
+        records.drain(6..10);
+        backend_op
+            .batch_insert(
+                &records
+                    .iter()
+                    .map(|record| ("0050".to_owned(), record.clone()))
+                    .collect(),
+            )
+            .unwrap();
+
+        let spread_audit = SpreadAudit::new(backend_op);
+        let mismatches = spread_audit.run(&["0050".to_owned()], true).unwrap();
+
+        assert_eq!(mismatches.len(), 2);
+        assert_eq!(mismatches[0].date, records[3].date);
+        assert_eq!(mismatches[0].cause, MismatchCause::Revision);
+        assert_eq!(mismatches[1].date, records[6].date);
+        assert_eq!(mismatches[1].cause, MismatchCause::MissingDays);
+        assert!(spread_audit
+            .run(&["0050".to_owned()], false)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::backtesting;
+use crate::diagram::diagram;
+use crate::report::html;
+
+pub const CONTRIBUTION_REPORT_FILENAME: &str = "contribution.html";
+
+/// P&L a stock contributed over a run, from its settled trades.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StockContribution {
+    pub stock_id: String,
+    pub pnl: i64,
+    /// Share of the total P&L, in percent; negative for stocks that lost money in a run that
+    /// made some.
+    pub share: f64,
+    pub trade_count: usize,
+    /// Share of the trades settled above their hold price, in percent.
+    pub hit_rate: f64,
+}
+
+/// Stocks ranked by their contribution to the P&L of a run, best first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContributionReport {
+    pub stocks: Vec<StockContribution>,
+    pub total_pnl: i64,
+}
+
+impl ContributionReport {
+    pub fn build(trade_ledger: &[backtesting::TradeRecord]) -> Self {
+        let mut stocks: Vec<StockContribution> = Vec::new();
+
+        for trade_record in trade_ledger {
+            let pnl = (trade_record.settle_price as i64 - trade_record.hold_price as i64)
+                * trade_record.num as i64;
+            let index = match stocks
+                .iter()
+                .position(|stock| stock.stock_id == trade_record.stock_id)
+            {
+                Some(index) => index,
+                None => {
+                    stocks.push(StockContribution {
+                        stock_id: trade_record.stock_id.to_owned(),
+                        pnl: 0,
+                        share: 0.0,
+                        trade_count: 0,
+                        hit_rate: 0.0,
+                    });
+                    stocks.len() - 1
+                }
+            };
+            let stock = &mut stocks[index];
+
+            stock.pnl += pnl;
+            stock.trade_count += 1;
+            if trade_record.get_return() > 0.0 {
+                stock.hit_rate += 1.0;
+            }
+        }
+
+        let total_pnl: i64 = stocks.iter().map(|stock| stock.pnl).sum();
+
+        for stock in stocks.iter_mut() {
+            stock.hit_rate = stock.hit_rate / stock.trade_count as f64 * 100.0;
+            if total_pnl != 0 {
+                stock.share = stock.pnl as f64 / total_pnl.abs() as f64 * 100.0;
+            }
+        }
+        stocks.sort_by_key(|stock| std::cmp::Reverse(stock.pnl));
+
+        ContributionReport { stocks, total_pnl }
+    }
+
+    /// Share of the total P&L, in percent, earned by the `count` best stocks; well over 100%
+    /// means the other stocks lost money overall and the run rests on a few names.
+    pub fn get_top_share(&self, count: usize) -> f64 {
+        self.stocks
+            .iter()
+            .take(count)
+            .map(|stock| stock.share)
+            .sum()
+    }
+
+    /// Page with a bar chart of the P&L by stock and a table of their trades.
+    pub fn to_html(&self, style: &diagram::DiagramStyle) -> String {
+        let mut plot = plotly::Plot::new();
+
+        plot.add_trace(
+            plotly::Bar::new(
+                self.stocks
+                    .iter()
+                    .map(|stock| stock.stock_id.to_owned())
+                    .collect(),
+                self.stocks.iter().map(|stock| stock.pnl).collect(),
+            )
+            .name("P&L"),
+        );
+        plot.set_layout(
+            style
+                .get_layout()
+                .title("P&L by stock".into())
+                .x_axis(plotly::layout::Axis::new().type_(plotly::layout::AxisType::Category)),
+        );
+
+        html::get_page(
+            "Contribution by stock",
+            &format!(
+                "<p>Total P&amp;L: {:+} over {} stocks. Best stock: {:.1}%, best two: {:.1}% \
+                 of the total.</p>{}{}",
+                self.total_pnl,
+                self.stocks.len(),
+                self.get_top_share(1),
+                self.get_top_share(2),
+                plot.to_inline_html(Some("contribution")),
+                html::get_table(
+                    &["Stock", "P&amp;L", "Share", "Trades", "Hit rate"],
+                    self.stocks
+                        .iter()
+                        .map(|stock| {
+                            vec![
+                                stock.stock_id.to_owned(),
+                                format!("{:+}", stock.pnl),
+                                format!("{:+.1}%", stock.share),
+                                stock.trade_count.to_string(),
+                                format!("{:.1}%", stock.hit_rate),
+                            ]
+                        })
+                        .collect(),
+                )
+            ),
+            true,
+        )
+    }
+}
+
+#[cfg(test)]
+mod contribution_test {
+    use crate::core::backtesting::TradeRecord;
+
+    use super::ContributionReport;
+
+    #[test]
+    fn build_check() {
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let get_trade = |stock_id: &str, hold_price: u32, settle_price: u32| TradeRecord {
+            stock_id: stock_id.to_owned(),
+            hold_date: date,
+            settle_date: date,
+            num: 1000,
+            hold_price,
+            settle_price,
+            beta: None,
+            benchmark_return: None,
+            settle_reason: None,
+        };
+        let report = ContributionReport::build(&[
+            get_trade("2330", 100, 130),
+            get_trade("2317", 100, 95),
+            get_trade("2330", 100, 90),
+            get_trade("2454", 100, 105),
+        ]);
+
+        assert_eq!(report.total_pnl, 20000);
+        assert_eq!(
+            report
+                .stocks
+                .iter()
+                .map(|stock| stock.stock_id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["2330", "2454", "2317"]
+        );
+        assert_eq!(report.stocks[0].trade_count, 2);
+        assert_eq!(report.stocks[0].hit_rate, 50.0);
+        assert_eq!(report.stocks[0].share, 100.0);
+        assert_eq!(report.stocks[2].share, -25.0);
+        assert_eq!(report.get_top_share(2), 125.0);
+    }
+}
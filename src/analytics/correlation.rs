@@ -0,0 +1,201 @@
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::dataview::view;
+use crate::diagram::diagram;
+use crate::strategy::schema;
+
+pub const BENCHMARK_CORRELATION_FILENAME: &str = "benchmark_correlation.html";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollingPoint {
+    pub date: chrono::NaiveDate,
+    pub correlation: f64,
+    pub beta: f64,
+}
+
+/// Correlation and beta of the daily returns of a run against a benchmark, over the whole run
+/// and over a trailing window.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkCorrelation {
+    pub benchmark_id: String,
+    pub period: usize,
+    pub correlation: f64,
+    pub beta: f64,
+    pub rolling: Vec<RollingPoint>,
+}
+
+/// Correlation and beta of `pairs` of (return, benchmark return).
+fn get_statistics<'a>(pairs: impl Iterator<Item = &'a (f64, f64)> + Clone) -> (f64, f64) {
+    let count = pairs.clone().count() as f64;
+    let mean = pairs.clone().map(|(value, _)| value).sum::<f64>() / count;
+    let benchmark_mean = pairs.clone().map(|(_, value)| value).sum::<f64>() / count;
+    let mut covariance = 0.0;
+    let mut variance = 0.0;
+    let mut benchmark_variance = 0.0;
+
+    for (value, benchmark_value) in pairs {
+        covariance += (value - mean) * (benchmark_value - benchmark_mean);
+        variance += (value - mean).powi(2);
+        benchmark_variance += (benchmark_value - benchmark_mean).powi(2);
+    }
+    if variance == 0.0 || benchmark_variance == 0.0 {
+        return (0.0, 0.0);
+    }
+    (
+        covariance / (variance * benchmark_variance).sqrt(),
+        covariance / benchmark_variance,
+    )
+}
+
+impl BenchmarkCorrelation {
+    /// Pairs the daily `returns` of a run, in percent, with those of the benchmark on the same
+    /// days; days the benchmark did not trade are skipped. `None` without two such days.
+    pub fn build(
+        benchmark_id: &str,
+        returns: &[(chrono::NaiveDate, f64)],
+        benchmark_records: &[schema::RawData],
+        period: usize,
+    ) -> Option<Self> {
+        let benchmark_returns: HashMap<chrono::NaiveDate, f64> =
+            view::ReturnsView::transform_by_records(benchmark_records)
+                .iter()
+                .map(|view| (view.date, view.simple_return * 100.0))
+                .collect();
+        let pairs: Vec<(chrono::NaiveDate, (f64, f64))> = returns
+            .iter()
+            .filter_map(|(date, value)| {
+                benchmark_returns
+                    .get(date)
+                    .map(|benchmark_value| (*date, (*value, *benchmark_value)))
+            })
+            .collect();
+
+        if pairs.len() < 2 {
+            return None;
+        }
+
+        let (correlation, beta) = get_statistics(pairs.iter().map(|(_, pair)| pair));
+        let mut window: VecDeque<(f64, f64)> = VecDeque::new();
+        let mut rolling = Vec::new();
+
+        for (date, pair) in pairs.iter() {
+            window.push_back(*pair);
+            if window.len() > period {
+                window.pop_front();
+            }
+            if window.len() == period.max(2) {
+                let (correlation, beta) = get_statistics(window.iter());
+
+                rolling.push(RollingPoint {
+                    date: *date,
+                    correlation,
+                    beta,
+                });
+            }
+        }
+
+        Some(BenchmarkCorrelation {
+            benchmark_id: benchmark_id.to_owned(),
+            period,
+            correlation,
+            beta,
+            rolling,
+        })
+    }
+
+    /// Rolling correlation, with the rolling beta on a secondary axis.
+    pub fn to_plot(&self, style: &diagram::DiagramStyle) -> plotly::Plot {
+        let mut plot = plotly::Plot::new();
+        let dates: Vec<String> = self
+            .rolling
+            .iter()
+            .map(|point| point.date.format("%Y-%m-%d").to_string())
+            .collect();
+
+        plot.add_trace(
+            plotly::Scatter::new(
+                dates.clone(),
+                self.rolling.iter().map(|point| point.correlation).collect(),
+            )
+            .mode(plotly::common::Mode::Lines)
+            .name(&format!("{}-day correlation", self.period)),
+        );
+        plot.add_trace(
+            plotly::Scatter::new(dates, self.rolling.iter().map(|point| point.beta).collect())
+                .mode(plotly::common::Mode::Lines)
+                .y_axis("y2")
+                .name(&format!("{}-day beta", self.period)),
+        );
+        plot.set_layout(
+            style
+                .get_layout()
+                .title(
+                    format!(
+                        "Against {}: correlation {:.2}, beta {:.2} over the run",
+                        self.benchmark_id, self.correlation, self.beta
+                    )
+                    .as_str()
+                    .into(),
+                )
+                .y_axis(
+                    plotly::layout::Axis::new()
+                        .title("Correlation".into())
+                        .range(vec![-1.0, 1.0]),
+                )
+                .y_axis2(
+                    plotly::layout::Axis::new()
+                        .title("Beta".into())
+                        .overlaying("y")
+                        .side(plotly::layout::AxisSide::Right),
+                ),
+        );
+        plot
+    }
+}
+
+#[cfg(test)]
+mod correlation_test {
+    use crate::strategy::schema;
+
+    use super::BenchmarkCorrelation;
+
+    #[test]
+    fn build_check() {
+        let start_date = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let mut close = 100.0;
+        let mut benchmark_records = Vec::new();
+        let mut returns = Vec::new();
+
+        for day in 0..30 {
+            let date = start_date + chrono::Duration::days(day);
+            let benchmark_return = if day % 3 == 0 { 2.0 } else { -1.0 };
+
+            if day > 0 {
+                close *= 1.0 + benchmark_return / 100.0;
+                // Twice as volatile as the benchmark.
+                returns.push((date, 2.0 * benchmark_return));
+            }
+            benchmark_records.push(schema::RawData {
+                date,
+                close,
+                ..Default::default()
+            });
+        }
+
+        let correlation =
+            BenchmarkCorrelation::build("0050", &returns, &benchmark_records, 10).unwrap();
+
+        assert!((correlation.correlation - 1.0).abs() < 1e-9);
+        assert!((correlation.beta - 2.0).abs() < 1e-9);
+        assert_eq!(correlation.rolling.len(), returns.len() - 9);
+        assert!(correlation
+            .rolling
+            .iter()
+            .all(|point| (point.beta - 2.0).abs() < 1e-9));
+        assert!(
+            BenchmarkCorrelation::build("0050", &returns[..1], &benchmark_records, 10).is_none()
+        );
+    }
+}
@@ -0,0 +1,311 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::backtesting;
+use crate::diagram::diagram;
+use crate::report::html;
+
+pub const RETURN_DISTRIBUTION_FILENAME: &str = "return_distribution.html";
+pub const HISTOGRAM_BINS: usize = 50;
+/// Confidence levels the value at risk is reported at.
+pub const CONFIDENCE_LEVELS: [f64; 2] = [0.95, 0.99];
+
+/// Historical tail risk of daily returns at a confidence level, as positive losses in percent.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TailRisk {
+    pub confidence: f64,
+    /// Loss not exceeded on `confidence` of the days.
+    pub var: f64,
+    /// Mean loss on the days beyond the value at risk, also called expected shortfall.
+    pub cvar: f64,
+}
+
+/// Distribution of the daily portfolio returns of a run, in percent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReturnDistribution {
+    pub returns: Vec<f64>,
+    pub mean: f64,
+    pub sd: f64,
+    pub skew: f64,
+    /// Excess kurtosis, zero for normally distributed returns.
+    pub kurtosis: f64,
+    pub tail_risks: Vec<TailRisk>,
+}
+
+impl ReturnDistribution {
+    /// Distribution of `returns`, or `None` when there are too few of them to spread.
+    pub fn new(returns: Vec<f64>) -> Option<Self> {
+        if returns.len() < 2 {
+            return None;
+        }
+
+        let count = returns.len() as f64;
+        let mean = returns.iter().sum::<f64>() / count;
+        let get_moment = |power: i32| {
+            returns
+                .iter()
+                .map(|value| (value - mean).powi(power))
+                .sum::<f64>()
+                / count
+        };
+        let variance = get_moment(2);
+        let mut sorted = returns.clone();
+
+        sorted.sort_by(|lhs, rhs| lhs.total_cmp(rhs));
+
+        let tail_risks = CONFIDENCE_LEVELS
+            .iter()
+            .map(|confidence| {
+                let cutoff = get_quantile(&sorted, 1.0 - confidence);
+                let tail: Vec<f64> = sorted
+                    .iter()
+                    .take_while(|value| **value <= cutoff)
+                    .cloned()
+                    .collect();
+
+                TailRisk {
+                    confidence: *confidence,
+                    var: -cutoff,
+                    cvar: match tail.is_empty() {
+                        true => -cutoff,
+                        false => -tail.iter().sum::<f64>() / tail.len() as f64,
+                    },
+                }
+            })
+            .collect();
+
+        Some(ReturnDistribution {
+            mean,
+            sd: (variance * count / (count - 1.0)).sqrt(),
+            skew: match variance > 0.0 {
+                true => get_moment(3) / variance.powf(1.5),
+                false => 0.0,
+            },
+            kurtosis: match variance > 0.0 {
+                true => get_moment(4) / variance.powi(2) - 3.0,
+                false => 0.0,
+            },
+            tail_risks,
+            returns,
+        })
+    }
+
+    pub fn from_equity_series(
+        liquidity: u32,
+        equity_series: &[backtesting::EquityPoint],
+    ) -> Option<Self> {
+        ReturnDistribution::new(
+            get_daily_returns(liquidity, equity_series)
+                .into_iter()
+                .map(|(_, value)| value)
+                .collect(),
+        )
+    }
+
+    /// Points of a normal QQ plot: the normal quantile each sorted return would have, against
+    /// the return.
+    pub fn get_qq_points(&self) -> Vec<(f64, f64)> {
+        let mut sorted = self.returns.clone();
+        let count = sorted.len() as f64;
+
+        sorted.sort_by(|lhs, rhs| lhs.total_cmp(rhs));
+        sorted
+            .into_iter()
+            .enumerate()
+            .map(|(index, value)| (get_normal_quantile((index as f64 + 0.5) / count), value))
+            .collect()
+    }
+
+    /// Page with the statistics, a histogram and a normal QQ plot of the returns.
+    pub fn to_html(&self, style: &diagram::DiagramStyle) -> String {
+        let mut histogram = plotly::Plot::new();
+        let mut qq = plotly::Plot::new();
+        let (theoretical, sample): (Vec<f64>, Vec<f64>) = self.get_qq_points().into_iter().unzip();
+        let bounds = match (theoretical.first(), theoretical.last()) {
+            (Some(first), Some(last)) => vec![*first, *last],
+            _ => Vec::new(),
+        };
+        let mut rows = vec![
+            vec!["Days".to_owned(), self.returns.len().to_string()],
+            vec!["Mean".to_owned(), format!("{:+.3}%", self.mean)],
+            vec!["Standard deviation".to_owned(), format!("{:.3}%", self.sd)],
+            vec!["Skew".to_owned(), format!("{:.3}", self.skew)],
+            vec![
+                "Excess kurtosis".to_owned(),
+                format!("{:.3}", self.kurtosis),
+            ],
+        ];
+
+        for tail_risk in &self.tail_risks {
+            let level = tail_risk.confidence * 100.0;
+
+            rows.push(vec![
+                format!("VaR {}%", level),
+                format!("{:.3}%", tail_risk.var),
+            ]);
+            rows.push(vec![
+                format!("CVaR {}%", level),
+                format!("{:.3}%", tail_risk.cvar),
+            ]);
+        }
+
+        histogram.add_trace(
+            plotly::Histogram::new(self.returns.clone())
+                .name("Daily return (%)")
+                .n_bins_x(HISTOGRAM_BINS),
+        );
+        histogram.set_layout(
+            style
+                .get_layout()
+                .title("Daily returns".into())
+                .x_axis(plotly::layout::Axis::new().title("Return (%)".into())),
+        );
+        qq.add_trace(
+            plotly::Scatter::new(theoretical, sample)
+                .mode(plotly::common::Mode::Markers)
+                .name("Daily return (%)"),
+        );
+        qq.add_trace(
+            plotly::Scatter::new(
+                bounds.clone(),
+                bounds
+                    .iter()
+                    .map(|quantile| self.mean + self.sd * quantile)
+                    .collect(),
+            )
+            .mode(plotly::common::Mode::Lines)
+            .name("Normal"),
+        );
+        qq.set_layout(
+            style
+                .get_layout()
+                .title("Normal QQ plot".into())
+                .x_axis(plotly::layout::Axis::new().title("Normal quantile".into()))
+                .y_axis(plotly::layout::Axis::new().title("Return (%)".into())),
+        );
+
+        html::get_page(
+            "Daily return distribution",
+            &format!(
+                "{}{}{}",
+                html::get_table(&["Statistic", "Value"], rows),
+                histogram.to_inline_html(Some("return_histogram")),
+                qq.to_inline_html(Some("return_qq"))
+            ),
+            true,
+        )
+    }
+}
+
+/// Daily returns, in percent, of an equity series starting from `liquidity`, leaving out cash
+/// flows the way the time-weighted return does.
+pub fn get_daily_returns(
+    liquidity: u32,
+    equity_series: &[backtesting::EquityPoint],
+) -> Vec<(chrono::NaiveDate, f64)> {
+    let mut last_equity = liquidity as f64;
+    let mut returns = Vec::new();
+
+    for equity_point in equity_series {
+        let base = last_equity + equity_point.cash_flow as f64;
+
+        if base > 0.0 {
+            returns.push((
+                equity_point.date,
+                (equity_point.equity as f64 / base - 1.0) * 100.0,
+            ));
+        }
+        last_equity = equity_point.equity as f64;
+    }
+    returns
+}
+
+/// Linearly interpolated quantile of sorted values.
+fn get_quantile(sorted: &[f64], p: f64) -> f64 {
+    let position = p.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let lower = position.floor() as usize;
+    let upper = position.ceil() as usize;
+
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (position - lower as f64)
+}
+
+/// Inverse of the standard normal CDF, by Acklam's rational approximation (relative error
+/// below 1.2e-9).
+pub fn get_normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969683028665376e1,
+        2.209460984245205e2,
+        -2.759285104469687e2,
+        1.38357751867269e2,
+        -3.066479806614716e1,
+        2.506628277459239,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e1,
+        1.615858368580409e2,
+        -1.556989798598866e2,
+        6.680131188771972e1,
+        -1.328068155288572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-3,
+        -3.223964580411365e-1,
+        -2.400758277161838,
+        -2.549732539343734,
+        4.374664141464968,
+        2.938163982698783,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-3,
+        3.224671290700398e-1,
+        2.445134137142996,
+        3.754408661907416,
+    ];
+    const P_LOW: f64 = 0.02425;
+
+    let p = p.clamp(f64::MIN_POSITIVE, 1.0 - f64::EPSILON);
+    let get_tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+
+    if p < P_LOW {
+        get_tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - P_LOW {
+        -get_tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+#[cfg(test)]
+mod distribution_test {
+    use super::{get_normal_quantile, ReturnDistribution};
+
+    #[test]
+    fn distribution_check() {
+        assert!(get_normal_quantile(0.5).abs() < 1e-9);
+        assert!((get_normal_quantile(0.975) - 1.959964).abs() < 1e-5);
+        assert!((get_normal_quantile(0.01) + 2.326348).abs() < 1e-5);
+
+        // -10, -9, ..., 9 percent.
+        let returns: Vec<f64> = (-10..10).map(|value| value as f64).collect();
+        let distribution = ReturnDistribution::new(returns).unwrap();
+
+        assert!((distribution.mean + 0.5).abs() < 1e-9);
+        assert!(distribution.skew.abs() < 1e-9);
+        assert!(distribution.kurtosis < 0.0);
+        assert!((distribution.tail_risks[0].var - 9.05).abs() < 1e-9);
+        assert!((distribution.tail_risks[0].cvar - 10.0).abs() < 1e-9);
+        assert!(ReturnDistribution::new(vec![1.0]).is_none());
+
+        let qq_points = distribution.get_qq_points();
+
+        assert_eq!(qq_points.len(), 20);
+        assert!(qq_points.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(qq_points[0].1, -10.0);
+    }
+}
@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::backtesting;
+use crate::report::html;
+
+pub const UNIT_ECONOMICS_REPORT_FILENAME: &str = "unit_economics.html";
+/// Multiples of the modeled costs the total return is re-priced at.
+pub const COST_MULTIPLIERS: [f64; 3] = [0.5, 1.0, 1.5];
+
+/// Trading costs the simulation leaves out, charged when the trade ledger is re-priced.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostModel {
+    /// Broker fee on the value of each buy and sell, in percent.
+    pub fee_rate: f64,
+    /// Transaction tax on the value of each sell, in percent.
+    pub tax_rate: f64,
+}
+
+impl std::default::Default for CostModel {
+    fn default() -> Self {
+        CostModel {
+            fee_rate: 0.1425,
+            tax_rate: 0.3,
+        }
+    }
+}
+
+/// Total return of a run had its costs been `multiplier` times the modeled ones.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CostSensitivity {
+    pub multiplier: f64,
+    /// In percent.
+    pub total_return: f64,
+}
+
+/// What a trade earns on average before and after costs, from the trade ledger of a run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnitEconomics {
+    pub trade_count: usize,
+    /// Average P&L per trade at the bar midpoints, before any cost.
+    pub gross_edge: f64,
+    /// Average P&L per trade after the slippage, fees and taxes.
+    pub net_edge: f64,
+    /// Gross edge as a share of the value bought, in percent; also the break-even cost, the
+    /// round trip cost at which the trades would earn nothing.
+    pub gross_edge_rate: f64,
+    pub net_edge_rate: f64,
+    /// Average cost per trade.
+    pub cost: f64,
+    /// Multiple of the modeled costs at which the trades would only break even; `None` when the
+    /// trades lose money before costs, or cost nothing.
+    pub break_even_multiplier: Option<f64>,
+    pub sensitivities: Vec<CostSensitivity>,
+}
+
+impl UnitEconomics {
+    /// Re-prices `trade_ledger` without rerunning the simulation. Its prices already carry the
+    /// slippage at `slippage_rate` percent, which is taken back out to get the gross edge;
+    /// `total_return` is the return of the run with that slippage but none of the other costs.
+    pub fn build(
+        trade_ledger: &[backtesting::TradeRecord],
+        slippage_rate: f64,
+        cost_model: &CostModel,
+        liquidity: u32,
+        total_return: f64,
+    ) -> Option<Self> {
+        if trade_ledger.is_empty() {
+            return None;
+        }
+
+        let mut gross_pnl = 0.0;
+        let mut cost = 0.0;
+        let mut slippage_cost = 0.0;
+        let mut buy_value = 0.0;
+
+        for trade_record in trade_ledger {
+            let num = trade_record.num as f64;
+            let buy = trade_record.hold_price as f64 / (1.0 + slippage_rate / 100.0) * num;
+            let sell = trade_record.settle_price as f64 / (1.0 - slippage_rate / 100.0) * num;
+
+            gross_pnl += sell - buy;
+            slippage_cost += (buy + sell) * slippage_rate / 100.0;
+            cost += (buy + sell) * (slippage_rate + cost_model.fee_rate) / 100.0
+                + sell * cost_model.tax_rate / 100.0;
+            buy_value += buy;
+        }
+
+        let count = trade_ledger.len() as f64;
+        let get_rate = |pnl: f64| match buy_value > 0.0 {
+            true => pnl / buy_value * 100.0,
+            false => 0.0,
+        };
+        let sensitivities = COST_MULTIPLIERS
+            .iter()
+            .map(|multiplier| CostSensitivity {
+                multiplier: *multiplier,
+                total_return: match liquidity {
+                    0 => 0.0,
+                    liquidity => {
+                        total_return
+                            + (slippage_cost - cost * multiplier) / liquidity as f64 * 100.0
+                    }
+                },
+            })
+            .collect();
+
+        Some(UnitEconomics {
+            trade_count: trade_ledger.len(),
+            gross_edge: gross_pnl / count,
+            net_edge: (gross_pnl - cost) / count,
+            gross_edge_rate: get_rate(gross_pnl),
+            net_edge_rate: get_rate(gross_pnl - cost),
+            cost: cost / count,
+            break_even_multiplier: match gross_pnl > 0.0 && cost > 0.0 {
+                true => Some(gross_pnl / cost),
+                false => None,
+            },
+            sensitivities,
+        })
+    }
+
+    pub fn to_html(&self) -> String {
+        html::get_page(
+            "Unit economics",
+            &format!(
+                "<p>{} trades. Break-even round trip cost: {:.2}% of the value bought{}.</p>{}\
+                 <h2>Cost sensitivity</h2>{}",
+                self.trade_count,
+                self.gross_edge_rate,
+                match self.break_even_multiplier {
+                    Some(multiplier) => format!(", {:.2}x the modeled costs", multiplier),
+                    None => String::new(),
+                },
+                html::get_table(
+                    &["", "Edge per trade", "Edge rate"],
+                    vec![
+                        vec![
+                            "Gross".to_owned(),
+                            format!("{:+.0}", self.gross_edge),
+                            format!("{:+.2}%", self.gross_edge_rate),
+                        ],
+                        vec![
+                            "Net".to_owned(),
+                            format!("{:+.0}", self.net_edge),
+                            format!("{:+.2}%", self.net_edge_rate),
+                        ],
+                    ],
+                ),
+                html::get_table(
+                    &["Costs", "Total return"],
+                    self.sensitivities
+                        .iter()
+                        .map(|sensitivity| {
+                            vec![
+                                format!("{:.0}%", sensitivity.multiplier * 100.0),
+                                format!("{:+.2}%", sensitivity.total_return),
+                            ]
+                        })
+                        .collect(),
+                )
+            ),
+            false,
+        )
+    }
+}
+
+#[cfg(test)]
+mod economics_test {
+    use crate::core::backtesting;
+
+    use super::{CostModel, UnitEconomics};
+
+    #[test]
+    fn unit_economics_check() {
+        let trade_record = backtesting::TradeRecord {
+            stock_id: "0050".to_owned(),
+            hold_date: chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
+            settle_date: chrono::NaiveDate::from_ymd_opt(1970, 1, 2).unwrap(),
+            num: 10,
+            hold_price: 100,
+            settle_price: 110,
+            beta: None,
+            benchmark_return: None,
+            settle_reason: None,
+        };
+        let cost_model = CostModel {
+            fee_rate: 0.5,
+            tax_rate: 1.0,
+        };
+        let unit_economics =
+            UnitEconomics::build(&[trade_record], 0.0, &cost_model, 1000, 10.0).unwrap();
+
+        // Fees of 5 + 5.5 and tax of 11 against a gross edge of 100.
+        assert_eq!(unit_economics.gross_edge, 100.0);
+        assert_eq!(unit_economics.cost, 21.5);
+        assert_eq!(unit_economics.net_edge, 78.5);
+        assert_eq!(unit_economics.gross_edge_rate, 10.0);
+        assert_eq!(unit_economics.break_even_multiplier, Some(100.0 / 21.5));
+        assert!((unit_economics.sensitivities[1].total_return - 7.85).abs() < 1e-9);
+        assert!(unit_economics.sensitivities[0].total_return > 7.85);
+        assert!(unit_economics.sensitivities[2].total_return < 7.85);
+        assert!(UnitEconomics::build(&[], 0.0, &cost_model, 1000, 0.0).is_none());
+    }
+}
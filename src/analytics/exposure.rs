@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::backtesting;
+use crate::diagram::diagram;
+
+pub const EXPOSURE_DIAGRAM_FILENAME: &str = "exposure_diagram.html";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExposurePoint {
+    pub date: chrono::NaiveDate,
+    /// Market value of the holdings, as a share of equity in percent.
+    pub invested: f64,
+    pub holding_count: usize,
+}
+
+/// How much of the equity a run kept invested, day by day.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExposureReport {
+    pub points: Vec<ExposurePoint>,
+    /// Share of the days with any holding, in percent.
+    pub deployed_days: f64,
+    /// Mean invested share of equity, in percent.
+    pub mean_invested: f64,
+    pub mean_holding_count: f64,
+}
+
+impl ExposureReport {
+    pub fn from_equity_series(equity_series: &[backtesting::EquityPoint]) -> Self {
+        let points: Vec<ExposurePoint> = equity_series
+            .iter()
+            .map(|equity_point| ExposurePoint {
+                date: equity_point.date,
+                invested: match equity_point.equity {
+                    0 => 0.0,
+                    equity => (equity_point.invested as f64 / equity as f64 * 100.0).min(100.0),
+                },
+                holding_count: equity_point.holding_count,
+            })
+            .collect();
+        let count = points.len().max(1) as f64;
+
+        ExposureReport {
+            deployed_days: points
+                .iter()
+                .filter(|point| point.holding_count > 0)
+                .count() as f64
+                / count
+                * 100.0,
+            mean_invested: points.iter().map(|point| point.invested).sum::<f64>() / count,
+            mean_holding_count: points
+                .iter()
+                .map(|point| point.holding_count as f64)
+                .sum::<f64>()
+                / count,
+            points,
+        }
+    }
+
+    /// Invested and cash shares of equity stacked to 100%, with the number of holdings on a
+    /// secondary axis.
+    pub fn to_plot(&self, style: &diagram::DiagramStyle) -> plotly::Plot {
+        let mut plot = plotly::Plot::new();
+        let dates: Vec<String> = self
+            .points
+            .iter()
+            .map(|point| point.date.format("%Y-%m-%d").to_string())
+            .collect();
+
+        plot.add_trace(
+            plotly::Scatter::new(
+                dates.clone(),
+                self.points.iter().map(|point| point.invested).collect(),
+            )
+            .mode(plotly::common::Mode::Lines)
+            .stack_group("exposure")
+            .name("Invested (%)"),
+        );
+        plot.add_trace(
+            plotly::Scatter::new(
+                dates.clone(),
+                self.points
+                    .iter()
+                    .map(|point| 100.0 - point.invested)
+                    .collect(),
+            )
+            .mode(plotly::common::Mode::Lines)
+            .stack_group("exposure")
+            .name("Cash (%)"),
+        );
+        plot.add_trace(
+            plotly::Scatter::new(
+                dates,
+                self.points
+                    .iter()
+                    .map(|point| point.holding_count as f64)
+                    .collect(),
+            )
+            .mode(plotly::common::Mode::Lines)
+            .y_axis("y2")
+            .name("Holdings"),
+        );
+        plot.set_layout(
+            style
+                .get_layout()
+                .title(
+                    format!(
+                        "Deployed on {:.1}% of days, {:.1}% invested and {:.1} holdings on average",
+                        self.deployed_days, self.mean_invested, self.mean_holding_count
+                    )
+                    .as_str()
+                    .into(),
+                )
+                .y_axis(
+                    plotly::layout::Axis::new()
+                        .title("Share of equity (%)".into())
+                        .range(vec![0.0, 100.0]),
+                )
+                .y_axis2(
+                    plotly::layout::Axis::new()
+                        .title("Holdings".into())
+                        .overlaying("y")
+                        .side(plotly::layout::AxisSide::Right),
+                ),
+        );
+        plot
+    }
+}
+
+#[cfg(test)]
+mod exposure_test {
+    use crate::core::backtesting::EquityPoint;
+
+    use super::ExposureReport;
+
+    #[test]
+    fn exposure_check() {
+        let start_date = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let equity_series: Vec<EquityPoint> = [(0, 0), (50000, 1), (100000, 2), (0, 0)]
+            .iter()
+            .enumerate()
+            .map(|(index, (invested, holding_count))| EquityPoint {
+                date: start_date + chrono::Duration::days(index as i64),
+                equity: 100000,
+                unhedged_equity: 100000,
+                cash_flow: 0,
+                invested: *invested,
+                holding_count: *holding_count,
+            })
+            .collect();
+        let report = ExposureReport::from_equity_series(&equity_series);
+
+        assert_eq!(report.points[1].invested, 50.0);
+        assert_eq!(report.deployed_days, 50.0);
+        assert_eq!(report.mean_invested, 37.5);
+        assert_eq!(report.mean_holding_count, 0.75);
+    }
+}
@@ -0,0 +1,289 @@
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::decision;
+use crate::dataview::view;
+use crate::storage::backend;
+
+pub const MOMENTUM_LOOKBACK: usize = 252;
+pub const MOMENTUM_SKIP: usize = 21;
+pub const VOLATILITY_PERIOD: usize = 60;
+pub const SIZE_PERIOD: usize = 20;
+pub const TRADING_DAYS_PER_YEAR: f64 = 252.0;
+
+#[derive(Debug)]
+pub enum Error {
+    Backend(backend::Error),
+}
+
+impl From<backend::Error> for Error {
+    fn from(err: backend::Error) -> Error {
+        Error::Backend(err)
+    }
+}
+
+/// Factor proxies of a stock or a portfolio. A factor is `None` when there is not enough
+/// history to compute it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FactorExposure {
+    /// Natural log of the average daily trading money. Market cap is not part of the stored
+    /// data, so traded value stands in for it.
+    pub size: Option<f64>,
+    /// 12-1 momentum: return from a year ago up to a month ago, in percent.
+    pub momentum: Option<f64>,
+    /// Annualized realized volatility of daily log returns, in percent.
+    pub volatility: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FactorReport {
+    /// Value-weighted exposure of the holdings on every portfolio date.
+    pub exposures: Vec<(chrono::NaiveDate, FactorExposure)>,
+    /// Mean of the daily exposures over the backtest.
+    pub average: FactorExposure,
+}
+
+impl FactorReport {
+    /// Recomputes `average` from `exposures`, for reports built up one portfolio at a time.
+    pub fn update_average(&mut self) {
+        let exposures = &self.exposures;
+
+        self.average = FactorExposure {
+            size: weighted_mean(exposures.iter().map(|(_, exposure)| (1.0, exposure.size))),
+            momentum: weighted_mean(
+                exposures
+                    .iter()
+                    .map(|(_, exposure)| (1.0, exposure.momentum)),
+            ),
+            volatility: weighted_mean(
+                exposures
+                    .iter()
+                    .map(|(_, exposure)| (1.0, exposure.volatility)),
+            ),
+        };
+    }
+}
+
+pub struct FactorAnalysis {
+    pub backend_op: Rc<dyn backend::BackendOp>,
+}
+
+impl FactorAnalysis {
+    pub fn new(backend_op: Rc<dyn backend::BackendOp>) -> Self {
+        FactorAnalysis { backend_op }
+    }
+
+    pub fn get_exposure(
+        &self,
+        stock_id: &str,
+        date: chrono::NaiveDate,
+    ) -> Result<FactorExposure, Error> {
+        let history_days = (MOMENTUM_LOOKBACK * 2) as i64;
+        let records = self.backend_op.query_by_range(
+            stock_id,
+            date - chrono::Duration::days(history_days),
+            date,
+        )?;
+        let mut exposure = FactorExposure::default();
+
+        if records.len() >= SIZE_PERIOD {
+            let trading_money: f64 = records[records.len() - SIZE_PERIOD..]
+                .iter()
+                .map(|record| record.trading_money as f64)
+                .sum();
+
+            if trading_money > 0.0 {
+                exposure.size = Some((trading_money / SIZE_PERIOD as f64).ln());
+            }
+        }
+        if records.len() > MOMENTUM_LOOKBACK {
+            let start_close = records[records.len() - 1 - MOMENTUM_LOOKBACK].close;
+            let end_close = records[records.len() - 1 - MOMENTUM_SKIP].close;
+
+            if start_close > 0.0 {
+                exposure.momentum = Some((end_close - start_close) / start_close * 100.0);
+            }
+        }
+
+        let returns = view::ReturnsView::transform_by_records(&records);
+
+        if returns.len() >= VOLATILITY_PERIOD {
+            let log_returns: Vec<f64> = returns[returns.len() - VOLATILITY_PERIOD..]
+                .iter()
+                .map(|view| view.log_return)
+                .collect();
+            let mean = log_returns.iter().sum::<f64>() / VOLATILITY_PERIOD as f64;
+            let variance = log_returns
+                .iter()
+                .map(|log_return| (log_return - mean) * (log_return - mean))
+                .sum::<f64>()
+                / (VOLATILITY_PERIOD - 1) as f64;
+
+            exposure.volatility = Some((variance * TRADING_DAYS_PER_YEAR).sqrt() * 100.0);
+        }
+
+        Ok(exposure)
+    }
+
+    /// Value-weighted exposure of the stocks held or selected in `portfolio`, or `None` if it
+    /// holds no stocks.
+    pub fn get_portfolio_exposure(
+        &self,
+        portfolio: &decision::Portfolio,
+    ) -> Result<Option<FactorExposure>, Error> {
+        let mut weighted = Vec::new();
+
+        for stock_info in portfolio
+            .stocks_hold
+            .iter()
+            .chain(portfolio.stocks_selected.iter())
+        {
+            let exposure = self.get_exposure(&stock_info.stock_id, portfolio.date)?;
+
+            weighted.push(((stock_info.num * stock_info.price) as f64, exposure));
+        }
+
+        if weighted.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(FactorExposure {
+            size: weighted_mean(
+                weighted
+                    .iter()
+                    .map(|(weight, exposure)| (*weight, exposure.size)),
+            ),
+            momentum: weighted_mean(
+                weighted
+                    .iter()
+                    .map(|(weight, exposure)| (*weight, exposure.momentum)),
+            ),
+            volatility: weighted_mean(
+                weighted
+                    .iter()
+                    .map(|(weight, exposure)| (*weight, exposure.volatility)),
+            ),
+        }))
+    }
+
+    pub fn analyze(&self, portfolios: &[decision::Portfolio]) -> Result<FactorReport, Error> {
+        let mut report = FactorReport::default();
+
+        for portfolio in portfolios {
+            if let Some(exposure) = self.get_portfolio_exposure(portfolio)? {
+                report.exposures.push((portfolio.date, exposure));
+            }
+        }
+
+        report.update_average();
+
+        Ok(report)
+    }
+}
+
+/// Weighted mean of the available values; `None` when no value is available.
+fn weighted_mean<I>(values: I) -> Option<f64>
+where
+    I: Iterator<Item = (f64, Option<f64>)>,
+{
+    let mut total = 0.0;
+    let mut total_weight = 0.0;
+
+    for (weight, value) in values {
+        if let Some(value) = value {
+            total += weight * value;
+            total_weight += weight;
+        }
+    }
+
+    if total_weight == 0.0 {
+        return None;
+    }
+    Some(total / total_weight)
+}
+
+#[cfg(test)]
+mod factor_test {
+    use std::rc::Rc;
+
+    use super::{FactorAnalysis, FactorExposure, FactorReport};
+    use crate::storage::memory;
+    use crate::testkit::generator;
+
+    #[test]
+    fn get_exposure_check() {
+        let backend_op = Rc::new(memory::MemoryBackend::new());
+        let start_date = chrono::NaiveDate::from_ymd_opt(2023, 1, 2).unwrap();
+        let flat_records = generator::SeriesBuilder::new(start_date, 100.0)
+            .flat(300)
+            .insert(backend_op.as_ref(), "flat")
+            .unwrap();
+
+        generator::SeriesBuilder::new(start_date, 100.0)
+            .trend(300, 0.1)
+            .insert(backend_op.as_ref(), "trend")
+            .unwrap();
+        generator::SeriesBuilder::new(start_date, 100.0)
+            .flat(10)
+            .insert(backend_op.as_ref(), "short")
+            .unwrap();
+
+        let date = flat_records.last().unwrap().date;
+        let factor_analysis = FactorAnalysis::new(backend_op);
+        let flat = factor_analysis.get_exposure("flat", date).unwrap();
+
+        // 1000 shares a day at 100.
+        assert!((flat.size.unwrap() - 11.512_925).abs() < 1e-6);
+        assert_eq!(flat.momentum, Some(0.0));
+        assert_eq!(flat.volatility, Some(0.0));
+
+        let trend = factor_analysis.get_exposure("trend", date).unwrap();
+
+        // 0.1% a day over the 231 trading days from a year ago up to a month ago.
+        assert!((trend.momentum.unwrap() - 25.971_383).abs() < 1e-6);
+        assert!(trend.volatility.unwrap() < 1e-6);
+        assert_eq!(
+            factor_analysis.get_exposure("short", date).unwrap(),
+            FactorExposure::default()
+        );
+    }
+
+    #[test]
+    fn update_average_check() {
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let mut report = FactorReport {
+            exposures: vec![
+                (
+                    date,
+                    FactorExposure {
+                        size: Some(1.0),
+                        momentum: None,
+                        volatility: Some(10.0),
+                    },
+                ),
+                (
+                    date.succ_opt().unwrap(),
+                    FactorExposure {
+                        size: Some(3.0),
+                        momentum: None,
+                        volatility: None,
+                    },
+                ),
+            ],
+            ..Default::default()
+        };
+
+        report.update_average();
+
+        // Days without a factor are left out of its average.
+        assert_eq!(
+            report.average,
+            FactorExposure {
+                size: Some(2.0),
+                momentum: None,
+                volatility: Some(10.0),
+            }
+        );
+    }
+}
@@ -0,0 +1,9 @@
+pub mod audit;
+pub mod contribution;
+pub mod correlation;
+pub mod distribution;
+pub mod economics;
+pub mod exposure;
+pub mod factor;
+pub mod signal;
+pub mod split;
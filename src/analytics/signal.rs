@@ -0,0 +1,240 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::diagram::diagram;
+use crate::storage::backend;
+use crate::strategy::strategy;
+
+pub const SIGNAL_HEATMAP_FILENAME: &str = "signal_heatmap.html";
+
+/// What the strategy says about a stock on a day, followed on its own, without the liquidity and
+/// holding limits of a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Signal {
+    /// No position and no entry signal, or no record on the day.
+    None,
+    Buy,
+    Hold,
+    Settle,
+}
+
+impl Signal {
+    /// Value of the signal in the heatmap.
+    pub fn get_level(&self) -> u8 {
+        match self {
+            Signal::None => 0,
+            Signal::Buy => 1,
+            Signal::Hold => 2,
+            Signal::Settle => 3,
+        }
+    }
+}
+
+/// Signals of a set of stocks over the days any of them traded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalHeatmap {
+    pub stock_ids: Vec<String>,
+    pub dates: Vec<chrono::NaiveDate>,
+    /// Signals indexed like `stock_ids`, then like `dates`.
+    pub signals: Vec<Vec<Signal>>,
+}
+
+impl SignalHeatmap {
+    /// Follows each of `stock_ids` from `start_date` to `end_date`: a stock is bought on the
+    /// first day it scores above zero and held until the strategy settles it.
+    pub fn compute(
+        strategy: &dyn strategy::StrategyAPI,
+        backend_op: &dyn backend::BackendOp,
+        stock_ids: &[String],
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> Result<Self, strategy::Error> {
+        let mut trading_dates = Vec::new();
+
+        for stock_id in stock_ids {
+            trading_dates.push(
+                backend_op
+                    .query_by_range(stock_id, start_date, end_date)?
+                    .into_iter()
+                    .map(|record| record.date)
+                    .collect::<BTreeSet<_>>(),
+            );
+        }
+
+        let dates: Vec<chrono::NaiveDate> = trading_dates
+            .iter()
+            .flatten()
+            .copied()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let mut signals = Vec::new();
+
+        for (stock_id, stock_dates) in stock_ids.iter().zip(&trading_dates) {
+            let mut hold_date = None;
+            let mut stock_signals = Vec::new();
+
+            for date in &dates {
+                if !stock_dates.contains(date) {
+                    stock_signals.push(Signal::None);
+                    continue;
+                }
+
+                let signal = match hold_date {
+                    Some(hold_date) => match strategy.settle_check(stock_id, hold_date, *date)? {
+                        Some(_) => Signal::Settle,
+                        None => Signal::Hold,
+                    },
+                    None => match strategy.analyze(stock_id, *date)?.point > 0 {
+                        true => Signal::Buy,
+                        false => Signal::None,
+                    },
+                };
+
+                hold_date = match signal {
+                    Signal::Buy => Some(*date),
+                    Signal::Settle => None,
+                    _ => hold_date,
+                };
+                stock_signals.push(signal);
+            }
+            signals.push(stock_signals);
+        }
+
+        Ok(SignalHeatmap {
+            stock_ids: stock_ids.to_vec(),
+            dates,
+            signals,
+        })
+    }
+
+    /// Stocks signaled `signal` on each day, most first, leaving out days without any.
+    pub fn get_clusters(&self, signal: Signal) -> Vec<(chrono::NaiveDate, usize)> {
+        let mut clusters: Vec<(chrono::NaiveDate, usize)> = self
+            .dates
+            .iter()
+            .enumerate()
+            .map(|(index, date)| {
+                (
+                    *date,
+                    self.signals
+                        .iter()
+                        .filter(|stock_signals| stock_signals[index] == signal)
+                        .count(),
+                )
+            })
+            .filter(|(_, count)| *count > 0)
+            .collect();
+
+        clusters.sort_by_key(|(date, count)| (std::cmp::Reverse(*count), *date));
+        clusters
+    }
+
+    /// Renders a raster of the signals, one row per stock and one column per day.
+    pub fn draw_heatmap(&self, style: &diagram::DiagramStyle, output: &diagram::Output) {
+        let mut plot = plotly::Plot::new();
+        let trace = plotly::HeatMap::new(
+            self.dates.iter().map(|date| date.to_string()).collect(),
+            self.stock_ids.clone(),
+            self.signals
+                .iter()
+                .map(|stock_signals| {
+                    stock_signals
+                        .iter()
+                        .map(|signal| signal.get_level())
+                        .collect::<Vec<_>>()
+                })
+                .collect(),
+        )
+        .zmin(Signal::None.get_level() as f64)
+        .zmax(Signal::Settle.get_level() as f64)
+        .name("Signal (0 none, 1 buy, 2 hold, 3 settle)");
+
+        plot.add_trace(trace);
+        plot.set_layout(
+            style
+                .get_layout()
+                .title("Signals: 0 none, 1 buy, 2 hold, 3 settle".into())
+                .y_axis(plotly::layout::Axis::new().type_(plotly::layout::AxisType::Category)),
+        );
+        diagram::render(&plot, style, output);
+    }
+}
+
+#[cfg(test)]
+mod signal_test {
+    use crate::storage::backend;
+    use crate::strategy::{schema, strategy};
+
+    use super::{Signal, SignalHeatmap};
+
+    #[test]
+    fn compute_check() {
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+        let date = |day| chrono::NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+
+        mock_backend_op
+            .expect_query_by_range()
+            .returning(move |stock_id, _, _| {
+                let days: Vec<u32> = match stock_id {
+                    "2330" => vec![2, 3, 4, 5],
+                    _ => vec![2, 4, 5],
+                };
+
+                Ok(days
+                    .into_iter()
+                    .map(|day| schema::RawData {
+                        date: date(day),
+                        ..Default::default()
+                    })
+                    .collect())
+            });
+        mock_strategy
+            .expect_analyze()
+            .returning(|_, _| {
+                Ok(strategy::Score {
+                    point: 0,
+                    trading_volume: 0,
+                })
+            })
+            .times(1);
+        mock_strategy.expect_analyze().returning(|_, _| {
+            Ok(strategy::Score {
+                point: 1,
+                trading_volume: 0,
+            })
+        });
+        mock_strategy.expect_settle_check().returning(
+            |_, hold_date, assess_date| match (assess_date - hold_date).num_days() {
+                days if days >= 2 => Ok(Some(strategy::SettleReason::SignalExit)),
+                _ => Ok(None),
+            },
+        );
+
+        let stock_ids = vec!["2330".to_owned(), "2317".to_owned()];
+        let heatmap = SignalHeatmap::compute(
+            &mock_strategy,
+            &mock_backend_op,
+            &stock_ids,
+            date(1),
+            date(5),
+        )
+        .unwrap();
+
+        assert_eq!(heatmap.dates, vec![date(2), date(3), date(4), date(5)]);
+        assert_eq!(
+            heatmap.signals,
+            vec![
+                vec![Signal::None, Signal::Buy, Signal::Hold, Signal::Settle],
+                vec![Signal::Buy, Signal::None, Signal::Settle, Signal::Buy],
+            ]
+        );
+        assert_eq!(
+            heatmap.get_clusters(Signal::Buy),
+            vec![(date(2), 1), (date(3), 1), (date(5), 1)]
+        );
+        assert_eq!(heatmap.get_clusters(Signal::Settle)[0], (date(4), 1));
+    }
+}
@@ -0,0 +1,190 @@
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::storage::backend;
+use crate::strategy::schema;
+
+pub const SPLIT_CANDIDATES_FILENAME: &str = "split_candidates.yaml";
+/// Share ratios of common splits; a 2 for 1 split roughly halves the price overnight.
+pub const SPLIT_RATIOS: [f64; 5] = [2.0, 3.0, 4.0, 5.0, 10.0];
+/// Relative distance allowed between the observed price drop and a split ratio.
+pub const RATIO_TOLERANCE: f64 = 0.05;
+/// Trading days averaged for the volume a split day is compared against.
+pub const VOLUME_PERIOD: usize = 20;
+/// Minimum volume of a split day over the average volume before it.
+pub const MIN_VOLUME_RATIO: f64 = 1.5;
+
+#[derive(Debug)]
+pub enum Error {
+    Backend(backend::Error),
+}
+
+impl From<backend::Error> for Error {
+    fn from(err: backend::Error) -> Error {
+        Error::Backend(err)
+    }
+}
+
+/// A probable unadjusted split, proposed for review rather than applied.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SplitCandidate {
+    pub stock_id: String,
+    /// First trading day quoted after the split.
+    pub date: chrono::NaiveDate,
+    pub prev_close: f64,
+    pub open: f64,
+    pub close: f64,
+    /// Split ratio matching the drop, e.g. 2.0 for a 2 for 1 split.
+    pub ratio: f64,
+    pub volume_ratio: f64,
+    /// Factor to multiply prices before `date` by; volumes are divided by it.
+    pub adjustment_factor: f64,
+}
+
+impl SplitCandidate {
+    /// Applies the proposed adjustment to the records before the split day.
+    pub fn adjust(&self, records: &[schema::RawData]) -> Vec<schema::RawData> {
+        records
+            .iter()
+            .map(|record| {
+                let mut record = record.clone();
+
+                if record.date < self.date {
+                    record.open *= self.adjustment_factor;
+                    record.high *= self.adjustment_factor;
+                    record.low *= self.adjustment_factor;
+                    record.close *= self.adjustment_factor;
+                    record.spread *= self.adjustment_factor;
+                    record.trading_volume =
+                        (record.trading_volume as f64 / self.adjustment_factor).round() as u64;
+                }
+                record
+            })
+            .collect()
+    }
+}
+
+/// Split ratio the drop from `prev_price` to `price` is close to, if any.
+fn get_split_ratio(prev_price: f64, price: f64) -> Option<f64> {
+    if prev_price <= 0.0 || price <= 0.0 {
+        return None;
+    }
+    let observed = prev_price / price;
+
+    SPLIT_RATIOS
+        .iter()
+        .copied()
+        .find(|ratio| (observed / ratio - 1.0).abs() <= RATIO_TOLERANCE)
+}
+
+/// Flags days of `records`, sorted by date, that gap down overnight by a split ratio on a volume
+/// spike and stay at the new level the next day.
+pub fn detect(stock_id: &str, records: &[schema::RawData]) -> Vec<SplitCandidate> {
+    let mut candidates = Vec::new();
+
+    for index in 1..records.len() {
+        let prev = &records[index - 1];
+        let record = &records[index];
+        let ratio = match get_split_ratio(prev.close, record.open) {
+            Some(ratio) if get_split_ratio(prev.close, record.close) == Some(ratio) => ratio,
+            _ => continue,
+        };
+
+        // A bad print recovers the next day; a split does not.
+        if records
+            .get(index + 1)
+            .is_some_and(|next| get_split_ratio(prev.close, next.close) != Some(ratio))
+        {
+            continue;
+        }
+
+        let volumes = &records[index.saturating_sub(VOLUME_PERIOD)..index];
+        let average_volume = volumes
+            .iter()
+            .map(|record| record.trading_volume as f64)
+            .sum::<f64>()
+            / volumes.len() as f64;
+        let volume_ratio = if average_volume > 0.0 {
+            record.trading_volume as f64 / average_volume
+        } else {
+            0.0
+        };
+
+        if volume_ratio < MIN_VOLUME_RATIO {
+            continue;
+        }
+        candidates.push(SplitCandidate {
+            stock_id: stock_id.to_owned(),
+            date: record.date,
+            prev_close: prev.close,
+            open: record.open,
+            close: record.close,
+            ratio,
+            volume_ratio,
+            adjustment_factor: 1.0 / ratio,
+        });
+    }
+    candidates
+}
+
+pub struct SplitDetector {
+    pub backend_op: Rc<dyn backend::BackendOp>,
+}
+
+impl SplitDetector {
+    pub fn new(backend_op: Rc<dyn backend::BackendOp>) -> Self {
+        SplitDetector { backend_op }
+    }
+
+    /// Scans the stored records of every stock for probable unadjusted splits.
+    pub fn scan(&self, stock_ids: &[String]) -> Result<Vec<SplitCandidate>, Error> {
+        let mut candidates = Vec::new();
+
+        for stock_id in stock_ids {
+            let mut records = self.backend_op.query_all(stock_id)?;
+
+            records.sort_by_key(|record| record.date);
+            candidates.extend(detect(stock_id, &records));
+        }
+        Ok(candidates)
+    }
+}
+
+#[cfg(test)]
+mod split_test {
+    use crate::testkit::generator;
+
+    use super::detect;
+
+    #[test]
+    fn detect_check() {
+        let start_date = chrono::NaiveDate::from_ymd_opt(2022, 1, 3).unwrap();
+        let mut records = generator::SeriesBuilder::new(start_date, 100.0)
+            .flat(30)
+            .build();
+
+        for record in records.iter_mut().skip(20) {
+            record.open /= 2.0;
+            record.high /= 2.0;
+            record.low /= 2.0;
+            record.close /= 2.0;
+            record.trading_volume *= 2;
+        }
+        // A one-day bad print is not a split.
+        records[10].open /= 10.0;
+        records[10].close /= 10.0;
+
+        let candidates = detect("0050", &records);
+
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].date, records[20].date);
+        assert_eq!(candidates[0].ratio, 2.0);
+        assert_eq!(candidates[0].adjustment_factor, 0.5);
+
+        let adjusted = candidates[0].adjust(&records);
+
+        assert!(detect("0050", &adjusted).is_empty());
+        assert_eq!(adjusted[19].close, records[20].close);
+    }
+}
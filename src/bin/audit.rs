@@ -0,0 +1,73 @@
+extern crate getopts;
+
+use std::rc::Rc;
+
+use veronica::analytics::audit;
+use veronica::config::config;
+use veronica::storage::backend;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt("c", "config", "set config path", "");
+    opts.reqopt("s", "stock_ids", "set comma separated stock ids", "");
+    opts.optopt("o", "output", "set spread audit output path", "");
+    opts.optflag(
+        "",
+        "fix",
+        "rewrite mismatched spreads with the recomputed ones",
+    );
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    let config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+    let stock_ids: Vec<String> = matches
+        .opt_str("s")
+        .unwrap()
+        .split(',')
+        .map(|stock_id| stock_id.trim().to_owned())
+        .filter(|stock_id| !stock_id.is_empty())
+        .collect();
+    let output = match matches.opt_str("o") {
+        Some(output) => output,
+        None => {
+            std::fs::create_dir_all(&config.portfolio_path).unwrap();
+            config.portfolio_path.to_owned() + "/" + audit::SPREAD_AUDIT_FILENAME
+        }
+    };
+    let fix = matches.opt_present("fix");
+    let backend_op = Rc::new(backend::SledBackend::new(&config.db_path).unwrap());
+    let mismatches = audit::SpreadAudit::new(backend_op)
+        .run(&stock_ids, fix)
+        .unwrap();
+
+    for mismatch in &mismatches {
+        println!(
+            "{} {}: stored spread {:.2}, expected {:.2} since {} ({:?})",
+            mismatch.stock_id,
+            mismatch.date,
+            mismatch.stored,
+            mismatch.expected,
+            mismatch.prev_date,
+            mismatch.cause
+        );
+    }
+    std::fs::write(&output, serde_yaml::to_string(&mismatches).unwrap()).unwrap();
+    println!(
+        "{} spread mismatches written to {}{}",
+        mismatches.len(),
+        output,
+        if fix {
+            ", corrected in the backend"
+        } else {
+            ""
+        }
+    );
+}
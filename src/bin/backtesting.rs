@@ -2,10 +2,12 @@ extern crate getopts;
 
 use std::rc::Rc;
 
-use veronica::config::config;
-use veronica::core::backtesting;
-use veronica::crawler::finmind;
-use veronica::storage::backend;
+use veronica::config::{config, profile};
+use veronica::core::{backtesting, calendar, clock, decision, fill, position, prefetch, reconcile};
+use veronica::crawler::{finmind, stocklist};
+use veronica::storage::run::RunOp;
+use veronica::storage::watchlist::WatchlistOp;
+use veronica::storage::{backend, journal, namespace};
 use veronica::strategy::strategy;
 
 fn main() {
@@ -13,6 +15,224 @@ fn main() {
     let mut opts = getopts::Options::new();
 
     opts.reqopt("c", "config", "set config path", "");
+    opts.optopt(
+        "u",
+        "user",
+        "run as this profile of the config, with its capital, strategy and portfolio path",
+        "NAME",
+    );
+    opts.optflag("", "chunked", "run in yearly chunks and stitch the results");
+    opts.optopt(
+        "",
+        "scenarios",
+        "replay the stress scenarios of these comma-separated names, or all of them",
+        "all|NAMES",
+    );
+    opts.optflag(
+        "",
+        "stream",
+        "stream portfolios to disk instead of keeping them in memory",
+    );
+
+    opts.optflag("", "profile", "print a timing breakdown of the run");
+    opts.optflag(
+        "",
+        "price-limit",
+        "defer fills on bars locked at the daily price limit",
+    );
+    opts.optflag("", "board-lot", "only buy whole board lots");
+    opts.optopt(
+        "",
+        "min-order-value",
+        "skip entries worth less than this (default 1000)",
+        "",
+    );
+    opts.optflag(
+        "",
+        "verify-data",
+        "fail before the run if the data it needs has gaps",
+    );
+    opts.optflag(
+        "",
+        "prefetch",
+        "crawl the gaps in the data the run needs before it starts",
+    );
+    opts.optopt(
+        "",
+        "pin-generation",
+        "fail unless the backend data is at this generation",
+        "",
+    );
+    opts.optopt(
+        "",
+        "slippage",
+        "fill buys this many percent above the bar midpoint and sells below it",
+        "",
+    );
+    opts.optopt(
+        "",
+        "fee-rate",
+        "charge this broker fee, in percent, on each trade in the unit economics report",
+        "",
+    );
+    opts.optopt(
+        "",
+        "tax-rate",
+        "charge this transaction tax, in percent, on each sell in the unit economics report",
+        "",
+    );
+    opts.optopt(
+        "",
+        "ticks",
+        "round fill prices against the order to ticks: tw, a fixed tick size, or a YAML file of \
+         tick bands",
+        "",
+    );
+    opts.optopt(
+        "",
+        "candidates",
+        "pick entries only among the stock ids of this file",
+        "",
+    );
+    opts.optopt(
+        "",
+        "watchlist",
+        "pick entries only among the stocks of this stored watchlist",
+        "",
+    );
+    opts.optopt(
+        "",
+        "position-notes",
+        "attach the notes of this file to positions and never settle those on manual hold",
+        "",
+    );
+    opts.optopt(
+        "",
+        "statement",
+        "replace the recommended fills with the executed trades of this CSV broker statement",
+        "",
+    );
+    opts.optopt(
+        "",
+        "valuation",
+        "mark held positions to market at the mid, close or last-trade price",
+        "",
+    );
+    opts.optopt(
+        "",
+        "fill-at",
+        "fill orders at the mid or open price of the day",
+        "",
+    );
+    opts.optopt(
+        "",
+        "scale-in",
+        "buy entries in equal tranches over this many trading days, overriding the strategy",
+        "",
+    );
+    opts.optopt(
+        "",
+        "scale-out",
+        "sell exits in equal tranches over this many trading days",
+        "",
+    );
+    opts.optopt(
+        "",
+        "volume-cap",
+        "fill at most this percentage of a day's volume, carrying the rest over",
+        "",
+    );
+    opts.optopt(
+        "",
+        "latency",
+        "act on signals this many trading days after they come out",
+        "",
+    );
+    opts.optopt(
+        "",
+        "latency-sweep",
+        "compare runs with each of these comma-separated signal latencies",
+        "",
+    );
+    opts.optopt(
+        "",
+        "shocks",
+        "compare the run with reruns under the shock models and seeds of this yaml file",
+        "",
+    );
+    opts.optopt(
+        "",
+        "cash-flows",
+        "apply the cash flow schedule of this yaml file",
+        "",
+    );
+    opts.optopt(
+        "",
+        "pairs",
+        "trade the pairs of this yaml file on the z-score of their spread",
+        "",
+    );
+    opts.optopt(
+        "",
+        "strategy",
+        "run the built-in strategy (bollinger_band, rsi, squeeze, gap, ma_crossover, high_breakout, dca), rule set, model or composite of this name",
+        "",
+    );
+    opts.optopt(
+        "",
+        "script",
+        "run the strategy of this rhai script instead of the Bollinger band one",
+        "",
+    );
+    opts.optopt(
+        "",
+        "rules",
+        "run the rule-based strategy of this name from the config",
+        "",
+    );
+    opts.optopt(
+        "",
+        "model",
+        "run the ONNX model of this name from the config (needs the onnx feature)",
+        "",
+    );
+    opts.optflag(
+        "",
+        "calendar",
+        "skip the holidays and settlement-only days of the stored trading calendar",
+    );
+    opts.optopt(
+        "",
+        "max-hold-days",
+        "settle positions held for this many trading days",
+        "",
+    );
+    opts.optopt(
+        "",
+        "resume",
+        "continue the recorded run of this id from its final holdings and liquidity",
+        "RUN_ID",
+    );
+    opts.optopt("", "until", "run through this day", "YYYY-MM-DD");
+    opts.optflag("", "no-record", "do not record the run in the run database");
+    opts.optopt(
+        "",
+        "as-of",
+        "run as if on this day, which dates the recorded run and journal and bounds prefetching",
+        "YYYY-MM-DD",
+    );
+    opts.optflag(
+        "",
+        "journal",
+        "journal the decision and the order plan of the last day",
+    );
+    opts.optmulti("t", "tag", "tag the recorded run (repeatable)", "");
+    opts.optopt("n", "note", "attach a note to the recorded run", "");
+    opts.optflag(
+        "",
+        "cache",
+        "reuse a recorded run with the same inputs instead of running again",
+    );
 
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
@@ -23,17 +243,220 @@ fn main() {
     };
 
     let config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+    let name = matches
+        .opt_str("user")
+        .unwrap_or(profile::DEFAULT_PROFILE.to_owned());
+    let profile = config
+        .get_profile(&name)
+        .unwrap_or_else(|| panic!("Unknown user {}", name));
+    let config = config.for_profile(&profile);
     let crawler = Rc::new(finmind::Finmind::new(&config.finmind_token));
     let backend_op = Rc::new(backend::SledBackend::new(&config.db_path).unwrap());
-    let mut backtesting = backtesting::Backtesting::new(
-        config,
-        crawler,
-        backend_op,
-        strategy::Strategies::BollingerBand,
-    );
+    let profile_op = Rc::new(namespace::Namespaced::new(
+        backend_op.clone(),
+        profile.get_namespace(),
+    ));
+    let strategy = if let Some(script_path) = matches.opt_str("script") {
+        strategy::Strategies::Script(script_path)
+    } else if let Some(name) = matches.opt_str("rules") {
+        strategy::Strategies::Rule(config.get_rule_set(&name).unwrap().clone())
+    } else if let Some(name) = matches.opt_str("model") {
+        strategy::Strategies::Onnx(config.get_model(&name).unwrap().clone())
+    } else if let Some(name) = matches.opt_str("strategy") {
+        config.get_strategy(&name).unwrap()
+    } else {
+        profile.get_strategy(&config).unwrap()
+    };
+    let scenarios = match matches.opt_str("scenarios") {
+        Some(names) if names == "all" => config.get_scenarios(),
+        Some(names) => names
+            .split(',')
+            .map(|name| {
+                config
+                    .get_scenarios()
+                    .into_iter()
+                    .find(|scenario| scenario.name == name)
+                    .unwrap_or_else(|| panic!("Unknown scenario {}", name))
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    let mut backtesting =
+        backtesting::Backtesting::new(config, crawler, backend_op.clone(), strategy);
 
-    backtesting.run(
-        chrono::NaiveDate::from_ymd_opt(2021, 6, 1).unwrap(),
-        chrono::NaiveDate::from_ymd_opt(2021, 12, 31).unwrap(),
-    );
+    backtesting.liquidity = profile.liquidity;
+    backtesting.stocks_hold_num = profile.stocks_hold_num;
+    backtesting.retain_portfolios = !matches.opt_present("stream");
+    backtesting.profiling = matches.opt_present("profile");
+    if matches.opt_present("price-limit") {
+        backtesting.price_limit = Some(fill::PriceLimit::default());
+    }
+    if matches.opt_present("board-lot") || matches.opt_present("min-order-value") {
+        let mut order_size = fill::OrderSize::default();
+
+        if matches.opt_present("board-lot") {
+            order_size.lot_policy = fill::LotPolicy::BoardLot;
+        }
+        if let Some(min_order_value) = matches.opt_str("min-order-value") {
+            order_size.min_order_value = min_order_value.parse().unwrap();
+        }
+        backtesting.order_size = Some(order_size);
+    }
+    if matches.opt_present("prefetch") {
+        backtesting.data_check = Some(prefetch::DataCheck::Prefetch);
+    } else if matches.opt_present("verify-data") {
+        backtesting.data_check = Some(prefetch::DataCheck::Verify);
+    }
+    if let Some(pinned_generation) = matches.opt_str("pin-generation") {
+        backtesting.pinned_generation = Some(pinned_generation.parse().unwrap());
+    }
+    if let Some(rate) = matches.opt_str("slippage") {
+        backtesting.slippage = Some(fill::Slippage {
+            rate: rate.parse().unwrap(),
+        });
+    }
+    if let Some(fee_rate) = matches.opt_str("fee-rate") {
+        backtesting.cost_model.fee_rate = fee_rate.parse().unwrap();
+    }
+    if let Some(tax_rate) = matches.opt_str("tax-rate") {
+        backtesting.cost_model.tax_rate = tax_rate.parse().unwrap();
+    }
+    if let Some(ticks) = matches.opt_str("ticks") {
+        let rule = match ticks.as_str() {
+            "tw" => fill::TickRule::Taiwan,
+            ticks => match ticks.parse() {
+                Ok(tick) => fill::TickRule::Fixed(tick),
+                Err(_) => {
+                    let data = std::fs::read_to_string(ticks).unwrap();
+
+                    fill::TickRule::Bands(serde_yaml::from_str(&data).unwrap())
+                }
+            },
+        };
+
+        backtesting.tick_size = Some(fill::TickSize {
+            rule,
+            ..Default::default()
+        });
+    }
+    if let Some(candidates_path) = matches.opt_str("candidates") {
+        backtesting.candidates = Some(stocklist::load_stock_list(&candidates_path).unwrap());
+    }
+    if let Some(name) = matches.opt_str("watchlist") {
+        let watchlist = profile_op
+            .get_watchlist(&name)
+            .unwrap()
+            .unwrap_or_else(|| panic!("Unknown watchlist {}", name));
+
+        backtesting.candidates = Some(watchlist.stock_ids);
+    }
+    if let Some(position_notes_path) = matches.opt_str("position-notes") {
+        backtesting.position_notes = position::load_position_notes(&position_notes_path).unwrap();
+    }
+    if let Some(statement_path) = matches.opt_str("statement") {
+        backtesting.executed_trades =
+            Some(reconcile::load_executed_trades(&statement_path).unwrap());
+    }
+    if let Some(valuation) = matches.opt_str("valuation") {
+        backtesting.valuation_policy = match valuation.as_str() {
+            "mid" => decision::ValuationPolicy::Mid,
+            "close" => decision::ValuationPolicy::Close,
+            "last-trade" => decision::ValuationPolicy::LastTrade,
+            _ => panic!("Unknown valuation policy {}", valuation),
+        };
+    }
+    if let Some(fill_at) = matches.opt_str("fill-at") {
+        backtesting.fill_policy = match fill_at.as_str() {
+            "mid" => decision::FillPolicy::Mid,
+            "open" => decision::FillPolicy::Open,
+            _ => panic!("Unknown fill price {}", fill_at),
+        };
+    }
+    if let Some(days) = matches.opt_str("scale-in") {
+        backtesting.scale_in = Some(fill::ScaleIn {
+            days: days.parse().unwrap(),
+        });
+    }
+    if let Some(days) = matches.opt_str("scale-out") {
+        backtesting.scale_out = Some(fill::ScaleOut {
+            days: days.parse().unwrap(),
+        });
+    }
+    if let Some(participation) = matches.opt_str("volume-cap") {
+        backtesting.volume_cap = Some(fill::VolumeCap {
+            participation: participation.parse().unwrap(),
+        });
+    }
+    if let Some(signal_latency) = matches.opt_str("latency") {
+        backtesting.signal_latency = signal_latency.parse().unwrap();
+    }
+    if let Some(cash_flows_path) = matches.opt_str("cash-flows") {
+        let data = std::fs::read_to_string(cash_flows_path).unwrap();
+
+        backtesting.cash_flows = Some(serde_yaml::from_str(&data).unwrap());
+    }
+    if let Some(pairs_path) = matches.opt_str("pairs") {
+        let data = std::fs::read_to_string(pairs_path).unwrap();
+
+        backtesting.pairs_trading = Some(serde_yaml::from_str(&data).unwrap());
+    }
+    if let Some(max_hold_days) = matches.opt_str("max-hold-days") {
+        backtesting.max_hold_days = Some(max_hold_days.parse().unwrap());
+    }
+    backtesting.clock =
+        clock::get_clock(matches.opt_str("as-of").map(|as_of| as_of.parse().unwrap()));
+    if matches.opt_present("journal") {
+        let mut journal = journal::Journal::new(backend_op.clone(), &profile.name);
+
+        journal.clock = backtesting.clock.clone();
+        backtesting.journal = Some(journal);
+    }
+    if !matches.opt_present("no-record") {
+        backtesting.run_op = Some(profile_op.clone());
+        backtesting.cache_runs = matches.opt_present("cache");
+        backtesting.run_tags = matches.opt_strs("tag");
+        backtesting.run_note = matches.opt_str("note").unwrap_or_default();
+    }
+
+    let mut start_date = chrono::NaiveDate::from_ymd_opt(2021, 6, 1).unwrap();
+    let end_date = match matches.opt_str("until") {
+        Some(until) => until.parse().unwrap(),
+        None => chrono::NaiveDate::from_ymd_opt(2021, 12, 31).unwrap(),
+    };
+
+    if let Some(run_id) = matches.opt_str("resume") {
+        let run = profile_op
+            .get_run(&run_id)
+            .unwrap()
+            .unwrap_or_else(|| panic!("Unknown run {}", run_id));
+
+        start_date = backtesting
+            .resume_from(&run)
+            .unwrap_or_else(|| panic!("Run {} recorded no end state to resume from", run_id));
+    }
+
+    if matches.opt_present("calendar") {
+        backtesting.calendar = Some(
+            calendar::TradingCalendar::load(backend_op.as_ref(), start_date, end_date).unwrap(),
+        );
+    }
+
+    if let Some(latencies) = matches.opt_str("latency-sweep") {
+        let latencies: Vec<usize> = latencies
+            .split(',')
+            .map(|latency| latency.parse().unwrap())
+            .collect();
+
+        backtesting.run_latencies(start_date, end_date, &latencies);
+    } else if let Some(shocks_path) = matches.opt_str("shocks") {
+        let data = std::fs::read_to_string(shocks_path).unwrap();
+
+        backtesting.run_shocked(start_date, end_date, &serde_yaml::from_str(&data).unwrap());
+    } else if !scenarios.is_empty() {
+        backtesting.run_scenarios(&scenarios);
+    } else if matches.opt_present("chunked") {
+        backtesting.run_chunked(start_date, end_date);
+    } else {
+        backtesting.run(start_date, end_date);
+    }
 }
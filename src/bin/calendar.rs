@@ -0,0 +1,117 @@
+extern crate getopts;
+
+use chrono::Datelike;
+
+use veronica::config::config;
+use veronica::core::clock::{self, Clock};
+use veronica::crawler::holiday;
+use veronica::storage::backend;
+use veronica::storage::calendar::{self, CalendarOp};
+
+fn print_usage(opts: &getopts::Options) {
+    println!(
+        "{}",
+        opts.usage(
+            "Usage: calendar -c <config> (update <year>... | list [<year>] \
+             | set <date> (closed | half | settlement | full) [<note>] | clear <date>)"
+        )
+    );
+}
+
+fn parse_session_kind(kind: &str) -> Option<calendar::SessionKind> {
+    match kind {
+        "closed" => Some(calendar::SessionKind::Closed),
+        "half" => Some(calendar::SessionKind::HalfDay),
+        "settlement" => Some(calendar::SessionKind::SettlementOnly),
+        "full" => Some(calendar::SessionKind::Full),
+        _ => None,
+    }
+}
+
+fn get_year_range(year: i32) -> (chrono::NaiveDate, chrono::NaiveDate) {
+    (
+        chrono::NaiveDate::from_ymd_opt(year, 1, 1).unwrap(),
+        chrono::NaiveDate::from_ymd_opt(year, 12, 31).unwrap(),
+    )
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt("c", "config", "set config path", "");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    let config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+    let backend_op = backend::SledBackend::new(&config.db_path).unwrap();
+    let date_arg = matches
+        .free
+        .get(1)
+        .and_then(|date| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok());
+
+    match matches.free.first().map(|command| command.as_str()) {
+        Some("update") if matches.free.len() > 1 => {
+            for year in &matches.free[1..] {
+                let year = year.parse::<i32>().unwrap();
+                let sessions = holiday::get_holiday_schedule(year).unwrap();
+
+                backend_op.insert_sessions(&sessions).unwrap();
+                println!("{} sessions of {} updated", sessions.len(), year);
+            }
+        }
+        Some("list") => {
+            let year = match matches.free.get(1) {
+                Some(year) => year.parse::<i32>().unwrap(),
+                None => clock::SystemClock.today().year(),
+            };
+            let (start_date, end_date) = get_year_range(year);
+
+            for session in backend_op.list_sessions(start_date, end_date).unwrap() {
+                println!(
+                    "{}  {:<16} {}",
+                    session.date,
+                    format!("{:?}", session.kind),
+                    session.note
+                );
+            }
+        }
+        Some("set") => {
+            let (date, kind) = match (
+                date_arg,
+                matches
+                    .free
+                    .get(2)
+                    .and_then(|kind| parse_session_kind(kind)),
+            ) {
+                (Some(date), Some(kind)) => (date, kind),
+                _ => return print_usage(&opts),
+            };
+
+            backend_op
+                .insert_sessions(&[calendar::Session {
+                    date,
+                    kind,
+                    note: matches.free[3..].join(" "),
+                }])
+                .unwrap();
+        }
+        Some("clear") => {
+            let date = match date_arg {
+                Some(date) => date,
+                None => return print_usage(&opts),
+            };
+
+            if !backend_op.delete_session(date).unwrap() {
+                println!("No session stored on {}", date);
+            }
+        }
+        _ => print_usage(&opts),
+    }
+}
@@ -0,0 +1,124 @@
+extern crate getopts;
+
+use std::rc::Rc;
+
+use veronica::config::config;
+use veronica::core::{backtesting, fill, scaling};
+use veronica::crawler::finmind;
+use veronica::storage::backend;
+use veronica::strategy::strategy;
+
+const DEFAULT_LEVELS: [u32; 5] = [200000, 1000000, 5000000, 20000000, 100000000];
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt("c", "config", "set config path", "");
+    opts.reqopt("", "start", "set start date (YYYY-MM-DD)", "");
+    opts.reqopt("", "end", "set end date (YYYY-MM-DD)", "");
+    opts.optopt(
+        "",
+        "levels",
+        "comma-separated initial capital levels (default 200000 up to 100000000)",
+        "",
+    );
+    opts.optopt(
+        "",
+        "volume-cap",
+        "fill at most this percentage of a day's volume (default 10)",
+        "",
+    );
+    opts.optopt(
+        "",
+        "tolerance",
+        "points of return a level may lose and still count as within capacity (default 2)",
+        "",
+    );
+    opts.optopt(
+        "",
+        "script",
+        "run the strategy of this rhai script instead of the Bollinger band one",
+        "",
+    );
+    opts.optflag("", "board-lot", "only buy whole board lots");
+    opts.optflag(
+        "",
+        "no-record",
+        "do not record the runs in the run database",
+    );
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    let config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+    let start_date =
+        chrono::NaiveDate::parse_from_str(&matches.opt_str("start").unwrap(), "%Y-%m-%d").unwrap();
+    let end_date =
+        chrono::NaiveDate::parse_from_str(&matches.opt_str("end").unwrap(), "%Y-%m-%d").unwrap();
+    let levels: Vec<u32> = match matches.opt_str("levels") {
+        Some(levels) => levels
+            .split(',')
+            .map(|level| level.parse().unwrap())
+            .collect(),
+        None => DEFAULT_LEVELS.to_vec(),
+    };
+    let tolerance = match matches.opt_str("tolerance") {
+        Some(tolerance) => tolerance.parse().unwrap(),
+        None => scaling::DEFAULT_CAPACITY_TOLERANCE,
+    };
+    let crawler = Rc::new(finmind::Finmind::new(&config.finmind_token));
+    let backend_op = Rc::new(backend::SledBackend::new(&config.db_path).unwrap());
+    let strategy = match matches.opt_str("script") {
+        Some(script_path) => strategy::Strategies::Script(script_path),
+        None => strategy::Strategies::BollingerBand,
+    };
+    let mut backtesting =
+        backtesting::Backtesting::new(config, crawler, backend_op.clone(), strategy);
+
+    backtesting.volume_cap = Some(match matches.opt_str("volume-cap") {
+        Some(participation) => fill::VolumeCap {
+            participation: participation.parse().unwrap(),
+        },
+        None => fill::VolumeCap::default(),
+    });
+    if matches.opt_present("board-lot") {
+        backtesting.order_size = Some(fill::OrderSize {
+            lot_policy: fill::LotPolicy::BoardLot,
+            ..Default::default()
+        });
+    }
+    if !matches.opt_present("no-record") {
+        backtesting.run_op = Some(backend_op);
+    }
+
+    let report = backtesting.run_capital_levels(start_date, end_date, &levels);
+
+    println!(
+        "{:>12} {:>10} {:>10} {:>10} {:>8} {:>14}",
+        "Capital", "Return", "Change", "Max DD", "Trades", "Partial fills"
+    );
+    for (level, change) in report.levels.iter().zip(report.get_return_changes()) {
+        println!(
+            "{:>12} {:>9.2}% {:>10.2} {:>9.2}% {:>8} {:>14}",
+            level.liquidity,
+            level.metrics.time_weighted_return,
+            change,
+            level.metrics.max_drawdown,
+            level.metrics.trade_count,
+            level.metrics.partial_fills
+        );
+    }
+    match report.get_capacity(tolerance) {
+        Some(capacity) => println!(
+            "Scales to {} while losing at most {} points of return",
+            capacity, tolerance
+        ),
+        None => println!("No capital level ran"),
+    }
+}
@@ -0,0 +1,73 @@
+extern crate getopts;
+
+use std::rc::Rc;
+
+use veronica::config::config;
+use veronica::crosssection::correlation;
+use veronica::crosssection::crosssection;
+use veronica::diagram::diagram;
+use veronica::storage::backend;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt("c", "config", "set config path", "");
+    opts.reqopt("s", "stock_ids", "set comma separated stock ids", "");
+    opts.reqopt("", "start", "set start date (YYYY-MM-DD)", "");
+    opts.reqopt("", "end", "set end date (YYYY-MM-DD)", "");
+    opts.optopt(
+        "d",
+        "distance",
+        "set max distance (1 - correlation) within a cluster",
+        "",
+    );
+    opts.optopt("o", "output", "set heatmap output path", "");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    let config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+    let stock_ids: Vec<String> = matches
+        .opt_str("s")
+        .unwrap()
+        .split(',')
+        .map(|stock_id| stock_id.trim().to_owned())
+        .filter(|stock_id| !stock_id.is_empty())
+        .collect();
+    let start_date =
+        chrono::NaiveDate::parse_from_str(&matches.opt_str("start").unwrap(), "%Y-%m-%d").unwrap();
+    let end_date =
+        chrono::NaiveDate::parse_from_str(&matches.opt_str("end").unwrap(), "%Y-%m-%d").unwrap();
+    let max_distance = match matches.opt_str("d") {
+        Some(distance) => distance.parse::<f64>().unwrap(),
+        None => correlation::MAX_CLUSTER_DISTANCE,
+    };
+    let output = match matches.opt_str("o") {
+        Some(output) => output,
+        None => {
+            std::fs::create_dir_all(&config.portfolio_path).unwrap();
+            config.portfolio_path.to_owned() + "/" + correlation::CORRELATION_DIAGRAM_FILENAME
+        }
+    };
+    let backend_op = Rc::new(backend::SledBackend::new(&config.db_path).unwrap());
+    let cross_section = crosssection::CrossSection::new(backend_op);
+    let matrix =
+        correlation::CorrelationMatrix::compute(&cross_section, &stock_ids, start_date, end_date)
+            .unwrap();
+    let clusters = matrix.cluster(max_distance);
+
+    for (index, cluster) in clusters.iter().enumerate() {
+        println!("Cluster {}: {}", index + 1, cluster.join(", "));
+    }
+    matrix.draw_heatmap(
+        &clusters,
+        &config.diagram_style,
+        &diagram::Output::Html(output),
+    );
+}
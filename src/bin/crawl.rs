@@ -0,0 +1,109 @@
+extern crate getopts;
+
+use std::rc::Rc;
+
+use veronica::config::config;
+use veronica::core::bulk;
+use veronica::core::clock::{self, Clock};
+use veronica::crawler::crawler::Crawler;
+use veronica::crawler::finmind;
+use veronica::storage::backend;
+
+const DEFAULT_THREADS: usize = 4;
+
+fn print_usage(opts: &getopts::Options) {
+    println!(
+        "{}",
+        opts.usage("Usage: crawl -c <config> [-m <manifest>] [-t <threads>] [--full-history]")
+    );
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt("c", "config", "set config path", "");
+    opts.optopt(
+        "m",
+        "manifest",
+        "set manifest path (defaults to the one in the portfolio path)",
+        "",
+    );
+    opts.optopt("t", "threads", "set number of concurrent requests", "");
+    opts.optflag(
+        "",
+        "full-history",
+        "plan a new crawl of the whole history of every stock, replacing the manifest",
+    );
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    let config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+    let path = match matches.opt_str("m") {
+        Some(path) => path,
+        None => format!(
+            "{}/{}",
+            config.portfolio_path,
+            bulk::CRAWL_MANIFEST_FILENAME
+        ),
+    };
+    let threads = match matches.opt_str("t") {
+        Some(threads) => threads.parse().unwrap(),
+        None => DEFAULT_THREADS,
+    };
+    let finmind = finmind::Finmind::new(&config.finmind_token);
+    let mut manifest = match matches.opt_present("full-history") {
+        true => {
+            let manifest = bulk::CrawlManifest::plan(
+                &finmind.get_stock_list().unwrap(),
+                bulk::get_history_start_date(),
+                clock::SystemClock.today(),
+            );
+
+            std::fs::create_dir_all(&config.portfolio_path).unwrap();
+            manifest.save(&path).unwrap();
+            println!("Planned {} requests into {}", manifest.chunks.len(), path);
+            manifest
+        }
+        false => match bulk::CrawlManifest::load(&path).unwrap() {
+            Some(manifest) => {
+                println!(
+                    "Resuming {}: {} of {} requests done",
+                    path,
+                    manifest.get_done_count(),
+                    manifest.chunks.len()
+                );
+                manifest
+            }
+            None => return print_usage(&opts),
+        },
+    };
+    let backend_op = Rc::new(backend::SledBackend::new(&config.db_path).unwrap());
+    let token = config.finmind_token.to_owned();
+    let get_remaining = || {
+        finmind
+            .get_user_info()
+            .map(|user_info| user_info.get_remaining())
+    };
+
+    bulk::BulkCrawl::new(backend_op, threads)
+        .run(
+            &mut manifest,
+            &path,
+            move |_| finmind::Finmind::new(&token),
+            Some(&get_remaining),
+        )
+        .unwrap();
+    println!(
+        "{} of {} requests done, {} failed; run again to retry the failed ones",
+        manifest.get_done_count(),
+        manifest.chunks.len(),
+        manifest.get_failed_count()
+    );
+}
@@ -3,8 +3,38 @@ extern crate getopts;
 use std::rc::Rc;
 
 use veronica::config::config;
-use veronica::storage::backend;
-use veronica::strategy::strategy::{self, StrategyAPI};
+use veronica::dataview::view::{self, Transform};
+use veronica::diagram::diagram;
+use veronica::export::export;
+use veronica::storage::backend::{self, BackendOp};
+use veronica::strategy::schema;
+
+/// Calendar days queried before `--start` so that indicators are warmed up.
+const WARM_UP_DAYS: i64 = 120;
+
+/// Writes the series of `view` to YAML so it can be inspected outside the crate.
+fn export_view(
+    view: &view::Views,
+    records: &Vec<schema::RawData>,
+    file_path: &str,
+) -> Result<(), view::Error> {
+    match view {
+        view::Views::None => export::to_yaml(file_path, records),
+        view::Views::BollingerBand => {
+            export::to_yaml(file_path, &view::BollingerBandView::transform(records)?)
+        }
+        view::Views::Atr => export::to_yaml(file_path, &view::AtrView::transform(records)?),
+        view::Views::EwmaVolatility => {
+            export::to_yaml(file_path, &view::EwmaVolatilityView::transform(records)?)
+        }
+        view::Views::Returns => export::to_yaml(file_path, &view::ReturnsView::transform(records)?),
+        view::Views::Rsi => export::to_yaml(file_path, &view::RsiView::transform(records)?),
+        view::Views::Macd => export::to_yaml(file_path, &view::MacdView::transform(records)?),
+        view::Views::Keltner => export::to_yaml(file_path, &view::KeltnerView::transform(records)?),
+        view::Views::Beta => println!("Beta needs a benchmark and is not exported"),
+    }
+    Ok(())
+}
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
@@ -12,9 +42,21 @@ fn main() {
 
     opts.reqopt("c", "config", "set config path", "");
     opts.reqopt("s", "stock_id", "set stock id", "");
+    opts.optopt("o", "output", "set html output path", "");
+    opts.optflag("", "show", "open the diagram in a browser instead");
+    opts.optopt("", "image", "also write a static image (png or svg)", "");
+    opts.optopt("", "start", "set start date (YYYY-MM-DD)", "");
+    opts.optopt("", "end", "set end date (YYYY-MM-DD)", "");
+    opts.optopt(
+        "",
+        "view",
+        "set view (none, bollinger, atr, ewma, returns, rsi, macd, keltner)",
+        "",
+    );
+    opts.optopt("", "yaml", "also write the view series to a yaml file", "");
 
     let matches = match opts.parse(&args[1..]) {
-        Ok(m) => { m }
+        Ok(m) => m,
         Err(f) => {
             println!("{}", f);
             return;
@@ -22,9 +64,62 @@ fn main() {
     };
 
     let stock_id = matches.opt_str("s").unwrap();
-    let config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+    let output = if matches.opt_present("show") {
+        diagram::Output::Show
+    } else {
+        diagram::Output::Html(
+            matches
+                .opt_str("o")
+                .unwrap_or(stock_id.to_owned() + ".html"),
+        )
+    };
+    let mut config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+
+    match matches.opt_str("image").as_deref() {
+        Some("png") => config.diagram_style.image_format = Some(diagram::ImageFormat::Png),
+        Some("svg") => config.diagram_style.image_format = Some(diagram::ImageFormat::Svg),
+        Some(format) => {
+            println!("Unsupported image format: {}", format);
+            return;
+        }
+        None => {}
+    }
+    let view = match matches
+        .opt_str("view")
+        .unwrap_or("bollinger".to_owned())
+        .parse::<view::Views>()
+    {
+        Ok(view) => view,
+        Err(err) => {
+            println!("{}", err);
+            return;
+        }
+    };
+    let start_date = matches
+        .opt_str("start")
+        .map(|date| chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d").unwrap());
+    let end_date = matches
+        .opt_str("end")
+        .map(|date| chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d").unwrap());
     let backend_op = Rc::new(backend::SledBackend::new(&config.db_path).unwrap());
-    let strategy = Rc::new(strategy::StrategyFactory::get(strategy::Strategies::BollingerBand, backend_op.clone()));
+    let mut records = backend_op.query_all(&stock_id).unwrap();
 
-    strategy.draw_view(&stock_id).unwrap();
+    if let Some(start_date) = start_date {
+        records.retain(|record| record.date >= start_date - chrono::Duration::days(WARM_UP_DAYS));
+    }
+    if let Some(end_date) = end_date {
+        records.retain(|record| record.date <= end_date);
+    }
+    if let Some(file_path) = matches.opt_str("yaml") {
+        export_view(&view, &records, &file_path).unwrap();
+    }
+    diagram::draw_view_diagram(
+        &stock_id,
+        &view,
+        &records,
+        start_date,
+        &config.diagram_style,
+        &output,
+    )
+    .unwrap();
 }
@@ -0,0 +1,190 @@
+extern crate getopts;
+
+use veronica::config::config;
+use veronica::server::http;
+use veronica::storage::backend;
+use veronica::storage::journal::{self, JournalOp};
+
+const DEFAULT_PORT: u16 = 8082;
+const API_PREFIX: &str = "journal";
+
+fn print_usage(opts: &getopts::Options) {
+    println!(
+        "{}",
+        opts.usage(
+            "Usage: journal -c <config> [-u <user>] [-k <kind>] [-a <seq>] (list | show <seq> \
+             | verify | serve [-p <port>])"
+        )
+    );
+}
+
+/// Entries after sequence number `after_seq` matching the `user` and `kind` filters.
+fn get_entries(
+    backend_op: &backend::SledBackend,
+    after_seq: u64,
+    user: Option<&str>,
+    kind: Option<&str>,
+) -> Result<Vec<journal::JournalEntry>, backend::Error> {
+    Ok(backend_op
+        .list_entries(after_seq)?
+        .into_iter()
+        .filter(|entry| user.is_none_or(|user| entry.profile == user))
+        .filter(|entry| kind.is_none_or(|kind| entry.event.get_kind() == kind))
+        .collect())
+}
+
+/// Serves one request of the read-only REST API:
+/// `GET /journal[?after=<seq>]`, `GET /journal/<seq>`, with the entries filtered as on the
+/// command line.
+fn handle(
+    request: &http::Request,
+    backend_op: &backend::SledBackend,
+    user: Option<&str>,
+    kind: Option<&str>,
+) -> http::Response {
+    if request.method != "GET" {
+        return http::Response::json("405 Method Not Allowed", &"Method Not Allowed");
+    }
+
+    let after_seq = request
+        .get_param("after")
+        .and_then(|after_seq| after_seq.parse().ok())
+        .unwrap_or(0);
+    let segments = match request.get_segments() {
+        Some(segments) => segments,
+        None => return http::Response::json("400 Bad Request", &"Bad Request"),
+    };
+    let segments: Vec<&str> = segments.iter().map(|segment| segment.as_str()).collect();
+    let result = match segments.as_slice() {
+        [API_PREFIX] => get_entries(backend_op, after_seq, user, kind)
+            .map(|entries| http::Response::json("200 OK", &entries)),
+        [API_PREFIX, seq] => match seq.parse::<u64>() {
+            Ok(seq) => backend_op.get_entry(seq).map(|entry| {
+                match entry.filter(|entry| user.is_none_or(|user| entry.profile == user)) {
+                    Some(entry) => http::Response::json("200 OK", &entry),
+                    None => http::Response::json("404 Not Found", &"Not Found"),
+                }
+            }),
+            Err(_) => return http::Response::json("404 Not Found", &"Not Found"),
+        },
+        _ => return http::Response::json("404 Not Found", &"Not Found"),
+    };
+
+    match result {
+        Ok(response) => response,
+        Err(err) => http::Response::json("500 Internal Server Error", &format!("{:?}", err)),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt("c", "config", "set config path", "");
+    opts.optopt("u", "user", "only show the entries of this profile", "NAME");
+    opts.optopt(
+        "k",
+        "kind",
+        "only show entries of this kind",
+        "decision|order_plan|notification",
+    );
+    opts.optopt(
+        "a",
+        "after",
+        "only show entries after this sequence number",
+        "",
+    );
+    opts.optopt("p", "port", "set listening port of serve", "");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    let config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+    let backend_op = backend::SledBackend::new(&config.db_path).unwrap();
+    let user = matches.opt_str("user");
+    let kind = matches.opt_str("kind");
+    let after_seq = match matches.opt_str("after") {
+        Some(after_seq) => after_seq.parse::<u64>().unwrap(),
+        None => 0,
+    };
+
+    match matches.free.first().map(|command| command.as_str()) {
+        Some("list") => {
+            for entry in
+                get_entries(&backend_op, after_seq, user.as_deref(), kind.as_deref()).unwrap()
+            {
+                let summary = match &entry.event {
+                    journal::JournalEvent::Decision(portfolio) => format!(
+                        "{}  hold: {}  selected: {}  settled: {}  equity: {}",
+                        portfolio.date,
+                        portfolio.stocks_hold.len(),
+                        portfolio.stocks_selected.len(),
+                        portfolio.stocks_settled.len(),
+                        portfolio.equity()
+                    ),
+                    journal::JournalEvent::OrderPlan(order_plan) => {
+                        format!("{}  {} orders", order_plan.date, order_plan.orders.len())
+                    }
+                    journal::JournalEvent::Notification {
+                        chat_id,
+                        text,
+                        file,
+                    } => format!(
+                        "chat {}  {}",
+                        chat_id,
+                        file.as_deref().unwrap_or(text.lines().next().unwrap_or(""))
+                    ),
+                };
+
+                println!(
+                    "{:>6}  {}  {:<10} {:<12} {}",
+                    entry.seq,
+                    entry.recorded_at.format("%Y-%m-%d %H:%M:%S"),
+                    entry.profile,
+                    entry.event.get_kind(),
+                    summary
+                );
+            }
+        }
+        Some("show") => {
+            let seq = match matches.free.get(1).and_then(|seq| seq.parse::<u64>().ok()) {
+                Some(seq) => seq,
+                None => return print_usage(&opts),
+            };
+
+            match backend_op.get_entry(seq).unwrap() {
+                Some(entry) => print!("{}", serde_yaml::to_string(&entry).unwrap()),
+                None => println!("Entry {} not found", seq),
+            }
+        }
+        Some("verify") => {
+            let entries = backend_op.list_entries(0).unwrap();
+
+            match journal::verify(&entries) {
+                Some(seq) => println!("Journal altered at entry {}", seq),
+                None => println!("Journal of {} entries intact", entries.len()),
+            }
+        }
+        Some("serve") => {
+            let port = match matches.opt_str("p") {
+                Some(port) => port.parse::<u16>().unwrap(),
+                None => DEFAULT_PORT,
+            };
+
+            println!(
+                "Serving journal on http://127.0.0.1:{}/{}",
+                port, API_PREFIX
+            );
+            http::serve(port, |request| {
+                handle(request, &backend_op, user.as_deref(), kind.as_deref())
+            })
+            .unwrap();
+        }
+        _ => print_usage(&opts),
+    }
+}
@@ -0,0 +1,79 @@
+extern crate getopts;
+
+use std::rc::Rc;
+
+use veronica::config::config;
+use veronica::core::backtesting;
+use veronica::crawler::finmind;
+use veronica::storage::{backend, run};
+use veronica::strategy::strategy;
+
+/// Years of history backtested when no period is given, ending with the last full year.
+const STANDARD_YEARS: i32 = 3;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt("c", "config", "set config path", "");
+    opts.optopt("", "start", "set start date (YYYY-MM-DD)", "");
+    opts.optopt("", "end", "set end date (YYYY-MM-DD)", "");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    let config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+    let last_year = chrono::Datelike::year(&chrono::Local::now().date_naive()) - 1;
+    let start_date = match matches.opt_str("start") {
+        Some(date) => chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d").unwrap(),
+        None => chrono::NaiveDate::from_ymd_opt(last_year - STANDARD_YEARS + 1, 1, 1).unwrap(),
+    };
+    let end_date = match matches.opt_str("end") {
+        Some(date) => chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d").unwrap(),
+        None => chrono::NaiveDate::from_ymd_opt(last_year, 12, 31).unwrap(),
+    };
+    let crawler = Rc::new(finmind::Finmind::new(&config.finmind_token));
+    let backend_op = Rc::new(backend::SledBackend::new(&config.db_path).unwrap());
+    let mut results: Vec<(strategy::Strategies, run::RunMetrics)> = Vec::new();
+
+    for strategy in config.get_builtin_strategies() {
+        let mut strategy_config = config.clone();
+
+        strategy_config.portfolio_path =
+            format!("{}/leaderboard/{:?}", config.portfolio_path, strategy);
+
+        let mut backtesting = backtesting::Backtesting::new(
+            strategy_config,
+            crawler.clone(),
+            backend_op.clone(),
+            strategy.clone(),
+        );
+
+        println!("Running {:?} from {} to {}", strategy, start_date, end_date);
+        backtesting.run(start_date, end_date);
+        results.push((strategy, backtesting.get_run_metrics()));
+    }
+
+    results.sort_by(|lhs, rhs| rhs.1.total_return.total_cmp(&lhs.1.total_return));
+
+    println!(
+        "{:<4} {:<20} {:>10} {:>10} {:>8} {:>10}",
+        "Rank", "Strategy", "Return", "Max DD", "Trades", "Win rate"
+    );
+    for (rank, (strategy, metrics)) in results.iter().enumerate() {
+        println!(
+            "{:<4} {:<20} {:>9.2}% {:>9.2}% {:>8} {:>9.2}%",
+            rank + 1,
+            format!("{:?}", strategy),
+            metrics.total_return,
+            metrics.max_drawdown,
+            metrics.trade_count,
+            metrics.win_rate
+        );
+    }
+}
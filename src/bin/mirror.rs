@@ -0,0 +1,63 @@
+extern crate getopts;
+
+use veronica::config::config;
+use veronica::storage::{backend, mirror};
+
+fn print_usage(opts: &getopts::Options) {
+    println!("{}", opts.usage("Usage: mirror -c <config> (push | pull)"));
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt("c", "config", "set config path", "");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    let config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+    let mirror_config = match &config.mirror {
+        Some(mirror_config) => mirror_config,
+        None => return println!("No mirror in the config"),
+    };
+    let backend_op = backend::SledBackend::new(&config.db_path).unwrap();
+    let store = mirror::S3Store::new(mirror_config);
+    let mirror = mirror::Mirror::new(&store);
+    let path = format!(
+        "{}/{}",
+        config.portfolio_path,
+        mirror::MIRROR_STATE_FILENAME
+    );
+    let mut state = mirror::MirrorState::load(&path).unwrap();
+
+    match matches.free.first().map(|command| command.as_str()) {
+        Some("push") => {
+            let uploaded = mirror
+                .push(
+                    &backend_op,
+                    &backend_op.get_stock_ids().unwrap(),
+                    &mut state,
+                )
+                .unwrap();
+
+            println!("Uploaded {} objects to {}", uploaded, mirror_config.bucket);
+        }
+        Some("pull") => {
+            let downloaded = mirror.pull(&backend_op, &mut state).unwrap();
+
+            println!(
+                "Downloaded {} objects from {}",
+                downloaded, mirror_config.bucket
+            );
+        }
+        _ => return print_usage(&opts),
+    }
+    std::fs::create_dir_all(&config.portfolio_path).unwrap();
+    state.save(&path).unwrap();
+}
@@ -0,0 +1,325 @@
+extern crate getopts;
+
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use veronica::config::config;
+use veronica::crawler::crawler::Crawler;
+use veronica::crawler::finmind;
+use veronica::export::export;
+use veronica::optimizer::pool::TrialPool;
+use veronica::optimizer::{crossval, genetic, pruner, search, tpe};
+use veronica::storage::backend;
+
+/// Parameters of a trial, and the stocks it is restricted to.
+type Job = (search::ParamSet, Option<Arc<[String]>>);
+
+fn evaluate(
+    pool: &TrialPool<Job>,
+    batch: &[search::ParamSet],
+    stock_ids: Option<&Arc<[String]>>,
+) -> Vec<Option<f64>> {
+    let fitnesses = pool.evaluate(
+        batch
+            .iter()
+            .map(|params| (params.clone(), stock_ids.cloned()))
+            .collect(),
+    );
+
+    for (params, fitness) in batch.iter().zip(fitnesses.iter()) {
+        if let Some(fitness) = fitness {
+            pool.println(format!("{:?} -> {:.2}", params, fitness));
+        }
+    }
+    fitnesses
+}
+
+fn run_genetic(
+    pool: &TrialPool<Job>,
+    ranges: &[search::ParamRange],
+    genetic_config: &genetic::GeneticConfig,
+    stock_ids: Option<&Arc<[String]>>,
+) -> genetic::GeneticReport {
+    let report = genetic::optimize_batch(ranges, genetic_config, |batch| {
+        evaluate(pool, batch, stock_ids)
+    });
+
+    println!(
+        "{:>10} {:>12} {:>12} {:>10} {:>6}",
+        "Generation", "Best", "Mean", "Diversity", "Runs"
+    );
+    for generation in report.generations.iter() {
+        println!(
+            "{:>10} {:>12.2} {:>12.2} {:>10.3} {:>6}",
+            generation.generation,
+            generation.best_fitness,
+            generation.mean_fitness,
+            generation.diversity,
+            generation.evaluations
+        );
+    }
+    println!(
+        "{} after {} generations, best {:?} -> {:.2}",
+        if report.cancelled {
+            "Cancelled"
+        } else if report.converged {
+            "Converged"
+        } else {
+            "Stopped"
+        },
+        report.generations.len(),
+        report.best.params,
+        report.best.fitness
+    );
+    report
+}
+
+fn run_tpe(
+    pool: &TrialPool<Job>,
+    ranges: &[search::ParamRange],
+    tpe_config: &tpe::TpeConfig,
+    stock_ids: Option<&Arc<[String]>>,
+) -> tpe::TpeReport {
+    let report = tpe::optimize_batch(ranges, tpe_config, |batch| evaluate(pool, batch, stock_ids));
+
+    println!(
+        "{} after {} trials {:?} -> {:.2}",
+        if report.cancelled {
+            "Cancelled"
+        } else {
+            "Best"
+        },
+        report.trials.len(),
+        report.best.params,
+        report.best.fitness
+    );
+    report
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt("c", "config", "set config path", "");
+    opts.reqopt("r", "rules", "set the rule set of the config to tune", "");
+    opts.reqopt("", "start", "set start date (YYYY-MM-DD)", "");
+    opts.reqopt("", "end", "set end date (YYYY-MM-DD)", "");
+    opts.optmulti(
+        "p",
+        "param",
+        "search a $name placeholder of the rules in name:min:max[:int] (repeatable)",
+        "",
+    );
+    opts.optopt(
+        "",
+        "objective",
+        "set the maximized metric: total_return (default), time_weighted_return, win_rate, \
+         max_drawdown or return_over_drawdown",
+        "",
+    );
+    opts.optopt(
+        "m",
+        "method",
+        "set the search method: genetic (default) or tpe",
+        "",
+    );
+    opts.optopt(
+        "",
+        "population",
+        "set the genetic population size (default 20)",
+        "",
+    );
+    opts.optopt(
+        "",
+        "generations",
+        "set the maximum genetic generations (default 30)",
+        "",
+    );
+    opts.optopt("", "trials", "set the tpe backtests (default 50)", "");
+    opts.optopt("", "seed", "set the random seed (default 1)", "");
+    opts.optopt(
+        "",
+        "folds",
+        "cross-validate the search over this many folds of the stock universe",
+        "",
+    );
+    opts.optflag(
+        "",
+        "prune",
+        "stop trials early that fall clearly behind the best one so far",
+    );
+    opts.optflag(
+        "",
+        "cache",
+        "record trials to the run store and reuse those already run with the same inputs",
+    );
+    opts.optopt(
+        "j",
+        "threads",
+        "set the trials run at once (default: one per cpu)",
+        "",
+    );
+    opts.optopt("o", "output", "set optimize report output path", "");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    let config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+    let rule_set = config
+        .get_rule_set(&matches.opt_str("r").unwrap())
+        .unwrap()
+        .clone();
+    let ranges: Vec<search::ParamRange> = matches
+        .opt_strs("p")
+        .iter()
+        .map(|range| range.parse().unwrap())
+        .collect();
+    let mut genetic_config = genetic::GeneticConfig::default();
+    let mut tpe_config = tpe::TpeConfig::default();
+
+    if let Some(population) = matches.opt_str("population") {
+        genetic_config.population = population.parse().unwrap();
+    }
+    if let Some(generations) = matches.opt_str("generations") {
+        genetic_config.generations = generations.parse().unwrap();
+    }
+    if let Some(trials) = matches.opt_str("trials") {
+        tpe_config.trials = trials.parse().unwrap();
+    }
+    if let Some(seed) = matches.opt_str("seed") {
+        genetic_config.seed = seed.parse().unwrap();
+        tpe_config.seed = genetic_config.seed;
+    }
+
+    let threads = match matches.opt_str("j") {
+        Some(threads) => threads.parse().unwrap(),
+        None => std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+    };
+
+    tpe_config.batch = threads;
+
+    let get_output = |filename: &str| match matches.opt_str("o") {
+        Some(output) => output,
+        None => {
+            std::fs::create_dir_all(&config.portfolio_path).unwrap();
+            config.portfolio_path.to_owned() + "/" + filename
+        }
+    };
+    let output = match matches.opt_present("folds") {
+        true => get_output(crossval::CROSS_VALIDATION_FILENAME),
+        false => get_output(search::OPTIMIZE_REPORT_FILENAME),
+    };
+    let objective = match matches.opt_str("objective") {
+        Some(objective) => objective.parse().unwrap(),
+        None => search::Objective::TotalReturn,
+    };
+    let start_date =
+        chrono::NaiveDate::parse_from_str(&matches.opt_str("start").unwrap(), "%Y-%m-%d").unwrap();
+    let end_date =
+        chrono::NaiveDate::parse_from_str(&matches.opt_str("end").unwrap(), "%Y-%m-%d").unwrap();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let cache = matches.opt_present("cache");
+    let pruner = matches
+        .opt_present("prune")
+        .then(|| pruner::Pruner::new(start_date, end_date));
+
+    {
+        let cancelled = cancelled.clone();
+
+        // Stops handing out trials; those running finish and the report is still written.
+        ctrlc::set_handler(move || cancelled.store(true, Ordering::SeqCst)).unwrap();
+    }
+
+    let pool = {
+        let config = config.clone();
+        let backend = backend::SledBackend::new(&config.db_path).unwrap();
+
+        TrialPool::new(threads, cancelled, move |worker| {
+            let evaluator = search::BacktestEvaluator {
+                config: config.clone(),
+                crawler: Rc::new(finmind::Finmind::new(&config.finmind_token)),
+                backend_op: Rc::new(backend.clone()),
+                rule_set: rule_set.clone(),
+                objective,
+                start_date,
+                end_date,
+                worker,
+                pruner: pruner.clone(),
+                run_op: match cache {
+                    true => Some(Rc::new(backend.clone())),
+                    false => None,
+                },
+            };
+
+            move |(params, stock_ids): Job| evaluator.evaluate_on(&params, stock_ids.as_deref())
+        })
+    };
+    let tpe = matches.opt_str("m").as_deref() == Some("tpe");
+
+    if let Some(folds) = matches.opt_str("folds") {
+        let stock_ids = finmind::Finmind::new(&config.finmind_token)
+            .get_stock_list()
+            .unwrap();
+        let report = crossval::cross_validate(
+            &stock_ids,
+            folds.parse().unwrap(),
+            genetic_config.seed,
+            |train| {
+                let train: Arc<[String]> = train.into();
+
+                match tpe {
+                    true => {
+                        let report = run_tpe(&pool, &ranges, &tpe_config, Some(&train));
+
+                        (!report.cancelled).then_some(report.best)
+                    }
+                    false => {
+                        let report = run_genetic(&pool, &ranges, &genetic_config, Some(&train));
+
+                        (!report.cancelled).then_some(report.best)
+                    }
+                }
+            },
+            |validation, params| {
+                evaluate(
+                    &pool,
+                    std::slice::from_ref(params),
+                    Some(&validation.into()),
+                )[0]
+            },
+        );
+
+        println!(
+            "{:<4} {:>8} {:>12} {:>12} {:>10}",
+            "Fold", "Stocks", "Train", "Validation", "Gap"
+        );
+        for fold_report in report.folds.iter() {
+            println!(
+                "{:<4} {:>8} {:>12.2} {:>12.2} {:>10.2}",
+                fold_report.fold,
+                fold_report.validation_stocks,
+                fold_report.best.fitness,
+                fold_report.validation_fitness,
+                fold_report.gap
+            );
+        }
+        println!(
+            "{:<4} {:>8} {:>12.2} {:>12.2} {:>10.2}",
+            "Mean", "", report.mean_train_fitness, report.mean_validation_fitness, report.mean_gap
+        );
+        if report.cancelled {
+            println!("Cancelled after {} folds", report.folds.len());
+        }
+        export::to_yaml(&output, &report);
+    } else if tpe {
+        export::to_yaml(&output, &run_tpe(&pool, &ranges, &tpe_config, None));
+    } else {
+        export::to_yaml(&output, &run_genetic(&pool, &ranges, &genetic_config, None));
+    }
+}
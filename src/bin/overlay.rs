@@ -0,0 +1,57 @@
+extern crate getopts;
+
+use veronica::config::config;
+use veronica::report::overlay;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt("c", "config", "set config path", "");
+    opts.optopt(
+        "o",
+        "output",
+        "set overlay report output path, inside the portfolio path by default",
+        "",
+    );
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    if matches.free.is_empty() {
+        println!(
+            "{}",
+            opts.usage("Usage: overlay -c <config> [-o <output>] <run_dir | summary>...")
+        );
+        return;
+    }
+
+    let config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+    let paths: Vec<&str> = matches.free.iter().map(|path| path.as_str()).collect();
+    let report = overlay::overlay(&paths).unwrap_or_else(|err| panic!("{}", err));
+    let output = match matches.opt_str("o") {
+        Some(output) => output,
+        None => {
+            std::fs::create_dir_all(&config.portfolio_path).unwrap();
+            config.portfolio_path.to_owned() + "/" + overlay::OVERLAY_REPORT_FILENAME
+        }
+    };
+
+    for curve in &report.curves {
+        println!(
+            "{:<24} return: {:>+8.2}%  time-weighted: {:>+8.2}%  max drawdown: {:>6.2}%",
+            curve.name, curve.total_return, curve.time_weighted_return, curve.max_drawdown
+        );
+    }
+    std::fs::write(&output, report.to_html(&config.diagram_style)).expect("Failed to write report");
+    println!(
+        "Overlay of {} runs written to {}",
+        report.curves.len(),
+        output
+    );
+}
@@ -0,0 +1,67 @@
+extern crate getopts;
+
+use std::rc::Rc;
+
+use veronica::config::config;
+use veronica::core::pairs;
+use veronica::crosssection::cointegration;
+use veronica::crosssection::crosssection;
+use veronica::export::export;
+use veronica::storage::backend;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt("c", "config", "set config path", "");
+    opts.reqopt("s", "stock_ids", "set comma separated stock ids", "");
+    opts.reqopt("", "start", "set start date (YYYY-MM-DD)", "");
+    opts.reqopt("", "end", "set end date (YYYY-MM-DD)", "");
+    opts.optopt("o", "output", "set output path of the pairs", "");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    let config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+    let stock_ids: Vec<String> = matches
+        .opt_str("s")
+        .unwrap()
+        .split(',')
+        .map(|stock_id| stock_id.trim().to_owned())
+        .filter(|stock_id| !stock_id.is_empty())
+        .collect();
+    let start_date =
+        chrono::NaiveDate::parse_from_str(&matches.opt_str("start").unwrap(), "%Y-%m-%d").unwrap();
+    let end_date =
+        chrono::NaiveDate::parse_from_str(&matches.opt_str("end").unwrap(), "%Y-%m-%d").unwrap();
+    let output = match matches.opt_str("o") {
+        Some(output) => output,
+        None => {
+            std::fs::create_dir_all(&config.portfolio_path).unwrap();
+            config.portfolio_path.to_owned() + "/" + pairs::PAIRS_FILENAME
+        }
+    };
+    let backend_op = Rc::new(backend::SledBackend::new(&config.db_path).unwrap());
+    let cross_section = crosssection::CrossSection::new(backend_op);
+    let cointegrations =
+        cointegration::scan(&cross_section, &stock_ids, start_date, end_date).unwrap();
+
+    for cointegration in &cointegrations {
+        println!(
+            "{} / {}: hedge ratio {:.3}, ADF statistic {:.3}",
+            cointegration.first_id,
+            cointegration.second_id,
+            cointegration.hedge_ratio,
+            cointegration.adf_statistic
+        );
+    }
+    export::to_yaml(
+        &output,
+        &pairs::PairsTrading::new(cointegrations.iter().map(pairs::Pair::from).collect()),
+    );
+}
@@ -0,0 +1,120 @@
+extern crate getopts;
+
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use veronica::config::config;
+use veronica::core::backtesting;
+use veronica::crawler::{finmind, stocklist};
+use veronica::dataview::view::{self, Transform};
+use veronica::storage::backend::{self, BackendOp};
+use veronica::strategy::strategy;
+
+const DEFAULT_RUNS: usize = 10;
+
+struct Measurement {
+    name: String,
+    durations: Vec<Duration>,
+}
+
+impl Measurement {
+    fn report(&self) {
+        let total: Duration = self.durations.iter().sum();
+        let min = self.durations.iter().min().unwrap();
+        let max = self.durations.iter().max().unwrap();
+
+        println!(
+            "{:<24} runs: {:>4}  mean: {:>12?}  min: {:>12?}  max: {:>12?}",
+            self.name,
+            self.durations.len(),
+            total / self.durations.len() as u32,
+            min,
+            max
+        );
+    }
+}
+
+fn measure<F: FnMut()>(name: &str, runs: usize, mut f: F) -> Measurement {
+    let mut durations = Vec::new();
+
+    for _ in 0..runs {
+        let start = Instant::now();
+
+        f();
+        durations.push(start.elapsed());
+    }
+
+    Measurement {
+        name: name.to_owned(),
+        durations,
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt("c", "config", "set config path", "");
+    opts.reqopt("s", "stock_id", "set stock id", "");
+    opts.reqopt("", "start", "set start date (YYYY-MM-DD)", "");
+    opts.reqopt("", "end", "set end date (YYYY-MM-DD)", "");
+    opts.optopt("n", "runs", "set number of runs per measurement", "");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    let mut config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+    let stock_id = matches.opt_str("s").unwrap();
+    let start_date =
+        chrono::NaiveDate::parse_from_str(&matches.opt_str("start").unwrap(), "%Y-%m-%d").unwrap();
+    let end_date =
+        chrono::NaiveDate::parse_from_str(&matches.opt_str("end").unwrap(), "%Y-%m-%d").unwrap();
+    let runs = match matches.opt_str("n") {
+        Some(runs) => runs.parse::<usize>().unwrap(),
+        None => DEFAULT_RUNS,
+    };
+    let backend_op = Rc::new(backend::SledBackend::new(&config.db_path).unwrap());
+    let records = backend_op
+        .query_by_range(&stock_id, start_date, end_date)
+        .unwrap();
+
+    println!("{} records of {} in range", records.len(), stock_id);
+
+    let mut measurements = Vec::new();
+
+    measurements.push(measure("query_by_range", runs, || {
+        backend_op
+            .query_by_range(&stock_id, start_date, end_date)
+            .unwrap();
+    }));
+    measurements.push(measure("BollingerBandView", runs, || {
+        view::BollingerBandView::transform(&records).unwrap();
+    }));
+
+    config.portfolio_path = config.portfolio_path.to_owned() + "/perf";
+
+    let crawler = Rc::new(stocklist::StockListCrawler::new(
+        Rc::new(finmind::Finmind::new(&config.finmind_token)),
+        vec![stock_id.to_owned()],
+    ));
+
+    measurements.push(measure("backtest", runs, || {
+        let mut backtesting = backtesting::Backtesting::new(
+            config.clone(),
+            crawler.clone(),
+            backend_op.clone(),
+            strategy::Strategies::BollingerBand,
+        );
+
+        backtesting.run(start_date, end_date);
+    }));
+
+    for measurement in &measurements {
+        measurement.report();
+    }
+}
@@ -0,0 +1,85 @@
+extern crate getopts;
+
+use veronica::config::{config, profile};
+use veronica::core::position;
+
+fn print_usage(opts: &getopts::Options) {
+    println!(
+        "{}",
+        opts.usage(
+            "Usage: positions -c <config> [-u <user>] [-f <file>] (list | hold <stock_id> | release <stock_id> \
+             | note <stock_id> <note>)"
+        )
+    );
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt("c", "config", "set config path", "");
+    opts.optopt("u", "user", "act as this profile of the config", "NAME");
+    opts.optopt(
+        "f",
+        "file",
+        "set position notes path (defaults to the one in the portfolio path)",
+        "",
+    );
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    let config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+    let name = matches
+        .opt_str("user")
+        .unwrap_or(profile::DEFAULT_PROFILE.to_owned());
+    let profile = config
+        .get_profile(&name)
+        .unwrap_or_else(|| panic!("Unknown user {}", name));
+    let config = config.for_profile(&profile);
+    let path = match matches.opt_str("f") {
+        Some(path) => path,
+        None => format!(
+            "{}/{}",
+            config.portfolio_path,
+            position::POSITION_NOTES_FILENAME
+        ),
+    };
+    let mut position_notes = position::load_position_notes(&path).unwrap();
+
+    match matches.free.first().map(|command| command.as_str()) {
+        Some("list") => {
+            for (stock_id, position_note) in &position_notes {
+                println!(
+                    "{:<8} {:<12} {}",
+                    stock_id,
+                    match position_note.manual_hold {
+                        true => "manual hold",
+                        false => "",
+                    },
+                    position_note.note
+                );
+            }
+        }
+        Some(command @ ("hold" | "release" | "note")) => {
+            let stock_id = match matches.free.get(1) {
+                Some(stock_id) => stock_id,
+                None => return print_usage(&opts),
+            };
+            let position_note = position_notes.entry(stock_id.to_owned()).or_default();
+
+            match command {
+                "hold" => position_note.manual_hold = true,
+                "release" => position_note.manual_hold = false,
+                _ => position_note.note = matches.free[2..].join(" "),
+            }
+            position::save_position_notes(&path, &position_notes).unwrap();
+        }
+        _ => print_usage(&opts),
+    }
+}
@@ -0,0 +1,132 @@
+extern crate getopts;
+
+use std::path::{Path, PathBuf};
+
+use veronica::server::http;
+
+const DEFAULT_PORT: u16 = 8080;
+
+fn get_content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("yaml") | Some("csv") | Some("txt") => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+fn collect_reports(dir: &Path, root: &Path, reports: &mut Vec<String>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path.is_dir() {
+            collect_reports(&path, root, reports);
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "html")
+        {
+            if let Ok(relative_path) = path.strip_prefix(root) {
+                reports.push(relative_path.to_string_lossy().replace('\\', "/"));
+            }
+        }
+    }
+}
+
+fn get_index(root: &Path) -> String {
+    let mut reports = Vec::new();
+
+    collect_reports(root, root, &mut reports);
+    reports.sort();
+
+    let items: Vec<String> = reports
+        .iter()
+        .map(|report| format!("<li><a href=\"/{0}\">{0}</a></li>", report))
+        .collect();
+
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>Veronica reports</title></head>\
+         <body><h1>{}</h1><ul>{}</ul></body></html>",
+        root.display(),
+        items.join("")
+    )
+}
+
+/// Maps the decoded path segments of a request onto a file under `root`, refusing anything
+/// that escapes it, including separators smuggled in as `%2F`.
+fn resolve(root: &Path, segments: &[String]) -> Option<PathBuf> {
+    let mut path = root.to_path_buf();
+
+    for segment in segments {
+        match segment.as_str() {
+            "." => continue,
+            ".." => return None,
+            segment if segment.contains(['/', '\\']) => return None,
+            segment => path.push(segment),
+        }
+    }
+    if path.is_file() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+fn handle(request: &http::Request, root: &Path) -> http::Response {
+    if request.method != "GET" {
+        return http::Response::text("405 Method Not Allowed", "Method Not Allowed");
+    }
+
+    let segments = match request.get_segments() {
+        Some(segments) => segments,
+        None => return http::Response::text("400 Bad Request", "Bad Request"),
+    };
+
+    if segments.is_empty() {
+        return http::Response::new(
+            "200 OK",
+            "text/html; charset=utf-8",
+            get_index(root).into_bytes(),
+        );
+    }
+
+    match resolve(root, &segments).map(|path| (std::fs::read(&path), path)) {
+        Some((Ok(body), path)) => http::Response::new("200 OK", get_content_type(&path), body),
+        _ => http::Response::text("404 Not Found", "Not Found"),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt(
+        "d",
+        "dir",
+        "set report directory (usually the portfolio path)",
+        "",
+    );
+    opts.optopt("p", "port", "set listening port", "");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    let root = PathBuf::from(matches.opt_str("d").unwrap());
+    let port = match matches.opt_str("p") {
+        Some(port) => port.parse::<u16>().unwrap(),
+        None => DEFAULT_PORT,
+    };
+
+    println!("Serving {} on http://127.0.0.1:{}/", root.display(), port);
+    http::serve(port, |request| handle(request, &root)).unwrap();
+}
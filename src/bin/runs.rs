@@ -0,0 +1,114 @@
+extern crate getopts;
+
+use std::rc::Rc;
+
+use veronica::config::{config, profile};
+use veronica::storage::backend::{self, BackendOp};
+use veronica::storage::namespace;
+use veronica::storage::run::RunOp;
+
+fn print_usage(opts: &getopts::Options) {
+    println!(
+        "{}",
+        opts.usage(
+            "Usage: runs -c <config> [-u <user>] (list [-t <tag>]... | show <run_id> | tag <run_id> <tag>... \
+             | untag <run_id> <tag>... | note <run_id> <note>)"
+        )
+    );
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt("c", "config", "set config path", "");
+    opts.optopt("u", "user", "act as this profile of the config", "NAME");
+    opts.optmulti("t", "tag", "only list runs with this tag (repeatable)", "");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    let config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+    let name = matches
+        .opt_str("user")
+        .unwrap_or(profile::DEFAULT_PROFILE.to_owned());
+    let profile = config
+        .get_profile(&name)
+        .unwrap_or_else(|| panic!("Unknown user {}", name));
+    let backend_op = Rc::new(backend::SledBackend::new(&config.db_path).unwrap());
+    let run_op = namespace::Namespaced::new(backend_op.clone(), profile.get_namespace());
+
+    match matches.free.first().map(|command| command.as_str()) {
+        Some("list") => {
+            for run in run_op.list_runs(&matches.opt_strs("tag")).unwrap() {
+                println!(
+                    "{}  {:?}  {} ~ {}  return: {:>8.2}%  mdd: {:>6.2}%  trades: {}  [{}]  {}",
+                    run.run_id,
+                    run.manifest.strategy,
+                    run.manifest.start_date,
+                    run.manifest.end_date,
+                    run.metrics.total_return,
+                    run.metrics.max_drawdown,
+                    run.metrics.trade_count,
+                    run.tags.join(", "),
+                    run.note
+                );
+            }
+        }
+        Some("show") => {
+            let run_id = match matches.free.get(1) {
+                Some(run_id) => run_id,
+                None => return print_usage(&opts),
+            };
+
+            match run_op.get_run(run_id).unwrap() {
+                Some(run) => {
+                    print!("{}", serde_yaml::to_string(&run).unwrap());
+
+                    let generation = backend_op.get_generation().unwrap();
+
+                    if let Some(data_generation) = run.manifest.data_generation {
+                        if data_generation != generation {
+                            println!(
+                                "Data changed since this run (generation {} -> {}), it cannot be \
+                                 reproduced exactly",
+                                data_generation, generation
+                            );
+                        }
+                    }
+                }
+                None => println!("Run {} not found", run_id),
+            }
+        }
+        Some(command @ ("tag" | "untag" | "note")) => {
+            let run_id = match matches.free.get(1) {
+                Some(run_id) => run_id,
+                None => return print_usage(&opts),
+            };
+            let mut run = match run_op.get_run(run_id).unwrap() {
+                Some(run) => run,
+                None => return println!("Run {} not found", run_id),
+            };
+            let values = &matches.free[2..];
+
+            match command {
+                "tag" => {
+                    for tag in values {
+                        if !run.tags.contains(tag) {
+                            run.tags.push(tag.to_owned());
+                        }
+                    }
+                }
+                "untag" => run.tags.retain(|tag| !values.contains(tag)),
+                _ => run.note = values.join(" "),
+            }
+            run_op.insert_run(&run).unwrap();
+        }
+        _ => print_usage(&opts),
+    }
+}
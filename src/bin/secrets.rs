@@ -0,0 +1,90 @@
+extern crate getopts;
+
+use std::io::BufRead;
+
+use veronica::config::secrets;
+
+fn print_usage(opts: &getopts::Options) {
+    println!(
+        "{}",
+        opts.usage(
+            "Usage: secrets -c <config> [--keychain] (list | set <name> [<value>] | remove <name>)\n\
+             Without a value, set reads it from the first line of stdin, keeping it out of the \
+             shell history. Refer to a secret in the config as secret:<name>, or \
+             keychain:<name> when stored with --keychain."
+        )
+    );
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt("c", "config", "set config path", "");
+    opts.optflag(
+        "",
+        "keychain",
+        "store in the OS keychain instead of the secrets file",
+    );
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    let path = secrets::get_secrets_path(&matches.opt_str("c").unwrap());
+    let command = matches.free.first().map(|command| command.as_str());
+    let name = matches.free.get(1);
+
+    if matches.opt_present("keychain") {
+        match (command, name) {
+            (Some("set"), Some(name)) => {
+                let secret = match matches.free.get(2) {
+                    Some(secret) => secret.to_owned(),
+                    None => read_secret(),
+                };
+
+                secrets::set_keychain_secret(name, &secret).unwrap();
+            }
+            _ => print_usage(&opts),
+        }
+        return;
+    }
+
+    let passphrase = secrets::get_passphrase().unwrap();
+    let mut stored = secrets::load_secrets(&path, &passphrase).unwrap();
+
+    match (command, name) {
+        (Some("list"), _) => {
+            for name in stored.keys() {
+                println!("{}", name);
+            }
+            return;
+        }
+        (Some("set"), Some(name)) => {
+            let secret = match matches.free.get(2) {
+                Some(secret) => secret.to_owned(),
+                None => read_secret(),
+            };
+
+            stored.insert(name.to_owned(), secret);
+        }
+        (Some("remove"), Some(name)) => {
+            if stored.remove(name).is_none() {
+                return println!("No secret {}", name);
+            }
+        }
+        _ => return print_usage(&opts),
+    }
+    secrets::save_secrets(&path, &passphrase, &stored).unwrap();
+}
+
+fn read_secret() -> String {
+    let mut line = String::new();
+
+    std::io::stdin().lock().read_line(&mut line).unwrap();
+    line.trim_end_matches(['\r', '\n']).to_owned()
+}
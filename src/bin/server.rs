@@ -0,0 +1,183 @@
+extern crate getopts;
+
+use std::rc::Rc;
+use std::sync::Arc;
+
+use veronica::config::{config, profile};
+use veronica::core::clock::{self, Clock};
+use veronica::core::decision;
+use veronica::crawler::finmind;
+use veronica::dataview::adjust;
+use veronica::server::{engine, graphql, http};
+use veronica::storage::backend;
+use veronica::strategy::strategy;
+
+const DEFAULT_PORT: u16 = 8083;
+/// Time between checks of whether the scheduled decision is due.
+const SCHEDULE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Decisions of `profile`, with its capital and strategy, on a backend and a crawler of their
+/// own.
+fn get_decision_builder(
+    config: config::Config,
+    profile: profile::Profile,
+    backend_op: backend::SledBackend,
+) -> Arc<engine::DecisionBuilder> {
+    let strategy_type = profile.get_strategy(&config).unwrap();
+
+    Arc::new(move || {
+        let backend_op = Rc::new(backend_op.clone());
+        let mut strategy = strategy::StrategyFactory::get(
+            strategy_type.clone(),
+            &config.strategy_params,
+            backend_op.clone(),
+        );
+
+        strategy.set_back_adjustment(config.get_back_adjustment());
+
+        let mut decision = decision::Decision::new(
+            Rc::new(finmind::Finmind::new(&config.finmind_token)),
+            backend_op,
+            Rc::new(strategy),
+        );
+
+        decision.liquidity = profile.liquidity;
+        decision.stocks_hold_num = profile.stocks_hold_num;
+        decision.circuit_breaker = config.circuit_breaker.clone();
+        decision.max_daily_loss = config.max_daily_loss;
+        decision.regime_filter = config.get_regime_filter(&strategy_type);
+        decision.breadth_filter = config.breadth_filter.clone();
+        decision.hedge = config.hedge.clone();
+        decision.dca = match &strategy_type {
+            strategy::Strategies::Dca(plan) => Some(plan.clone()),
+            _ => None,
+        };
+        decision
+    })
+}
+
+fn to_response(result: Result<engine::Reply, engine::Error>) -> http::Response {
+    match result {
+        Ok(engine::Reply::Portfolio(Some(portfolio))) => http::Response::json("200 OK", &portfolio),
+        Ok(engine::Reply::Portfolio(None)) => {
+            http::Response::json("404 Not Found", &"No decision yet")
+        }
+        Ok(engine::Reply::Trades(order_plans)) => http::Response::json("200 OK", &order_plans),
+        Err(engine::Error::AlreadyDecided(date)) => {
+            http::Response::json("409 Conflict", &format!("{} is decided already", date))
+        }
+        Err(engine::Error::NoTrading(date)) => {
+            http::Response::json("404 Not Found", &format!("No trading on {}", date))
+        }
+        Err(err) => http::Response::json("500 Internal Server Error", &format!("{:?}", err)),
+    }
+}
+
+/// Serves one request of the REST API:
+/// `POST /decisions[/<YYYY-MM-DD>]` decides the day, today by default,
+/// `GET /portfolio` answers the portfolio of the last decided day and `GET /trades` the orders
+/// of every decided day. `POST /graphql` queries the stored records and their views, see
+/// `graphql::execute`.
+fn handle(
+    request: &http::Request,
+    engine: &engine::Engine,
+    backend_op: &backend::SledBackend,
+    back_adjustment: &adjust::BackAdjustment,
+) -> http::Response {
+    let segments = match request.get_segments() {
+        Some(segments) => segments,
+        None => return http::Response::json("400 Bad Request", &"Bad Request"),
+    };
+    let segments: Vec<&str> = segments.iter().map(|segment| segment.as_str()).collect();
+    let command = match (request.method.as_str(), segments.as_slice()) {
+        ("POST", ["decisions"]) => engine::Command::RunDecision(clock::SystemClock.today()),
+        ("POST", ["decisions", date]) => match date.parse() {
+            Ok(date) => engine::Command::RunDecision(date),
+            Err(_) => return http::Response::json("400 Bad Request", &"Invalid date"),
+        },
+        ("GET", ["portfolio"]) => engine::Command::GetPortfolio,
+        ("GET", ["trades"]) => engine::Command::ListTrades,
+        ("POST", ["graphql"]) => {
+            return match serde_json::from_slice(&request.body) {
+                Ok(graphql_request) => http::Response::json(
+                    "200 OK",
+                    &graphql::execute(backend_op, back_adjustment, &graphql_request),
+                ),
+                Err(err) => http::Response::json("400 Bad Request", &err.to_string()),
+            }
+        }
+        (_, ["decisions"] | ["decisions", _] | ["portfolio"] | ["trades"] | ["graphql"]) => {
+            return http::Response::json("405 Method Not Allowed", &"Method Not Allowed")
+        }
+        _ => return http::Response::json("404 Not Found", &"Not Found"),
+    };
+
+    to_response(engine.execute(command))
+}
+
+/// Decides each day once the clock passes `time`, alongside the requests served.
+fn schedule(engine: engine::Engine, time: chrono::NaiveTime) {
+    let mut decided_date = None;
+
+    loop {
+        let now = clock::SystemClock.now();
+
+        if now.time() >= time && decided_date != Some(now.date()) {
+            decided_date = Some(now.date());
+            match engine.execute(engine::Command::RunDecision(now.date())) {
+                Ok(_) => println!("Decided {}", now.date()),
+                Err(err) => println!("Failed to decide {}: {:?}", now.date(), err),
+            }
+        }
+        std::thread::sleep(SCHEDULE_INTERVAL);
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt("c", "config", "set config path", "");
+    opts.optopt("u", "user", "trade as this profile of the config", "NAME");
+    opts.optopt("p", "port", "set listening port", "");
+    opts.optopt("", "schedule", "decide every day at this time", "HH:MM");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    let config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+    let name = matches
+        .opt_str("user")
+        .unwrap_or(profile::DEFAULT_PROFILE.to_owned());
+    let profile = config
+        .get_profile(&name)
+        .unwrap_or_else(|| panic!("Unknown user {}", name));
+    let backend_op = backend::SledBackend::new(&config.db_path).unwrap();
+    let port = match matches.opt_str("p") {
+        Some(port) => port.parse::<u16>().unwrap(),
+        None => DEFAULT_PORT,
+    };
+    let back_adjustment = config.get_back_adjustment();
+    let engine = engine::Engine::new(get_decision_builder(config, profile, backend_op.clone()));
+
+    if let Some(time) = matches.opt_str("schedule") {
+        let time = chrono::NaiveTime::parse_from_str(&time, "%H:%M").unwrap();
+        let engine = engine.clone();
+
+        std::thread::spawn(move || schedule(engine, time));
+    }
+
+    println!(
+        "Serving the engine of {} on http://127.0.0.1:{}/",
+        name, port
+    );
+    http::serve(port, |request| {
+        handle(request, &engine, &backend_op, &back_adjustment)
+    })
+    .unwrap();
+}
@@ -0,0 +1,102 @@
+extern crate getopts;
+
+use std::rc::Rc;
+
+use veronica::analytics::signal;
+use veronica::config::config;
+use veronica::diagram::diagram;
+use veronica::storage::backend;
+use veronica::strategy::strategy;
+
+/// Days listed as the busiest of each signal.
+const TOP_DAYS: usize = 5;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt("c", "config", "set config path", "");
+    opts.reqopt("s", "stock_ids", "set comma separated stock ids", "");
+    opts.reqopt("", "start", "set start date (YYYY-MM-DD)", "");
+    opts.reqopt("", "end", "set end date (YYYY-MM-DD)", "");
+    opts.optopt(
+        "",
+        "script",
+        "follow the strategy of this rhai script instead of the Bollinger band one",
+        "",
+    );
+    opts.optopt(
+        "",
+        "rules",
+        "follow the rule-based strategy of this name from the config",
+        "",
+    );
+    opts.optopt("o", "output", "set heatmap output path", "");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    let config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+    let stock_ids: Vec<String> = matches
+        .opt_str("s")
+        .unwrap()
+        .split(',')
+        .map(|stock_id| stock_id.trim().to_owned())
+        .filter(|stock_id| !stock_id.is_empty())
+        .collect();
+    let start_date =
+        chrono::NaiveDate::parse_from_str(&matches.opt_str("start").unwrap(), "%Y-%m-%d").unwrap();
+    let end_date =
+        chrono::NaiveDate::parse_from_str(&matches.opt_str("end").unwrap(), "%Y-%m-%d").unwrap();
+    let strategies = if let Some(script_path) = matches.opt_str("script") {
+        strategy::Strategies::Script(script_path)
+    } else if let Some(name) = matches.opt_str("rules") {
+        strategy::Strategies::Rule(config.get_rule_set(&name).unwrap().clone())
+    } else {
+        strategy::Strategies::BollingerBand
+    };
+    let output = match matches.opt_str("o") {
+        Some(output) => output,
+        None => {
+            std::fs::create_dir_all(&config.portfolio_path).unwrap();
+            config.portfolio_path.to_owned() + "/" + signal::SIGNAL_HEATMAP_FILENAME
+        }
+    };
+    let backend_op = Rc::new(backend::SledBackend::new(&config.db_path).unwrap());
+    let mut strategy =
+        strategy::StrategyFactory::get(strategies, &config.strategy_params, backend_op.clone());
+
+    strategy.set_back_adjustment(config.get_back_adjustment());
+    let heatmap = signal::SignalHeatmap::compute(
+        &strategy,
+        backend_op.as_ref(),
+        &stock_ids,
+        start_date,
+        end_date,
+    )
+    .unwrap();
+
+    for (name, kind) in [
+        ("Buy", signal::Signal::Buy),
+        ("Settle", signal::Signal::Settle),
+    ] {
+        let days: Vec<String> = heatmap
+            .get_clusters(kind)
+            .into_iter()
+            .take(TOP_DAYS)
+            .map(|(date, count)| format!("{} ({})", date, count))
+            .collect();
+
+        println!("{} signals clustered on: {}", name, days.join(", "));
+    }
+    heatmap.draw_heatmap(
+        &config.diagram_style,
+        &diagram::Output::Html(output.to_owned()),
+    );
+    println!("Signal heatmap written to {}", output);
+}
@@ -0,0 +1,63 @@
+extern crate getopts;
+
+use std::rc::Rc;
+
+use veronica::analytics::split;
+use veronica::config::config;
+use veronica::storage::backend;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt("c", "config", "set config path", "");
+    opts.reqopt("s", "stock_ids", "set comma separated stock ids", "");
+    opts.optopt("o", "output", "set split candidates output path", "");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    let config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+    let stock_ids: Vec<String> = matches
+        .opt_str("s")
+        .unwrap()
+        .split(',')
+        .map(|stock_id| stock_id.trim().to_owned())
+        .filter(|stock_id| !stock_id.is_empty())
+        .collect();
+    let output = match matches.opt_str("o") {
+        Some(output) => output,
+        None => {
+            std::fs::create_dir_all(&config.portfolio_path).unwrap();
+            config.portfolio_path.to_owned() + "/" + split::SPLIT_CANDIDATES_FILENAME
+        }
+    };
+    let backend_op = Rc::new(backend::SledBackend::new(&config.db_path).unwrap());
+    let candidates = split::SplitDetector::new(backend_op)
+        .scan(&stock_ids)
+        .unwrap();
+
+    for candidate in &candidates {
+        println!(
+            "{} {}: {:.2} -> {:.2} (1:{} split, volume x{:.1}), adjustment factor {}",
+            candidate.stock_id,
+            candidate.date,
+            candidate.prev_close,
+            candidate.close,
+            candidate.ratio,
+            candidate.volume_ratio,
+            candidate.adjustment_factor
+        );
+    }
+    std::fs::write(&output, serde_yaml::to_string(&candidates).unwrap()).unwrap();
+    println!(
+        "{} split candidates written to {} for review",
+        candidates.len(),
+        output
+    );
+}
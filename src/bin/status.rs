@@ -0,0 +1,121 @@
+extern crate getopts;
+
+use veronica::config::config;
+use veronica::core::{backtesting, clock};
+use veronica::crawler::finmind;
+use veronica::storage::backend::{self, BackendOp};
+
+/// Stock probed for the most recent stored record; it trades on every market day.
+const DEFAULT_PROBE_STOCK_ID: &str = "0050";
+/// Calendar days searched back for the most recent stored record.
+const PROBE_DAYS: i64 = 30;
+
+struct Status {
+    failed: bool,
+}
+
+impl Status {
+    fn ok(&self, name: &str, detail: &str) {
+        println!("[OK]   {:<10} {}", name, detail);
+    }
+
+    fn fail(&mut self, name: &str, detail: &str) {
+        self.failed = true;
+        println!("[FAIL] {:<10} {}", name, detail);
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt("c", "config", "set config path", "");
+    opts.optopt("", "as-of", "check as if on this day", "YYYY-MM-DD");
+    opts.optopt(
+        "s",
+        "stock_id",
+        "set stock id probed for the latest record",
+        "",
+    );
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    let config = match config::load_config(&matches.opt_str("c").unwrap()) {
+        Some(config) => config,
+        None => {
+            println!("[FAIL] {:<10} cannot load config", "config");
+            std::process::exit(1);
+        }
+    };
+    let stock_id = matches
+        .opt_str("s")
+        .unwrap_or(DEFAULT_PROBE_STOCK_ID.to_owned());
+    let today =
+        clock::get_clock(matches.opt_str("as-of").map(|as_of| as_of.parse().unwrap())).today();
+    let mut status = Status { failed: false };
+
+    match backend::SledBackend::new(&config.db_path) {
+        Ok(backend_op) => {
+            status.ok("backend", &config.db_path);
+            match backend_op.query_by_range(
+                &stock_id,
+                today - chrono::Duration::days(PROBE_DAYS),
+                today,
+            ) {
+                Ok(records) => match records.last() {
+                    Some(record) => status.ok(
+                        "record",
+                        &format!("latest {} record on {}", stock_id, record.date),
+                    ),
+                    None => status.fail(
+                        "record",
+                        &format!("no {} record in the last {} days", stock_id, PROBE_DAYS),
+                    ),
+                },
+                Err(err) => status.fail("record", &format!("{:?}", err)),
+            }
+        }
+        Err(err) => status.fail("backend", &format!("{:?}", err)),
+    }
+
+    match finmind::Finmind::new(&config.finmind_token).get_user_info() {
+        Ok(user_info) if user_info.get_remaining() > 0 => status.ok(
+            "finmind",
+            &format!(
+                "{} of {} requests remaining",
+                user_info.get_remaining(),
+                user_info.api_request_limit
+            ),
+        ),
+        Ok(user_info) => status.fail(
+            "finmind",
+            &format!("quota of {} requests used up", user_info.api_request_limit),
+        ),
+        Err(err) => status.fail("finmind", &format!("{:?}", err)),
+    }
+
+    let summary_path =
+        config.portfolio_path.to_owned() + "/" + backtesting::PORTFOLIO_SUMMARY_FILENAME;
+    let summary = std::fs::read_to_string(&summary_path)
+        .ok()
+        .and_then(|data| serde_yaml::from_str::<backtesting::PortfolioSummary>(&data).ok());
+
+    match summary.and_then(|summary| summary.last_date) {
+        Some(date) if date == today => status.ok("decision", &format!("produced for {}", date)),
+        Some(date) => status.fail("decision", &format!("latest decision is for {}", date)),
+        None => status.fail(
+            "decision",
+            &format!("no decision found in {}", summary_path),
+        ),
+    }
+
+    if status.failed {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,372 @@
+extern crate getopts;
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use veronica::config::config;
+use veronica::core::{backtesting, clock, decision, picks};
+use veronica::crawler::finmind;
+use veronica::dataview::view;
+use veronica::diagram::diagram;
+use veronica::notifier::telegram;
+use veronica::storage::backend::{self, BackendOp};
+use veronica::storage::watchlist::WatchlistOp;
+use veronica::storage::{journal, namespace};
+use veronica::strategy::strategy;
+
+const PICKS_NUM: usize = 5;
+/// Calendar days shown by `/chart`.
+const CHART_DAYS: i64 = 365;
+/// Calendar days queried before the chart starts so that indicators are warmed up.
+const CHART_WARM_UP_DAYS: i64 = 120;
+/// Time between checks for a new decision to notify the picks diff of.
+const PICKS_DIFF_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+const HELP: &str = "/portfolio - holdings of the latest decision\n\
+                    /picks [YYYY-MM-DD] [WATCHLIST] - screening results of a day, today by default\n\
+                    /chart STOCK_ID - chart of the last year";
+
+/// Bot of one profile, answering the chats of that profile.
+struct Bot {
+    config: config::Config,
+    backend_op: Rc<backend::SledBackend>,
+    watchlist_op: namespace::Namespaced<backend::SledBackend>,
+    journal: journal::Journal,
+    clock: Rc<dyn clock::Clock>,
+    decision: decision::Decision,
+    /// Date of the last decision whose picks diff was sent.
+    notified_date: Cell<Option<chrono::NaiveDate>>,
+}
+
+impl Bot {
+    fn handle(&self, text: &str) -> Result<Reply, String> {
+        let mut args = text.split_whitespace();
+
+        match args.next() {
+            Some("/portfolio") => self.get_portfolio().map(Reply::Text),
+            Some("/picks") => {
+                let mut arg = args.next();
+                let date = match arg
+                    .and_then(|date| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+                {
+                    Some(date) => {
+                        arg = args.next();
+                        date
+                    }
+                    None => self.clock.today(),
+                };
+
+                self.get_picks(date, arg).map(Reply::Text)
+            }
+            Some("/chart") => {
+                let stock_id = args.next().ok_or("Usage: /chart STOCK_ID")?;
+
+                if !stock_id.chars().all(|c| c.is_ascii_alphanumeric()) {
+                    return Err(format!("Invalid stock id {}", stock_id));
+                }
+
+                self.draw_chart(stock_id).map(Reply::File)
+            }
+            _ => Ok(Reply::Text(HELP.to_owned())),
+        }
+    }
+
+    fn load_portfolios(&self) -> Result<Vec<decision::Portfolio>, String> {
+        let path = self.config.portfolio_path.to_owned() + "/" + backtesting::PORTFOLIO_FILENAME;
+        let data = std::fs::read_to_string(&path).map_err(|err| err.to_string())?;
+
+        serde_yaml::from_str(&data).map_err(|err| err.to_string())
+    }
+
+    fn get_portfolio(&self) -> Result<String, String> {
+        let portfolios = self.load_portfolios()?;
+        let portfolio = portfolios.last().ok_or("No portfolio yet")?;
+        let mut lines = vec![format!("Portfolio of {}", portfolio.date)];
+
+        for stock_info in portfolio
+            .stocks_hold
+            .iter()
+            .chain(&portfolio.stocks_selected)
+        {
+            let mut line = format!(
+                "{} x {} @ {}",
+                stock_info.stock_id, stock_info.num, stock_info.price
+            );
+
+            if let Some(halt) = &stock_info.halt {
+                line.push_str(&format!(" [halted {} days]", halt.days));
+            }
+            if let Some(position_note) = &stock_info.position_note {
+                if position_note.manual_hold {
+                    line.push_str(" [manual hold]");
+                }
+                if !position_note.note.is_empty() {
+                    line.push_str(&format!(" - {}", position_note.note));
+                }
+            }
+            lines.push(line);
+        }
+        lines.push(format!("Cash: {}", portfolio.liquidity));
+        lines.push(format!("Equity: {}", portfolio.equity()));
+        Ok(lines.join("\n"))
+    }
+
+    /// Best scored stocks of `date`, among those of the watchlist named `watchlist` if any.
+    fn get_picks(
+        &self,
+        date: chrono::NaiveDate,
+        watchlist: Option<&str>,
+    ) -> Result<String, String> {
+        let (stock_scores, mut lines) = match watchlist {
+            Some(name) => {
+                let watchlist = self
+                    .watchlist_op
+                    .get_watchlist(name)
+                    .map_err(|err| format!("{:?}", err))?
+                    .ok_or(format!("Unknown watchlist {}", name))?;
+
+                (
+                    self.decision
+                        .get_candidate_scores(date, &watchlist.stock_ids),
+                    vec![format!("Picks of {} in {}", date, name)],
+                )
+            }
+            None => (
+                self.decision.get_stock_scores(date),
+                vec![format!("Picks of {}", date)],
+            ),
+        };
+        let stock_scores = stock_scores.map_err(|err| format!("{:?}", err))?;
+
+        for (stock_id, score) in stock_scores
+            .iter()
+            .filter(|(_, score)| score.point > 0)
+            .take(PICKS_NUM)
+        {
+            lines.push(format!("{} score {}", stock_id, score.point));
+        }
+        if lines.len() == 1 {
+            lines.push("No picks".to_owned());
+        }
+        Ok(lines.join("\n"))
+    }
+
+    /// Diff of today's decision against the one before, unless it was sent already.
+    fn get_picks_diff(&self) -> Result<Option<picks::PicksDiff>, String> {
+        let portfolios = self.load_portfolios()?;
+        let (portfolio, previous) = match portfolios.split_last() {
+            Some((portfolio, previous)) => (portfolio, previous.last()),
+            None => return Ok(None),
+        };
+
+        if portfolio.date != self.clock.today() || self.notified_date.get() == Some(portfolio.date)
+        {
+            return Ok(None);
+        }
+        self.notified_date.set(Some(portfolio.date));
+        Ok(Some(picks::PicksDiff::build(previous, portfolio)))
+    }
+
+    /// Sends the picks diff of today's decision to the chats of the profile, once.
+    fn notify_picks_diff(&self, telegram: &telegram::Telegram) {
+        let text = match self.get_picks_diff() {
+            Ok(Some(picks_diff)) => picks_diff.to_string(),
+            Ok(None) => return,
+            Err(err) => return println!("Failed to diff the picks: {}", err),
+        };
+
+        for chat_id in &self.config.telegram_chat_ids {
+            if let Err(err) = telegram.send_message(*chat_id, &text) {
+                println!("Failed to notify chat {}: {:?}", chat_id, err);
+                continue;
+            }
+            if let Err(err) = self.journal.record(journal::JournalEvent::Notification {
+                chat_id: *chat_id,
+                text: text.to_owned(),
+                file: None,
+            }) {
+                println!("Failed to journal the notification: {:?}", err);
+            }
+        }
+    }
+
+    fn draw_chart(&self, stock_id: &str) -> Result<String, String> {
+        let start_date = self.clock.today() - chrono::Duration::days(CHART_DAYS);
+        let mut records = self
+            .backend_op
+            .query_all(stock_id)
+            .map_err(|err| format!("{:?}", err))?;
+
+        records.retain(|record| {
+            record.date >= start_date - chrono::Duration::days(CHART_WARM_UP_DAYS)
+        });
+        if records.is_empty() {
+            return Err(format!("No records of {}", stock_id));
+        }
+
+        let path = std::env::temp_dir().join(stock_id.to_owned() + ".html");
+        let path = path.to_str().unwrap().to_owned();
+
+        diagram::draw_view_diagram(
+            stock_id,
+            &view::Views::BollingerBand,
+            &records,
+            Some(start_date),
+            &self.config.diagram_style,
+            &diagram::Output::Html(path.to_owned()),
+        )
+        .map_err(|err| format!("{:?}", err))?;
+
+        if let Some(image_format) = self.config.diagram_style.image_format {
+            let image_path =
+                std::path::Path::new(&path).with_extension(image_format.get_extension());
+
+            if image_path.exists() {
+                return Ok(image_path.to_str().unwrap().to_owned());
+            }
+        }
+        Ok(path)
+    }
+}
+
+enum Reply {
+    Text(String),
+    File(String),
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt("c", "config", "set config path", "");
+    opts.optopt(
+        "",
+        "as-of",
+        "answer as if on this day, to replay the bot over history",
+        "YYYY-MM-DD",
+    );
+    opts.optflag(
+        "",
+        "picks-diff",
+        "send what changed in the picks and settles once the day's decision is written",
+    );
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    let config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+    let telegram = telegram::Telegram::new(&config.telegram_token).unwrap();
+    let crawler = Rc::new(finmind::Finmind::new(&config.finmind_token));
+    let backend_op = Rc::new(backend::SledBackend::new(&config.db_path).unwrap());
+    let clock = clock::get_clock(matches.opt_str("as-of").map(|as_of| as_of.parse().unwrap()));
+    let bots: Vec<Bot> = config
+        .get_profiles()
+        .iter()
+        .map(|profile| {
+            let mut strategy = strategy::StrategyFactory::get(
+                profile.get_strategy(&config).unwrap(),
+                &config.strategy_params,
+                backend_op.clone(),
+            );
+
+            strategy.set_back_adjustment(config.get_back_adjustment());
+
+            let strategy = Rc::new(strategy);
+
+            let mut decision =
+                decision::Decision::new(crawler.clone(), backend_op.clone(), strategy);
+            let mut journal = journal::Journal::new(backend_op.clone(), &profile.name);
+
+            decision.clock = clock.clone();
+            journal.clock = clock.clone();
+
+            Bot {
+                config: config.for_profile(profile),
+                backend_op: backend_op.clone(),
+                watchlist_op: namespace::Namespaced::new(
+                    backend_op.clone(),
+                    profile.get_namespace(),
+                ),
+                journal,
+                clock: clock.clone(),
+                decision,
+                notified_date: Cell::new(None),
+            }
+        })
+        .collect();
+    let mut offset = 0;
+    let mut last_picks_check: Option<std::time::Instant> = None;
+
+    loop {
+        if matches.opt_present("picks-diff")
+            && last_picks_check.is_none_or(|checked_at| checked_at.elapsed() >= PICKS_DIFF_INTERVAL)
+        {
+            for bot in &bots {
+                bot.notify_picks_diff(&telegram);
+            }
+            last_picks_check = Some(std::time::Instant::now());
+        }
+
+        let updates = match telegram.get_updates(offset) {
+            Ok(updates) => updates,
+            Err(err) => {
+                println!("Failed to get updates: {:?}", err);
+                std::thread::sleep(std::time::Duration::from_secs(5));
+                continue;
+            }
+        };
+
+        for update in updates {
+            offset = update.update_id + 1;
+
+            let message = match update.message {
+                Some(message) => message,
+                None => continue,
+            };
+            let chat_id = message.chat.id;
+
+            let bot = match bots
+                .iter()
+                .find(|bot| bot.config.telegram_chat_ids.contains(&chat_id))
+            {
+                Some(bot) => bot,
+                None => {
+                    println!("Ignored message from chat {}", chat_id);
+                    continue;
+                }
+            };
+
+            let (result, event) = match bot.handle(&message.text.unwrap_or_default()) {
+                Ok(Reply::File(path)) => (
+                    telegram.send_file(chat_id, &path),
+                    journal::JournalEvent::Notification {
+                        chat_id,
+                        text: String::new(),
+                        file: Some(path),
+                    },
+                ),
+                Ok(Reply::Text(text)) | Err(text) => (
+                    telegram.send_message(chat_id, &text),
+                    journal::JournalEvent::Notification {
+                        chat_id,
+                        text,
+                        file: None,
+                    },
+                ),
+            };
+
+            match result {
+                Ok(_) => {
+                    if let Err(err) = bot.journal.record(event) {
+                        println!("Failed to journal the reply: {:?}", err);
+                    }
+                }
+                Err(err) => println!("Failed to reply: {:?}", err),
+            }
+        }
+    }
+}
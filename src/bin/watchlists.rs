@@ -0,0 +1,186 @@
+extern crate getopts;
+
+use std::rc::Rc;
+
+use veronica::config::{config, profile};
+use veronica::server::http;
+use veronica::storage::watchlist::{self, WatchlistOp};
+use veronica::storage::{backend, namespace};
+
+const DEFAULT_PORT: u16 = 8081;
+const API_PREFIX: &str = "watchlists";
+/// Prefix of the API of a profile other than the default one, `/users/<name>/watchlists`.
+const USER_PREFIX: &str = "users";
+
+fn print_usage(opts: &getopts::Options) {
+    println!(
+        "{}",
+        opts.usage(
+            "Usage: watchlists -c <config> [-u <user>] (list | show <name> | add <name> <stock_id>... \
+             | remove <name> <stock_id>... | note <name> <note> | delete <name> | serve [-p <port>])"
+        )
+    );
+}
+
+/// Serves one request of the REST API:
+/// `GET /watchlists`, `GET|PUT|DELETE /watchlists/<name>`, where `PUT` takes a JSON watchlist
+/// and stores it under `<name>`. The same paths under `/users/<user>` serve the watchlists of
+/// that profile.
+fn handle(
+    request: &http::Request,
+    config: &config::Config,
+    backend_op: &Rc<backend::SledBackend>,
+) -> http::Response {
+    let segments = match request.get_segments() {
+        Some(segments) => segments,
+        None => return http::Response::json("400 Bad Request", &"Bad Request"),
+    };
+    let segments: Vec<&str> = segments.iter().map(|segment| segment.as_str()).collect();
+    let (user, segments) = match segments.as_slice() {
+        [USER_PREFIX, user, segments @ ..] => (*user, segments),
+        segments => (profile::DEFAULT_PROFILE, segments),
+    };
+    let profile = match config.get_profile(user) {
+        Some(profile) => profile,
+        None => return http::Response::json("404 Not Found", &"Unknown user"),
+    };
+    let watchlist_op = namespace::Namespaced::new(backend_op.clone(), profile.get_namespace());
+    let name = match segments {
+        [API_PREFIX] => None,
+        [API_PREFIX, name] => Some(*name),
+        _ => return http::Response::json("404 Not Found", &"Not Found"),
+    };
+
+    let result = match (request.method.as_str(), name) {
+        ("GET", None) => watchlist_op
+            .list_watchlists()
+            .map(|watchlists| http::Response::json("200 OK", &watchlists)),
+        ("GET", Some(name)) => watchlist_op
+            .get_watchlist(name)
+            .map(|watchlist| match watchlist {
+                Some(watchlist) => http::Response::json("200 OK", &watchlist),
+                None => http::Response::json("404 Not Found", &"Not Found"),
+            }),
+        ("PUT", Some(name)) => {
+            let mut watchlist: watchlist::Watchlist = match serde_json::from_slice(&request.body) {
+                Ok(watchlist) => watchlist,
+                Err(err) => return http::Response::json("400 Bad Request", &err.to_string()),
+            };
+
+            watchlist.name = name.to_owned();
+            watchlist_op
+                .insert_watchlist(&watchlist)
+                .map(|_| http::Response::json("200 OK", &watchlist))
+        }
+        ("DELETE", Some(name)) => {
+            watchlist_op
+                .delete_watchlist(name)
+                .map(|deleted| match deleted {
+                    true => http::Response::json("200 OK", &()),
+                    false => http::Response::json("404 Not Found", &"Not Found"),
+                })
+        }
+        _ => Ok(http::Response::json(
+            "405 Method Not Allowed",
+            &"Method Not Allowed",
+        )),
+    };
+
+    match result {
+        Ok(response) => response,
+        Err(err) => http::Response::json("500 Internal Server Error", &format!("{:?}", err)),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt("c", "config", "set config path", "");
+    opts.optopt("u", "user", "act as this profile of the config", "NAME");
+    opts.optopt("p", "port", "set listening port of serve", "");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    let config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+    let name = matches
+        .opt_str("user")
+        .unwrap_or(profile::DEFAULT_PROFILE.to_owned());
+    let profile = config
+        .get_profile(&name)
+        .unwrap_or_else(|| panic!("Unknown user {}", name));
+    let backend_op = Rc::new(backend::SledBackend::new(&config.db_path).unwrap());
+    let watchlist_op = namespace::Namespaced::new(backend_op.clone(), profile.get_namespace());
+
+    match matches.free.first().map(|command| command.as_str()) {
+        Some("list") => {
+            for watchlist in watchlist_op.list_watchlists().unwrap() {
+                println!(
+                    "{}  ({} stocks)  {}",
+                    watchlist.name,
+                    watchlist.stock_ids.len(),
+                    watchlist.note
+                );
+            }
+        }
+        Some("show") => {
+            let name = match matches.free.get(1) {
+                Some(name) => name,
+                None => return print_usage(&opts),
+            };
+
+            match watchlist_op.get_watchlist(name).unwrap() {
+                Some(watchlist) => print!("{}", serde_yaml::to_string(&watchlist).unwrap()),
+                None => println!("Watchlist {} not found", name),
+            }
+        }
+        Some(command @ ("add" | "remove" | "note")) => {
+            let name = match matches.free.get(1) {
+                Some(name) => name,
+                None => return print_usage(&opts),
+            };
+            let mut watchlist = match watchlist_op.get_watchlist(name).unwrap() {
+                Some(watchlist) => watchlist,
+                None if command == "add" => watchlist::Watchlist::new(name),
+                None => return println!("Watchlist {} not found", name),
+            };
+            let values = &matches.free[2..];
+
+            match command {
+                "add" => watchlist.add(values),
+                "remove" => watchlist.remove(values),
+                _ => watchlist.note = values.join(" "),
+            }
+            watchlist_op.insert_watchlist(&watchlist).unwrap();
+        }
+        Some("delete") => {
+            let name = match matches.free.get(1) {
+                Some(name) => name,
+                None => return print_usage(&opts),
+            };
+
+            if !watchlist_op.delete_watchlist(name).unwrap() {
+                println!("Watchlist {} not found", name);
+            }
+        }
+        Some("serve") => {
+            let port = match matches.opt_str("p") {
+                Some(port) => port.parse::<u16>().unwrap(),
+                None => DEFAULT_PORT,
+            };
+
+            println!(
+                "Serving watchlists on http://127.0.0.1:{}/{}",
+                port, API_PREFIX
+            );
+            http::serve(port, |request| handle(request, &config, &backend_op)).unwrap();
+        }
+        _ => print_usage(&opts),
+    }
+}
@@ -0,0 +1,87 @@
+extern crate getopts;
+
+use veronica::config::config;
+use veronica::core::{backtesting, decision};
+use veronica::notifier::telegram;
+use veronica::report::weekly;
+
+fn load_yaml<T: serde::de::DeserializeOwned>(file_path: &str) -> T {
+    let data = std::fs::read_to_string(file_path).expect("Failed to read yaml");
+
+    serde_yaml::from_str(&data).expect("Failed to deserialize yaml")
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut opts = getopts::Options::new();
+
+    opts.reqopt("c", "config", "set config path", "");
+    opts.optflag(
+        "",
+        "send",
+        "send the report to the configured Telegram chats",
+    );
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            println!("{}", f);
+            return;
+        }
+    };
+
+    let config = config::load_config(&matches.opt_str("c").unwrap()).unwrap();
+    let get_full_path = |filename: &str| config.portfolio_path.to_owned() + "/" + filename;
+    let portfolios: Vec<decision::Portfolio> =
+        load_yaml(&get_full_path(backtesting::PORTFOLIO_FILENAME));
+    let trade_ledger: Vec<backtesting::TradeRecord> =
+        load_yaml(&get_full_path(backtesting::TRADE_LEDGER_FILENAME));
+    let report = match weekly::WeeklyReport::build(&portfolios, &trade_ledger) {
+        Some(report) => report,
+        None => {
+            println!("No portfolio to report");
+            return;
+        }
+    };
+    let report_path = get_full_path(weekly::WEEKLY_REPORT_FILENAME);
+
+    std::fs::write(&report_path, report.to_html()).expect("Failed to write report");
+    println!(
+        "Weekly report {} ~ {}: {:+.2}%, written to {}",
+        report.start_date,
+        report.end_date,
+        report.get_return(),
+        report_path
+    );
+
+    if matches.opt_present("send") {
+        let telegram = telegram::Telegram::new(&config.telegram_token).unwrap();
+        let mut summary = format!(
+            "Weekly report {} ~ {}\nEquity: {} -> {} ({:+.2}%)\nTrades settled: {}\nStocks entered: {}",
+            report.start_date,
+            report.end_date,
+            report.start_equity,
+            report.end_equity,
+            report.get_return(),
+            report.trades_settled.len(),
+            report.stocks_entered.len()
+        );
+
+        for position in report.get_halted_positions() {
+            summary.push_str(&format!(
+                "\nHalted: {} ({} days)",
+                position.stock_id,
+                position.halted_days.unwrap_or(0)
+            ));
+        }
+
+        for chat_id in &config.telegram_chat_ids {
+            if let Err(err) = telegram
+                .send_message(*chat_id, &summary)
+                .and_then(|_| telegram.send_file(*chat_id, &report_path))
+            {
+                println!("Failed to send report to {}: {:?}", chat_id, err);
+            }
+        }
+    }
+}
@@ -2,11 +2,81 @@ use std::option::Option;
 
 use serde::{Deserialize, Serialize};
 
+use crate::core::{hedge, regime, risk, scenario};
+use crate::crawler::mapping;
+use crate::crosssection::breadth;
+use crate::dataview::adjust;
+use crate::diagram::diagram;
+use crate::storage::mirror;
+use crate::strategy::{composite, onnx, rule, strategy};
+
+use super::{profile, secrets};
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Config {
     pub db_path: String,
     pub portfolio_path: String,
     pub finmind_token: String,
+    #[serde(default)]
+    pub diagram_style: diagram::DiagramStyle,
+    #[serde(default)]
+    pub telegram_token: String,
+    /// Chats the Telegram bot answers; messages from any other chat are ignored.
+    #[serde(default)]
+    pub telegram_chat_ids: Vec<i64>,
+    /// Price sources read through a field mapping instead of a dedicated crawler.
+    #[serde(default)]
+    pub sources: Vec<mapping::SourceMapping>,
+    /// Rule-based strategies, run by name with `backtesting --rules`.
+    #[serde(default)]
+    pub rules: Vec<rule::RuleSet>,
+    /// ONNX models, run by name with `backtesting --model`.
+    #[serde(default)]
+    pub models: Vec<onnx::ModelConfig>,
+    /// Weighted ensembles of the strategies above and the built-in ones, run by name with
+    /// `backtesting --strategy`.
+    #[serde(default)]
+    pub composites: Vec<composite::CompositeConfig>,
+    /// Stress scenarios besides the predefined ones, run with `backtesting --scenarios`.
+    #[serde(default)]
+    pub scenarios: Vec<scenario::Scenario>,
+    /// Object store the backend is mirrored to with `mirror push`.
+    #[serde(default)]
+    pub mirror: Option<mirror::MirrorConfig>,
+    /// Users sharing the price records of `db_path`, each with their own portfolio and
+    /// notification targets, selected with `--user`.
+    #[serde(default)]
+    pub profiles: Vec<profile::Profile>,
+    /// Stocks, such as ETFs paying distributions, whose indicators are computed on prices
+    /// back-adjusted for the distributions instead of the stored quotes.
+    #[serde(default)]
+    pub back_adjusted_stock_ids: Vec<String>,
+    /// Window sizes and band widths of the built-in strategies.
+    #[serde(default)]
+    pub strategy_params: strategy::StrategyParams,
+    /// Liquidates the holdings and pauses entries once the equity falls too far from its peak.
+    #[serde(default)]
+    pub circuit_breaker: Option<risk::CircuitBreaker>,
+    /// Loss from the previous trading day's equity, in percent, above which no new positions
+    /// are opened.
+    #[serde(default)]
+    pub max_daily_loss: Option<f64>,
+    /// Regime the market has to be in for entries, for the strategies that set none of their
+    /// own.
+    #[serde(default)]
+    pub regime_filter: Option<regime::RegimeFilter>,
+    /// Share of the universe above its moving average below which no new positions are opened.
+    #[serde(default)]
+    pub breadth_filter: Option<breadth::BreadthFilter>,
+    /// Instrument held against the stock exposure, accounted apart from the holdings.
+    #[serde(default)]
+    pub hedge: Option<hedge::Hedge>,
+    /// Stock id of the benchmark the trade ledger of backtests measures beta against.
+    #[serde(default)]
+    pub benchmark_id: Option<String>,
+    /// Exports the average factor exposures of the holdings over backtests.
+    #[serde(default)]
+    pub factor_report: bool,
 }
 
 impl std::default::Default for Config {
@@ -15,16 +85,175 @@ impl std::default::Default for Config {
             db_path: "".to_owned(),
             portfolio_path: "".to_owned(),
             finmind_token: "".to_owned(),
+            diagram_style: diagram::DiagramStyle::default(),
+            telegram_token: "".to_owned(),
+            telegram_chat_ids: Vec::new(),
+            sources: Vec::new(),
+            rules: Vec::new(),
+            models: Vec::new(),
+            composites: Vec::new(),
+            scenarios: Vec::new(),
+            mirror: None,
+            profiles: Vec::new(),
+            back_adjusted_stock_ids: Vec::new(),
+            strategy_params: strategy::StrategyParams::default(),
+            circuit_breaker: None,
+            max_daily_loss: None,
+            regime_filter: None,
+            breadth_filter: None,
+            hedge: None,
+            benchmark_id: None,
+            factor_report: false,
+        }
+    }
+}
+
+impl Config {
+    pub fn get_source(&self, name: &str) -> Option<&mapping::SourceMapping> {
+        self.sources.iter().find(|source| source.name == name)
+    }
+
+    pub fn get_rule_set(&self, name: &str) -> Option<&rule::RuleSet> {
+        self.rules.iter().find(|rule_set| rule_set.name == name)
+    }
+
+    pub fn get_model(&self, name: &str) -> Option<&onnx::ModelConfig> {
+        self.models.iter().find(|model| model.name == name)
+    }
+
+    pub fn get_composite(&self, name: &str) -> Option<&composite::CompositeConfig> {
+        self.composites
+            .iter()
+            .find(|composite| composite.name == name)
+    }
+
+    /// Strategy named `name`, looked up among the composites, the rule sets, the models and then
+    /// the built-in strategies.
+    pub fn get_strategy(&self, name: &str) -> Option<strategy::Strategies> {
+        match self.get_composite(name) {
+            Some(composite) => composite
+                .members
+                .iter()
+                .map(|member| {
+                    self.get_member_strategy(&member.strategy)
+                        .map(|strategy| (strategy, member.weight))
+                })
+                .collect::<Option<Vec<_>>>()
+                .map(strategy::Strategies::Composite),
+            None => self.get_member_strategy(name),
+        }
+    }
+
+    /// Strategy named `name` among all but the composites, which do not nest.
+    fn get_member_strategy(&self, name: &str) -> Option<strategy::Strategies> {
+        if let Some(rule_set) = self.get_rule_set(name) {
+            return Some(strategy::Strategies::Rule(rule_set.clone()));
+        }
+        if let Some(model) = self.get_model(name) {
+            return Some(strategy::Strategies::Onnx(model.clone()));
+        }
+        strategy::Strategies::get_builtin(name).map(|builtin| self.configure(builtin))
+    }
+
+    /// Every built-in strategy, with the parameters of the config.
+    pub fn get_builtin_strategies(&self) -> Vec<strategy::Strategies> {
+        strategy::Strategies::all()
+            .into_iter()
+            .map(|builtin| self.configure(builtin))
+            .collect()
+    }
+
+    fn configure(&self, builtin: strategy::Strategies) -> strategy::Strategies {
+        match builtin {
+            strategy::Strategies::MaCrossover(_) => {
+                strategy::Strategies::MaCrossover(self.strategy_params.ma_crossover.clone())
+            }
+            strategy::Strategies::Dca(_) => {
+                strategy::Strategies::Dca(self.strategy_params.dca.clone())
+            }
+            builtin => builtin,
         }
     }
+
+    /// Configured profiles; the default profile alone when there are none.
+    pub fn get_profiles(&self) -> Vec<profile::Profile> {
+        match self.profiles.is_empty() {
+            true => vec![self.get_default_profile()],
+            false => self.profiles.clone(),
+        }
+    }
+
+    /// Profile named `name`. The default profile, made of the top-level settings, is there
+    /// even when not configured.
+    pub fn get_profile(&self, name: &str) -> Option<profile::Profile> {
+        match self.profiles.iter().find(|profile| profile.name == name) {
+            Some(profile) => Some(profile.clone()),
+            None if name == profile::DEFAULT_PROFILE => Some(self.get_default_profile()),
+            None => None,
+        }
+    }
+
+    fn get_default_profile(&self) -> profile::Profile {
+        let mut default_profile = profile::Profile::new(profile::DEFAULT_PROFILE);
+
+        default_profile.telegram_chat_ids = self.telegram_chat_ids.clone();
+        default_profile
+    }
+
+    /// Config seen by `profile`, with its own portfolio path and chats.
+    pub fn for_profile(&self, profile: &profile::Profile) -> Config {
+        Config {
+            portfolio_path: profile.get_portfolio_path(&self.portfolio_path),
+            telegram_chat_ids: profile.telegram_chat_ids.clone(),
+            ..self.clone()
+        }
+    }
+
+    /// Regime filter of `strategy`: its own, or else the one of the config.
+    pub fn get_regime_filter(
+        &self,
+        strategy: &strategy::Strategies,
+    ) -> Option<regime::RegimeFilter> {
+        strategy
+            .get_regime_filter()
+            .or_else(|| self.regime_filter.clone())
+    }
+
+    pub fn get_back_adjustment(&self) -> adjust::BackAdjustment {
+        adjust::BackAdjustment::new(&self.back_adjusted_stock_ids)
+    }
+
+    /// Predefined stress scenarios along with those of the config.
+    pub fn get_scenarios(&self) -> Vec<scenario::Scenario> {
+        scenario::get_scenarios(&self.scenarios)
+    }
 }
 
+/// Loads the config at `config_path`, reading the values that refer to a secret from the
+/// secrets file next to it or the OS keychain. Refuses a config with a composite that has no
+/// members, or a weight that is not a finite positive number.
 pub fn load_config(config_path: &str) -> Option<Config> {
-    let data = std::fs::read_to_string(config_path).ok();
+    let data = std::fs::read_to_string(config_path).ok()?;
+    let mut value: serde_yaml::Value = serde_yaml::from_str(&data).ok()?;
+
+    if let Err(err) = secrets::resolve(&mut value, &secrets::get_secrets_path(config_path)) {
+        eprintln!("Cannot read the secrets of {}: {}", config_path, err);
+        return None;
+    }
+
+    let config: Config = serde_yaml::from_value(value).ok()?;
 
-    if data.is_none() {
+    if let Some(composite) = config
+        .composites
+        .iter()
+        .find(|composite| !composite.is_valid())
+    {
+        eprintln!(
+            "Composite {} of {} needs members, each of a finite positive weight",
+            composite.name, config_path
+        );
         return None;
     }
-    serde_yaml::from_str(&data.unwrap()).ok()
+    Some(config)
 }
 
@@ -1 +1,3 @@
 pub mod config;
+pub mod profile;
+pub mod secrets;
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+
+use crate::strategy::strategy;
+
+use super::config;
+
+/// Profile standing for the top-level settings of a config without profiles. Its state is
+/// kept where it always was: directly in the portfolio path and without a backend namespace.
+pub const DEFAULT_PROFILE: &str = "default";
+
+#[derive(Debug)]
+pub enum Error {
+    /// The profile names a strategy that is neither a rule set nor a model of the config.
+    UnknownStrategy { profile: String, strategy: String },
+}
+
+fn default_liquidity() -> u32 {
+    200000
+}
+
+fn default_stocks_hold_num() -> usize {
+    5
+}
+
+/// A user of a shared deployment, with their own capital, strategy and notification targets.
+/// Profiles share the price records of the backend; their portfolios, watchlists and runs are
+/// kept apart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    #[serde(default = "default_liquidity")]
+    pub liquidity: u32,
+    #[serde(default = "default_stocks_hold_num")]
+    pub stocks_hold_num: usize,
+    /// Name of a rule set or a model of the config; the Bollinger band strategy when unset.
+    #[serde(default)]
+    pub strategy: Option<String>,
+    /// Chats the Telegram bot answers for this profile.
+    #[serde(default)]
+    pub telegram_chat_ids: Vec<i64>,
+}
+
+impl Profile {
+    pub fn new(name: &str) -> Self {
+        Profile {
+            name: name.to_owned(),
+            liquidity: default_liquidity(),
+            stocks_hold_num: default_stocks_hold_num(),
+            strategy: None,
+            telegram_chat_ids: Vec::new(),
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.name == DEFAULT_PROFILE
+    }
+
+    /// Prefix of the backend keys of the profile's own state; none for the default profile.
+    pub fn get_namespace(&self) -> Option<&str> {
+        match self.is_default() {
+            true => None,
+            false => Some(&self.name),
+        }
+    }
+
+    /// Strategy the profile trades with, resolved among the rule sets and models of `config`.
+    pub fn get_strategy(&self, config: &config::Config) -> Result<strategy::Strategies, Error> {
+        match &self.strategy {
+            Some(name) => config
+                .get_strategy(name)
+                .ok_or_else(|| Error::UnknownStrategy {
+                    profile: self.name.to_owned(),
+                    strategy: name.to_owned(),
+                }),
+            None => Ok(strategy::Strategies::BollingerBand),
+        }
+    }
+
+    /// Directory of the profile's portfolio files, a subdirectory of `portfolio_path` named
+    /// after the profile.
+    pub fn get_portfolio_path(&self, portfolio_path: &str) -> String {
+        match self.is_default() {
+            true => portfolio_path.to_owned(),
+            false => format!("{}/{}", portfolio_path, self.name),
+        }
+    }
+}
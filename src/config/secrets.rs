@@ -0,0 +1,375 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+
+/// The encrypted secrets file is kept next to the config.
+pub const SECRETS_FILENAME: &str = "secrets.enc";
+/// Passphrase the secrets file is encrypted with, so a daemon can read it unattended.
+pub const PASSPHRASE_ENV: &str = "VERONICA_SECRETS_PASSPHRASE";
+/// Config values starting with these are read from the secrets file or the OS keychain, e.g.
+/// `finmind_token: secret:finmind_token`.
+pub const SECRET_PREFIX: &str = "secret:";
+pub const KEYCHAIN_PREFIX: &str = "keychain:";
+const KEYCHAIN_SERVICE: &str = "veronica";
+#[cfg(feature = "secrets")]
+const SALT_SIZE: usize = 16;
+#[cfg(feature = "secrets")]
+const NONCE_SIZE: usize = 12;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// `PASSPHRASE_ENV` is not set.
+    NoPassphrase,
+    /// The config refers to a secret the secrets file does not hold.
+    NoSecret {
+        name: String,
+        path: PathBuf,
+    },
+    /// The secrets file is not hex encoded or its nonce has the wrong size.
+    Corrupted,
+    /// The secrets file does not decrypt with the passphrase.
+    WrongPassphrase,
+    /// Key derivation, randomness or encryption failed.
+    Crypto(String),
+    /// The keychain command failed, with what it printed.
+    Keychain(String),
+    /// Built without the `secrets` feature.
+    Unsupported,
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::Json(err)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Io(err) => write!(fmt, "{}", err),
+            Error::Json(err) => write!(fmt, "{}", err),
+            Error::NoPassphrase => write!(fmt, "{} is not set", PASSPHRASE_ENV),
+            Error::NoSecret { name, path } => {
+                write!(fmt, "no secret {} in {}", name, path.display())
+            }
+            Error::Corrupted => write!(fmt, "the secrets file is corrupted"),
+            Error::WrongPassphrase => write!(
+                fmt,
+                "cannot decrypt the secrets file, is the passphrase right?"
+            ),
+            Error::Crypto(err) => write!(fmt, "{}", err),
+            Error::Keychain(err) => write!(fmt, "keychain error: {}", err),
+            Error::Unsupported => write!(fmt, "built without the secrets feature"),
+        }
+    }
+}
+
+/// Secret values by name.
+pub type Secrets = BTreeMap<String, String>;
+
+/// Salt, nonce and ciphertext of encrypted data.
+type Sealed = (Vec<u8>, Vec<u8>, Vec<u8>);
+
+/// Secrets encrypted with ChaCha20-Poly1305 under a key derived from the passphrase with
+/// Argon2, all hex encoded.
+#[derive(Serialize, Deserialize)]
+struct SecretsFile {
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+pub fn get_secrets_path(config_path: &str) -> PathBuf {
+    Path::new(config_path).with_file_name(SECRETS_FILENAME)
+}
+
+pub fn get_passphrase() -> Result<String, Error> {
+    std::env::var(PASSPHRASE_ENV).map_err(|_| Error::NoPassphrase)
+}
+
+/// Secrets stored at `path`; none when there is no such file yet.
+pub fn load_secrets(path: &Path, passphrase: &str) -> Result<Secrets, Error> {
+    let data = match std::fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Secrets::new()),
+        Err(err) => return Err(Error::Io(err)),
+    };
+    let secrets_file: SecretsFile = serde_json::from_str(&data)?;
+    let plaintext = decrypt(
+        passphrase,
+        &from_hex(&secrets_file.salt)?,
+        &from_hex(&secrets_file.nonce)?,
+        &from_hex(&secrets_file.ciphertext)?,
+    )?;
+
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+/// Encrypts `secrets` into `path`, with a fresh salt and nonce.
+pub fn save_secrets(path: &Path, passphrase: &str, secrets: &Secrets) -> Result<(), Error> {
+    let plaintext = serde_json::to_vec(secrets)?;
+    let (salt, nonce, ciphertext) = encrypt(passphrase, &plaintext)?;
+    let data = serde_json::to_string(&SecretsFile {
+        salt: to_hex(&salt),
+        nonce: to_hex(&nonce),
+        ciphertext: to_hex(&ciphertext),
+    })?;
+
+    Ok(std::fs::write(path, data)?)
+}
+
+/// Replaces the config values referring to a secret with the secret, anywhere in `value`. The
+/// secrets file is only decrypted when some value refers to it.
+pub fn resolve(value: &mut serde_yaml::Value, secrets_path: &Path) -> Result<(), Error> {
+    let mut secrets = None;
+
+    resolve_value(value, &mut |reference| {
+        if let Some(name) = reference.strip_prefix(KEYCHAIN_PREFIX) {
+            return get_keychain_secret(name).map(Some);
+        }
+
+        let name = match reference.strip_prefix(SECRET_PREFIX) {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+
+        if secrets.is_none() {
+            secrets = Some(load_secrets(secrets_path, &get_passphrase()?)?);
+        }
+        match secrets.as_ref().and_then(|secrets| secrets.get(name)) {
+            Some(secret) => Ok(Some(secret.to_owned())),
+            None => Err(Error::NoSecret {
+                name: name.to_owned(),
+                path: secrets_path.to_owned(),
+            }),
+        }
+    })
+}
+
+fn resolve_value(
+    value: &mut serde_yaml::Value,
+    get_secret: &mut dyn FnMut(&str) -> Result<Option<String>, Error>,
+) -> Result<(), Error> {
+    match value {
+        serde_yaml::Value::String(text) => {
+            if let Some(secret) = get_secret(text)? {
+                *text = secret;
+            }
+        }
+        serde_yaml::Value::Sequence(values) => {
+            for value in values {
+                resolve_value(value, get_secret)?;
+            }
+        }
+        serde_yaml::Value::Mapping(mapping) => {
+            for (_, value) in mapping.iter_mut() {
+                resolve_value(value, get_secret)?;
+            }
+        }
+        serde_yaml::Value::Tagged(tagged) => resolve_value(&mut tagged.value, get_secret)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn run_command(command: &mut Command, input: Option<&str>) -> Result<String, Error> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input.as_bytes())?;
+    }
+
+    let output = child.wait_with_output()?;
+
+    match output.status.success() {
+        true => Ok(String::from_utf8_lossy(&output.stdout)
+            .trim_end_matches('\n')
+            .to_owned()),
+        false => Err(Error::Keychain(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        )),
+    }
+}
+
+/// Secret `name` of the OS keychain: the login keychain on macOS, the Secret Service through
+/// `secret-tool` elsewhere.
+#[cfg(target_os = "macos")]
+pub fn get_keychain_secret(name: &str) -> Result<String, Error> {
+    run_command(
+        Command::new("security").args([
+            "find-generic-password",
+            "-s",
+            KEYCHAIN_SERVICE,
+            "-a",
+            name,
+            "-w",
+        ]),
+        None,
+    )
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn get_keychain_secret(name: &str) -> Result<String, Error> {
+    run_command(
+        Command::new("secret-tool").args(["lookup", "service", KEYCHAIN_SERVICE, "name", name]),
+        None,
+    )
+}
+
+/// Stores `secret` as `name` in the OS keychain. The secret is written to the standard input of
+/// the keychain command, never onto its command line where other processes could read it.
+#[cfg(target_os = "macos")]
+pub fn set_keychain_secret(name: &str, secret: &str) -> Result<(), Error> {
+    // A trailing `-w` without a value makes `security` prompt for the password and its retyping.
+    run_command(
+        Command::new("security").args([
+            "add-generic-password",
+            "-U",
+            "-s",
+            KEYCHAIN_SERVICE,
+            "-a",
+            name,
+            "-w",
+        ]),
+        Some(&format!("{}\n{}\n", secret, secret)),
+    )
+    .map(|_| ())
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn set_keychain_secret(name: &str, secret: &str) -> Result<(), Error> {
+    run_command(
+        Command::new("secret-tool").args([
+            "store",
+            &format!("--label={} {}", KEYCHAIN_SERVICE, name),
+            "service",
+            KEYCHAIN_SERVICE,
+            "name",
+            name,
+        ]),
+        Some(secret),
+    )
+    .map(|_| ())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(text: &str) -> Result<Vec<u8>, Error> {
+    (0..text.len())
+        .step_by(2)
+        .map(|index| {
+            text.get(index..index + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or(Error::Corrupted)
+        })
+        .collect()
+}
+
+#[cfg(feature = "secrets")]
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], Error> {
+    let mut key = [0; 32];
+
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| Error::Crypto(err.to_string()))?;
+    Ok(key)
+}
+
+#[cfg(feature = "secrets")]
+fn encrypt(passphrase: &str, plaintext: &[u8]) -> Result<Sealed, Error> {
+    use chacha20poly1305::aead::{Aead, KeyInit};
+
+    let mut salt = vec![0; SALT_SIZE];
+    let mut nonce = vec![0; NONCE_SIZE];
+
+    getrandom::getrandom(&mut salt).map_err(|err| Error::Crypto(err.to_string()))?;
+    getrandom::getrandom(&mut nonce).map_err(|err| Error::Crypto(err.to_string()))?;
+
+    let key = derive_key(passphrase, &salt)?;
+    let ciphertext = chacha20poly1305::ChaCha20Poly1305::new(&key.into())
+        .encrypt(chacha20poly1305::Nonce::from_slice(&nonce), plaintext)
+        .map_err(|err| Error::Crypto(err.to_string()))?;
+
+    Ok((salt, nonce, ciphertext))
+}
+
+#[cfg(feature = "secrets")]
+fn decrypt(
+    passphrase: &str,
+    salt: &[u8],
+    nonce: &[u8],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, Error> {
+    use chacha20poly1305::aead::{Aead, KeyInit};
+
+    if nonce.len() != NONCE_SIZE {
+        return Err(Error::Corrupted);
+    }
+
+    let key = derive_key(passphrase, salt)?;
+
+    chacha20poly1305::ChaCha20Poly1305::new(&key.into())
+        .decrypt(chacha20poly1305::Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| Error::WrongPassphrase)
+}
+
+#[cfg(not(feature = "secrets"))]
+fn encrypt(_passphrase: &str, _plaintext: &[u8]) -> Result<Sealed, Error> {
+    Err(Error::Unsupported)
+}
+
+#[cfg(not(feature = "secrets"))]
+fn decrypt(
+    _passphrase: &str,
+    _salt: &[u8],
+    _nonce: &[u8],
+    _ciphertext: &[u8],
+) -> Result<Vec<u8>, Error> {
+    Err(Error::Unsupported)
+}
+
+#[cfg(all(test, feature = "secrets"))]
+mod secrets_test {
+    use super::{load_secrets, resolve, save_secrets, Error, Secrets};
+
+    #[test]
+    fn secrets_check() {
+        let path = std::env::temp_dir().join("veronica_secrets_check.enc");
+        let mut secrets = Secrets::new();
+
+        secrets.insert("finmind_token".to_owned(), "token".to_owned());
+        save_secrets(&path, "passphrase", &secrets).unwrap();
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("token"));
+        assert_eq!(load_secrets(&path, "passphrase").unwrap(), secrets);
+        assert!(matches!(
+            load_secrets(&path, "wrong"),
+            Err(Error::WrongPassphrase)
+        ));
+
+        let mut value: serde_yaml::Value =
+            serde_yaml::from_str("db_path: db\nfinmind_token: secret:finmind_token").unwrap();
+
+        std::env::set_var(super::PASSPHRASE_ENV, "passphrase");
+        resolve(&mut value, &path).unwrap();
+        assert_eq!(value["finmind_token"].as_str(), Some("token"));
+        assert_eq!(value["db_path"].as_str(), Some("db"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,18 +1,103 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 use std::rc::Rc;
 
+use chrono::Datelike;
 use serde::{Deserialize, Serialize};
 
+use crate::analytics::{contribution, correlation, distribution, economics, exposure, factor};
 use crate::config::config;
 use crate::crawler::crawler;
+use crate::crosssection::breadth;
+use crate::dataview::{adjust, view};
+use crate::diagram::diagram;
 use crate::export::export;
-use crate::storage::backend;
+use crate::optimizer::pruner;
+use crate::storage::{backend, journal, run};
 use crate::strategy::{schema, strategy};
 
-use super::decision;
+use super::{
+    calendar, cashflow, clock, decision, fill, halt, hedge, latency, lot, order, pairs, position,
+    prefetch, profiler, reconcile, regime, risk, scaling, scenario, shock,
+};
 
+/// Stock whose trading days measure the warm-up history when no benchmark is set.
+pub const WARM_UP_REFERENCE_ID: &str = "0050";
 pub const PORTFOLIO_FILENAME: &str = "portfolio.yaml";
 pub const FUND_DIAGRAM_FILENAME: &str = "fund_diagram.html";
+pub const TRADE_LEDGER_FILENAME: &str = "trade_ledger.yaml";
+pub const FACTOR_REPORT_FILENAME: &str = "factor_report.yaml";
+pub const PORTFOLIO_SUMMARY_FILENAME: &str = "portfolio_summary.yaml";
+/// Equity points of a run streaming its portfolios, kept next to the portfolio summary.
+pub const EQUITY_SERIES_FILENAME: &str = "equity_series.yaml";
+pub const ORDER_PLAN_FILENAME: &str = "order_plan.txt";
+pub const ORDER_PLAN_JSON_FILENAME: &str = "order_plan.json";
+pub const TIMING_FILENAME: &str = "timing.yaml";
+pub const CHUNKED_REPORT_FILENAME: &str = "chunked_report.yaml";
+pub const REALIZED_LOTS_FILENAME: &str = "realized_lots.yaml";
+pub const CHUNKED_FUND_DIAGRAM_FILENAME: &str = "chunked_fund_diagram.html";
+
+/// Everything a run's result depends on, digested into its cache key.
+#[derive(Serialize)]
+struct CacheKeyInput<'a> {
+    strategy: &'a strategy::Strategies,
+    /// Digest of the script or model file the strategy reads, which its path does not cover.
+    strategy_file: Option<String>,
+    stock_ids: Vec<String>,
+    start_date: chrono::NaiveDate,
+    end_date: chrono::NaiveDate,
+    data_generation: Option<u64>,
+    liquidity: u32,
+    stocks_hold_num: usize,
+    circuit_breaker: &'a Option<risk::CircuitBreaker>,
+    max_daily_loss: Option<f64>,
+    regime_filter: &'a Option<regime::RegimeFilter>,
+    breadth_filter: &'a Option<breadth::BreadthFilter>,
+    hedge: &'a Option<hedge::Hedge>,
+    missing_data_policy: decision::MissingDataPolicy,
+    halt_policy: &'a Option<halt::HaltPolicy>,
+    price_limit: &'a Option<fill::PriceLimit>,
+    order_size: &'a Option<fill::OrderSize>,
+    cash_flows: &'a Option<cashflow::CashFlowSchedule>,
+    benchmark_id: &'a Option<String>,
+    // Left out when unset, so the keys of runs recorded before these settings still match.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    slippage: &'a Option<fill::Slippage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shock: Option<(&'a Vec<shock::ShockModel>, u64)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signal_latency: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tick_size: &'a Option<fill::TickSize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    volume_cap: &'a Option<fill::VolumeCap>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scale_out: &'a Option<fill::ScaleOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scale_in: &'a Option<fill::ScaleIn>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    valuation_policy: Option<decision::ValuationPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    candidates: &'a Option<Vec<String>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    position_notes: &'a position::PositionNotes,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    executed_trades: &'a Option<Vec<reconcile::ExecutedTrade>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    calendar: &'a Option<calendar::TradingCalendar>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_hold_days: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_state: &'a Option<decision::EndState>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    back_adjusted_stock_ids: &'a Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    strategy_params: Option<&'a strategy::StrategyParams>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fill_policy: Option<decision::FillPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pairs_trading: &'a Option<pairs::PairsTrading>,
+}
 
 #[derive(Serialize, Deserialize)]
 pub struct StockTradeInfo {
@@ -20,6 +105,166 @@ pub struct StockTradeInfo {
     pub trade_series: Vec<(chrono::NaiveDate, chrono::NaiveDate)>,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TradeRecord {
+    pub stock_id: String,
+    pub hold_date: chrono::NaiveDate,
+    pub settle_date: chrono::NaiveDate,
+    pub num: u32,
+    pub hold_price: u32,
+    pub settle_price: u32,
+    /// Rolling beta against the benchmark as of the hold date.
+    pub beta: Option<f64>,
+    /// Benchmark return over the holding period, in percent.
+    pub benchmark_return: Option<f64>,
+    pub settle_reason: Option<strategy::SettleReason>,
+}
+
+impl TradeRecord {
+    /// Return of the trade, in percent.
+    pub fn get_return(&self) -> f64 {
+        if self.hold_price == 0 {
+            return 0.0;
+        }
+        (self.settle_price as f64 - self.hold_price as f64) / self.hold_price as f64 * 100.0
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EquityPoint {
+    pub date: chrono::NaiveDate,
+    pub equity: u32,
+    pub unhedged_equity: i64,
+    #[serde(default)]
+    pub cash_flow: i64,
+    /// Market value of the stocks held at the end of the day.
+    #[serde(default)]
+    pub invested: u32,
+    #[serde(default)]
+    pub holding_count: usize,
+}
+
+/// Statistics kept for every portfolio of a run, whether or not the portfolios themselves are
+/// retained.
+#[derive(Default, Serialize, Deserialize)]
+pub struct PortfolioSummary {
+    pub count: usize,
+    pub peak_equity: u32,
+    /// Maximum drawdown over the run, in percent.
+    pub max_drawdown: f64,
+    pub risk_event_count: usize,
+    /// Fills deferred because the bar was locked at the price limit.
+    #[serde(default)]
+    pub deferred_fill_count: usize,
+    /// Entries skipped for not fitting the order size constraints.
+    #[serde(default)]
+    pub skipped_order_count: usize,
+    /// Orders the volume cap kept from filling in full on the day.
+    #[serde(default)]
+    pub partial_fill_count: usize,
+    /// Equity of every day summarized by `add`; empty when the points are streamed instead.
+    pub equity_series: Vec<EquityPoint>,
+    /// Last day summarized.
+    #[serde(default)]
+    pub last_date: Option<chrono::NaiveDate>,
+    /// Realized P&L of the lots closed in each year, with the unrealized P&L at its end.
+    #[serde(default)]
+    pub yearly_pnl: Vec<lot::YearlyPnl>,
+}
+
+impl PortfolioSummary {
+    /// Summarizes `portfolio`, keeping its equity point in `equity_series`.
+    pub fn add(&mut self, portfolio: &decision::Portfolio) {
+        let equity_point = self.summarize(portfolio);
+
+        self.equity_series.push(equity_point);
+    }
+
+    /// Summarizes `portfolio` into the statistics only, handing its equity point back for the
+    /// caller to stream.
+    pub fn summarize(&mut self, portfolio: &decision::Portfolio) -> EquityPoint {
+        let equity = portfolio.equity();
+
+        self.count += 1;
+        self.last_date = Some(portfolio.date);
+        self.peak_equity =
+            ((self.peak_equity as i64 + portfolio.cash_flow).max(0) as u32).max(equity);
+        self.max_drawdown = self
+            .max_drawdown
+            .max(risk::drawdown(self.peak_equity, equity));
+        self.risk_event_count += portfolio.risk_events.len();
+        self.deferred_fill_count += portfolio
+            .risk_events
+            .iter()
+            .filter(|risk_event| matches!(risk_event, risk::RiskEvent::FillDeferred { .. }))
+            .count();
+        self.skipped_order_count += portfolio
+            .risk_events
+            .iter()
+            .filter(|risk_event| matches!(risk_event, risk::RiskEvent::OrderSkipped { .. }))
+            .count();
+        self.partial_fill_count += portfolio
+            .risk_events
+            .iter()
+            .filter(|risk_event| matches!(risk_event, risk::RiskEvent::PartialFill { .. }))
+            .count();
+
+        let year = chrono::Datelike::year(&portfolio.date);
+
+        if self.yearly_pnl.last().map(|yearly_pnl| yearly_pnl.year) != Some(year) {
+            self.yearly_pnl.push(lot::YearlyPnl {
+                year,
+                realized: 0,
+                unrealized: 0,
+            });
+        }
+        if let Some(yearly_pnl) = self.yearly_pnl.last_mut() {
+            yearly_pnl.realized += portfolio.realized_pnl();
+            yearly_pnl.unrealized = portfolio.unrealized_pnl;
+        }
+
+        EquityPoint {
+            date: portfolio.date,
+            equity,
+            unhedged_equity: portfolio.unhedged_equity(),
+            cash_flow: portfolio.cash_flow,
+            invested: portfolio.invested(),
+            holding_count: portfolio.stocks_hold.len() + portfolio.stocks_selected.len(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ChunkResult {
+    pub start_date: chrono::NaiveDate,
+    pub end_date: chrono::NaiveDate,
+    pub start_equity: u32,
+    pub end_equity: u32,
+    /// Maximum drawdown within the chunk, in percent.
+    pub max_drawdown: f64,
+}
+
+impl ChunkResult {
+    /// Return of the chunk, in percent.
+    pub fn get_return(&self) -> f64 {
+        if self.start_equity == 0 {
+            return 0.0;
+        }
+        (self.end_equity as f64 - self.start_equity as f64) / self.start_equity as f64 * 100.0
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct ChunkedReport {
+    pub chunks: Vec<ChunkResult>,
+    /// Chain-linked equity, expressed in the starting liquidity of the first chunk.
+    pub equity_curve: Vec<(chrono::NaiveDate, f64)>,
+    /// Compounded return over all chunks, in percent.
+    pub total_return: f64,
+    /// Maximum drawdown of the stitched curve, in percent.
+    pub max_drawdown: f64,
+}
+
 pub struct Backtesting {
     pub config: config::Config,
     pub crawler: Rc<dyn crawler::Crawler>,
@@ -27,9 +272,113 @@ pub struct Backtesting {
     pub strategy: strategy::Strategies,
     pub start_date: chrono::NaiveDate,
     pub end_date: chrono::NaiveDate,
+    /// First simulated day, after the strategy's warm-up; set by the last run.
+    pub effective_start_date: Option<chrono::NaiveDate>,
     pub liquidity: u32,
     pub stocks_hold_num: usize,
+    pub circuit_breaker: Option<risk::CircuitBreaker>,
+    pub max_daily_loss: Option<f64>,
+    pub regime_filter: Option<regime::RegimeFilter>,
+    pub breadth_filter: Option<breadth::BreadthFilter>,
+    pub hedge: Option<hedge::Hedge>,
+    pub missing_data_policy: decision::MissingDataPolicy,
+    /// Price held positions, and so the fund diagram, are marked to market at.
+    pub valuation_policy: decision::ValuationPolicy,
+    pub fill_policy: decision::FillPolicy,
+    pub halt_policy: Option<halt::HaltPolicy>,
+    pub price_limit: Option<fill::PriceLimit>,
+    pub order_size: Option<fill::OrderSize>,
+    pub slippage: Option<fill::Slippage>,
+    pub tick_size: Option<fill::TickSize>,
+    pub volume_cap: Option<fill::VolumeCap>,
+    pub scale_out: Option<fill::ScaleOut>,
+    /// Overrides the scale-in days of the strategy.
+    pub scale_in: Option<fill::ScaleIn>,
+    pub cash_flows: Option<cashflow::CashFlowSchedule>,
+    /// Pairs traded on their spread alongside the strategy.
+    pub pairs_trading: Option<pairs::PairsTrading>,
+    /// Perturbs the prices the simulation reads and the slippage of its fills, to measure how
+    /// fragile the strategy is; the same `shock_seed` gives the same perturbations.
+    pub shocks: Vec<shock::ShockModel>,
+    pub shock_seed: u64,
+    /// Trading days between a signal and the orders it leads to.
+    pub signal_latency: usize,
+    /// Stocks entries are picked from instead of the crawler's stock list.
+    pub candidates: Option<Vec<String>>,
+    /// Notes and manual holds of positions, attached to the held stocks of the portfolios.
+    pub position_notes: position::PositionNotes,
+    /// Trades executed at the broker, replacing the recommended fills of the days they cover.
+    pub executed_trades: Option<Vec<reconcile::ExecutedTrade>>,
+    /// Sessions of the exchange over the run, so that holidays and settlement-only days are not
+    /// traded on.
+    pub calendar: Option<calendar::TradingCalendar>,
+    /// Trading days after which a position is settled.
+    pub max_hold_days: Option<usize>,
+    /// Holdings and liquidity the run starts from instead of `liquidity` in cash, see
+    /// `resume_from`.
+    pub start_state: Option<decision::EndState>,
+    /// Recorded run `start_state` is the end state of.
+    pub parent_run_id: Option<String>,
+    /// Stocks the strategy sees on back-adjusted prices, the configured ones by default.
+    pub back_adjustment: adjust::BackAdjustment,
+    /// Parameters of the built-in strategies, the configured ones by default.
+    pub strategy_params: strategy::StrategyParams,
+    /// Holdings and liquidity the last run ended with.
+    pub end_state: Option<decision::EndState>,
+    /// Verifies or prefetches the data the run needs before it starts.
+    pub data_check: Option<prefetch::DataCheck>,
+    /// Fees and taxes the unit economics report charges on top of the simulated fills.
+    pub cost_model: economics::CostModel,
+    /// Refuses to run unless the backend is at this generation, so a run reads exactly the data
+    /// of the run it reproduces.
+    pub pinned_generation: Option<u64>,
+    /// Backend generation the last run read its data at.
+    pub data_generation: Option<u64>,
+    /// Stock id of the benchmark the trade ledger measures beta against.
+    pub benchmark_id: Option<String>,
+    /// Exports the average factor exposures of the holdings over the run.
+    pub factor_report: bool,
+    /// Keeps every portfolio in `portfolios`. When disabled, portfolios are streamed to the
+    /// portfolio file and their equity points to `EQUITY_SERIES_FILENAME` as they are produced,
+    /// and only the statistics of `summary` are kept in memory; the reports read the equity
+    /// points back once the run is over. The pruner, comparing equity curves as the run goes,
+    /// has none to compare then.
+    pub retain_portfolios: bool,
+    /// Times crawling, backend queries, strategy analysis and export, and reports the breakdown
+    /// at the end of the run.
+    pub profiling: bool,
+    /// Records every finished run, with its manifest, metrics and trades, to this store.
+    pub run_op: Option<Rc<dyn run::RunOp>>,
+    pub run_tags: Vec<String>,
+    pub run_note: String,
+    /// Journals the decision and the order plan of the last simulated day, the ones acted upon
+    /// in live operation.
+    pub journal: Option<journal::Journal>,
+    /// Time the run takes place at, which dates the recorded run and bounds the data prefetched.
+    /// The simulated days have a clock of their own.
+    pub clock: Rc<dyn clock::Clock>,
+    /// Stops the run early once it falls clearly behind the best run of a parameter search.
+    pub pruner: Option<pruner::Pruner>,
+    /// Day the last run was stopped by the pruner, if it was.
+    pub pruned_date: Option<chrono::NaiveDate>,
+    /// Reuses the result of a run recorded to `run_op` with the same cache key instead of
+    /// simulating again.
+    pub cache_runs: bool,
+    /// Recorded run the last run was served from, if it was.
+    pub cached_run: Option<run::Run>,
     pub portfolios: Vec<decision::Portfolio>,
+    pub summary: PortfolioSummary,
+    pub trade_ledger: Vec<TradeRecord>,
+    pub realized_lots: Vec<lot::RealizedLot>,
+    /// Orders implied by the decision of the last simulated day.
+    pub order_plan: Option<order::OrderPlan>,
+    portfolio_stream: Option<export::YamlStream>,
+    equity_stream: Option<export::YamlStream>,
+    /// File the equity points of the last run were streamed to, if they were.
+    equity_series_path: Option<String>,
+    last_portfolio: Option<decision::Portfolio>,
+    factor_exposures: factor::FactorReport,
+    profiler: Option<Rc<profiler::Profiler>>,
 }
 
 impl Backtesting {
@@ -39,6 +388,16 @@ impl Backtesting {
         backend_op: Rc<dyn backend::BackendOp>,
         strategy: strategy::Strategies,
     ) -> Self {
+        let back_adjustment = config.get_back_adjustment();
+        let strategy_params = config.strategy_params.clone();
+        let circuit_breaker = config.circuit_breaker.clone();
+        let max_daily_loss = config.max_daily_loss;
+        let regime_filter = config.get_regime_filter(&strategy);
+        let breadth_filter = config.breadth_filter.clone();
+        let hedge = config.hedge.clone();
+        let benchmark_id = config.benchmark_id.clone();
+        let factor_report = config.factor_report;
+
         Backtesting {
             config,
             crawler,
@@ -46,9 +405,68 @@ impl Backtesting {
             strategy,
             start_date: chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
             end_date: chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
+            effective_start_date: None,
             liquidity: 200000,
             stocks_hold_num: 5,
+            circuit_breaker,
+            max_daily_loss,
+            regime_filter,
+            breadth_filter,
+            hedge,
+            missing_data_policy: decision::MissingDataPolicy::SkipDay,
+            valuation_policy: decision::ValuationPolicy::Mid,
+            fill_policy: decision::FillPolicy::Mid,
+            halt_policy: None,
+            price_limit: None,
+            order_size: None,
+            slippage: None,
+            tick_size: None,
+            volume_cap: None,
+            scale_out: None,
+            scale_in: None,
+            cash_flows: None,
+            pairs_trading: None,
+            shocks: Vec::new(),
+            shock_seed: 0,
+            signal_latency: 0,
+            candidates: None,
+            position_notes: position::PositionNotes::new(),
+            executed_trades: None,
+            calendar: None,
+            max_hold_days: None,
+            start_state: None,
+            parent_run_id: None,
+            back_adjustment,
+            strategy_params,
+            end_state: None,
+            data_check: None,
+            cost_model: economics::CostModel::default(),
+            pinned_generation: None,
+            data_generation: None,
+            benchmark_id,
+            factor_report,
+            retain_portfolios: true,
+            profiling: false,
+            run_op: None,
+            run_tags: Vec::new(),
+            run_note: "".to_owned(),
+            journal: None,
+            clock: Rc::new(clock::SystemClock),
+            pruner: None,
+            pruned_date: None,
+            cache_runs: false,
+            cached_run: None,
             portfolios: Vec::new(),
+            summary: PortfolioSummary::default(),
+            trade_ledger: Vec::new(),
+            realized_lots: Vec::new(),
+            order_plan: None,
+            portfolio_stream: None,
+            equity_stream: None,
+            equity_series_path: None,
+            last_portfolio: None,
+            factor_exposures: factor::FactorReport::default(),
+            profiler: None,
         }
     }
 
@@ -56,50 +474,961 @@ impl Backtesting {
         self.start_date = start_date;
         self.end_date = end_date;
 
-        let strategy = Rc::new(strategy::StrategyFactory::get(
+        self.equity_series_path = None;
+        if !self.retain_portfolios {
+            let equity_series_path = self.get_full_path(EQUITY_SERIES_FILENAME);
+
+            std::fs::create_dir_all(&self.config.portfolio_path).unwrap();
+            self.portfolio_stream = Some(export::YamlStream::new(
+                &self.get_full_path(PORTFOLIO_FILENAME),
+            ));
+            self.equity_stream = Some(export::YamlStream::new(&equity_series_path));
+            self.equity_series_path = Some(equity_series_path);
+        }
+
+        let crawler = self.crawler.clone();
+        let backend_op = self.backend_op.clone();
+
+        if self.profiling {
+            let profiler = Rc::new(profiler::Profiler::new());
+
+            self.crawler = Rc::new(profiler::ProfiledCrawler {
+                crawler: crawler.clone(),
+                profiler: profiler.clone(),
+            });
+            self.backend_op = Rc::new(profiler::ProfiledBackend {
+                backend_op: backend_op.clone(),
+                profiler: profiler.clone(),
+            });
+            self.profiler = Some(profiler);
+        }
+
+        self.prepare_data(start_date, end_date);
+        self.pin_data();
+        self.cached_run = self.find_cached_run();
+
+        if let Some(cached_run) = &self.cached_run {
+            println!(
+                "Reusing run {} recorded with the same inputs",
+                cached_run.run_id
+            );
+            self.effective_start_date = cached_run.manifest.effective_start_date;
+            self.trade_ledger = cached_run.trades.clone();
+            self.end_state = cached_run.end_state.clone();
+            self.portfolio_stream = None;
+            self.equity_stream = None;
+        } else {
+            let trade_stocks = self.simulate();
+
+            self.check_data_generation();
+            self.portfolio_stream = None;
+            self.equity_stream = None;
+            self.enter_phase(profiler::Phase::Export);
+            self.fill_benchmark_info();
+            self.export_trade(&trade_stocks);
+            self.draw_diagram(&trade_stocks);
+            self.record_run();
+            self.record_journal();
+            self.exit_phase();
+        }
+
+        if let Some(profiler) = self.profiler.take() {
+            println!("{}", profiler.report());
+            export::to_yaml(
+                &self.get_full_path(TIMING_FILENAME),
+                &profiler.get_timings(),
+            );
+            self.crawler = crawler;
+            self.backend_op = backend_op;
+        }
+    }
+
+    /// Digest of the strategy, universe, date range, execution settings and data generation of
+    /// the run, so that a run with the same key would give the same result.
+    pub fn get_cache_key(&self) -> String {
+        let strategy_file = match &self.strategy {
+            strategy::Strategies::Script(path) => Some(path.to_owned()),
+            strategy::Strategies::Onnx(model_config) => Some(model_config.model_path.to_owned()),
+            _ => None,
+        };
+        let mut stock_ids = self.crawler.get_stock_list().unwrap_or(vec![]);
+
+        stock_ids.sort();
+
+        let input = CacheKeyInput {
+            strategy: &self.strategy,
+            strategy_file: strategy_file
+                .and_then(|path| std::fs::read(path).ok())
+                .map(|bytes| run::get_digest(&bytes)),
+            stock_ids,
+            start_date: self.start_date,
+            end_date: self.end_date,
+            data_generation: self.data_generation,
+            liquidity: self.liquidity,
+            stocks_hold_num: self.stocks_hold_num,
+            circuit_breaker: &self.circuit_breaker,
+            max_daily_loss: self.max_daily_loss,
+            regime_filter: &self.regime_filter,
+            breadth_filter: &self.breadth_filter,
+            hedge: &self.hedge,
+            missing_data_policy: self.missing_data_policy,
+            halt_policy: &self.halt_policy,
+            price_limit: &self.price_limit,
+            order_size: &self.order_size,
+            cash_flows: &self.cash_flows,
+            benchmark_id: &self.benchmark_id,
+            slippage: &self.slippage,
+            shock: match self.shocks.is_empty() {
+                true => None,
+                false => Some((&self.shocks, self.shock_seed)),
+            },
+            signal_latency: match self.signal_latency {
+                0 => None,
+                signal_latency => Some(signal_latency),
+            },
+            tick_size: &self.tick_size,
+            volume_cap: &self.volume_cap,
+            scale_out: &self.scale_out,
+            scale_in: &self.scale_in,
+            valuation_policy: match self.valuation_policy {
+                decision::ValuationPolicy::Mid => None,
+                valuation_policy => Some(valuation_policy),
+            },
+            candidates: &self.candidates,
+            position_notes: &self.position_notes,
+            executed_trades: &self.executed_trades,
+            calendar: &self.calendar,
+            max_hold_days: self.max_hold_days,
+            start_state: &self.start_state,
+            back_adjusted_stock_ids: &self.back_adjustment.stock_ids,
+            strategy_params: match &self.strategy_params {
+                strategy_params if *strategy_params == strategy::StrategyParams::default() => None,
+                strategy_params => Some(strategy_params),
+            },
+            fill_policy: match self.fill_policy {
+                decision::FillPolicy::Mid => None,
+                fill_policy => Some(fill_policy),
+            },
+            pairs_trading: &self.pairs_trading,
+        };
+
+        run::get_digest(&serde_json::to_vec(&input).unwrap())
+    }
+
+    /// Scale-in of the run: the one set on the backtest, or else the strategy's own.
+    pub fn get_scale_in(&self) -> Option<fill::ScaleIn> {
+        match &self.scale_in {
+            Some(scale_in) => Some(scale_in.clone()),
+            None => self
+                .strategy
+                .get_scale_in_days()
+                .map(|days| fill::ScaleIn { days }),
+        }
+    }
+
+    fn find_cached_run(&self) -> Option<run::Run> {
+        match (&self.run_op, self.cache_runs) {
+            (Some(run_op), true) => run_op.find_cached_run(&self.get_cache_key()).unwrap(),
+            _ => None,
+        }
+    }
+
+    /// Continues `run` from its end state: the next run starts with its holdings and liquidity,
+    /// and its returns are measured against the equity it ended with. Returns the day after
+    /// `run` ended, to start the next run on, or `None` when `run` recorded no end state.
+    pub fn resume_from(&mut self, run: &run::Run) -> Option<chrono::NaiveDate> {
+        let end_state = run.end_state.as_ref()?;
+
+        self.liquidity = end_state.equity();
+        self.stocks_hold_num = run.manifest.stocks_hold_num;
+        self.start_state = Some(end_state.clone());
+        self.parent_run_id = Some(run.run_id.to_owned());
+        end_state.date.succ_opt()
+    }
+
+    fn record_run(&self) {
+        let run_op = match &self.run_op {
+            Some(run_op) => run_op,
+            None => return,
+        };
+        let created_at = self.clock.now();
+        let run = run::Run {
+            run_id: created_at.format("%Y%m%d%H%M%S%3f").to_string(),
+            manifest: run::RunManifest {
+                strategy: self.strategy.clone(),
+                start_date: self.start_date,
+                end_date: self.end_date,
+                effective_start_date: self.effective_start_date,
+                data_generation: self.data_generation,
+                liquidity: self.liquidity,
+                stocks_hold_num: self.stocks_hold_num,
+                benchmark_id: self.benchmark_id.clone(),
+                created_at,
+                cache_key: match self.pruned_date {
+                    Some(_) => None,
+                    None => Some(self.get_cache_key()),
+                },
+                parent_run_id: self.parent_run_id.clone(),
+            },
+            metrics: self.get_run_metrics(),
+            trades: self.trade_ledger.clone(),
+            tags: self.run_tags.clone(),
+            note: self.run_note.to_owned(),
+            end_state: self.end_state.clone(),
+        };
+
+        run_op.insert_run(&run).unwrap();
+        println!("Recorded run {}", run.run_id);
+    }
+
+    fn record_journal(&mut self) {
+        let journal = match &self.journal {
+            Some(journal) => journal,
+            None => return,
+        };
+
+        if let Some(portfolio) = self.last_portfolio.take() {
+            journal
+                .record(journal::JournalEvent::Decision(portfolio))
+                .unwrap();
+        }
+        if let Some(order_plan) = &self.order_plan {
+            journal
+                .record(journal::JournalEvent::OrderPlan(order_plan.clone()))
+                .unwrap();
+        }
+    }
+
+    pub fn get_run_metrics(&self) -> run::RunMetrics {
+        if let Some(cached_run) = &self.cached_run {
+            return cached_run.metrics.clone();
+        }
+
+        let equity_series = self.get_equity_series();
+        let final_equity = match equity_series.last() {
+            Some(equity_point) => equity_point.equity,
+            None => self.liquidity,
+        };
+        let win_count = self
+            .trade_ledger
+            .iter()
+            .filter(|trade_record| trade_record.get_return() > 0.0)
+            .count();
+
+        run::RunMetrics {
+            final_equity,
+            total_return: if self.liquidity == 0 {
+                0.0
+            } else {
+                (final_equity as f64 - self.liquidity as f64) / self.liquidity as f64 * 100.0
+            },
+            max_drawdown: self.summary.max_drawdown,
+            trade_count: self.trade_ledger.len(),
+            win_rate: if self.trade_ledger.is_empty() {
+                0.0
+            } else {
+                win_count as f64 / self.trade_ledger.len() as f64 * 100.0
+            },
+            time_weighted_return: cashflow::time_weighted_return(
+                self.liquidity,
+                &equity_series
+                    .iter()
+                    .map(|equity_point| (equity_point.equity, equity_point.cash_flow))
+                    .collect::<Vec<_>>(),
+            ),
+            net_cash_flow: equity_series
+                .iter()
+                .map(|equity_point| equity_point.cash_flow)
+                .sum(),
+            deferred_fills: self.summary.deferred_fill_count,
+            skipped_orders: self.summary.skipped_order_count,
+            partial_fills: self.summary.partial_fill_count,
+        }
+    }
+
+    fn enter_phase(&self, phase: profiler::Phase) {
+        if let Some(profiler) = &self.profiler {
+            profiler.enter(phase);
+        }
+    }
+
+    fn exit_phase(&self) {
+        if let Some(profiler) = &self.profiler {
+            profiler.exit();
+        }
+    }
+
+    /// Runs the backtest in calendar-year chunks, each starting afresh from `liquidity`, and
+    /// chain-links their equity curves into one. Positions still open at the end of a chunk are
+    /// valued at that day's price. Only the stitched curve and per-chunk summaries are kept, so
+    /// memory stays bounded by a single year of portfolios.
+    pub fn run_chunked(
+        &mut self,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> ChunkedReport {
+        let mut report = ChunkedReport::default();
+        let mut chunk_start_date = start_date;
+        let mut scale = 1.0;
+
+        self.equity_series_path = None;
+        self.prepare_data(start_date, end_date);
+        self.pin_data();
+
+        while chunk_start_date <= end_date {
+            let chunk_end_date = chrono::NaiveDate::from_ymd_opt(chunk_start_date.year(), 12, 31)
+                .unwrap()
+                .min(end_date);
+            let mut chunk = ChunkResult {
+                start_date: chunk_start_date,
+                end_date: chunk_end_date,
+                start_equity: self.liquidity,
+                end_equity: self.liquidity,
+                max_drawdown: 0.0,
+            };
+            let mut peak_equity = self.liquidity;
+
+            self.start_date = chunk_start_date;
+            self.end_date = chunk_end_date;
+            self.portfolios.clear();
+            self.summary = PortfolioSummary::default();
+            self.trade_ledger.clear();
+            self.realized_lots.clear();
+            self.simulate();
+
+            for equity_point in &self.summary.equity_series {
+                let equity = equity_point.equity;
+
+                peak_equity = peak_equity.max(equity);
+                chunk.max_drawdown = chunk.max_drawdown.max(risk::drawdown(peak_equity, equity));
+                chunk.end_equity = equity;
+                report
+                    .equity_curve
+                    .push((equity_point.date, equity as f64 * scale));
+            }
+            if chunk.start_equity > 0 {
+                scale *= chunk.end_equity as f64 / chunk.start_equity as f64;
+            }
+            report.chunks.push(chunk);
+            chunk_start_date = chunk_end_date.succ_opt().unwrap();
+        }
+
+        self.start_date = start_date;
+        self.end_date = end_date;
+        self.portfolios.clear();
+        self.summary = PortfolioSummary::default();
+        self.trade_ledger.clear();
+        self.realized_lots.clear();
+        report.total_return = (scale - 1.0) * 100.0;
+
+        let mut peak_equity = 0.0;
+
+        for (_, equity) in &report.equity_curve {
+            if *equity > peak_equity {
+                peak_equity = *equity;
+            }
+            if peak_equity > 0.0 {
+                report.max_drawdown = report
+                    .max_drawdown
+                    .max((peak_equity - equity) / peak_equity * 100.0);
+            }
+        }
+
+        std::fs::create_dir_all(&self.config.portfolio_path).unwrap();
+        export::to_yaml(&self.get_full_path(CHUNKED_REPORT_FILENAME), &report);
+        self.draw_chunked_fund_diagram(&report);
+        self.check_data_generation();
+        report
+    }
+
+    /// Replays the strategy over each of `scenarios` in turn and tabulates their metrics. Each
+    /// run writes its output to a directory of its own under `portfolio_path` and is recorded
+    /// with a `scenario:<name>` tag.
+    pub fn run_scenarios(&mut self, scenarios: &[scenario::Scenario]) -> scenario::ScenarioReport {
+        let portfolio_path = self.config.portfolio_path.to_owned();
+        let run_tags = self.run_tags.clone();
+        let mut report = scenario::ScenarioReport::default();
+
+        for scenario in scenarios {
+            println!(
+                "Scenario {}: {} to {}",
+                scenario.name, scenario.start_date, scenario.end_date
+            );
+            self.config.portfolio_path = format!(
+                "{}/{}/{}",
+                portfolio_path,
+                scenario::SCENARIO_DIRECTORY,
+                scenario.name
+            );
+            self.run_tags = run_tags.clone();
+            self.run_tags.push(format!("scenario:{}", scenario.name));
+            self.reset();
+            self.run(scenario.start_date, scenario.end_date);
+            report.results.push(scenario::ScenarioResult {
+                scenario: scenario.clone(),
+                metrics: self.get_run_metrics(),
+            });
+        }
+
+        self.config.portfolio_path = portfolio_path;
+        self.run_tags = run_tags;
+        std::fs::create_dir_all(&self.config.portfolio_path).unwrap();
+        export::to_yaml(
+            &self.get_full_path(scenario::SCENARIO_REPORT_FILENAME),
+            &report,
+        );
+        std::fs::write(
+            self.get_full_path(scenario::SCENARIO_TABLE_FILENAME),
+            report.to_html(),
+        )
+        .expect("Failed to write html");
+        report
+    }
+
+    /// Runs the backtest once as is, then again under the shocks of `shock_config` with each of
+    /// its seeds, and compares the shocked runs with the first. Each run writes its output to a
+    /// directory of its own under `portfolio_path` and is recorded with a `shock:<seed>` tag.
+    pub fn run_shocked(
+        &mut self,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+        shock_config: &shock::ShockConfig,
+    ) -> shock::ShockReport {
+        let portfolio_path = self.config.portfolio_path.to_owned();
+        let run_tags = self.run_tags.clone();
+        let get_path =
+            |name: &str| format!("{}/{}/{}", portfolio_path, shock::SHOCK_DIRECTORY, name);
+
+        self.config.portfolio_path = get_path("baseline");
+        self.shocks = Vec::new();
+        self.reset();
+        self.run(start_date, end_date);
+
+        let mut report = shock::ShockReport {
+            models: shock_config.models.clone(),
+            baseline: self.get_run_metrics(),
+            results: Vec::new(),
+        };
+
+        self.shocks = shock_config.models.clone();
+        for seed in &shock_config.seeds {
+            println!("Shock seed {}", seed);
+            self.config.portfolio_path = get_path(&format!("seed-{}", seed));
+            self.shock_seed = *seed;
+            self.run_tags = run_tags.clone();
+            self.run_tags.push(format!("shock:{}", seed));
+            self.reset();
+            self.run(start_date, end_date);
+            report.results.push(shock::ShockResult {
+                seed: *seed,
+                metrics: self.get_run_metrics(),
+            });
+        }
+
+        self.config.portfolio_path = portfolio_path;
+        self.run_tags = run_tags;
+        self.shocks = Vec::new();
+        std::fs::create_dir_all(&self.config.portfolio_path).unwrap();
+        export::to_yaml(&self.get_full_path(shock::SHOCK_REPORT_FILENAME), &report);
+        std::fs::write(
+            self.get_full_path(shock::SHOCK_TABLE_FILENAME),
+            report.to_html(),
+        )
+        .expect("Failed to write html");
+        report
+    }
+
+    /// Runs the backtest with each of `latencies` trading days between a signal and its orders,
+    /// to tell how much a slower workflow would cost. Each run writes its output to a directory
+    /// of its own under `portfolio_path` and is recorded with a `latency:<days>` tag.
+    pub fn run_latencies(
+        &mut self,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+        latencies: &[usize],
+    ) -> latency::LatencyReport {
+        let portfolio_path = self.config.portfolio_path.to_owned();
+        let run_tags = self.run_tags.clone();
+        let signal_latency = self.signal_latency;
+        let mut latencies = latencies.to_vec();
+        let mut report = latency::LatencyReport::default();
+
+        latencies.sort();
+        latencies.dedup();
+        for latency in latencies {
+            println!("Signal latency T+{}", latency);
+            self.config.portfolio_path = format!(
+                "{}/{}/t{}",
+                portfolio_path,
+                latency::LATENCY_DIRECTORY,
+                latency
+            );
+            self.signal_latency = latency;
+            self.run_tags = run_tags.clone();
+            self.run_tags.push(format!("latency:{}", latency));
+            self.reset();
+            self.run(start_date, end_date);
+            report.results.push(latency::LatencyResult {
+                latency,
+                metrics: self.get_run_metrics(),
+            });
+        }
+
+        self.config.portfolio_path = portfolio_path;
+        self.run_tags = run_tags;
+        self.signal_latency = signal_latency;
+        std::fs::create_dir_all(&self.config.portfolio_path).unwrap();
+        export::to_yaml(
+            &self.get_full_path(latency::LATENCY_REPORT_FILENAME),
+            &report,
+        );
+        std::fs::write(
+            self.get_full_path(latency::LATENCY_TABLE_FILENAME),
+            report.to_html(),
+        )
+        .expect("Failed to write html");
+        report
+    }
+
+    /// Runs the backtest starting from each of `levels` of capital, to tell how far the strategy
+    /// scales. Only meaningful with a volume cap, which makes large orders fill slower. Each run
+    /// writes its output to a directory of its own under `portfolio_path` and is recorded with a
+    /// `capital:<liquidity>` tag.
+    pub fn run_capital_levels(
+        &mut self,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+        levels: &[u32],
+    ) -> scaling::ScalingReport {
+        let portfolio_path = self.config.portfolio_path.to_owned();
+        let run_tags = self.run_tags.clone();
+        let liquidity = self.liquidity;
+        let mut levels = levels.to_vec();
+        let mut report = scaling::ScalingReport::default();
+
+        levels.sort();
+        levels.dedup();
+        for level in levels {
+            println!("Capital {}", level);
+            self.config.portfolio_path = format!(
+                "{}/{}/{}",
+                portfolio_path,
+                scaling::SCALING_DIRECTORY,
+                level
+            );
+            self.liquidity = level;
+            self.run_tags = run_tags.clone();
+            self.run_tags.push(format!("capital:{}", level));
+            self.reset();
+            self.run(start_date, end_date);
+            report.levels.push(scaling::CapitalLevel {
+                liquidity: level,
+                metrics: self.get_run_metrics(),
+            });
+        }
+
+        self.config.portfolio_path = portfolio_path;
+        self.run_tags = run_tags;
+        self.liquidity = liquidity;
+        std::fs::create_dir_all(&self.config.portfolio_path).unwrap();
+        export::to_yaml(
+            &self.get_full_path(scaling::SCALING_REPORT_FILENAME),
+            &report,
+        );
+        std::fs::write(
+            self.get_full_path(scaling::SCALING_TABLE_FILENAME),
+            report.to_html(),
+        )
+        .expect("Failed to write html");
+        report
+    }
+
+    /// Clears what the last run left behind, so the next one starts afresh.
+    fn reset(&mut self) {
+        self.portfolios.clear();
+        self.summary = PortfolioSummary::default();
+        self.trade_ledger.clear();
+        self.realized_lots.clear();
+        self.order_plan = None;
+        self.factor_exposures = factor::FactorReport::default();
+    }
+
+    /// Backend the simulation reads prices from: the store, perturbed by the shocks if any.
+    fn get_market(&self) -> Rc<dyn backend::BackendOp> {
+        match self.shocks.is_empty() {
+            true => self.backend_op.clone(),
+            false => Rc::new(shock::ShockedBackend::new(
+                self.backend_op.clone(),
+                self.shocks.clone(),
+                self.shock_seed,
+            )),
+        }
+    }
+
+    fn get_slippage(&self) -> Option<fill::Slippage> {
+        let factor = shock::get_slippage_factor(&self.shocks);
+
+        if factor == 1.0 {
+            return self.slippage.clone();
+        }
+
+        let slippage = self.slippage.clone().unwrap_or_default();
+
+        Some(fill::Slippage {
+            rate: slippage.rate * factor,
+        })
+    }
+
+    fn simulate(&mut self) -> HashMap<String, Vec<(chrono::NaiveDate, chrono::NaiveDate)>> {
+        let market = self.get_market();
+        let mut market_strategy = strategy::StrategyFactory::get(
             self.strategy.clone(),
-            self.backend_op.clone(),
-        ));
-        let mut decision =
-            decision::Decision::new(self.crawler.clone(), self.backend_op.clone(), strategy);
-        let mut date = self.start_date;
-        let mut stocks_hold = HashMap::new();
+            &self.strategy_params,
+            market.clone(),
+        );
+
+        market_strategy.set_back_adjustment(self.back_adjustment.clone());
+
+        let mut strategy: Rc<dyn strategy::StrategyAPI> = Rc::new(market_strategy);
+
+        if let Some(profiler) = &self.profiler {
+            strategy = Rc::new(profiler::ProfiledStrategy {
+                strategy,
+                profiler: profiler.clone(),
+            });
+        }
+
+        let effective_start_date = self.get_effective_start_date(strategy.min_history_days());
+        let mut decision = decision::Decision::new(self.crawler.clone(), market, strategy);
+        let mut date = effective_start_date;
+        let simulated_clock = Rc::new(clock::ManualClock::from_date(date));
+        let mut stocks_hold: HashMap<String, (chrono::NaiveDate, u32, u32)> = HashMap::new();
         let mut trade_stocks = HashMap::new();
 
+        if effective_start_date > self.start_date {
+            println!(
+                "Warm-up: scoring starts on {} instead of {}",
+                effective_start_date, self.start_date
+            );
+        }
+        self.effective_start_date = Some(effective_start_date);
+        self.pruned_date = None;
         decision.liquidity = self.liquidity;
         decision.stocks_hold_num = self.stocks_hold_num;
+        decision.circuit_breaker = self.circuit_breaker.clone();
+        decision.max_daily_loss = self.max_daily_loss;
+        decision.regime_filter = self.regime_filter.clone();
+        decision.breadth_filter = self.breadth_filter.clone();
+        decision.hedge = self.hedge.clone();
+        decision.missing_data_policy = self.missing_data_policy;
+        decision.valuation_policy = self.valuation_policy;
+        decision.fill_policy = self.fill_policy;
+        decision.halt_policy = self.halt_policy.clone();
+        decision.price_limit = self.price_limit.clone();
+        decision.order_size = self.order_size.clone();
+        decision.slippage = self.get_slippage();
+        decision.tick_size = self.tick_size.clone();
+        decision.signal_latency = self.signal_latency;
+        decision.volume_cap = self.volume_cap.clone();
+        decision.scale_out = self.scale_out.clone();
+        decision.scale_in = self.get_scale_in();
+        decision.candidates = self.candidates.clone();
+        decision.position_notes = self.position_notes.clone();
+        decision.statement = self
+            .executed_trades
+            .as_ref()
+            .map(|executed_trades| reconcile::Statement::new(executed_trades));
+        decision.cash_flows = self.cash_flows.clone();
+        decision.dca = match &self.strategy {
+            strategy::Strategies::Dca(plan) => Some(plan.clone()),
+            _ => None,
+        };
+        decision.pairs_trading = self.pairs_trading.clone();
+        decision.clock = simulated_clock.clone();
+        decision.calendar = self.calendar.clone();
+        decision.max_hold_days = self.max_hold_days;
+        if let Some(start_state) = &self.start_state {
+            decision.resume(start_state);
+            for holding in &start_state.holdings {
+                stocks_hold.insert(
+                    holding.stock_id.to_owned(),
+                    (holding.hold_date, holding.hold_price, holding.num),
+                );
+            }
+        }
 
         while date <= self.end_date {
+            simulated_clock.set_date(date);
+            self.enter_phase(profiler::Phase::Decision);
+
             let portfolio_opt = decision.calc_portfolio(date).unwrap();
 
+            self.exit_phase();
+
             if portfolio_opt.is_some() {
                 let portfolio = portfolio_opt.unwrap();
 
                 for stock_info in &portfolio.stocks_settled {
-                    let hold_date = stocks_hold.get(&stock_info.stock_id).unwrap();
+                    let (hold_date, hold_price, hold_num) =
+                        stocks_hold.get_mut(&stock_info.stock_id).unwrap();
 
                     trade_stocks
                         .entry(stock_info.stock_id.to_owned())
                         .or_insert(Vec::new())
                         .push((*hold_date, date));
-                    stocks_hold.remove(&stock_info.stock_id);
+                    self.trade_ledger.push(TradeRecord {
+                        stock_id: stock_info.stock_id.to_owned(),
+                        hold_date: *hold_date,
+                        settle_date: date,
+                        num: stock_info.num,
+                        hold_price: *hold_price,
+                        settle_price: stock_info.price,
+                        beta: None,
+                        benchmark_return: None,
+                        settle_reason: stock_info.settle_reason,
+                    });
+                    // Part of the position stays held when the volume cap splits the sale.
+                    *hold_num = hold_num.saturating_sub(stock_info.num);
+                    if *hold_num == 0 {
+                        stocks_hold.remove(&stock_info.stock_id);
+                    }
                 }
                 for stock_info in &portfolio.stocks_selected {
-                    stocks_hold.insert(stock_info.stock_id.to_owned(), date);
+                    let (_, hold_price, hold_num) = stocks_hold
+                        .entry(stock_info.stock_id.to_owned())
+                        .or_insert((date, 0, 0));
+                    let num = *hold_num + stock_info.num;
+
+                    // Entries filled over several days are held at their average price.
+                    *hold_price = ((*hold_price as u64 * *hold_num as u64
+                        + stock_info.price as u64 * stock_info.num as u64)
+                        / num.max(1) as u64) as u32;
+                    *hold_num = num;
+                }
+                self.record_portfolio(portfolio);
+
+                if let Some(pruner) = &self.pruner {
+                    if pruner.should_prune(self.liquidity, &self.summary) {
+                        println!("Pruned on {}", date);
+                        self.pruned_date = Some(date);
+                        break;
+                    }
                 }
-                self.portfolios.push(portfolio);
             }
             date = date.succ_opt().unwrap();
         }
+        self.end_state = decision.get_end_state();
+
+        trade_stocks
+    }
+
+    /// Data read over the run: the strategy's own requirements plus the instruments of the
+    /// benchmark, the hedge and the regime filter, and the stocks of the traded pairs.
+    fn get_data_requirements(&self) -> strategy::DataRequirements {
+        let strategy: Rc<dyn strategy::StrategyAPI> = Rc::new(strategy::StrategyFactory::get(
+            self.strategy.clone(),
+            &self.strategy_params,
+            self.backend_op.clone(),
+        ));
+        let mut requirements = strategy.data_requirements();
+        let mut instrument_ids: Vec<String> = [
+            self.benchmark_id.to_owned(),
+            self.hedge
+                .as_ref()
+                .map(|hedge| hedge.instrument_id.to_owned()),
+            self.regime_filter
+                .as_ref()
+                .map(|regime_filter| regime_filter.index_id.to_owned()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        if let Some(pairs_trading) = &self.pairs_trading {
+            for pair in &pairs_trading.pairs {
+                instrument_ids.push(pair.first_id.to_owned());
+                instrument_ids.push(pair.second_id.to_owned());
+            }
+        }
+
+        for instrument_id in instrument_ids {
+            let dataset = strategy::Dataset::InstrumentPrices(instrument_id);
+
+            if !requirements.datasets.contains(&dataset) {
+                requirements.datasets.push(dataset);
+            }
+        }
+        requirements
+    }
+
+    fn prepare_data(&self, start_date: chrono::NaiveDate, end_date: chrono::NaiveDate) {
+        let data_check = match self.data_check {
+            Some(data_check) => data_check,
+            None => return,
+        };
+        let mut prefetcher =
+            prefetch::Prefetcher::new(self.crawler.clone(), self.backend_op.clone());
+
+        prefetcher.clock = self.clock.clone();
+
+        if let Err(err) = prefetcher.prepare(
+            &self.get_data_requirements(),
+            start_date,
+            end_date,
+            data_check,
+        ) {
+            panic!("Failed to prepare data: {}", err);
+        }
+    }
+
+    /// Records the backend generation the run reads, after any prefetch, and checks it against
+    /// the pinned one.
+    fn pin_data(&mut self) {
+        let generation = self.backend_op.get_generation().unwrap();
+
+        if let Some(pinned_generation) = self.pinned_generation {
+            if generation != pinned_generation {
+                panic!(
+                    "Backend data is at generation {}, not the pinned generation {}",
+                    generation, pinned_generation
+                );
+            }
+        }
+        println!("Running on data generation {}", generation);
+        self.data_generation = Some(generation);
+    }
+
+    /// Fails the run when the backend was written to while it ran, as its results could not be
+    /// reproduced.
+    fn check_data_generation(&self) {
+        let generation = self.backend_op.get_generation().unwrap();
+
+        if let Some(data_generation) = self.data_generation {
+            if generation != data_generation {
+                panic!(
+                    "Backend data changed during the run, from generation {} to {}",
+                    data_generation, generation
+                );
+            }
+        }
+    }
+
+    /// First day with `min_history_days` trading days of history before it, measured on the
+    /// benchmark or the warm-up reference. Falls back to `start_date` when the reference has too
+    /// few records to tell.
+    fn get_effective_start_date(&self, min_history_days: usize) -> chrono::NaiveDate {
+        if min_history_days == 0 {
+            return self.start_date;
+        }
+
+        let reference_id = self
+            .benchmark_id
+            .to_owned()
+            .unwrap_or(WARM_UP_REFERENCE_ID.to_owned());
+        let records = self
+            .backend_op
+            .query_by_range(
+                &reference_id,
+                self.start_date - chrono::Duration::days(min_history_days as i64 * 2),
+                self.end_date,
+            )
+            .unwrap();
+
+        match records.get(min_history_days) {
+            Some(record) => record.date.max(self.start_date),
+            None => self.start_date,
+        }
+    }
+
+    fn record_portfolio(&mut self, portfolio: decision::Portfolio) {
+        match &mut self.equity_stream {
+            Some(equity_stream) => equity_stream.push(&self.summary.summarize(&portfolio)),
+            None => self.summary.add(&portfolio),
+        }
+        self.realized_lots
+            .extend(portfolio.realized_lots.iter().cloned());
+        self.order_plan = Some(order::OrderPlan::from_portfolio(&portfolio));
+        if self.journal.is_some() {
+            self.last_portfolio = Some(portfolio.clone());
+        }
+        if self.factor_report {
+            let factor_analysis = factor::FactorAnalysis::new(self.backend_op.clone());
+
+            if let Some(exposure) = factor_analysis.get_portfolio_exposure(&portfolio).unwrap() {
+                self.factor_exposures
+                    .exposures
+                    .push((portfolio.date, exposure));
+            }
+        }
+        if let Some(portfolio_stream) = &mut self.portfolio_stream {
+            portfolio_stream.push(&portfolio);
+        } else if self.retain_portfolios {
+            self.portfolios.push(portfolio);
+        }
+    }
+
+    fn fill_benchmark_info(&mut self) {
+        let benchmark_id = match &self.benchmark_id {
+            Some(benchmark_id) => benchmark_id.to_owned(),
+            None => return,
+        };
+        let history_days = view::BETA_PERIOD as i64 * 2;
 
-        self.export_trade(&trade_stocks);
-        self.draw_diagram(&trade_stocks);
+        for trade_record in self.trade_ledger.iter_mut() {
+            let start_date = trade_record.hold_date - chrono::Duration::days(history_days);
+            let records = self
+                .backend_op
+                .query_by_range(&trade_record.stock_id, start_date, trade_record.hold_date)
+                .unwrap();
+            let benchmark_records = self
+                .backend_op
+                .query_by_range(&benchmark_id, start_date, trade_record.settle_date)
+                .unwrap();
+            let views = view::BetaView::transform_by_benchmark(
+                &records,
+                &benchmark_records,
+                view::BETA_PERIOD,
+            )
+            .unwrap();
+            let hold_close = benchmark_records
+                .iter()
+                .rev()
+                .find(|record| record.date <= trade_record.hold_date);
+            let settle_close = benchmark_records.last();
+
+            trade_record.beta = views.last().map(|view| view.beta);
+            if let (Some(hold_record), Some(settle_record)) = (hold_close, settle_close) {
+                if hold_record.close > 0.0 {
+                    trade_record.benchmark_return =
+                        Some((settle_record.close - hold_record.close) / hold_record.close * 100.0);
+                }
+            }
+        }
+    }
+
+    fn render(&self, plot: &plotly::Plot, filename: &str) {
+        diagram::render(
+            plot,
+            &self.config.diagram_style,
+            &diagram::Output::Html(self.get_full_path(filename)),
+        );
     }
 
     fn get_full_path(&self, filename: &str) -> String {
         self.config.portfolio_path.to_owned() + "/" + filename
     }
 
+    /// Equity points of the last run, read back from their file when they were streamed.
+    fn get_equity_series(&self) -> Cow<'_, [EquityPoint]> {
+        let equity_series_path = match &self.equity_series_path {
+            Some(equity_series_path) => equity_series_path,
+            None => return Cow::Borrowed(&self.summary.equity_series),
+        };
+        let data = std::fs::read_to_string(equity_series_path).expect("Failed to read yaml");
+
+        Cow::Owned(serde_yaml::from_str(&data).expect("Failed to parse yaml"))
+    }
+
     fn get_stock_trade_info(
         &self,
         stock_id: &str,
@@ -107,7 +1436,7 @@ impl Backtesting {
     ) -> StockTradeInfo {
         let records = self
             .backend_op
-            .query_by_range(&stock_id, self.start_date, self.end_date)
+            .query_by_range(stock_id, self.start_date, self.end_date)
             .unwrap();
 
         StockTradeInfo {
@@ -117,7 +1446,7 @@ impl Backtesting {
     }
 
     fn export_trade(
-        &self,
+        &mut self,
         trade_stocks: &HashMap<String, Vec<(chrono::NaiveDate, chrono::NaiveDate)>>,
     ) {
         std::fs::create_dir_all(&self.config.portfolio_path).unwrap();
@@ -125,10 +1454,45 @@ impl Backtesting {
         for (stock_id, trade_series) in trade_stocks {
             export::to_yaml(
                 &self.get_full_path(&(stock_id.to_owned() + ".yaml")),
-                &self.get_stock_trade_info(&stock_id, &trade_series),
+                &self.get_stock_trade_info(stock_id, trade_series),
+            );
+        }
+        if self.retain_portfolios {
+            export::to_yaml(&self.get_full_path(PORTFOLIO_FILENAME), &self.portfolios);
+        }
+        export::to_yaml(
+            &self.get_full_path(PORTFOLIO_SUMMARY_FILENAME),
+            &self.summary,
+        );
+        export::to_yaml(
+            &self.get_full_path(TRADE_LEDGER_FILENAME),
+            &self.trade_ledger,
+        );
+        export::to_yaml(
+            &self.get_full_path(REALIZED_LOTS_FILENAME),
+            &self.realized_lots,
+        );
+        if let Some(order_plan) = &self.order_plan {
+            std::fs::write(
+                self.get_full_path(ORDER_PLAN_FILENAME),
+                order_plan.to_string(),
+            )
+            .expect("Failed to write order plan");
+            std::fs::write(
+                self.get_full_path(ORDER_PLAN_JSON_FILENAME),
+                order_plan
+                    .to_json()
+                    .expect("Failed to serialize order plan"),
+            )
+            .expect("Failed to write order plan");
+        }
+        if self.factor_report {
+            self.factor_exposures.update_average();
+            export::to_yaml(
+                &self.get_full_path(FACTOR_REPORT_FILENAME),
+                &self.factor_exposures,
             );
         }
-        export::to_yaml(&self.get_full_path(PORTFOLIO_FILENAME), &self.portfolios);
     }
 
     fn draw_diagram(
@@ -138,17 +1502,95 @@ impl Backtesting {
         std::fs::create_dir_all(&self.config.portfolio_path).unwrap();
 
         for (stock_id, trade_series) in trade_stocks {
-            self.draw_trade_diagram(
-                &stock_id,
-                &self.get_stock_trade_info(&stock_id, &trade_series),
-            );
+            self.draw_trade_diagram(stock_id, &self.get_stock_trade_info(stock_id, trade_series));
         }
         self.draw_fund_diagram();
+        self.draw_return_distribution();
+        self.draw_benchmark_correlation();
+        self.render(
+            &exposure::ExposureReport::from_equity_series(&self.get_equity_series())
+                .to_plot(&self.config.diagram_style),
+            exposure::EXPOSURE_DIAGRAM_FILENAME,
+        );
+        if !self.trade_ledger.is_empty() {
+            std::fs::write(
+                self.get_full_path(contribution::CONTRIBUTION_REPORT_FILENAME),
+                contribution::ContributionReport::build(&self.trade_ledger)
+                    .to_html(&self.config.diagram_style),
+            )
+            .expect("Failed to write html");
+        }
+        if let Some(unit_economics) = self.get_unit_economics() {
+            std::fs::write(
+                self.get_full_path(economics::UNIT_ECONOMICS_REPORT_FILENAME),
+                unit_economics.to_html(),
+            )
+            .expect("Failed to write html");
+        }
+    }
+
+    /// Edge per trade before and after the costs of `cost_model`, re-priced from the trade ledger
+    /// of the last run.
+    pub fn get_unit_economics(&self) -> Option<economics::UnitEconomics> {
+        economics::UnitEconomics::build(
+            &self.trade_ledger,
+            self.slippage.as_ref().map_or(0.0, |slippage| slippage.rate),
+            &self.cost_model,
+            self.liquidity,
+            self.get_run_metrics().total_return,
+        )
+    }
+
+    fn draw_return_distribution(&self) {
+        let distribution = match distribution::ReturnDistribution::from_equity_series(
+            self.liquidity,
+            &self.get_equity_series(),
+        ) {
+            Some(distribution) => distribution,
+            None => return,
+        };
+
+        std::fs::write(
+            self.get_full_path(distribution::RETURN_DISTRIBUTION_FILENAME),
+            distribution.to_html(&self.config.diagram_style),
+        )
+        .expect("Failed to write html");
+    }
+
+    fn draw_benchmark_correlation(&self) {
+        let benchmark_id = match &self.benchmark_id {
+            Some(benchmark_id) => benchmark_id,
+            None => return,
+        };
+        // A week back, so the first day of the run has a benchmark return too.
+        let benchmark_records = self
+            .backend_op
+            .query_by_range(
+                benchmark_id,
+                self.start_date - chrono::Duration::days(7),
+                self.end_date,
+            )
+            .unwrap();
+        let benchmark_correlation = match correlation::BenchmarkCorrelation::build(
+            benchmark_id,
+            &distribution::get_daily_returns(self.liquidity, &self.get_equity_series()),
+            &benchmark_records,
+            view::BETA_PERIOD,
+        ) {
+            Some(benchmark_correlation) => benchmark_correlation,
+            None => return,
+        };
+
+        self.render(
+            &benchmark_correlation.to_plot(&self.config.diagram_style),
+            correlation::BENCHMARK_CORRELATION_FILENAME,
+        );
     }
 
     fn draw_trade_diagram(&self, stock_id: &str, trade_info: &StockTradeInfo) {
+        let style = &self.config.diagram_style;
         let mut plot = plotly::Plot::new();
-        let mut layout = plotly::Layout::new();
+        let mut layout = style.get_layout();
         let mut date_series = Vec::new();
         let mut open_series = Vec::new();
         let mut high_series = Vec::new();
@@ -164,64 +1606,184 @@ impl Backtesting {
         }
 
         for (hold_date, settle_date) in &trade_info.trade_series {
-            layout.add_shape(
-                plotly::layout::Shape::new()
-                    .x_ref("x")
-                    .y_ref("paper")
-                    .shape_type(plotly::layout::ShapeType::Rect)
-                    .x0(hold_date.to_string())
-                    .y0(0)
-                    .x1(settle_date.to_string())
-                    .y1(1)
-                    .fill_color(plotly::common::color::NamedColor::BurlyWood)
-                    .opacity(0.5)
-                    .layer(plotly::layout::ShapeLayer::Below)
-                    .line(plotly::layout::ShapeLine::new().width(0.)),
-            );
+            layout.add_shape(style.get_hold_shape(*hold_date, *settle_date));
         }
 
-        let trace = Box::new(
-            plotly::Candlestick::new(
-                date_series.clone(),
-                open_series.clone(),
-                high_series.clone(),
-                low_series.clone(),
-                close_series.clone(),
-            )
-            .name(&stock_id),
+        let trace = style.get_candlestick(
+            stock_id,
+            date_series,
+            open_series,
+            high_series,
+            low_series,
+            close_series,
         );
 
         plot.add_trace(trace);
         plot.set_layout(layout);
-        plot.write_html(self.get_full_path(&(stock_id.to_owned() + ".html")));
+        self.render(&plot, &(stock_id.to_owned() + ".html"));
     }
 
     fn draw_fund_diagram(&self) {
         let mut plot = plotly::Plot::new();
         let mut date_series = Vec::new();
         let mut fund_series = Vec::new();
+        let mut unhedged_fund_series = Vec::new();
         let mut text_series = Vec::new();
+        let mut equity_map = HashMap::new();
+        let mut last_equity = self.liquidity as i64;
 
-        for portfolio in &self.portfolios {
-            let mut fund = portfolio.liquidity;
+        for (index, equity_point) in self.get_equity_series().iter().enumerate() {
+            let equity = equity_point.equity as i64;
+            let mut lines = vec![format!(
+                "P&L: {:+} ({:+.2}%)",
+                equity - last_equity,
+                (equity - last_equity) as f64 / last_equity.max(1) as f64 * 100.0
+            )];
 
-            for stock_info in &portfolio.stocks_hold {
-                fund += stock_info.price * stock_info.num;
-            }
-            for stock_info in &portfolio.stocks_selected {
-                fund += stock_info.price * stock_info.num;
+            if let Some(portfolio) = self.portfolios.get(index) {
+                lines.extend(self.get_trade_lines(portfolio));
             }
-            date_series.push(portfolio.date);
-            fund_series.push(fund);
-            text_series.push(portfolio.to_string());
+            date_series.push(equity_point.date);
+            fund_series.push(equity);
+            unhedged_fund_series.push(equity_point.unhedged_equity);
+            text_series.push(lines.join("<br>"));
+            equity_map.insert(equity_point.date, equity);
+            last_equity = equity;
         }
 
-        let trace = plotly::Scatter::new(date_series, fund_series)
+        let trace = plotly::Scatter::new(date_series.clone(), fund_series)
             .text_array(text_series)
             .mode(plotly::common::Mode::Lines)
-            .name("Fund");
+            .name(&format!("Fund (marked at {})", self.valuation_policy));
+        let mut trade_date_series = Vec::new();
+        let mut trade_fund_series = Vec::new();
+        let mut trade_text_series = Vec::new();
+        let mut links = Vec::new();
+
+        for trade_record in &self.trade_ledger {
+            trade_date_series.push(trade_record.settle_date);
+            trade_fund_series.push(*equity_map.get(&trade_record.settle_date).unwrap_or(&0));
+            trade_text_series.push(format!(
+                "{} {} ~ {}<br>{} x {} -> {} ({:+.2}%)",
+                trade_record.stock_id,
+                trade_record.hold_date,
+                trade_record.settle_date,
+                trade_record.num,
+                trade_record.hold_price,
+                trade_record.settle_price,
+                trade_record.get_return()
+            ));
+            links.push(trade_record.stock_id.to_owned() + ".html");
+        }
+
+        let trade_trace = plotly::Scatter::new(trade_date_series, trade_fund_series)
+            .text_array(trade_text_series)
+            .mode(plotly::common::Mode::Markers)
+            .name("Settled trades");
 
         plot.add_trace(trace);
-        plot.write_html(self.get_full_path(FUND_DIAGRAM_FILENAME));
+        plot.add_trace(trade_trace);
+        if self.hedge.is_some() {
+            let unhedged_trace = plotly::Scatter::new(date_series, unhedged_fund_series)
+                .mode(plotly::common::Mode::Lines)
+                .name("Fund (unhedged)");
+
+            plot.add_trace(unhedged_trace);
+        }
+        plot.set_layout(self.config.diagram_style.get_layout());
+        diagram::render_with_links(
+            &plot,
+            &self.config.diagram_style,
+            &diagram::Output::Html(self.get_full_path(FUND_DIAGRAM_FILENAME)),
+            1,
+            &links,
+        );
+    }
+
+    /// Hover lines describing the trades and risk events of a day.
+    fn get_trade_lines(&self, portfolio: &decision::Portfolio) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        for stock_info in &portfolio.stocks_settled {
+            let trade_return = self
+                .trade_ledger
+                .iter()
+                .find(|trade_record| {
+                    trade_record.stock_id == stock_info.stock_id
+                        && trade_record.settle_date == portfolio.date
+                })
+                .map(|trade_record| trade_record.get_return());
+
+            lines.push(match trade_return {
+                Some(trade_return) => format!(
+                    "Settled {} x {} @ {} ({:+.2}%)",
+                    stock_info.stock_id, stock_info.num, stock_info.price, trade_return
+                ),
+                None => format!(
+                    "Settled {} x {} @ {}",
+                    stock_info.stock_id, stock_info.num, stock_info.price
+                ),
+            });
+        }
+        for stock_info in &portfolio.stocks_selected {
+            lines.push(format!(
+                "Entered {} x {} @ {} ({})",
+                stock_info.stock_id,
+                stock_info.num,
+                stock_info.price,
+                stock_info.num * stock_info.price
+            ));
+        }
+        for risk_event in &portfolio.risk_events {
+            lines.push(risk_event.to_string());
+        }
+        if !portfolio.stocks_hold.is_empty() {
+            let stock_ids: Vec<&str> = portfolio
+                .stocks_hold
+                .iter()
+                .map(|stock_info| stock_info.stock_id.as_str())
+                .collect();
+
+            lines.push(format!("Holding {}", stock_ids.join(", ")));
+        }
+        lines
+    }
+
+    fn draw_chunked_fund_diagram(&self, report: &ChunkedReport) {
+        let mut plot = plotly::Plot::new();
+        let mut layout = self.config.diagram_style.get_layout();
+        let date_series: Vec<chrono::NaiveDate> =
+            report.equity_curve.iter().map(|(date, _)| *date).collect();
+        let fund_series: Vec<f64> = report
+            .equity_curve
+            .iter()
+            .map(|(_, equity)| *equity)
+            .collect();
+
+        for chunk in report.chunks.iter().skip(1) {
+            layout.add_shape(
+                plotly::layout::Shape::new()
+                    .x_ref("x")
+                    .y_ref("paper")
+                    .shape_type(plotly::layout::ShapeType::Line)
+                    .x0(chunk.start_date.to_string())
+                    .y0(0)
+                    .x1(chunk.start_date.to_string())
+                    .y1(1)
+                    .line(
+                        plotly::layout::ShapeLine::new()
+                            .width(1.)
+                            .color(plotly::common::color::NamedColor::Gray),
+                    ),
+            );
+        }
+
+        let trace = plotly::Scatter::new(date_series, fund_series)
+            .mode(plotly::common::Mode::Lines)
+            .name("Fund (stitched)");
+
+        plot.add_trace(trace);
+        plot.set_layout(layout);
+        self.render(&plot, CHUNKED_FUND_DIAGRAM_FILENAME);
     }
 }
@@ -0,0 +1,397 @@
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+
+use crate::crawler::{crawler, finmind};
+use crate::storage::backend;
+use crate::strategy::schema;
+
+/// The manifest of a bulk crawl is kept in the portfolio path.
+pub const CRAWL_MANIFEST_FILENAME: &str = "crawl_manifest.json";
+/// Requests between saves of the manifest. Chunks stored since the last save are crawled again
+/// when the crawl is resumed, which only overwrites the same records.
+const SAVE_INTERVAL: usize = 100;
+/// Wait after the crawler reports its request quota used up, as `Utils::update_raw_data` does.
+const QUOTA_WAIT: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug)]
+pub enum Error {
+    Backend(backend::Error),
+    Crawler(crawler::Error),
+    /// The manifest could not be read or written.
+    Io(std::io::Error),
+    Json(serde_json::Error),
+}
+
+impl From<backend::Error> for Error {
+    fn from(err: backend::Error) -> Error {
+        Error::Backend(err)
+    }
+}
+
+impl From<crawler::Error> for Error {
+    fn from(err: crawler::Error) -> Error {
+        Error::Crawler(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::Json(err)
+    }
+}
+
+/// Earliest date of a full history crawl, before any record FinMind has.
+pub fn get_history_start_date() -> chrono::NaiveDate {
+    chrono::NaiveDate::from_ymd_opt(1990, 1, 1).unwrap()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ChunkState {
+    Pending,
+    /// Crawled and stored, with the number of records.
+    Done(usize),
+    /// Failed with the error; retried when the crawl is resumed.
+    Failed(String),
+}
+
+/// One request of a bulk crawl: a stock over at most a calendar year.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrawlChunk {
+    pub stock_id: String,
+    pub start_date: chrono::NaiveDate,
+    pub end_date: chrono::NaiveDate,
+    pub state: ChunkState,
+}
+
+/// Every request of a bulk crawl and how far it got, saved as the crawl goes so one cut short by
+/// an error or Ctrl-C continues where it stopped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrawlManifest {
+    pub start_date: chrono::NaiveDate,
+    pub end_date: chrono::NaiveDate,
+    pub chunks: Vec<CrawlChunk>,
+}
+
+impl CrawlManifest {
+    /// Plans the requests for every stock of `stock_list` over `start_date..=end_date`.
+    pub fn plan(
+        stock_list: &[String],
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> Self {
+        let chunks = stock_list
+            .iter()
+            .flat_map(|stock_id| {
+                finmind::split_by_year(start_date, end_date)
+                    .into_iter()
+                    .map(|(start_date, end_date)| CrawlChunk {
+                        stock_id: stock_id.to_owned(),
+                        start_date,
+                        end_date,
+                        state: ChunkState::Pending,
+                    })
+            })
+            .collect();
+
+        CrawlManifest {
+            start_date,
+            end_date,
+            chunks,
+        }
+    }
+
+    /// Manifest stored at `path`, or `None` when there is no such file.
+    pub fn load(path: &str) -> Result<Option<Self>, Error> {
+        match std::fs::read_to_string(path) {
+            Ok(data) => Ok(Some(serde_json::from_str(&data)?)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(Error::Io(err)),
+        }
+    }
+
+    /// Stores the manifest at `path`, through a temporary file so an interrupted save keeps the
+    /// previous one.
+    pub fn save(&self, path: &str) -> Result<(), Error> {
+        let temp_path = format!("{}.tmp", path);
+        let data = serde_json::to_string(self)?;
+
+        std::fs::write(&temp_path, data)?;
+        Ok(std::fs::rename(&temp_path, path)?)
+    }
+
+    pub fn get_done_count(&self) -> usize {
+        self.chunks
+            .iter()
+            .filter(|chunk| matches!(chunk.state, ChunkState::Done(_)))
+            .count()
+    }
+
+    pub fn get_failed_count(&self) -> usize {
+        self.chunks
+            .iter()
+            .filter(|chunk| matches!(chunk.state, ChunkState::Failed(_)))
+            .count()
+    }
+}
+
+type CrawlResult = Result<Vec<schema::RawData>, crawler::Error>;
+
+/// Crawls the chunks of a manifest on worker threads. The crawler is not shared across threads,
+/// so each worker builds its own with `build`; records are stored from the calling thread.
+pub struct BulkCrawl {
+    pub backend_op: Rc<dyn backend::BackendOp>,
+    pub threads: usize,
+    pub quota_wait: Duration,
+}
+
+impl BulkCrawl {
+    pub fn new(backend_op: Rc<dyn backend::BackendOp>, threads: usize) -> Self {
+        BulkCrawl {
+            backend_op,
+            threads,
+            quota_wait: QUOTA_WAIT,
+        }
+    }
+
+    /// Crawls the chunks of `manifest` not done yet, saving it to `path` as it goes. Before
+    /// each round, `get_remaining` tells how many requests the quota has left, so no more are
+    /// sent than it allows; a round that still runs into the limit waits `quota_wait` and
+    /// retries what was refused.
+    pub fn run<B, C>(
+        &self,
+        manifest: &mut CrawlManifest,
+        path: &str,
+        build: B,
+        get_remaining: Option<&dyn Fn() -> Result<u64, crawler::Error>>,
+    ) -> Result<(), Error>
+    where
+        B: Fn(usize) -> C + Send + Sync + 'static,
+        C: crawler::Crawler,
+    {
+        let mut queue: VecDeque<usize> = manifest
+            .chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| !matches!(chunk.state, ChunkState::Done(_)))
+            .map(|(index, _)| index)
+            .collect();
+        let (job_sender, job_receiver) = mpsc::channel::<(usize, crawler::Args)>();
+        let (result_sender, results) = mpsc::channel::<(usize, CrawlResult)>();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let build = Arc::new(build);
+        let workers: Vec<thread::JoinHandle<()>> = (0..self.threads.max(1))
+            .map(|worker| {
+                let job_receiver = job_receiver.clone();
+                let result_sender = result_sender.clone();
+                let build = build.clone();
+
+                thread::spawn(move || {
+                    let crawler = build(worker);
+
+                    loop {
+                        let job = job_receiver.lock().unwrap().recv();
+                        let (index, args) = match job {
+                            Ok(job) => job,
+                            Err(_) => break,
+                        };
+
+                        if result_sender
+                            .send((index, crawler.get_stock_data(&args)))
+                            .is_err()
+                        {
+                            break;
+                        }
+                    }
+                })
+            })
+            .collect();
+        let progress = ProgressBar::new(manifest.chunks.len() as u64);
+
+        progress.set_style(
+            ProgressStyle::with_template("{elapsed_precise} [{bar:40}] {pos}/{len} requests {msg}")
+                .unwrap()
+                .progress_chars("=> "),
+        );
+        progress.set_position(manifest.get_done_count() as u64);
+
+        let result = loop {
+            if queue.is_empty() {
+                break Ok(());
+            }
+
+            let round_size = match get_remaining {
+                Some(get_remaining) => match get_remaining() {
+                    Ok(remaining) => (remaining as usize).min(queue.len()),
+                    Err(crawler::Error::RateLimitReached) => 0,
+                    Err(err) => break Err(Error::Crawler(err)),
+                },
+                None => queue.len(),
+            };
+
+            if round_size == 0 {
+                progress.println("Request quota used up, waiting for it to reset...");
+                thread::sleep(self.quota_wait);
+                continue;
+            }
+
+            for index in queue.drain(..round_size) {
+                let chunk = &manifest.chunks[index];
+
+                job_sender
+                    .send((
+                        index,
+                        crawler::Args {
+                            stock_id: chunk.stock_id.to_owned(),
+                            start_date: chunk.start_date,
+                            end_date: chunk.end_date,
+                        },
+                    ))
+                    .unwrap();
+            }
+
+            let mut rate_limited = false;
+            let mut round_result = Ok(());
+
+            for count in 1..=round_size {
+                let (index, result) = results.recv().unwrap();
+                let chunk = &mut manifest.chunks[index];
+
+                match result {
+                    Ok(records) => {
+                        let records: Vec<(String, schema::RawData)> = records
+                            .into_iter()
+                            .map(|record| (chunk.stock_id.to_owned(), record))
+                            .collect();
+
+                        if let Err(err) = self.backend_op.batch_insert(&records) {
+                            round_result = Err(Error::Backend(err));
+                            continue;
+                        }
+                        chunk.state = ChunkState::Done(records.len());
+                        progress.inc(1);
+                    }
+                    Err(crawler::Error::RateLimitReached) => {
+                        rate_limited = true;
+                        queue.push_back(index);
+                    }
+                    Err(err) => {
+                        progress.println(format!(
+                            "Failed to crawl {} {}..{}: {:?}",
+                            chunk.stock_id, chunk.start_date, chunk.end_date, err
+                        ));
+                        chunk.state = ChunkState::Failed(format!("{:?}", err));
+                    }
+                }
+                if count % SAVE_INTERVAL == 0 || count == round_size {
+                    if let Err(err) = manifest.save(path) {
+                        round_result = Err(err);
+                    }
+                }
+            }
+            if round_result.is_err() {
+                break round_result;
+            }
+            if rate_limited {
+                progress.println("Request quota used up, waiting for it to reset...");
+                thread::sleep(self.quota_wait);
+            }
+        };
+
+        // Closing the channel stops the workers once the queue is drained.
+        drop(job_sender);
+        for worker in workers {
+            let _ = worker.join();
+        }
+        progress.finish();
+        result
+    }
+}
+
+#[cfg(test)]
+mod bulk_test {
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    use crate::crawler::crawler;
+    use crate::storage::backend::BackendOp;
+    use crate::storage::memory::MemoryBackend;
+    use crate::strategy::schema;
+
+    use super::{BulkCrawl, ChunkState, CrawlManifest};
+
+    #[test]
+    fn bulk_crawl_check() {
+        let get_date =
+            |year, month, day| chrono::NaiveDate::from_ymd_opt(year, month, day).unwrap();
+        let path = std::env::temp_dir()
+            .join("veronica_bulk_crawl_check.json")
+            .to_string_lossy()
+            .to_string();
+        let mut manifest = CrawlManifest::plan(
+            &["0050".to_owned(), "2330".to_owned()],
+            get_date(2020, 6, 1),
+            get_date(2021, 3, 1),
+        );
+
+        assert_eq!(manifest.chunks.len(), 4);
+        assert_eq!(manifest.chunks[1].start_date, get_date(2021, 1, 1));
+
+        let backend_op = Rc::new(MemoryBackend::new());
+        let mut bulk_crawl = BulkCrawl::new(backend_op.clone(), 2);
+
+        bulk_crawl.quota_wait = Duration::ZERO;
+
+        // 2330 cannot be crawled at first, and the quota allows three requests at a time.
+        let build = |fail: bool| {
+            move |_| {
+                let mut mock_crawler = crawler::MockCrawler::new();
+
+                mock_crawler.expect_get_stock_data().returning(move |args| {
+                    match fail && args.stock_id == "2330" {
+                        true => Err(crawler::Error::BadRequest),
+                        false => Ok(vec![schema::RawData {
+                            date: args.start_date,
+                            ..Default::default()
+                        }]),
+                    }
+                });
+                mock_crawler
+            }
+        };
+        let get_remaining = || Ok(3);
+
+        bulk_crawl
+            .run(&mut manifest, &path, build(true), Some(&get_remaining))
+            .unwrap();
+        assert_eq!(manifest.get_done_count(), 2);
+        assert_eq!(manifest.get_failed_count(), 2);
+
+        let mut manifest = CrawlManifest::load(&path).unwrap().unwrap();
+
+        assert_eq!(manifest.get_failed_count(), 2);
+        bulk_crawl
+            .run(&mut manifest, &path, build(false), None)
+            .unwrap();
+        assert!(manifest
+            .chunks
+            .iter()
+            .all(|chunk| chunk.state == ChunkState::Done(1)));
+        assert!(backend_op
+            .query("2330", get_date(2021, 1, 1))
+            .unwrap()
+            .is_some());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
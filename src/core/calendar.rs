@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+
+use chrono::Datelike;
+use serde::{Deserialize, Serialize};
+
+use crate::storage::{backend, calendar};
+
+/// Trading calendar of the exchange: full sessions on weekdays and none on weekends, except on
+/// the dates with a stored session.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TradingCalendar {
+    sessions: BTreeMap<chrono::NaiveDate, calendar::SessionKind>,
+}
+
+impl TradingCalendar {
+    pub fn from_sessions(sessions: &[calendar::Session]) -> Self {
+        TradingCalendar {
+            sessions: sessions
+                .iter()
+                .map(|session| (session.date, session.kind))
+                .collect(),
+        }
+    }
+
+    /// Calendar with the sessions stored from `start_date` to `end_date`.
+    pub fn load(
+        calendar_op: &dyn calendar::CalendarOp,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> Result<Self, backend::Error> {
+        Ok(TradingCalendar::from_sessions(
+            &calendar_op.list_sessions(start_date, end_date)?,
+        ))
+    }
+
+    pub fn get_session_kind(&self, date: chrono::NaiveDate) -> calendar::SessionKind {
+        match self.sessions.get(&date) {
+            Some(kind) => *kind,
+            None => match date.weekday() {
+                chrono::Weekday::Sat | chrono::Weekday::Sun => calendar::SessionKind::Closed,
+                _ => calendar::SessionKind::Full,
+            },
+        }
+    }
+
+    /// Whether stocks trade on `date`, in a full or a half-day session.
+    pub fn is_trading_day(&self, date: chrono::NaiveDate) -> bool {
+        matches!(
+            self.get_session_kind(date),
+            calendar::SessionKind::Full | calendar::SessionKind::HalfDay
+        )
+    }
+
+    /// Trading days after `start_date` up to and including `end_date`.
+    pub fn count_trading_days(
+        &self,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> usize {
+        start_date
+            .iter_days()
+            .skip(1)
+            .take_while(|date| *date <= end_date)
+            .filter(|date| self.is_trading_day(*date))
+            .count()
+    }
+
+    /// First trading day after `date`.
+    pub fn next_trading_day(&self, date: chrono::NaiveDate) -> chrono::NaiveDate {
+        date.iter_days()
+            .skip(1)
+            .find(|date| self.is_trading_day(*date))
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod calendar_test {
+    use crate::storage::calendar::{CalendarOp, Session, SessionKind};
+    use crate::storage::memory::MemoryBackend;
+
+    use super::TradingCalendar;
+
+    #[test]
+    fn trading_calendar_check() {
+        let backend = MemoryBackend::new();
+        let date = |day| chrono::NaiveDate::from_ymd_opt(2024, 2, day).unwrap();
+
+        backend
+            .insert_sessions(&[
+                Session {
+                    date: date(5),
+                    kind: SessionKind::HalfDay,
+                    note: String::new(),
+                },
+                Session {
+                    date: date(6),
+                    kind: SessionKind::SettlementOnly,
+                    note: String::new(),
+                },
+                Session {
+                    date: date(7),
+                    kind: SessionKind::Closed,
+                    note: String::new(),
+                },
+                Session {
+                    date: date(17),
+                    kind: SessionKind::Full,
+                    note: "make-up day".to_owned(),
+                },
+            ])
+            .unwrap();
+
+        let calendar = TradingCalendar::load(&backend, date(1), date(29)).unwrap();
+
+        assert!(calendar.is_trading_day(date(5)));
+        assert!(!calendar.is_trading_day(date(6)));
+        assert!(!calendar.is_trading_day(date(10)));
+        assert!(calendar.is_trading_day(date(17)));
+        assert_eq!(calendar.get_session_kind(date(8)), SessionKind::Full);
+        assert_eq!(calendar.next_trading_day(date(5)), date(8));
+        // 5th to 19th: the 8th, 9th, 12th to 16th, the 17th and the 19th.
+        assert_eq!(calendar.count_trading_days(date(5), date(19)), 9);
+        assert_eq!(calendar.count_trading_days(date(19), date(5)), 0);
+        assert!(backend.delete_session(date(7)).unwrap());
+        assert!(!backend.delete_session(date(7)).unwrap());
+        assert_eq!(backend.list_sessions(date(6), date(17)).unwrap().len(), 2);
+    }
+}
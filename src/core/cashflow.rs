@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+/// An external deposit (positive) or withdrawal (negative).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CashFlow {
+    pub date: chrono::NaiveDate,
+    pub amount: i64,
+}
+
+/// External cash flows applied to liquidity during a run. A flow falling on a non-trading day is
+/// applied on the next trading day.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CashFlowSchedule {
+    /// Deposited on the first trading day of every month but the first one of the run.
+    #[serde(default)]
+    pub monthly_contribution: i64,
+    #[serde(default)]
+    pub flows: Vec<CashFlow>,
+}
+
+impl CashFlowSchedule {
+    /// Sum of the flows after `last_date` up to and including `date`.
+    pub fn get_amount(&self, last_date: chrono::NaiveDate, date: chrono::NaiveDate) -> i64 {
+        let mut amount: i64 = self
+            .flows
+            .iter()
+            .filter(|cash_flow| cash_flow.date > last_date && cash_flow.date <= date)
+            .map(|cash_flow| cash_flow.amount)
+            .sum();
+
+        if chrono::Datelike::month(&last_date) != chrono::Datelike::month(&date)
+            || chrono::Datelike::year(&last_date) != chrono::Datelike::year(&date)
+        {
+            amount += self.monthly_contribution;
+        }
+        amount
+    }
+}
+
+/// Time-weighted return, in percent, of an equity series starting from `start_equity`. Each
+/// `(equity, cash_flow)` point holds the equity at the end of the day and the flow applied at its
+/// start, so flows do not count as gains or losses.
+pub fn time_weighted_return(start_equity: u32, points: &[(u32, i64)]) -> f64 {
+    let mut growth = 1.0;
+    let mut last_equity = start_equity as f64;
+
+    for (equity, cash_flow) in points {
+        let base = last_equity + *cash_flow as f64;
+
+        if base > 0.0 {
+            growth *= *equity as f64 / base;
+        }
+        last_equity = *equity as f64;
+    }
+    (growth - 1.0) * 100.0
+}
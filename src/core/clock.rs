@@ -0,0 +1,97 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use crate::crawler::crawler;
+
+/// Source of the current time. Code that needs "today" asks its clock rather than the system, so
+/// the same code runs live, replayed over history or driven by a test.
+pub trait Clock {
+    fn now(&self) -> chrono::NaiveDateTime;
+
+    fn today(&self) -> chrono::NaiveDate {
+        self.now().date()
+    }
+}
+
+/// Time of the system in the market's timezone, for live operation, so that today is the
+/// exchange's trading date wherever the host is.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> chrono::NaiveDateTime {
+        chrono::Utc::now()
+            .with_timezone(&crawler::MARKET_TIMEZONE)
+            .naive_local()
+    }
+}
+
+/// Clock standing still until it is set or advanced, for replays and tests.
+pub struct ManualClock {
+    now: Cell<chrono::NaiveDateTime>,
+}
+
+impl ManualClock {
+    pub fn new(now: chrono::NaiveDateTime) -> Self {
+        ManualClock {
+            now: Cell::new(now),
+        }
+    }
+
+    /// Clock at the start of `date`.
+    pub fn from_date(date: chrono::NaiveDate) -> Self {
+        ManualClock::new(date.and_hms_opt(0, 0, 0).unwrap())
+    }
+
+    pub fn set(&self, now: chrono::NaiveDateTime) {
+        self.now.set(now);
+    }
+
+    /// Moves the clock to the start of `date`.
+    pub fn set_date(&self, date: chrono::NaiveDate) {
+        self.set(date.and_hms_opt(0, 0, 0).unwrap());
+    }
+
+    pub fn advance(&self, duration: chrono::Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> chrono::NaiveDateTime {
+        self.now.get()
+    }
+}
+
+/// System clock, or a clock standing at the start of `as_of` to replay that day.
+pub fn get_clock(as_of: Option<chrono::NaiveDate>) -> Rc<dyn Clock> {
+    match as_of {
+        Some(date) => Rc::new(ManualClock::from_date(date)),
+        None => Rc::new(SystemClock),
+    }
+}
+
+#[cfg(test)]
+mod clock_test {
+    use super::{Clock, ManualClock};
+
+    #[test]
+    fn manual_clock_check() {
+        let clock = ManualClock::from_date(chrono::NaiveDate::from_ymd_opt(2024, 7, 1).unwrap());
+
+        clock.advance(chrono::Duration::hours(25));
+        assert_eq!(
+            clock.today(),
+            chrono::NaiveDate::from_ymd_opt(2024, 7, 2).unwrap()
+        );
+        assert_eq!(
+            clock.now().time(),
+            chrono::NaiveTime::from_hms_opt(1, 0, 0).unwrap()
+        );
+
+        clock.set_date(chrono::NaiveDate::from_ymd_opt(2024, 6, 28).unwrap());
+        assert_eq!(
+            clock.today(),
+            chrono::NaiveDate::from_ymd_opt(2024, 6, 28).unwrap()
+        );
+    }
+}
@@ -1,20 +1,29 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fmt::Debug;
 use std::rc::Rc;
 
 use serde::{Deserialize, Serialize};
 
 use crate::crawler::crawler;
+use crate::crosssection::{breadth, crosssection};
 use crate::storage::backend;
-use crate::strategy::schema;
-use crate::strategy::strategy;
+use crate::strategy::{dca, schema, strategy};
+
+use super::{
+    calendar, cashflow, clock, fill, halt, hedge, lot, order, pairs, position, reconcile, regime,
+    risk,
+};
 
 #[derive(Debug)]
 pub enum Error {
     Backend(backend::Error),
     Crawler(crawler::Error),
     Strategy(strategy::Error),
+    Regime(regime::Error),
+    CrossSection(crosssection::Error),
     BackendRecordNotFound,
+    /// The day to decide for is past the clock's today, so its data cannot be known yet.
+    FutureDate(chrono::NaiveDate),
 }
 
 impl From<backend::Error> for Error {
@@ -35,20 +44,171 @@ impl From<strategy::Error> for Error {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl From<regime::Error> for Error {
+    fn from(err: regime::Error) -> Error {
+        Error::Regime(err)
+    }
+}
+
+impl From<crosssection::Error> for Error {
+    fn from(err: crosssection::Error) -> Error {
+        Error::CrossSection(err)
+    }
+}
+
+/// What the engine does on a day when some, but not all, held stocks have no record, e.g.
+/// because one of them is suspended. A day on which none of the held stocks has a record is
+/// treated as a market holiday and skipped under every policy.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MissingDataPolicy {
+    /// Produce no portfolio for the day.
+    SkipDay,
+    /// Value the stock at its last known price.
+    CarryForward,
+    /// Carry the last known price forward, and settle at it once the stock has been missing for
+    /// `days` consecutive trading days.
+    SettleAfter { days: usize },
+}
+
+impl std::fmt::Display for MissingDataPolicy {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MissingDataPolicy::SkipDay => fmt.write_str("skip day"),
+            MissingDataPolicy::CarryForward => fmt.write_str("carry forward"),
+            MissingDataPolicy::SettleAfter { days } => write!(fmt, "settle after {} days", days),
+        }
+    }
+}
+
+/// Price held positions are marked to market at. Positions bought on the day are valued at their
+/// fill price whatever the policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ValuationPolicy {
+    /// Midpoint of the day's high and low, the price fills are modelled at.
+    #[default]
+    Mid,
+    /// Close of the day.
+    Close,
+    /// Price of the position's latest fill, kept until it trades again.
+    LastTrade,
+}
+
+impl std::fmt::Display for ValuationPolicy {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ValuationPolicy::Mid => fmt.write_str("mid"),
+            ValuationPolicy::Close => fmt.write_str("close"),
+            ValuationPolicy::LastTrade => fmt.write_str("last trade"),
+        }
+    }
+}
+
+/// Price of the bar orders fill at, before slippage and tick rounding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum FillPolicy {
+    /// Midpoint of the day's high and low.
+    #[default]
+    Mid,
+    /// Open of the day, for strategies acting on the opening print such as gaps.
+    Open,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StockInfo {
     pub stock_id: String,
     pub num: u32,
     pub price: u32,
+    /// Set on settled positions only.
+    #[serde(default)]
+    pub settle_reason: Option<strategy::SettleReason>,
+    /// Set on held positions whose trading is halted.
+    #[serde(default)]
+    pub halt: Option<halt::Halt>,
+    /// Set on held positions the trader attached a note or a manual hold to.
+    #[serde(default)]
+    pub position_note: Option<position::PositionNote>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Portfolio {
     pub date: chrono::NaiveDate,
     pub stocks_selected: Vec<StockInfo>,
     pub stocks_hold: Vec<StockInfo>,
     pub stocks_settled: Vec<StockInfo>,
     pub liquidity: u32,
+    pub risk_events: Vec<risk::RiskEvent>,
+    pub hedge: Option<hedge::HedgePosition>,
+    /// External cash flow applied to the liquidity at the start of the day.
+    #[serde(default)]
+    pub cash_flow: i64,
+    /// Lots closed on the day, first in, first out.
+    #[serde(default)]
+    pub realized_lots: Vec<lot::RealizedLot>,
+    /// P&L of the open lots at the day's prices.
+    #[serde(default)]
+    pub unrealized_pnl: i64,
+    /// Stocks whose trades on the broker statement differ from the recommended ones.
+    #[serde(default)]
+    pub reconciliations: Vec<reconcile::Reconciliation>,
+    /// Pair positions open at the end of the day.
+    #[serde(default)]
+    pub pair_positions: Vec<pairs::PairPosition>,
+    /// Pair positions closed on the day.
+    #[serde(default)]
+    pub pairs_settled: Vec<pairs::PairPosition>,
+}
+
+impl Portfolio {
+    pub fn equity(&self) -> u32 {
+        let mut equity = self.liquidity + self.invested();
+
+        if let Some(hedge_position) = &self.hedge {
+            equity += hedge_position.value();
+        }
+
+        let pairs_value: i64 = self
+            .pair_positions
+            .iter()
+            .map(|pair_position| pair_position.value())
+            .sum();
+
+        (equity as i64 + pairs_value).max(0) as u32
+    }
+
+    pub fn realized_pnl(&self) -> i64 {
+        self.realized_lots
+            .iter()
+            .map(|realized_lot| realized_lot.get_pnl())
+            .sum()
+    }
+
+    /// Market value of the stocks held, leaving out halted ones excluded from equity.
+    pub fn invested(&self) -> u32 {
+        let mut invested = 0;
+
+        for stock_info in &self.stocks_hold {
+            if let Some(halt::Halt {
+                excluded_from_equity: true,
+                ..
+            }) = stock_info.halt
+            {
+                continue;
+            }
+            invested += stock_info.price * stock_info.num;
+        }
+        for stock_info in &self.stocks_selected {
+            invested += stock_info.price * stock_info.num;
+        }
+        invested
+    }
+
+    /// Equity as if the hedge had never been traded.
+    pub fn unhedged_equity(&self) -> i64 {
+        match &self.hedge {
+            Some(hedge_position) => self.equity() as i64 - hedge_position.pnl,
+            None => self.equity() as i64,
+        }
+    }
 }
 
 impl std::default::Default for Portfolio {
@@ -59,6 +219,14 @@ impl std::default::Default for Portfolio {
             stocks_hold: Vec::new(),
             stocks_settled: Vec::new(),
             liquidity: 0,
+            risk_events: Vec::new(),
+            hedge: None,
+            cash_flow: 0,
+            realized_lots: Vec::new(),
+            unrealized_pnl: 0,
+            reconciliations: Vec::new(),
+            pair_positions: Vec::new(),
+            pairs_settled: Vec::new(),
         }
     }
 }
@@ -80,17 +248,129 @@ impl std::fmt::Display for Portfolio {
 
         fmt.write_str("Stocks: ")?;
         fmt.write_str(&stock_ids.join(", "))?;
+
+        if !self.risk_events.is_empty() {
+            let events: Vec<String> = self
+                .risk_events
+                .iter()
+                .map(|risk_event| risk_event.to_string())
+                .collect();
+
+            fmt.write_str("; Events: ")?;
+            fmt.write_str(&events.join(", "))?;
+        }
         Ok(())
     }
 }
 
+/// Position held at the end of a run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Holding {
+    pub stock_id: String,
+    pub hold_date: chrono::NaiveDate,
+    pub num: u32,
+    /// Average price the position was bought at.
+    pub hold_price: u32,
+    /// Price the position was last valued at.
+    pub price: u32,
+    pub lots: Vec<lot::TaxLot>,
+}
+
+/// Holdings and liquidity a run ends with, which a following run can start from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EndState {
+    pub date: chrono::NaiveDate,
+    pub liquidity: u32,
+    pub holdings: Vec<Holding>,
+}
+
+impl EndState {
+    pub fn equity(&self) -> u32 {
+        self.liquidity
+            + self
+                .holdings
+                .iter()
+                .map(|holding| holding.price * holding.num)
+                .sum::<u32>()
+    }
+}
+
 pub struct Decision {
     pub crawler: Rc<dyn crawler::Crawler>,
     pub backend_op: Rc<dyn backend::BackendOp>,
     pub strategy: Rc<dyn strategy::StrategyAPI>,
     pub stocks_hold_num: usize,
     pub liquidity: u32,
+    pub circuit_breaker: Option<risk::CircuitBreaker>,
+    /// Loss from the previous trading day's equity, in percent, above which no new positions are opened.
+    pub max_daily_loss: Option<f64>,
+    pub regime_filter: Option<regime::RegimeFilter>,
+    pub breadth_filter: Option<breadth::BreadthFilter>,
+    pub hedge: Option<hedge::Hedge>,
+    /// Pairs traded on the z-score of their spread, next to the strategy's positions. The end
+    /// state carries open pair positions as cash, at their last value.
+    pub pairs_trading: Option<pairs::PairsTrading>,
+    pub missing_data_policy: MissingDataPolicy,
+    pub valuation_policy: ValuationPolicy,
+    pub fill_policy: FillPolicy,
+    pub halt_policy: Option<halt::HaltPolicy>,
+    /// Defers fills on bars locked at the price limit to the next trading day.
+    pub price_limit: Option<fill::PriceLimit>,
+    pub order_size: Option<fill::OrderSize>,
+    pub slippage: Option<fill::Slippage>,
+    pub tick_size: Option<fill::TickSize>,
+    pub volume_cap: Option<fill::VolumeCap>,
+    pub scale_out: Option<fill::ScaleOut>,
+    pub scale_in: Option<fill::ScaleIn>,
+    pub cash_flows: Option<cashflow::CashFlowSchedule>,
+    /// Buys a fixed amount of an instrument on a schedule, for the dollar-cost averaging
+    /// baseline, see `dca::Strategy`.
+    pub dca: Option<dca::Plan>,
+    /// Trading days between a signal and the orders it leads to, as when orders are entered by
+    /// hand some time after the scores come out. Entries and exits act on the scores and exit
+    /// checks of that many trading days before.
+    pub signal_latency: usize,
+    /// Stocks entries are picked from, e.g. the output of a screener or a model, instead of the
+    /// crawler's whole stock list. They are still scored and ranked by the strategy.
+    pub candidates: Option<Vec<String>>,
+    /// Notes and manual holds of positions, by stock id. Positions on manual hold are only ever
+    /// closed by hand.
+    pub position_notes: position::PositionNotes,
+    /// Trades executed at the broker; the days it reports replace the engine's fills, so the
+    /// following decisions start from the real holdings.
+    pub statement: Option<reconcile::Statement>,
+    /// Today of the decision; days after it are refused. Backtests move it along with the
+    /// simulated day.
+    pub clock: Rc<dyn clock::Clock>,
+    /// Sessions of the exchange. Days it has no trading session on are skipped, whatever data
+    /// there is for them.
+    pub calendar: Option<calendar::TradingCalendar>,
+    /// Trading days after which a position is settled, counted on the calendar, or on weekdays
+    /// without one.
+    pub max_hold_days: Option<usize>,
     stocks_hold: HashMap<String, (chrono::NaiveDate, u32)>,
+    peak_equity: u32,
+    last_equity: u32,
+    pause_days_left: usize,
+    hedge_position: hedge::HedgePosition,
+    hedge_cash_flow: i64,
+    pair_positions: Vec<pairs::PairPosition>,
+    last_prices: HashMap<String, u32>,
+    /// Price of the latest fill of each stock.
+    fill_prices: HashMap<String, u32>,
+    missing_days: HashMap<String, usize>,
+    deferred_settles: HashMap<String, strategy::SettleReason>,
+    /// Trading days left to finish the exits being scaled out.
+    scale_out_days: HashMap<String, usize>,
+    /// Shares left to buy, and trading days left to buy them in, of the entries being scaled in.
+    scale_in_entries: HashMap<String, (u32, usize)>,
+    /// Shares of entries the volume cap kept from filling, bought on the following days.
+    pending_entries: HashMap<String, u32>,
+    /// Day of the last purchase of the dollar-cost averaging plan.
+    last_dca_date: Option<chrono::NaiveDate>,
+    last_date: Option<chrono::NaiveDate>,
+    trading_dates: VecDeque<chrono::NaiveDate>,
+    lot_book: lot::LotBook,
 }
 
 impl Decision {
@@ -105,22 +385,115 @@ impl Decision {
             strategy: strategy,
             stocks_hold_num: 5,
             liquidity: 200000,
+            circuit_breaker: None,
+            max_daily_loss: None,
+            regime_filter: None,
+            breadth_filter: None,
+            hedge: None,
+            pairs_trading: None,
+            missing_data_policy: MissingDataPolicy::SkipDay,
+            valuation_policy: ValuationPolicy::Mid,
+            fill_policy: FillPolicy::Mid,
+            halt_policy: None,
+            price_limit: None,
+            order_size: None,
+            slippage: None,
+            tick_size: None,
+            volume_cap: None,
+            scale_out: None,
+            scale_in: None,
+            cash_flows: None,
+            dca: None,
+            signal_latency: 0,
+            candidates: None,
+            position_notes: position::PositionNotes::new(),
+            statement: None,
+            clock: Rc::new(clock::SystemClock),
+            calendar: None,
+            max_hold_days: None,
             stocks_hold: HashMap::new(),
+            peak_equity: 0,
+            last_equity: 0,
+            pause_days_left: 0,
+            hedge_position: hedge::HedgePosition::default(),
+            hedge_cash_flow: 0,
+            pair_positions: Vec::new(),
+            last_prices: HashMap::new(),
+            fill_prices: HashMap::new(),
+            missing_days: HashMap::new(),
+            deferred_settles: HashMap::new(),
+            scale_out_days: HashMap::new(),
+            scale_in_entries: HashMap::new(),
+            pending_entries: HashMap::new(),
+            last_dca_date: None,
+            last_date: None,
+            trading_dates: VecDeque::new(),
+            lot_book: lot::LotBook::default(),
         }
     }
-    fn get_select_stocks(&self, assess_date: chrono::NaiveDate) -> Result<Vec<String>, Error> {
-        let stock_list = self.crawler.get_stock_list().unwrap_or(vec![]);
+    /// Scores every stock of the stock list on `assess_date`, best first.
+    pub fn get_stock_scores(
+        &self,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<Vec<(String, strategy::Score)>, Error> {
+        let stock_list = match &self.candidates {
+            Some(candidates) => candidates.clone(),
+            None => self.crawler.get_stock_list().unwrap_or(vec![]),
+        };
+
+        self.get_candidate_scores(assess_date, &stock_list)
+    }
+
+    /// Scores each of `candidates` on `assess_date`, best first.
+    pub fn get_candidate_scores(
+        &self,
+        assess_date: chrono::NaiveDate,
+        candidates: &[String],
+    ) -> Result<Vec<(String, strategy::Score)>, Error> {
         let mut stock_scores: Vec<(String, strategy::Score)> = Vec::new();
-        let mut stocks_selected = Vec::new();
 
-        for stock_id in stock_list {
+        for stock_id in candidates {
             stock_scores.push((
                 stock_id.clone(),
-                self.strategy.analyze(&stock_id, assess_date)?,
+                self.strategy.analyze(stock_id, assess_date)?,
             ));
         }
 
         stock_scores.sort_by(|lhs, rhs| rhs.1.cmp(&lhs.1));
+        Ok(stock_scores)
+    }
+
+    /// Trading day whose signals are acted on today, `None` until `signal_latency` trading days
+    /// have passed.
+    fn get_signal_date(&self) -> Option<chrono::NaiveDate> {
+        match self.trading_dates.len() > self.signal_latency {
+            true => self.trading_dates.front().cloned(),
+            false => None,
+        }
+    }
+
+    fn get_select_stocks(&self) -> Result<Vec<String>, Error> {
+        let signal_date = match self.get_signal_date() {
+            Some(signal_date) => signal_date,
+            None => return Ok(Vec::new()),
+        };
+
+        Ok(self.select_by_scores(self.get_stock_scores(signal_date)?))
+    }
+
+    /// Stocks among `candidates` worth entering on `assess_date`, best scored first and as many
+    /// as the free slots allow, for candidates supplied from outside the stock list.
+    pub fn get_select_stocks_from(
+        &self,
+        assess_date: chrono::NaiveDate,
+        candidates: &[String],
+    ) -> Result<Vec<String>, Error> {
+        Ok(self.select_by_scores(self.get_candidate_scores(assess_date, candidates)?))
+    }
+
+    /// Best scored stocks not held yet, as many as the free slots allow.
+    fn select_by_scores(&self, stock_scores: Vec<(String, strategy::Score)>) -> Vec<String> {
+        let mut stocks_selected = Vec::new();
 
         for (stock_id, score) in stock_scores.iter() {
             if self.stocks_hold.len() + stocks_selected.len() == self.stocks_hold_num {
@@ -139,18 +512,49 @@ impl Decision {
             }
         }
 
-        Ok(stocks_selected)
+        stocks_selected
     }
 
-    fn get_settle_stocks(&self, assess_date: chrono::NaiveDate) -> Result<Vec<String>, Error> {
+    fn is_max_hold_reached(&self, hold_date: chrono::NaiveDate) -> bool {
+        let (max_hold_days, assess_date) = match (self.max_hold_days, self.trading_dates.back()) {
+            (Some(max_hold_days), Some(assess_date)) => (max_hold_days, *assess_date),
+            _ => return false,
+        };
+        let hold_days = match &self.calendar {
+            Some(calendar) => calendar.count_trading_days(hold_date, assess_date),
+            None => calendar::TradingCalendar::default().count_trading_days(hold_date, assess_date),
+        };
+
+        hold_days >= max_hold_days
+    }
+
+    fn get_settle_stocks(&self) -> Result<Vec<(String, strategy::SettleReason)>, Error> {
         let mut stocks_settled = Vec::new();
+        let signal_date = self.get_signal_date();
 
         for (stock_id, (hold_date, _)) in &self.stocks_hold {
-            if self
-                .strategy
-                .settle_check(stock_id, *hold_date, assess_date)?
+            if self.missing_days.contains_key(stock_id) {
+                continue;
+            }
+            if let Some(settle_reason) = self.deferred_settles.get(stock_id) {
+                stocks_settled.push((stock_id.to_owned(), *settle_reason));
+                continue;
+            }
+            if self.is_max_hold_reached(*hold_date) {
+                stocks_settled.push((stock_id.to_owned(), strategy::SettleReason::MaxHoldDays));
+                continue;
+            }
+            // Signals from before the entry was filled cannot close it.
+            let signal_date = match signal_date {
+                Some(signal_date) if signal_date > *hold_date => signal_date,
+                _ => continue,
+            };
+
+            if let Some(settle_reason) =
+                self.strategy
+                    .settle_check(stock_id, *hold_date, signal_date)?
             {
-                stocks_settled.push(stock_id.to_owned());
+                stocks_settled.push((stock_id.to_owned(), settle_reason));
             }
         }
 
@@ -162,28 +566,107 @@ impl Decision {
         assess_date: chrono::NaiveDate,
         portfolio: &mut Portfolio,
     ) -> Result<(), Error> {
-        for stock_id in self.get_settle_stocks(assess_date)? {
-            let stock_num = self
-                .stocks_hold
-                .get(&stock_id)
-                .ok_or(Error::BackendRecordNotFound)?
-                .1;
-            let record = self
-                .backend_op
-                .query(&stock_id, assess_date)?
-                .ok_or(Error::BackendRecordNotFound)?;
-            let price = ((record.high + record.low) / 2.0) as u32;
+        let stocks_hold = &self.stocks_hold;
 
-            portfolio.stocks_settled.push(StockInfo {
+        self.deferred_settles
+            .retain(|stock_id, _| stocks_hold.contains_key(stock_id));
+        self.scale_out_days
+            .retain(|stock_id, _| stocks_hold.contains_key(stock_id));
+        for (stock_id, settle_reason) in self.get_settle_stocks()? {
+            self.settle_stock(assess_date, &stock_id, settle_reason, portfolio)?;
+        }
+
+        portfolio.liquidity = self.liquidity;
+        Ok(())
+    }
+
+    /// Sells `stock_id` for `settle_reason` at the fill price of the day. Holdings on manual
+    /// hold are kept, and sales the price limit locks or the volume cap cuts short are deferred
+    /// to the following days.
+    fn settle_stock(
+        &mut self,
+        assess_date: chrono::NaiveDate,
+        stock_id: &str,
+        settle_reason: strategy::SettleReason,
+        portfolio: &mut Portfolio,
+    ) -> Result<(), Error> {
+        if position::is_manual_hold(&self.position_notes, stock_id) {
+            self.deferred_settles.remove(stock_id);
+            self.scale_in_entries.remove(stock_id);
+            self.scale_out_days.remove(stock_id);
+            portfolio
+                .risk_events
+                .push(risk::RiskEvent::SettleOverridden {
+                    stock_id: stock_id.to_owned(),
+                    settle_reason,
+                });
+            return Ok(());
+        }
+
+        let stock_num = self
+            .stocks_hold
+            .get(stock_id)
+            .ok_or(Error::BackendRecordNotFound)?
+            .1;
+        let record = self
+            .backend_op
+            .query(stock_id, assess_date)?
+            .ok_or(Error::BackendRecordNotFound)?;
+
+        if self.is_locked(&record, order::Side::Sell) {
+            self.deferred_settles
+                .insert(stock_id.to_owned(), settle_reason);
+            portfolio.risk_events.push(risk::RiskEvent::FillDeferred {
                 stock_id: stock_id.to_owned(),
-                num: stock_num,
-                price: price,
+                side: order::Side::Sell,
             });
-            self.liquidity += stock_num * price;
-            self.stocks_hold.remove(&stock_id);
+            return Ok(());
         }
 
-        portfolio.liquidity = self.liquidity;
+        let price = self.get_fill_price(&record, order::Side::Sell);
+        let order_num = self.get_tranche_num(stock_id, stock_num);
+        let settle_num = self.get_fillable_num(order_num, &record);
+
+        self.pending_entries.remove(stock_id);
+        self.scale_in_entries.remove(stock_id);
+        if settle_num < stock_num {
+            self.deferred_settles
+                .insert(stock_id.to_owned(), settle_reason);
+        }
+        if settle_num < order_num {
+            portfolio.risk_events.push(risk::RiskEvent::PartialFill {
+                stock_id: stock_id.to_owned(),
+                side: order::Side::Sell,
+                num: settle_num,
+                remaining: stock_num - settle_num,
+            });
+            if settle_num == 0 {
+                return Ok(());
+            }
+        }
+
+        portfolio.stocks_settled.push(StockInfo {
+            stock_id: stock_id.to_owned(),
+            num: settle_num,
+            price,
+            settle_reason: Some(settle_reason),
+            halt: None,
+            position_note: None,
+        });
+        self.liquidity += settle_num * price;
+        self.fill_prices.insert(stock_id.to_owned(), price);
+        if settle_num < stock_num {
+            if let Some((_, num)) = self.stocks_hold.get_mut(stock_id) {
+                *num -= settle_num;
+            }
+            if let Some(days_left) = self.scale_out_days.get_mut(stock_id) {
+                *days_left = days_left.saturating_sub(1).max(1);
+            }
+            return Ok(());
+        }
+        self.stocks_hold.remove(stock_id);
+        self.deferred_settles.remove(stock_id);
+        self.scale_out_days.remove(stock_id);
         Ok(())
     }
 
@@ -193,9 +676,12 @@ impl Decision {
         portfolio: &mut Portfolio,
     ) -> Result<(), Error> {
         for stock_id in self.stocks_hold.keys().cloned() {
-            let mut data = self.backend_op.query(&stock_id, assess_date)?;
-            let record = data.get_or_insert(schema::RawData::default());
+            let price = match self.backend_op.query(&stock_id, assess_date)? {
+                Some(record) => self.get_valuation_price(&stock_id, &record),
+                None => *self.last_prices.get(&stock_id).unwrap_or(&0),
+            };
 
+            self.last_prices.insert(stock_id.to_owned(), price);
             portfolio.stocks_hold.push(StockInfo {
                 stock_id: stock_id.to_owned(),
                 num: self
@@ -203,7 +689,10 @@ impl Decision {
                     .get(&stock_id)
                     .ok_or(Error::BackendRecordNotFound)?
                     .1,
-                price: ((record.high + record.low) / 2.0) as u32,
+                price,
+                settle_reason: None,
+                halt: self.get_halt(&stock_id),
+                position_note: self.position_notes.get(&stock_id).cloned(),
             });
         }
 
@@ -216,25 +705,96 @@ impl Decision {
         assess_date: chrono::NaiveDate,
         portfolio: &mut Portfolio,
     ) -> Result<(), Error> {
-        let stocks_selected = self.get_select_stocks(assess_date)?;
+        let stocks_selected = self.get_select_stocks()?;
 
         if !stocks_selected.is_empty() {
-            let invest_max_per_stock = self.liquidity / stocks_selected.len() as u32;
+            let reserve = match &self.hedge {
+                Some(hedge) => (self.liquidity as f64 * hedge.get_reserve_ratio()) as u32,
+                None => 0,
+            };
+            // Cash the tranches still to come of scaled in entries are bought with.
+            let committed: u32 = self
+                .scale_in_entries
+                .iter()
+                .map(|(stock_id, (num, _))| num * self.last_prices.get(stock_id).unwrap_or(&0))
+                .sum();
+            let invest_max_per_stock =
+                self.liquidity.saturating_sub(reserve + committed) / stocks_selected.len() as u32;
 
             for stock_id in stocks_selected {
                 let record = self
                     .backend_op
                     .query(&stock_id, assess_date)?
                     .ok_or(Error::BackendRecordNotFound)?;
-                let price = ((record.high + record.low) / 2.0) as u32;
-                let stock_num = invest_max_per_stock / price;
+
+                if self.is_locked(&record, order::Side::Buy) {
+                    portfolio.risk_events.push(risk::RiskEvent::FillDeferred {
+                        stock_id: stock_id.to_owned(),
+                        side: order::Side::Buy,
+                    });
+                    continue;
+                }
+
+                let price = self.get_fill_price(&record, order::Side::Buy);
+                let mut stock_num = invest_max_per_stock / price;
+
+                if let Some(order_size) = &self.order_size {
+                    stock_num = match order_size.adjust(stock_num, price) {
+                        Some(stock_num) => stock_num,
+                        None => {
+                            portfolio.risk_events.push(risk::RiskEvent::OrderSkipped {
+                                stock_id: stock_id.to_owned(),
+                                num: stock_num,
+                                price,
+                            });
+                            continue;
+                        }
+                    };
+                }
+
+                let target_num = stock_num;
+
+                if let Some(scale_in) = &self.scale_in {
+                    stock_num = scale_in.get_tranche(stock_num, scale_in.days, self.get_lot_size());
+                }
+
+                let fill_num = self.get_fillable_num(stock_num, &record);
+
+                if fill_num < stock_num {
+                    portfolio.risk_events.push(risk::RiskEvent::PartialFill {
+                        stock_id: stock_id.to_owned(),
+                        side: order::Side::Buy,
+                        num: fill_num,
+                        remaining: stock_num - fill_num,
+                    });
+                    // An entry that fills nothing today is dropped rather than carried over.
+                    if fill_num == 0 {
+                        continue;
+                    }
+                    self.pending_entries
+                        .insert(stock_id.to_owned(), stock_num - fill_num);
+                }
+                if let Some(scale_in) = &self.scale_in {
+                    if target_num > stock_num {
+                        self.scale_in_entries.insert(
+                            stock_id.to_owned(),
+                            (target_num - stock_num, scale_in.days.saturating_sub(1)),
+                        );
+                    }
+                }
+                stock_num = fill_num;
 
                 portfolio.stocks_selected.push(StockInfo {
                     stock_id: stock_id.to_owned(),
                     num: stock_num,
                     price: price,
+                    settle_reason: None,
+                    halt: None,
+                    position_note: self.position_notes.get(&stock_id).cloned(),
                 });
                 self.liquidity -= stock_num * price;
+                self.last_prices.insert(stock_id.to_owned(), price);
+                self.fill_prices.insert(stock_id.to_owned(), price);
                 self.stocks_hold.insert(stock_id, (assess_date, stock_num));
             }
         }
@@ -243,119 +803,3060 @@ impl Decision {
         Ok(())
     }
 
-    fn has_trading_data(&self, assess_date: chrono::NaiveDate) -> Result<bool, Error> {
-        for stock_id in self.stocks_hold.keys().cloned() {
-            if self.backend_op.query(&stock_id, assess_date)?.is_none() {
-                return Ok(false);
+    /// Buys more of the entries the volume cap kept from filling, as far as the day's volume and
+    /// the cash allow. The shares bought are listed with the day's selected stocks.
+    fn handle_pending_entries(
+        &mut self,
+        assess_date: chrono::NaiveDate,
+        portfolio: &mut Portfolio,
+    ) -> Result<(), Error> {
+        let mut stock_ids: Vec<String> = self.pending_entries.keys().cloned().collect();
+
+        stock_ids.sort();
+        for stock_id in stock_ids {
+            let remaining = self.pending_entries[&stock_id];
+            let record = match self.backend_op.query(&stock_id, assess_date)? {
+                Some(record) => record,
+                None => continue,
+            };
+
+            if !self.stocks_hold.contains_key(&stock_id)
+                || self.is_locked(&record, order::Side::Buy)
+            {
+                continue;
+            }
+
+            let price = self.get_fill_price(&record, order::Side::Buy);
+            let affordable = match price {
+                0 => remaining,
+                price => remaining.min(self.liquidity / price),
+            };
+            let fill_num = self.get_fillable_num(affordable, &record);
+
+            if affordable < remaining {
+                self.pending_entries.remove(&stock_id);
+            } else if fill_num < remaining {
+                self.pending_entries
+                    .insert(stock_id.to_owned(), remaining - fill_num);
+                portfolio.risk_events.push(risk::RiskEvent::PartialFill {
+                    stock_id: stock_id.to_owned(),
+                    side: order::Side::Buy,
+                    num: fill_num,
+                    remaining: remaining - fill_num,
+                });
+            } else {
+                self.pending_entries.remove(&stock_id);
+            }
+            if fill_num == 0 {
+                continue;
+            }
+
+            portfolio.stocks_selected.push(StockInfo {
+                stock_id: stock_id.to_owned(),
+                num: fill_num,
+                price,
+                settle_reason: None,
+                halt: None,
+                position_note: self.position_notes.get(&stock_id).cloned(),
+            });
+            self.liquidity -= fill_num * price;
+            self.last_prices.insert(stock_id.to_owned(), price);
+            self.fill_prices.insert(stock_id.to_owned(), price);
+            if let Some((_, num)) = self.stocks_hold.get_mut(&stock_id) {
+                *num += fill_num;
             }
         }
-        Ok(true)
+
+        self.pending_entries
+            .retain(|stock_id, _| self.stocks_hold.contains_key(stock_id));
+        portfolio.liquidity = self.liquidity;
+        Ok(())
     }
 
-    pub fn calc_portfolio(
+    /// Buys the amount of the dollar-cost averaging plan on the first trading day of each of its
+    /// periods, adding to the position held. A purchase the price limit or the cash keeps from
+    /// filling is tried again on the following days of the period.
+    fn handle_dca(
         &mut self,
         assess_date: chrono::NaiveDate,
-    ) -> Result<Option<Portfolio>, Error> {
-        if !self.has_trading_data(assess_date)? {
-            return Ok(None);
+        portfolio: &mut Portfolio,
+    ) -> Result<(), Error> {
+        let plan = match &self.dca {
+            Some(plan) => plan.clone(),
+            None => return Ok(()),
+        };
+
+        if self
+            .last_dca_date
+            .is_some_and(|last_dca_date| !plan.frequency.is_new_period(last_dca_date, assess_date))
+        {
+            return Ok(());
         }
 
-        let mut portfolio = Portfolio {
-            date: assess_date,
-            stocks_selected: Vec::new(),
-            stocks_hold: Vec::new(),
-            stocks_settled: Vec::new(),
-            liquidity: 0,
+        let record = match self.backend_op.query(&plan.instrument_id, assess_date)? {
+            Some(record) => record,
+            None => return Ok(()),
         };
 
-        self.handle_settle_stocks(assess_date, &mut portfolio)?;
-        self.handle_hold_stocks(assess_date, &mut portfolio)?;
-        self.handle_selected_stocks(assess_date, &mut portfolio)?;
-        Ok(Some(portfolio))
+        if self.is_locked(&record, order::Side::Buy) {
+            portfolio.risk_events.push(risk::RiskEvent::FillDeferred {
+                stock_id: plan.instrument_id.to_owned(),
+                side: order::Side::Buy,
+            });
+            return Ok(());
+        }
+
+        let price = self.get_fill_price(&record, order::Side::Buy);
+        let mut num = match price {
+            0 => 0,
+            price => plan.amount.min(self.liquidity) / price,
+        };
+
+        if let Some(order_size) = &self.order_size {
+            num = order_size.adjust(num, price).unwrap_or(0);
+        }
+        num = self.get_fillable_num(num, &record);
+        if num == 0 {
+            return Ok(());
+        }
+
+        portfolio.stocks_selected.push(StockInfo {
+            stock_id: plan.instrument_id.to_owned(),
+            num,
+            price,
+            settle_reason: None,
+            halt: None,
+            position_note: self.position_notes.get(&plan.instrument_id).cloned(),
+        });
+        self.liquidity -= num * price;
+        self.last_prices
+            .insert(plan.instrument_id.to_owned(), price);
+        self.fill_prices
+            .insert(plan.instrument_id.to_owned(), price);
+        self.stocks_hold
+            .entry(plan.instrument_id.to_owned())
+            .or_insert((assess_date, 0))
+            .1 += num;
+        self.last_dca_date = Some(assess_date);
+        portfolio.liquidity = self.liquidity;
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod decision_test {
-    use std::rc::Rc;
+    /// Buys the day's tranche of each entry being scaled in, as far as the cash allows, or
+    /// cancels the rest of the entry once the strategy no longer scores the stock as a buy. The
+    /// shares bought are listed with the day's selected stocks.
+    fn handle_scale_in_entries(
+        &mut self,
+        assess_date: chrono::NaiveDate,
+        portfolio: &mut Portfolio,
+    ) -> Result<(), Error> {
+        let scale_in = match &self.scale_in {
+            Some(scale_in) => scale_in.clone(),
+            None => return Ok(()),
+        };
+        let signal_date = match self.get_signal_date() {
+            Some(signal_date) => signal_date,
+            None => return Ok(()),
+        };
+        let mut stock_ids: Vec<String> = self.scale_in_entries.keys().cloned().collect();
 
-    use crate::core::decision::Decision;
-    use crate::crawler::crawler;
-    use crate::storage::backend;
-    use crate::strategy::{schema, strategy};
+        self.scale_in_entries
+            .retain(|stock_id, _| self.stocks_hold.contains_key(stock_id));
+        stock_ids.sort();
+        for stock_id in stock_ids {
+            let (remaining, days_left) = match self.scale_in_entries.get(&stock_id) {
+                Some(scale_in_entry) => *scale_in_entry,
+                None => continue,
+            };
 
-    #[test]
-    fn select_stocks_all_zero_score() {
-        let mut mock_crawler = crawler::MockCrawler::new();
-        let mut mock_backend_op = backend::MockBackendOp::new();
-        let mut mock_strategy = strategy::MockStrategyAPI::new();
+            if self.strategy.analyze(&stock_id, signal_date)?.point <= 0 {
+                self.scale_in_entries.remove(&stock_id);
+                portfolio.risk_events.push(risk::RiskEvent::EntryCancelled {
+                    stock_id: stock_id.to_owned(),
+                    remaining,
+                });
+                continue;
+            }
 
-        mock_crawler.expect_get_stock_list().returning(|| {
-            Ok(vec![
-                "0050".to_owned(),
-                "0051".to_owned(),
-                "0052".to_owned(),
-            ])
-        });
-        mock_backend_op
-            .expect_query()
-            .returning(|stock_id, _| match stock_id {
-                "0050" => {
-                    return Ok(Some(schema::RawData {
-                        ..Default::default()
-                    }))
-                }
-                "0051" => {
-                    return Ok(Some(schema::RawData {
-                        ..Default::default()
-                    }))
-                }
-                "0052" => {
-                    return Ok(Some(schema::RawData {
-                        ..Default::default()
-                    }))
-                }
-                _ => return Ok(None),
+            let record = match self.backend_op.query(&stock_id, assess_date)? {
+                Some(record) => record,
+                None => continue,
+            };
+
+            if self.is_locked(&record, order::Side::Buy) {
+                continue;
+            }
+
+            let price = self.get_fill_price(&record, order::Side::Buy);
+            let lot_size = self.get_lot_size();
+            let tranche = scale_in.get_tranche(remaining, days_left, lot_size);
+            let tranche = match price {
+                0 => tranche,
+                price => tranche.min(self.liquidity / price / lot_size * lot_size),
+            };
+            let fill_num = self.get_fillable_num(tranche, &record);
+
+            match remaining - fill_num {
+                0 => self.scale_in_entries.remove(&stock_id),
+                remaining => self.scale_in_entries.insert(
+                    stock_id.to_owned(),
+                    (remaining, days_left.saturating_sub(1).max(1)),
+                ),
+            };
+            if fill_num == 0 {
+                continue;
+            }
+
+            portfolio.stocks_selected.push(StockInfo {
+                stock_id: stock_id.to_owned(),
+                num: fill_num,
+                price,
+                settle_reason: None,
+                halt: None,
+                position_note: self.position_notes.get(&stock_id).cloned(),
             });
-        mock_strategy
-            .expect_analyze()
-            .returning(|stock_id, _| match stock_id {
-                "0050" => {
-                    return Ok(strategy::Score {
-                        point: 0,
-                        trading_volume: 0,
-                    })
-                }
-                "0051" => {
-                    return Ok(strategy::Score {
-                        point: 0,
-                        trading_volume: 0,
-                    })
+            self.liquidity -= fill_num * price;
+            self.last_prices.insert(stock_id.to_owned(), price);
+            self.fill_prices.insert(stock_id.to_owned(), price);
+            if let Some((_, num)) = self.stocks_hold.get_mut(&stock_id) {
+                *num += fill_num;
+            }
+        }
+
+        portfolio.liquidity = self.liquidity;
+        Ok(())
+    }
+
+    /// Applies the scheduled cash flows since the previous trading day. Withdrawals are capped at
+    /// the cash available, and the equity references of the risk checks move along with the
+    /// flows so they are not mistaken for gains or losses.
+    fn handle_cash_flows(&mut self, assess_date: chrono::NaiveDate, portfolio: &mut Portfolio) {
+        let last_date = self.last_date.replace(assess_date);
+        let (cash_flows, last_date) = match (&self.cash_flows, last_date) {
+            (Some(cash_flows), Some(last_date)) => (cash_flows, last_date),
+            _ => return,
+        };
+        let amount = cash_flows
+            .get_amount(last_date, assess_date)
+            .max(-(self.liquidity as i64));
+
+        self.liquidity = (self.liquidity as i64 + amount) as u32;
+        self.last_equity = (self.last_equity as i64 + amount).max(0) as u32;
+        self.peak_equity = (self.peak_equity as i64 + amount).max(0) as u32;
+        portfolio.cash_flow = amount;
+        portfolio.liquidity = self.liquidity;
+    }
+
+    /// Books the day's fills into the tax lots, sales first as they come first in the day, then
+    /// values the lots still open. The hedge is not part of the lots.
+    fn handle_tax_lots(&mut self, assess_date: chrono::NaiveDate, portfolio: &mut Portfolio) {
+        for stock_info in &portfolio.stocks_settled {
+            portfolio.realized_lots.extend(self.lot_book.sell(
+                &stock_info.stock_id,
+                assess_date,
+                stock_info.num,
+                stock_info.price,
+            ));
+        }
+        for stock_info in &portfolio.stocks_selected {
+            self.lot_book.buy(
+                &stock_info.stock_id,
+                assess_date,
+                stock_info.num,
+                stock_info.price,
+            );
+        }
+
+        // A stock added to, by the dollar-cost averaging plan or a scale-in tranche, is both held
+        // and selected; its cost basis covers both and is taken off once.
+        let mut values: HashMap<&str, i64> = HashMap::new();
+
+        for stock_info in portfolio
+            .stocks_hold
+            .iter()
+            .chain(&portfolio.stocks_selected)
+        {
+            *values.entry(&stock_info.stock_id).or_insert(0) +=
+                stock_info.num as i64 * stock_info.price as i64;
+        }
+        portfolio.unrealized_pnl = values
+            .iter()
+            .map(|(stock_id, value)| value - self.lot_book.get_cost_basis(stock_id))
+            .sum();
+    }
+
+    /// Shares of an order for `num` that fill on the bar of `record` under the volume cap, in
+    /// whole lots when the order size asks for them.
+    fn get_fillable_num(&self, num: u32, record: &schema::RawData) -> u32 {
+        let volume_cap = match &self.volume_cap {
+            Some(volume_cap) => volume_cap,
+            None => return num,
+        };
+        let num = volume_cap.cap(num, record);
+
+        match &self.order_size {
+            Some(order_size) => {
+                let lot_size = order_size.lot_policy.get_lot_size();
+
+                num / lot_size * lot_size
+            }
+            None => num,
+        }
+    }
+
+    /// Shares of the `num` held to sell today: the whole position, or the day's tranche when exits
+    /// are scaled out.
+    fn get_tranche_num(&mut self, stock_id: &str, num: u32) -> u32 {
+        let scale_out = match &self.scale_out {
+            Some(scale_out) => scale_out,
+            None => return num,
+        };
+        let days_left = *self
+            .scale_out_days
+            .entry(stock_id.to_owned())
+            .or_insert(scale_out.days);
+
+        scale_out.get_tranche(num, days_left, self.get_lot_size())
+    }
+
+    fn get_lot_size(&self) -> u32 {
+        match &self.order_size {
+            Some(order_size) => order_size.lot_policy.get_lot_size(),
+            None => 1,
+        }
+    }
+
+    /// Price `stock_id` is marked to market at on the bar of `record`.
+    fn get_valuation_price(&self, stock_id: &str, record: &schema::RawData) -> u32 {
+        let mid = ((record.high + record.low) / 2.0) as u32;
+
+        match self.valuation_policy {
+            ValuationPolicy::Mid => mid,
+            ValuationPolicy::Close => record.close as u32,
+            ValuationPolicy::LastTrade => *self.fill_prices.get(stock_id).unwrap_or(&mid),
+        }
+    }
+
+    /// Price a fill on `record` gets: the price of the bar the fill policy picks, moved against
+    /// the order by the slippage and rounded to the tick size.
+    fn get_fill_price(&self, record: &schema::RawData, side: order::Side) -> u32 {
+        let price = match self.fill_policy {
+            FillPolicy::Mid => (record.high + record.low) / 2.0,
+            FillPolicy::Open => record.open,
+        };
+        let price = match &self.slippage {
+            Some(slippage) => slippage.apply(price, side),
+            None => price,
+        };
+
+        match &self.tick_size {
+            Some(tick_size) => tick_size.round(price, side) as u32,
+            None => price as u32,
+        }
+    }
+
+    fn is_locked(&self, record: &schema::RawData, side: order::Side) -> bool {
+        match &self.price_limit {
+            Some(price_limit) => price_limit.is_locked(record, side),
+            None => false,
+        }
+    }
+
+    /// Liquidates the holdings once the equity falls too far from its peak, selling them the
+    /// way exits are sold. Holdings missing from the day's data, or already being sold, are
+    /// settled on the following days.
+    fn handle_circuit_breaker(
+        &mut self,
+        assess_date: chrono::NaiveDate,
+        portfolio: &mut Portfolio,
+    ) -> Result<(), Error> {
+        let circuit_breaker = match &self.circuit_breaker {
+            Some(circuit_breaker) => circuit_breaker.clone(),
+            None => return Ok(()),
+        };
+        let equity = portfolio.equity();
+
+        if !circuit_breaker.is_triggered(self.peak_equity, equity) {
+            self.peak_equity = self.peak_equity.max(equity);
+            return Ok(());
+        }
+
+        portfolio
+            .risk_events
+            .push(risk::RiskEvent::CircuitBreakerTriggered {
+                drawdown: risk::drawdown(self.peak_equity, equity),
+            });
+
+        let stock_ids: Vec<String> = portfolio
+            .stocks_hold
+            .iter()
+            .map(|stock_info| stock_info.stock_id.to_owned())
+            .collect();
+
+        for stock_id in stock_ids {
+            let settling = portfolio
+                .stocks_settled
+                .iter()
+                .any(|stock_info| stock_info.stock_id == stock_id);
+
+            if settling
+                || (self.missing_days.contains_key(&stock_id)
+                    && !position::is_manual_hold(&self.position_notes, &stock_id))
+            {
+                self.deferred_settles
+                    .insert(stock_id.to_owned(), strategy::SettleReason::CircuitBreaker);
+                portfolio.risk_events.push(risk::RiskEvent::FillDeferred {
+                    stock_id,
+                    side: order::Side::Sell,
+                });
+                continue;
+            }
+            self.settle_stock(
+                assess_date,
+                &stock_id,
+                strategy::SettleReason::CircuitBreaker,
+                portfolio,
+            )?;
+        }
+
+        let stocks_hold = &self.stocks_hold;
+
+        portfolio.stocks_hold.retain_mut(|stock_info| {
+            match stocks_hold.get(&stock_info.stock_id) {
+                Some((_, num)) => {
+                    stock_info.num = *num;
+                    true
                 }
-                "0052" => {
-                    return Ok(strategy::Score {
-                        point: 0,
-                        trading_volume: 0,
+                None => false,
+            }
+        });
+        portfolio.liquidity = self.liquidity;
+        self.pause_days_left = circuit_breaker.pause_days;
+        self.peak_equity = portfolio.equity();
+        Ok(())
+    }
+
+    fn is_entry_paused(&mut self, portfolio: &mut Portfolio) -> bool {
+        if self.pause_days_left == 0 {
+            return false;
+        }
+        self.pause_days_left -= 1;
+        portfolio.risk_events.push(risk::RiskEvent::EntriesPaused);
+        true
+    }
+
+    fn is_daily_loss_limit_reached(&self, portfolio: &mut Portfolio) -> bool {
+        let max_daily_loss = match self.max_daily_loss {
+            Some(max_daily_loss) => max_daily_loss,
+            None => return false,
+        };
+        let loss = risk::drawdown(self.last_equity, portfolio.equity());
+
+        if loss < max_daily_loss {
+            return false;
+        }
+        portfolio
+            .risk_events
+            .push(risk::RiskEvent::DailyLossLimitReached { loss });
+        true
+    }
+
+    fn assess_regime(
+        &self,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<Option<regime::Regime>, Error> {
+        match &self.regime_filter {
+            Some(regime_filter) => Ok(regime_filter.assess(&self.backend_op, assess_date)?),
+            None => Ok(None),
+        }
+    }
+
+    fn is_regime_favorable(&self, regime: &Option<regime::Regime>) -> bool {
+        match (&self.regime_filter, regime) {
+            (Some(regime_filter), Some(regime)) => regime_filter.allows_entry(regime),
+            _ => true,
+        }
+    }
+
+    fn is_regime_unfavorable(
+        &self,
+        regime: &Option<regime::Regime>,
+        portfolio: &mut Portfolio,
+    ) -> bool {
+        if self.is_regime_favorable(regime) {
+            return false;
+        }
+        if let Some(regime) = regime {
+            portfolio
+                .risk_events
+                .push(risk::RiskEvent::UnfavorableRegime {
+                    volatility_percentile: regime.volatility_percentile,
+                    trending: regime.trending,
+                });
+        }
+        true
+    }
+
+    fn is_breadth_low(
+        &self,
+        assess_date: chrono::NaiveDate,
+        portfolio: &mut Portfolio,
+    ) -> Result<bool, Error> {
+        let breadth_filter = match &self.breadth_filter {
+            Some(breadth_filter) => breadth_filter,
+            None => return Ok(false),
+        };
+        let stock_list = self.crawler.get_stock_list().unwrap_or(vec![]);
+        let cross_section = crosssection::CrossSection::new(self.backend_op.clone());
+        let breadth = match breadth::above_sma(
+            &cross_section,
+            &stock_list,
+            assess_date,
+            breadth_filter.sma_period,
+        )? {
+            Some(breadth) => breadth,
+            None => return Ok(false),
+        };
+
+        if breadth_filter.allows_entry(breadth) {
+            return Ok(false);
+        }
+        portfolio
+            .risk_events
+            .push(risk::RiskEvent::LowBreadth { breadth });
+        Ok(true)
+    }
+
+    fn handle_hedge_valuation(
+        &mut self,
+        assess_date: chrono::NaiveDate,
+        portfolio: &mut Portfolio,
+    ) -> Result<(), Error> {
+        let hedge = match &self.hedge {
+            Some(hedge) => hedge,
+            None => return Ok(()),
+        };
+
+        if let Some(record) = self.backend_op.query(&hedge.instrument_id, assess_date)? {
+            self.hedge_position.price = ((record.high + record.low) / 2.0) as u32;
+        }
+        self.hedge_position.instrument_id = hedge.instrument_id.to_owned();
+        self.hedge_position.pnl = self.hedge_cash_flow + self.hedge_position.value() as i64;
+        portfolio.hedge = Some(self.hedge_position.clone());
+        Ok(())
+    }
+
+    fn handle_hedge_rebalance(
+        &mut self,
+        assess_date: chrono::NaiveDate,
+        favorable_regime: bool,
+        portfolio: &mut Portfolio,
+    ) -> Result<(), Error> {
+        let hedge = match &self.hedge {
+            Some(hedge) => hedge,
+            None => return Ok(()),
+        };
+
+        if self.hedge_position.price == 0
+            || self
+                .backend_op
+                .query(&hedge.instrument_id, assess_date)?
+                .is_none()
+        {
+            return Ok(());
+        }
+
+        let exposure: u32 = portfolio
+            .stocks_hold
+            .iter()
+            .chain(portfolio.stocks_selected.iter())
+            .map(|stock_info| stock_info.num * stock_info.price)
+            .sum();
+        let target_value = exposure as f64 * hedge.get_ratio(favorable_regime) / 100.0;
+        let target_num = (target_value / self.hedge_position.price as f64) as u32;
+        let price = self.hedge_position.price;
+
+        if target_num > self.hedge_position.num {
+            let num = (target_num - self.hedge_position.num).min(self.liquidity / price);
+
+            self.liquidity -= num * price;
+            self.hedge_cash_flow -= (num * price) as i64;
+            self.hedge_position.num += num;
+        } else {
+            let num = self.hedge_position.num - target_num;
+
+            self.liquidity += num * price;
+            self.hedge_cash_flow += (num * price) as i64;
+            self.hedge_position.num -= num;
+        }
+
+        portfolio.liquidity = self.liquidity;
+        portfolio.hedge = Some(self.hedge_position.clone());
+        Ok(())
+    }
+
+    fn handle_pairs_valuation(
+        &mut self,
+        assess_date: chrono::NaiveDate,
+        portfolio: &mut Portfolio,
+    ) -> Result<(), Error> {
+        let mut index = 0;
+
+        while index < self.pair_positions.len() {
+            let mut records = Vec::new();
+            let mut prices = Vec::new();
+
+            for leg in &self.pair_positions[index].legs {
+                let record = self.backend_op.query(&leg.stock_id, assess_date)?;
+
+                prices.push(match &record {
+                    Some(record) => self.get_valuation_price(&leg.stock_id, record),
+                    None => leg.price,
+                });
+                records.push(record);
+            }
+            for (leg, price) in self.pair_positions[index].legs.iter_mut().zip(prices) {
+                leg.price = price;
+            }
+
+            // Once a short leg has lost what the pair is worth, the pair is closed on the day
+            // rather than left to lose more than it put up.
+            if self.pair_positions[index].value() > 0 || records.iter().any(Option::is_none) {
+                index += 1;
+                continue;
+            }
+
+            let mut pair_position = self.pair_positions.remove(index);
+
+            for (leg, record) in pair_position.legs.iter_mut().zip(records.iter().flatten()) {
+                leg.price = self.get_fill_price(record, leg.get_exit_side());
+            }
+            portfolio.risk_events.push(risk::RiskEvent::PairStoppedOut {
+                first_id: pair_position.pair.first_id.to_owned(),
+                second_id: pair_position.pair.second_id.to_owned(),
+                value: pair_position.value(),
+            });
+            self.settle_pair(pair_position, portfolio);
+        }
+
+        portfolio.pair_positions = self.pair_positions.clone();
+        Ok(())
+    }
+
+    /// Takes the value of the closed `pair_position` into the liquidity, a loss when a short
+    /// leg lost more than the pair put up. Liquidity cannot go below zero, so a loss beyond the
+    /// cash left is only taken up to it.
+    fn settle_pair(&mut self, pair_position: pairs::PairPosition, portfolio: &mut Portfolio) {
+        self.liquidity = (self.liquidity as i64 + pair_position.value()).max(0) as u32;
+        portfolio.liquidity = self.liquidity;
+        portfolio.pairs_settled.push(pair_position);
+    }
+
+    /// Closes the pair positions whose spread reverted and, when entries are allowed, opens
+    /// those whose spread strayed.
+    fn handle_pairs_trading(
+        &mut self,
+        assess_date: chrono::NaiveDate,
+        entry_allowed: bool,
+        portfolio: &mut Portfolio,
+    ) -> Result<(), Error> {
+        let pairs_trading = match &self.pairs_trading {
+            Some(pairs_trading) => pairs_trading.clone(),
+            None => return Ok(()),
+        };
+
+        for pair in &pairs_trading.pairs {
+            let records = match (
+                self.backend_op.query(&pair.first_id, assess_date)?,
+                self.backend_op.query(&pair.second_id, assess_date)?,
+            ) {
+                (Some(first), Some(second)) => [first, second],
+                _ => continue,
+            };
+            let z_score = match pairs::get_z_score(
+                self.backend_op.as_ref(),
+                pair,
+                pairs_trading.lookback,
+                assess_date,
+            )? {
+                Some(z_score) => z_score,
+                None => continue,
+            };
+
+            match self
+                .pair_positions
+                .iter()
+                .position(|pair_position| pair_position.pair == *pair)
+            {
+                Some(index) if z_score.abs() <= pairs_trading.exit_z => {
+                    let mut pair_position = self.pair_positions.remove(index);
+
+                    for (leg, record) in pair_position.legs.iter_mut().zip(&records) {
+                        leg.price = self.get_fill_price(record, leg.get_exit_side());
+                    }
+                    self.settle_pair(pair_position, portfolio);
+                }
+                // A pair stopped out today is not opened again until the next day.
+                None if entry_allowed
+                    && z_score.abs() >= pairs_trading.entry_z
+                    && !portfolio
+                        .pairs_settled
+                        .iter()
+                        .any(|pair_position| pair_position.pair == *pair) =>
+                {
+                    let first_value = self.liquidity as f64 * pairs_trading.leg_ratio / 100.0;
+                    let values = [first_value, first_value * pair.hedge_ratio.abs()];
+                    let mut pair_position = pairs::PairPosition::new(pair, assess_date, z_score);
+
+                    for ((leg, record), value) in
+                        pair_position.legs.iter_mut().zip(&records).zip(values)
+                    {
+                        leg.entry_price = self.get_fill_price(record, leg.get_entry_side());
+                        leg.price = leg.entry_price;
+                        if leg.entry_price > 0 {
+                            leg.num = (value / leg.entry_price as f64) as u32;
+                        }
+                    }
+                    if pair_position.legs.iter().any(|leg| leg.num == 0)
+                        || pair_position.cost() > self.liquidity
+                    {
+                        continue;
+                    }
+                    self.liquidity -= pair_position.cost();
+                    self.pair_positions.push(pair_position);
+                }
+                _ => {}
+            }
+        }
+
+        portfolio.liquidity = self.liquidity;
+        portfolio.pair_positions = self.pair_positions.clone();
+        Ok(())
+    }
+
+    /// Held stocks without a record on `assess_date`.
+    fn get_missing_stocks(&self, assess_date: chrono::NaiveDate) -> Result<Vec<String>, Error> {
+        let mut stocks_missing = Vec::new();
+
+        for stock_id in self.stocks_hold.keys().cloned() {
+            if self.backend_op.query(&stock_id, assess_date)?.is_none() {
+                stocks_missing.push(stock_id);
+            }
+        }
+        Ok(stocks_missing)
+    }
+
+    fn has_trading_data(&self, stocks_missing: &[String]) -> bool {
+        if stocks_missing.is_empty() {
+            return true;
+        }
+        match self.missing_data_policy {
+            MissingDataPolicy::SkipDay => false,
+            _ => stocks_missing.len() < self.stocks_hold.len(),
+        }
+    }
+
+    /// Counts how long each held stock has been missing, records it, and settles the stocks the
+    /// policy gives up on at their last known price.
+    fn handle_missing_stocks(
+        &mut self,
+        assess_date: chrono::NaiveDate,
+        stocks_missing: &[String],
+        portfolio: &mut Portfolio,
+    ) -> Result<(), Error> {
+        self.handle_resumed_stocks(assess_date, stocks_missing, portfolio)?;
+        for stock_id in stocks_missing {
+            let days = self.missing_days.entry(stock_id.to_owned()).or_insert(0);
+
+            *days += 1;
+            match &self.halt_policy {
+                Some(halt_policy) if halt_policy.is_halted(*days) => {
+                    portfolio.risk_events.push(risk::RiskEvent::TradingHalted {
+                        stock_id: stock_id.to_owned(),
+                        days: *days,
                     })
                 }
-                _ => return Ok(strategy::Score::default()),
+                _ => portfolio.risk_events.push(risk::RiskEvent::MissingData {
+                    stock_id: stock_id.to_owned(),
+                    days: *days,
+                    policy: self.missing_data_policy,
+                }),
+            }
+
+            let settle_days = match self.missing_data_policy {
+                MissingDataPolicy::SettleAfter { days } => days,
+                _ => continue,
+            };
+
+            if *days < settle_days || position::is_manual_hold(&self.position_notes, stock_id) {
+                continue;
+            }
+
+            let stock_num = match self.stocks_hold.remove(stock_id) {
+                Some((_, stock_num)) => stock_num,
+                None => continue,
+            };
+            let price = *self.last_prices.get(stock_id).unwrap_or(&0);
+
+            self.missing_days.remove(stock_id);
+            self.liquidity += stock_num * price;
+            portfolio.stocks_settled.push(StockInfo {
+                stock_id: stock_id.to_owned(),
+                num: stock_num,
+                price,
+                settle_reason: Some(strategy::SettleReason::Delisting),
+                halt: None,
+                position_note: None,
+            });
+        }
+        portfolio.liquidity = self.liquidity;
+        Ok(())
+    }
+
+    /// Stops counting the stocks that trade again, and settles the ones coming back from a halt
+    /// when the halt policy asks for it.
+    fn handle_resumed_stocks(
+        &mut self,
+        assess_date: chrono::NaiveDate,
+        stocks_missing: &[String],
+        portfolio: &mut Portfolio,
+    ) -> Result<(), Error> {
+        let stocks_resumed: Vec<(String, usize)> = self
+            .missing_days
+            .iter()
+            .filter(|(stock_id, _)| !stocks_missing.contains(stock_id))
+            .map(|(stock_id, days)| (stock_id.to_owned(), *days))
+            .collect();
+
+        for (stock_id, days) in stocks_resumed {
+            self.missing_days.remove(&stock_id);
+
+            let halt_policy = match &self.halt_policy {
+                Some(halt_policy) if halt_policy.is_halted(days) => halt_policy,
+                _ => continue,
+            };
+
+            portfolio.risk_events.push(risk::RiskEvent::TradingResumed {
+                stock_id: stock_id.to_owned(),
+                days,
+            });
+            if halt_policy.action != halt::HaltAction::SettleOnResume
+                || position::is_manual_hold(&self.position_notes, &stock_id)
+            {
+                continue;
+            }
+
+            let stock_num = match self.stocks_hold.remove(&stock_id) {
+                Some((_, stock_num)) => stock_num,
+                None => continue,
+            };
+            let record = self
+                .backend_op
+                .query(&stock_id, assess_date)?
+                .ok_or(Error::BackendRecordNotFound)?;
+            let price = self.get_fill_price(&record, order::Side::Sell);
+
+            self.liquidity += stock_num * price;
+            portfolio.stocks_settled.push(StockInfo {
+                stock_id: stock_id.to_owned(),
+                num: stock_num,
+                price,
+                settle_reason: Some(strategy::SettleReason::HaltResumed),
+                halt: None,
+                position_note: None,
+            });
+        }
+        portfolio.liquidity = self.liquidity;
+        Ok(())
+    }
+
+    /// Replaces the day's fills with the trades of the statement, stock by stock, when it reports
+    /// the day. Sales are capped at the shares held before the day; purchases beyond the cash,
+    /// e.g. paid with deposits the engine does not know of, leave it at zero.
+    fn handle_reconciliation(
+        &mut self,
+        assess_date: chrono::NaiveDate,
+        stocks_hold_before: &HashMap<String, (chrono::NaiveDate, u32)>,
+        portfolio: &mut Portfolio,
+    ) -> Result<(), Error> {
+        let executed_trades = match &self.statement {
+            Some(statement) => match statement.get_trades(assess_date) {
+                Some(executed_trades) => executed_trades.clone(),
+                None => return Ok(()),
+            },
+            None => return Ok(()),
+        };
+        let mut stock_ids: Vec<String> = portfolio
+            .stocks_selected
+            .iter()
+            .chain(&portfolio.stocks_settled)
+            .map(|stock_info| stock_info.stock_id.to_owned())
+            .chain(
+                executed_trades
+                    .iter()
+                    .map(|executed_trade| executed_trade.stock_id.to_owned()),
+            )
+            .collect();
+
+        stock_ids.sort();
+        stock_ids.dedup();
+        for stock_id in stock_ids {
+            let mut recommended: i64 = 0;
+
+            for stock_info in &portfolio.stocks_selected {
+                if stock_info.stock_id == stock_id {
+                    recommended += stock_info.num as i64;
+                    self.liquidity += stock_info.num * stock_info.price;
+                }
+            }
+            for stock_info in &portfolio.stocks_settled {
+                if stock_info.stock_id == stock_id {
+                    recommended -= stock_info.num as i64;
+                    self.liquidity -= stock_info.num * stock_info.price;
+                }
+            }
+            portfolio
+                .stocks_selected
+                .retain(|stock_info| stock_info.stock_id != stock_id);
+            portfolio
+                .stocks_settled
+                .retain(|stock_info| stock_info.stock_id != stock_id);
+            portfolio
+                .stocks_hold
+                .retain(|stock_info| stock_info.stock_id != stock_id);
+
+            let (hold_date, held_num) = match stocks_hold_before.get(&stock_id) {
+                Some((hold_date, held_num)) => (*hold_date, *held_num),
+                None => (assess_date, 0),
+            };
+            let mut bought = 0;
+            let mut sold = 0;
+
+            for executed_trade in &executed_trades {
+                if executed_trade.stock_id != stock_id {
+                    continue;
+                }
+                match executed_trade.side {
+                    order::Side::Buy => {
+                        bought += executed_trade.num;
+                        self.fill_prices
+                            .insert(stock_id.to_owned(), executed_trade.price);
+                        self.liquidity = self
+                            .liquidity
+                            .saturating_sub(executed_trade.num * executed_trade.price);
+                        portfolio.stocks_selected.push(StockInfo {
+                            stock_id: stock_id.to_owned(),
+                            num: executed_trade.num,
+                            price: executed_trade.price,
+                            settle_reason: None,
+                            halt: None,
+                            position_note: self.position_notes.get(&stock_id).cloned(),
+                        });
+                    }
+                    order::Side::Sell => {
+                        let num = executed_trade.num.min(held_num - sold);
+
+                        if num == 0 {
+                            continue;
+                        }
+                        sold += num;
+                        self.liquidity += num * executed_trade.price;
+                        self.fill_prices
+                            .insert(stock_id.to_owned(), executed_trade.price);
+                        portfolio.stocks_settled.push(StockInfo {
+                            stock_id: stock_id.to_owned(),
+                            num,
+                            price: executed_trade.price,
+                            settle_reason: Some(strategy::SettleReason::Manual),
+                            halt: None,
+                            position_note: None,
+                        });
+                    }
+                }
+            }
+
+            let hold_num = held_num - sold;
+
+            if hold_num > 0 {
+                let price = match self.backend_op.query(&stock_id, assess_date)? {
+                    Some(record) => self.get_valuation_price(&stock_id, &record),
+                    None => *self.last_prices.get(&stock_id).unwrap_or(&0),
+                };
+
+                self.last_prices.insert(stock_id.to_owned(), price);
+                portfolio.stocks_hold.push(StockInfo {
+                    stock_id: stock_id.to_owned(),
+                    num: hold_num,
+                    price,
+                    settle_reason: None,
+                    halt: self.get_halt(&stock_id),
+                    position_note: self.position_notes.get(&stock_id).cloned(),
+                });
+            }
+            match hold_num + bought {
+                0 => self.stocks_hold.remove(&stock_id),
+                num => self
+                    .stocks_hold
+                    .insert(stock_id.to_owned(), (hold_date, num)),
+            };
+            self.pending_entries.remove(&stock_id);
+            self.deferred_settles.remove(&stock_id);
+
+            let executed = bought as i64 - sold as i64;
+
+            if executed != recommended {
+                portfolio.reconciliations.push(reconcile::Reconciliation {
+                    stock_id,
+                    recommended,
+                    executed,
+                });
+            }
+        }
+
+        portfolio.liquidity = self.liquidity;
+        Ok(())
+    }
+
+    fn get_halt(&self, stock_id: &str) -> Option<halt::Halt> {
+        let halt_policy = self.halt_policy.as_ref()?;
+        let days = *self.missing_days.get(stock_id)?;
+
+        if !halt_policy.is_halted(days) {
+            return None;
+        }
+        Some(halt::Halt {
+            days,
+            excluded_from_equity: halt_policy.action == halt::HaltAction::ExcludeFromEquity,
+        })
+    }
+
+    pub fn calc_portfolio(
+        &mut self,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<Option<Portfolio>, Error> {
+        if assess_date > self.clock.today() {
+            return Err(Error::FutureDate(assess_date));
+        }
+        if self
+            .calendar
+            .as_ref()
+            .is_some_and(|calendar| !calendar.is_trading_day(assess_date))
+        {
+            return Ok(None);
+        }
+
+        let stocks_missing = self.get_missing_stocks(assess_date)?;
+
+        if !self.has_trading_data(&stocks_missing) {
+            return Ok(None);
+        }
+
+        let stocks_hold_before = self.stocks_hold.clone();
+
+        self.trading_dates.push_back(assess_date);
+        if self.trading_dates.len() > self.signal_latency + 1 {
+            self.trading_dates.pop_front();
+        }
+
+        let mut portfolio = Portfolio {
+            date: assess_date,
+            stocks_selected: Vec::new(),
+            stocks_hold: Vec::new(),
+            stocks_settled: Vec::new(),
+            liquidity: 0,
+            risk_events: Vec::new(),
+            hedge: None,
+            cash_flow: 0,
+            realized_lots: Vec::new(),
+            unrealized_pnl: 0,
+            reconciliations: Vec::new(),
+            pair_positions: Vec::new(),
+            pairs_settled: Vec::new(),
+        };
+        let regime = self.assess_regime(assess_date)?;
+
+        self.handle_cash_flows(assess_date, &mut portfolio);
+        self.handle_missing_stocks(assess_date, &stocks_missing, &mut portfolio)?;
+        self.handle_settle_stocks(assess_date, &mut portfolio)?;
+        self.handle_hold_stocks(assess_date, &mut portfolio)?;
+        self.handle_pending_entries(assess_date, &mut portfolio)?;
+        self.handle_scale_in_entries(assess_date, &mut portfolio)?;
+        self.handle_hedge_valuation(assess_date, &mut portfolio)?;
+        self.handle_pairs_valuation(assess_date, &mut portfolio)?;
+        self.handle_circuit_breaker(assess_date, &mut portfolio)?;
+
+        let entry_allowed = !self.is_entry_paused(&mut portfolio)
+            && !self.is_daily_loss_limit_reached(&mut portfolio)
+            && !self.is_regime_unfavorable(&regime, &mut portfolio)
+            && !self.is_breadth_low(assess_date, &mut portfolio)?;
+
+        self.handle_pairs_trading(assess_date, entry_allowed, &mut portfolio)?;
+        if entry_allowed {
+            self.handle_dca(assess_date, &mut portfolio)?;
+            self.handle_selected_stocks(assess_date, &mut portfolio)?;
+        }
+        self.handle_hedge_rebalance(
+            assess_date,
+            self.is_regime_favorable(&regime),
+            &mut portfolio,
+        )?;
+        self.handle_reconciliation(assess_date, &stocks_hold_before, &mut portfolio)?;
+        self.handle_tax_lots(assess_date, &mut portfolio);
+        self.last_equity = portfolio.equity();
+        Ok(Some(portfolio))
+    }
+
+    /// Holdings and liquidity after the last decided day, if any day was decided. Entries and
+    /// exits still being filled are not part of it; open pair positions are marked to market and
+    /// counted in the liquidity, as if closed at their last value.
+    pub fn get_end_state(&self) -> Option<EndState> {
+        let date = self.last_date?;
+        let mut holdings: Vec<Holding> = self
+            .stocks_hold
+            .iter()
+            .map(|(stock_id, (hold_date, num))| Holding {
+                stock_id: stock_id.to_owned(),
+                hold_date: *hold_date,
+                num: *num,
+                hold_price: match *num {
+                    0 => 0,
+                    num => (self.lot_book.get_cost_basis(stock_id) / num as i64) as u32,
+                },
+                price: self
+                    .last_prices
+                    .get(stock_id)
+                    .or(self.fill_prices.get(stock_id))
+                    .copied()
+                    .unwrap_or(0),
+                lots: self.lot_book.get_lots(stock_id),
+            })
+            .collect();
+
+        let pairs_value: i64 = self
+            .pair_positions
+            .iter()
+            .map(|pair_position| pair_position.value())
+            .sum();
+
+        holdings.sort_by(|a, b| a.stock_id.cmp(&b.stock_id));
+        Some(EndState {
+            date,
+            liquidity: (self.liquidity as i64 + pairs_value).max(0) as u32,
+            holdings,
+        })
+    }
+
+    /// Starts from the holdings and liquidity of `end_state` instead of `liquidity` in cash, so
+    /// that the decision continues a previous run.
+    pub fn resume(&mut self, end_state: &EndState) {
+        self.liquidity = end_state.liquidity;
+        self.last_date = Some(end_state.date);
+        for holding in &end_state.holdings {
+            self.stocks_hold.insert(
+                holding.stock_id.to_owned(),
+                (holding.hold_date, holding.num),
+            );
+            self.last_prices
+                .insert(holding.stock_id.to_owned(), holding.price);
+            self.fill_prices
+                .insert(holding.stock_id.to_owned(), holding.hold_price);
+            for tax_lot in &holding.lots {
+                self.lot_book
+                    .buy(&holding.stock_id, tax_lot.date, tax_lot.num, tax_lot.price);
+            }
+        }
+        self.peak_equity = end_state.equity();
+        self.last_equity = end_state.equity();
+    }
+}
+
+#[cfg(test)]
+mod decision_test {
+    use std::rc::Rc;
+
+    use crate::core::decision::{
+        Decision, EndState, Error, MissingDataPolicy, Portfolio, StockInfo, ValuationPolicy,
+    };
+    use crate::core::{
+        calendar, cashflow, clock, fill, halt, hedge, lot, order, pairs, position, reconcile,
+        regime, risk,
+    };
+    use crate::crawler::crawler;
+    use crate::crosssection::breadth;
+    use crate::storage::calendar::{Session, SessionKind};
+    use crate::storage::{backend, memory};
+    use crate::strategy::{dca, schema, strategy};
+    use crate::testkit::generator;
+
+    #[test]
+    fn future_date_check() {
+        let mut decision = Decision::new(
+            Rc::new(crawler::MockCrawler::new()),
+            Rc::new(backend::MockBackendOp::new()),
+            Rc::new(strategy::MockStrategyAPI::new()),
+        );
+        let today = chrono::NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+
+        decision.clock = Rc::new(clock::ManualClock::from_date(today));
+        assert!(matches!(
+            decision.calc_portfolio(today.succ_opt().unwrap()),
+            Err(Error::FutureDate(date)) if date == today.succ_opt().unwrap()
+        ));
+    }
+
+    #[test]
+    fn select_stocks_all_zero_score() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+
+        mock_crawler.expect_get_stock_list().returning(|| {
+            Ok(vec![
+                "0050".to_owned(),
+                "0051".to_owned(),
+                "0052".to_owned(),
+            ])
+        });
+        mock_backend_op
+            .expect_query()
+            .returning(|stock_id, _| match stock_id {
+                "0050" => {
+                    return Ok(Some(schema::RawData {
+                        ..Default::default()
+                    }))
+                }
+                "0051" => {
+                    return Ok(Some(schema::RawData {
+                        ..Default::default()
+                    }))
+                }
+                "0052" => {
+                    return Ok(Some(schema::RawData {
+                        ..Default::default()
+                    }))
+                }
+                _ => return Ok(None),
+            });
+        mock_strategy
+            .expect_analyze()
+            .returning(|stock_id, _| match stock_id {
+                "0050" => {
+                    return Ok(strategy::Score {
+                        point: 0,
+                        trading_volume: 0,
+                    })
+                }
+                "0051" => {
+                    return Ok(strategy::Score {
+                        point: 0,
+                        trading_volume: 0,
+                    })
+                }
+                "0052" => {
+                    return Ok(strategy::Score {
+                        point: 0,
+                        trading_volume: 0,
+                    })
+                }
+                _ => return Ok(strategy::Score::default()),
+            });
+
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            Rc::new(mock_backend_op),
+            Rc::new(mock_strategy),
+        );
+        let portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert!(portfolio.stocks_selected.is_empty());
+    }
+
+    #[test]
+    fn select_stocks_score_in_order() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+
+        mock_crawler.expect_get_stock_list().returning(|| {
+            Ok(vec![
+                "0050".to_owned(),
+                "0051".to_owned(),
+                "0052".to_owned(),
+            ])
+        });
+        mock_backend_op
+            .expect_query()
+            .returning(|stock_id, _| match stock_id {
+                "0050" => {
+                    return Ok(Some(schema::RawData {
+                        low: 1.0,
+                        high: 1.0,
+                        ..Default::default()
+                    }))
+                }
+                "0051" => {
+                    return Ok(Some(schema::RawData {
+                        low: 1.0,
+                        high: 1.0,
+                        ..Default::default()
+                    }))
+                }
+                "0052" => {
+                    return Ok(Some(schema::RawData {
+                        high: 1.0,
+                        low: 1.0,
+                        ..Default::default()
+                    }))
+                }
+                _ => return Ok(None),
+            });
+        mock_strategy
+            .expect_analyze()
+            .returning(|stock_id, _| match stock_id {
+                "0050" => {
+                    return Ok(strategy::Score {
+                        point: 2,
+                        trading_volume: 0,
+                    })
+                }
+                "0051" => {
+                    return Ok(strategy::Score {
+                        point: 3,
+                        trading_volume: 0,
+                    })
+                }
+                "0052" => {
+                    return Ok(strategy::Score {
+                        point: 4,
+                        trading_volume: 0,
+                    })
+                }
+                _ => return Ok(strategy::Score::default()),
+            });
+
+        let expected_stock_ids = vec!["0052".to_owned(), "0051".to_owned(), "0050".to_owned()];
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            Rc::new(mock_backend_op),
+            Rc::new(mock_strategy),
+        );
+        let portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
+            .unwrap()
+            .unwrap();
+        let selected_stock_ids: Vec<String> = portfolio
+            .stocks_selected
+            .into_iter()
+            .map(|stock_info| stock_info.stock_id)
+            .collect();
+
+        assert_eq!(selected_stock_ids, expected_stock_ids);
+    }
+
+    #[test]
+    fn select_stocks_score_no_duplicated_id() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+
+        mock_crawler.expect_get_stock_list().returning(|| {
+            Ok(vec![
+                "0050".to_owned(),
+                "0051".to_owned(),
+                "0052".to_owned(),
+            ])
+        });
+        mock_backend_op
+            .expect_query()
+            .returning(|stock_id, _| match stock_id {
+                "0050" => {
+                    return Ok(Some(schema::RawData {
+                        low: 1.0,
+                        high: 1.0,
+                        ..Default::default()
+                    }))
+                }
+                "0051" => return Ok(Some(schema::RawData::default())),
+                "0052" => return Ok(Some(schema::RawData::default())),
+                _ => return Ok(None),
+            });
+        mock_strategy
+            .expect_analyze()
+            .returning(|stock_id, _| match stock_id {
+                "0050" => {
+                    return Ok(strategy::Score {
+                        point: 2,
+                        trading_volume: 0,
+                    })
+                }
+                "0051" => return Ok(strategy::Score::default()),
+                "0052" => return Ok(strategy::Score::default()),
+                _ => return Ok(strategy::Score::default()),
+            });
+        mock_strategy
+            .expect_settle_check()
+            .returning(|_, _, _| Ok(None));
+
+        let expected_stock_ids = vec!["0050".to_owned()];
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            Rc::new(mock_backend_op),
+            Rc::new(mock_strategy),
+        );
+        let mut selected_stock_ids: Vec<String> = Vec::new();
+
+        decision.stocks_hold_num = 4;
+
+        let mut portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
+            .unwrap()
+            .unwrap();
+
+        for stock_info in portfolio.stocks_selected {
+            selected_stock_ids.push(stock_info.stock_id);
+        }
+        portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 2).unwrap())
+            .unwrap()
+            .unwrap();
+        for stock_info in portfolio.stocks_selected {
+            selected_stock_ids.push(stock_info.stock_id);
+        }
+
+        assert_eq!(selected_stock_ids, expected_stock_ids);
+    }
+
+    #[test]
+    fn select_stocks_num_check() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec!["0050".to_owned()]));
+        mock_backend_op
+            .expect_query()
+            .returning(|stock_id, _| match stock_id {
+                "0050" => {
+                    return Ok(Some(schema::RawData {
+                        low: 2.0,
+                        high: 8.0,
+                        ..Default::default()
+                    }))
+                }
+                _ => return Ok(None),
+            });
+        mock_strategy
+            .expect_analyze()
+            .returning(|stock_id, _| match stock_id {
+                "0050" => {
+                    return Ok(strategy::Score {
+                        point: 1,
+                        trading_volume: 0,
+                    })
+                }
+                _ => return Ok(strategy::Score::default()),
+            });
+
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            Rc::new(mock_backend_op),
+            Rc::new(mock_strategy),
+        );
+
+        decision.liquidity = 8;
+
+        let portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(portfolio.stocks_selected.len(), 1);
+        assert_eq!(portfolio.stocks_selected[0].stock_id, "0050");
+        assert_eq!(portfolio.stocks_selected[0].num, 1);
+        assert_eq!(portfolio.stocks_selected[0].price, 5);
+    }
+
+    #[test]
+    fn hold_stocks_detail_check() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec!["0050".to_owned()]));
+        mock_backend_op
+            .expect_query()
+            .returning(|stock_id, _| match stock_id {
+                "0050" => {
+                    return Ok(Some(schema::RawData {
+                        low: 2.0,
+                        high: 8.0,
+                        ..Default::default()
+                    }))
+                }
+                _ => return Ok(None),
+            });
+        mock_strategy
+            .expect_analyze()
+            .returning(|stock_id, _| match stock_id {
+                "0050" => {
+                    return Ok(strategy::Score {
+                        point: 2,
+                        trading_volume: 0,
+                    })
+                }
+                _ => return Ok(strategy::Score::default()),
+            });
+        mock_strategy
+            .expect_settle_check()
+            .returning(|_, _, _| Ok(None));
+
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            Rc::new(mock_backend_op),
+            Rc::new(mock_strategy),
+        );
+
+        decision.liquidity = 8;
+        decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
+            .unwrap()
+            .unwrap();
+
+        let portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 2).unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(portfolio.stocks_selected.len(), 0);
+        assert_eq!(portfolio.stocks_hold.len(), 1);
+        assert_eq!(portfolio.stocks_settled.len(), 0);
+        assert_eq!(portfolio.stocks_hold[0].stock_id, "0050");
+        assert_eq!(portfolio.stocks_hold[0].num, 1);
+        assert_eq!(portfolio.stocks_hold[0].price, 5);
+    }
+
+    #[test]
+    fn settle_stocks_detail_check() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec!["0050".to_owned()]));
+        mock_backend_op
+            .expect_query()
+            .returning(|stock_id, _| match stock_id {
+                "0050" => {
+                    return Ok(Some(schema::RawData {
+                        low: 2.0,
+                        high: 8.0,
+                        ..Default::default()
+                    }))
+                }
+                _ => return Ok(None),
+            });
+        mock_strategy
+            .expect_analyze()
+            .returning(|stock_id, assess_date| match stock_id {
+                "0050" => {
+                    return Ok(strategy::Score {
+                        point: (assess_date == chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
+                            as i64,
+                        trading_volume: 0,
+                    })
+                }
+                _ => return Ok(strategy::Score::default()),
+            });
+        mock_strategy
+            .expect_settle_check()
+            .returning(|_, _, _| Ok(Some(strategy::SettleReason::SignalExit)));
+
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            Rc::new(mock_backend_op),
+            Rc::new(mock_strategy),
+        );
+
+        decision.liquidity = 8;
+        decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
+            .unwrap()
+            .unwrap();
+
+        let portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 2).unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(portfolio.stocks_selected.len(), 0);
+        assert_eq!(portfolio.stocks_hold.len(), 0);
+        assert_eq!(portfolio.stocks_settled.len(), 1);
+        assert_eq!(portfolio.stocks_settled[0].stock_id, "0050");
+        assert_eq!(portfolio.stocks_settled[0].num, 1);
+        assert_eq!(portfolio.stocks_settled[0].price, 5);
+        assert_eq!(
+            portfolio.stocks_settled[0].settle_reason,
+            Some(strategy::SettleReason::SignalExit)
+        );
+    }
+
+    #[test]
+    fn liquidity_check() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec!["0050".to_owned(), "0051".to_owned()]));
+        mock_backend_op
+            .expect_query()
+            .returning(|stock_id, date| match stock_id {
+                "0050" => match &date.format("%Y-%m-%d").to_string()[..] {
+                    "1970-01-01" => {
+                        return Ok(Some(schema::RawData {
+                            low: 2.0,
+                            high: 8.0,
+                            ..Default::default()
+                        }))
+                    }
+                    "1970-01-02" => {
+                        return Ok(Some(schema::RawData {
+                            low: 4.0,
+                            high: 16.0,
+                            ..Default::default()
+                        }))
+                    }
+                    _ => return Ok(None),
+                },
+                "0051" => match &date.format("%Y-%m-%d").to_string()[..] {
+                    "1970-01-01" => {
+                        return Ok(Some(schema::RawData {
+                            low: 4.0,
+                            high: 8.0,
+                            ..Default::default()
+                        }))
+                    }
+                    "1970-01-02" => {
+                        return Ok(Some(schema::RawData {
+                            low: 8.0,
+                            high: 16.0,
+                            ..Default::default()
+                        }))
+                    }
+                    _ => return Ok(None),
+                },
+                _ => return Ok(None),
+            });
+        mock_strategy
+            .expect_analyze()
+            .returning(|stock_id, assess_date| match stock_id {
+                "0050" => match &assess_date.format("%Y-%m-%d").to_string()[..] {
+                    "1970-01-01" => {
+                        return Ok(strategy::Score {
+                            point: 2,
+                            trading_volume: 10,
+                        })
+                    }
+                    "1970-01-02" => {
+                        return Ok(strategy::Score {
+                            point: 0,
+                            trading_volume: 0,
+                        })
+                    }
+                    _ => return Ok(strategy::Score::default()),
+                },
+                "0051" => match &assess_date.format("%Y-%m-%d").to_string()[..] {
+                    "1970-01-01" => {
+                        return Ok(strategy::Score {
+                            point: 4,
+                            trading_volume: 20,
+                        })
+                    }
+                    "1970-01-02" => {
+                        return Ok(strategy::Score {
+                            point: 0,
+                            trading_volume: 0,
+                        })
+                    }
+                    _ => return Ok(strategy::Score::default()),
+                },
+                _ => return Ok(strategy::Score::default()),
+            });
+        mock_strategy
+            .expect_settle_check()
+            .returning(|_, _, _| Ok(Some(strategy::SettleReason::SignalExit)));
+
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            Rc::new(mock_backend_op),
+            Rc::new(mock_strategy),
+        );
+
+        decision.liquidity = 20;
+
+        let mut portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(portfolio.liquidity, 4);
+
+        portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 2).unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(portfolio.liquidity, 36);
+    }
+
+    #[test]
+    fn circuit_breaker_liquidates_and_pauses() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec!["0050".to_owned()]));
+        mock_backend_op.expect_query().returning(|_, date| {
+            match &date.format("%Y-%m-%d").to_string()[..] {
+                "1970-01-01" => Ok(Some(schema::RawData {
+                    low: 10.0,
+                    high: 10.0,
+                    ..Default::default()
+                })),
+                _ => Ok(Some(schema::RawData {
+                    low: 5.0,
+                    high: 5.0,
+                    ..Default::default()
+                })),
+            }
+        });
+        mock_strategy.expect_analyze().returning(|_, _| {
+            Ok(strategy::Score {
+                point: 1,
+                trading_volume: 0,
+            })
+        });
+        mock_strategy
+            .expect_settle_check()
+            .returning(|_, _, _| Ok(None));
+
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            Rc::new(mock_backend_op),
+            Rc::new(mock_strategy),
+        );
+
+        decision.liquidity = 100;
+        decision.circuit_breaker = Some(risk::CircuitBreaker {
+            max_drawdown: 20.0,
+            pause_days: 2,
+        });
+
+        let mut portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(portfolio.stocks_selected.len(), 1);
+
+        portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 2).unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(portfolio.stocks_hold.len(), 0);
+        assert_eq!(portfolio.stocks_settled.len(), 1);
+        assert_eq!(portfolio.stocks_selected.len(), 0);
+        assert_eq!(portfolio.liquidity, 50);
+        assert_eq!(
+            portfolio.stocks_settled[0].settle_reason,
+            Some(strategy::SettleReason::CircuitBreaker)
+        );
+        assert_eq!(
+            portfolio.risk_events[0],
+            risk::RiskEvent::CircuitBreakerTriggered { drawdown: 50.0 }
+        );
+
+        portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 3).unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(portfolio.stocks_selected.len(), 0);
+        assert_eq!(portfolio.risk_events, vec![risk::RiskEvent::EntriesPaused]);
+
+        portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 4).unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(portfolio.stocks_selected.len(), 1);
+    }
+
+    #[test]
+    fn circuit_breaker_defers_locked_holdings() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec!["0050".to_owned()]));
+        mock_backend_op.expect_query().returning(|_, date| {
+            match &date.format("%Y-%m-%d").to_string()[..] {
+                "1970-01-01" => Ok(Some(schema::RawData {
+                    high: 10.0,
+                    low: 10.0,
+                    close: 10.0,
+                    ..Default::default()
+                })),
+                // Locked limit down.
+                "1970-01-02" => Ok(Some(schema::RawData {
+                    high: 5.0,
+                    low: 5.0,
+                    close: 5.0,
+                    spread: -5.0,
+                    ..Default::default()
+                })),
+                _ => Ok(Some(schema::RawData {
+                    high: 6.0,
+                    low: 4.0,
+                    close: 5.0,
+                    ..Default::default()
+                })),
+            }
+        });
+        mock_strategy.expect_analyze().returning(|_, _| {
+            Ok(strategy::Score {
+                point: 1,
+                trading_volume: 0,
+            })
+        });
+        mock_strategy
+            .expect_settle_check()
+            .returning(|_, _, _| Ok(None));
+
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            Rc::new(mock_backend_op),
+            Rc::new(mock_strategy),
+        );
+
+        decision.liquidity = 100;
+        decision.price_limit = Some(fill::PriceLimit::default());
+        decision.circuit_breaker = Some(risk::CircuitBreaker {
+            max_drawdown: 20.0,
+            pause_days: 2,
+        });
+        decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
+            .unwrap()
+            .unwrap();
+        decision.slippage = Some(fill::Slippage { rate: 20.0 });
+
+        let mut portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 2).unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(portfolio.stocks_hold.len(), 1);
+        assert!(portfolio.stocks_settled.is_empty());
+        assert_eq!(portfolio.liquidity, 0);
+        assert_eq!(
+            portfolio.risk_events[..2],
+            [
+                risk::RiskEvent::CircuitBreakerTriggered { drawdown: 50.0 },
+                risk::RiskEvent::FillDeferred {
+                    stock_id: "0050".to_owned(),
+                    side: order::Side::Sell,
+                },
+            ]
+        );
+
+        // Sold once the lock is gone, at the fill price after slippage.
+        portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 3).unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert!(portfolio.stocks_hold.is_empty());
+        assert_eq!(portfolio.stocks_settled.len(), 1);
+        assert_eq!(portfolio.stocks_settled[0].price, 4);
+        assert_eq!(
+            portfolio.stocks_settled[0].settle_reason,
+            Some(strategy::SettleReason::CircuitBreaker)
+        );
+        assert_eq!(portfolio.liquidity, 40);
+        assert!(portfolio.stocks_selected.is_empty());
+    }
+
+    #[test]
+    fn daily_loss_limit_blocks_entries() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec!["0050".to_owned(), "0051".to_owned()]));
+        mock_backend_op.expect_query().returning(|_, date| {
+            match &date.format("%Y-%m-%d").to_string()[..] {
+                "1970-01-01" => Ok(Some(schema::RawData {
+                    low: 10.0,
+                    high: 10.0,
+                    ..Default::default()
+                })),
+                _ => Ok(Some(schema::RawData {
+                    low: 9.0,
+                    high: 9.0,
+                    ..Default::default()
+                })),
+            }
+        });
+        mock_strategy
+            .expect_analyze()
+            .returning(|stock_id, assess_date| match stock_id {
+                "0050" => Ok(strategy::Score {
+                    point: 1,
+                    trading_volume: 0,
+                }),
+                _ => Ok(strategy::Score {
+                    point: (assess_date > chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
+                        as i64,
+                    trading_volume: 0,
+                }),
+            });
+        mock_strategy
+            .expect_settle_check()
+            .returning(|_, _, _| Ok(None));
+
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            Rc::new(mock_backend_op),
+            Rc::new(mock_strategy),
+        );
+
+        decision.liquidity = 100;
+        decision.max_daily_loss = Some(5.0);
+
+        let mut portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(portfolio.stocks_selected.len(), 1);
+
+        portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 2).unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(portfolio.stocks_selected.len(), 0);
+        assert_eq!(
+            portfolio.risk_events,
+            vec![risk::RiskEvent::DailyLossLimitReached { loss: 10.0 }]
+        );
+
+        portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 3).unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(portfolio.stocks_selected.len(), 1);
+        assert_eq!(portfolio.stocks_selected[0].stock_id, "0051");
+        assert!(portfolio.risk_events.is_empty());
+    }
+
+    #[test]
+    fn regime_filter_blocks_entries_on_volatile_index() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec!["0051".to_owned()]));
+        mock_backend_op.expect_query().returning(|_, _| {
+            Ok(Some(schema::RawData {
+                low: 1.0,
+                high: 1.0,
+                ..Default::default()
+            }))
+        });
+        mock_backend_op
+            .expect_query_by_range()
+            .returning(|_, _, end_date| {
+                let mut records = Vec::new();
+                let mut date = chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+
+                while date <= end_date {
+                    let range = if date == end_date { 10.0 } else { 1.0 };
+
+                    records.push(schema::RawData {
+                        low: 100.0,
+                        high: 100.0 + range,
+                        close: 100.0,
+                        date,
+                        ..Default::default()
+                    });
+                    date = date.succ_opt().unwrap();
+                }
+                Ok(records)
+            });
+        mock_strategy.expect_analyze().returning(|_, _| {
+            Ok(strategy::Score {
+                point: 1,
+                trading_volume: 0,
+            })
+        });
+
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            Rc::new(mock_backend_op),
+            Rc::new(mock_strategy),
+        );
+
+        decision.regime_filter = Some(regime::RegimeFilter {
+            index_id: "0050".to_owned(),
+            measure: regime::VolatilityMeasure::Atr,
+            atr_period: 5,
+            lookback: 20,
+            calm_percentile: 80.0,
+            trend_period: 5,
+            requirement: regime::Requirement::Calm,
+        });
+
+        let portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 2, 1).unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert!(portfolio.stocks_selected.is_empty());
+        assert_eq!(
+            portfolio.risk_events,
+            vec![risk::RiskEvent::UnfavorableRegime {
+                volatility_percentile: 100.0,
+                trending: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn breadth_filter_blocks_entries_on_weak_market() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec!["0050".to_owned(), "0051".to_owned()]));
+        mock_backend_op.expect_query().returning(|_, _| {
+            Ok(Some(schema::RawData {
+                low: 1.0,
+                high: 1.0,
+                ..Default::default()
+            }))
+        });
+        mock_backend_op
+            .expect_query_by_range()
+            .returning(|stock_id, start_date, end_date| {
+                let mut records = Vec::new();
+                let mut date = start_date;
+                let mut close = 100.0;
+
+                while date <= end_date {
+                    records.push(schema::RawData {
+                        close,
+                        date,
+                        ..Default::default()
+                    });
+                    close += if stock_id == "0050" { 1.0 } else { -1.0 };
+                    date = date.succ_opt().unwrap();
+                }
+                Ok(records)
+            });
+        mock_strategy.expect_analyze().returning(|_, _| {
+            Ok(strategy::Score {
+                point: 1,
+                trading_volume: 0,
+            })
+        });
+
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            Rc::new(mock_backend_op),
+            Rc::new(mock_strategy),
+        );
+
+        decision.breadth_filter = Some(breadth::BreadthFilter {
+            sma_period: 5,
+            min_breadth: 60.0,
+        });
+
+        let portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 2, 1).unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert!(portfolio.stocks_selected.is_empty());
+        assert_eq!(
+            portfolio.risk_events,
+            vec![risk::RiskEvent::LowBreadth { breadth: 50.0 }]
+        );
+    }
+
+    #[test]
+    fn hedge_scales_with_exposure() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec!["0050".to_owned()]));
+        mock_backend_op.expect_query().returning(|stock_id, date| {
+            let price = match (stock_id, &date.format("%Y-%m-%d").to_string()[..]) {
+                ("0050", "1970-01-01") => 10.0,
+                ("0050", _) => 8.0,
+                (_, "1970-01-01") => 10.0,
+                (_, _) => 12.0,
+            };
+
+            Ok(Some(schema::RawData {
+                low: price,
+                high: price,
+                ..Default::default()
+            }))
+        });
+        mock_strategy.expect_analyze().returning(|_, assess_date| {
+            Ok(strategy::Score {
+                point: (assess_date == chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()) as i64,
+                trading_volume: 0,
+            })
+        });
+        mock_strategy
+            .expect_settle_check()
+            .returning(|_, _, _| Ok(None));
+
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            Rc::new(mock_backend_op),
+            Rc::new(mock_strategy),
+        );
+
+        decision.liquidity = 300;
+        decision.hedge = Some(hedge::Hedge {
+            instrument_id: "00632R".to_owned(),
+            ratio: 50.0,
+            unfavorable_ratio: 50.0,
+        });
+
+        let mut portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(portfolio.stocks_selected[0].num, 20);
+        assert_eq!(portfolio.hedge.as_ref().unwrap().num, 10);
+        assert_eq!(portfolio.liquidity, 0);
+
+        portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 2).unwrap())
+            .unwrap()
+            .unwrap();
+
+        let hedge_position = portfolio.hedge.as_ref().unwrap();
+
+        assert_eq!(hedge_position.num, 6);
+        assert_eq!(hedge_position.pnl, 20);
+        assert_eq!(portfolio.liquidity, 48);
+        assert_eq!(portfolio.equity(), 280);
+        assert_eq!(portfolio.unhedged_equity(), 260);
+    }
+
+    #[test]
+    fn pairs_trading_check() {
+        let backend_op = Rc::new(memory::MemoryBackend::new());
+        let start_date = chrono::NaiveDate::from_ymd_opt(2022, 1, 3).unwrap();
+        // The first stock gaps 5% away from the second for a day, then gaps back.
+        let records = generator::SeriesBuilder::new(start_date, 100.0)
+            .mean_reverting(40, 1.0, 10)
+            .gap(5.0)
+            .trend(1, 5.0)
+            .gap(-100.0 / 21.0)
+            .trend(1, -100.0 / 21.0)
+            .flat(5)
+            .insert(backend_op.as_ref(), "2330")
+            .unwrap();
+
+        generator::SeriesBuilder::new(start_date, 100.0)
+            .flat(records.len())
+            .insert(backend_op.as_ref(), "2303")
+            .unwrap();
+
+        let mut mock_crawler = crawler::MockCrawler::new();
+
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec![]));
+
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            backend_op,
+            Rc::new(strategy::MockStrategyAPI::new()),
+        );
+
+        decision.liquidity = 100000;
+        decision.pairs_trading = Some(pairs::PairsTrading {
+            lookback: 20,
+            ..pairs::PairsTrading::new(vec![pairs::Pair {
+                first_id: "2330".to_owned(),
+                second_id: "2303".to_owned(),
+                hedge_ratio: 1.0,
+            }])
+        });
+
+        let (portfolios, end_states): (Vec<Portfolio>, Vec<EndState>) = records
+            .iter()
+            .map(|record| {
+                (
+                    decision.calc_portfolio(record.date).unwrap().unwrap(),
+                    decision.get_end_state().unwrap(),
+                )
+            })
+            .unzip();
+        let open_index = portfolios
+            .iter()
+            .position(|portfolio| !portfolio.pair_positions.is_empty())
+            .unwrap();
+        let pair_position = &portfolios[open_index].pair_positions[0];
+
+        assert_eq!(open_index, 40);
+        assert_eq!(pair_position.legs[0].side, pairs::Side::Short);
+        assert_eq!(pair_position.legs[1].side, pairs::Side::Long);
+        assert_eq!(portfolios[open_index].equity(), 100000);
+        // The open pair is carried into the end state as cash.
+        assert!(portfolios[open_index].liquidity < 100000);
+        assert_eq!(end_states[open_index].liquidity, 100000);
+
+        let portfolio = &portfolios[open_index + 1];
+
+        assert!(portfolio.pair_positions.is_empty());
+        assert_eq!(portfolio.pairs_settled.len(), 1);
+        assert!(portfolio.pairs_settled[0].pnl() > 0);
+        assert_eq!(
+            portfolio.liquidity as i64,
+            100000 + portfolio.pairs_settled[0].pnl()
+        );
+    }
+
+    #[test]
+    fn pairs_stop_out_check() {
+        let backend_op = Rc::new(memory::MemoryBackend::new());
+        let start_date = chrono::NaiveDate::from_ymd_opt(2022, 1, 3).unwrap();
+        // The first stock gaps 5% away from the second, which opens a pair shorting it, then
+        // more than triples.
+        let records = generator::SeriesBuilder::new(start_date, 100.0)
+            .mean_reverting(40, 1.0, 10)
+            .gap(5.0)
+            .trend(1, 5.0)
+            .gap(250.0)
+            .trend(1, 250.0)
+            .flat(3)
+            .insert(backend_op.as_ref(), "2330")
+            .unwrap();
+
+        generator::SeriesBuilder::new(start_date, 100.0)
+            .flat(records.len())
+            .insert(backend_op.as_ref(), "2303")
+            .unwrap();
+
+        let mut mock_crawler = crawler::MockCrawler::new();
+
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec![]));
+
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            backend_op,
+            Rc::new(strategy::MockStrategyAPI::new()),
+        );
+
+        decision.liquidity = 100000;
+        decision.pairs_trading = Some(pairs::PairsTrading {
+            lookback: 20,
+            ..pairs::PairsTrading::new(vec![pairs::Pair {
+                first_id: "2330".to_owned(),
+                second_id: "2303".to_owned(),
+                hedge_ratio: 1.0,
+            }])
+        });
+
+        let portfolios: Vec<Portfolio> = records
+            .iter()
+            .map(|record| decision.calc_portfolio(record.date).unwrap().unwrap())
+            .collect();
+        let open_index = portfolios
+            .iter()
+            .position(|portfolio| !portfolio.pair_positions.is_empty())
+            .unwrap();
+        let cost = portfolios[open_index].pair_positions[0].cost() as i64;
+        let portfolio = &portfolios[open_index + 1];
+        let pair_position = &portfolio.pairs_settled[0];
+
+        assert_eq!(pair_position.legs[0].side, pairs::Side::Short);
+        assert!(portfolio.pair_positions.is_empty());
+        assert!(pair_position.legs[0].value() < 0);
+        assert!(pair_position.pnl() < -cost);
+        assert!(matches!(
+            portfolio.risk_events[..],
+            [risk::RiskEvent::PairStoppedOut { value, .. }] if value < 0
+        ));
+        // The loss beyond what the pair put up is taken off the cash too.
+        assert_eq!(portfolio.liquidity as i64, 100000 + pair_position.pnl());
+        assert_eq!(portfolio.equity() as i64, 100000 + pair_position.pnl());
+    }
+
+    #[test]
+    fn missing_data_policy_check() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec!["0050".to_owned(), "0051".to_owned()]));
+        mock_backend_op
+            .expect_query()
+            .returning(|stock_id, date| match stock_id {
+                "0051" if date > chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap() => Ok(None),
+                _ => Ok(Some(schema::RawData {
+                    low: 2.0,
+                    high: 8.0,
+                    ..Default::default()
+                })),
+            });
+        mock_strategy.expect_analyze().returning(|_, assess_date| {
+            Ok(strategy::Score {
+                point: (assess_date == chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()) as i64,
+                trading_volume: 0,
+            })
+        });
+        mock_strategy
+            .expect_settle_check()
+            .returning(|_, _, _| Ok(None));
+
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            Rc::new(mock_backend_op),
+            Rc::new(mock_strategy),
+        );
+
+        decision.liquidity = 20;
+        decision.stocks_hold_num = 2;
+        decision.missing_data_policy = MissingDataPolicy::SettleAfter { days: 2 };
+        decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
+            .unwrap()
+            .unwrap();
+
+        let mut portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 2).unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(portfolio.stocks_hold.len(), 2);
+        assert_eq!(portfolio.stocks_settled.len(), 0);
+        assert!(portfolio
+            .stocks_hold
+            .iter()
+            .all(|stock_info| stock_info.price == 5));
+        assert_eq!(
+            portfolio.risk_events,
+            vec![risk::RiskEvent::MissingData {
+                stock_id: "0051".to_owned(),
+                days: 1,
+                policy: MissingDataPolicy::SettleAfter { days: 2 },
+            }]
+        );
+
+        portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 3).unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(portfolio.stocks_hold.len(), 1);
+        assert_eq!(portfolio.stocks_settled.len(), 1);
+        assert_eq!(portfolio.stocks_settled[0].stock_id, "0051");
+        assert_eq!(portfolio.stocks_settled[0].price, 5);
+        assert_eq!(
+            portfolio.stocks_settled[0].settle_reason,
+            Some(strategy::SettleReason::Delisting)
+        );
+        assert_eq!(portfolio.liquidity, 10);
+
+        decision.missing_data_policy = MissingDataPolicy::SkipDay;
+        assert!(decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 4).unwrap())
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn halt_policy_check() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec!["0050".to_owned(), "0051".to_owned()]));
+        mock_backend_op
+            .expect_query()
+            .returning(|stock_id, date| match stock_id {
+                "0051"
+                    if date > chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()
+                        && date < chrono::NaiveDate::from_ymd_opt(1970, 1, 4).unwrap() =>
+                {
+                    Ok(None)
+                }
+                _ => Ok(Some(schema::RawData {
+                    low: 2.0,
+                    high: 8.0,
+                    ..Default::default()
+                })),
+            });
+        mock_strategy.expect_analyze().returning(|_, assess_date| {
+            Ok(strategy::Score {
+                point: (assess_date == chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()) as i64,
+                trading_volume: 0,
+            })
+        });
+        mock_strategy
+            .expect_settle_check()
+            .returning(|_, _, _| Ok(None));
+
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            Rc::new(mock_backend_op),
+            Rc::new(mock_strategy),
+        );
+
+        decision.liquidity = 20;
+        decision.stocks_hold_num = 2;
+        decision.missing_data_policy = MissingDataPolicy::CarryForward;
+        decision.halt_policy = Some(halt::HaltPolicy {
+            min_days: 2,
+            action: halt::HaltAction::SettleOnResume,
+        });
+        decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
+            .unwrap()
+            .unwrap();
+
+        let mut portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 2).unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert!(portfolio
+            .stocks_hold
+            .iter()
+            .all(|stock_info| stock_info.halt.is_none()));
+
+        portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 3).unwrap())
+            .unwrap()
+            .unwrap();
+
+        let stock_info = portfolio
+            .stocks_hold
+            .iter()
+            .find(|stock_info| stock_info.stock_id == "0051")
+            .unwrap();
+
+        assert_eq!(
+            stock_info.halt,
+            Some(halt::Halt {
+                days: 2,
+                excluded_from_equity: false,
+            })
+        );
+        assert_eq!(
+            portfolio.risk_events,
+            vec![risk::RiskEvent::TradingHalted {
+                stock_id: "0051".to_owned(),
+                days: 2,
+            }]
+        );
+
+        portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 4).unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(portfolio.stocks_hold.len(), 1);
+        assert_eq!(portfolio.stocks_settled.len(), 1);
+        assert_eq!(portfolio.stocks_settled[0].stock_id, "0051");
+        assert_eq!(
+            portfolio.stocks_settled[0].settle_reason,
+            Some(strategy::SettleReason::HaltResumed)
+        );
+        assert_eq!(
+            portfolio.risk_events,
+            vec![risk::RiskEvent::TradingResumed {
+                stock_id: "0051".to_owned(),
+                days: 2,
+            }]
+        );
+    }
+
+    #[test]
+    fn price_limit_check() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec!["0050".to_owned()]));
+        mock_backend_op.expect_query().returning(|_, date| {
+            if date == chrono::NaiveDate::from_ymd_opt(1970, 1, 2).unwrap() {
+                return Ok(Some(schema::RawData {
+                    high: 9.0,
+                    low: 9.0,
+                    close: 9.0,
+                    spread: -1.0,
+                    ..Default::default()
+                }));
+            }
+            Ok(Some(schema::RawData {
+                high: 10.0,
+                low: 10.0,
+                close: 10.0,
+                ..Default::default()
+            }))
+        });
+        mock_strategy.expect_analyze().returning(|_, assess_date| {
+            Ok(strategy::Score {
+                point: (assess_date == chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()) as i64,
+                trading_volume: 0,
+            })
+        });
+        mock_strategy
+            .expect_settle_check()
+            .returning(|_, _, assess_date| {
+                if assess_date == chrono::NaiveDate::from_ymd_opt(1970, 1, 2).unwrap() {
+                    return Ok(Some(strategy::SettleReason::SignalExit));
+                }
+                Ok(None)
+            });
+
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            Rc::new(mock_backend_op),
+            Rc::new(mock_strategy),
+        );
+
+        decision.liquidity = 100;
+        decision.stocks_hold_num = 1;
+        decision.price_limit = Some(fill::PriceLimit::default());
+        decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
+            .unwrap()
+            .unwrap();
+
+        let mut portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 2).unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(portfolio.stocks_hold.len(), 1);
+        assert_eq!(portfolio.stocks_settled.len(), 0);
+        assert_eq!(
+            portfolio.risk_events,
+            vec![risk::RiskEvent::FillDeferred {
+                stock_id: "0050".to_owned(),
+                side: order::Side::Sell,
+            }]
+        );
+
+        portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 3).unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(portfolio.stocks_settled.len(), 1);
+        assert_eq!(portfolio.stocks_settled[0].price, 10);
+        assert_eq!(
+            portfolio.stocks_settled[0].settle_reason,
+            Some(strategy::SettleReason::SignalExit)
+        );
+        assert!(portfolio.risk_events.is_empty());
+    }
+
+    #[test]
+    fn signal_latency_check() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+        let get_date = |day| chrono::NaiveDate::from_ymd_opt(1970, 1, day).unwrap();
+
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec!["0050".to_owned()]));
+        mock_backend_op.expect_query().returning(|_, _| {
+            Ok(Some(schema::RawData {
+                high: 10.0,
+                low: 10.0,
+                ..Default::default()
+            }))
+        });
+        mock_strategy
+            .expect_analyze()
+            .returning(move |_, assess_date| {
+                Ok(strategy::Score {
+                    point: (assess_date == get_date(1)) as i64,
+                    trading_volume: 0,
+                })
+            });
+        mock_strategy
+            .expect_settle_check()
+            .returning(move |_, _, assess_date| match assess_date == get_date(4) {
+                true => Ok(Some(strategy::SettleReason::SignalExit)),
+                false => Ok(None),
+            });
+
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            Rc::new(mock_backend_op),
+            Rc::new(mock_strategy),
+        );
+
+        decision.liquidity = 100;
+        decision.stocks_hold_num = 1;
+        decision.signal_latency = 2;
+
+        // The entry signal of the 1st is acted on on the 3rd, the exit one of the 4th on the 6th.
+        let counts: Vec<(usize, usize)> = (1..=7)
+            .map(|day| {
+                let portfolio = decision.calc_portfolio(get_date(day)).unwrap().unwrap();
+
+                (
+                    portfolio.stocks_selected.len(),
+                    portfolio.stocks_settled.len(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            counts,
+            vec![(0, 0), (0, 0), (1, 0), (0, 0), (0, 0), (0, 1), (0, 0)]
+        );
+    }
+
+    #[test]
+    fn volume_cap_check() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+        let get_date = |day| chrono::NaiveDate::from_ymd_opt(1970, 1, day).unwrap();
+
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec!["0050".to_owned()]));
+        mock_backend_op.expect_query().returning(|_, _| {
+            Ok(Some(schema::RawData {
+                high: 10.0,
+                low: 10.0,
+                trading_volume: 200,
+                ..Default::default()
+            }))
+        });
+        mock_strategy
+            .expect_analyze()
+            .returning(move |_, assess_date| {
+                Ok(strategy::Score {
+                    point: (assess_date == get_date(1)) as i64,
+                    trading_volume: 0,
+                })
+            });
+        mock_strategy
+            .expect_settle_check()
+            .returning(move |_, _, assess_date| match assess_date == get_date(3) {
+                true => Ok(Some(strategy::SettleReason::SignalExit)),
+                false => Ok(None),
+            });
+
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            Rc::new(mock_backend_op),
+            Rc::new(mock_strategy),
+        );
+
+        decision.liquidity = 1000;
+        decision.stocks_hold_num = 1;
+        decision.volume_cap = Some(fill::VolumeCap::default());
+
+        // 100 shares wanted, 20 a day allowed.
+        let mut portfolio = decision.calc_portfolio(get_date(1)).unwrap().unwrap();
+
+        assert_eq!(portfolio.stocks_selected[0].num, 20);
+        assert_eq!(portfolio.liquidity, 800);
+        assert_eq!(
+            portfolio.risk_events,
+            vec![risk::RiskEvent::PartialFill {
+                stock_id: "0050".to_owned(),
+                side: order::Side::Buy,
+                num: 20,
+                remaining: 80,
+            }]
+        );
+
+        portfolio = decision.calc_portfolio(get_date(2)).unwrap().unwrap();
+
+        assert_eq!(portfolio.stocks_hold[0].num, 20);
+        assert_eq!(portfolio.stocks_selected[0].num, 20);
+        assert_eq!(portfolio.liquidity, 600);
+
+        // The exit cancels the rest of the entry and sells over two days.
+        portfolio = decision.calc_portfolio(get_date(3)).unwrap().unwrap();
+
+        assert_eq!(portfolio.stocks_settled[0].num, 20);
+        assert_eq!(portfolio.stocks_hold[0].num, 20);
+        assert!(portfolio.stocks_selected.is_empty());
+
+        portfolio = decision.calc_portfolio(get_date(4)).unwrap().unwrap();
+
+        assert_eq!(portfolio.stocks_settled[0].num, 20);
+        assert!(portfolio.stocks_hold.is_empty());
+        assert_eq!(portfolio.liquidity, 1000);
+    }
+
+    #[test]
+    fn scale_out_check() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+        let get_date = |day| chrono::NaiveDate::from_ymd_opt(1970, 1, day).unwrap();
+
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec!["0050".to_owned()]));
+        mock_backend_op.expect_query().returning(|_, _| {
+            Ok(Some(schema::RawData {
+                high: 10.0,
+                low: 10.0,
+                ..Default::default()
+            }))
+        });
+        mock_strategy
+            .expect_analyze()
+            .returning(move |_, assess_date| {
+                Ok(strategy::Score {
+                    point: (assess_date == get_date(1)) as i64,
+                    trading_volume: 0,
+                })
+            });
+        mock_strategy
+            .expect_settle_check()
+            .returning(move |_, _, assess_date| match assess_date == get_date(2) {
+                true => Ok(Some(strategy::SettleReason::StopLoss)),
+                false => Ok(None),
+            });
+
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            Rc::new(mock_backend_op),
+            Rc::new(mock_strategy),
+        );
+
+        decision.liquidity = 1000;
+        decision.stocks_hold_num = 1;
+        decision.scale_out = Some(fill::ScaleOut { days: 3 });
+        decision.calc_portfolio(get_date(1)).unwrap().unwrap();
+
+        // 100 shares sold over three days, the exit carrying on without the signal.
+        for (day, settled, held) in [(2, 34, 66), (3, 33, 33), (4, 33, 0)] {
+            let portfolio = decision.calc_portfolio(get_date(day)).unwrap().unwrap();
+
+            assert_eq!(portfolio.stocks_settled.len(), 1);
+            assert_eq!(portfolio.stocks_settled[0].num, settled);
+            assert_eq!(
+                portfolio.stocks_settled[0].settle_reason,
+                Some(strategy::SettleReason::StopLoss)
+            );
+            assert_eq!(
+                portfolio
+                    .stocks_hold
+                    .iter()
+                    .map(|stock_info| stock_info.num)
+                    .sum::<u32>(),
+                held
+            );
+            assert!(portfolio.risk_events.is_empty());
+        }
+        assert_eq!(fill::ScaleOut { days: 3 }.get_tranche(2500, 3, 1000), 1000);
+        assert_eq!(fill::ScaleOut { days: 3 }.get_tranche(500, 1, 1000), 500);
+    }
+
+    #[test]
+    fn scale_in_check() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+        let get_date = |day| chrono::NaiveDate::from_ymd_opt(1970, 1, day).unwrap();
+
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec!["0050".to_owned(), "0051".to_owned()]));
+        mock_backend_op.expect_query().returning(|_, _| {
+            Ok(Some(schema::RawData {
+                high: 10.0,
+                low: 10.0,
+                ..Default::default()
+            }))
+        });
+        // 0051 stops scoring as a buy on the third day.
+        mock_strategy
+            .expect_analyze()
+            .returning(move |stock_id, assess_date| {
+                Ok(strategy::Score {
+                    point: (stock_id == "0050" || assess_date < get_date(3)) as i64,
+                    trading_volume: 0,
+                })
             });
+        mock_strategy
+            .expect_settle_check()
+            .returning(|_, _, _| Ok(None));
 
         let mut decision = Decision::new(
             Rc::new(mock_crawler),
             Rc::new(mock_backend_op),
             Rc::new(mock_strategy),
         );
-        let portfolio = decision
-            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
-            .unwrap()
-            .unwrap();
+        let get_selected = |portfolio: &Portfolio| {
+            let mut stocks_selected: Vec<(String, u32)> = portfolio
+                .stocks_selected
+                .iter()
+                .map(|stock_info| (stock_info.stock_id.to_owned(), stock_info.num))
+                .collect();
+
+            stocks_selected.sort();
+            stocks_selected
+        };
+
+        decision.liquidity = 2000;
+        decision.stocks_hold_num = 2;
+        decision.scale_in = Some(fill::ScaleIn { days: 3 });
+
+        // 100 shares of each wanted, in tranches of 34, 33 and 33.
+        let portfolio = decision.calc_portfolio(get_date(1)).unwrap().unwrap();
+
+        assert_eq!(
+            get_selected(&portfolio),
+            vec![("0050".to_owned(), 34), ("0051".to_owned(), 34)]
+        );
+        assert_eq!(portfolio.liquidity, 2000 - 680);
+
+        let portfolio = decision.calc_portfolio(get_date(2)).unwrap().unwrap();
+
+        assert_eq!(
+            get_selected(&portfolio),
+            vec![("0050".to_owned(), 33), ("0051".to_owned(), 33)]
+        );
+
+        let portfolio = decision.calc_portfolio(get_date(3)).unwrap().unwrap();
+
+        assert_eq!(get_selected(&portfolio), vec![("0050".to_owned(), 33)]);
+        assert_eq!(
+            portfolio.risk_events,
+            vec![risk::RiskEvent::EntryCancelled {
+                stock_id: "0051".to_owned(),
+                remaining: 33,
+            }]
+        );
+        assert_eq!(portfolio.liquidity, 2000 - 1000 - 670);
+
+        let portfolio = decision.calc_portfolio(get_date(4)).unwrap().unwrap();
 
         assert!(portfolio.stocks_selected.is_empty());
     }
 
     #[test]
-    fn select_stocks_score_in_order() {
+    fn scale_in_zero_days_check() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+        let get_date = |day| chrono::NaiveDate::from_ymd_opt(1970, 1, day).unwrap();
+
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec!["0050".to_owned()]));
+        mock_backend_op.expect_query().returning(|_, _| {
+            Ok(Some(schema::RawData {
+                high: 10.0,
+                low: 10.0,
+                trading_volume: 1000,
+                ..Default::default()
+            }))
+        });
+        mock_strategy.expect_analyze().returning(|_, _| {
+            Ok(strategy::Score {
+                point: 1,
+                trading_volume: 0,
+            })
+        });
+        mock_strategy
+            .expect_settle_check()
+            .returning(|_, _, _| Ok(None));
+
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            Rc::new(mock_backend_op),
+            Rc::new(mock_strategy),
+        );
+
+        decision.liquidity = 1000;
+        decision.stocks_hold_num = 1;
+        decision.scale_in = Some(fill::ScaleIn { days: 0 });
+
+        let portfolio = decision.calc_portfolio(get_date(1)).unwrap().unwrap();
+
+        // Zero days builds the whole position at once, as a single day does.
+        assert_eq!(portfolio.stocks_selected.len(), 1);
+        assert_eq!(portfolio.stocks_selected[0].num, 100);
+        assert_eq!(portfolio.liquidity, 0);
+        assert!(decision.scale_in_entries.is_empty());
+    }
+
+    #[test]
+    fn valuation_policy_check() {
+        let get_date = |day| chrono::NaiveDate::from_ymd_opt(1970, 1, day).unwrap();
+
+        for (valuation_policy, price) in [
+            (ValuationPolicy::Mid, 12),
+            (ValuationPolicy::Close, 13),
+            (ValuationPolicy::LastTrade, 10),
+        ] {
+            let mut mock_crawler = crawler::MockCrawler::new();
+            let mut mock_backend_op = backend::MockBackendOp::new();
+            let mut mock_strategy = strategy::MockStrategyAPI::new();
+
+            mock_crawler
+                .expect_get_stock_list()
+                .returning(|| Ok(vec!["0050".to_owned()]));
+            mock_backend_op
+                .expect_query()
+                .returning(move |_, date| match date == get_date(1) {
+                    true => Ok(Some(schema::RawData {
+                        high: 10.0,
+                        low: 10.0,
+                        close: 10.0,
+                        ..Default::default()
+                    })),
+                    false => Ok(Some(schema::RawData {
+                        high: 14.0,
+                        low: 10.0,
+                        close: 13.0,
+                        ..Default::default()
+                    })),
+                });
+            mock_strategy.expect_analyze().returning(|_, _| {
+                Ok(strategy::Score {
+                    point: 1,
+                    trading_volume: 0,
+                })
+            });
+            mock_strategy
+                .expect_settle_check()
+                .returning(|_, _, _| Ok(None));
+
+            let mut decision = Decision::new(
+                Rc::new(mock_crawler),
+                Rc::new(mock_backend_op),
+                Rc::new(mock_strategy),
+            );
+
+            decision.liquidity = 100;
+            decision.stocks_hold_num = 1;
+            decision.valuation_policy = valuation_policy;
+            decision.calc_portfolio(get_date(1)).unwrap().unwrap();
+
+            let portfolio = decision.calc_portfolio(get_date(2)).unwrap().unwrap();
+
+            assert_eq!(portfolio.stocks_hold[0].price, price);
+            assert_eq!(portfolio.equity(), 10 * price);
+        }
+    }
+
+    #[test]
+    fn tick_size_check() {
+        let get_date = |day| chrono::NaiveDate::from_ymd_opt(1970, 1, day).unwrap();
+
+        for (tick_size, liquidity) in [
+            (None, 400),
+            (Some(fill::TickSize::default()), 399),
+            (
+                Some(fill::TickSize {
+                    rule: fill::TickRule::Fixed(5.0),
+                    rounding: fill::TickRounding::Nearest,
+                }),
+                400,
+            ),
+        ] {
+            let mut mock_crawler = crawler::MockCrawler::new();
+            let mut mock_backend_op = backend::MockBackendOp::new();
+            let mut mock_strategy = strategy::MockStrategyAPI::new();
+
+            mock_crawler
+                .expect_get_stock_list()
+                .returning(|| Ok(vec!["0050".to_owned()]));
+            mock_backend_op.expect_query().returning(|_, _| {
+                Ok(Some(schema::RawData {
+                    high: 600.0,
+                    low: 600.0,
+                    close: 600.0,
+                    ..Default::default()
+                }))
+            });
+            mock_strategy.expect_analyze().returning(|_, _| {
+                Ok(strategy::Score {
+                    point: 1,
+                    trading_volume: 0,
+                })
+            });
+            mock_strategy
+                .expect_settle_check()
+                .returning(|_, _, _| Ok(None));
+
+            let mut decision = Decision::new(
+                Rc::new(mock_crawler),
+                Rc::new(mock_backend_op),
+                Rc::new(mock_strategy),
+            );
+
+            decision.liquidity = 1000;
+            decision.stocks_hold_num = 1;
+            decision.slippage = Some(fill::Slippage { rate: 0.1 });
+            decision.tick_size = tick_size;
+            decision.calc_portfolio(get_date(1)).unwrap().unwrap();
+
+            let portfolio = decision.calc_portfolio(get_date(2)).unwrap().unwrap();
+
+            assert_eq!(portfolio.stocks_hold[0].num, 1);
+            assert_eq!(portfolio.liquidity, liquidity);
+        }
+    }
+
+    #[test]
+    fn candidates_check() {
         let mut mock_crawler = crawler::MockCrawler::new();
         let mut mock_backend_op = backend::MockBackendOp::new();
         let mut mock_strategy = strategy::MockStrategyAPI::new();
@@ -367,180 +3868,373 @@ mod decision_test {
                 "0052".to_owned(),
             ])
         });
-        mock_backend_op
-            .expect_query()
-            .returning(|stock_id, _| match stock_id {
-                "0050" => {
-                    return Ok(Some(schema::RawData {
-                        low: 1.0,
-                        high: 1.0,
-                        ..Default::default()
-                    }))
-                }
-                "0051" => {
-                    return Ok(Some(schema::RawData {
-                        low: 1.0,
-                        high: 1.0,
-                        ..Default::default()
-                    }))
-                }
-                "0052" => {
-                    return Ok(Some(schema::RawData {
-                        high: 1.0,
-                        low: 1.0,
-                        ..Default::default()
-                    }))
-                }
-                _ => return Ok(None),
-            });
+        mock_backend_op.expect_query().returning(|_, _| {
+            Ok(Some(schema::RawData {
+                high: 10.0,
+                low: 10.0,
+                ..Default::default()
+            }))
+        });
         mock_strategy
             .expect_analyze()
             .returning(|stock_id, _| match stock_id {
-                "0050" => {
-                    return Ok(strategy::Score {
-                        point: 2,
-                        trading_volume: 0,
-                    })
-                }
-                "0051" => {
-                    return Ok(strategy::Score {
-                        point: 3,
-                        trading_volume: 0,
-                    })
-                }
-                "0052" => {
-                    return Ok(strategy::Score {
-                        point: 4,
-                        trading_volume: 0,
-                    })
-                }
-                _ => return Ok(strategy::Score::default()),
+                "0050" => Ok(strategy::Score {
+                    point: 3,
+                    trading_volume: 0,
+                }),
+                "0051" => Ok(strategy::Score {
+                    point: 2,
+                    trading_volume: 0,
+                }),
+                _ => Ok(strategy::Score {
+                    point: 1,
+                    trading_volume: 0,
+                }),
             });
 
-        let expected_stock_ids = vec!["0052".to_owned(), "0051".to_owned(), "0050".to_owned()];
         let mut decision = Decision::new(
             Rc::new(mock_crawler),
             Rc::new(mock_backend_op),
             Rc::new(mock_strategy),
         );
+        let date = chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+
+        decision.liquidity = 1000;
+        decision.stocks_hold_num = 1;
+        assert_eq!(
+            decision
+                .get_select_stocks_from(date, &["0052".to_owned(), "0051".to_owned()])
+                .unwrap(),
+            vec!["0051".to_owned()]
+        );
+
+        decision.candidates = Some(vec!["0052".to_owned()]);
+
+        let portfolio = decision.calc_portfolio(date).unwrap().unwrap();
+
+        assert_eq!(portfolio.stocks_selected.len(), 1);
+        assert_eq!(portfolio.stocks_selected[0].stock_id, "0052");
+    }
+
+    #[test]
+    fn manual_hold_check() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec!["0050".to_owned(), "0051".to_owned()]));
+        mock_backend_op.expect_query().returning(|_, _| {
+            Ok(Some(schema::RawData {
+                high: 10.0,
+                low: 10.0,
+                ..Default::default()
+            }))
+        });
+        mock_strategy.expect_analyze().returning(|_, _| {
+            Ok(strategy::Score {
+                point: 1,
+                trading_volume: 0,
+            })
+        });
+        mock_strategy
+            .expect_settle_check()
+            .returning(|_, _, _| Ok(Some(strategy::SettleReason::StopLoss)));
+
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            Rc::new(mock_backend_op),
+            Rc::new(mock_strategy),
+        );
+        let date = chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+        let position_note = position::PositionNote {
+            manual_hold: true,
+            note: "earnings next week".to_owned(),
+        };
+
+        decision.liquidity = 1000;
+        decision.stocks_hold_num = 2;
+        decision
+            .position_notes
+            .insert("0050".to_owned(), position_note.clone());
+        decision.calc_portfolio(date).unwrap().unwrap();
+
         let portfolio = decision
-            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
+            .calc_portfolio(date.succ_opt().unwrap())
             .unwrap()
             .unwrap();
-        let selected_stock_ids: Vec<String> = portfolio
-            .stocks_selected
-            .into_iter()
-            .map(|stock_info| stock_info.stock_id)
-            .collect();
 
-        assert_eq!(selected_stock_ids, expected_stock_ids);
+        assert_eq!(portfolio.stocks_settled.len(), 1);
+        assert_eq!(portfolio.stocks_settled[0].stock_id, "0051");
+        assert_eq!(portfolio.stocks_hold.len(), 1);
+        assert_eq!(portfolio.stocks_hold[0].stock_id, "0050");
+        assert_eq!(portfolio.stocks_hold[0].position_note, Some(position_note));
+        assert_eq!(
+            portfolio.risk_events,
+            vec![risk::RiskEvent::SettleOverridden {
+                stock_id: "0050".to_owned(),
+                settle_reason: strategy::SettleReason::StopLoss,
+            }]
+        );
     }
 
     #[test]
-    fn select_stocks_score_no_duplicated_id() {
+    fn calendar_check() {
         let mut mock_crawler = crawler::MockCrawler::new();
         let mut mock_backend_op = backend::MockBackendOp::new();
         let mut mock_strategy = strategy::MockStrategyAPI::new();
 
-        mock_crawler.expect_get_stock_list().returning(|| {
-            Ok(vec![
-                "0050".to_owned(),
-                "0051".to_owned(),
-                "0052".to_owned(),
-            ])
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec!["0050".to_owned()]));
+        mock_backend_op.expect_query().returning(|_, _| {
+            Ok(Some(schema::RawData {
+                high: 10.0,
+                low: 10.0,
+                ..Default::default()
+            }))
+        });
+        mock_strategy.expect_analyze().returning(|_, _| {
+            Ok(strategy::Score {
+                point: 1,
+                trading_volume: 0,
+            })
         });
-        mock_backend_op
-            .expect_query()
-            .returning(|stock_id, _| match stock_id {
-                "0050" => {
-                    return Ok(Some(schema::RawData {
-                        low: 1.0,
-                        high: 1.0,
-                        ..Default::default()
-                    }))
-                }
-                "0051" => return Ok(Some(schema::RawData::default())),
-                "0052" => return Ok(Some(schema::RawData::default())),
-                _ => return Ok(None),
-            });
         mock_strategy
-            .expect_analyze()
-            .returning(|stock_id, _| match stock_id {
-                "0050" => {
-                    return Ok(strategy::Score {
-                        point: 2,
-                        trading_volume: 0,
-                    })
-                }
-                "0051" => return Ok(strategy::Score::default()),
-                "0052" => return Ok(strategy::Score::default()),
-                _ => return Ok(strategy::Score::default()),
+            .expect_settle_check()
+            .returning(|_, _, _| Ok(None));
+
+        let mut decision = Decision::new(
+            Rc::new(mock_crawler),
+            Rc::new(mock_backend_op),
+            Rc::new(mock_strategy),
+        );
+        let date = |day| chrono::NaiveDate::from_ymd_opt(2024, 2, day).unwrap();
+
+        decision.liquidity = 1000;
+        decision.max_hold_days = Some(2);
+        decision.calendar = Some(calendar::TradingCalendar::from_sessions(&[
+            Session {
+                date: date(6),
+                kind: SessionKind::SettlementOnly,
+                note: String::new(),
+            },
+            Session {
+                date: date(7),
+                kind: SessionKind::Closed,
+                note: String::new(),
+            },
+        ]));
+        decision.calc_portfolio(date(5)).unwrap().unwrap();
+        assert!(decision.calc_portfolio(date(6)).unwrap().is_none());
+        assert!(decision.calc_portfolio(date(7)).unwrap().is_none());
+        assert!(decision
+            .calc_portfolio(date(8))
+            .unwrap()
+            .unwrap()
+            .stocks_settled
+            .is_empty());
+
+        let portfolio = decision.calc_portfolio(date(9)).unwrap().unwrap();
+
+        assert_eq!(portfolio.stocks_settled.len(), 1);
+        assert_eq!(
+            portfolio.stocks_settled[0].settle_reason,
+            Some(strategy::SettleReason::MaxHoldDays)
+        );
+    }
+
+    #[test]
+    fn resume_check() {
+        let get_decision = || {
+            let mut mock_crawler = crawler::MockCrawler::new();
+            let mut mock_backend_op = backend::MockBackendOp::new();
+            let mut mock_strategy = strategy::MockStrategyAPI::new();
+
+            mock_crawler
+                .expect_get_stock_list()
+                .returning(|| Ok(vec!["0050".to_owned(), "0051".to_owned()]));
+            mock_backend_op.expect_query().returning(|_, _| {
+                Ok(Some(schema::RawData {
+                    high: 10.0,
+                    low: 10.0,
+                    ..Default::default()
+                }))
+            });
+            mock_strategy.expect_analyze().returning(|_, _| {
+                Ok(strategy::Score {
+                    point: 1,
+                    trading_volume: 0,
+                })
             });
+            mock_strategy
+                .expect_settle_check()
+                .returning(|_, _, _| Ok(None));
+
+            let mut decision = Decision::new(
+                Rc::new(mock_crawler),
+                Rc::new(mock_backend_op),
+                Rc::new(mock_strategy),
+            );
+
+            decision.liquidity = 1005;
+            decision.stocks_hold_num = 1;
+            decision
+        };
+        let date = chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+        let mut decision = get_decision();
+
+        assert!(decision.get_end_state().is_none());
+        decision.calc_portfolio(date).unwrap().unwrap();
+
+        let end_state = decision.get_end_state().unwrap();
+
+        assert_eq!(end_state.date, date);
+        assert_eq!(end_state.liquidity, 5);
+        assert_eq!(end_state.holdings.len(), 1);
+        assert_eq!(end_state.holdings[0].hold_price, 10);
+        assert_eq!(end_state.holdings[0].lots.len(), 1);
+        assert_eq!(end_state.equity(), 1005);
+
+        let mut resumed = get_decision();
+
+        resumed.resume(&end_state);
+
+        let portfolio = resumed
+            .calc_portfolio(date.succ_opt().unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert!(portfolio.stocks_selected.is_empty());
+        assert_eq!(portfolio.stocks_hold.len(), 1);
+        assert_eq!(
+            portfolio.stocks_hold[0].stock_id,
+            end_state.holdings[0].stock_id
+        );
+        assert_eq!(portfolio.liquidity, 5);
+        assert_eq!(resumed.get_end_state().unwrap().holdings[0].hold_date, date);
+    }
+
+    #[test]
+    fn reconciliation_check() {
+        let mut mock_crawler = crawler::MockCrawler::new();
+        let mut mock_backend_op = backend::MockBackendOp::new();
+        let mut mock_strategy = strategy::MockStrategyAPI::new();
+
+        mock_crawler
+            .expect_get_stock_list()
+            .returning(|| Ok(vec!["0050".to_owned(), "0051".to_owned()]));
+        mock_backend_op.expect_query().returning(|_, _| {
+            Ok(Some(schema::RawData {
+                high: 10.0,
+                low: 10.0,
+                ..Default::default()
+            }))
+        });
+        mock_strategy.expect_analyze().returning(|_, _| {
+            Ok(strategy::Score {
+                point: 1,
+                trading_volume: 0,
+            })
+        });
         mock_strategy
             .expect_settle_check()
-            .returning(|_, _, _| Ok(false));
+            .returning(|_, _, _| Ok(None));
 
-        let expected_stock_ids = vec!["0050".to_owned()];
         let mut decision = Decision::new(
             Rc::new(mock_crawler),
             Rc::new(mock_backend_op),
             Rc::new(mock_strategy),
         );
-        let mut selected_stock_ids: Vec<String> = Vec::new();
+        let date = chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+        let get_trade = |stock_id: &str, num, price| reconcile::ExecutedTrade {
+            date,
+            stock_id: stock_id.to_owned(),
+            side: order::Side::Buy,
+            num,
+            price,
+        };
 
-        decision.stocks_hold_num = 4;
+        decision.liquidity = 1000;
+        decision.stocks_hold_num = 2;
+        decision.statement = Some(reconcile::Statement::new(&[
+            get_trade("0050", 30, 11),
+            get_trade("0052", 10, 10),
+        ]));
 
-        let mut portfolio = decision
-            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
-            .unwrap()
-            .unwrap();
+        let portfolio = decision.calc_portfolio(date).unwrap().unwrap();
+        let get_nums = |stock_infos: &Vec<StockInfo>| {
+            stock_infos
+                .iter()
+                .map(|stock_info| (stock_info.stock_id.to_owned(), stock_info.num))
+                .collect::<Vec<_>>()
+        };
 
-        for stock_info in portfolio.stocks_selected {
-            selected_stock_ids.push(stock_info.stock_id);
-        }
-        portfolio = decision
-            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 2).unwrap())
+        assert_eq!(
+            get_nums(&portfolio.stocks_selected),
+            vec![("0050".to_owned(), 30), ("0052".to_owned(), 10)]
+        );
+        assert_eq!(portfolio.liquidity, 1000 - 30 * 11 - 10 * 10);
+        assert_eq!(
+            portfolio
+                .reconciliations
+                .iter()
+                .map(|reconciliation| (
+                    reconciliation.stock_id.as_str(),
+                    reconciliation.recommended,
+                    reconciliation.executed
+                ))
+                .collect::<Vec<_>>(),
+            vec![("0050", 50, 30), ("0051", 50, 0), ("0052", 0, 10)]
+        );
+
+        // The next day starts from the real holdings, which fill both slots.
+        let mut portfolio = decision
+            .calc_portfolio(date.succ_opt().unwrap())
             .unwrap()
             .unwrap();
-        for stock_info in portfolio.stocks_selected {
-            selected_stock_ids.push(stock_info.stock_id);
-        }
 
-        assert_eq!(selected_stock_ids, expected_stock_ids);
+        portfolio
+            .stocks_hold
+            .sort_by(|a, b| a.stock_id.cmp(&b.stock_id));
+        assert_eq!(
+            get_nums(&portfolio.stocks_hold),
+            vec![("0050".to_owned(), 30), ("0052".to_owned(), 10)]
+        );
+        assert!(portfolio.stocks_selected.is_empty());
+        assert!(portfolio.reconciliations.is_empty());
     }
 
     #[test]
-    fn select_stocks_num_check() {
+    fn order_size_check() {
         let mut mock_crawler = crawler::MockCrawler::new();
         let mut mock_backend_op = backend::MockBackendOp::new();
         let mut mock_strategy = strategy::MockStrategyAPI::new();
 
         mock_crawler
             .expect_get_stock_list()
-            .returning(|| Ok(vec!["0050".to_owned()]));
+            .returning(|| Ok(vec!["0050".to_owned(), "0051".to_owned()]));
         mock_backend_op
             .expect_query()
             .returning(|stock_id, _| match stock_id {
-                "0050" => {
-                    return Ok(Some(schema::RawData {
-                        low: 2.0,
-                        high: 8.0,
-                        ..Default::default()
-                    }))
-                }
-                _ => return Ok(None),
-            });
-        mock_strategy
-            .expect_analyze()
-            .returning(|stock_id, _| match stock_id {
-                "0050" => {
-                    return Ok(strategy::Score {
-                        point: 1,
-                        trading_volume: 0,
-                    })
-                }
-                _ => return Ok(strategy::Score::default()),
+                "0050" => Ok(Some(schema::RawData {
+                    high: 5.0,
+                    low: 5.0,
+                    ..Default::default()
+                })),
+                _ => Ok(Some(schema::RawData {
+                    high: 8.0,
+                    low: 8.0,
+                    ..Default::default()
+                })),
             });
+        mock_strategy.expect_analyze().returning(|_, _| {
+            Ok(strategy::Score {
+                point: 1,
+                trading_volume: 0,
+            })
+        });
 
         let mut decision = Decision::new(
             Rc::new(mock_crawler),
@@ -548,7 +4242,12 @@ mod decision_test {
             Rc::new(mock_strategy),
         );
 
-        decision.liquidity = 8;
+        decision.liquidity = 12000;
+        decision.stocks_hold_num = 2;
+        decision.order_size = Some(fill::OrderSize {
+            lot_policy: fill::LotPolicy::BoardLot,
+            min_order_value: 5000,
+        });
 
         let portfolio = decision
             .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
@@ -557,12 +4256,20 @@ mod decision_test {
 
         assert_eq!(portfolio.stocks_selected.len(), 1);
         assert_eq!(portfolio.stocks_selected[0].stock_id, "0050");
-        assert_eq!(portfolio.stocks_selected[0].num, 1);
-        assert_eq!(portfolio.stocks_selected[0].price, 5);
+        assert_eq!(portfolio.stocks_selected[0].num, 1000);
+        assert_eq!(portfolio.liquidity, 7000);
+        assert_eq!(
+            portfolio.risk_events,
+            vec![risk::RiskEvent::OrderSkipped {
+                stock_id: "0051".to_owned(),
+                num: 750,
+                price: 8,
+            }]
+        );
     }
 
     #[test]
-    fn hold_stocks_detail_check() {
+    fn cash_flows_check() {
         let mut mock_crawler = crawler::MockCrawler::new();
         let mut mock_backend_op = backend::MockBackendOp::new();
         let mut mock_strategy = strategy::MockStrategyAPI::new();
@@ -570,32 +4277,19 @@ mod decision_test {
         mock_crawler
             .expect_get_stock_list()
             .returning(|| Ok(vec!["0050".to_owned()]));
-        mock_backend_op
-            .expect_query()
-            .returning(|stock_id, _| match stock_id {
-                "0050" => {
-                    return Ok(Some(schema::RawData {
-                        low: 2.0,
-                        high: 8.0,
-                        ..Default::default()
-                    }))
-                }
-                _ => return Ok(None),
-            });
-        mock_strategy
-            .expect_analyze()
-            .returning(|stock_id, _| match stock_id {
-                "0050" => {
-                    return Ok(strategy::Score {
-                        point: 2,
-                        trading_volume: 0,
-                    })
-                }
-                _ => return Ok(strategy::Score::default()),
-            });
-        mock_strategy
-            .expect_settle_check()
-            .returning(|_, _, _| Ok(false));
+        mock_backend_op.expect_query().returning(|_, _| {
+            Ok(Some(schema::RawData {
+                high: 10.0,
+                low: 10.0,
+                ..Default::default()
+            }))
+        });
+        mock_strategy.expect_analyze().returning(|_, _| {
+            Ok(strategy::Score {
+                point: 0,
+                trading_volume: 0,
+            })
+        });
 
         let mut decision = Decision::new(
             Rc::new(mock_crawler),
@@ -603,173 +4297,148 @@ mod decision_test {
             Rc::new(mock_strategy),
         );
 
-        decision.liquidity = 8;
-        decision
-            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
+        decision.liquidity = 100;
+        decision.cash_flows = Some(cashflow::CashFlowSchedule {
+            monthly_contribution: 50,
+            flows: vec![cashflow::CashFlow {
+                date: chrono::NaiveDate::from_ymd_opt(1970, 2, 3).unwrap(),
+                amount: -500,
+            }],
+        });
+
+        let mut portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 30).unwrap())
             .unwrap()
             .unwrap();
 
-        let portfolio = decision
-            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 2).unwrap())
+        assert_eq!(portfolio.cash_flow, 0);
+        assert_eq!(portfolio.liquidity, 100);
+
+        portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 2, 2).unwrap())
             .unwrap()
             .unwrap();
 
-        assert_eq!(portfolio.stocks_selected.len(), 0);
-        assert_eq!(portfolio.stocks_hold.len(), 1);
-        assert_eq!(portfolio.stocks_settled.len(), 0);
-        assert_eq!(portfolio.stocks_hold[0].stock_id, "0050");
-        assert_eq!(portfolio.stocks_hold[0].num, 1);
-        assert_eq!(portfolio.stocks_hold[0].price, 5);
+        assert_eq!(portfolio.cash_flow, 50);
+        assert_eq!(portfolio.liquidity, 150);
+
+        portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 2, 3).unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(portfolio.cash_flow, -150);
+        assert_eq!(portfolio.liquidity, 0);
+        assert_eq!(
+            cashflow::time_weighted_return(100, &[(200, 0), (600, 100)]),
+            300.0
+        );
     }
 
     #[test]
-    fn settle_stocks_detail_check() {
+    fn dca_check() {
+        let backend_op = Rc::new(memory::MemoryBackend::new());
+        let start_date = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let records = generator::SeriesBuilder::new(start_date, 100.0)
+            .flat(65)
+            .insert(backend_op.as_ref(), "0050")
+            .unwrap();
         let mut mock_crawler = crawler::MockCrawler::new();
-        let mut mock_backend_op = backend::MockBackendOp::new();
-        let mut mock_strategy = strategy::MockStrategyAPI::new();
 
         mock_crawler
             .expect_get_stock_list()
-            .returning(|| Ok(vec!["0050".to_owned()]));
-        mock_backend_op
-            .expect_query()
-            .returning(|stock_id, _| match stock_id {
-                "0050" => {
-                    return Ok(Some(schema::RawData {
-                        low: 2.0,
-                        high: 8.0,
-                        ..Default::default()
-                    }))
-                }
-                _ => return Ok(None),
-            });
-        mock_strategy
-            .expect_analyze()
-            .returning(|stock_id, assess_date| match stock_id {
-                "0050" => {
-                    return Ok(strategy::Score {
-                        point: (assess_date == chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
-                            as i64,
-                        trading_volume: 0,
-                    })
-                }
-                _ => return Ok(strategy::Score::default()),
-            });
-        mock_strategy
-            .expect_settle_check()
-            .returning(|_, _, _| Ok(true));
+            .returning(|| Ok(vec![]));
 
+        let plan = dca::Plan::default();
         let mut decision = Decision::new(
             Rc::new(mock_crawler),
-            Rc::new(mock_backend_op),
-            Rc::new(mock_strategy),
+            backend_op.clone(),
+            Rc::new(strategy::StrategyFactory::get(
+                strategy::Strategies::Dca(plan.clone()),
+                &strategy::StrategyParams::default(),
+                backend_op,
+            )),
         );
 
-        decision.liquidity = 8;
-        decision
-            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
-            .unwrap()
-            .unwrap();
+        // Every month's contribution is invested on the day it comes in.
+        decision.liquidity = plan.amount;
+        decision.cash_flows = Some(cashflow::CashFlowSchedule {
+            monthly_contribution: plan.amount as i64,
+            flows: Vec::new(),
+        });
+        decision.dca = Some(plan);
 
-        let portfolio = decision
-            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 2).unwrap())
-            .unwrap()
-            .unwrap();
+        let portfolios: Vec<Portfolio> = records
+            .iter()
+            .map(|record| decision.calc_portfolio(record.date).unwrap().unwrap())
+            .collect();
+        let buy_dates: Vec<chrono::NaiveDate> = portfolios
+            .iter()
+            .filter(|portfolio| !portfolio.stocks_selected.is_empty())
+            .map(|portfolio| portfolio.date)
+            .collect();
 
-        assert_eq!(portfolio.stocks_selected.len(), 0);
-        assert_eq!(portfolio.stocks_hold.len(), 0);
-        assert_eq!(portfolio.stocks_settled.len(), 1);
-        assert_eq!(portfolio.stocks_settled[0].stock_id, "0050");
-        assert_eq!(portfolio.stocks_settled[0].num, 1);
-        assert_eq!(portfolio.stocks_settled[0].price, 5);
+        assert_eq!(
+            buy_dates,
+            vec![
+                start_date,
+                chrono::NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(),
+                chrono::NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            ]
+        );
+        assert!(portfolios
+            .iter()
+            .all(|portfolio| portfolio.stocks_settled.is_empty()));
+
+        // Bought and held at the same flat price, so nothing is gained or lost on any day.
+        assert!(portfolios
+            .iter()
+            .all(|portfolio| portfolio.unrealized_pnl == 0));
+
+        let end_state = decision.get_end_state().unwrap();
+
+        assert_eq!(end_state.liquidity, 0);
+        assert_eq!(end_state.holdings.len(), 1);
+        assert_eq!(end_state.holdings[0].hold_date, start_date);
+        assert_eq!(end_state.holdings[0].num, 300);
     }
 
     #[test]
-    fn liquidity_check() {
+    fn tax_lots_check() {
         let mut mock_crawler = crawler::MockCrawler::new();
         let mut mock_backend_op = backend::MockBackendOp::new();
         let mut mock_strategy = strategy::MockStrategyAPI::new();
 
         mock_crawler
             .expect_get_stock_list()
-            .returning(|| Ok(vec!["0050".to_owned(), "0051".to_owned()]));
-        mock_backend_op
-            .expect_query()
-            .returning(|stock_id, date| match stock_id {
-                "0050" => match &date.format("%Y-%m-%d").to_string()[..] {
-                    "1970-01-01" => {
-                        return Ok(Some(schema::RawData {
-                            low: 2.0,
-                            high: 8.0,
-                            ..Default::default()
-                        }))
-                    }
-                    "1970-01-02" => {
-                        return Ok(Some(schema::RawData {
-                            low: 4.0,
-                            high: 16.0,
-                            ..Default::default()
-                        }))
-                    }
-                    _ => return Ok(None),
-                },
-                "0051" => match &date.format("%Y-%m-%d").to_string()[..] {
-                    "1970-01-01" => {
-                        return Ok(Some(schema::RawData {
-                            low: 4.0,
-                            high: 8.0,
-                            ..Default::default()
-                        }))
-                    }
-                    "1970-01-02" => {
-                        return Ok(Some(schema::RawData {
-                            low: 8.0,
-                            high: 16.0,
-                            ..Default::default()
-                        }))
-                    }
-                    _ => return Ok(None),
-                },
-                _ => return Ok(None),
-            });
-        mock_strategy
-            .expect_analyze()
-            .returning(|stock_id, assess_date| match stock_id {
-                "0050" => match &assess_date.format("%Y-%m-%d").to_string()[..] {
-                    "1970-01-01" => {
-                        return Ok(strategy::Score {
-                            point: 2,
-                            trading_volume: 10,
-                        })
-                    }
-                    "1970-01-02" => {
-                        return Ok(strategy::Score {
-                            point: 0,
-                            trading_volume: 0,
-                        })
-                    }
-                    _ => return Ok(strategy::Score::default()),
-                },
-                "0051" => match &assess_date.format("%Y-%m-%d").to_string()[..] {
-                    "1970-01-01" => {
-                        return Ok(strategy::Score {
-                            point: 4,
-                            trading_volume: 20,
-                        })
-                    }
-                    "1970-01-02" => {
-                        return Ok(strategy::Score {
-                            point: 0,
-                            trading_volume: 0,
-                        })
-                    }
-                    _ => return Ok(strategy::Score::default()),
-                },
-                _ => return Ok(strategy::Score::default()),
-            });
+            .returning(|| Ok(vec!["0050".to_owned()]));
+        mock_backend_op.expect_query().returning(|_, date| {
+            let price = match chrono::Datelike::day(&date) {
+                1 => 10.0,
+                2 => 12.0,
+                _ => 15.0,
+            };
+
+            Ok(Some(schema::RawData {
+                high: price,
+                low: price,
+                ..Default::default()
+            }))
+        });
+        mock_strategy.expect_analyze().returning(|_, assess_date| {
+            Ok(strategy::Score {
+                point: (assess_date == chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()) as i64,
+                trading_volume: 0,
+            })
+        });
         mock_strategy
             .expect_settle_check()
-            .returning(|_, _, _| Ok(true));
+            .returning(|_, _, assess_date| {
+                if assess_date == chrono::NaiveDate::from_ymd_opt(1970, 1, 3).unwrap() {
+                    return Ok(Some(strategy::SettleReason::SignalExit));
+                }
+                Ok(None)
+            });
 
         let mut decision = Decision::new(
             Rc::new(mock_crawler),
@@ -777,19 +4446,41 @@ mod decision_test {
             Rc::new(mock_strategy),
         );
 
-        decision.liquidity = 20;
+        decision.liquidity = 100;
+        decision.stocks_hold_num = 1;
 
         let mut portfolio = decision
             .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
             .unwrap()
             .unwrap();
 
-        assert_eq!(portfolio.liquidity, 4);
+        assert_eq!(portfolio.unrealized_pnl, 0);
 
         portfolio = decision
             .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 2).unwrap())
             .unwrap()
             .unwrap();
-        assert_eq!(portfolio.liquidity, 36);
+
+        assert_eq!(portfolio.unrealized_pnl, 20);
+        assert!(portfolio.realized_lots.is_empty());
+
+        portfolio = decision
+            .calc_portfolio(chrono::NaiveDate::from_ymd_opt(1970, 1, 3).unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(portfolio.unrealized_pnl, 0);
+        assert_eq!(
+            portfolio.realized_lots,
+            vec![lot::RealizedLot {
+                stock_id: "0050".to_owned(),
+                buy_date: chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
+                sell_date: chrono::NaiveDate::from_ymd_opt(1970, 1, 3).unwrap(),
+                num: 10,
+                buy_price: 10,
+                sell_price: 15,
+            }]
+        );
+        assert_eq!(portfolio.realized_pnl(), 50);
     }
 }
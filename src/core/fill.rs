@@ -0,0 +1,305 @@
+use serde::{Deserialize, Serialize};
+
+use crate::strategy::schema;
+
+use super::order;
+
+/// Shares in a board lot on the Taiwan Stock Exchange.
+pub const BOARD_LOT_SIZE: u32 = 1000;
+
+/// Prices move in ticks, so a bar closing at the limit usually lands slightly inside it.
+const LIMIT_TOLERANCE: f64 = 0.5;
+
+/// Daily price limit of the market. A bar trading at a single price at the limit is locked: sells
+/// cannot fill on a limit-down day, nor buys on a limit-up day.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceLimit {
+    /// Maximum move from the previous close, in percent.
+    pub limit: f64,
+}
+
+impl std::default::Default for PriceLimit {
+    fn default() -> Self {
+        PriceLimit { limit: 10.0 }
+    }
+}
+
+impl PriceLimit {
+    pub fn is_locked(&self, record: &schema::RawData, side: order::Side) -> bool {
+        let prev_close = record.close - record.spread;
+
+        if record.high != record.low || prev_close <= 0.0 {
+            return false;
+        }
+
+        let change = record.spread / prev_close * 100.0;
+
+        match side {
+            order::Side::Buy => change >= self.limit - LIMIT_TOLERANCE,
+            order::Side::Sell => change <= -(self.limit - LIMIT_TOLERANCE),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LotPolicy {
+    /// Any number of shares, traded in the odd-lot session.
+    OddLot,
+    /// Whole board lots only.
+    BoardLot,
+}
+
+impl LotPolicy {
+    pub fn get_lot_size(&self) -> u32 {
+        match self {
+            LotPolicy::OddLot => 1,
+            LotPolicy::BoardLot => BOARD_LOT_SIZE,
+        }
+    }
+}
+
+/// Keeps orders to what a broker would practically accept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderSize {
+    pub lot_policy: LotPolicy,
+    /// Orders worth less than this are skipped, as fees would eat most of them.
+    pub min_order_value: u32,
+}
+
+impl std::default::Default for OrderSize {
+    fn default() -> Self {
+        OrderSize {
+            lot_policy: LotPolicy::OddLot,
+            min_order_value: 1000,
+        }
+    }
+}
+
+impl OrderSize {
+    /// Rounds `num` down to whole lots, or `None` when the order has to be skipped.
+    pub fn adjust(&self, num: u32, price: u32) -> Option<u32> {
+        let lot_size = self.lot_policy.get_lot_size();
+        let num = num / lot_size * lot_size;
+
+        if num == 0 || num * price < self.min_order_value {
+            return None;
+        }
+        Some(num)
+    }
+}
+
+/// Cost of crossing the spread and moving the price, as a share of the bar's midpoint: buys fill
+/// above it and sells below it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Slippage {
+    /// Adverse move from the midpoint, in percent.
+    pub rate: f64,
+}
+
+impl std::default::Default for Slippage {
+    fn default() -> Self {
+        Slippage { rate: 0.1 }
+    }
+}
+
+impl Slippage {
+    pub fn apply(&self, price: f64, side: order::Side) -> f64 {
+        match side {
+            order::Side::Buy => price * (1.0 + self.rate / 100.0),
+            order::Side::Sell => price * (1.0 - self.rate / 100.0),
+        }
+    }
+}
+
+/// Smallest price step of a price band: prices from `from` up, until the next band, move in
+/// steps of `tick`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TickBand {
+    pub from: f64,
+    pub tick: f64,
+}
+
+/// Price steps orders are quoted in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TickRule {
+    /// Ticks of stocks on the Taiwan Stock Exchange, from 0.01 below 10 up to 5 from 1000 on.
+    Taiwan,
+    /// One tick at any price, as on most US and European exchanges.
+    Fixed(f64),
+    /// Bands of another market, sorted by `from`.
+    Bands(Vec<TickBand>),
+}
+
+impl TickRule {
+    pub fn get_tick(&self, price: f64) -> f64 {
+        match self {
+            TickRule::Taiwan => match price {
+                price if price < 10.0 => 0.01,
+                price if price < 50.0 => 0.05,
+                price if price < 100.0 => 0.1,
+                price if price < 500.0 => 0.5,
+                price if price < 1000.0 => 1.0,
+                _ => 5.0,
+            },
+            TickRule::Fixed(tick) => *tick,
+            TickRule::Bands(bands) => bands
+                .iter()
+                .rev()
+                .find(|band| price >= band.from)
+                .or(bands.first())
+                .map_or(0.0, |band| band.tick),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TickRounding {
+    /// Buys round up and sells down, so a fill never gets a better price than the market quotes.
+    Adverse,
+    Nearest,
+}
+
+/// Rounds fill prices to ticks the market would quote, so that even a small slippage costs at
+/// least a tick.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TickSize {
+    pub rule: TickRule,
+    pub rounding: TickRounding,
+}
+
+impl std::default::Default for TickSize {
+    fn default() -> Self {
+        TickSize {
+            rule: TickRule::Taiwan,
+            rounding: TickRounding::Adverse,
+        }
+    }
+}
+
+/// Keeps float error from pushing a price already on a tick to the next one.
+const TICK_EPSILON: f64 = 1e-6;
+
+impl TickSize {
+    pub fn round(&self, price: f64, side: order::Side) -> f64 {
+        let tick = self.rule.get_tick(price);
+
+        if tick <= 0.0 {
+            return price;
+        }
+
+        let ticks = price / tick;
+        let ticks = match (self.rounding, side) {
+            (TickRounding::Nearest, _) => ticks.round(),
+            (TickRounding::Adverse, order::Side::Buy) => (ticks - TICK_EPSILON).ceil(),
+            (TickRounding::Adverse, order::Side::Sell) => (ticks + TICK_EPSILON).floor(),
+        };
+
+        ticks * tick
+    }
+}
+
+/// Caps each fill at a share of the day's traded volume, so a large order in a thin stock fills
+/// over several days instead of all at once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VolumeCap {
+    /// Largest share of the day's `trading_volume` an order may take, in percent.
+    pub participation: f64,
+}
+
+impl std::default::Default for VolumeCap {
+    fn default() -> Self {
+        VolumeCap {
+            participation: 10.0,
+        }
+    }
+}
+
+impl VolumeCap {
+    /// Shares of an order for `num` that fill on the bar of `record`.
+    pub fn cap(&self, num: u32, record: &schema::RawData) -> u32 {
+        let limit = record.trading_volume as f64 * self.participation / 100.0;
+
+        num.min(limit.min(u32::MAX as f64) as u32)
+    }
+}
+
+/// Exits positions gradually, in equal tranches over `days` trading days at each day's fill price,
+/// rather than all at once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScaleOut {
+    pub days: usize,
+}
+
+impl std::default::Default for ScaleOut {
+    fn default() -> Self {
+        ScaleOut { days: 3 }
+    }
+}
+
+impl ScaleOut {
+    /// Shares of the `num` left to sell in `days_left` trading days sold today, in whole lots of
+    /// `lot_size` unless fewer are left.
+    pub fn get_tranche(&self, num: u32, days_left: usize, lot_size: u32) -> u32 {
+        get_tranche(num, days_left, lot_size)
+    }
+}
+
+/// Builds positions gradually, in equal tranches over `days` trading days at each day's fill
+/// price. The tranches left are cancelled once the strategy stops scoring the stock as a buy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScaleIn {
+    pub days: usize,
+}
+
+impl std::default::Default for ScaleIn {
+    fn default() -> Self {
+        ScaleIn { days: 3 }
+    }
+}
+
+impl ScaleIn {
+    /// Shares of the `num` left to buy in `days_left` trading days bought today, in whole lots of
+    /// `lot_size` unless fewer are left.
+    pub fn get_tranche(&self, num: u32, days_left: usize, lot_size: u32) -> u32 {
+        get_tranche(num, days_left, lot_size)
+    }
+}
+
+fn get_tranche(num: u32, days_left: usize, lot_size: u32) -> u32 {
+    let tranche = num.div_ceil(days_left.max(1) as u32);
+
+    (tranche.div_ceil(lot_size) * lot_size).min(num)
+}
+
+#[cfg(test)]
+mod fill_test {
+    use super::{order, TickBand, TickRule, TickSize};
+
+    #[test]
+    fn tick_size_check() {
+        let tick_size = TickSize::default();
+
+        assert_eq!(tick_size.rule.get_tick(9.99), 0.01);
+        assert_eq!(tick_size.rule.get_tick(10.0), 0.05);
+        assert_eq!(tick_size.rule.get_tick(99.9), 0.1);
+        assert_eq!(tick_size.rule.get_tick(1000.0), 5.0);
+        assert_eq!(tick_size.round(601.0, order::Side::Buy), 601.0);
+        assert_eq!(tick_size.round(600.6, order::Side::Buy), 601.0);
+        assert_eq!(tick_size.round(600.6, order::Side::Sell), 600.0);
+        assert_eq!(tick_size.round(1002.0, order::Side::Buy), 1005.0);
+
+        let rule = TickRule::Bands(vec![
+            TickBand {
+                from: 0.0,
+                tick: 0.01,
+            },
+            TickBand {
+                from: 1.0,
+                tick: 0.05,
+            },
+        ]);
+
+        assert_eq!(rule.get_tick(0.5), 0.01);
+        assert_eq!(rule.get_tick(3.0), 0.05);
+    }
+}
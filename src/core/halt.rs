@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HaltAction {
+    /// Keep the position and its last known value, only flag it as illiquid.
+    MarkIlliquid,
+    /// Flag the position and leave it out of the equity until trading resumes.
+    ExcludeFromEquity,
+    /// Flag the position and settle it on the first day trading resumes.
+    SettleOnResume,
+}
+
+/// Treats a held stock without records for `min_days` consecutive trading days as halted.
+/// Days are only counted when the missing data policy lets the engine run through them, i.e.
+/// not under `MissingDataPolicy::SkipDay`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HaltPolicy {
+    pub min_days: usize,
+    pub action: HaltAction,
+}
+
+impl std::default::Default for HaltPolicy {
+    fn default() -> Self {
+        HaltPolicy {
+            min_days: 3,
+            action: HaltAction::MarkIlliquid,
+        }
+    }
+}
+
+impl HaltPolicy {
+    pub fn is_halted(&self, missing_days: usize) -> bool {
+        missing_days >= self.min_days
+    }
+}
+
+/// Halt state of a held position.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Halt {
+    pub days: usize,
+    pub excluded_from_equity: bool,
+}
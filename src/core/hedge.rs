@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hedge {
+    /// Stock id of the hedging instrument, e.g. an inverse ETF.
+    pub instrument_id: String,
+    /// Hedge value as a percentage of the stock exposure.
+    pub ratio: f64,
+    /// Ratio used instead while the regime filter deems the market unfavorable.
+    pub unfavorable_ratio: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HedgePosition {
+    pub instrument_id: String,
+    pub num: u32,
+    pub price: u32,
+    /// Profit of the hedge since the start of the run, realized and unrealized.
+    pub pnl: i64,
+}
+
+impl std::default::Default for Hedge {
+    fn default() -> Self {
+        Hedge {
+            instrument_id: "00632R".to_owned(),
+            ratio: 50.0,
+            unfavorable_ratio: 100.0,
+        }
+    }
+}
+
+impl Hedge {
+    pub fn get_ratio(&self, favorable_regime: bool) -> f64 {
+        if favorable_regime {
+            return self.ratio;
+        }
+        self.unfavorable_ratio
+    }
+
+    /// Share of the liquidity kept aside for the hedge when opening stock positions.
+    pub fn get_reserve_ratio(&self) -> f64 {
+        let ratio = self.ratio.max(self.unfavorable_ratio) / 100.0;
+
+        ratio / (1.0 + ratio)
+    }
+}
+
+impl HedgePosition {
+    pub fn value(&self) -> u32 {
+        self.num * self.price
+    }
+}
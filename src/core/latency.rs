@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+
+use crate::report::html;
+use crate::storage::run;
+
+pub const LATENCY_DIRECTORY: &str = "latency";
+pub const LATENCY_REPORT_FILENAME: &str = "latency_report.yaml";
+pub const LATENCY_TABLE_FILENAME: &str = "latency_report.html";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyResult {
+    /// Trading days between a signal and its orders.
+    pub latency: usize,
+    pub metrics: run::RunMetrics,
+}
+
+/// Metrics of one backtest run with each signal latency, shortest first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LatencyReport {
+    pub results: Vec<LatencyResult>,
+}
+
+impl LatencyReport {
+    /// Time-weighted return lost per trading day of latency, in points, by a least-squares fit
+    /// over the runs.
+    pub fn get_return_per_day(&self) -> f64 {
+        let count = self.results.len() as f64;
+        let mean_latency = self
+            .results
+            .iter()
+            .map(|result| result.latency as f64)
+            .sum::<f64>()
+            / count;
+        let mean_return = self
+            .results
+            .iter()
+            .map(|result| result.metrics.time_weighted_return)
+            .sum::<f64>()
+            / count;
+        let mut covariance = 0.0;
+        let mut variance = 0.0;
+
+        for result in &self.results {
+            let latency = result.latency as f64 - mean_latency;
+
+            covariance += latency * (result.metrics.time_weighted_return - mean_return);
+            variance += latency.powi(2);
+        }
+        match variance > 0.0 {
+            true => -covariance / variance,
+            false => 0.0,
+        }
+    }
+
+    pub fn to_html(&self) -> String {
+        let base_return = match self.results.first() {
+            Some(result) => result.metrics.time_weighted_return,
+            None => 0.0,
+        };
+
+        html::get_page(
+            "Signal latency",
+            &format!(
+                "<p>Return lost per trading day of latency: {:+.2} points.</p>{}",
+                self.get_return_per_day(),
+                html::get_table(
+                    &[
+                        "Latency",
+                        "Return",
+                        "Change",
+                        "Max drawdown",
+                        "Trades",
+                        "Win rate",
+                    ],
+                    self.results
+                        .iter()
+                        .map(|result| {
+                            vec![
+                                format!("T+{}", result.latency),
+                                format!("{:+.2}%", result.metrics.time_weighted_return),
+                                format!(
+                                    "{:+.2}",
+                                    result.metrics.time_weighted_return - base_return
+                                ),
+                                format!("{:.2}%", result.metrics.max_drawdown),
+                                result.metrics.trade_count.to_string(),
+                                format!("{:.1}%", result.metrics.win_rate),
+                            ]
+                        })
+                        .collect(),
+                )
+            ),
+            false,
+        )
+    }
+}
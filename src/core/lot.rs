@@ -0,0 +1,109 @@
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+/// Shares bought together at one price.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaxLot {
+    pub date: chrono::NaiveDate,
+    pub num: u32,
+    pub price: u32,
+}
+
+/// The part of a lot closed by a sale.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RealizedLot {
+    pub stock_id: String,
+    pub buy_date: chrono::NaiveDate,
+    pub sell_date: chrono::NaiveDate,
+    pub num: u32,
+    pub buy_price: u32,
+    pub sell_price: u32,
+}
+
+impl RealizedLot {
+    pub fn get_pnl(&self) -> i64 {
+        (self.sell_price as i64 - self.buy_price as i64) * self.num as i64
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct YearlyPnl {
+    pub year: i32,
+    pub realized: i64,
+    /// Unrealized P&L on the last trading day of the year.
+    pub unrealized: i64,
+}
+
+/// Open lots per stock, closed first in, first out.
+#[derive(Debug, Default)]
+pub struct LotBook {
+    lots: HashMap<String, VecDeque<TaxLot>>,
+}
+
+impl LotBook {
+    pub fn buy(&mut self, stock_id: &str, date: chrono::NaiveDate, num: u32, price: u32) {
+        if num == 0 {
+            return;
+        }
+        self.lots
+            .entry(stock_id.to_owned())
+            .or_default()
+            .push_back(TaxLot { date, num, price });
+    }
+
+    /// Closes `num` shares against the oldest lots. Shares beyond the open lots are ignored.
+    pub fn sell(
+        &mut self,
+        stock_id: &str,
+        date: chrono::NaiveDate,
+        mut num: u32,
+        price: u32,
+    ) -> Vec<RealizedLot> {
+        let mut realized_lots = Vec::new();
+        let lots = match self.lots.get_mut(stock_id) {
+            Some(lots) => lots,
+            None => return realized_lots,
+        };
+
+        while num > 0 {
+            let lot = match lots.front_mut() {
+                Some(lot) => lot,
+                None => break,
+            };
+            let lot_num = lot.num.min(num);
+
+            realized_lots.push(RealizedLot {
+                stock_id: stock_id.to_owned(),
+                buy_date: lot.date,
+                sell_date: date,
+                num: lot_num,
+                buy_price: lot.price,
+                sell_price: price,
+            });
+            lot.num -= lot_num;
+            num -= lot_num;
+            if lot.num == 0 {
+                lots.pop_front();
+            }
+        }
+        if lots.is_empty() {
+            self.lots.remove(stock_id);
+        }
+        realized_lots
+    }
+
+    pub fn get_lots(&self, stock_id: &str) -> Vec<TaxLot> {
+        match self.lots.get(stock_id) {
+            Some(lots) => lots.iter().cloned().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn get_cost_basis(&self, stock_id: &str) -> i64 {
+        self.get_lots(stock_id)
+            .iter()
+            .map(|lot| lot.num as i64 * lot.price as i64)
+            .sum()
+    }
+}
@@ -1,4 +1,24 @@
 pub mod backtesting;
+pub mod bulk;
+pub mod calendar;
+pub mod cashflow;
+pub mod clock;
 pub mod decision;
+pub mod fill;
+pub mod halt;
+pub mod hedge;
+pub mod latency;
+pub mod lot;
+pub mod order;
+pub mod pairs;
+pub mod picks;
+pub mod position;
+pub mod prefetch;
+pub mod profiler;
+pub mod reconcile;
+pub mod regime;
+pub mod risk;
+pub mod scaling;
+pub mod scenario;
+pub mod shock;
 pub mod utils;
-
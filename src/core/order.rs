@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+
+use super::decision;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Order {
+    pub side: Side,
+    pub stock_id: String,
+    pub num: u32,
+    /// Reference price the decision was made at; the fill may differ.
+    pub price: u32,
+}
+
+impl Order {
+    pub fn get_value(&self) -> u32 {
+        self.num * self.price
+    }
+}
+
+impl std::fmt::Display for Order {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.side {
+            Side::Buy => write!(
+                fmt,
+                "BUY  {} shares of {} at ~{} ({})",
+                self.num,
+                self.stock_id,
+                self.price,
+                self.get_value()
+            ),
+            Side::Sell => write!(
+                fmt,
+                "SELL all {} shares of {} at ~{} ({})",
+                self.num,
+                self.stock_id,
+                self.price,
+                self.get_value()
+            ),
+        }
+    }
+}
+
+/// Orders to place for a day's decision: positions settled are sold, newly selected ones are
+/// bought.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderPlan {
+    pub date: chrono::NaiveDate,
+    pub orders: Vec<Order>,
+    /// Cash left once every order is filled at its reference price.
+    pub liquidity: u32,
+}
+
+impl OrderPlan {
+    pub fn from_portfolio(portfolio: &decision::Portfolio) -> Self {
+        let mut orders = Vec::new();
+
+        for stock_info in &portfolio.stocks_settled {
+            orders.push(Order {
+                side: Side::Sell,
+                stock_id: stock_info.stock_id.to_owned(),
+                num: stock_info.num,
+                price: stock_info.price,
+            });
+        }
+        for stock_info in &portfolio.stocks_selected {
+            orders.push(Order {
+                side: Side::Buy,
+                stock_id: stock_info.stock_id.to_owned(),
+                num: stock_info.num,
+                price: stock_info.price,
+            });
+        }
+
+        OrderPlan {
+            date: portfolio.date,
+            orders,
+            liquidity: portfolio.liquidity,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl std::fmt::Display for OrderPlan {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(fmt, "Orders for {}", self.date)?;
+        if self.orders.is_empty() {
+            writeln!(fmt, "No orders")?;
+        }
+        for order in &self.orders {
+            writeln!(fmt, "{}", order)?;
+        }
+        write!(fmt, "Cash after orders: {}", self.liquidity)
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::crosssection::cointegration;
+use crate::storage::backend;
+
+use super::order;
+
+pub const PAIRS_FILENAME: &str = "pairs.yaml";
+
+/// Pair of stocks traded on their spread, `ln(first) - hedge_ratio * ln(second)`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pair {
+    pub first_id: String,
+    pub second_id: String,
+    pub hedge_ratio: f64,
+}
+
+impl From<&cointegration::Cointegration> for Pair {
+    fn from(cointegration: &cointegration::Cointegration) -> Pair {
+        Pair {
+            first_id: cointegration.first_id.to_owned(),
+            second_id: cointegration.second_id.to_owned(),
+            hedge_ratio: cointegration.hedge_ratio,
+        }
+    }
+}
+
+/// Pairs traded alongside the strategy: once the spread of a pair strays `entry_z` standard
+/// deviations from its mean, the rich stock is sold short and the cheap one bought, and both
+/// legs are closed together once the spread is back within `exit_z`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PairsTrading {
+    pub pairs: Vec<Pair>,
+    /// Trading days the mean and standard deviation of the spread are taken over.
+    #[serde(default = "default_lookback")]
+    pub lookback: usize,
+    #[serde(default = "default_entry_z")]
+    pub entry_z: f64,
+    #[serde(default = "default_exit_z")]
+    pub exit_z: f64,
+    /// Liquidity put into the first leg of a pair when it opens, in percent; the second leg
+    /// gets that times the hedge ratio.
+    #[serde(default = "default_leg_ratio")]
+    pub leg_ratio: f64,
+}
+
+fn default_lookback() -> usize {
+    60
+}
+
+fn default_entry_z() -> f64 {
+    2.0
+}
+
+fn default_exit_z() -> f64 {
+    0.5
+}
+
+fn default_leg_ratio() -> f64 {
+    10.0
+}
+
+impl PairsTrading {
+    pub fn new(pairs: Vec<Pair>) -> Self {
+        PairsTrading {
+            pairs,
+            lookback: default_lookback(),
+            entry_z: default_entry_z(),
+            exit_z: default_exit_z(),
+            leg_ratio: default_leg_ratio(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Side {
+    Long,
+    Short,
+}
+
+/// Stock of a pair position. Short legs are fully collateralized: their notional at entry is
+/// set aside from the liquidity, and returned with the profit or loss of the short on cover.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Leg {
+    pub stock_id: String,
+    pub side: Side,
+    pub num: u32,
+    pub entry_price: u32,
+    /// Price the leg was last valued at.
+    pub price: u32,
+}
+
+impl Leg {
+    /// Side of the order opening the leg.
+    pub fn get_entry_side(&self) -> order::Side {
+        match self.side {
+            Side::Long => order::Side::Buy,
+            Side::Short => order::Side::Sell,
+        }
+    }
+
+    /// Side of the order closing the leg.
+    pub fn get_exit_side(&self) -> order::Side {
+        match self.side {
+            Side::Long => order::Side::Sell,
+            Side::Short => order::Side::Buy,
+        }
+    }
+
+    /// Liquidity the leg takes when opened.
+    pub fn cost(&self) -> u32 {
+        self.num * self.entry_price
+    }
+
+    pub fn pnl(&self) -> i64 {
+        let change = self.price as i64 - self.entry_price as i64;
+
+        match self.side {
+            Side::Long => change * self.num as i64,
+            Side::Short => -change * self.num as i64,
+        }
+    }
+
+    /// Liquidity the leg gives back when closed at its last price.
+    pub fn value(&self) -> i64 {
+        self.cost() as i64 + self.pnl()
+    }
+}
+
+/// Legs of a pair, opened and closed on the same day.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PairPosition {
+    pub pair: Pair,
+    pub open_date: chrono::NaiveDate,
+    /// Z-score of the spread the position was opened at.
+    pub entry_z: f64,
+    pub legs: Vec<Leg>,
+}
+
+impl PairPosition {
+    /// Position betting on the spread of `pair` reverting from `z_score`, with legs yet to be
+    /// sized and filled.
+    pub fn new(pair: &Pair, open_date: chrono::NaiveDate, z_score: f64) -> Self {
+        // A spread above its mean has the first stock rich against the second.
+        let (first_side, second_side) = match z_score > 0.0 {
+            true => (Side::Short, Side::Long),
+            false => (Side::Long, Side::Short),
+        };
+
+        PairPosition {
+            pair: pair.clone(),
+            open_date,
+            entry_z: z_score,
+            legs: vec![
+                Leg {
+                    stock_id: pair.first_id.to_owned(),
+                    side: first_side,
+                    num: 0,
+                    entry_price: 0,
+                    price: 0,
+                },
+                Leg {
+                    stock_id: pair.second_id.to_owned(),
+                    side: second_side,
+                    num: 0,
+                    entry_price: 0,
+                    price: 0,
+                },
+            ],
+        }
+    }
+
+    pub fn cost(&self) -> u32 {
+        self.legs.iter().map(|leg| leg.cost()).sum()
+    }
+
+    pub fn pnl(&self) -> i64 {
+        self.legs.iter().map(|leg| leg.pnl()).sum()
+    }
+
+    pub fn value(&self) -> i64 {
+        self.legs.iter().map(|leg| leg.value()).sum()
+    }
+}
+
+/// Z-score of the spread of `pair` on `assess_date`, against the spreads of the last `lookback`
+/// days both stocks traded on, that day included. None when either stock has no record on
+/// `assess_date`, with fewer common days, or with a constant spread.
+pub fn get_z_score(
+    backend_op: &dyn backend::BackendOp,
+    pair: &Pair,
+    lookback: usize,
+    assess_date: chrono::NaiveDate,
+) -> Result<Option<f64>, backend::Error> {
+    let start_date = assess_date - chrono::Duration::days(lookback as i64 * 2);
+    let first_closes: BTreeMap<chrono::NaiveDate, f64> = backend_op
+        .query_by_range(&pair.first_id, start_date, assess_date)?
+        .iter()
+        .map(|record| (record.date, record.close))
+        .collect();
+    let mut spreads: Vec<(chrono::NaiveDate, f64)> = backend_op
+        .query_by_range(&pair.second_id, start_date, assess_date)?
+        .iter()
+        .filter_map(|record| {
+            first_closes.get(&record.date).map(|first| {
+                (
+                    record.date,
+                    first.ln() - pair.hedge_ratio * record.close.ln(),
+                )
+            })
+        })
+        .collect();
+
+    spreads.sort_by_key(|(date, _)| *date);
+    match spreads.last() {
+        Some((date, _)) if *date == assess_date && lookback >= 2 && spreads.len() >= lookback => {}
+        _ => return Ok(None),
+    }
+
+    let spreads: Vec<f64> = spreads[spreads.len() - lookback..]
+        .iter()
+        .map(|(_, spread)| *spread)
+        .collect();
+    let mean = spreads.iter().sum::<f64>() / lookback as f64;
+    let variance = spreads
+        .iter()
+        .map(|spread| (spread - mean).powi(2))
+        .sum::<f64>()
+        / lookback as f64;
+
+    if variance == 0.0 {
+        return Ok(None);
+    }
+    Ok(Some((spreads[lookback - 1] - mean) / variance.sqrt()))
+}
+
+#[cfg(test)]
+mod pairs_test {
+    use crate::core::order;
+
+    use super::{Pair, PairPosition, Side};
+
+    #[test]
+    fn pair_position_check() {
+        let pair = Pair {
+            first_id: "2330".to_owned(),
+            second_id: "2303".to_owned(),
+            hedge_ratio: 1.0,
+        };
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+        let mut position = PairPosition::new(&pair, date, 2.5);
+
+        assert_eq!(position.legs[0].side, Side::Short);
+        assert_eq!(position.legs[0].get_entry_side(), order::Side::Sell);
+        assert_eq!(position.legs[1].side, Side::Long);
+        assert_eq!(position.legs[1].get_exit_side(), order::Side::Sell);
+
+        for (leg, (num, price)) in position.legs.iter_mut().zip([(10, 100), (20, 50)]) {
+            leg.num = num;
+            leg.entry_price = price;
+            leg.price = price;
+        }
+        assert_eq!(position.cost(), 2000);
+
+        // The rich stock falls back, the cheap one catches up.
+        position.legs[0].price = 90;
+        position.legs[1].price = 55;
+        assert_eq!(position.pnl(), 200);
+        assert_eq!(position.value(), 2200);
+
+        // A short can lose more than the collateral set aside for it.
+        position.legs[0].price = 250;
+        assert_eq!(position.legs[0].value(), -500);
+    }
+}
@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+
+use super::decision;
+
+/// Change of the recommendations from one decision to the next.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PicksDiff {
+    pub date: chrono::NaiveDate,
+    /// Stocks recommended to buy that were not recommended before.
+    pub new: Vec<String>,
+    /// Stocks recommended before that are no longer, without being settled.
+    pub removed: Vec<String>,
+    /// Stocks recommended to settle.
+    pub settle: Vec<String>,
+}
+
+fn get_recommended(portfolio: &decision::Portfolio) -> Vec<&str> {
+    portfolio
+        .stocks_hold
+        .iter()
+        .chain(&portfolio.stocks_selected)
+        .map(|stock_info| stock_info.stock_id.as_str())
+        .collect()
+}
+
+impl PicksDiff {
+    /// Diff of `portfolio` against `previous`, the portfolio of the trading day before it, or
+    /// against nothing when there is none.
+    pub fn build(previous: Option<&decision::Portfolio>, portfolio: &decision::Portfolio) -> Self {
+        let recommended_before = previous.map_or(Vec::new(), get_recommended);
+        let recommended = get_recommended(portfolio);
+        let settle: Vec<String> = portfolio
+            .stocks_settled
+            .iter()
+            .map(|stock_info| stock_info.stock_id.to_owned())
+            .collect();
+
+        PicksDiff {
+            date: portfolio.date,
+            new: portfolio
+                .stocks_selected
+                .iter()
+                .map(|stock_info| stock_info.stock_id.to_owned())
+                .filter(|stock_id| !recommended_before.contains(&stock_id.as_str()))
+                .collect(),
+            removed: recommended_before
+                .iter()
+                .filter(|stock_id| !recommended.contains(stock_id))
+                .filter(|stock_id| !settle.iter().any(|settled| settled == *stock_id))
+                .map(|stock_id| stock_id.to_string())
+                .collect(),
+            settle,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.new.is_empty() && self.removed.is_empty() && self.settle.is_empty()
+    }
+}
+
+impl std::fmt::Display for PicksDiff {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut parts = Vec::new();
+
+        for (name, stock_ids) in [
+            ("new", &self.new),
+            ("removed", &self.removed),
+            ("settle", &self.settle),
+        ] {
+            if !stock_ids.is_empty() {
+                parts.push(format!("{}: {}", name, stock_ids.join(", ")));
+            }
+        }
+        if parts.is_empty() {
+            parts.push("no changes".to_owned());
+        }
+        write!(fmt, "Picks of {}: {}", self.date, parts.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod picks_test {
+    use crate::core::decision::{Portfolio, StockInfo};
+
+    use super::PicksDiff;
+
+    #[test]
+    fn build_check() {
+        let get_stock_info = |stock_id: &str| StockInfo {
+            stock_id: stock_id.to_owned(),
+            num: 1000,
+            price: 100,
+            settle_reason: None,
+            halt: None,
+            position_note: None,
+        };
+        let previous = Portfolio {
+            stocks_hold: vec![get_stock_info("2603"), get_stock_info("2412")],
+            stocks_selected: vec![get_stock_info("2317")],
+            ..Default::default()
+        };
+        let portfolio = Portfolio {
+            date: chrono::NaiveDate::from_ymd_opt(2024, 7, 2).unwrap(),
+            stocks_hold: vec![get_stock_info("2317")],
+            stocks_selected: vec![get_stock_info("2330")],
+            stocks_settled: vec![get_stock_info("2603")],
+            ..Default::default()
+        };
+        let diff = PicksDiff::build(Some(&previous), &portfolio);
+
+        assert_eq!(diff.new, vec!["2330"]);
+        assert_eq!(diff.removed, vec!["2412"]);
+        assert_eq!(diff.settle, vec!["2603"]);
+        assert_eq!(
+            diff.to_string(),
+            "Picks of 2024-07-02: new: 2330, removed: 2412, settle: 2603"
+        );
+        assert!(PicksDiff::build(Some(&previous), &previous).is_empty());
+        assert_eq!(PicksDiff::build(None, &previous).new, vec!["2317"]);
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Position notes are kept next to the portfolio file, in the portfolio path.
+pub const POSITION_NOTES_FILENAME: &str = "position_notes.yaml";
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Yaml(serde_yaml::Error),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_yaml::Error> for Error {
+    fn from(err: serde_yaml::Error) -> Error {
+        Error::Yaml(err)
+    }
+}
+
+/// What the trader attached to a position by hand.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PositionNote {
+    /// Keeps the engine from settling the position, whatever its exit checks, the circuit breaker
+    /// or the missing data policy say; it is left to the trader to close it.
+    #[serde(default)]
+    pub manual_hold: bool,
+    #[serde(default)]
+    pub note: String,
+}
+
+impl PositionNote {
+    pub fn is_empty(&self) -> bool {
+        !self.manual_hold && self.note.is_empty()
+    }
+}
+
+/// Notes by stock id.
+pub type PositionNotes = BTreeMap<String, PositionNote>;
+
+pub fn is_manual_hold(position_notes: &PositionNotes, stock_id: &str) -> bool {
+    position_notes
+        .get(stock_id)
+        .is_some_and(|position_note| position_note.manual_hold)
+}
+
+/// Notes stored at `path`; none when there is no such file yet.
+pub fn load_position_notes(path: &str) -> Result<PositionNotes, Error> {
+    match std::fs::read_to_string(path) {
+        Ok(data) => Ok(serde_yaml::from_str(&data)?),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(PositionNotes::new()),
+        Err(err) => Err(Error::Io(err)),
+    }
+}
+
+/// Stores `position_notes` at `path`, leaving out the empty ones.
+pub fn save_position_notes(path: &str, position_notes: &PositionNotes) -> Result<(), Error> {
+    let position_notes: PositionNotes = position_notes
+        .iter()
+        .filter(|(_, position_note)| !position_note.is_empty())
+        .map(|(stock_id, position_note)| (stock_id.to_owned(), position_note.clone()))
+        .collect();
+    let data = serde_yaml::to_string(&position_notes)?;
+
+    Ok(std::fs::write(path, data)?)
+}
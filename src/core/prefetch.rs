@@ -0,0 +1,187 @@
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::crawler::crawler;
+use crate::storage::backend;
+use crate::strategy::strategy;
+
+use super::clock;
+
+/// Calendar days without records still taken for market holidays, Lunar New Year included.
+const MAX_HOLIDAY_DAYS: i64 = 14;
+
+#[derive(Debug)]
+pub enum Error {
+    Backend(backend::Error),
+    Crawler(crawler::Error),
+    MissingData {
+        stock_id: String,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    },
+}
+
+impl From<backend::Error> for Error {
+    fn from(err: backend::Error) -> Error {
+        Error::Backend(err)
+    }
+}
+
+impl From<crawler::Error> for Error {
+    fn from(err: crawler::Error) -> Error {
+        Error::Crawler(err)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Backend(err) => write!(fmt, "backend error: {:?}", err),
+            Error::Crawler(err) => write!(fmt, "crawler error: {:?}", err),
+            Error::MissingData {
+                stock_id,
+                start_date,
+                end_date,
+            } => write!(
+                fmt,
+                "missing data for {} {}..{}",
+                stock_id,
+                start_date.format("%Y-%m"),
+                end_date.format("%Y-%m")
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DataCheck {
+    /// Fails on the first gap in the required data.
+    Verify,
+    /// Crawls the gaps into the backend; gaps the crawler cannot fill are left as they are.
+    Prefetch,
+}
+
+pub struct Prefetcher {
+    pub crawler: Rc<dyn crawler::Crawler>,
+    pub backend_op: Rc<dyn backend::BackendOp>,
+    /// Data is not expected past its today.
+    pub clock: Rc<dyn clock::Clock>,
+}
+
+impl Prefetcher {
+    pub fn new(crawler: Rc<dyn crawler::Crawler>, backend_op: Rc<dyn backend::BackendOp>) -> Self {
+        Prefetcher {
+            crawler,
+            backend_op,
+            clock: Rc::new(clock::SystemClock),
+        }
+    }
+
+    /// Checks the datasets of `requirements` over the window of a run from `start_date` to
+    /// `end_date`. Stocks of the stock list may be listed or delisted within the window, so only
+    /// their inner gaps count; instruments have to cover the whole window.
+    pub fn prepare(
+        &self,
+        requirements: &strategy::DataRequirements,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+        data_check: DataCheck,
+    ) -> Result<(), Error> {
+        let (start_date, end_date) = requirements.get_window(start_date, end_date);
+        let end_date = end_date.min(self.clock.today());
+
+        for dataset in &requirements.datasets {
+            match dataset {
+                strategy::Dataset::StockPrices => {
+                    for stock_id in self.crawler.get_stock_list()? {
+                        self.prepare_stock(&stock_id, start_date, end_date, false, data_check)?;
+                    }
+                }
+                strategy::Dataset::InstrumentPrices(stock_id) => {
+                    self.prepare_stock(stock_id, start_date, end_date, true, data_check)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn prepare_stock(
+        &self,
+        stock_id: &str,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+        full_window: bool,
+        data_check: DataCheck,
+    ) -> Result<(), Error> {
+        let records = self
+            .backend_op
+            .query_by_range(stock_id, start_date, end_date)?;
+        let dates: Vec<chrono::NaiveDate> = records.iter().map(|record| record.date).collect();
+
+        for (gap_start_date, gap_end_date) in get_gaps(&dates, start_date, end_date, full_window) {
+            match data_check {
+                DataCheck::Verify => {
+                    return Err(Error::MissingData {
+                        stock_id: stock_id.to_owned(),
+                        start_date: gap_start_date,
+                        end_date: gap_end_date,
+                    })
+                }
+                DataCheck::Prefetch => {
+                    let records = self.crawler.get_stock_data(&crawler::Args {
+                        stock_id: stock_id.to_owned(),
+                        start_date: gap_start_date,
+                        end_date: gap_end_date,
+                    })?;
+
+                    self.backend_op.batch_insert(
+                        &records
+                            .into_iter()
+                            .map(|record| (stock_id.to_owned(), record))
+                            .collect(),
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Ranges within `start_date..=end_date` longer than a holiday without any of `dates`, which are
+/// sorted. Without `full_window`, the ranges before the first and after the last date are not
+/// gaps.
+pub fn get_gaps(
+    dates: &[chrono::NaiveDate],
+    start_date: chrono::NaiveDate,
+    end_date: chrono::NaiveDate,
+    full_window: bool,
+) -> Vec<(chrono::NaiveDate, chrono::NaiveDate)> {
+    let mut gaps = Vec::new();
+    let (first_date, last_date) = match (dates.first(), dates.last()) {
+        (Some(first_date), Some(last_date)) => (*first_date, *last_date),
+        _ => {
+            if full_window {
+                gaps.push((start_date, end_date));
+            }
+            return gaps;
+        }
+    };
+    let max_gap = chrono::Duration::days(MAX_HOLIDAY_DAYS);
+
+    if full_window && first_date - start_date > max_gap {
+        gaps.push((start_date, first_date - chrono::Duration::days(1)));
+    }
+    for pair in dates.windows(2) {
+        if pair[1] - pair[0] > max_gap {
+            gaps.push((
+                pair[0] + chrono::Duration::days(1),
+                pair[1] - chrono::Duration::days(1),
+            ));
+        }
+    }
+    if full_window && end_date - last_date > max_gap {
+        gaps.push((last_date + chrono::Duration::days(1), end_date));
+    }
+    gaps
+}
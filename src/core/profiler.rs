@@ -0,0 +1,239 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::crawler::crawler;
+use crate::diagram::diagram;
+use crate::storage::backend;
+use crate::strategy::{schema, strategy};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Phase {
+    Decision,
+    Crawling,
+    BackendQuery,
+    StrategyAnalysis,
+    Export,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Timing {
+    pub phase: Phase,
+    pub calls: u64,
+    pub duration: Duration,
+}
+
+/// Accumulates wall-clock time per phase. Phases nest: while an inner phase runs, the outer
+/// one is paused, so every duration is exclusive of the phases entered within it.
+#[derive(Default)]
+pub struct Profiler {
+    stack: RefCell<Vec<(Phase, Instant)>>,
+    timings: RefCell<Vec<Timing>>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Profiler::default()
+    }
+
+    pub fn enter(&self, phase: Phase) {
+        let now = Instant::now();
+        let mut stack = self.stack.borrow_mut();
+
+        if let Some((outer_phase, start)) = stack.last() {
+            self.add(*outer_phase, now - *start, 0);
+        }
+        stack.push((phase, now));
+    }
+
+    pub fn exit(&self) {
+        let now = Instant::now();
+        let mut stack = self.stack.borrow_mut();
+
+        if let Some((phase, start)) = stack.pop() {
+            self.add(phase, now - start, 1);
+        }
+        if let Some((_, start)) = stack.last_mut() {
+            *start = now;
+        }
+    }
+
+    pub fn measure<T, F: FnOnce() -> T>(&self, phase: Phase, f: F) -> T {
+        self.enter(phase);
+
+        let result = f();
+
+        self.exit();
+        result
+    }
+
+    fn add(&self, phase: Phase, duration: Duration, calls: u64) {
+        let mut timings = self.timings.borrow_mut();
+
+        match timings.iter_mut().find(|timing| timing.phase == phase) {
+            Some(timing) => {
+                timing.calls += calls;
+                timing.duration += duration;
+            }
+            None => timings.push(Timing {
+                phase,
+                calls,
+                duration,
+            }),
+        }
+    }
+
+    pub fn get_timings(&self) -> Vec<Timing> {
+        let mut timings = self.timings.borrow().clone();
+
+        timings.sort_by_key(|timing| std::cmp::Reverse(timing.duration));
+        timings
+    }
+
+    pub fn report(&self) -> String {
+        let timings = self.get_timings();
+        let total: Duration = timings.iter().map(|timing| timing.duration).sum();
+        let mut lines = Vec::new();
+
+        for timing in &timings {
+            let ratio = if total.is_zero() {
+                0.0
+            } else {
+                timing.duration.as_secs_f64() / total.as_secs_f64() * 100.0
+            };
+
+            lines.push(format!(
+                "{:<18} {:>10} calls {:>12.3}s {:>6.2}%",
+                format!("{:?}", timing.phase),
+                timing.calls,
+                timing.duration.as_secs_f64(),
+                ratio
+            ));
+        }
+        lines.push(format!("{:<18} {:>29.3}s", "Total", total.as_secs_f64()));
+        lines.join("\n")
+    }
+}
+
+pub struct ProfiledCrawler {
+    pub crawler: Rc<dyn crawler::Crawler>,
+    pub profiler: Rc<Profiler>,
+}
+
+impl crawler::Crawler for ProfiledCrawler {
+    fn get_stock_data(&self, args: &crawler::Args) -> Result<Vec<schema::RawData>, crawler::Error> {
+        self.profiler
+            .measure(Phase::Crawling, || self.crawler.get_stock_data(args))
+    }
+
+    fn get_stock_list(&self) -> Result<Vec<String>, crawler::Error> {
+        self.profiler
+            .measure(Phase::Crawling, || self.crawler.get_stock_list())
+    }
+}
+
+pub struct ProfiledBackend {
+    pub backend_op: Rc<dyn backend::BackendOp>,
+    pub profiler: Rc<Profiler>,
+}
+
+impl backend::BackendOp for ProfiledBackend {
+    fn batch_insert(&self, records: &Vec<(String, schema::RawData)>) -> Result<(), backend::Error> {
+        self.profiler.measure(Phase::BackendQuery, || {
+            self.backend_op.batch_insert(records)
+        })
+    }
+
+    fn query(
+        &self,
+        stock_id: &str,
+        date: chrono::NaiveDate,
+    ) -> Result<Option<schema::RawData>, backend::Error> {
+        self.profiler.measure(Phase::BackendQuery, || {
+            self.backend_op.query(stock_id, date)
+        })
+    }
+
+    fn query_by_range(
+        &self,
+        stock_id: &str,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> Result<Vec<schema::RawData>, backend::Error> {
+        self.profiler.measure(Phase::BackendQuery, || {
+            self.backend_op
+                .query_by_range(stock_id, start_date, end_date)
+        })
+    }
+
+    fn query_all(&self, stock_id: &str) -> Result<Vec<schema::RawData>, backend::Error> {
+        self.profiler
+            .measure(Phase::BackendQuery, || self.backend_op.query_all(stock_id))
+    }
+
+    fn batch_delete(
+        &self,
+        records: &Vec<(String, chrono::NaiveDate)>,
+    ) -> Result<(), backend::Error> {
+        self.profiler.measure(Phase::BackendQuery, || {
+            self.backend_op.batch_delete(records)
+        })
+    }
+
+    fn get_generation(&self) -> Result<u64, backend::Error> {
+        self.backend_op.get_generation()
+    }
+}
+
+pub struct ProfiledStrategy {
+    pub strategy: Rc<dyn strategy::StrategyAPI>,
+    pub profiler: Rc<Profiler>,
+}
+
+impl strategy::StrategyAPI for ProfiledStrategy {
+    fn analyze(
+        &self,
+        stock_id: &str,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<strategy::Score, strategy::Error> {
+        self.profiler.measure(Phase::StrategyAnalysis, || {
+            self.strategy.analyze(stock_id, assess_date)
+        })
+    }
+
+    fn settle_check(
+        &self,
+        stock_id: &str,
+        hold_date: chrono::NaiveDate,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<Option<strategy::SettleReason>, strategy::Error> {
+        self.profiler.measure(Phase::StrategyAnalysis, || {
+            self.strategy.settle_check(stock_id, hold_date, assess_date)
+        })
+    }
+
+    fn draw_view(
+        &self,
+        stock_id: &str,
+        style: &diagram::DiagramStyle,
+        output: &diagram::Output,
+    ) -> Result<(), strategy::Error> {
+        self.strategy.draw_view(stock_id, style, output)
+    }
+
+    fn export_view(&self, stock_id: &str, file_path: &str) -> Result<(), strategy::Error> {
+        self.profiler.measure(Phase::Export, || {
+            self.strategy.export_view(stock_id, file_path)
+        })
+    }
+
+    fn min_history_days(&self) -> usize {
+        self.strategy.min_history_days()
+    }
+
+    fn data_requirements(&self) -> strategy::DataRequirements {
+        self.strategy.data_requirements()
+    }
+}
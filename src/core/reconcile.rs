@@ -0,0 +1,55 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::order;
+
+/// Trade executed at the broker, as read from a statement.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecutedTrade {
+    pub date: chrono::NaiveDate,
+    pub stock_id: String,
+    pub side: order::Side,
+    pub num: u32,
+    pub price: u32,
+}
+
+/// Loads the executed trades of a statement, a CSV file with a `date,stock_id,side,num,price`
+/// header where `side` is `Buy` or `Sell`.
+pub fn load_executed_trades(path: &str) -> Result<Vec<ExecutedTrade>, csv::Error> {
+    csv::Reader::from_path(path)?.deserialize().collect()
+}
+
+/// Shares of a stock the engine recommended trading on a day against those actually traded,
+/// bought less sold.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reconciliation {
+    pub stock_id: String,
+    pub recommended: i64,
+    pub executed: i64,
+}
+
+/// Executed trades by day. A day with any trade on the statement is taken as fully reported: the
+/// engine's fills of that day are replaced by the executed trades, and fills with no trade
+/// behind them are dropped. Days without trades keep the engine's fills.
+#[derive(Debug, Clone, Default)]
+pub struct Statement {
+    days: BTreeMap<chrono::NaiveDate, Vec<ExecutedTrade>>,
+}
+
+impl Statement {
+    pub fn new(executed_trades: &[ExecutedTrade]) -> Self {
+        let mut days: BTreeMap<chrono::NaiveDate, Vec<ExecutedTrade>> = BTreeMap::new();
+
+        for executed_trade in executed_trades {
+            days.entry(executed_trade.date)
+                .or_default()
+                .push(executed_trade.clone());
+        }
+        Statement { days }
+    }
+
+    pub fn get_trades(&self, date: chrono::NaiveDate) -> Option<&Vec<ExecutedTrade>> {
+        self.days.get(&date)
+    }
+}
@@ -0,0 +1,166 @@
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use ta::indicators::SimpleMovingAverage;
+use ta::Next;
+
+use crate::dataview::view;
+use crate::storage::backend;
+use crate::strategy::schema;
+
+#[derive(Debug)]
+pub enum Error {
+    Backend(backend::Error),
+    Dataview(view::Error),
+    Ta(ta::errors::TaError),
+}
+
+impl From<backend::Error> for Error {
+    fn from(err: backend::Error) -> Error {
+        Error::Backend(err)
+    }
+}
+
+impl From<view::Error> for Error {
+    fn from(err: view::Error) -> Error {
+        Error::Dataview(err)
+    }
+}
+
+impl From<ta::errors::TaError> for Error {
+    fn from(err: ta::errors::TaError) -> Error {
+        Error::Ta(err)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum VolatilityMeasure {
+    /// Average true range relative to the close.
+    Atr,
+    /// RiskMetrics-style EWMA of daily log returns.
+    Ewma,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Requirement {
+    Calm,
+    Trending,
+    CalmOrTrending,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegimeFilter {
+    /// Stock id of the instrument standing in for the market index.
+    pub index_id: String,
+    pub measure: VolatilityMeasure,
+    pub atr_period: usize,
+    /// Number of trading days the volatility percentile is ranked against.
+    pub lookback: usize,
+    /// Volatility percentile at or below which the market counts as calm.
+    pub calm_percentile: f64,
+    /// SMA period the index close has to stay above for the market to count as trending.
+    pub trend_period: usize,
+    pub requirement: Requirement,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Regime {
+    pub volatility_percentile: f64,
+    pub trending: bool,
+}
+
+impl std::default::Default for RegimeFilter {
+    fn default() -> Self {
+        RegimeFilter {
+            index_id: "0050".to_owned(),
+            measure: VolatilityMeasure::Atr,
+            atr_period: view::ATR_PERIOD,
+            lookback: 250,
+            calm_percentile: 80.0,
+            trend_period: 60,
+            requirement: Requirement::Calm,
+        }
+    }
+}
+
+impl RegimeFilter {
+    /// Returns `None` when the index has no record on `date` to assess the regime from.
+    pub fn assess(
+        &self,
+        backend_op: &Rc<dyn backend::BackendOp>,
+        date: chrono::NaiveDate,
+    ) -> Result<Option<Regime>, Error> {
+        let history_days = (self.lookback + self.atr_period.max(self.trend_period)) * 2;
+        let records = backend_op.query_by_range(
+            &self.index_id,
+            date - chrono::Duration::days(history_days as i64),
+            date,
+        )?;
+
+        match records.last() {
+            Some(record) if record.date == date => {}
+            _ => return Ok(None),
+        }
+
+        let volatilities = self.get_volatilities(&records)?;
+        let volatility = match volatilities.first() {
+            Some(volatility) => *volatility,
+            None => return Ok(None),
+        };
+        let rank = volatilities
+            .iter()
+            .filter(|other| **other <= volatility)
+            .count();
+        let mut trending = false;
+
+        if records.len() >= self.trend_period {
+            let mut sma = SimpleMovingAverage::new(self.trend_period)?;
+            let mut close = 0.0;
+            let mut average = 0.0;
+
+            for record in &records {
+                close = record.close;
+                average = sma.next(record.close);
+            }
+            trending = close > average;
+        }
+
+        Ok(Some(Regime {
+            volatility_percentile: rank as f64 / volatilities.len() as f64 * 100.0,
+            trending,
+        }))
+    }
+
+    /// Volatility series in reverse chronological order, truncated to the lookback window.
+    fn get_volatilities(&self, records: &[schema::RawData]) -> Result<Vec<f64>, Error> {
+        let volatilities: Vec<f64> = match self.measure {
+            VolatilityMeasure::Atr => view::AtrView::transform_by_period(records, self.atr_period)?
+                .iter()
+                .map(|view| {
+                    if view.close == 0.0 {
+                        return 0.0;
+                    }
+                    view.atr / view.close
+                })
+                .collect(),
+            VolatilityMeasure::Ewma => {
+                view::EwmaVolatilityView::transform_by_lambda(records, view::EWMA_LAMBDA)?
+                    .iter()
+                    .map(|view| view.volatility)
+                    .collect()
+            }
+        };
+
+        Ok(volatilities.into_iter().rev().take(self.lookback).collect())
+    }
+
+    pub fn allows_entry(&self, regime: &Regime) -> bool {
+        let calm = regime.volatility_percentile <= self.calm_percentile;
+
+        match self.requirement {
+            Requirement::Calm => calm,
+            Requirement::Trending => regime.trending,
+            Requirement::CalmOrTrending => calm || regime.trending,
+        }
+    }
+}
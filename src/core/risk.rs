@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+
+use crate::strategy::strategy;
+
+use super::{decision, order};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreaker {
+    /// Drawdown from the equity peak, in percent, which triggers the breaker.
+    pub max_drawdown: f64,
+    /// Number of trading days new entries stay paused, the triggering day included.
+    pub pause_days: usize,
+}
+
+impl std::default::Default for CircuitBreaker {
+    fn default() -> Self {
+        CircuitBreaker {
+            max_drawdown: 20.0,
+            pause_days: 20,
+        }
+    }
+}
+
+impl CircuitBreaker {
+    pub fn is_triggered(&self, peak_equity: u32, equity: u32) -> bool {
+        drawdown(peak_equity, equity) >= self.max_drawdown
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RiskEvent {
+    CircuitBreakerTriggered {
+        drawdown: f64,
+    },
+    EntriesPaused,
+    DailyLossLimitReached {
+        loss: f64,
+    },
+    UnfavorableRegime {
+        volatility_percentile: f64,
+        trending: bool,
+    },
+    LowBreadth {
+        breadth: f64,
+    },
+    /// A held stock had no record; `days` counts the consecutive trading days it has been missing.
+    MissingData {
+        stock_id: String,
+        days: usize,
+        policy: decision::MissingDataPolicy,
+    },
+    /// A held stock has been missing long enough to be considered halted.
+    TradingHalted {
+        stock_id: String,
+        days: usize,
+    },
+    TradingResumed {
+        stock_id: String,
+        days: usize,
+    },
+    /// The bar was locked at the price limit; sells are retried on the next trading day, buys are
+    /// left to the next day's selection.
+    FillDeferred {
+        stock_id: String,
+        side: order::Side,
+    },
+    /// The entry did not fit the lot policy or was worth less than the minimum order value.
+    OrderSkipped {
+        stock_id: String,
+        num: u32,
+        price: u32,
+    },
+    /// The order took as much of the day's volume as allowed; `remaining` shares are carried to
+    /// the next trading day.
+    PartialFill {
+        stock_id: String,
+        side: order::Side,
+        num: u32,
+        remaining: u32,
+    },
+    /// The strategy stopped scoring a stock being scaled in as a buy; the `remaining` shares of
+    /// the entry are not bought.
+    EntryCancelled {
+        stock_id: String,
+        remaining: u32,
+    },
+    /// The position is on manual hold, so the exit it was due for is left to the trader.
+    SettleOverridden {
+        stock_id: String,
+        settle_reason: strategy::SettleReason,
+    },
+    /// A short leg lost the whole value of the pair, so the pair was closed before owing more
+    /// than it put up; `value` is what the closing gave back, negative when it took cash.
+    PairStoppedOut {
+        first_id: String,
+        second_id: String,
+        value: i64,
+    },
+}
+
+impl std::fmt::Display for RiskEvent {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RiskEvent::CircuitBreakerTriggered { drawdown } => {
+                write!(fmt, "circuit breaker triggered (drawdown {:.2}%)", drawdown)
+            }
+            RiskEvent::EntriesPaused => fmt.write_str("entries paused"),
+            RiskEvent::DailyLossLimitReached { loss } => {
+                write!(fmt, "daily loss limit reached (loss {:.2}%)", loss)
+            }
+            RiskEvent::UnfavorableRegime {
+                volatility_percentile,
+                trending,
+            } => write!(
+                fmt,
+                "unfavorable regime (volatility percentile {:.2}, trending {})",
+                volatility_percentile, trending
+            ),
+            RiskEvent::LowBreadth { breadth } => write!(fmt, "low breadth ({:.2}%)", breadth),
+            RiskEvent::MissingData {
+                stock_id,
+                days,
+                policy,
+            } => write!(fmt, "no data for {} ({} days, {})", stock_id, days, policy),
+            RiskEvent::TradingHalted { stock_id, days } => {
+                write!(fmt, "{} halted ({} days)", stock_id, days)
+            }
+            RiskEvent::TradingResumed { stock_id, days } => {
+                write!(fmt, "{} resumed after {} days", stock_id, days)
+            }
+            RiskEvent::FillDeferred { stock_id, side } => {
+                write!(fmt, "{:?} of {} deferred (locked at limit)", side, stock_id)
+            }
+            RiskEvent::OrderSkipped {
+                stock_id,
+                num,
+                price,
+            } => write!(
+                fmt,
+                "order of {} x {} @ {} skipped (below minimum order)",
+                stock_id, num, price
+            ),
+            RiskEvent::PartialFill {
+                stock_id,
+                side,
+                num,
+                remaining,
+            } => write!(
+                fmt,
+                "{:?} of {} partially filled ({} filled, {} carried over)",
+                side, stock_id, num, remaining
+            ),
+            RiskEvent::EntryCancelled {
+                stock_id,
+                remaining,
+            } => write!(
+                fmt,
+                "entry of {} cancelled ({} shares left unbought)",
+                stock_id, remaining
+            ),
+            RiskEvent::SettleOverridden {
+                stock_id,
+                settle_reason,
+            } => write!(
+                fmt,
+                "{:?} of {} overridden (manual hold)",
+                settle_reason, stock_id
+            ),
+            RiskEvent::PairStoppedOut {
+                first_id,
+                second_id,
+                value,
+            } => write!(
+                fmt,
+                "pair {}/{} stopped out (closed for {})",
+                first_id, second_id, value
+            ),
+        }
+    }
+}
+
+pub fn drawdown(peak_equity: u32, equity: u32) -> f64 {
+    if peak_equity == 0 || equity >= peak_equity {
+        return 0.0;
+    }
+    (peak_equity - equity) as f64 / peak_equity as f64 * 100.0
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+
+use crate::report::html;
+use crate::storage::run;
+
+pub const SCALING_DIRECTORY: &str = "scaling";
+pub const SCALING_REPORT_FILENAME: &str = "scaling_report.yaml";
+pub const SCALING_TABLE_FILENAME: &str = "scaling_report.html";
+/// Return, in points, a level may give up against the smallest one and still count as within
+/// the capacity of the strategy.
+pub const DEFAULT_CAPACITY_TOLERANCE: f64 = 2.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapitalLevel {
+    pub liquidity: u32,
+    pub metrics: run::RunMetrics,
+}
+
+/// Metrics of one backtest run at each level of initial capital, smallest first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScalingReport {
+    pub levels: Vec<CapitalLevel>,
+}
+
+impl ScalingReport {
+    /// Time-weighted return of each level less that of the smallest, in points.
+    pub fn get_return_changes(&self) -> Vec<f64> {
+        let base_return = match self.levels.first() {
+            Some(level) => level.metrics.time_weighted_return,
+            None => return Vec::new(),
+        };
+
+        self.levels
+            .iter()
+            .map(|level| level.metrics.time_weighted_return - base_return)
+            .collect()
+    }
+
+    /// Largest capital level before the first one to lose more than `tolerance` points of return
+    /// against the smallest level.
+    pub fn get_capacity(&self, tolerance: f64) -> Option<u32> {
+        self.levels
+            .iter()
+            .zip(self.get_return_changes())
+            .take_while(|(_, change)| *change >= -tolerance)
+            .map(|(level, _)| level.liquidity)
+            .last()
+    }
+
+    pub fn to_html(&self) -> String {
+        let capacity = match self.get_capacity(DEFAULT_CAPACITY_TOLERANCE) {
+            Some(capacity) => capacity.to_string(),
+            None => "unknown".to_owned(),
+        };
+
+        html::get_page(
+            "Capital scaling",
+            &format!(
+                "<p>Capacity, losing at most {} points of return: {}.</p>{}",
+                DEFAULT_CAPACITY_TOLERANCE,
+                capacity,
+                html::get_table(
+                    &[
+                        "Capital",
+                        "Return",
+                        "Change",
+                        "Max drawdown",
+                        "Trades",
+                        "Partial fills",
+                    ],
+                    self.levels
+                        .iter()
+                        .zip(self.get_return_changes())
+                        .map(|(level, change)| {
+                            vec![
+                                level.liquidity.to_string(),
+                                format!("{:+.2}%", level.metrics.time_weighted_return),
+                                format!("{:+.2}", change),
+                                format!("{:.2}%", level.metrics.max_drawdown),
+                                level.metrics.trade_count.to_string(),
+                                level.metrics.partial_fills.to_string(),
+                            ]
+                        })
+                        .collect(),
+                )
+            ),
+            false,
+        )
+    }
+}
+
+#[cfg(test)]
+mod scaling_test {
+    use crate::storage::run;
+
+    use super::{CapitalLevel, ScalingReport};
+
+    #[test]
+    fn get_capacity_check() {
+        let report = ScalingReport {
+            levels: [
+                (100000, 20.0),
+                (1000000, 19.0),
+                (10000000, 15.0),
+                (100000000, 19.5),
+            ]
+            .iter()
+            .map(|(liquidity, time_weighted_return)| CapitalLevel {
+                liquidity: *liquidity,
+                metrics: run::RunMetrics {
+                    time_weighted_return: *time_weighted_return,
+                    ..Default::default()
+                },
+            })
+            .collect(),
+        };
+
+        assert_eq!(report.get_return_changes(), vec![0.0, -1.0, -5.0, -0.5]);
+        assert_eq!(report.get_capacity(2.0), Some(1000000));
+        assert_eq!(report.get_capacity(0.5), Some(100000));
+        assert_eq!(ScalingReport::default().get_capacity(2.0), None);
+    }
+}
@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+
+use crate::report::html;
+use crate::storage::run;
+
+pub const SCENARIO_DIRECTORY: &str = "scenarios";
+pub const SCENARIO_REPORT_FILENAME: &str = "scenario_report.yaml";
+pub const SCENARIO_TABLE_FILENAME: &str = "scenario_report.html";
+
+/// Date range of a market episode a strategy is replayed over.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    pub start_date: chrono::NaiveDate,
+    pub end_date: chrono::NaiveDate,
+    #[serde(default)]
+    pub description: String,
+}
+
+impl Scenario {
+    fn new(name: &str, start: (i32, u32, u32), end: (i32, u32, u32), description: &str) -> Self {
+        Scenario {
+            name: name.to_owned(),
+            start_date: chrono::NaiveDate::from_ymd_opt(start.0, start.1, start.2).unwrap(),
+            end_date: chrono::NaiveDate::from_ymd_opt(end.0, end.1, end.2).unwrap(),
+            description: description.to_owned(),
+        }
+    }
+}
+
+pub fn get_predefined() -> Vec<Scenario> {
+    vec![
+        Scenario::new(
+            "2008",
+            (2008, 5, 1),
+            (2009, 3, 31),
+            "Global financial crisis, from the May 2008 peak through the March 2009 low",
+        ),
+        Scenario::new(
+            "2020",
+            (2020, 1, 2),
+            (2020, 6, 30),
+            "COVID crash of March 2020 and the rebound after it",
+        ),
+        Scenario::new(
+            "2022",
+            (2022, 1, 3),
+            (2022, 12, 30),
+            "Drawdown of 2022 under rising interest rates",
+        ),
+    ]
+}
+
+/// Predefined scenarios followed by `custom` ones, such as those of the config; a custom
+/// scenario replaces the predefined one of the same name.
+pub fn get_scenarios(custom: &[Scenario]) -> Vec<Scenario> {
+    let mut scenarios: Vec<Scenario> = get_predefined()
+        .into_iter()
+        .filter(|scenario| custom.iter().all(|custom| custom.name != scenario.name))
+        .collect();
+
+    scenarios.extend(custom.iter().cloned());
+    scenarios
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenarioResult {
+    pub scenario: Scenario,
+    pub metrics: run::RunMetrics,
+}
+
+/// Metrics of one strategy replayed over each scenario.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScenarioReport {
+    pub results: Vec<ScenarioResult>,
+}
+
+impl ScenarioReport {
+    pub fn to_html(&self) -> String {
+        html::get_page(
+            "Stress scenarios",
+            &html::get_table(
+                &[
+                    "Scenario",
+                    "Start",
+                    "End",
+                    "Return",
+                    "Max drawdown",
+                    "Trades",
+                    "Win rate",
+                    "Description",
+                ],
+                self.results
+                    .iter()
+                    .map(|result| {
+                        vec![
+                            result.scenario.name.to_owned(),
+                            result.scenario.start_date.to_string(),
+                            result.scenario.end_date.to_string(),
+                            format!("{:+.2}%", result.metrics.time_weighted_return),
+                            format!("{:.2}%", result.metrics.max_drawdown),
+                            result.metrics.trade_count.to_string(),
+                            format!("{:.1}%", result.metrics.win_rate),
+                            result.scenario.description.to_owned(),
+                        ]
+                    })
+                    .collect(),
+            ),
+            false,
+        )
+    }
+}
+
+#[cfg(test)]
+mod scenario_test {
+    use super::{get_scenarios, Scenario};
+
+    #[test]
+    fn get_scenarios_check() {
+        let custom = vec![
+            Scenario::new("2020", (2020, 2, 1), (2020, 4, 30), ""),
+            Scenario::new("2015", (2015, 6, 1), (2015, 9, 30), "China selloff"),
+        ];
+        let scenarios = get_scenarios(&custom);
+
+        assert_eq!(
+            scenarios
+                .iter()
+                .map(|scenario| scenario.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["2008", "2022", "2020", "2015"]
+        );
+        assert_eq!(scenarios[2], custom[0]);
+    }
+}
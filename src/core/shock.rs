@@ -0,0 +1,346 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::optimizer::search;
+use crate::report::html;
+use crate::storage::{backend, run};
+use crate::strategy::schema;
+
+pub const SHOCK_DIRECTORY: &str = "shocks";
+pub const SHOCK_REPORT_FILENAME: &str = "shock_report.yaml";
+pub const SHOCK_TABLE_FILENAME: &str = "shock_report.html";
+
+/// Perturbation of the market a backtest is replayed under.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ShockModel {
+    /// Each stock gaps down `size` percent on a trading day with `probability`, and trades that
+    /// much lower from then on. Holdings are hit as often as any other stock.
+    GapDown { probability: f64, size: f64 },
+    /// Scales the slippage of every fill, the default slippage when none is set.
+    Slippage { factor: f64 },
+    /// Drops a day of a stock's records with `probability`, as if it had not been crawled.
+    MissingData { probability: f64 },
+}
+
+/// Shock models, and the seeds a backtest is repeated with under them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShockConfig {
+    pub models: Vec<ShockModel>,
+    pub seeds: Vec<u64>,
+}
+
+/// Serves the records of `backend_op` as perturbed by the price shocks of `models`. A stock's
+/// shocked series is derived from its whole history once, from `seed` and the stock id, so every
+/// query of a run sees the same market and the same seed gives the same market again.
+pub struct ShockedBackend {
+    pub backend_op: Rc<dyn backend::BackendOp>,
+    pub models: Vec<ShockModel>,
+    pub seed: u64,
+    series: RefCell<HashMap<String, Rc<Vec<schema::RawData>>>>,
+}
+
+impl ShockedBackend {
+    pub fn new(backend_op: Rc<dyn backend::BackendOp>, models: Vec<ShockModel>, seed: u64) -> Self {
+        ShockedBackend {
+            backend_op,
+            models,
+            seed,
+            series: RefCell::new(HashMap::new()),
+        }
+    }
+
+    fn get_series(&self, stock_id: &str) -> Result<Rc<Vec<schema::RawData>>, backend::Error> {
+        if let Some(series) = self.series.borrow().get(stock_id) {
+            return Ok(series.clone());
+        }
+
+        let series = Rc::new(self.shock(stock_id, self.backend_op.query_all(stock_id)?));
+
+        self.series
+            .borrow_mut()
+            .insert(stock_id.to_owned(), series.clone());
+        Ok(series)
+    }
+
+    fn shock(&self, stock_id: &str, records: Vec<schema::RawData>) -> Vec<schema::RawData> {
+        let stock_seed = u64::from_str_radix(&run::get_digest(stock_id.as_bytes()), 16).unwrap();
+        let mut rng = search::Rng::new(self.seed ^ stock_seed);
+        let mut scale = 1.0;
+        let mut prev_close: Option<f64> = None;
+        let mut shocked = Vec::new();
+
+        for mut record in records {
+            let mut missing = false;
+
+            for model in &self.models {
+                match model {
+                    ShockModel::GapDown { probability, size } => {
+                        if rng.next_f64() < *probability {
+                            scale *= 1.0 - size / 100.0;
+                        }
+                    }
+                    ShockModel::MissingData { probability } => {
+                        missing |= rng.next_f64() < *probability;
+                    }
+                    ShockModel::Slippage { .. } => {}
+                }
+            }
+
+            let close = record.close * scale;
+
+            record.open *= scale;
+            record.high *= scale;
+            record.low *= scale;
+            record.spread = match prev_close {
+                Some(prev_close) => close - prev_close,
+                None => record.spread * scale,
+            };
+            record.close = close;
+            prev_close = Some(close);
+            if !missing {
+                shocked.push(record);
+            }
+        }
+        shocked
+    }
+}
+
+/// Product of the slippage factors of `models`.
+pub fn get_slippage_factor(models: &[ShockModel]) -> f64 {
+    models
+        .iter()
+        .map(|model| match model {
+            ShockModel::Slippage { factor } => *factor,
+            _ => 1.0,
+        })
+        .product()
+}
+
+impl backend::BackendOp for ShockedBackend {
+    fn batch_insert(&self, records: &Vec<(String, schema::RawData)>) -> Result<(), backend::Error> {
+        self.series.borrow_mut().clear();
+        self.backend_op.batch_insert(records)
+    }
+
+    fn query(
+        &self,
+        stock_id: &str,
+        date: chrono::NaiveDate,
+    ) -> Result<Option<schema::RawData>, backend::Error> {
+        Ok(self
+            .get_series(stock_id)?
+            .iter()
+            .find(|record| record.date == date)
+            .cloned())
+    }
+
+    fn query_by_range(
+        &self,
+        stock_id: &str,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> Result<Vec<schema::RawData>, backend::Error> {
+        Ok(self
+            .get_series(stock_id)?
+            .iter()
+            .filter(|record| record.date >= start_date && record.date <= end_date)
+            .cloned()
+            .collect())
+    }
+
+    fn query_all(&self, stock_id: &str) -> Result<Vec<schema::RawData>, backend::Error> {
+        Ok(self.get_series(stock_id)?.to_vec())
+    }
+
+    fn batch_delete(
+        &self,
+        records: &Vec<(String, chrono::NaiveDate)>,
+    ) -> Result<(), backend::Error> {
+        self.series.borrow_mut().clear();
+        self.backend_op.batch_delete(records)
+    }
+
+    fn get_generation(&self) -> Result<u64, backend::Error> {
+        self.backend_op.get_generation()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShockResult {
+    pub seed: u64,
+    pub metrics: run::RunMetrics,
+}
+
+/// Metrics of a backtest without shocks and under them with each seed; the further the shocked
+/// runs fall from the baseline, the more fragile the strategy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShockReport {
+    pub models: Vec<ShockModel>,
+    pub baseline: run::RunMetrics,
+    pub results: Vec<ShockResult>,
+}
+
+impl ShockReport {
+    /// Mean time-weighted return of the shocked runs less that of the baseline, in points.
+    pub fn get_mean_return_change(&self) -> f64 {
+        if self.results.is_empty() {
+            return 0.0;
+        }
+        self.results
+            .iter()
+            .map(|result| result.metrics.time_weighted_return - self.baseline.time_weighted_return)
+            .sum::<f64>()
+            / self.results.len() as f64
+    }
+
+    /// Worst time-weighted return of the runs, the baseline included, in percent.
+    pub fn get_worst_return(&self) -> f64 {
+        self.results
+            .iter()
+            .map(|result| result.metrics.time_weighted_return)
+            .fold(self.baseline.time_weighted_return, f64::min)
+    }
+
+    pub fn to_html(&self) -> String {
+        let get_row = |name: String, metrics: &run::RunMetrics| {
+            vec![
+                name,
+                format!("{:+.2}%", metrics.time_weighted_return),
+                format!(
+                    "{:+.2}",
+                    metrics.time_weighted_return - self.baseline.time_weighted_return
+                ),
+                format!("{:.2}%", metrics.max_drawdown),
+                metrics.trade_count.to_string(),
+                format!("{:.1}%", metrics.win_rate),
+            ]
+        };
+        let mut rows = vec![get_row("Baseline".to_owned(), &self.baseline)];
+
+        rows.extend(
+            self.results
+                .iter()
+                .map(|result| get_row(format!("Seed {}", result.seed), &result.metrics)),
+        );
+
+        html::get_page(
+            "Shock injection",
+            &format!(
+                "<p>Models: {:?}</p><p>Mean return change: {:+.2} points, worst return: \
+                 {:+.2}%.</p>{}",
+                self.models,
+                self.get_mean_return_change(),
+                self.get_worst_return(),
+                html::get_table(
+                    &[
+                        "Run",
+                        "Return",
+                        "Change",
+                        "Max drawdown",
+                        "Trades",
+                        "Win rate"
+                    ],
+                    rows,
+                )
+            ),
+            false,
+        )
+    }
+}
+
+#[cfg(test)]
+mod shock_test {
+    use std::rc::Rc;
+
+    use crate::storage::backend::{BackendOp, MockBackendOp};
+    use crate::strategy::schema;
+
+    use super::{get_slippage_factor, ShockModel, ShockedBackend};
+
+    fn get_backend_op() -> Rc<MockBackendOp> {
+        let mut mock_backend_op = MockBackendOp::new();
+
+        mock_backend_op.expect_query_all().returning(|_| {
+            let start_date = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+
+            Ok((0..100)
+                .map(|day| schema::RawData {
+                    open: 100.0,
+                    high: 100.0,
+                    low: 100.0,
+                    close: 100.0,
+                    date: start_date + chrono::Duration::days(day),
+                    ..Default::default()
+                })
+                .collect())
+        });
+        Rc::new(mock_backend_op)
+    }
+
+    #[test]
+    fn shocked_backend_check() {
+        let models = vec![
+            ShockModel::GapDown {
+                probability: 0.1,
+                size: 10.0,
+            },
+            ShockModel::MissingData { probability: 0.2 },
+        ];
+        let shocked_backend = ShockedBackend::new(get_backend_op(), models.clone(), 7);
+        let records = shocked_backend.query_all("2330").unwrap();
+
+        assert!(records.len() < 100 && records.len() > 50);
+        assert!(records
+            .windows(2)
+            .all(|pair| pair[1].close <= pair[0].close));
+        assert!(records.last().unwrap().close < 100.0);
+        assert!(records
+            .iter()
+            .all(|record| record.high == record.close && record.open == record.close));
+
+        // Same seed, same market; another seed, another one.
+        let again = ShockedBackend::new(get_backend_op(), models.clone(), 7);
+        let other = ShockedBackend::new(get_backend_op(), models, 8);
+        let get_closes = |records: Vec<schema::RawData>| {
+            records
+                .iter()
+                .map(|record| (record.date, record.close))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            get_closes(again.query_all("2330").unwrap()),
+            get_closes(records.clone())
+        );
+        assert_ne!(
+            get_closes(other.query_all("2330").unwrap()),
+            get_closes(records.clone())
+        );
+        let missing_date = (1..records.len())
+            .map(|index| records[index - 1].date.succ_opt().unwrap())
+            .find(|date| records.iter().all(|record| record.date != *date))
+            .unwrap();
+
+        assert_eq!(
+            shocked_backend
+                .query("2330", records[3].date)
+                .unwrap()
+                .map(|record| record.close),
+            Some(records[3].close)
+        );
+        assert!(shocked_backend
+            .query("2330", missing_date)
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            get_slippage_factor(&[
+                ShockModel::Slippage { factor: 2.0 },
+                ShockModel::MissingData { probability: 0.1 }
+            ]),
+            2.0
+        );
+    }
+}
@@ -4,6 +4,8 @@ use mockall::automock;
 use std::{io::Read, result::Result};
 
 const STOCK_MONTH_REVENUE_URL: &str = "https://quality.data.gov.tw/dq_download_csv.php?nid=11549&md5_url=da96048521360db9f23a2b47c9c31155";
+/// Timezone of TWSE, whose local trading dates every record is dated with.
+pub const MARKET_TIMEZONE: chrono_tz::Tz = chrono_tz::Asia::Taipei;
 
 pub struct Args {
     pub stock_id: String,
@@ -17,6 +19,7 @@ pub enum Error {
     Url(url::ParseError),
     Io(std::io::Error),
     Csv(csv::Error),
+    Json(serde_json::Error),
     BadRequest,
     RateLimitReached,
     Unknown,
@@ -64,3 +67,9 @@ impl From<csv::Error> for Error {
     }
 }
 
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::Json(err)
+    }
+}
+
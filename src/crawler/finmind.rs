@@ -1,26 +1,97 @@
 use crate::crawler::crawler;
 use crate::strategy::schema;
-use serde::Deserialize;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+use std::cell::Cell;
 use std::result::Result;
 
 const FINMIND_V4_URL: &str = "https://api.finmindtrade.com/api/v4/data";
+const FINMIND_USER_INFO_URL: &str = "https://api.web.finmindtrade.com/v2/user_info";
 const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
 
+/// FinMind sends numbers either as JSON numbers or as strings, e.g. "1,234.5".
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Number {
+    Int(u64),
+    Float(f64),
+    Text(String),
+}
+
+impl Number {
+    fn into_f64(self) -> Result<f64, String> {
+        match self {
+            Number::Int(value) => Ok(value as f64),
+            Number::Float(value) => Ok(value),
+            Number::Text(text) => text
+                .trim()
+                .replace(',', "")
+                .parse()
+                .map_err(|_| format!("invalid number {:?}", text)),
+        }
+    }
+
+    fn into_u64(self) -> Result<u64, String> {
+        match self.into_f64()? {
+            value if value >= 0.0 && value.fract() == 0.0 => Ok(value as u64),
+            value => Err(format!("invalid count {}", value)),
+        }
+    }
+}
+
+fn deserialize_f64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    Number::deserialize(deserializer)?
+        .into_f64()
+        .map_err(D::Error::custom)
+}
+
+/// For fields a record is still usable without: null counts as zero.
+fn deserialize_optional_f64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    match Option::<Number>::deserialize(deserializer)? {
+        Some(number) => number.into_f64().map_err(D::Error::custom),
+        None => Ok(0.0),
+    }
+}
+
+fn deserialize_optional_u64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    match Option::<Number>::deserialize(deserializer)? {
+        Some(number) => number.into_u64().map_err(D::Error::custom),
+        None => Ok(0),
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct TaiwanStockPrice {
     pub stock_id: String,
+    #[serde(deserialize_with = "deserialize_f64")]
     pub open: f64,
+    #[serde(deserialize_with = "deserialize_f64")]
     pub max: f64,
+    #[serde(deserialize_with = "deserialize_f64")]
     pub min: f64,
+    #[serde(deserialize_with = "deserialize_f64")]
     pub close: f64,
+    #[serde(default, deserialize_with = "deserialize_optional_f64")]
     pub spread: f64,
     pub date: chrono::NaiveDate,
-    #[serde(alias = "Trading_Volume")]
+    #[serde(
+        alias = "Trading_Volume",
+        default,
+        deserialize_with = "deserialize_optional_u64"
+    )]
     pub trading_volume: u64,
-    #[serde(alias = "Trading_money")]
+    #[serde(
+        alias = "Trading_money",
+        default,
+        deserialize_with = "deserialize_optional_u64"
+    )]
     pub trading_money: u64,
-    #[serde(alias = "Trading_turnover")]
+    #[serde(
+        alias = "Trading_turnover",
+        default,
+        deserialize_with = "deserialize_optional_f64"
+    )]
     pub trading_turnover: f64,
 }
 
@@ -44,36 +115,96 @@ impl From<TaiwanStockPrice> for schema::RawData {
 pub struct Response {
     pub msg: String,
     pub status: usize,
-    pub data: Vec<TaiwanStockPrice>,
+    /// Records are parsed one by one, so a malformed one does not fail the whole response.
+    #[serde(default)]
+    pub data: Vec<serde_json::Value>,
+}
+
+/// Parses the records of a response, skipping the malformed ones with a warning. Returns the
+/// records and the number of records skipped.
+pub fn parse_records(values: Vec<serde_json::Value>) -> (Vec<schema::RawData>, usize) {
+    let mut records = Vec::new();
+    let mut skipped = 0;
+
+    for (index, value) in values.into_iter().enumerate() {
+        match serde_json::from_value::<TaiwanStockPrice>(value) {
+            Ok(record) => records.push(record.into()),
+            Err(err) => {
+                println!("Skipped malformed FinMind record #{}: {}", index, err);
+                skipped += 1;
+            }
+        }
+    }
+    (records, skipped)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct UserInfo {
+    pub msg: String,
+    pub status: usize,
+    #[serde(default)]
+    pub user_count: u64,
+    #[serde(default)]
+    pub api_request_limit: u64,
+}
+
+impl UserInfo {
+    /// Requests left in the current quota window.
+    pub fn get_remaining(&self) -> u64 {
+        self.api_request_limit.saturating_sub(self.user_count)
+    }
 }
 
 pub struct Finmind {
     token: String,
+    skipped_records: Cell<usize>,
 }
 
 impl Finmind {
     pub fn new(token: &str) -> Self {
         Finmind {
             token: token.to_owned(),
+            skipped_records: Cell::new(0),
         }
     }
-}
 
-impl crawler::Crawler for Finmind {
-    fn get_stock_data(&self, args: &crawler::Args) -> Result<Vec<schema::RawData>, crawler::Error> {
+    /// Malformed records skipped since the crawler was created.
+    pub fn get_skipped_records(&self) -> usize {
+        self.skipped_records.get()
+    }
+
+    pub fn get_user_info(&self) -> Result<UserInfo, crawler::Error> {
+        let url = reqwest::Url::parse_with_params(
+            FINMIND_USER_INFO_URL,
+            &[("token", self.token.to_owned())],
+        )?;
+        let resp: UserInfo = reqwest::blocking::get(url)?.json()?;
+
+        match resp.status {
+            200 => Ok(resp),
+            400 => Err(crawler::Error::BadRequest),
+            402 => Err(crawler::Error::RateLimitReached),
+            _ => Err(crawler::Error::Unknown),
+        }
+    }
+
+    fn get_stock_data_by_range(
+        &self,
+        stock_id: &str,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> Result<Vec<schema::RawData>, crawler::Error> {
         let url = reqwest::Url::parse_with_params(
             FINMIND_V4_URL,
             &[
-                ("data_id", args.stock_id.to_owned()),
+                ("data_id", stock_id.to_owned()),
                 ("dataset", "TaiwanStockPrice".to_owned()),
                 (
                     "start_date",
-                    args.start_date.format(DEFAULT_DATE_FORMAT).to_string(),
-                ),
-                (
-                    "end_date",
-                    args.end_date.format(DEFAULT_DATE_FORMAT).to_string(),
+                    start_date.format(DEFAULT_DATE_FORMAT).to_string(),
                 ),
+                ("end_date", end_date.format(DEFAULT_DATE_FORMAT).to_string()),
                 ("token", self.token.to_owned()),
             ],
         )?;
@@ -81,7 +212,20 @@ impl crawler::Crawler for Finmind {
         let resp: Response = reqwest::blocking::get(url)?.json()?;
 
         match resp.status {
-            200 => Ok(resp.data.into_iter().map(|record| record.into()).collect()),
+            200 => {
+                let total = resp.data.len();
+                let (records, skipped) = parse_records(resp.data);
+
+                if skipped > 0 {
+                    println!(
+                        "Skipped {} of {} FinMind records of {}",
+                        skipped, total, stock_id
+                    );
+                    self.skipped_records
+                        .set(self.skipped_records.get() + skipped);
+                }
+                Ok(records)
+            }
             400 => Err(crawler::Error::BadRequest),
             402 => Err(crawler::Error::RateLimitReached),
             _ => Err(crawler::Error::Unknown),
@@ -89,3 +233,82 @@ impl crawler::Crawler for Finmind {
     }
 }
 
+/// Splits `start_date..=end_date` into calendar years, as FinMind truncates responses over long
+/// ranges.
+pub fn split_by_year(
+    start_date: chrono::NaiveDate,
+    end_date: chrono::NaiveDate,
+) -> Vec<(chrono::NaiveDate, chrono::NaiveDate)> {
+    let mut chunks = Vec::new();
+    let mut chunk_start_date = start_date;
+
+    while chunk_start_date <= end_date {
+        let year = chrono::Datelike::year(&chunk_start_date);
+        let chunk_end_date = chrono::NaiveDate::from_ymd_opt(year, 12, 31)
+            .unwrap()
+            .min(end_date);
+
+        chunks.push((chunk_start_date, chunk_end_date));
+        chunk_start_date = chrono::NaiveDate::from_ymd_opt(year + 1, 1, 1).unwrap();
+    }
+    chunks
+}
+
+impl crawler::Crawler for Finmind {
+    fn get_stock_data(&self, args: &crawler::Args) -> Result<Vec<schema::RawData>, crawler::Error> {
+        let mut records = Vec::new();
+
+        for (start_date, end_date) in split_by_year(args.start_date, args.end_date) {
+            records.extend(self.get_stock_data_by_range(&args.stock_id, start_date, end_date)?);
+        }
+        records.sort_by_key(|record| record.date);
+        records.dedup_by_key(|record| record.date);
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod finmind_test {
+    use super::{parse_records, split_by_year};
+
+    #[test]
+    fn parse_records_check() {
+        let values: Vec<serde_json::Value> = serde_json::from_str(
+            r#"[
+                {"stock_id": "0050", "date": "2022-01-03", "open": 10.0, "max": "1,012.5",
+                 "min": 9.5, "close": "10", "spread": null, "Trading_Volume": "1000"},
+                {"stock_id": "0050", "date": "2022-01-04", "open": null, "max": 11.0,
+                 "min": 9.5, "close": 10.5},
+                {"stock_id": "0050", "date": "2022-01-05", "max": 11.0, "min": 9.5,
+                 "close": 10.5},
+                {"stock_id": "0050", "date": "2022-01-06", "open": "--", "max": 11.0,
+                 "min": 9.5, "close": 10.5}
+            ]"#,
+        )
+        .unwrap();
+        let (records, skipped) = parse_records(values);
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(skipped, 3);
+        assert_eq!(records[0].high, 1012.5);
+        assert_eq!(records[0].close, 10.0);
+        assert_eq!(records[0].spread, 0.0);
+        assert_eq!(records[0].trading_volume, 1000);
+        assert_eq!(records[0].trading_money, 0);
+    }
+
+    #[test]
+    fn split_by_year_check() {
+        let date = |year, month, day| chrono::NaiveDate::from_ymd_opt(year, month, day).unwrap();
+
+        assert_eq!(
+            split_by_year(date(2010, 6, 1), date(2012, 3, 31)),
+            vec![
+                (date(2010, 6, 1), date(2010, 12, 31)),
+                (date(2011, 1, 1), date(2011, 12, 31)),
+                (date(2012, 1, 1), date(2012, 3, 31)),
+            ]
+        );
+        assert!(split_by_year(date(2012, 1, 2), date(2012, 1, 1)).is_empty());
+    }
+}
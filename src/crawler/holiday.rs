@@ -0,0 +1,115 @@
+use serde::Deserialize;
+
+use crate::crawler::crawler;
+use crate::storage::calendar;
+
+const TWSE_HOLIDAY_SCHEDULE_URL: &str =
+    "https://www.twse.com.tw/rwd/zh/holidaySchedule/holidaySchedule";
+/// TWSE dates its schedule in years of the Republic of China, counted from 1912.
+const ROC_YEAR_OFFSET: i32 = 1911;
+
+#[derive(Deserialize)]
+struct Response {
+    stat: String,
+    #[serde(default)]
+    data: Vec<Vec<String>>,
+}
+
+fn parse_date(value: &str) -> Option<chrono::NaiveDate> {
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Some(date);
+    }
+
+    let mut parts = value.split('/').map(|part| part.trim().parse::<u32>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+
+    chrono::NaiveDate::from_ymd_opt(year as i32 + ROC_YEAR_OFFSET, month, day)
+}
+
+fn get_session_kind(name: &str, description: &str) -> Option<calendar::SessionKind> {
+    if name.contains("開始交易") || name.contains("最後交易") {
+        if description.contains("半日") {
+            Some(calendar::SessionKind::HalfDay)
+        } else {
+            None
+        }
+    } else if name.contains("結算交割") || description.contains("結算交割") {
+        Some(calendar::SessionKind::SettlementOnly)
+    } else {
+        Some(calendar::SessionKind::Closed)
+    }
+}
+
+/// Sessions of the rows of a TWSE holiday schedule, each `[name, date, weekday, description]`.
+/// Rows marking the first or last trading day around a holiday are regular sessions and left
+/// out, unless trading only lasts half a day.
+pub fn parse_holiday_schedule(rows: &[Vec<String>]) -> Vec<calendar::Session> {
+    rows.iter()
+        .filter_map(|row| {
+            let name = row.first()?;
+            let date = parse_date(row.get(1)?)?;
+            let description = row.get(3).map_or("", |description| description.as_str());
+            let kind = get_session_kind(name, description)?;
+
+            Some(calendar::Session {
+                date,
+                kind,
+                note: name.to_owned(),
+            })
+        })
+        .collect()
+}
+
+/// Sessions of the TWSE holiday schedule of `year`.
+pub fn get_holiday_schedule(year: i32) -> Result<Vec<calendar::Session>, crawler::Error> {
+    let url = reqwest::Url::parse_with_params(
+        TWSE_HOLIDAY_SCHEDULE_URL,
+        &[
+            ("queryYear", (year - ROC_YEAR_OFFSET).to_string()),
+            ("response", "json".to_owned()),
+        ],
+    )?;
+    let resp: Response = reqwest::blocking::get(url)?.json()?;
+
+    match resp.stat.as_str() {
+        "ok" | "OK" => Ok(parse_holiday_schedule(&resp.data)),
+        _ => Err(crawler::Error::BadRequest),
+    }
+}
+
+#[cfg(test)]
+mod holiday_test {
+    use crate::storage::calendar::SessionKind;
+
+    use super::parse_holiday_schedule;
+
+    #[test]
+    fn parse_holiday_schedule_check() {
+        let rows: Vec<Vec<String>> = serde_json::from_str(
+            r#"[
+                ["中華民國開國紀念日", "2024-01-01", "一", "依規定放假1日。"],
+                ["國曆新年開始交易", "2024-01-02", "二", ""],
+                ["農曆春節前最後交易日", "113/02/05", "一", "半日交易。"],
+                ["市場無交易，僅辦理結算交割作業", "113/02/06", "二", ""],
+                ["農曆除夕", "2024-02-08", "四", ""],
+                ["颱風停止上班", "bad date", "四", ""]
+            ]"#,
+        )
+        .unwrap();
+        let sessions = parse_holiday_schedule(&rows);
+
+        assert_eq!(
+            sessions
+                .iter()
+                .map(|session| (session.date.to_string(), session.kind))
+                .collect::<Vec<_>>(),
+            vec![
+                ("2024-01-01".to_owned(), SessionKind::Closed),
+                ("2024-02-05".to_owned(), SessionKind::HalfDay),
+                ("2024-02-06".to_owned(), SessionKind::SettlementOnly),
+                ("2024-02-08".to_owned(), SessionKind::Closed),
+            ]
+        );
+        assert_eq!(sessions[3].note, "農曆除夕");
+    }
+}
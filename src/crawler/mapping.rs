@@ -0,0 +1,334 @@
+use std::collections::HashMap;
+
+use chrono::TimeZone;
+use serde::{Deserialize, Serialize};
+
+use crate::crawler::crawler;
+use crate::strategy::schema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Format {
+    /// CSV with a header row.
+    Csv,
+    /// JSON array of objects, located by `records_path`.
+    Json,
+}
+
+/// Column or key names of the source for each field of `schema::RawData`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldMapping {
+    pub date: String,
+    pub open: String,
+    pub high: String,
+    pub low: String,
+    pub close: String,
+    /// Computed from the previous close when the source has none.
+    #[serde(default)]
+    pub spread: Option<String>,
+    #[serde(default)]
+    pub trading_volume: Option<String>,
+    #[serde(default)]
+    pub trading_money: Option<String>,
+}
+
+fn default_date_format() -> String {
+    "%Y-%m-%d".to_owned()
+}
+
+fn default_multiplier() -> f64 {
+    1.0
+}
+
+fn default_market_timezone() -> chrono_tz::Tz {
+    crawler::MARKET_TIMEZONE
+}
+
+/// `date_format` of Unix timestamps in seconds.
+pub const UNIX_TIMESTAMP_FORMAT: &str = "%s";
+
+/// Declares how to fetch and read the daily prices of a simple CSV or JSON source.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceMapping {
+    pub name: String,
+    /// Request URL, where `{stock_id}`, `{start_date}` and `{end_date}` are substituted.
+    pub url: String,
+    pub format: Format,
+    /// JSON pointer to the array of records, e.g. `/data`; empty when the array is the document.
+    #[serde(default)]
+    pub records_path: String,
+    pub fields: FieldMapping,
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    /// Timezone of the date values of a source stamping bars with an instant, e.g. `UTC` for a
+    /// Unix timestamp; `date_format` then reads a date and time. Unset when the values are
+    /// trading dates already.
+    #[serde(default)]
+    pub timezone: Option<chrono_tz::Tz>,
+    /// Timezone of the exchange the source quotes; instants are dated with its local date.
+    #[serde(default = "default_market_timezone")]
+    pub market_timezone: chrono_tz::Tz,
+    /// Multiplies the prices read, e.g. 0.01 for a source quoting in cents.
+    #[serde(default = "default_multiplier")]
+    pub price_multiplier: f64,
+    /// Multiplies the volumes read, e.g. 1000 for a source counting board lots.
+    #[serde(default = "default_multiplier")]
+    pub volume_multiplier: f64,
+}
+
+impl SourceMapping {
+    pub fn get_url(&self, args: &crawler::Args) -> String {
+        self.url
+            .replace("{stock_id}", &args.stock_id)
+            .replace(
+                "{start_date}",
+                &args.start_date.format("%Y-%m-%d").to_string(),
+            )
+            .replace("{end_date}", &args.end_date.format("%Y-%m-%d").to_string())
+    }
+
+    /// Reads the records of a response body, skipping rows that cannot be read with a warning,
+    /// and keeps those within the dates of `args`, oldest first.
+    pub fn parse(
+        &self,
+        body: &str,
+        args: &crawler::Args,
+    ) -> Result<Vec<schema::RawData>, crawler::Error> {
+        let rows = match self.format {
+            Format::Csv => get_csv_rows(body)?,
+            Format::Json => get_json_rows(body, &self.records_path)?,
+        };
+        let mut records = Vec::new();
+
+        for (index, row) in rows.iter().enumerate() {
+            match self.get_record(row) {
+                Ok(record) => {
+                    if record.date >= args.start_date && record.date <= args.end_date {
+                        records.push(record);
+                    }
+                }
+                Err(err) => println!("Skipped {} row #{}: {}", self.name, index, err),
+            }
+        }
+        records.sort_by_key(|record| record.date);
+        if self.fields.spread.is_none() {
+            for index in 1..records.len() {
+                records[index].spread = records[index].close - records[index - 1].close;
+            }
+        }
+        Ok(records)
+    }
+
+    fn get_record(&self, row: &HashMap<String, String>) -> Result<schema::RawData, String> {
+        let get_value = |name: &str| {
+            row.get(name)
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+                .ok_or(format!("missing {}", name))
+        };
+        let get_number = |name: &str| -> Result<f64, String> {
+            let value = get_value(name)?;
+
+            value
+                .replace(',', "")
+                .parse::<f64>()
+                .map_err(|_| format!("invalid {} {:?}", name, value))
+        };
+        let get_optional_number = |name: &Option<String>| match name {
+            Some(name) => get_number(name),
+            None => Ok(0.0),
+        };
+        let date_value = get_value(&self.fields.date)?;
+
+        Ok(schema::RawData {
+            open: get_number(&self.fields.open)? * self.price_multiplier,
+            high: get_number(&self.fields.high)? * self.price_multiplier,
+            low: get_number(&self.fields.low)? * self.price_multiplier,
+            close: get_number(&self.fields.close)? * self.price_multiplier,
+            spread: get_optional_number(&self.fields.spread)? * self.price_multiplier,
+            date: self
+                .get_date(date_value)
+                .ok_or(format!("invalid date {:?}", date_value))?,
+            trading_volume: (get_optional_number(&self.fields.trading_volume)?
+                * self.volume_multiplier) as u64,
+            trading_money: get_optional_number(&self.fields.trading_money)? as u64,
+        })
+    }
+
+    /// Trading date of a date value, on the market's local calendar.
+    fn get_date(&self, value: &str) -> Option<chrono::NaiveDate> {
+        let timezone = match self.timezone {
+            Some(timezone) => timezone,
+            None => return chrono::NaiveDate::parse_from_str(value, &self.date_format).ok(),
+        };
+        let instant = if self.date_format == UNIX_TIMESTAMP_FORMAT {
+            chrono::DateTime::from_timestamp(value.parse().ok()?, 0)?
+        } else if let Ok(instant) = chrono::DateTime::parse_from_str(value, &self.date_format) {
+            instant.to_utc()
+        } else {
+            let local = chrono::NaiveDateTime::parse_from_str(value, &self.date_format).ok()?;
+
+            timezone.from_local_datetime(&local).earliest()?.to_utc()
+        };
+
+        Some(instant.with_timezone(&self.market_timezone).date_naive())
+    }
+}
+
+fn get_csv_rows(body: &str) -> Result<Vec<HashMap<String, String>>, crawler::Error> {
+    let mut reader = csv::Reader::from_reader(body.as_bytes());
+    let headers = reader.headers()?.clone();
+    let mut rows = Vec::new();
+
+    for result in reader.records() {
+        let record = result?;
+
+        rows.push(
+            headers
+                .iter()
+                .zip(record.iter())
+                .map(|(header, value)| (header.trim().to_owned(), value.to_owned()))
+                .collect(),
+        );
+    }
+    Ok(rows)
+}
+
+fn get_json_rows(
+    body: &str,
+    records_path: &str,
+) -> Result<Vec<HashMap<String, String>>, crawler::Error> {
+    let document: serde_json::Value = serde_json::from_str(body)?;
+    let values = match document
+        .pointer(records_path)
+        .and_then(|value| value.as_array())
+    {
+        Some(values) => values,
+        None => return Err(crawler::Error::BadRequest),
+    };
+
+    Ok(values
+        .iter()
+        .map(|value| match value.as_object() {
+            Some(object) => object
+                .iter()
+                .filter_map(|(key, value)| {
+                    let value = match value {
+                        serde_json::Value::String(text) => text.to_owned(),
+                        serde_json::Value::Number(number) => number.to_string(),
+                        _ => return None,
+                    };
+
+                    Some((key.to_owned(), value))
+                })
+                .collect(),
+            None => HashMap::new(),
+        })
+        .collect())
+}
+
+/// Crawler for a source described by a `SourceMapping`. The stock list still comes from the
+/// default listing of `crawler::Crawler`.
+pub struct MappedCrawler {
+    pub mapping: SourceMapping,
+}
+
+impl MappedCrawler {
+    pub fn new(mapping: SourceMapping) -> Self {
+        MappedCrawler { mapping }
+    }
+}
+
+impl crawler::Crawler for MappedCrawler {
+    fn get_stock_data(&self, args: &crawler::Args) -> Result<Vec<schema::RawData>, crawler::Error> {
+        let body = reqwest::blocking::get(self.mapping.get_url(args))?.text()?;
+
+        self.mapping.parse(&body, args)
+    }
+}
+
+#[cfg(test)]
+mod mapping_test {
+    use crate::crawler::crawler;
+
+    use super::{FieldMapping, Format, SourceMapping};
+
+    #[test]
+    fn parse_csv_check() {
+        let mapping = SourceMapping {
+            name: "cents".to_owned(),
+            url: "https://example.com/{stock_id}.csv".to_owned(),
+            format: Format::Csv,
+            records_path: "".to_owned(),
+            fields: FieldMapping {
+                date: "Day".to_owned(),
+                open: "O".to_owned(),
+                high: "H".to_owned(),
+                low: "L".to_owned(),
+                close: "C".to_owned(),
+                spread: None,
+                trading_volume: Some("Lots".to_owned()),
+                trading_money: None,
+            },
+            date_format: "%Y/%m/%d".to_owned(),
+            timezone: None,
+            market_timezone: crawler::MARKET_TIMEZONE,
+            price_multiplier: 0.01,
+            volume_multiplier: 1000.0,
+        };
+        let args = crawler::Args {
+            stock_id: "0050".to_owned(),
+            start_date: chrono::NaiveDate::from_ymd_opt(2022, 1, 3).unwrap(),
+            end_date: chrono::NaiveDate::from_ymd_opt(2022, 1, 5).unwrap(),
+        };
+        let body = "Day,O,H,L,C,Lots\n\
+                    2022/01/04,1000,1100,950,1050,2\n\
+                    2022/01/03,1000,1000,1000,1000,1\n\
+                    2022/01/05,--,1100,950,1050,2\n\
+                    2022/01/06,1000,1100,950,1050,2\n";
+        let records = mapping.parse(body, &args).unwrap();
+
+        assert_eq!(
+            mapping.get_url(&args),
+            "https://example.com/0050.csv".to_owned()
+        );
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].high, 11.0);
+        assert_eq!(records[1].spread, 0.5);
+        assert_eq!(records[1].trading_volume, 2000);
+    }
+
+    #[test]
+    fn parse_timestamp_check() {
+        let mut mapping: SourceMapping = serde_yaml::from_str(
+            "name: us\n\
+             url: https://example.com/{stock_id}.json\n\
+             format: Json\n\
+             fields: {date: t, open: o, high: h, low: l, close: c}\n\
+             date_format: '%s'\n\
+             timezone: UTC\n\
+             market_timezone: America/New_York",
+        )
+        .unwrap();
+
+        // 2022-01-04 01:00 UTC is still the evening of the 3rd in New York.
+        assert_eq!(
+            mapping.get_date("1641258000"),
+            chrono::NaiveDate::from_ymd_opt(2022, 1, 3)
+        );
+
+        mapping.date_format = "%Y-%m-%dT%H:%M:%S%z".to_owned();
+        mapping.market_timezone = crawler::MARKET_TIMEZONE;
+        assert_eq!(
+            mapping.get_date("2022-01-03T17:00:00+0000"),
+            chrono::NaiveDate::from_ymd_opt(2022, 1, 4)
+        );
+
+        mapping.date_format = "%Y-%m-%d %H:%M".to_owned();
+        mapping.timezone = Some(chrono_tz::America::New_York);
+        assert_eq!(
+            mapping.get_date("2022-01-03 16:00"),
+            chrono::NaiveDate::from_ymd_opt(2022, 1, 4)
+        );
+        assert_eq!(mapping.get_date("2022-01-03"), None);
+    }
+}
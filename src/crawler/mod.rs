@@ -1,2 +1,5 @@
 pub mod crawler;
 pub mod finmind;
+pub mod holiday;
+pub mod mapping;
+pub mod stocklist;
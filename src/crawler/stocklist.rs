@@ -0,0 +1,49 @@
+use std::rc::Rc;
+
+use crate::crawler::crawler;
+use crate::strategy::schema;
+
+/// Crawler restricting the stock list to a fixed set of stock ids while delegating data
+/// requests to another crawler.
+pub struct StockListCrawler {
+    pub crawler: Rc<dyn crawler::Crawler>,
+    pub stock_list: Vec<String>,
+}
+
+impl StockListCrawler {
+    pub fn new(crawler: Rc<dyn crawler::Crawler>, stock_list: Vec<String>) -> Self {
+        StockListCrawler {
+            crawler,
+            stock_list,
+        }
+    }
+}
+
+/// Reads stock ids from a text file, separated by whitespace or commas; anything after a `#` on
+/// a line is a comment.
+pub fn load_stock_list(path: &str) -> std::io::Result<Vec<String>> {
+    let data = std::fs::read_to_string(path)?;
+
+    Ok(data
+        .lines()
+        .flat_map(|line| {
+            line.split('#')
+                .next()
+                .unwrap_or("")
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|stock_id| !stock_id.is_empty())
+                .map(|stock_id| stock_id.to_owned())
+                .collect::<Vec<_>>()
+        })
+        .collect())
+}
+
+impl crawler::Crawler for StockListCrawler {
+    fn get_stock_data(&self, args: &crawler::Args) -> Result<Vec<schema::RawData>, crawler::Error> {
+        self.crawler.get_stock_data(args)
+    }
+
+    fn get_stock_list(&self) -> Result<Vec<String>, crawler::Error> {
+        Ok(self.stock_list.clone())
+    }
+}
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use ta::indicators::SimpleMovingAverage;
+use ta::Next;
+
+use crate::strategy::schema;
+
+use super::crosssection::{self, CrossSection};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BreadthFilter {
+    pub sma_period: usize,
+    /// Percentage of the universe above its SMA below which no new positions are opened.
+    pub min_breadth: f64,
+}
+
+impl std::default::Default for BreadthFilter {
+    fn default() -> Self {
+        BreadthFilter {
+            sma_period: 20,
+            min_breadth: 50.0,
+        }
+    }
+}
+
+impl BreadthFilter {
+    pub fn allows_entry(&self, breadth: f64) -> bool {
+        breadth >= self.min_breadth
+    }
+}
+
+/// Percentage of `stock_ids` closing above their `sma_period`-day SMA on `date`, or `None` if
+/// no stock has enough history to tell.
+pub fn above_sma(
+    cross_section: &CrossSection,
+    stock_ids: &[String],
+    date: chrono::NaiveDate,
+    sma_period: usize,
+) -> Result<Option<f64>, crosssection::Error> {
+    let values = cross_section.compute(stock_ids, date, sma_period * 2, |records| {
+        is_above_sma(records, sma_period)
+    })?;
+
+    if values.is_empty() {
+        return Ok(None);
+    }
+
+    let above_count = values.iter().filter(|(_, above)| *above > 0.0).count();
+
+    Ok(Some(above_count as f64 / values.len() as f64 * 100.0))
+}
+
+fn is_above_sma(
+    records: &[schema::RawData],
+    sma_period: usize,
+) -> Result<Option<f64>, crosssection::Error> {
+    if records.len() < sma_period {
+        return Ok(None);
+    }
+
+    let mut sma = SimpleMovingAverage::new(sma_period)?;
+    let mut average = 0.0;
+
+    for record in records {
+        average = sma.next(record.close);
+    }
+
+    let close = records.last().unwrap().close;
+
+    Ok(Some((close > average) as u8 as f64))
+}
@@ -0,0 +1,212 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::crosssection::{self, CrossSection};
+
+/// 5% critical value of the Engle-Granger test with two variables and a constant (MacKinnon,
+/// 2010). Statistics below it reject that the spread wanders off for good.
+pub const ADF_CRITICAL_VALUE: f64 = -3.34;
+/// Days both stocks have to have traded on for a pair to be tested.
+pub const MIN_COMMON_DAYS: usize = 30;
+
+/// Engle-Granger test of a pair: the log price of the first stock is regressed on that of the
+/// second, and the residual, the spread, is tested for a unit root.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cointegration {
+    pub first_id: String,
+    pub second_id: String,
+    /// Slope of the regression, the units of the second stock's log price the spread holds
+    /// against one of the first.
+    pub hedge_ratio: f64,
+    pub intercept: f64,
+    /// Dickey-Fuller statistic of the spread; the more negative, the faster it reverts.
+    pub adf_statistic: f64,
+}
+
+impl Cointegration {
+    /// Tests `first_id` against `second_id` on `closes`, their closes on the days both traded,
+    /// oldest first. None with fewer than `MIN_COMMON_DAYS` days or without any variation.
+    pub fn test(first_id: &str, second_id: &str, closes: &[(f64, f64)]) -> Option<Cointegration> {
+        if closes.len() < MIN_COMMON_DAYS
+            || closes
+                .iter()
+                .any(|(first, second)| *first <= 0.0 || *second <= 0.0)
+        {
+            return None;
+        }
+
+        let log_closes: Vec<(f64, f64)> = closes
+            .iter()
+            .map(|(first, second)| (first.ln(), second.ln()))
+            .collect();
+        let (intercept, hedge_ratio) = regress(&log_closes)?;
+        let spreads: Vec<f64> = log_closes
+            .iter()
+            .map(|(first, second)| first - intercept - hedge_ratio * second)
+            .collect();
+
+        Some(Cointegration {
+            first_id: first_id.to_owned(),
+            second_id: second_id.to_owned(),
+            hedge_ratio,
+            intercept,
+            adf_statistic: get_adf_statistic(&spreads)?,
+        })
+    }
+
+    pub fn is_cointegrated(&self) -> bool {
+        self.adf_statistic < ADF_CRITICAL_VALUE
+    }
+}
+
+/// Tests every pair of `stock_ids` on their closes between `start_date` and `end_date`, and
+/// returns the cointegrated ones, the most mean reverting first.
+pub fn scan(
+    cross_section: &CrossSection,
+    stock_ids: &[String],
+    start_date: chrono::NaiveDate,
+    end_date: chrono::NaiveDate,
+) -> Result<Vec<Cointegration>, crosssection::Error> {
+    let mut closes_series = Vec::new();
+
+    for stock_id in stock_ids {
+        let closes: BTreeMap<chrono::NaiveDate, f64> = cross_section
+            .backend_op
+            .query_by_range(stock_id, start_date, end_date)?
+            .iter()
+            .map(|record| (record.date, record.close))
+            .collect();
+
+        closes_series.push(closes);
+    }
+
+    let mut cointegrations = Vec::new();
+
+    for i in 0..stock_ids.len() {
+        for j in i + 1..stock_ids.len() {
+            let closes: Vec<(f64, f64)> = closes_series[i]
+                .iter()
+                .filter_map(|(date, first)| {
+                    closes_series[j].get(date).map(|second| (*first, *second))
+                })
+                .collect();
+
+            match Cointegration::test(&stock_ids[i], &stock_ids[j], &closes) {
+                Some(cointegration) if cointegration.is_cointegrated() => {
+                    cointegrations.push(cointegration)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    cointegrations.sort_by(|a, b| a.adf_statistic.total_cmp(&b.adf_statistic));
+    Ok(cointegrations)
+}
+
+/// Intercept and slope of the least squares fit of the first values on the second ones.
+fn regress(points: &[(f64, f64)]) -> Option<(f64, f64)> {
+    let count = points.len() as f64;
+    let mean_y = points.iter().map(|(y, _)| y).sum::<f64>() / count;
+    let mean_x = points.iter().map(|(_, x)| x).sum::<f64>() / count;
+    let mut covariance = 0.0;
+    let mut variance_x = 0.0;
+
+    for (y, x) in points {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance_x += (x - mean_x) * (x - mean_x);
+    }
+
+    if variance_x == 0.0 {
+        return None;
+    }
+
+    let slope = covariance / variance_x;
+
+    Some((mean_y - slope * mean_x, slope))
+}
+
+/// t-statistic of `gamma` in `spread[t] - spread[t - 1] = gamma * spread[t - 1] + error`, the
+/// Dickey-Fuller regression without lags; the spread is a regression residual, so it has no
+/// constant.
+fn get_adf_statistic(spreads: &[f64]) -> Option<f64> {
+    let lagged = &spreads[..spreads.len() - 1];
+    let diffs: Vec<f64> = spreads.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    let sum_squares: f64 = lagged.iter().map(|spread| spread * spread).sum();
+
+    if sum_squares == 0.0 {
+        return None;
+    }
+
+    let gamma = lagged
+        .iter()
+        .zip(&diffs)
+        .map(|(spread, diff)| spread * diff)
+        .sum::<f64>()
+        / sum_squares;
+    let residual_variance = lagged
+        .iter()
+        .zip(&diffs)
+        .map(|(spread, diff)| (diff - gamma * spread).powi(2))
+        .sum::<f64>()
+        / (diffs.len() - 1) as f64;
+
+    if residual_variance == 0.0 {
+        return None;
+    }
+    Some(gamma / (residual_variance / sum_squares).sqrt())
+}
+
+#[cfg(test)]
+mod cointegration_test {
+    use super::Cointegration;
+
+    /// Daily price ratios of up to 1% either way, from a linear congruential generator so that
+    /// the test is repeatable.
+    fn get_steps(days: usize, seed: u64) -> Vec<f64> {
+        let mut state = seed;
+
+        (0..days)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                1.0 + ((state >> 33) as f64 / (1u64 << 31) as f64 - 0.5) / 50.0
+            })
+            .collect()
+    }
+
+    fn get_random_walk(days: usize, seed: u64) -> Vec<f64> {
+        get_steps(days, seed)
+            .into_iter()
+            .scan(100.0, |price, step| {
+                *price *= step;
+                Some(*price)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_check() {
+        let walk = get_random_walk(250, 1);
+        // Twice the walk in log terms, off by a noise that does not build up.
+        let cointegrated: Vec<(f64, f64)> = walk
+            .iter()
+            .zip(get_steps(250, 3))
+            .map(|(price, noise)| (price * price / 100.0 * noise, *price))
+            .collect();
+        let cointegration = Cointegration::test("2330", "2303", &cointegrated).unwrap();
+
+        assert!((cointegration.hedge_ratio - 2.0).abs() < 0.1);
+        assert!(cointegration.is_cointegrated());
+
+        let independent: Vec<(f64, f64)> =
+            walk.iter().cloned().zip(get_random_walk(250, 2)).collect();
+
+        assert!(!Cointegration::test("2330", "2317", &independent)
+            .unwrap()
+            .is_cointegrated());
+        assert!(Cointegration::test("2330", "2317", &independent[..10]).is_none());
+    }
+}
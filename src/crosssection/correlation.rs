@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::dataview::view;
+use crate::diagram::diagram;
+
+use super::crosssection::{self, CrossSection};
+
+pub const CORRELATION_DIAGRAM_FILENAME: &str = "correlation.html";
+pub const MAX_CLUSTER_DISTANCE: f64 = 0.5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorrelationMatrix {
+    pub stock_ids: Vec<String>,
+    /// Pearson correlation of daily log returns, indexed like `stock_ids`.
+    pub values: Vec<Vec<f64>>,
+}
+
+impl CorrelationMatrix {
+    /// Correlates the daily returns of `stock_ids` between `start_date` and `end_date`. Each pair
+    /// is compared on the days both stocks traded; pairs with fewer than two common days, or
+    /// with a constant price, are given a correlation of zero.
+    pub fn compute(
+        cross_section: &CrossSection,
+        stock_ids: &[String],
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> Result<CorrelationMatrix, crosssection::Error> {
+        let mut returns_series = Vec::new();
+
+        for stock_id in stock_ids {
+            let records = cross_section
+                .backend_op
+                .query_by_range(stock_id, start_date, end_date)?;
+            let returns: HashMap<chrono::NaiveDate, f64> =
+                view::ReturnsView::transform_by_records(&records)
+                    .iter()
+                    .map(|view| (view.date, view.log_return))
+                    .collect();
+
+            returns_series.push(returns);
+        }
+
+        let mut values = vec![vec![0.0; stock_ids.len()]; stock_ids.len()];
+
+        for i in 0..stock_ids.len() {
+            values[i][i] = 1.0;
+            for j in i + 1..stock_ids.len() {
+                let correlation = correlate(&returns_series[i], &returns_series[j]);
+
+                values[i][j] = correlation;
+                values[j][i] = correlation;
+            }
+        }
+
+        Ok(CorrelationMatrix {
+            stock_ids: stock_ids.to_vec(),
+            values,
+        })
+    }
+
+    /// Average-linkage hierarchical clustering on the distance `1 - correlation`. Clusters keep
+    /// merging while the closest pair is at most `max_distance` apart.
+    pub fn cluster(&self, max_distance: f64) -> Vec<Vec<String>> {
+        let mut clusters: Vec<Vec<usize>> = (0..self.stock_ids.len()).map(|i| vec![i]).collect();
+
+        loop {
+            let mut closest: Option<(usize, usize, f64)> = None;
+
+            for i in 0..clusters.len() {
+                for j in i + 1..clusters.len() {
+                    let distance = self.get_linkage(&clusters[i], &clusters[j]);
+
+                    match closest {
+                        Some((_, _, min_distance)) if min_distance <= distance => {}
+                        _ => closest = Some((i, j, distance)),
+                    }
+                }
+            }
+
+            match closest {
+                Some((i, j, distance)) if distance <= max_distance => {
+                    let merged = clusters.remove(j);
+
+                    clusters[i].extend(merged);
+                }
+                _ => break,
+            }
+        }
+
+        clusters
+            .iter()
+            .map(|cluster| {
+                cluster
+                    .iter()
+                    .map(|index| self.stock_ids[*index].to_owned())
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn get_linkage(&self, lhs: &[usize], rhs: &[usize]) -> f64 {
+        let mut total = 0.0;
+
+        for i in lhs {
+            for j in rhs {
+                total += 1.0 - self.values[*i][*j];
+            }
+        }
+
+        total / (lhs.len() * rhs.len()) as f64
+    }
+
+    /// Renders the matrix as a heatmap with the stocks ordered cluster by cluster, so that
+    /// correlated groups show up as blocks along the diagonal.
+    pub fn draw_heatmap(
+        &self,
+        clusters: &[Vec<String>],
+        style: &diagram::DiagramStyle,
+        output: &diagram::Output,
+    ) {
+        let index_map: HashMap<&str, usize> = self
+            .stock_ids
+            .iter()
+            .enumerate()
+            .map(|(index, stock_id)| (stock_id.as_str(), index))
+            .collect();
+        let order: Vec<usize> = clusters
+            .iter()
+            .flatten()
+            .map(|stock_id| index_map[stock_id.as_str()])
+            .collect();
+        let labels: Vec<String> = order
+            .iter()
+            .map(|index| self.stock_ids[*index].to_owned())
+            .collect();
+        let values: Vec<Vec<f64>> = order
+            .iter()
+            .map(|i| order.iter().map(|j| self.values[*i][*j]).collect())
+            .collect();
+        let mut plot = plotly::Plot::new();
+        let trace = plotly::HeatMap::new(labels.clone(), labels, values)
+            .zmin(-1.0)
+            .zmax(1.0)
+            .name("Correlation");
+
+        plot.add_trace(trace);
+        plot.set_layout(style.get_layout());
+        diagram::render(&plot, style, output);
+    }
+}
+
+fn correlate(lhs: &HashMap<chrono::NaiveDate, f64>, rhs: &HashMap<chrono::NaiveDate, f64>) -> f64 {
+    let pairs: Vec<(f64, f64)> = lhs
+        .iter()
+        .filter_map(|(date, x)| rhs.get(date).map(|y| (*x, *y)))
+        .collect();
+
+    if pairs.len() < 2 {
+        return 0.0;
+    }
+
+    let count = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|(x, _)| x).sum::<f64>() / count;
+    let mean_y = pairs.iter().map(|(_, y)| y).sum::<f64>() / count;
+    let mut covariance = 0.0;
+    let mut variance_x = 0.0;
+    let mut variance_y = 0.0;
+
+    for (x, y) in &pairs {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance_x += (x - mean_x) * (x - mean_x);
+        variance_y += (y - mean_y) * (y - mean_y);
+    }
+
+    if variance_x == 0.0 || variance_y == 0.0 {
+        return 0.0;
+    }
+
+    covariance / (variance_x * variance_y).sqrt()
+}
+
+#[cfg(test)]
+mod correlation_test {
+    use std::rc::Rc;
+
+    use super::{CorrelationMatrix, MAX_CLUSTER_DISTANCE};
+    use crate::crosssection::crosssection::CrossSection;
+    use crate::storage::{backend::BackendOp, memory};
+    use crate::testkit::generator;
+
+    #[test]
+    fn compute_check() {
+        let backend_op = Rc::new(memory::MemoryBackend::new());
+        let start_date = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let insert = |stock_id: &str, closes: Vec<f64>| {
+            let mut records = generator::SeriesBuilder::new(start_date, closes[0])
+                .flat(closes.len())
+                .build();
+
+            for (record, close) in records.iter_mut().zip(closes) {
+                record.close = close;
+            }
+            backend_op
+                .batch_insert(
+                    &records
+                        .into_iter()
+                        .map(|record| (stock_id.to_owned(), record))
+                        .collect(),
+                )
+                .unwrap();
+        };
+        let closes = [100.0, 102.0, 99.0, 103.0, 101.0, 104.0];
+
+        // "b" moves like "a" at twice the price, "c" by the opposite log returns.
+        insert("a", closes.to_vec());
+        insert("b", closes.iter().map(|close| close * 2.0).collect());
+        insert("c", closes.iter().map(|close| 10000.0 / close).collect());
+
+        let stock_ids: Vec<String> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|stock_id| stock_id.to_string())
+            .collect();
+        let end_date = chrono::NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        let matrix = CorrelationMatrix::compute(
+            &CrossSection::new(backend_op),
+            &stock_ids,
+            start_date,
+            end_date,
+        )
+        .unwrap();
+
+        assert!((0..4).all(|i| matrix.values[i][i] == 1.0));
+        assert!((matrix.values[0][1] - 1.0).abs() < 1e-9);
+        assert!((matrix.values[0][2] + 1.0).abs() < 1e-9);
+        assert!((matrix.values[2][1] + 1.0).abs() < 1e-9);
+        // "d" has no records to correlate.
+        assert_eq!(matrix.values[0][3], 0.0);
+        assert_eq!(
+            matrix.cluster(MAX_CLUSTER_DISTANCE),
+            vec![vec!["a", "b"], vec!["c"], vec!["d"]]
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use std::rc::Rc;
+
+use crate::storage::backend;
+use crate::strategy::schema;
+
+#[derive(Debug)]
+pub enum Error {
+    Backend(backend::Error),
+    Ta(ta::errors::TaError),
+}
+
+impl From<backend::Error> for Error {
+    fn from(err: backend::Error) -> Error {
+        Error::Backend(err)
+    }
+}
+
+impl From<ta::errors::TaError> for Error {
+    fn from(err: ta::errors::TaError) -> Error {
+        Error::Ta(err)
+    }
+}
+
+pub struct CrossSection {
+    pub backend_op: Rc<dyn backend::BackendOp>,
+}
+
+impl CrossSection {
+    pub fn new(backend_op: Rc<dyn backend::BackendOp>) -> Self {
+        CrossSection { backend_op }
+    }
+
+    /// Applies `metric` to the records of the last `history_days` calendar days of every stock
+    /// trading on `date`. Stocks without a record on `date` or for which `metric` yields `None`
+    /// are left out.
+    pub fn compute<F>(
+        &self,
+        stock_ids: &[String],
+        date: chrono::NaiveDate,
+        history_days: usize,
+        metric: F,
+    ) -> Result<Vec<(String, f64)>, Error>
+    where
+        F: Fn(&[schema::RawData]) -> Result<Option<f64>, Error>,
+    {
+        let start_date = date - chrono::Duration::days(history_days as i64);
+        let mut values = Vec::new();
+
+        for stock_id in stock_ids {
+            let records = self.backend_op.query_by_range(stock_id, start_date, date)?;
+
+            match records.last() {
+                Some(record) if record.date == date => {}
+                _ => continue,
+            }
+            if let Some(value) = metric(&records)? {
+                values.push((stock_id.to_owned(), value));
+            }
+        }
+
+        Ok(values)
+    }
+}
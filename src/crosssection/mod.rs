@@ -0,0 +1,4 @@
+pub mod breadth;
+pub mod cointegration;
+pub mod correlation;
+pub mod crosssection;
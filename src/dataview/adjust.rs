@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+
+use crate::strategy::schema;
+
+/// Gap below which the close and the reference price of the next day are taken as equal, as a
+/// ratio of the close; anything smaller is rounding of the quotes.
+pub const MIN_DISTRIBUTION_RATIO: f64 = 0.001;
+
+/// A cash distribution inferred from the quotes around its ex-date.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Distribution {
+    /// First trading day quoted without the distribution.
+    pub date: chrono::NaiveDate,
+    pub amount: f64,
+    /// Factor to multiply prices before `date` by.
+    pub adjustment_factor: f64,
+}
+
+/// Distributions of `records`, sorted by date. The spread of a record is its change against the
+/// reference price the exchange set for the day, which on an ex-date is the previous close less
+/// the distribution; the difference between the two is the distribution.
+pub fn get_distributions(records: &[schema::RawData]) -> Vec<Distribution> {
+    records
+        .windows(2)
+        .filter_map(|pair| {
+            let (prev, record) = (&pair[0], &pair[1]);
+            let amount = prev.close - (record.close - record.spread);
+
+            if prev.close <= 0.0 || amount <= prev.close * MIN_DISTRIBUTION_RATIO {
+                return None;
+            }
+            Some(Distribution {
+                date: record.date,
+                amount,
+                adjustment_factor: (prev.close - amount) / prev.close,
+            })
+        })
+        .collect()
+}
+
+/// Copy of `records`, sorted by date, with the prices before each distribution scaled down so
+/// that the series runs on without the drop of the ex-dates. The latest prices are left as
+/// quoted; volumes are untouched since a distribution does not change the number of shares.
+pub fn back_adjust(records: &[schema::RawData]) -> Vec<schema::RawData> {
+    let distributions = get_distributions(records);
+    let mut adjusted = records.to_vec();
+
+    for distribution in &distributions {
+        for record in adjusted
+            .iter_mut()
+            .take_while(|record| record.date < distribution.date)
+        {
+            record.open *= distribution.adjustment_factor;
+            record.high *= distribution.adjustment_factor;
+            record.low *= distribution.adjustment_factor;
+            record.close *= distribution.adjustment_factor;
+            record.spread *= distribution.adjustment_factor;
+        }
+    }
+    adjusted
+}
+
+/// Stocks whose views are computed on back-adjusted prices, while the stored records and the
+/// prices orders fill at stay as quoted.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BackAdjustment {
+    pub stock_ids: Vec<String>,
+}
+
+impl BackAdjustment {
+    pub fn new(stock_ids: &[String]) -> Self {
+        BackAdjustment {
+            stock_ids: stock_ids.to_vec(),
+        }
+    }
+
+    pub fn is_adjusted(&self, stock_id: &str) -> bool {
+        self.stock_ids
+            .iter()
+            .any(|adjusted_id| adjusted_id == stock_id)
+    }
+
+    /// Records of `stock_id` as its views should see them.
+    pub fn apply(&self, stock_id: &str, records: Vec<schema::RawData>) -> Vec<schema::RawData> {
+        match self.is_adjusted(stock_id) {
+            true => back_adjust(&records),
+            false => records,
+        }
+    }
+}
+
+#[cfg(test)]
+mod adjust_test {
+    use crate::testkit::generator;
+
+    use super::{get_distributions, BackAdjustment};
+
+    #[test]
+    fn back_adjust_check() {
+        let start_date = chrono::NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+        let mut records = generator::SeriesBuilder::new(start_date, 100.0)
+            .flat(30)
+            .build();
+
+        // Ex-dividend of 2.0 on day 20: the price drops, the spread against the reference
+        // price does not.
+        for record in records.iter_mut().skip(20) {
+            record.open -= 2.0;
+            record.high -= 2.0;
+            record.low -= 2.0;
+            record.close -= 2.0;
+        }
+
+        let distributions = get_distributions(&records);
+
+        assert_eq!(distributions.len(), 1);
+        assert_eq!(distributions[0].date, records[20].date);
+        assert!((distributions[0].amount - 2.0).abs() < 1e-9);
+
+        let back_adjustment = BackAdjustment::new(&["0050".to_owned()]);
+        let adjusted = back_adjustment.apply("0050", records.clone());
+
+        assert!((adjusted[19].close - 98.0).abs() < 1e-9);
+        assert_eq!(adjusted[20].close, records[20].close);
+        assert_eq!(adjusted[0].trading_volume, records[0].trading_volume);
+        assert!(get_distributions(&adjusted).is_empty());
+        assert_eq!(
+            back_adjustment.apply("2330", records.clone())[19].close,
+            100.0
+        );
+    }
+}
@@ -1,2 +1,3 @@
+pub mod adjust;
+pub mod rolling;
 pub mod view;
-
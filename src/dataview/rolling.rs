@@ -0,0 +1,72 @@
+use std::collections::VecDeque;
+
+use super::view;
+
+/// Maximum of the last `period` values. The candidates are kept in a queue of decreasing values,
+/// so each value is pushed and popped once and a step costs amortized constant time, however
+/// long the window.
+pub struct RollingMax {
+    period: usize,
+    count: usize,
+    candidates: VecDeque<(usize, f64)>,
+}
+
+impl RollingMax {
+    pub fn new(period: usize) -> Result<RollingMax, view::Error> {
+        if period == 0 {
+            return Err(ta::errors::TaError::InvalidParameter.into());
+        }
+        Ok(RollingMax {
+            period,
+            count: 0,
+            candidates: VecDeque::new(),
+        })
+    }
+
+    /// Adds `value` and returns the maximum of the window ending with it.
+    pub fn next(&mut self, value: f64) -> f64 {
+        while self
+            .candidates
+            .back()
+            .is_some_and(|(_, candidate)| *candidate <= value)
+        {
+            self.candidates.pop_back();
+        }
+        self.candidates.push_back((self.count, value));
+        while self
+            .candidates
+            .front()
+            .is_some_and(|(index, _)| index + self.period <= self.count)
+        {
+            self.candidates.pop_front();
+        }
+        self.count += 1;
+        self.candidates.front().map_or(value, |(_, max)| *max)
+    }
+}
+
+#[cfg(test)]
+mod rolling_test {
+    use super::RollingMax;
+
+    #[test]
+    fn rolling_max_check() {
+        let values = [3.0, 1.0, 4.0, 1.0, 5.0, 9.0, 2.0, 6.0, 5.0, 3.0, 5.0];
+        let mut rolling_max = RollingMax::new(3).unwrap();
+        let maxima: Vec<f64> = values
+            .iter()
+            .map(|value| rolling_max.next(*value))
+            .collect();
+        let expected: Vec<f64> = (0..values.len())
+            .map(|index| {
+                values[index.saturating_sub(2)..=index]
+                    .iter()
+                    .cloned()
+                    .fold(f64::MIN, f64::max)
+            })
+            .collect();
+
+        assert_eq!(maxima, expected);
+        assert!(RollingMax::new(0).is_err());
+    }
+}
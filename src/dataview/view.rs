@@ -1,14 +1,39 @@
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
 use std::result::Result;
-use ta::indicators::{SimpleMovingAverage, StandardDeviation};
+use ta::indicators::{
+    AverageTrueRange, ExponentialMovingAverage, MovingAverageConvergenceDivergence,
+    RelativeStrengthIndex, SimpleMovingAverage, StandardDeviation,
+};
 use ta::Next;
 
 use crate::strategy::{bollinger_band, schema};
 
+use super::rolling;
+
+pub const ATR_PERIOD: usize = 14;
+pub const EWMA_LAMBDA: f64 = 0.94;
+pub const BETA_PERIOD: usize = 60;
+pub const RSI_PERIOD: usize = 14;
+pub const MACD_FAST_PERIOD: usize = 12;
+pub const MACD_SLOW_PERIOD: usize = 26;
+pub const MACD_SIGNAL_PERIOD: usize = 9;
+pub const KELTNER_PERIOD: usize = 20;
+/// ATRs between the middle line of the Keltner channel and its bounds.
+pub const KELTNER_MULTIPLIER: f64 = 1.5;
+pub const GAP_VOLUME_PERIOD: usize = 20;
+
 pub enum Views {
     None,
     BollingerBand,
+    Atr,
+    EwmaVolatility,
+    Returns,
+    Beta,
+    Rsi,
+    Macd,
+    Keltner,
 }
 
 #[derive(Debug)]
@@ -24,8 +49,129 @@ pub struct BollingerBandView {
     pub close: f64,
     pub date: NaiveDate,
     pub volume: u64,
+    /// Middle band: the moving average of the typical price, simple or exponential depending on
+    /// the basis the view was computed with.
     pub sma: f64,
     pub sd: f64,
+    /// Position of the typical price within the bands, 0 on the lower band and 1 on the upper.
+    #[serde(default)]
+    pub percent_b: f64,
+    /// Width of the bands relative to the middle band.
+    #[serde(default)]
+    pub bandwidth: f64,
+}
+
+/// Moving average the middle band of the Bollinger bands follows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BandBasis {
+    #[default]
+    Sma,
+    Ema,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AtrView {
+    pub date: NaiveDate,
+    pub close: f64,
+    pub atr: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EwmaVolatilityView {
+    pub date: NaiveDate,
+    pub close: f64,
+    pub log_return: f64,
+    /// Daily volatility estimated from the returns up to and including this day.
+    pub volatility: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ReturnsView {
+    pub date: NaiveDate,
+    pub close: f64,
+    pub simple_return: f64,
+    pub log_return: f64,
+    /// Return compounded from the first close of the series up to this day.
+    pub cumulative_return: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BetaView {
+    pub date: NaiveDate,
+    pub close: f64,
+    /// Beta of the daily returns against the benchmark over the trailing window.
+    pub beta: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RsiView {
+    pub date: NaiveDate,
+    pub close: f64,
+    pub rsi: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MacdView {
+    pub date: NaiveDate,
+    pub close: f64,
+    pub macd: f64,
+    pub signal: f64,
+    pub histogram: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct KeltnerView {
+    pub date: NaiveDate,
+    pub close: f64,
+    /// Middle line: the EMA of the close.
+    pub ema: f64,
+    pub atr: f64,
+    pub upper: f64,
+    pub lower: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GapView {
+    pub date: NaiveDate,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    /// Reference price of the day: the previous close, net of any distribution.
+    pub prev_close: f64,
+    /// Move of the open from `prev_close`, in percent.
+    pub gap: f64,
+    pub volume: u64,
+    /// Volume over the average volume of the days before.
+    pub volume_ratio: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CrossoverView {
+    pub date: NaiveDate,
+    pub close: f64,
+    pub volume: u64,
+    pub short_sma: f64,
+    pub long_sma: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HighView {
+    pub date: NaiveDate,
+    pub high: f64,
+    pub close: f64,
+    /// Highest high of the days before, over the period of the view.
+    pub prior_high: f64,
+    pub volume: u64,
+    /// Volume over the average volume of the days before.
+    pub volume_ratio: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SmaView {
+    pub date: NaiveDate,
+    pub close: f64,
+    pub sma: f64,
 }
 
 pub trait Transform {
@@ -51,7 +197,78 @@ impl Default for BollingerBandView {
             volume: 0,
             sma: 0.0,
             sd: 0.0,
+            percent_b: 0.0,
+            bandwidth: 0.0,
+        }
+    }
+}
+
+enum MovingAverage {
+    Sma(SimpleMovingAverage),
+    Ema(ExponentialMovingAverage),
+}
+
+impl MovingAverage {
+    fn new(basis: BandBasis, period: usize) -> Result<MovingAverage, Error> {
+        Ok(match basis {
+            BandBasis::Sma => MovingAverage::Sma(SimpleMovingAverage::new(period)?),
+            BandBasis::Ema => MovingAverage::Ema(ExponentialMovingAverage::new(period)?),
+        })
+    }
+
+    fn next(&mut self, value: f64) -> f64 {
+        match self {
+            MovingAverage::Sma(sma) => sma.next(value),
+            MovingAverage::Ema(ema) => ema.next(value),
+        }
+    }
+}
+
+impl BollingerBandView {
+    /// Bands `band_size` standard deviations around the `basis` average of the typical price
+    /// over `period` days.
+    pub fn transform_by_params(
+        records: &[schema::RawData],
+        period: usize,
+        band_size: f64,
+        basis: BandBasis,
+    ) -> Result<Vec<BollingerBandView>, Error> {
+        let mut views = Vec::new();
+        let mut sd = StandardDeviation::new(period)?;
+        let mut average = MovingAverage::new(basis, period)?;
+
+        for (idx, record) in records.iter().enumerate() {
+            let mut view = BollingerBandView {
+                open: record.open,
+                high: record.high,
+                low: record.low,
+                close: record.close,
+                date: record.date,
+                volume: record.trading_volume,
+                ..Default::default()
+            };
+            let price = (record.high + record.low + record.close) / 3.0;
+
+            view.sma = average.next(price);
+            view.sd = sd.next(price);
+
+            let width = 2.0 * band_size * view.sd;
+
+            // A flat window has no band to sit in, nor a zero average a width to scale by.
+            view.percent_b = match width == 0.0 {
+                true => 0.0,
+                false => (price - (view.sma - band_size * view.sd)) / width,
+            };
+            view.bandwidth = match view.sma == 0.0 {
+                true => 0.0,
+                false => width / view.sma,
+            };
+            if idx + 1 >= period {
+                views.push(view);
+            }
         }
+
+        Ok(views)
     }
 }
 
@@ -59,24 +276,490 @@ impl Transform for BollingerBandView {
     type View = BollingerBandView;
 
     fn transform(records: &Vec<schema::RawData>) -> Result<Vec<Self::View>, Error> {
+        BollingerBandView::transform_by_params(
+            records,
+            bollinger_band::PERIOD,
+            bollinger_band::BAND_SIZE,
+            BandBasis::Sma,
+        )
+    }
+}
+
+impl Default for AtrView {
+    fn default() -> AtrView {
+        AtrView {
+            date: chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
+            close: 0.0,
+            atr: 0.0,
+        }
+    }
+}
+
+impl AtrView {
+    pub fn transform_by_period(
+        records: &[schema::RawData],
+        period: usize,
+    ) -> Result<Vec<AtrView>, Error> {
         let mut views = Vec::new();
-        let mut sd = StandardDeviation::new(bollinger_band::PERIOD)?;
-        let mut sma = SimpleMovingAverage::new(bollinger_band::PERIOD)?;
+        let mut atr = AverageTrueRange::new(period)?;
 
         for (idx, record) in records.iter().enumerate() {
-            let mut view = BollingerBandView {
+            let view = AtrView {
+                date: record.date,
+                close: record.close,
+                atr: atr.next(record),
+            };
+
+            if idx + 1 >= period {
+                views.push(view);
+            }
+        }
+
+        Ok(views)
+    }
+}
+
+impl Transform for AtrView {
+    type View = AtrView;
+
+    fn transform(records: &Vec<schema::RawData>) -> Result<Vec<Self::View>, Error> {
+        AtrView::transform_by_period(records, ATR_PERIOD)
+    }
+}
+
+impl Default for EwmaVolatilityView {
+    fn default() -> EwmaVolatilityView {
+        EwmaVolatilityView {
+            date: chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
+            close: 0.0,
+            log_return: 0.0,
+            volatility: 0.0,
+        }
+    }
+}
+
+impl EwmaVolatilityView {
+    pub fn transform_by_lambda(
+        records: &[schema::RawData],
+        lambda: f64,
+    ) -> Result<Vec<EwmaVolatilityView>, Error> {
+        if lambda <= 0.0 || lambda >= 1.0 {
+            return Err(Error::Ta(ta::errors::TaError::InvalidParameter));
+        }
+
+        let mut views = Vec::new();
+        let mut variance: Option<f64> = None;
+
+        for returns in ReturnsView::transform_by_records(records) {
+            let log_return = returns.log_return;
+
+            variance = Some(match variance {
+                Some(variance) => lambda * variance + (1.0 - lambda) * log_return * log_return,
+                None => log_return * log_return,
+            });
+            views.push(EwmaVolatilityView {
+                date: returns.date,
+                close: returns.close,
+                log_return,
+                volatility: variance.unwrap().sqrt(),
+            });
+        }
+
+        Ok(views)
+    }
+}
+
+impl Transform for EwmaVolatilityView {
+    type View = EwmaVolatilityView;
+
+    fn transform(records: &Vec<schema::RawData>) -> Result<Vec<Self::View>, Error> {
+        EwmaVolatilityView::transform_by_lambda(records, EWMA_LAMBDA)
+    }
+}
+
+impl Default for ReturnsView {
+    fn default() -> ReturnsView {
+        ReturnsView {
+            date: chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
+            close: 0.0,
+            simple_return: 0.0,
+            log_return: 0.0,
+            cumulative_return: 0.0,
+        }
+    }
+}
+
+impl ReturnsView {
+    /// Daily returns starting from the second record; a non-positive close yields zero returns
+    /// for the days around it rather than an infinite or NaN value.
+    pub fn transform_by_records(records: &[schema::RawData]) -> Vec<ReturnsView> {
+        let mut views = Vec::new();
+        let base = match records.first() {
+            Some(record) => record.close,
+            None => return views,
+        };
+
+        for window in records.windows(2) {
+            let (prev, record) = (&window[0], &window[1]);
+            let mut view = ReturnsView {
+                date: record.date,
+                close: record.close,
+                ..Default::default()
+            };
+
+            if prev.close > 0.0 && record.close > 0.0 {
+                view.simple_return = record.close / prev.close - 1.0;
+                view.log_return = (record.close / prev.close).ln();
+            }
+            if base > 0.0 {
+                view.cumulative_return = record.close / base - 1.0;
+            }
+            views.push(view);
+        }
+
+        views
+    }
+}
+
+impl Transform for ReturnsView {
+    type View = ReturnsView;
+
+    fn transform(records: &Vec<schema::RawData>) -> Result<Vec<Self::View>, Error> {
+        Ok(ReturnsView::transform_by_records(records))
+    }
+}
+
+impl Default for BetaView {
+    fn default() -> BetaView {
+        BetaView {
+            date: chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
+            close: 0.0,
+            beta: 0.0,
+        }
+    }
+}
+
+impl BetaView {
+    /// Rolling beta of `records` against `benchmark_records` over the last `period` days both
+    /// traded. Days the benchmark has no record for are skipped, and a view is only emitted once
+    /// the window is full.
+    pub fn transform_by_benchmark(
+        records: &[schema::RawData],
+        benchmark_records: &[schema::RawData],
+        period: usize,
+    ) -> Result<Vec<BetaView>, Error> {
+        if period < 2 {
+            return Err(Error::Ta(ta::errors::TaError::InvalidParameter));
+        }
+
+        let benchmark_returns: HashMap<NaiveDate, f64> =
+            ReturnsView::transform_by_records(benchmark_records)
+                .iter()
+                .map(|view| (view.date, view.simple_return))
+                .collect();
+        let mut window: VecDeque<(f64, f64)> = VecDeque::new();
+        let mut views = Vec::new();
+
+        for returns in ReturnsView::transform_by_records(records) {
+            let benchmark_return = match benchmark_returns.get(&returns.date) {
+                Some(benchmark_return) => *benchmark_return,
+                None => continue,
+            };
+
+            window.push_back((returns.simple_return, benchmark_return));
+            if window.len() > period {
+                window.pop_front();
+            }
+            if window.len() < period {
+                continue;
+            }
+
+            let mean = window.iter().map(|(value, _)| value).sum::<f64>() / period as f64;
+            let benchmark_mean = window.iter().map(|(_, value)| value).sum::<f64>() / period as f64;
+            let mut covariance = 0.0;
+            let mut benchmark_variance = 0.0;
+
+            for (value, benchmark_value) in &window {
+                covariance += (value - mean) * (benchmark_value - benchmark_mean);
+                benchmark_variance +=
+                    (benchmark_value - benchmark_mean) * (benchmark_value - benchmark_mean);
+            }
+
+            views.push(BetaView {
+                date: returns.date,
+                close: returns.close,
+                beta: if benchmark_variance == 0.0 {
+                    0.0
+                } else {
+                    covariance / benchmark_variance
+                },
+            });
+        }
+
+        Ok(views)
+    }
+}
+
+impl std::str::FromStr for Views {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "none" => Ok(Views::None),
+            "bollinger" => Ok(Views::BollingerBand),
+            "atr" => Ok(Views::Atr),
+            "ewma" => Ok(Views::EwmaVolatility),
+            "returns" => Ok(Views::Returns),
+            "beta" => Ok(Views::Beta),
+            "rsi" => Ok(Views::Rsi),
+            "macd" => Ok(Views::Macd),
+            "keltner" => Ok(Views::Keltner),
+            _ => Err(format!("unknown view: {}", name)),
+        }
+    }
+}
+
+impl Default for RsiView {
+    fn default() -> RsiView {
+        RsiView {
+            date: chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
+            close: 0.0,
+            rsi: 0.0,
+        }
+    }
+}
+
+impl RsiView {
+    pub fn transform_by_period(
+        records: &[schema::RawData],
+        period: usize,
+    ) -> Result<Vec<RsiView>, Error> {
+        let mut views = Vec::new();
+        let mut rsi = RelativeStrengthIndex::new(period)?;
+
+        for (idx, record) in records.iter().enumerate() {
+            let view = RsiView {
+                date: record.date,
+                close: record.close,
+                rsi: rsi.next(record.close),
+            };
+
+            if idx >= period {
+                views.push(view);
+            }
+        }
+
+        Ok(views)
+    }
+}
+
+impl Transform for RsiView {
+    type View = RsiView;
+
+    fn transform(records: &Vec<schema::RawData>) -> Result<Vec<Self::View>, Error> {
+        RsiView::transform_by_period(records, RSI_PERIOD)
+    }
+}
+
+impl Default for MacdView {
+    fn default() -> MacdView {
+        MacdView {
+            date: chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
+            close: 0.0,
+            macd: 0.0,
+            signal: 0.0,
+            histogram: 0.0,
+        }
+    }
+}
+
+impl MacdView {
+    pub fn transform_by_periods(
+        records: &[schema::RawData],
+        fast_period: usize,
+        slow_period: usize,
+        signal_period: usize,
+    ) -> Result<Vec<MacdView>, Error> {
+        let mut views = Vec::new();
+        let mut macd =
+            MovingAverageConvergenceDivergence::new(fast_period, slow_period, signal_period)?;
+
+        for (idx, record) in records.iter().enumerate() {
+            let output = macd.next(record.close);
+            let view = MacdView {
+                date: record.date,
+                close: record.close,
+                macd: output.macd,
+                signal: output.signal,
+                histogram: output.histogram,
+            };
+
+            if idx + 1 >= slow_period {
+                views.push(view);
+            }
+        }
+
+        Ok(views)
+    }
+}
+
+impl Transform for MacdView {
+    type View = MacdView;
+
+    fn transform(records: &Vec<schema::RawData>) -> Result<Vec<Self::View>, Error> {
+        MacdView::transform_by_periods(
+            records,
+            MACD_FAST_PERIOD,
+            MACD_SLOW_PERIOD,
+            MACD_SIGNAL_PERIOD,
+        )
+    }
+}
+
+impl Default for KeltnerView {
+    fn default() -> KeltnerView {
+        KeltnerView {
+            date: chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
+            close: 0.0,
+            ema: 0.0,
+            atr: 0.0,
+            upper: 0.0,
+            lower: 0.0,
+        }
+    }
+}
+
+impl KeltnerView {
+    /// Channel `multiplier` ATRs around the EMA of the close, both over `period` days.
+    pub fn transform_by_params(
+        records: &[schema::RawData],
+        period: usize,
+        multiplier: f64,
+    ) -> Result<Vec<KeltnerView>, Error> {
+        let mut views = Vec::new();
+        let mut ema = ExponentialMovingAverage::new(period)?;
+        let mut atr = AverageTrueRange::new(period)?;
+
+        for (idx, record) in records.iter().enumerate() {
+            let mut view = KeltnerView {
+                date: record.date,
+                close: record.close,
+                ema: ema.next(record.close),
+                atr: atr.next(record),
+                ..Default::default()
+            };
+
+            view.upper = view.ema + multiplier * view.atr;
+            view.lower = view.ema - multiplier * view.atr;
+            if idx + 1 >= period {
+                views.push(view);
+            }
+        }
+
+        Ok(views)
+    }
+}
+
+impl Transform for KeltnerView {
+    type View = KeltnerView;
+
+    fn transform(records: &Vec<schema::RawData>) -> Result<Vec<Self::View>, Error> {
+        KeltnerView::transform_by_params(records, KELTNER_PERIOD, KELTNER_MULTIPLIER)
+    }
+}
+
+impl Default for GapView {
+    fn default() -> GapView {
+        GapView {
+            date: chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
+            open: 0.0,
+            high: 0.0,
+            low: 0.0,
+            close: 0.0,
+            prev_close: 0.0,
+            gap: 0.0,
+            volume: 0,
+            volume_ratio: 0.0,
+        }
+    }
+}
+
+impl GapView {
+    /// Gaps of the days after the first `volume_period`, whose volumes are averaged for the
+    /// volume ratio of the next day.
+    pub fn transform_by_period(
+        records: &[schema::RawData],
+        volume_period: usize,
+    ) -> Result<Vec<GapView>, Error> {
+        let mut views = Vec::new();
+        let mut volume_sma = SimpleMovingAverage::new(volume_period)?;
+        let mut average_volume = 0.0;
+
+        for (idx, record) in records.iter().enumerate() {
+            let prev_close = record.close - record.spread;
+            let view = GapView {
+                date: record.date,
                 open: record.open,
                 high: record.high,
                 low: record.low,
                 close: record.close,
+                prev_close,
+                gap: match prev_close > 0.0 {
+                    true => (record.open / prev_close - 1.0) * 100.0,
+                    false => 0.0,
+                },
+                volume: record.trading_volume,
+                volume_ratio: match average_volume > 0.0 {
+                    true => record.trading_volume as f64 / average_volume,
+                    false => 0.0,
+                },
+            };
+
+            if idx >= volume_period {
+                views.push(view);
+            }
+            average_volume = volume_sma.next(record.trading_volume as f64);
+        }
+
+        Ok(views)
+    }
+
+    /// Whether the price traded back to `prev_close` during the day.
+    pub fn is_filled(&self) -> bool {
+        self.low <= self.prev_close && self.high >= self.prev_close
+    }
+}
+
+impl Transform for GapView {
+    type View = GapView;
+
+    fn transform(records: &Vec<schema::RawData>) -> Result<Vec<Self::View>, Error> {
+        GapView::transform_by_period(records, GAP_VOLUME_PERIOD)
+    }
+}
+
+impl CrossoverView {
+    /// SMAs of the close over `short_period` and `long_period` days, from the day the longer one
+    /// is warmed up.
+    pub fn transform_by_periods(
+        records: &[schema::RawData],
+        short_period: usize,
+        long_period: usize,
+    ) -> Result<Vec<CrossoverView>, Error> {
+        let mut views = Vec::new();
+        let mut short_sma = SimpleMovingAverage::new(short_period)?;
+        let mut long_sma = SimpleMovingAverage::new(long_period)?;
+
+        for (idx, record) in records.iter().enumerate() {
+            let view = CrossoverView {
                 date: record.date,
+                close: record.close,
                 volume: record.trading_volume,
-                ..Default::default()
+                short_sma: short_sma.next(record.close),
+                long_sma: long_sma.next(record.close),
             };
-            view.sma = sma.next((record.high + record.low + record.close) / 3.0);
-            view.sd = sd.next((record.high + record.low + record.close) / 3.0);
 
-            if idx + 1 >= bollinger_band::PERIOD {
+            if idx + 1 >= short_period.max(long_period) {
                 views.push(view);
             }
         }
@@ -84,3 +767,188 @@ impl Transform for BollingerBandView {
         Ok(views)
     }
 }
+
+impl HighView {
+    /// Highs of the `high_period` days before each day, from the day they are all in, along
+    /// with the volume against the `volume_period` days before.
+    pub fn transform_by_periods(
+        records: &[schema::RawData],
+        high_period: usize,
+        volume_period: usize,
+    ) -> Result<Vec<HighView>, Error> {
+        let mut views = Vec::new();
+        let mut rolling_max = rolling::RollingMax::new(high_period)?;
+        let mut volume_sma = SimpleMovingAverage::new(volume_period)?;
+        let mut prior_high = 0.0;
+        let mut average_volume = 0.0;
+
+        for (idx, record) in records.iter().enumerate() {
+            let view = HighView {
+                date: record.date,
+                high: record.high,
+                close: record.close,
+                prior_high,
+                volume: record.trading_volume,
+                volume_ratio: match average_volume > 0.0 {
+                    true => record.trading_volume as f64 / average_volume,
+                    false => 0.0,
+                },
+            };
+
+            if idx >= high_period.max(volume_period) {
+                views.push(view);
+            }
+            prior_high = rolling_max.next(record.high);
+            average_volume = volume_sma.next(record.trading_volume as f64);
+        }
+
+        Ok(views)
+    }
+}
+
+impl SmaView {
+    /// SMA of the close over `period` days, from the day it is warmed up.
+    pub fn transform_by_period(
+        records: &[schema::RawData],
+        period: usize,
+    ) -> Result<Vec<SmaView>, Error> {
+        let mut views = Vec::new();
+        let mut sma = SimpleMovingAverage::new(period)?;
+
+        for (idx, record) in records.iter().enumerate() {
+            let view = SmaView {
+                date: record.date,
+                close: record.close,
+                sma: sma.next(record.close),
+            };
+
+            if idx + 1 >= period {
+                views.push(view);
+            }
+        }
+
+        Ok(views)
+    }
+}
+
+#[cfg(test)]
+mod view_test {
+    use super::{
+        AtrView, BandBasis, BetaView, BollingerBandView, EwmaVolatilityView, ReturnsView,
+        EWMA_LAMBDA,
+    };
+    use crate::strategy::schema;
+    use crate::testkit::generator;
+
+    /// Weekday records from 2024-01-01 closing at `closes`.
+    fn get_records(closes: &[f64]) -> Vec<schema::RawData> {
+        let start_date = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let mut records = generator::SeriesBuilder::new(start_date, closes[0])
+            .flat(closes.len())
+            .build();
+
+        for (record, close) in records.iter_mut().zip(closes) {
+            record.close = *close;
+        }
+        records
+    }
+
+    #[test]
+    fn atr_view_check() {
+        let start_date = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        // High and low half a percent either side of a flat close, a true range of 1 every day.
+        let records = generator::SeriesBuilder::new(start_date, 100.0)
+            .flat(20)
+            .build();
+        let views = AtrView::transform_by_period(&records, 14).unwrap();
+
+        assert_eq!(views.len(), 7);
+        assert_eq!(views[0].date, records[13].date);
+        assert!(views.iter().all(|view| (view.atr - 1.0).abs() < 1e-9));
+        assert!(AtrView::transform_by_period(&records, 0).is_err());
+    }
+
+    #[test]
+    fn bollinger_band_view_flat_check() {
+        for close in [100.0, 0.0] {
+            let views = BollingerBandView::transform_by_params(
+                &get_records(&[close; 25]),
+                20,
+                2.0,
+                BandBasis::Sma,
+            )
+            .unwrap();
+
+            assert_eq!(views.len(), 6);
+            assert!(views
+                .iter()
+                .all(|view| view.percent_b == 0.0 && view.bandwidth == 0.0));
+        }
+    }
+
+    #[test]
+    fn ewma_volatility_view_check() {
+        let views = EwmaVolatilityView::transform_by_lambda(
+            &get_records(&[100.0, 110.0, 99.0]),
+            EWMA_LAMBDA,
+        )
+        .unwrap();
+
+        assert_eq!(views.len(), 2);
+        // The first estimate is the first squared return alone, then each day weighs in 6%.
+        assert!((views[0].log_return - 0.095_310_18).abs() < 1e-8);
+        assert!((views[0].volatility - 0.095_310_18).abs() < 1e-8);
+        assert!((views[1].log_return + 0.105_360_52).abs() < 1e-8);
+        assert!((views[1].volatility - 0.095_942_89).abs() < 1e-8);
+        assert!(EwmaVolatilityView::transform_by_lambda(&get_records(&[100.0]), 1.0).is_err());
+    }
+
+    #[test]
+    fn returns_view_check() {
+        let records = get_records(&[100.0, 110.0, 99.0]);
+        let views = ReturnsView::transform_by_records(&records);
+
+        assert_eq!(views.len(), 2);
+        assert_eq!(views[0].date, records[1].date);
+        assert!((views[0].simple_return - 0.1).abs() < 1e-9);
+        assert!((views[0].log_return - 0.095_310_18).abs() < 1e-8);
+        assert!((views[0].cumulative_return - 0.1).abs() < 1e-9);
+        assert!((views[1].simple_return + 0.1).abs() < 1e-9);
+        assert!((views[1].log_return + 0.105_360_52).abs() < 1e-8);
+        assert!((views[1].cumulative_return + 0.01).abs() < 1e-9);
+
+        // No return is taken across a missing close.
+        let views = ReturnsView::transform_by_records(&get_records(&[100.0, 0.0, 120.0]));
+
+        assert!(views
+            .iter()
+            .all(|view| view.simple_return == 0.0 && view.log_return == 0.0));
+        assert!((views[1].cumulative_return - 0.2).abs() < 1e-9);
+        assert!(ReturnsView::transform_by_records(&[]).is_empty());
+    }
+
+    #[test]
+    fn beta_view_check() {
+        let benchmark_closes = [100.0, 101.0, 99.0, 102.0, 100.0];
+        let mut closes = vec![50.0];
+
+        // Twice the daily returns of the benchmark.
+        for window in benchmark_closes.windows(2) {
+            closes.push(closes.last().unwrap() * (1.0 + 2.0 * (window[1] / window[0] - 1.0)));
+        }
+
+        let records = get_records(&closes);
+        let views =
+            BetaView::transform_by_benchmark(&records, &get_records(&benchmark_closes), 3).unwrap();
+
+        assert_eq!(views.len(), 2);
+        assert_eq!(views[0].date, records[3].date);
+        assert!(views.iter().all(|view| (view.beta - 2.0).abs() < 1e-9));
+
+        let flat_views =
+            BetaView::transform_by_benchmark(&records, &get_records(&[100.0; 5]), 3).unwrap();
+
+        assert!(flat_views.iter().all(|view| view.beta == 0.0));
+        assert!(BetaView::transform_by_benchmark(&records, &records, 1).is_err());
+    }
+}
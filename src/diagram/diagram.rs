@@ -0,0 +1,487 @@
+use plotly::common::{Direction, Line, Mode};
+use plotly::layout::themes::BuiltinTheme;
+use serde::{Deserialize, Serialize};
+
+use crate::dataview::view::{self, Transform};
+use crate::strategy::{bollinger_band, schema};
+
+#[derive(Debug)]
+pub enum Error {
+    Dataview(view::Error),
+    UnsupportedView,
+}
+
+impl From<view::Error> for Error {
+    fn from(err: view::Error) -> Error {
+        Error::Dataview(err)
+    }
+}
+
+/// Where a rendered diagram goes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Output {
+    /// Writes a standalone HTML file to the path.
+    Html(String),
+    /// Opens the diagram in a browser, which needs a desktop session.
+    Show,
+}
+
+const CLICK_SCRIPT: &str = r#"<script>
+window.addEventListener('load', function() {
+    var links = [{links}];
+    document.querySelectorAll('.js-plotly-plot').forEach(function(div) {
+        div.on('plotly_click', function(data) {
+            var point = data.points[0];
+            if (point.curveNumber === {trace_index} && links[point.pointIndex]) {
+                window.open(links[point.pointIndex], '_blank');
+            }
+        });
+    });
+});
+</script>
+"#;
+
+pub const DEFAULT_IMAGE_WIDTH: usize = 1200;
+pub const DEFAULT_IMAGE_HEIGHT: usize = 800;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ImageFormat {
+    Png,
+    Svg,
+}
+
+impl ImageFormat {
+    pub fn get_extension(&self) -> &str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Svg => "svg",
+        }
+    }
+}
+
+/// Renders `plot` to `output`. HTML files get a static image written next to them, with the
+/// extension swapped, when `style` asks for one.
+pub fn render(plot: &plotly::Plot, style: &DiagramStyle, output: &Output) {
+    match output {
+        Output::Html(path) => {
+            plot.write_html(path);
+            if let Some(image_format) = style.image_format {
+                let image_path =
+                    std::path::Path::new(path).with_extension(image_format.get_extension());
+
+                write_image(plot, style, &image_path, image_format);
+            }
+        }
+        Output::Show => plot.show(),
+    }
+}
+
+/// Like `render`, but clicking point `n` of trace `trace_index` in the HTML output opens
+/// `links[n]`.
+pub fn render_with_links(
+    plot: &plotly::Plot,
+    style: &DiagramStyle,
+    output: &Output,
+    trace_index: usize,
+    links: &[String],
+) {
+    let path = match output {
+        Output::Html(path) => path,
+        Output::Show => return render(plot, style, output),
+    };
+    let links: Vec<String> = links
+        .iter()
+        .map(|link| format!("\"{}\"", link.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect();
+    let script = CLICK_SCRIPT
+        .replace("{links}", &links.join(", "))
+        .replace("{trace_index}", &trace_index.to_string());
+    let html = plot.to_html().replacen("</body>", &(script + "</body>"), 1);
+
+    std::fs::write(path, html).expect("Failed to write html");
+    if let Some(image_format) = style.image_format {
+        let image_path = std::path::Path::new(path).with_extension(image_format.get_extension());
+
+        write_image(plot, style, &image_path, image_format);
+    }
+}
+
+#[cfg(feature = "image")]
+fn write_image(
+    plot: &plotly::Plot,
+    style: &DiagramStyle,
+    path: &std::path::Path,
+    image_format: ImageFormat,
+) {
+    let format = match image_format {
+        ImageFormat::Png => plotly::ImageFormat::PNG,
+        ImageFormat::Svg => plotly::ImageFormat::SVG,
+    };
+
+    plot.write_image(
+        path,
+        format,
+        style.width.unwrap_or(DEFAULT_IMAGE_WIDTH),
+        style.height.unwrap_or(DEFAULT_IMAGE_HEIGHT),
+        1.0,
+    );
+}
+
+#[cfg(not(feature = "image"))]
+fn write_image(
+    _plot: &plotly::Plot,
+    _style: &DiagramStyle,
+    path: &std::path::Path,
+    _image_format: ImageFormat,
+) {
+    eprintln!(
+        "Skip writing {}: static image export requires the `image` feature",
+        path.display()
+    );
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Theme {
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiagramStyle {
+    pub theme: Theme,
+    /// Colors accept anything plotly understands, e.g. `red` or `#26a69a`.
+    pub increasing_color: String,
+    pub decreasing_color: String,
+    /// Fill of the rectangles marking holding periods.
+    pub hold_color: String,
+    pub hold_opacity: f64,
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub range_slider: bool,
+    /// Also writes a static image next to every HTML diagram.
+    pub image_format: Option<ImageFormat>,
+}
+
+impl std::default::Default for DiagramStyle {
+    fn default() -> Self {
+        DiagramStyle {
+            theme: Theme::Light,
+            increasing_color: "#26a69a".to_owned(),
+            decreasing_color: "#ef5350".to_owned(),
+            hold_color: "burlywood".to_owned(),
+            hold_opacity: 0.5,
+            width: None,
+            height: None,
+            range_slider: true,
+            image_format: None,
+        }
+    }
+}
+
+impl DiagramStyle {
+    pub fn get_layout(&self) -> plotly::Layout {
+        let template = match self.theme {
+            Theme::Light => BuiltinTheme::PlotlyWhite.build(),
+            Theme::Dark => BuiltinTheme::PlotlyDark.build(),
+        };
+        let mut layout = plotly::Layout::new().template(template).x_axis(
+            plotly::layout::Axis::new()
+                .range_slider(plotly::layout::RangeSlider::new().visible(self.range_slider)),
+        );
+
+        if let Some(width) = self.width {
+            layout = layout.width(width);
+        }
+        if let Some(height) = self.height {
+            layout = layout.height(height);
+        }
+        layout
+    }
+
+    pub fn get_candlestick(
+        &self,
+        name: &str,
+        date_series: Vec<String>,
+        open_series: Vec<f64>,
+        high_series: Vec<f64>,
+        low_series: Vec<f64>,
+        close_series: Vec<f64>,
+    ) -> Box<plotly::Candlestick<String, f64>> {
+        Box::new(
+            plotly::Candlestick::new(
+                date_series,
+                open_series,
+                high_series,
+                low_series,
+                close_series,
+            )
+            .name(name)
+            .increasing(Direction::Increasing {
+                line: Line::new().color(self.increasing_color.to_owned()),
+            })
+            .decreasing(Direction::Decreasing {
+                line: Line::new().color(self.decreasing_color.to_owned()),
+            }),
+        )
+    }
+
+    /// Rectangle spanning the full height of the plot between two dates.
+    pub fn get_hold_shape(
+        &self,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> plotly::layout::Shape {
+        plotly::layout::Shape::new()
+            .x_ref("x")
+            .y_ref("paper")
+            .shape_type(plotly::layout::ShapeType::Rect)
+            .x0(start_date.to_string())
+            .y0(0)
+            .x1(end_date.to_string())
+            .y1(1)
+            .fill_color(self.hold_color.to_owned())
+            .opacity(self.hold_opacity)
+            .layer(plotly::layout::ShapeLayer::Below)
+            .line(plotly::layout::ShapeLine::new().width(0.))
+    }
+}
+
+fn get_date_series<T, F: Fn(&T) -> chrono::NaiveDate>(views: &[T], get_date: F) -> Vec<String> {
+    views
+        .iter()
+        .map(|view| get_date(view).format("%Y-%m-%d").to_string())
+        .collect()
+}
+
+fn get_line<T, F: Fn(&T) -> f64>(
+    name: &str,
+    views: &[T],
+    dates: &[String],
+    get_value: F,
+) -> Box<plotly::Scatter<String, f64>> {
+    plotly::Scatter::new(dates.to_vec(), views.iter().map(get_value).collect())
+        .mode(Mode::Lines)
+        .name(name)
+}
+
+/// Draws the candlesticks of `records` from `start_date` on, overlaid with `view`. Bands share
+/// the price axis while oscillators go on a secondary axis. `records` should start early
+/// enough before `start_date` for the indicator to warm up.
+pub fn draw_view_diagram(
+    stock_id: &str,
+    view: &view::Views,
+    records: &Vec<schema::RawData>,
+    start_date: Option<chrono::NaiveDate>,
+    style: &DiagramStyle,
+    output: &Output,
+) -> Result<(), Error> {
+    let start_date = start_date.unwrap_or(chrono::NaiveDate::MIN);
+    let start_index = records
+        .iter()
+        .position(|record| record.date >= start_date)
+        .unwrap_or(records.len());
+    let price_records = &records[start_index..];
+    let mut plot = plotly::Plot::new();
+    let mut layout = style.get_layout();
+    let mut traces: Vec<Box<plotly::Scatter<String, f64>>> = Vec::new();
+    let mut secondary_axis = false;
+
+    match view {
+        view::Views::None => {}
+        view::Views::BollingerBand => {
+            return draw_bollinger_band_diagram(records, start_index, style, output);
+        }
+        view::Views::Atr => {
+            let views = view::AtrView::transform(records)?;
+            let views: Vec<&view::AtrView> = views
+                .iter()
+                .filter(|view| view.date >= start_date)
+                .collect();
+            let dates = get_date_series(&views, |view| view.date);
+
+            traces.push(get_line("ATR", &views, &dates, |view| view.atr));
+            secondary_axis = true;
+        }
+        view::Views::EwmaVolatility => {
+            let views = view::EwmaVolatilityView::transform(records)?;
+            let views: Vec<&view::EwmaVolatilityView> = views
+                .iter()
+                .filter(|view| view.date >= start_date)
+                .collect();
+            let dates = get_date_series(&views, |view| view.date);
+
+            traces.push(get_line("EWMA volatility", &views, &dates, |view| {
+                view.volatility
+            }));
+            secondary_axis = true;
+        }
+        view::Views::Returns => {
+            let views = view::ReturnsView::transform_by_records(price_records);
+            let dates = get_date_series(&views, |view| view.date);
+
+            traces.push(get_line("Cumulative return", &views, &dates, |view| {
+                view.cumulative_return
+            }));
+            secondary_axis = true;
+        }
+        view::Views::Rsi => {
+            let views = view::RsiView::transform(records)?;
+            let views: Vec<&view::RsiView> = views
+                .iter()
+                .filter(|view| view.date >= start_date)
+                .collect();
+            let dates = get_date_series(&views, |view| view.date);
+
+            traces.push(get_line("RSI", &views, &dates, |view| view.rsi));
+            secondary_axis = true;
+        }
+        view::Views::Macd => {
+            let views = view::MacdView::transform(records)?;
+            let views: Vec<&view::MacdView> = views
+                .iter()
+                .filter(|view| view.date >= start_date)
+                .collect();
+            let dates = get_date_series(&views, |view| view.date);
+
+            traces.push(get_line("MACD", &views, &dates, |view| view.macd));
+            traces.push(get_line("Signal", &views, &dates, |view| view.signal));
+            secondary_axis = true;
+        }
+        view::Views::Keltner => {
+            let views: Vec<view::KeltnerView> = view::KeltnerView::transform(records)?
+                .into_iter()
+                .filter(|view| view.date >= start_date)
+                .collect();
+            let dates = get_date_series(&views, |view| view.date);
+
+            traces.extend(get_keltner_lines(&views, &dates));
+        }
+        view::Views::Beta => return Err(Error::UnsupportedView),
+    }
+
+    plot.add_trace(style.get_candlestick(
+        stock_id,
+        get_date_series(price_records, |record| record.date),
+        price_records.iter().map(|record| record.open).collect(),
+        price_records.iter().map(|record| record.high).collect(),
+        price_records.iter().map(|record| record.low).collect(),
+        price_records.iter().map(|record| record.close).collect(),
+    ));
+    for trace in traces {
+        if secondary_axis {
+            plot.add_trace(trace.y_axis("y2"));
+        } else {
+            plot.add_trace(trace);
+        }
+    }
+    if secondary_axis {
+        layout = layout.y_axis2(
+            plotly::layout::Axis::new()
+                .overlaying("y")
+                .side(plotly::layout::AxisSide::Right),
+        );
+    }
+    plot.set_layout(layout);
+    render(&plot, style, output);
+
+    Ok(())
+}
+
+fn draw_bollinger_band_diagram(
+    records: &Vec<schema::RawData>,
+    start_index: usize,
+    style: &DiagramStyle,
+    output: &Output,
+) -> Result<(), Error> {
+    let views = view::BollingerBandView::transform(records)?;
+    let start_date = match records.get(start_index) {
+        Some(record) => record.date,
+        None => chrono::NaiveDate::MAX,
+    };
+    let views: Vec<view::BollingerBandView> = views
+        .into_iter()
+        .filter(|view| view.date >= start_date)
+        .collect();
+    let plot = render_bollinger(
+        &views,
+        bollinger_band::PERIOD,
+        bollinger_band::BAND_SIZE,
+        style,
+    );
+
+    render(&plot, style, output);
+    Ok(())
+}
+
+fn get_keltner_lines(
+    views: &[view::KeltnerView],
+    dates: &[String],
+) -> [Box<plotly::Scatter<String, f64>>; 3] {
+    [
+        get_line("Keltner upper", views, dates, |view| view.upper),
+        get_line("Keltner EMA", views, dates, |view| view.ema),
+        get_line("Keltner lower", views, dates, |view| view.lower),
+    ]
+}
+
+/// Builds the Bollinger band chart the way the strategy reads it: candles, the `period` SMA
+/// and bands at 1sd and `band_size` sd on both sides.
+pub fn render_bollinger(
+    views: &[view::BollingerBandView],
+    period: usize,
+    band_size: f64,
+    style: &DiagramStyle,
+) -> plotly::Plot {
+    let dates = get_date_series(views, |view| view.date);
+    let mut plot = plotly::Plot::new();
+
+    plot.add_trace(style.get_candlestick(
+        "Candlestick",
+        dates.clone(),
+        views.iter().map(|view| view.open).collect(),
+        views.iter().map(|view| view.high).collect(),
+        views.iter().map(|view| view.low).collect(),
+        views.iter().map(|view| view.close).collect(),
+    ));
+    plot.add_trace(get_line(
+        &format!("{} Period SMA", period),
+        views,
+        &dates,
+        |view| view.sma,
+    ));
+    for (name, sign) in [("Upper", 1.0), ("Lower", -1.0)] {
+        plot.add_trace(get_line(
+            &format!("{} Band ({}sd)", name, band_size),
+            views,
+            &dates,
+            |view| view.sma + sign * band_size * view.sd,
+        ));
+        plot.add_trace(get_line(
+            &format!("{} Band (1sd)", name),
+            views,
+            &dates,
+            |view| view.sma + sign * view.sd,
+        ));
+    }
+    plot.set_layout(style.get_layout());
+    plot
+}
+
+/// Bollinger band chart of `bollinger_band_views` with the Keltner channel of `keltner_views`
+/// over it, to see where the bands squeeze inside the channel.
+pub fn render_squeeze(
+    bollinger_band_views: &[view::BollingerBandView],
+    keltner_views: &[view::KeltnerView],
+    period: usize,
+    band_size: f64,
+    style: &DiagramStyle,
+) -> plotly::Plot {
+    let mut plot = render_bollinger(bollinger_band_views, period, band_size, style);
+    let dates = get_date_series(keltner_views, |view| view.date);
+
+    for trace in get_keltner_lines(keltner_views, &dates) {
+        plot.add_trace(trace);
+    }
+    plot
+}
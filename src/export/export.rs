@@ -1,6 +1,30 @@
+use std::io::Write;
+
 pub fn to_yaml<T: serde::Serialize>(file_path: &str, views: &T) {
     let value = serde_yaml::to_string(views).expect("Failed to serialize data to string");
 
     std::fs::write(file_path, value).expect("Failed to write yaml");
 }
 
+/// Writes items one at a time as a YAML sequence, so long series need not be kept in memory.
+pub struct YamlStream {
+    writer: std::io::BufWriter<std::fs::File>,
+}
+
+impl YamlStream {
+    pub fn new(file_path: &str) -> Self {
+        let file = std::fs::File::create(file_path).expect("Failed to create yaml");
+
+        YamlStream {
+            writer: std::io::BufWriter::new(file),
+        }
+    }
+
+    pub fn push<T: serde::Serialize>(&mut self, item: &T) {
+        let value = serde_yaml::to_string(&[item]).expect("Failed to serialize data to string");
+
+        self.writer
+            .write_all(value.as_bytes())
+            .expect("Failed to write yaml");
+    }
+}
@@ -0,0 +1,194 @@
+//! C-compatible API for embedding the strategy scoring in other trading systems. Dates are
+//! passed as `YYYYMMDD` integers and a series as parallel arrays of `len` elements, oldest
+//! first. The matching header is `include/veronica.h`.
+
+use crate::strategy::schema;
+use crate::strategy::strategy::{self, StrategyAPI};
+
+pub const FFI_OK: i32 = 0;
+/// A pointer is null, a date cannot be read or the strategy id is unknown.
+pub const FFI_INVALID_ARGUMENT: i32 = -1;
+/// The strategy failed to score the series.
+pub const FFI_STRATEGY_ERROR: i32 = -2;
+
+/// Stock id the series is stored under.
+const SERIES_ID: &str = "series";
+
+#[repr(C)]
+pub struct FfiSeries {
+    pub dates: *const i32,
+    pub open: *const f64,
+    pub high: *const f64,
+    pub low: *const f64,
+    pub close: *const f64,
+    pub volume: *const u64,
+    pub len: usize,
+}
+
+#[repr(C)]
+pub struct FfiScoreParams {
+    /// Index of the strategy in `Strategies::all()`; 0 is the Bollinger band strategy.
+    pub strategy: u32,
+    pub assess_date: i32,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FfiScore {
+    /// Above zero is a buy signal.
+    pub point: i64,
+    pub trading_volume: u64,
+}
+
+fn get_date(date: i32) -> Option<chrono::NaiveDate> {
+    chrono::NaiveDate::from_ymd_opt(date / 10000, (date / 100 % 100) as u32, (date % 100) as u32)
+}
+
+/// # Safety
+///
+/// Every array of `series` must be valid for `series.len` reads.
+unsafe fn get_records(series: &FfiSeries) -> Option<Vec<schema::RawData>> {
+    let arrays = [series.open, series.high, series.low, series.close];
+
+    if series.len == 0 {
+        return Some(Vec::new());
+    }
+    if series.dates.is_null()
+        || series.volume.is_null()
+        || arrays.iter().any(|array| array.is_null())
+    {
+        return None;
+    }
+
+    let dates = std::slice::from_raw_parts(series.dates, series.len);
+    let open = std::slice::from_raw_parts(series.open, series.len);
+    let high = std::slice::from_raw_parts(series.high, series.len);
+    let low = std::slice::from_raw_parts(series.low, series.len);
+    let close = std::slice::from_raw_parts(series.close, series.len);
+    let volume = std::slice::from_raw_parts(series.volume, series.len);
+    let mut records = Vec::with_capacity(series.len);
+
+    for index in 0..series.len {
+        records.push(schema::RawData {
+            open: open[index],
+            high: high[index],
+            low: low[index],
+            close: close[index],
+            spread: if index == 0 {
+                0.0
+            } else {
+                close[index] - close[index - 1]
+            },
+            date: get_date(dates[index])?,
+            trading_volume: volume[index],
+            trading_money: (close[index] * volume[index] as f64) as u64,
+        });
+    }
+    Some(records)
+}
+
+/// Scores the series on `params.assess_date` and writes the result to `score`. Returns
+/// `FFI_OK` or one of the error codes, leaving `score` untouched on error.
+///
+/// # Safety
+///
+/// `series`, `params` and `score` must be valid pointers, and every array of `series` valid for
+/// `series.len` reads.
+#[no_mangle]
+pub unsafe extern "C" fn score_stock(
+    series: *const FfiSeries,
+    params: *const FfiScoreParams,
+    score: *mut FfiScore,
+) -> i32 {
+    if series.is_null() || params.is_null() || score.is_null() {
+        return FFI_INVALID_ARGUMENT;
+    }
+
+    let params = &*params;
+    let records = match get_records(&*series) {
+        Some(records) => records,
+        None => return FFI_INVALID_ARGUMENT,
+    };
+    let (strategies, assess_date) = match (
+        strategy::Strategies::all().get(params.strategy as usize),
+        get_date(params.assess_date),
+    ) {
+        (Some(strategies), Some(assess_date)) => (strategies.clone(), assess_date),
+        _ => return FFI_INVALID_ARGUMENT,
+    };
+    let result = strategy::StrategyFactory::get_by_records(strategies, SERIES_ID, records)
+        .and_then(|strategy| strategy.analyze(SERIES_ID, assess_date));
+
+    match result {
+        Ok(result) => {
+            *score = FfiScore {
+                point: result.point,
+                trading_volume: result.trading_volume,
+            };
+            FFI_OK
+        }
+        Err(_) => FFI_STRATEGY_ERROR,
+    }
+}
+
+#[cfg(test)]
+mod ffi_test {
+    use std::rc::Rc;
+
+    use crate::storage::memory;
+    use crate::strategy::strategy::{self, StrategyAPI};
+    use crate::testkit::generator;
+
+    use super::{score_stock, FfiScore, FfiScoreParams, FfiSeries, FFI_INVALID_ARGUMENT, FFI_OK};
+
+    #[test]
+    fn score_stock_check() {
+        let backend_op = Rc::new(memory::MemoryBackend::new());
+        let start_date = chrono::NaiveDate::from_ymd_opt(2022, 1, 3).unwrap();
+        let records = generator::SeriesBuilder::new(start_date, 100.0)
+            .trend(80, 1.0)
+            .insert(backend_op.as_ref(), "0050")
+            .unwrap();
+        let assess_date = records.last().unwrap().date;
+        let expected = strategy::StrategyFactory::get(
+            strategy::Strategies::BollingerBand,
+            &strategy::StrategyParams::default(),
+            backend_op,
+        )
+        .analyze("0050", assess_date)
+        .unwrap();
+        let dates: Vec<i32> = records
+            .iter()
+            .map(|record| record.date.format("%Y%m%d").to_string().parse().unwrap())
+            .collect();
+        let open: Vec<f64> = records.iter().map(|record| record.open).collect();
+        let high: Vec<f64> = records.iter().map(|record| record.high).collect();
+        let low: Vec<f64> = records.iter().map(|record| record.low).collect();
+        let close: Vec<f64> = records.iter().map(|record| record.close).collect();
+        let volume: Vec<u64> = records.iter().map(|record| record.trading_volume).collect();
+        let series = FfiSeries {
+            dates: dates.as_ptr(),
+            open: open.as_ptr(),
+            high: high.as_ptr(),
+            low: low.as_ptr(),
+            close: close.as_ptr(),
+            volume: volume.as_ptr(),
+            len: records.len(),
+        };
+        let mut params = FfiScoreParams {
+            strategy: 0,
+            assess_date: *dates.last().unwrap(),
+        };
+        let mut score = FfiScore::default();
+
+        assert_eq!(unsafe { score_stock(&series, &params, &mut score) }, FFI_OK);
+        assert!(score.point > 0);
+        assert_eq!(score.point, expected.point);
+
+        params.assess_date = 20221350;
+        assert_eq!(
+            unsafe { score_stock(&series, &params, &mut score) },
+            FFI_INVALID_ARGUMENT
+        );
+    }
+}
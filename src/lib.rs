@@ -1,8 +1,30 @@
+#[cfg(feature = "native")]
+pub mod analytics;
+#[cfg(feature = "native")]
 pub mod config;
+#[cfg(feature = "native")]
 pub mod core;
+#[cfg(feature = "native")]
 pub mod crawler;
+pub mod crosssection;
 pub mod dataview;
+pub mod diagram;
 pub mod export;
+pub mod ffi;
+#[cfg(feature = "native")]
+pub mod notifier;
+#[cfg(feature = "native")]
+pub mod optimizer;
+// The pyo3 macros expand to `?` conversions clippy flags as useless.
+#[cfg(feature = "python")]
+#[allow(clippy::useless_conversion)]
+pub mod python;
+#[cfg(feature = "native")]
+pub mod report;
+#[cfg(feature = "native")]
+pub mod server;
 pub mod storage;
 pub mod strategy;
-
+pub mod testkit;
+#[cfg(feature = "wasm")]
+pub mod wasm;
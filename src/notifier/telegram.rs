@@ -0,0 +1,125 @@
+use serde::Deserialize;
+
+const TELEGRAM_API_URL: &str = "https://api.telegram.org";
+/// Seconds `getUpdates` waits for a message before returning empty.
+const POLL_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug)]
+pub enum Error {
+    Reqwest(reqwest::Error),
+    Io(std::io::Error),
+    Api(String),
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Error {
+        Error::Reqwest(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Chat {
+    pub id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Message {
+    pub chat: Chat,
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Update {
+    pub update_id: i64,
+    pub message: Option<Message>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Response<T> {
+    ok: bool,
+    description: Option<String>,
+    result: Option<T>,
+}
+
+impl<T> Response<T> {
+    fn into_result(self) -> Result<T, Error> {
+        match (self.ok, self.result) {
+            (true, Some(result)) => Ok(result),
+            _ => Err(Error::Api(self.description.unwrap_or_default())),
+        }
+    }
+}
+
+pub struct Telegram {
+    token: String,
+    client: reqwest::blocking::Client,
+}
+
+impl Telegram {
+    pub fn new(token: &str) -> Result<Self, Error> {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(std::time::Duration::from_secs(POLL_TIMEOUT_SECS * 2))
+            .build()?;
+
+        Ok(Telegram {
+            token: token.to_owned(),
+            client,
+        })
+    }
+
+    fn get_url(&self, method: &str) -> String {
+        format!("{}/bot{}/{}", TELEGRAM_API_URL, self.token, method)
+    }
+
+    /// Long-polls for updates with an id of at least `offset`.
+    pub fn get_updates(&self, offset: i64) -> Result<Vec<Update>, Error> {
+        let resp: Response<Vec<Update>> = self
+            .client
+            .get(self.get_url("getUpdates"))
+            .query(&[
+                ("offset", offset.to_string()),
+                ("timeout", POLL_TIMEOUT_SECS.to_string()),
+            ])
+            .send()?
+            .json()?;
+
+        resp.into_result()
+    }
+
+    pub fn send_message(&self, chat_id: i64, text: &str) -> Result<(), Error> {
+        let resp: Response<serde::de::IgnoredAny> = self
+            .client
+            .post(self.get_url("sendMessage"))
+            .form(&[("chat_id", chat_id.to_string()), ("text", text.to_owned())])
+            .send()?
+            .json()?;
+
+        resp.into_result().map(|_| ())
+    }
+
+    /// Sends `file_path` as a photo when it is a PNG, as a file otherwise.
+    pub fn send_file(&self, chat_id: i64, file_path: &str) -> Result<(), Error> {
+        let (method, field) = if file_path.ends_with(".png") {
+            ("sendPhoto", "photo")
+        } else {
+            ("sendDocument", "document")
+        };
+        let form = reqwest::blocking::multipart::Form::new()
+            .text("chat_id", chat_id.to_string())
+            .file(field, file_path)?;
+        let resp: Response<serde::de::IgnoredAny> = self
+            .client
+            .post(self.get_url(method))
+            .multipart(form)
+            .send()?
+            .json()?;
+
+        resp.into_result().map(|_| ())
+    }
+}
@@ -0,0 +1,193 @@
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::crawler::crawler;
+use crate::optimizer::search::{ParamSet, Rng, Trial};
+use crate::strategy::schema;
+
+pub const CROSS_VALIDATION_FILENAME: &str = "cross_validation.yaml";
+
+/// Crawler whose stock list is restricted to a fold of the universe; prices are still read
+/// through `crawler`.
+pub struct UniverseCrawler {
+    pub crawler: Rc<dyn crawler::Crawler>,
+    pub stock_ids: Vec<String>,
+}
+
+impl crawler::Crawler for UniverseCrawler {
+    fn get_stock_data(&self, args: &crawler::Args) -> Result<Vec<schema::RawData>, crawler::Error> {
+        self.crawler.get_stock_data(args)
+    }
+
+    fn get_stock_list(&self) -> Result<Vec<String>, crawler::Error> {
+        Ok(self.stock_ids.clone())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FoldReport {
+    pub fold: usize,
+    pub train_stocks: usize,
+    pub validation_stocks: usize,
+    /// Best parameters found on the training stocks, with their training fitness.
+    pub best: Trial,
+    /// Fitness of the same parameters on the held-out stocks.
+    pub validation_fitness: f64,
+    /// Training less validation fitness; large gaps mean parameters fitted to particular
+    /// stocks rather than to the market.
+    pub gap: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrossValReport {
+    pub folds: Vec<FoldReport>,
+    pub mean_train_fitness: f64,
+    pub mean_validation_fitness: f64,
+    pub mean_gap: f64,
+    /// Whether the run was interrupted; only the folds completed until then are reported.
+    #[serde(default)]
+    pub cancelled: bool,
+}
+
+/// Universe shuffled by `seed` and dealt into `folds` folds of near equal size.
+pub fn split_universe(stock_ids: &[String], folds: usize, seed: u64) -> Vec<Vec<String>> {
+    let mut rng = Rng::new(seed);
+    let mut stock_ids = stock_ids.to_vec();
+    let folds = folds.clamp(1, stock_ids.len().max(1));
+    let mut split = vec![Vec::new(); folds];
+
+    // Fisher-Yates.
+    for index in (1..stock_ids.len()).rev() {
+        stock_ids.swap(index, rng.below(index + 1));
+    }
+    for (index, stock_id) in stock_ids.into_iter().enumerate() {
+        split[index % folds].push(stock_id);
+    }
+    split
+}
+
+/// Validates a parameter search across the stock universe rather than across time: each fold
+/// in turn is held out, `optimize` searches on the other stocks, and `evaluate` scores the best
+/// parameters on the held-out ones. Either returning `None` cancels the remaining folds.
+pub fn cross_validate(
+    stock_ids: &[String],
+    folds: usize,
+    seed: u64,
+    mut optimize: impl FnMut(&[String]) -> Option<Trial>,
+    mut evaluate: impl FnMut(&[String], &ParamSet) -> Option<f64>,
+) -> CrossValReport {
+    let split = split_universe(stock_ids, folds, seed);
+    let mut fold_reports = Vec::new();
+    let mut cancelled = false;
+
+    for (fold, validation) in split.iter().enumerate() {
+        let train: Vec<String> = split
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != fold)
+            .flat_map(|(_, stock_ids)| stock_ids.iter().cloned())
+            .collect();
+
+        if train.is_empty() || validation.is_empty() {
+            continue;
+        }
+
+        let best = match optimize(&train) {
+            Some(best) => best,
+            None => {
+                cancelled = true;
+                break;
+            }
+        };
+        let validation_fitness = match evaluate(validation, &best.params) {
+            Some(validation_fitness) => validation_fitness,
+            None => {
+                cancelled = true;
+                break;
+            }
+        };
+
+        fold_reports.push(FoldReport {
+            fold,
+            train_stocks: train.len(),
+            validation_stocks: validation.len(),
+            gap: best.fitness - validation_fitness,
+            best,
+            validation_fitness,
+        });
+    }
+
+    let count = fold_reports.len().max(1) as f64;
+    let get_mean = |get: fn(&FoldReport) -> f64| fold_reports.iter().map(get).sum::<f64>() / count;
+
+    CrossValReport {
+        mean_train_fitness: get_mean(|fold_report| fold_report.best.fitness),
+        mean_validation_fitness: get_mean(|fold_report| fold_report.validation_fitness),
+        mean_gap: get_mean(|fold_report| fold_report.gap),
+        folds: fold_reports,
+        cancelled,
+    }
+}
+
+#[cfg(test)]
+mod crossval_test {
+    use std::collections::HashSet;
+
+    use crate::optimizer::search::{ParamSet, Trial};
+
+    use super::{cross_validate, split_universe};
+
+    #[test]
+    fn cross_validate_check() {
+        let stock_ids: Vec<String> = (0..10).map(|index| format!("{}", 1000 + index)).collect();
+        let split = split_universe(&stock_ids, 3, 7);
+
+        assert_eq!(split.len(), 3);
+        assert_eq!(
+            split.iter().map(|fold| fold.len()).collect::<Vec<_>>(),
+            vec![4, 3, 3]
+        );
+        assert_eq!(
+            split.iter().flatten().collect::<HashSet<_>>().len(),
+            stock_ids.len()
+        );
+
+        let get_fitness = |stock_ids: &[String]| {
+            stock_ids
+                .iter()
+                .filter(|stock_id| stock_id.ends_with(['0', '2', '4', '6', '8']))
+                .count() as f64
+                / stock_ids.len() as f64
+        };
+        let report = cross_validate(
+            &stock_ids,
+            3,
+            7,
+            |train| {
+                Some(Trial {
+                    params: ParamSet::new(),
+                    fitness: get_fitness(train),
+                })
+            },
+            |validation, _| Some(get_fitness(validation)),
+        );
+
+        assert!(!report.cancelled);
+        assert_eq!(report.folds.len(), 3);
+        for fold_report in report.folds.iter() {
+            assert_eq!(
+                fold_report.train_stocks + fold_report.validation_stocks,
+                stock_ids.len()
+            );
+            assert_eq!(
+                fold_report.gap,
+                fold_report.best.fitness - fold_report.validation_fitness
+            );
+        }
+        assert!(
+            (report.mean_gap - (report.mean_train_fitness - report.mean_validation_fitness)).abs()
+                < 1e-9
+        );
+    }
+}
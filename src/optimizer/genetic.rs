@@ -0,0 +1,301 @@
+use serde::{Deserialize, Serialize};
+
+use crate::optimizer::search::{self, ParamRange, ParamSet, Rng, Trial};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneticConfig {
+    pub population: usize,
+    pub generations: usize,
+    /// Chance that a child mixes both parents instead of copying the first.
+    pub crossover_rate: f64,
+    /// Chance that each parameter of a child is perturbed.
+    pub mutation_rate: f64,
+    /// Standard deviation of a perturbation, as a share of the parameter range.
+    pub mutation_scale: f64,
+    /// Best trials carried over unchanged to the next generation.
+    pub elite: usize,
+    /// Trials drawn per parent selection; the fittest of them is the parent.
+    pub tournament: usize,
+    /// Generations without improving the best fitness by more than `min_improvement` before
+    /// the search stops.
+    pub patience: usize,
+    pub min_improvement: f64,
+    pub seed: u64,
+}
+
+impl std::default::Default for GeneticConfig {
+    fn default() -> Self {
+        GeneticConfig {
+            population: 20,
+            generations: 30,
+            crossover_rate: 0.8,
+            mutation_rate: 0.2,
+            mutation_scale: 0.1,
+            elite: 2,
+            tournament: 3,
+            patience: 5,
+            min_improvement: 0.01,
+            seed: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationReport {
+    pub generation: usize,
+    pub best_fitness: f64,
+    pub mean_fitness: f64,
+    /// Mean standard deviation of the parameters over the population, as a share of their
+    /// ranges; close to zero once the population has converged.
+    pub diversity: f64,
+    /// Backtests run for this generation, leaving out parameter sets already evaluated.
+    pub evaluations: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneticReport {
+    pub best: Trial,
+    pub generations: Vec<GenerationReport>,
+    /// Whether the search stopped early for lack of improvement.
+    pub converged: bool,
+    /// Whether the search was interrupted; the trials completed until then are kept.
+    #[serde(default)]
+    pub cancelled: bool,
+    /// Every evaluated trial, in evaluation order.
+    pub trials: Vec<Trial>,
+}
+
+fn get_diversity(ranges: &[ParamRange], population: &[Trial]) -> f64 {
+    if ranges.is_empty() || population.is_empty() {
+        return 0.0;
+    }
+
+    let count = population.len() as f64;
+    let total: f64 = ranges
+        .iter()
+        .filter(|range| range.get_width() > 0.0)
+        .map(|range| {
+            let values: Vec<f64> = population
+                .iter()
+                .map(|trial| trial.params[&range.name] / range.get_width())
+                .collect();
+            let mean = values.iter().sum::<f64>() / count;
+
+            (values
+                .iter()
+                .map(|value| (value - mean).powi(2))
+                .sum::<f64>()
+                / count)
+                .sqrt()
+        })
+        .sum();
+
+    total / ranges.len() as f64
+}
+
+fn select<'a>(population: &'a [Trial], config: &GeneticConfig, rng: &mut Rng) -> &'a Trial {
+    (0..config.tournament.max(1))
+        .map(|_| &population[rng.below(population.len())])
+        .max_by(|lhs, rhs| lhs.fitness.total_cmp(&rhs.fitness))
+        .unwrap()
+}
+
+fn breed(
+    ranges: &[ParamRange],
+    lhs: &ParamSet,
+    rhs: &ParamSet,
+    config: &GeneticConfig,
+    rng: &mut Rng,
+) -> ParamSet {
+    let crossover = rng.next_f64() < config.crossover_rate;
+
+    ranges
+        .iter()
+        .map(|range| {
+            let mut value = if crossover {
+                // Blend crossover: anywhere between the parents, and a little beyond.
+                let weight = rng.next_f64() * 1.5 - 0.25;
+
+                lhs[&range.name] + weight * (rhs[&range.name] - lhs[&range.name])
+            } else {
+                lhs[&range.name]
+            };
+
+            if rng.next_f64() < config.mutation_rate {
+                value += rng.next_normal() * config.mutation_scale * range.get_width();
+            }
+            (range.name.clone(), range.get_value(value))
+        })
+        .collect()
+}
+
+/// Evolves a population of parameter sets towards the highest `evaluate` fitness, with
+/// tournament selection, blend crossover, gaussian mutation and elitism. Identical parameter
+/// sets are evaluated once.
+pub fn optimize(
+    ranges: &[ParamRange],
+    config: &GeneticConfig,
+    mut evaluate: impl FnMut(&ParamSet) -> f64,
+) -> GeneticReport {
+    optimize_batch(ranges, config, |batch| {
+        batch.iter().map(|params| Some(evaluate(params))).collect()
+    })
+}
+
+/// [`optimize`] evaluating each generation as one batch, e.g. across a
+/// [`TrialPool`](crate::optimizer::pool::TrialPool). A `None` fitness means the trial was
+/// cancelled, which ends the search.
+pub fn optimize_batch(
+    ranges: &[ParamRange],
+    config: &GeneticConfig,
+    mut evaluate: impl FnMut(&[ParamSet]) -> Vec<Option<f64>>,
+) -> GeneticReport {
+    let mut rng = Rng::new(config.seed);
+    let mut trials: Vec<Trial> = Vec::new();
+    let mut generations: Vec<GenerationReport> = Vec::new();
+    let mut candidates: Vec<ParamSet> = (0..config.population.max(1))
+        .map(|_| search::sample(ranges, &mut rng))
+        .collect();
+    let mut stale_generations = 0;
+    let mut converged = false;
+    let mut cancelled = false;
+
+    for generation in 0..config.generations.max(1) {
+        let evaluated_count = trials.len();
+        let mut batch: Vec<ParamSet> = Vec::new();
+
+        for params in candidates.iter() {
+            if !trials.iter().any(|trial| trial.params == *params) && !batch.contains(params) {
+                batch.push(params.clone());
+            }
+        }
+        let fitnesses = evaluate(&batch);
+
+        for (params, fitness) in batch.into_iter().zip(fitnesses) {
+            match fitness {
+                Some(fitness) => trials.push(Trial { params, fitness }),
+                None => cancelled = true,
+            }
+        }
+        if cancelled {
+            break;
+        }
+
+        let mut population: Vec<Trial> = candidates
+            .iter()
+            .map(|params| {
+                trials
+                    .iter()
+                    .find(|trial| trial.params == *params)
+                    .cloned()
+                    .unwrap()
+            })
+            .collect();
+
+        population.sort_by(|lhs, rhs| rhs.fitness.total_cmp(&lhs.fitness));
+
+        let best_fitness = population[0].fitness;
+        let previous_best = generations.last().map(|report| report.best_fitness);
+
+        generations.push(GenerationReport {
+            generation,
+            best_fitness: previous_best.map_or(best_fitness, |previous| previous.max(best_fitness)),
+            mean_fitness: population.iter().map(|trial| trial.fitness).sum::<f64>()
+                / population.len() as f64,
+            diversity: get_diversity(ranges, &population),
+            evaluations: trials.len() - evaluated_count,
+        });
+        match previous_best {
+            Some(previous) if best_fitness <= previous + config.min_improvement => {
+                stale_generations += 1;
+            }
+            _ => stale_generations = 0,
+        }
+        if stale_generations >= config.patience.max(1) {
+            converged = true;
+            break;
+        }
+
+        candidates = population
+            .iter()
+            .take(config.elite)
+            .map(|trial| trial.params.clone())
+            .collect();
+        while candidates.len() < population.len() {
+            let lhs = select(&population, config, &mut rng);
+            let rhs = select(&population, config, &mut rng);
+
+            candidates.push(breed(ranges, &lhs.params, &rhs.params, config, &mut rng));
+        }
+    }
+
+    GeneticReport {
+        best: search::get_best(&trials),
+        generations,
+        converged,
+        cancelled,
+        trials,
+    }
+}
+
+#[cfg(test)]
+mod genetic_test {
+    use crate::optimizer::search::ParamRange;
+
+    use super::{optimize, optimize_batch, GeneticConfig};
+
+    #[test]
+    fn optimize_check() {
+        let ranges: Vec<ParamRange> = vec![
+            "period:5:60:int".parse().unwrap(),
+            "band:0.5:4".parse().unwrap(),
+        ];
+        let config = GeneticConfig {
+            generations: 100,
+            ..GeneticConfig::default()
+        };
+        let report = optimize(&ranges, &config, |params| {
+            -(params["period"] - 20.0).powi(2) - 10.0 * (params["band"] - 2.0).powi(2)
+        });
+
+        assert!(report.converged);
+        assert!(report.generations.len() < config.generations);
+        assert!((report.best.params["period"] - 20.0).abs() <= 2.0);
+        assert!((report.best.params["band"] - 2.0).abs() < 0.5);
+        assert!(report
+            .generations
+            .windows(2)
+            .all(|pair| pair[1].best_fitness >= pair[0].best_fitness));
+    }
+
+    #[test]
+    fn optimize_batch_check() {
+        let ranges: Vec<ParamRange> = vec!["band:0.5:4".parse().unwrap()];
+        let config = GeneticConfig::default();
+        let mut batches = 0;
+        let report = optimize_batch(&ranges, &config, |batch| {
+            batches += 1;
+            // Interrupted halfway through the third generation.
+            batch
+                .iter()
+                .enumerate()
+                .map(|(index, params)| {
+                    (batches < 3 || index < batch.len() / 2).then(|| -params["band"])
+                })
+                .collect()
+        });
+
+        assert!(report.cancelled);
+        assert!(!report.converged);
+        assert_eq!(report.generations.len(), 2);
+        assert!(report.trials.len() > report.generations[0].evaluations);
+        assert_eq!(
+            report.best.fitness,
+            report
+                .trials
+                .iter()
+                .map(|trial| trial.fitness)
+                .fold(f64::NEG_INFINITY, f64::max)
+        );
+    }
+}
@@ -0,0 +1,6 @@
+pub mod crossval;
+pub mod genetic;
+pub mod pool;
+pub mod pruner;
+pub mod search;
+pub mod tpe;
@@ -0,0 +1,143 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// Runs trials on worker threads. The engine shares its crawler and backend through `Rc`, so
+/// each worker builds its own evaluator with `build` and keeps it for the life of the pool;
+/// only what `build` captures, e.g. a sled handle, is shared.
+pub struct TrialPool<J> {
+    jobs: Option<mpsc::Sender<(usize, J)>>,
+    results: mpsc::Receiver<(usize, Option<f64>)>,
+    workers: Vec<thread::JoinHandle<()>>,
+    cancelled: Arc<AtomicBool>,
+    progress: ProgressBar,
+}
+
+impl<J: Send + 'static> TrialPool<J> {
+    pub fn new<B, E>(threads: usize, cancelled: Arc<AtomicBool>, build: B) -> Self
+    where
+        B: Fn(usize) -> E + Send + Sync + 'static,
+        E: FnMut(J) -> f64,
+    {
+        let (job_sender, job_receiver) = mpsc::channel::<(usize, J)>();
+        let (result_sender, results) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let build = Arc::new(build);
+        let workers = (0..threads.max(1))
+            .map(|worker| {
+                let job_receiver = job_receiver.clone();
+                let result_sender = result_sender.clone();
+                let cancelled = cancelled.clone();
+                let build = build.clone();
+
+                thread::spawn(move || {
+                    let mut evaluate = build(worker);
+
+                    loop {
+                        let job = job_receiver.lock().unwrap().recv();
+                        let (index, job) = match job {
+                            Ok(job) => job,
+                            Err(_) => break,
+                        };
+                        let fitness = match cancelled.load(Ordering::SeqCst) {
+                            true => None,
+                            false => Some(evaluate(job)),
+                        };
+
+                        if result_sender.send((index, fitness)).is_err() {
+                            break;
+                        }
+                    }
+                })
+            })
+            .collect();
+        let progress = ProgressBar::new(0);
+
+        progress.set_style(
+            ProgressStyle::with_template("{elapsed_precise} [{bar:40}] {pos}/{len} trials {msg}")
+                .unwrap()
+                .progress_chars("=> "),
+        );
+        TrialPool {
+            jobs: Some(job_sender),
+            results,
+            workers,
+            cancelled,
+            progress,
+        }
+    }
+
+    /// Fitness of each job, in order. Once cancelled, trials already running still finish, but
+    /// the rest are skipped and come back `None`.
+    pub fn evaluate(&self, jobs: Vec<J>) -> Vec<Option<f64>> {
+        let count = jobs.len();
+        let mut fitnesses = vec![None; count];
+
+        self.progress.inc_length(count as u64);
+        for job in jobs.into_iter().enumerate() {
+            self.jobs.as_ref().unwrap().send(job).unwrap();
+        }
+        for _ in 0..count {
+            let (index, fitness) = self.results.recv().unwrap();
+
+            fitnesses[index] = fitness;
+            self.progress.inc(1);
+        }
+        if self.is_cancelled() {
+            self.progress.set_message("(cancelled)");
+        }
+        fitnesses
+    }
+
+    /// Prints above the progress bar.
+    pub fn println(&self, message: impl AsRef<str>) {
+        self.progress.println(message);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+impl<J> Drop for TrialPool<J> {
+    fn drop(&mut self) {
+        // Closing the channel stops the workers once the queue is drained.
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+        self.progress.finish();
+    }
+}
+
+#[cfg(test)]
+mod pool_test {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use super::TrialPool;
+
+    #[test]
+    fn evaluate_check() {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let pool = TrialPool::new(4, cancelled.clone(), |worker| {
+            // Per-worker state, as an evaluator owning an `Rc` would have.
+            let offset = std::rc::Rc::new(worker as f64 * 0.0);
+
+            move |value: f64| value * value + *offset
+        });
+
+        assert_eq!(
+            pool.evaluate((0..20).map(|value| value as f64).collect()),
+            (0..20)
+                .map(|value| Some((value * value) as f64))
+                .collect::<Vec<_>>()
+        );
+
+        cancelled.store(true, Ordering::SeqCst);
+        assert!(pool.is_cancelled());
+        assert_eq!(pool.evaluate(vec![1.0, 2.0]), vec![None, None]);
+    }
+}
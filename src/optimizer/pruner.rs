@@ -0,0 +1,177 @@
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::backtesting::{EquityPoint, PortfolioSummary};
+use crate::core::{cashflow, risk};
+
+/// Shares of the date range at which a trial is compared with the best one so far.
+pub const DEFAULT_CHECKPOINTS: [f64; 3] = [0.25, 0.5, 0.75];
+pub const DEFAULT_RETURN_MARGIN: f64 = 10.0;
+pub const DEFAULT_DRAWDOWN_MARGIN: f64 = 10.0;
+
+/// Return and drawdown of a trial as of a checkpoint, in percent.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointMetrics {
+    pub time_weighted_return: f64,
+    pub max_drawdown: f64,
+}
+
+struct BestTrial {
+    fitness: f64,
+    checkpoints: Vec<Option<CheckpointMetrics>>,
+}
+
+/// Aborts backtests partway through the date range once they are clearly behind the fittest
+/// trial completed so far: at each checkpoint, a trial whose return trails the best trial's by
+/// more than `return_margin` points, or whose drawdown exceeds it by more than
+/// `drawdown_margin` points, is stopped. Clones share the best trial, so one pruner serves every
+/// worker of a search.
+#[derive(Clone)]
+pub struct Pruner {
+    pub checkpoint_dates: Vec<chrono::NaiveDate>,
+    pub return_margin: f64,
+    pub drawdown_margin: f64,
+    best: Arc<Mutex<Option<BestTrial>>>,
+}
+
+impl Pruner {
+    pub fn new(start_date: chrono::NaiveDate, end_date: chrono::NaiveDate) -> Self {
+        let days = (end_date - start_date).num_days() as f64;
+
+        Pruner {
+            checkpoint_dates: DEFAULT_CHECKPOINTS
+                .iter()
+                .map(|share| start_date + chrono::Duration::days((days * share).round() as i64))
+                .collect(),
+            return_margin: DEFAULT_RETURN_MARGIN,
+            drawdown_margin: DEFAULT_DRAWDOWN_MARGIN,
+            best: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Index of the checkpoint the last day of `equity_series` is the first to reach.
+    fn get_checkpoint(&self, equity_series: &[EquityPoint]) -> Option<usize> {
+        let date = equity_series.last()?.date;
+        let previous_date = equity_series
+            .len()
+            .checked_sub(2)
+            .map(|index| equity_series[index].date);
+
+        self.checkpoint_dates.iter().position(|checkpoint_date| {
+            date >= *checkpoint_date
+                && previous_date.is_none_or(|previous_date| previous_date < *checkpoint_date)
+        })
+    }
+
+    /// Whether the run summarized by `summary` should stop; called after every simulated day.
+    pub fn should_prune(&self, liquidity: u32, summary: &PortfolioSummary) -> bool {
+        let checkpoint = match self.get_checkpoint(&summary.equity_series) {
+            Some(checkpoint) => checkpoint,
+            None => return false,
+        };
+        let best = self.best.lock().unwrap();
+        let best_metrics = match best.as_ref().and_then(|best| best.checkpoints[checkpoint]) {
+            Some(best_metrics) => best_metrics,
+            None => return false,
+        };
+        let metrics = get_metrics(liquidity, &summary.equity_series);
+
+        metrics.time_weighted_return < best_metrics.time_weighted_return - self.return_margin
+            || metrics.max_drawdown > best_metrics.max_drawdown + self.drawdown_margin
+    }
+
+    /// Records a completed trial, which becomes the one others are compared with if it is the
+    /// fittest so far.
+    pub fn record(&self, fitness: f64, liquidity: u32, summary: &PortfolioSummary) {
+        let mut best = self.best.lock().unwrap();
+
+        if best.as_ref().is_some_and(|best| best.fitness >= fitness) {
+            return;
+        }
+        *best = Some(BestTrial {
+            fitness,
+            checkpoints: self
+                .checkpoint_dates
+                .iter()
+                .map(|checkpoint_date| {
+                    let count = summary
+                        .equity_series
+                        .iter()
+                        .position(|equity_point| equity_point.date >= *checkpoint_date)?;
+
+                    Some(get_metrics(liquidity, &summary.equity_series[..=count]))
+                })
+                .collect(),
+        });
+    }
+}
+
+fn get_metrics(liquidity: u32, equity_series: &[EquityPoint]) -> CheckpointMetrics {
+    let mut peak_equity = liquidity;
+    let mut max_drawdown: f64 = 0.0;
+
+    for equity_point in equity_series {
+        peak_equity =
+            ((peak_equity as i64 + equity_point.cash_flow).max(0) as u32).max(equity_point.equity);
+        max_drawdown = max_drawdown.max(risk::drawdown(peak_equity, equity_point.equity));
+    }
+    CheckpointMetrics {
+        time_weighted_return: cashflow::time_weighted_return(
+            liquidity,
+            &equity_series
+                .iter()
+                .map(|equity_point| (equity_point.equity, equity_point.cash_flow))
+                .collect::<Vec<_>>(),
+        ),
+        max_drawdown,
+    }
+}
+
+#[cfg(test)]
+mod pruner_test {
+    use crate::core::backtesting::{EquityPoint, PortfolioSummary};
+
+    use super::Pruner;
+
+    fn get_summary(start_date: chrono::NaiveDate, equities: &[u32]) -> PortfolioSummary {
+        PortfolioSummary {
+            equity_series: equities
+                .iter()
+                .enumerate()
+                .map(|(index, equity)| EquityPoint {
+                    date: start_date + chrono::Duration::days(index as i64),
+                    equity: *equity,
+                    unhedged_equity: *equity as i64,
+                    cash_flow: 0,
+                    invested: 0,
+                    holding_count: 0,
+                })
+                .collect(),
+            ..PortfolioSummary::default()
+        }
+    }
+
+    #[test]
+    fn should_prune_check() {
+        let start_date = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let pruner = Pruner::new(start_date, start_date + chrono::Duration::days(100));
+        let rising: Vec<u32> = (0..=100).map(|day| 100000 + day * 500).collect();
+        let falling: Vec<u32> = (0..=100).map(|day| 100000 - day * 200).collect();
+
+        // Nothing to compare with before a trial completes.
+        assert!(!pruner.should_prune(100000, &get_summary(start_date, &falling[..=25])));
+
+        pruner.record(50.0, 100000, &get_summary(start_date, &rising));
+
+        // Only checked on the day a checkpoint is reached.
+        assert!(!pruner.should_prune(100000, &get_summary(start_date, &falling[..=24])));
+        assert!(pruner.should_prune(100000, &get_summary(start_date, &falling[..=25])));
+        assert!(!pruner.should_prune(100000, &get_summary(start_date, &falling[..=26])));
+        assert!(!pruner.should_prune(100000, &get_summary(start_date, &rising[..=50])));
+
+        // A less fit trial does not replace the best one.
+        pruner.record(-20.0, 100000, &get_summary(start_date, &falling));
+        assert!(pruner.should_prune(100000, &get_summary(start_date, &falling[..=75])));
+    }
+}
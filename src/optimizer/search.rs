@@ -0,0 +1,260 @@
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::config;
+use crate::core::backtesting;
+use crate::crawler::crawler;
+use crate::optimizer::{crossval, pruner};
+use crate::storage::{backend, run};
+use crate::strategy::{rule, strategy};
+
+pub const OPTIMIZE_REPORT_FILENAME: &str = "optimize.yaml";
+pub const OPTIMIZE_RUN_TAG: &str = "optimize";
+
+/// Values of the searched parameters by name.
+pub type ParamSet = BTreeMap<String, f64>;
+
+/// Range a parameter is searched in, written `name:min:max`, or `name:min:max:int` for whole
+/// numbers such as periods.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParamRange {
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    #[serde(default)]
+    pub integer: bool,
+}
+
+impl std::str::FromStr for ParamRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split(':').collect();
+        let get_bound = |field: &str| {
+            field
+                .parse::<f64>()
+                .map_err(|_| format!("invalid bound {} in {}", field, s))
+        };
+
+        match fields.as_slice() {
+            [name, min, max] | [name, min, max, "int"] if !name.is_empty() => {
+                let (min, max) = (get_bound(min)?, get_bound(max)?);
+
+                if min > max {
+                    return Err(format!("empty range in {}", s));
+                }
+                Ok(ParamRange {
+                    name: name.to_string(),
+                    min,
+                    max,
+                    integer: fields.len() == 4,
+                })
+            }
+            _ => Err(format!("expected name:min:max[:int], got {}", s)),
+        }
+    }
+}
+
+impl ParamRange {
+    /// `value` clamped to the range, and rounded for whole-number parameters.
+    pub fn get_value(&self, value: f64) -> f64 {
+        let value = value.clamp(self.min, self.max);
+
+        if self.integer {
+            return value.round();
+        }
+        value
+    }
+
+    pub fn get_width(&self) -> f64 {
+        self.max - self.min
+    }
+}
+
+/// Backtest metric a search maximizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Objective {
+    TotalReturn,
+    TimeWeightedReturn,
+    WinRate,
+    /// Negated maximum drawdown, so that the shallowest drawdown wins.
+    MaxDrawdown,
+    /// Total return per percent of maximum drawdown.
+    ReturnOverDrawdown,
+}
+
+impl std::str::FromStr for Objective {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "total_return" => Ok(Objective::TotalReturn),
+            "time_weighted_return" => Ok(Objective::TimeWeightedReturn),
+            "win_rate" => Ok(Objective::WinRate),
+            "max_drawdown" => Ok(Objective::MaxDrawdown),
+            "return_over_drawdown" => Ok(Objective::ReturnOverDrawdown),
+            _ => Err(format!("unknown objective: {}", name)),
+        }
+    }
+}
+
+impl Objective {
+    pub fn get(&self, metrics: &run::RunMetrics) -> f64 {
+        match self {
+            Objective::TotalReturn => metrics.total_return,
+            Objective::TimeWeightedReturn => metrics.time_weighted_return,
+            Objective::WinRate => metrics.win_rate,
+            Objective::MaxDrawdown => -metrics.max_drawdown,
+            Objective::ReturnOverDrawdown => {
+                metrics.total_return / metrics.max_drawdown.abs().max(1.0)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Trial {
+    pub params: ParamSet,
+    pub fitness: f64,
+}
+
+/// Small seeded generator (xorshift64*), so that a search is reproducible from its seed.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed.max(1) }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniform in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in `[0, bound)`.
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_f64() * bound as f64) as usize
+    }
+
+    /// Standard normal, by the Box-Muller transform.
+    pub fn next_normal(&mut self) -> f64 {
+        let u = 1.0 - self.next_f64();
+        let v = self.next_f64();
+
+        (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+    }
+}
+
+/// Uniformly drawn parameter set.
+pub fn sample(ranges: &[ParamRange], rng: &mut Rng) -> ParamSet {
+    ranges
+        .iter()
+        .map(|range| {
+            (
+                range.name.clone(),
+                range.get_value(range.min + rng.next_f64() * range.get_width()),
+            )
+        })
+        .collect()
+}
+
+/// Fittest of the trials; an empty list, from a search cancelled before its first trial, gives
+/// an empty parameter set that loses to any other.
+pub fn get_best(trials: &[Trial]) -> Trial {
+    trials
+        .iter()
+        .max_by(|lhs, rhs| lhs.fitness.total_cmp(&rhs.fitness))
+        .cloned()
+        .unwrap_or(Trial {
+            params: ParamSet::new(),
+            fitness: f64::NEG_INFINITY,
+        })
+}
+
+/// Runs a rule-based strategy over a date range for every parameter set a search asks for. The
+/// `$name` placeholders of the rule set are bound to the parameters of each trial.
+pub struct BacktestEvaluator {
+    pub config: config::Config,
+    pub crawler: Rc<dyn crawler::Crawler>,
+    pub backend_op: Rc<dyn backend::BackendOp>,
+    pub rule_set: rule::RuleSet,
+    pub objective: Objective,
+    pub start_date: chrono::NaiveDate,
+    pub end_date: chrono::NaiveDate,
+    /// Worker running the trials, so that concurrent trials write to separate directories.
+    pub worker: usize,
+    /// Stops trials early that fall clearly behind the best one; a pruned trial scores the
+    /// objective of its partial run.
+    pub pruner: Option<pruner::Pruner>,
+    /// Records trials here, tagged `optimize`, and reuses those recorded with the same inputs.
+    pub run_op: Option<Rc<dyn run::RunOp>>,
+}
+
+impl BacktestEvaluator {
+    /// Backtest of the parameters, over the stocks of `stock_ids` only when given.
+    pub fn get_backtesting(
+        &self,
+        params: &ParamSet,
+        stock_ids: Option<&[String]>,
+    ) -> backtesting::Backtesting {
+        let mut trial_config = self.config.clone();
+        let crawler: Rc<dyn crawler::Crawler> = match stock_ids {
+            Some(stock_ids) => Rc::new(crossval::UniverseCrawler {
+                crawler: self.crawler.clone(),
+                stock_ids: stock_ids.to_vec(),
+            }),
+            None => self.crawler.clone(),
+        };
+
+        trial_config.portfolio_path = format!(
+            "{}/optimize/trial/{}",
+            self.config.portfolio_path, self.worker
+        );
+        let mut backtesting = backtesting::Backtesting::new(
+            trial_config,
+            crawler,
+            self.backend_op.clone(),
+            strategy::Strategies::Rule(self.rule_set.bind(params)),
+        );
+
+        backtesting.pruner = self.pruner.clone();
+        if let Some(run_op) = &self.run_op {
+            backtesting.run_op = Some(run_op.clone());
+            backtesting.cache_runs = true;
+            backtesting.run_tags = vec![OPTIMIZE_RUN_TAG.to_owned()];
+        }
+        backtesting
+    }
+
+    pub fn evaluate(&self, params: &ParamSet) -> f64 {
+        self.evaluate_on(params, None)
+    }
+
+    pub fn evaluate_on(&self, params: &ParamSet, stock_ids: Option<&[String]>) -> f64 {
+        let mut backtesting = self.get_backtesting(params, stock_ids);
+
+        backtesting.run(self.start_date, self.end_date);
+
+        let fitness = self.objective.get(&backtesting.get_run_metrics());
+
+        if let (Some(pruner), None, None) = (
+            &self.pruner,
+            backtesting.pruned_date,
+            &backtesting.cached_run,
+        ) {
+            pruner.record(fitness, backtesting.liquidity, &backtesting.summary);
+        }
+        fitness
+    }
+}
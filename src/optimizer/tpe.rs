@@ -0,0 +1,264 @@
+use serde::{Deserialize, Serialize};
+
+use crate::optimizer::search::{self, ParamRange, ParamSet, Rng, Trial};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TpeConfig {
+    /// Backtests run in total, random ones included.
+    pub trials: usize,
+    /// Randomly drawn trials before the estimators take over.
+    pub startup_trials: usize,
+    /// Share of the trials, the fittest, the estimator of good parameters is fitted to.
+    pub gamma: f64,
+    /// Parameter sets drawn from the good estimator per trial; the most promising is run.
+    pub candidates: usize,
+    /// Trials suggested together from the same estimators, so that they can run concurrently.
+    pub batch: usize,
+    pub seed: u64,
+}
+
+impl std::default::Default for TpeConfig {
+    fn default() -> Self {
+        TpeConfig {
+            trials: 50,
+            startup_trials: 10,
+            gamma: 0.25,
+            candidates: 24,
+            batch: 1,
+            seed: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TpeReport {
+    pub best: Trial,
+    /// Every trial in order; a repeated parameter set reuses the fitness of the first.
+    pub trials: Vec<Trial>,
+    /// Best fitness after each trial.
+    pub best_fitness: Vec<f64>,
+    /// Whether the search was interrupted; the trials completed until then are kept.
+    #[serde(default)]
+    pub cancelled: bool,
+}
+
+/// One-dimensional Parzen estimator over parameter values scaled to `[0, 1]`: a gaussian per
+/// observation, plus a wide prior one so that no region is ever ruled out.
+struct Parzen {
+    means: Vec<f64>,
+    sigma: f64,
+}
+
+const PRIOR_MEAN: f64 = 0.5;
+const PRIOR_SIGMA: f64 = 1.0;
+const MIN_SIGMA: f64 = 0.05;
+
+impl Parzen {
+    fn new(values: Vec<f64>) -> Self {
+        let count = values.len().max(1) as f64;
+        let mean = values.iter().sum::<f64>() / count;
+        let sd = (values
+            .iter()
+            .map(|value| (value - mean).powi(2))
+            .sum::<f64>()
+            / count)
+            .sqrt();
+
+        Parzen {
+            // Scott's rule.
+            sigma: (1.06 * sd * count.powf(-0.2)).max(MIN_SIGMA),
+            means: values,
+        }
+    }
+
+    fn get_density(&self, x: f64) -> f64 {
+        let get_normal = |mean: f64, sigma: f64| {
+            (-0.5 * ((x - mean) / sigma).powi(2)).exp()
+                / (sigma * (2.0 * std::f64::consts::PI).sqrt())
+        };
+        let total: f64 = self
+            .means
+            .iter()
+            .map(|mean| get_normal(*mean, self.sigma))
+            .sum::<f64>()
+            + get_normal(PRIOR_MEAN, PRIOR_SIGMA);
+
+        total / (self.means.len() + 1) as f64
+    }
+
+    fn sample(&self, rng: &mut Rng) -> f64 {
+        let index = rng.below(self.means.len() + 1);
+        let (mean, sigma) = match self.means.get(index) {
+            Some(mean) => (*mean, self.sigma),
+            None => (PRIOR_MEAN, PRIOR_SIGMA),
+        };
+
+        (mean + rng.next_normal() * sigma).clamp(0.0, 1.0)
+    }
+}
+
+fn get_scaled(range: &ParamRange, value: f64) -> f64 {
+    if range.get_width() == 0.0 {
+        return 0.5;
+    }
+    (value - range.min) / range.get_width()
+}
+
+/// Parameter set maximizing the ratio of the good to the bad density among candidates drawn
+/// from the good estimators, each parameter modelled independently.
+fn suggest(ranges: &[ParamRange], trials: &[Trial], config: &TpeConfig, rng: &mut Rng) -> ParamSet {
+    let mut sorted: Vec<&Trial> = trials.iter().collect();
+
+    sorted.sort_by(|lhs, rhs| rhs.fitness.total_cmp(&lhs.fitness));
+
+    let good_count = ((sorted.len() as f64 * config.gamma).ceil() as usize).clamp(1, sorted.len());
+    let (good, bad) = sorted.split_at(good_count);
+    let estimators: Vec<(Parzen, Parzen)> = ranges
+        .iter()
+        .map(|range| {
+            let get_values = |trials: &[&Trial]| {
+                trials
+                    .iter()
+                    .map(|trial| get_scaled(range, trial.params[&range.name]))
+                    .collect()
+            };
+
+            (Parzen::new(get_values(good)), Parzen::new(get_values(bad)))
+        })
+        .collect();
+    let mut best: Option<(f64, ParamSet)> = None;
+
+    for _ in 0..config.candidates.max(1) {
+        let mut params = ParamSet::new();
+        let mut ratio = 0.0;
+
+        for (range, (good, bad)) in ranges.iter().zip(estimators.iter()) {
+            let value = range.get_value(range.min + good.sample(rng) * range.get_width());
+            let scaled = get_scaled(range, value);
+
+            ratio += good.get_density(scaled).ln() - bad.get_density(scaled).ln();
+            params.insert(range.name.clone(), value);
+        }
+        if best
+            .as_ref()
+            .is_none_or(|(best_ratio, _)| ratio > *best_ratio)
+        {
+            best = Some((ratio, params));
+        }
+    }
+    best.unwrap().1
+}
+
+/// Tree-structured Parzen estimator search: after a few random trials, each trial runs the
+/// parameters most likely to land among the fittest seen so far. It usually needs far fewer
+/// backtests than a grid or a genetic search. Identical parameter sets are evaluated once.
+pub fn optimize(
+    ranges: &[ParamRange],
+    config: &TpeConfig,
+    mut evaluate: impl FnMut(&ParamSet) -> f64,
+) -> TpeReport {
+    optimize_batch(ranges, config, |batch| {
+        batch.iter().map(|params| Some(evaluate(params))).collect()
+    })
+}
+
+/// [`optimize`] suggesting `config.batch` trials at a time and evaluating them as one batch,
+/// e.g. across a [`TrialPool`](crate::optimizer::pool::TrialPool). A `None` fitness means the
+/// trial was cancelled, which ends the search.
+pub fn optimize_batch(
+    ranges: &[ParamRange],
+    config: &TpeConfig,
+    mut evaluate: impl FnMut(&[ParamSet]) -> Vec<Option<f64>>,
+) -> TpeReport {
+    let mut rng = Rng::new(config.seed);
+    let mut trials: Vec<Trial> = Vec::new();
+    let mut best_fitness: Vec<f64> = Vec::new();
+    let mut cancelled = false;
+
+    while trials.len() < config.trials.max(1) && !cancelled {
+        let count = config.batch.clamp(1, config.trials.max(1) - trials.len());
+        let suggestions: Vec<ParamSet> = (trials.len()..trials.len() + count)
+            .map(|index| match index < config.startup_trials.max(1) {
+                true => search::sample(ranges, &mut rng),
+                false => suggest(ranges, &trials, config, &mut rng),
+            })
+            .collect();
+        let mut batch: Vec<ParamSet> = Vec::new();
+
+        for params in suggestions.iter() {
+            if !trials.iter().any(|trial| trial.params == *params) && !batch.contains(params) {
+                batch.push(params.clone());
+            }
+        }
+
+        let fitnesses = evaluate(&batch);
+
+        for params in suggestions {
+            let fitness = match trials.iter().find(|trial| trial.params == params) {
+                Some(trial) => trial.fitness,
+                None => match batch
+                    .iter()
+                    .position(|evaluated| *evaluated == params)
+                    .and_then(|index| fitnesses[index])
+                {
+                    Some(fitness) => fitness,
+                    None => {
+                        cancelled = true;
+                        continue;
+                    }
+                },
+            };
+
+            trials.push(Trial { params, fitness });
+            best_fitness.push(
+                best_fitness
+                    .last()
+                    .map_or(fitness, |best: &f64| best.max(fitness)),
+            );
+        }
+    }
+
+    TpeReport {
+        best: search::get_best(&trials),
+        trials,
+        best_fitness,
+        cancelled,
+    }
+}
+
+#[cfg(test)]
+mod tpe_test {
+    use crate::optimizer::search::ParamRange;
+
+    use super::{optimize, TpeConfig};
+
+    #[test]
+    fn optimize_check() {
+        let ranges: Vec<ParamRange> = vec![
+            "period:5:60:int".parse().unwrap(),
+            "band:0.5:4".parse().unwrap(),
+        ];
+        let config = TpeConfig::default();
+        let report = optimize(&ranges, &config, |params| {
+            -(params["period"] - 20.0).powi(2) - 10.0 * (params["band"] - 2.0).powi(2)
+        });
+
+        assert_eq!(report.trials.len(), config.trials);
+        assert!(
+            report.best_fitness[config.trials - 1] > report.best_fitness[config.startup_trials - 1]
+        );
+        assert!((report.best.params["period"] - 20.0).abs() <= 4.0);
+        assert!((report.best.params["band"] - 2.0).abs() < 0.5);
+
+        let config = TpeConfig {
+            batch: 4,
+            ..TpeConfig::default()
+        };
+        let report = optimize(&ranges, &config, |params| {
+            -(params["period"] - 20.0).powi(2) - 10.0 * (params["band"] - 2.0).powi(2)
+        });
+
+        assert_eq!(report.trials.len(), config.trials);
+        assert!((report.best.params["period"] - 20.0).abs() <= 6.0);
+    }
+}
@@ -0,0 +1,208 @@
+use std::rc::Rc;
+
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use serde::Serialize;
+
+use crate::config::config;
+use crate::core::backtesting;
+use crate::crawler::finmind;
+use crate::dataview::view::{self, Transform};
+use crate::storage::backend::{self, BackendOp};
+use crate::strategy::strategy;
+
+fn to_python(py: Python<'_>, value: &serde_json::Value) -> PyResult<PyObject> {
+    Ok(match value {
+        serde_json::Value::Null => py.None(),
+        serde_json::Value::Bool(value) => value.into_py(py),
+        serde_json::Value::Number(number) => match (number.as_i64(), number.as_u64()) {
+            (Some(value), _) => value.into_py(py),
+            (None, Some(value)) => value.into_py(py),
+            (None, None) => number.as_f64().unwrap_or(f64::NAN).into_py(py),
+        },
+        serde_json::Value::String(value) => value.into_py(py),
+        serde_json::Value::Array(values) => {
+            let list = PyList::empty_bound(py);
+
+            for value in values {
+                list.append(to_python(py, value)?)?;
+            }
+            list.into_py(py)
+        }
+        serde_json::Value::Object(values) => {
+            let dict = PyDict::new_bound(py);
+
+            for (key, value) in values {
+                dict.set_item(key, to_python(py, value)?)?;
+            }
+            dict.into_py(py)
+        }
+    })
+}
+
+/// Converts records, views or metrics into plain Python lists and dicts, dates as ISO strings,
+/// ready for `pandas.DataFrame`.
+fn serialize<T: Serialize>(py: Python<'_>, value: &T) -> PyResult<PyObject> {
+    let value =
+        serde_json::to_value(value).map_err(|err| PyValueError::new_err(err.to_string()))?;
+
+    to_python(py, &value)
+}
+
+fn backend_error(err: backend::Error) -> PyErr {
+    PyRuntimeError::new_err(format!("{:?}", err))
+}
+
+fn view_error(err: view::Error) -> PyErr {
+    PyRuntimeError::new_err(format!("{:?}", err))
+}
+
+/// Read access to the stored records and their views.
+#[pyclass(unsendable, name = "Backend")]
+pub struct Backend {
+    backend_op: Rc<backend::SledBackend>,
+}
+
+#[pymethods]
+impl Backend {
+    #[new]
+    fn new(db_path: &str) -> PyResult<Self> {
+        Ok(Backend {
+            backend_op: Rc::new(backend::SledBackend::new(db_path).map_err(backend_error)?),
+        })
+    }
+
+    fn query_by_range(
+        &self,
+        py: Python<'_>,
+        stock_id: &str,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> PyResult<PyObject> {
+        let records = self
+            .backend_op
+            .query_by_range(stock_id, start_date, end_date)
+            .map_err(backend_error)?;
+
+        serialize(py, &records)
+    }
+
+    fn query_all(&self, py: Python<'_>, stock_id: &str) -> PyResult<PyObject> {
+        let records = self.backend_op.query_all(stock_id).map_err(backend_error)?;
+
+        serialize(py, &records)
+    }
+
+    /// Transforms the records of a stock into a view, named as in `draw_diagram --view`.
+    fn get_view(
+        &self,
+        py: Python<'_>,
+        view: &str,
+        stock_id: &str,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> PyResult<PyObject> {
+        let views = view.parse::<view::Views>().map_err(PyValueError::new_err)?;
+        let records = self
+            .backend_op
+            .query_by_range(stock_id, start_date, end_date)
+            .map_err(backend_error)?;
+
+        match views {
+            view::Views::None => serialize(py, &records),
+            view::Views::BollingerBand => serialize(
+                py,
+                &view::BollingerBandView::transform(&records).map_err(view_error)?,
+            ),
+            view::Views::Atr => {
+                serialize(py, &view::AtrView::transform(&records).map_err(view_error)?)
+            }
+            view::Views::EwmaVolatility => serialize(
+                py,
+                &view::EwmaVolatilityView::transform(&records).map_err(view_error)?,
+            ),
+            view::Views::Returns => serialize(
+                py,
+                &view::ReturnsView::transform(&records).map_err(view_error)?,
+            ),
+            view::Views::Rsi => {
+                serialize(py, &view::RsiView::transform(&records).map_err(view_error)?)
+            }
+            view::Views::Macd => serialize(
+                py,
+                &view::MacdView::transform(&records).map_err(view_error)?,
+            ),
+            view::Views::Keltner => serialize(
+                py,
+                &view::KeltnerView::transform(&records).map_err(view_error)?,
+            ),
+            view::Views::Beta => Err(PyValueError::new_err(
+                "the beta view needs a benchmark, use get_beta_view",
+            )),
+        }
+    }
+
+    fn get_beta_view(
+        &self,
+        py: Python<'_>,
+        stock_id: &str,
+        benchmark_id: &str,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> PyResult<PyObject> {
+        let records = self
+            .backend_op
+            .query_by_range(stock_id, start_date, end_date)
+            .map_err(backend_error)?;
+        let benchmark_records = self
+            .backend_op
+            .query_by_range(benchmark_id, start_date, end_date)
+            .map_err(backend_error)?;
+
+        serialize(
+            py,
+            &view::BetaView::transform_by_benchmark(
+                &records,
+                &benchmark_records,
+                view::BETA_PERIOD,
+            )
+            .map_err(view_error)?,
+        )
+    }
+}
+
+/// Runs a backtest as the `backtesting` command does and returns its metrics. Reports are
+/// written under the `portfolio_path` of the config. Pass `backend` to reuse an open database,
+/// as it cannot be opened twice.
+#[pyfunction]
+#[pyo3(signature = (config_path, start_date, end_date, strategy = "BollingerBand", backend = None))]
+fn run_backtest(
+    py: Python<'_>,
+    config_path: &str,
+    start_date: chrono::NaiveDate,
+    end_date: chrono::NaiveDate,
+    strategy: &str,
+    backend: Option<PyRef<'_, Backend>>,
+) -> PyResult<PyObject> {
+    let config = config::load_config(config_path)
+        .ok_or_else(|| PyValueError::new_err(format!("cannot load config {}", config_path)))?;
+    let strategy: strategy::Strategies = serde_yaml::from_str(strategy)
+        .map_err(|_| PyValueError::new_err(format!("unknown strategy {}", strategy)))?;
+    let backend_op = match backend {
+        Some(backend) => backend.backend_op.clone(),
+        None => Rc::new(backend::SledBackend::new(&config.db_path).map_err(backend_error)?),
+    };
+    let crawler = Rc::new(finmind::Finmind::new(&config.finmind_token));
+    let mut backtesting = backtesting::Backtesting::new(config, crawler, backend_op, strategy);
+
+    backtesting.run(start_date, end_date);
+    serialize(py, &backtesting.get_run_metrics())
+}
+
+#[pymodule]
+fn veronica(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<Backend>()?;
+    module.add_function(wrap_pyfunction!(run_backtest, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,37 @@
+/// Loads plotly.js for the charts embedded with `plotly::Plot::to_inline_html`.
+pub const PLOTLY_SCRIPT: &str =
+    "<script src=\"https://cdn.plot.ly/plotly-2.12.1.min.js\" charset=\"utf-8\"></script>";
+
+/// Standalone page around `body`, loading plotly.js when the body embeds charts.
+pub fn get_page(title: &str, body: &str, plots: bool) -> String {
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{}</title>{}</head><body>\
+         <h1>{}</h1>{}</body></html>",
+        title,
+        if plots { PLOTLY_SCRIPT } else { "" },
+        title,
+        body
+    )
+}
+
+pub fn get_table(headers: &[&str], rows: Vec<Vec<String>>) -> String {
+    if rows.is_empty() {
+        return "<p>None</p>".to_owned();
+    }
+
+    let mut html = String::from("<table border=\"1\" cellpadding=\"4\"><tr>");
+
+    for header in headers {
+        html.push_str(&format!("<th>{}</th>", header));
+    }
+    html.push_str("</tr>");
+    for row in rows {
+        html.push_str("<tr>");
+        for cell in row {
+            html.push_str(&format!("<td>{}</td>", cell));
+        }
+        html.push_str("</tr>");
+    }
+    html.push_str("</table>");
+    html
+}
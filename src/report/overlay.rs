@@ -0,0 +1,255 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::{backtesting, cashflow};
+use crate::diagram::diagram;
+
+use super::html;
+
+pub const OVERLAY_REPORT_FILENAME: &str = "overlay.html";
+/// Value every curve starts from once normalized.
+pub const NORMALIZED_BASE: f64 = 100.0;
+
+/// Failure to load the portfolio summary at the path.
+#[derive(Debug)]
+pub enum Error {
+    Io(std::path::PathBuf, std::io::Error),
+    Yaml(std::path::PathBuf, serde_yaml::Error),
+    /// The run recorded no equity to draw a curve from.
+    NoEquity(std::path::PathBuf),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Io(path, err) => write!(fmt, "{}: {}", path.display(), err),
+            Error::Yaml(path, err) => write!(fmt, "{}: {}", path.display(), err),
+            Error::NoEquity(path) => write!(fmt, "{}: no equity recorded", path.display()),
+        }
+    }
+}
+
+/// Equity curve of one run, normalized to start at `NORMALIZED_BASE`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OverlayCurve {
+    pub name: String,
+    /// Growth of the equity, chained day by day so that deposits and withdrawals do not show as
+    /// gains or losses.
+    pub points: Vec<(chrono::NaiveDate, f64)>,
+    pub start_equity: u32,
+    pub end_equity: u32,
+    /// Return over the run, in percent, external cash flows included.
+    pub total_return: f64,
+    /// Time-weighted return over the run, in percent.
+    pub time_weighted_return: f64,
+    /// Maximum drawdown over the run, in percent.
+    pub max_drawdown: f64,
+}
+
+impl OverlayCurve {
+    pub fn build(name: &str, summary: &backtesting::PortfolioSummary) -> Option<Self> {
+        let first_point = summary.equity_series.first()?;
+        let last_point = summary.equity_series.last()?;
+        let start_equity = (first_point.equity as i64 - first_point.cash_flow).max(0) as u32;
+        let mut last_equity = start_equity as f64;
+        let mut value = NORMALIZED_BASE;
+        let mut points = Vec::new();
+
+        for equity_point in &summary.equity_series {
+            let base = last_equity + equity_point.cash_flow as f64;
+
+            if base > 0.0 {
+                value *= equity_point.equity as f64 / base;
+            }
+            last_equity = equity_point.equity as f64;
+            points.push((equity_point.date, value));
+        }
+
+        Some(OverlayCurve {
+            name: name.to_owned(),
+            points,
+            start_equity,
+            end_equity: last_point.equity,
+            total_return: if start_equity == 0 {
+                0.0
+            } else {
+                (last_point.equity as f64 - start_equity as f64) / start_equity as f64 * 100.0
+            },
+            time_weighted_return: cashflow::time_weighted_return(
+                start_equity,
+                &summary
+                    .equity_series
+                    .iter()
+                    .map(|equity_point| (equity_point.equity, equity_point.cash_flow))
+                    .collect::<Vec<_>>(),
+            ),
+            max_drawdown: summary.max_drawdown,
+        })
+    }
+}
+
+/// Equity curves of several runs on one normalized scale.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OverlayReport {
+    pub curves: Vec<OverlayCurve>,
+}
+
+impl OverlayReport {
+    /// Page with the curves on one chart and a table of their metrics.
+    pub fn to_html(&self, style: &diagram::DiagramStyle) -> String {
+        let mut plot = plotly::Plot::new();
+
+        for curve in &self.curves {
+            plot.add_trace(
+                plotly::Scatter::new(
+                    curve
+                        .points
+                        .iter()
+                        .map(|(date, _)| date.to_string())
+                        .collect(),
+                    curve.points.iter().map(|(_, value)| *value).collect(),
+                )
+                .mode(plotly::common::Mode::Lines)
+                .name(&curve.name),
+            );
+        }
+        plot.set_layout(
+            style.get_layout().title(
+                format!("Equity, {} at start", NORMALIZED_BASE)
+                    .as_str()
+                    .into(),
+            ),
+        );
+
+        html::get_page(
+            "Equity curve overlay",
+            &format!(
+                "{}{}",
+                plot.to_inline_html(Some("overlay")),
+                html::get_table(
+                    &[
+                        "Run",
+                        "Start",
+                        "End",
+                        "Start equity",
+                        "End equity",
+                        "Return",
+                        "Time-weighted return",
+                        "Max drawdown",
+                    ],
+                    self.curves
+                        .iter()
+                        .map(|curve| {
+                            vec![
+                                curve.name.to_owned(),
+                                curve
+                                    .points
+                                    .first()
+                                    .map_or("-".to_owned(), |(date, _)| date.to_string()),
+                                curve
+                                    .points
+                                    .last()
+                                    .map_or("-".to_owned(), |(date, _)| date.to_string()),
+                                curve.start_equity.to_string(),
+                                curve.end_equity.to_string(),
+                                format!("{:+.2}%", curve.total_return),
+                                format!("{:+.2}%", curve.time_weighted_return),
+                                format!("{:.2}%", curve.max_drawdown),
+                            ]
+                        })
+                        .collect(),
+                )
+            ),
+            true,
+        )
+    }
+}
+
+/// Summary file of a run, given either its output directory or the file itself.
+fn get_summary_path(path: &str) -> std::path::PathBuf {
+    let path = std::path::Path::new(path);
+
+    match path.is_dir() {
+        true => path.join(backtesting::PORTFOLIO_SUMMARY_FILENAME),
+        false => path.to_path_buf(),
+    }
+}
+
+/// Name of a run on the chart: its output directory.
+fn get_curve_name(path: &str) -> String {
+    let summary_path = get_summary_path(path);
+
+    summary_path
+        .parent()
+        .and_then(|dir| dir.file_name())
+        .map_or(path.to_owned(), |name| name.to_string_lossy().into_owned())
+}
+
+/// Loads the equity curves of the runs at `paths`, each the output directory of a run or its
+/// portfolio summary, and normalizes them for comparison.
+pub fn overlay(paths: &[&str]) -> Result<OverlayReport, Error> {
+    let mut report = OverlayReport::default();
+
+    for path in paths {
+        let summary_path = get_summary_path(path);
+        let data = std::fs::read_to_string(&summary_path)
+            .map_err(|err| Error::Io(summary_path.clone(), err))?;
+        let mut summary: backtesting::PortfolioSummary =
+            serde_yaml::from_str(&data).map_err(|err| Error::Yaml(summary_path.clone(), err))?;
+
+        // Runs streaming their portfolios keep the equity points in a file of their own.
+        if summary.equity_series.is_empty() {
+            let equity_series_path =
+                summary_path.with_file_name(backtesting::EQUITY_SERIES_FILENAME);
+
+            if let Ok(data) = std::fs::read_to_string(&equity_series_path) {
+                summary.equity_series = serde_yaml::from_str(&data)
+                    .map_err(|err| Error::Yaml(equity_series_path, err))?;
+            }
+        }
+        let curve = OverlayCurve::build(&get_curve_name(path), &summary)
+            .ok_or_else(|| Error::NoEquity(summary_path.clone()))?;
+
+        report.curves.push(curve);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod overlay_test {
+    use crate::core::backtesting::{EquityPoint, PortfolioSummary};
+
+    use super::OverlayCurve;
+
+    #[test]
+    fn build_check() {
+        let get_point = |day: u32, equity: u32, cash_flow: i64| EquityPoint {
+            date: chrono::NaiveDate::from_ymd_opt(2024, 1, day).unwrap(),
+            equity,
+            unhedged_equity: equity as i64,
+            cash_flow,
+            invested: 0,
+            holding_count: 0,
+        };
+        let summary = PortfolioSummary {
+            max_drawdown: 5.0,
+            equity_series: vec![
+                get_point(2, 1000, 0),
+                get_point(3, 1100, 0),
+                get_point(4, 2200, 1100),
+                get_point(5, 2420, 0),
+            ],
+            ..Default::default()
+        };
+        let curve = OverlayCurve::build("run", &summary).unwrap();
+        let values: Vec<f64> = curve.points.iter().map(|(_, value)| *value).collect();
+
+        assert_eq!(values.len(), 4);
+        assert!((values[0] - 100.0).abs() < 1e-9);
+        assert!((values[1] - 110.0).abs() < 1e-9);
+        assert!((values[2] - 110.0).abs() < 1e-9);
+        assert!((values[3] - 121.0).abs() < 1e-9);
+        assert!((curve.time_weighted_return - 21.0).abs() < 1e-9);
+        assert!((curve.total_return - 142.0).abs() < 1e-9);
+        assert!(OverlayCurve::build("empty", &PortfolioSummary::default()).is_none());
+    }
+}
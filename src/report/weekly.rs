@@ -0,0 +1,236 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::{backtesting, decision};
+
+use super::html::get_table;
+
+pub const WEEKLY_REPORT_FILENAME: &str = "weekly_report.html";
+/// Calendar days covered by a report, ending on the date of the latest portfolio.
+pub const REPORT_DAYS: i64 = 7;
+
+#[derive(Serialize, Deserialize)]
+pub struct OpenPosition {
+    pub stock_id: String,
+    pub hold_date: Option<chrono::NaiveDate>,
+    pub num: u32,
+    pub hold_price: Option<u32>,
+    pub price: u32,
+    /// Trading days the stock has been halted, if it is.
+    #[serde(default)]
+    pub halted_days: Option<usize>,
+}
+
+impl OpenPosition {
+    /// Unrealized return, in percent.
+    pub fn get_return(&self) -> Option<f64> {
+        match self.hold_price {
+            Some(hold_price) if hold_price > 0 => {
+                Some((self.price as f64 - hold_price as f64) / hold_price as f64 * 100.0)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WeeklyReport {
+    pub start_date: chrono::NaiveDate,
+    pub end_date: chrono::NaiveDate,
+    pub start_equity: u32,
+    pub end_equity: u32,
+    pub trades_settled: Vec<backtesting::TradeRecord>,
+    pub stocks_entered: Vec<(chrono::NaiveDate, decision::StockInfo)>,
+    /// Positions still open at the end of the week, weakest first, as they are the likeliest to
+    /// be settled next.
+    pub settle_candidates: Vec<OpenPosition>,
+}
+
+impl WeeklyReport {
+    /// Summarizes the week ending on the date of the last portfolio, or `None` when there is no
+    /// portfolio at all.
+    pub fn build(
+        portfolios: &[decision::Portfolio],
+        trade_ledger: &[backtesting::TradeRecord],
+    ) -> Option<Self> {
+        let last_portfolio = portfolios.last()?;
+        let end_date = last_portfolio.date;
+        let start_date = end_date - chrono::Duration::days(REPORT_DAYS - 1);
+        let start_index = portfolios
+            .iter()
+            .position(|portfolio| portfolio.date >= start_date)
+            .unwrap_or(portfolios.len() - 1);
+        let start_equity = match start_index {
+            0 => portfolios[0].equity(),
+            index => portfolios[index - 1].equity(),
+        };
+        let mut stocks_entered = Vec::new();
+
+        for portfolio in &portfolios[start_index..] {
+            for stock_info in &portfolio.stocks_selected {
+                stocks_entered.push((portfolio.date, stock_info.clone()));
+            }
+        }
+
+        let mut settle_candidates: Vec<OpenPosition> = last_portfolio
+            .stocks_hold
+            .iter()
+            .chain(&last_portfolio.stocks_selected)
+            .map(|stock_info| {
+                let entry = portfolios.iter().rev().find_map(|portfolio| {
+                    portfolio
+                        .stocks_selected
+                        .iter()
+                        .find(|selected| selected.stock_id == stock_info.stock_id)
+                        .map(|selected| (portfolio.date, selected.price))
+                });
+
+                OpenPosition {
+                    stock_id: stock_info.stock_id.to_owned(),
+                    hold_date: entry.map(|(date, _)| date),
+                    num: stock_info.num,
+                    hold_price: entry.map(|(_, price)| price),
+                    price: stock_info.price,
+                    halted_days: stock_info.halt.as_ref().map(|halt| halt.days),
+                }
+            })
+            .collect();
+
+        settle_candidates.sort_by(|lhs, rhs| {
+            lhs.get_return()
+                .unwrap_or(0.0)
+                .total_cmp(&rhs.get_return().unwrap_or(0.0))
+        });
+
+        Some(WeeklyReport {
+            start_date,
+            end_date,
+            start_equity,
+            end_equity: last_portfolio.equity(),
+            trades_settled: trade_ledger
+                .iter()
+                .filter(|trade_record| {
+                    trade_record.settle_date >= start_date && trade_record.settle_date <= end_date
+                })
+                .cloned()
+                .collect(),
+            stocks_entered,
+            settle_candidates,
+        })
+    }
+
+    /// Equity change over the week, in percent.
+    pub fn get_return(&self) -> f64 {
+        if self.start_equity == 0 {
+            return 0.0;
+        }
+        (self.end_equity as f64 - self.start_equity as f64) / self.start_equity as f64 * 100.0
+    }
+
+    pub fn get_halted_positions(&self) -> Vec<&OpenPosition> {
+        self.settle_candidates
+            .iter()
+            .filter(|position| position.halted_days.is_some())
+            .collect()
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+
+        html.push_str("<!DOCTYPE html><html><head><meta charset=\"utf-8\">");
+        html.push_str(&format!(
+            "<title>Weekly report {} ~ {}</title></head><body>",
+            self.start_date, self.end_date
+        ));
+        html.push_str(&format!(
+            "<h1>Weekly report {} ~ {}</h1>",
+            self.start_date, self.end_date
+        ));
+        html.push_str(&format!(
+            "<p>Equity: {} &rarr; {} ({:+.2}%)</p>",
+            self.start_equity,
+            self.end_equity,
+            self.get_return()
+        ));
+
+        html.push_str("<h2>Trades settled</h2>");
+        html.push_str(&get_table(
+            &[
+                "Stock",
+                "Hold date",
+                "Settle date",
+                "Shares",
+                "Hold price",
+                "Settle price",
+                "Return",
+            ],
+            self.trades_settled
+                .iter()
+                .map(|trade_record| {
+                    vec![
+                        trade_record.stock_id.to_owned(),
+                        trade_record.hold_date.to_string(),
+                        trade_record.settle_date.to_string(),
+                        trade_record.num.to_string(),
+                        trade_record.hold_price.to_string(),
+                        trade_record.settle_price.to_string(),
+                        format!("{:+.2}%", trade_record.get_return()),
+                    ]
+                })
+                .collect(),
+        ));
+
+        html.push_str("<h2>Stocks entered</h2>");
+        html.push_str(&get_table(
+            &["Stock", "Date", "Shares", "Price", "Value"],
+            self.stocks_entered
+                .iter()
+                .map(|(date, stock_info)| {
+                    vec![
+                        stock_info.stock_id.to_owned(),
+                        date.to_string(),
+                        stock_info.num.to_string(),
+                        stock_info.price.to_string(),
+                        (stock_info.num * stock_info.price).to_string(),
+                    ]
+                })
+                .collect(),
+        ));
+
+        html.push_str("<h2>Settle candidates</h2>");
+        html.push_str(&get_table(
+            &[
+                "Stock",
+                "Hold date",
+                "Shares",
+                "Hold price",
+                "Price",
+                "Return",
+                "Halted",
+            ],
+            self.settle_candidates
+                .iter()
+                .map(|position| {
+                    vec![
+                        position.stock_id.to_owned(),
+                        position
+                            .hold_date
+                            .map_or("-".to_owned(), |date| date.to_string()),
+                        position.num.to_string(),
+                        position
+                            .hold_price
+                            .map_or("-".to_owned(), |price| price.to_string()),
+                        position.price.to_string(),
+                        position
+                            .get_return()
+                            .map_or("-".to_owned(), |ret| format!("{:+.2}%", ret)),
+                        position
+                            .halted_days
+                            .map_or("-".to_owned(), |days| format!("{} days", days)),
+                    ]
+                })
+                .collect(),
+        ));
+        html.push_str("</body></html>");
+        html
+    }
+}
@@ -0,0 +1,196 @@
+use std::sync::{Arc, RwLock};
+
+use crate::core::{decision, order};
+
+#[derive(Debug)]
+pub enum Error {
+    Decision(decision::Error),
+    /// The day is not after the last decided one.
+    AlreadyDecided(chrono::NaiveDate),
+    /// No trading took place on the day, or its data is missing.
+    NoTrading(chrono::NaiveDate),
+    /// A command panicked while holding the state.
+    Poisoned,
+}
+
+impl From<decision::Error> for Error {
+    fn from(err: decision::Error) -> Error {
+        Error::Decision(err)
+    }
+}
+
+/// Requests to the engine. Every access to the paper-trading state goes through one of them.
+#[derive(Debug, Clone)]
+pub enum Command {
+    /// Decides the day, continuing from the state the last decided day ended with.
+    RunDecision(chrono::NaiveDate),
+    /// Portfolio of the last decided day.
+    GetPortfolio,
+    /// Orders of every decided day.
+    ListTrades,
+}
+
+#[derive(Debug, Clone)]
+pub enum Reply {
+    Portfolio(Option<Box<decision::Portfolio>>),
+    Trades(Vec<order::OrderPlan>),
+}
+
+/// Paper-trading state kept between commands.
+#[derive(Debug, Default)]
+struct State {
+    end_state: Option<decision::EndState>,
+    portfolios: Vec<decision::Portfolio>,
+}
+
+/// Builds a configured decision on handles of its own. Decisions hold `Rc`s, so each command
+/// builds one on its thread and resumes it from the shared state instead of sharing it.
+pub type DecisionBuilder = dyn Fn() -> decision::Decision + Send + Sync;
+
+/// Service over the paper-trading state of a profile, shared by the HTTP handlers and the
+/// scheduler. Clones share the same state; decisions are run one at a time, and readers see
+/// either the state before a decision or after it.
+#[derive(Clone)]
+pub struct Engine {
+    build_decision: Arc<DecisionBuilder>,
+    state: Arc<RwLock<State>>,
+}
+
+impl Engine {
+    pub fn new(build_decision: Arc<DecisionBuilder>) -> Self {
+        Engine {
+            build_decision,
+            state: Arc::new(RwLock::new(State::default())),
+        }
+    }
+
+    pub fn execute(&self, command: Command) -> Result<Reply, Error> {
+        match command {
+            Command::RunDecision(date) => self.run_decision(date),
+            Command::GetPortfolio => {
+                let state = self.state.read().map_err(|_| Error::Poisoned)?;
+
+                Ok(Reply::Portfolio(
+                    state.portfolios.last().cloned().map(Box::new),
+                ))
+            }
+            Command::ListTrades => {
+                let state = self.state.read().map_err(|_| Error::Poisoned)?;
+
+                Ok(Reply::Trades(
+                    state
+                        .portfolios
+                        .iter()
+                        .map(order::OrderPlan::from_portfolio)
+                        .collect(),
+                ))
+            }
+        }
+    }
+
+    /// Holds the state for writing over the whole decision, so that a second decision waits
+    /// for the first one's end state instead of starting from the same one.
+    fn run_decision(&self, date: chrono::NaiveDate) -> Result<Reply, Error> {
+        let mut state = self.state.write().map_err(|_| Error::Poisoned)?;
+
+        if let Some(end_state) = &state.end_state {
+            if date <= end_state.date {
+                return Err(Error::AlreadyDecided(date));
+            }
+        }
+
+        let mut decision = (self.build_decision)();
+
+        if let Some(end_state) = &state.end_state {
+            decision.resume(end_state);
+        }
+
+        let portfolio = decision
+            .calc_portfolio(date)?
+            .ok_or(Error::NoTrading(date))?;
+
+        state.end_state = decision.get_end_state();
+        state.portfolios.push(portfolio.clone());
+        Ok(Reply::Portfolio(Some(Box::new(portfolio))))
+    }
+}
+
+#[cfg(test)]
+mod engine_test {
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    use super::{Command, Engine, Error, Reply};
+    use crate::core::decision;
+    use crate::crawler::crawler;
+    use crate::storage::memory;
+    use crate::strategy::{dca, strategy};
+    use crate::testkit::generator;
+
+    #[test]
+    fn execute_check() {
+        let start_date = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let engine = Engine::new(Arc::new(move || {
+            let backend_op = Rc::new(memory::MemoryBackend::new());
+            let mut mock_crawler = crawler::MockCrawler::new();
+
+            generator::SeriesBuilder::new(start_date, 100.0)
+                .flat(65)
+                .insert(backend_op.as_ref(), "0050")
+                .unwrap();
+            mock_crawler
+                .expect_get_stock_list()
+                .returning(|| Ok(vec![]));
+
+            let plan = dca::Plan::default();
+            let mut decision = decision::Decision::new(
+                Rc::new(mock_crawler),
+                backend_op.clone(),
+                Rc::new(strategy::StrategyFactory::get(
+                    strategy::Strategies::Dca(plan.clone()),
+                    &strategy::StrategyParams::default(),
+                    backend_op,
+                )),
+            );
+
+            decision.dca = Some(plan);
+            decision
+        }));
+        let scheduler = engine.clone();
+        let first_date = start_date;
+        let second_date = chrono::NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+
+        std::thread::spawn(move || scheduler.execute(Command::RunDecision(first_date)))
+            .join()
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            engine.execute(Command::RunDecision(first_date)),
+            Err(Error::AlreadyDecided(_))
+        ));
+        engine.execute(Command::RunDecision(second_date)).unwrap();
+
+        let portfolio = match engine.execute(Command::GetPortfolio).unwrap() {
+            Reply::Portfolio(portfolio) => portfolio.unwrap(),
+            reply => panic!("Unexpected reply {:?}", reply),
+        };
+
+        // The second day resumes from the position the first one bought.
+        assert_eq!(portfolio.date, second_date);
+        assert_eq!(portfolio.stocks_hold.len(), 1);
+        assert_eq!(portfolio.stocks_selected.len(), 1);
+
+        let order_plans = match engine.execute(Command::ListTrades).unwrap() {
+            Reply::Trades(order_plans) => order_plans,
+            reply => panic!("Unexpected reply {:?}", reply),
+        };
+
+        assert_eq!(
+            order_plans
+                .iter()
+                .map(|order_plan| (order_plan.date, order_plan.orders.len()))
+                .collect::<Vec<_>>(),
+            vec![(first_date, 1), (second_date, 1)]
+        );
+    }
+}
@@ -0,0 +1,550 @@
+use std::collections::HashMap;
+
+use graphql_parser::query;
+use serde::{Deserialize, Serialize};
+
+use crate::dataview::{adjust, view};
+use crate::storage::backend;
+
+#[derive(Debug)]
+pub enum Error {
+    Parse(query::ParseError),
+    Backend(backend::Error),
+    View(view::Error),
+    Json(serde_json::Error),
+    /// Query outside the schema, or an argument of the wrong type.
+    Invalid(String),
+}
+
+impl From<query::ParseError> for Error {
+    fn from(err: query::ParseError) -> Error {
+        Error::Parse(err)
+    }
+}
+
+impl From<backend::Error> for Error {
+    fn from(err: backend::Error) -> Error {
+        Error::Backend(err)
+    }
+}
+
+impl From<view::Error> for Error {
+    fn from(err: view::Error) -> Error {
+        Error::View(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::Json(err)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Parse(err) => write!(fmt, "{}", err),
+            Error::Backend(err) => write!(fmt, "backend error: {:?}", err),
+            Error::View(err) => write!(fmt, "view error: {:?}", err),
+            Error::Json(err) => write!(fmt, "{}", err),
+            Error::Invalid(message) => write!(fmt, "{}", message),
+        }
+    }
+}
+
+type Object = serde_json::Map<String, serde_json::Value>;
+type Field = query::Field<'static, String>;
+type SelectionSet = query::SelectionSet<'static, String>;
+
+/// Body of a GraphQL request over HTTP.
+#[derive(Debug, Deserialize)]
+pub struct Request {
+    pub query: String,
+    #[serde(default)]
+    pub variables: Option<Object>,
+    #[serde(default, rename = "operationName")]
+    pub operation_name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResponseError {
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Response {
+    pub data: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<ResponseError>,
+}
+
+/// Fragments and variables of the operation being executed, and the records it reads.
+struct Context<'a> {
+    backend_op: &'a dyn backend::BackendOp,
+    back_adjustment: &'a adjust::BackAdjustment,
+    fragments: HashMap<&'a str, &'a query::FragmentDefinition<'static, String>>,
+    variables: Object,
+}
+
+/// Runs the query of `request` against the stored records, on the schema:
+///
+/// ```graphql
+/// type Query {
+///   stocks(ids: [String!]!, start: String, end: String): [Stock!]!
+/// }
+///
+/// type Stock {
+///   id: String!
+///   records: [Record!]!
+///   sma(period: Int!): [Sma!]!
+///   rsi(period: Int): [Rsi!]!
+///   atr(period: Int): [Atr!]!
+/// }
+/// ```
+///
+/// The lists of a stock are its stored records and their views between `start` and `end`,
+/// both `YYYY-MM-DD` and inclusive; the views are warmed up on the records before `start`. The
+/// fields of `Record`, `Sma`, `Rsi` and `Atr` are those of `schema::RawData`, `view::SmaView`,
+/// `view::RsiView` and `view::AtrView`.
+///
+/// A query either succeeds as a whole or answers no data and the error.
+pub fn execute(
+    backend_op: &dyn backend::BackendOp,
+    back_adjustment: &adjust::BackAdjustment,
+    request: &Request,
+) -> Response {
+    match execute_query(backend_op, back_adjustment, request) {
+        Ok(data) => Response {
+            data: Some(serde_json::Value::Object(data)),
+            errors: Vec::new(),
+        },
+        Err(err) => Response {
+            data: None,
+            errors: vec![ResponseError {
+                message: err.to_string(),
+            }],
+        },
+    }
+}
+
+fn execute_query(
+    backend_op: &dyn backend::BackendOp,
+    back_adjustment: &adjust::BackAdjustment,
+    request: &Request,
+) -> Result<Object, Error> {
+    let document = query::parse_query::<String>(&request.query)?.into_static();
+    let mut fragments = HashMap::new();
+    let mut operations = Vec::new();
+
+    for definition in &document.definitions {
+        match definition {
+            query::Definition::Operation(operation) => operations.push(operation),
+            query::Definition::Fragment(fragment) => {
+                fragments.insert(fragment.name.as_str(), fragment);
+            }
+        }
+    }
+
+    let operation = match (&request.operation_name, operations.as_slice()) {
+        (None, [operation]) => *operation,
+        (None, _) => {
+            return Err(Error::Invalid(
+                "operationName is required with several operations".to_owned(),
+            ))
+        }
+        (Some(name), _) => operations
+            .iter()
+            .find(|operation| get_operation_name(operation) == Some(name))
+            .copied()
+            .ok_or_else(|| Error::Invalid(format!("Unknown operation {}", name)))?,
+    };
+    let (variable_definitions, selection_set) = match operation {
+        query::OperationDefinition::SelectionSet(selection_set) => (&[][..], selection_set),
+        query::OperationDefinition::Query(query) => {
+            (query.variable_definitions.as_slice(), &query.selection_set)
+        }
+        _ => return Err(Error::Invalid("Only queries are supported".to_owned())),
+    };
+    let mut variables = request.variables.clone().unwrap_or_default();
+
+    for definition in variable_definitions {
+        if let (false, Some(default_value)) = (
+            variables.contains_key(&definition.name),
+            &definition.default_value,
+        ) {
+            let value = to_json(default_value, &Object::new());
+
+            variables.insert(definition.name.to_owned(), value);
+        }
+    }
+
+    let context = Context {
+        backend_op,
+        back_adjustment,
+        fragments,
+        variables,
+    };
+
+    context.resolve_query(selection_set)
+}
+
+fn get_operation_name<'a>(
+    operation: &'a query::OperationDefinition<'static, String>,
+) -> Option<&'a String> {
+    match operation {
+        query::OperationDefinition::SelectionSet(_) => None,
+        query::OperationDefinition::Query(query) => query.name.as_ref(),
+        query::OperationDefinition::Mutation(mutation) => mutation.name.as_ref(),
+        query::OperationDefinition::Subscription(subscription) => subscription.name.as_ref(),
+    }
+}
+
+/// JSON of a literal or a variable of the query; variables that are not set are null.
+fn to_json(value: &query::Value<'static, String>, variables: &Object) -> serde_json::Value {
+    match value {
+        query::Value::Variable(name) => variables
+            .get(name)
+            .cloned()
+            .unwrap_or(serde_json::Value::Null),
+        query::Value::Int(number) => number
+            .as_i64()
+            .map(serde_json::Value::from)
+            .unwrap_or(serde_json::Value::Null),
+        query::Value::Float(number) => serde_json::Value::from(*number),
+        query::Value::String(string) | query::Value::Enum(string) => {
+            serde_json::Value::from(string.to_owned())
+        }
+        query::Value::Boolean(boolean) => serde_json::Value::from(*boolean),
+        query::Value::Null => serde_json::Value::Null,
+        query::Value::List(values) => serde_json::Value::Array(
+            values
+                .iter()
+                .map(|value| to_json(value, variables))
+                .collect(),
+        ),
+        query::Value::Object(fields) => serde_json::Value::Object(
+            fields
+                .iter()
+                .map(|(name, value)| (name.to_owned(), to_json(value, variables)))
+                .collect(),
+        ),
+    }
+}
+
+/// Key of the field in the response: its alias, or its name.
+fn get_key(field: &Field) -> String {
+    field.alias.as_ref().unwrap_or(&field.name).to_owned()
+}
+
+fn is_in_range(
+    date: chrono::NaiveDate,
+    (start_date, end_date): (Option<chrono::NaiveDate>, Option<chrono::NaiveDate>),
+) -> bool {
+    start_date.is_none_or(|start_date| date >= start_date)
+        && end_date.is_none_or(|end_date| date <= end_date)
+}
+
+impl<'a> Context<'a> {
+    /// Fields of `selection_set` on an object of `type_name`, with the fragments it spreads
+    /// expanded. `spread` holds the fragments being expanded, to refuse cycles.
+    fn collect_fields(
+        &self,
+        selection_set: &'a SelectionSet,
+        type_name: &str,
+        spread: &mut Vec<&'a str>,
+    ) -> Result<Vec<&'a Field>, Error> {
+        let mut fields = Vec::new();
+
+        for selection in &selection_set.items {
+            let (directives, type_condition, selection_set) = match selection {
+                query::Selection::Field(field) => {
+                    if !field.directives.is_empty() {
+                        return Err(Error::Invalid("Directives are not supported".to_owned()));
+                    }
+                    fields.push(field);
+                    continue;
+                }
+                query::Selection::FragmentSpread(fragment_spread) => {
+                    let name = fragment_spread.fragment_name.as_str();
+                    let fragment = self
+                        .fragments
+                        .get(name)
+                        .ok_or_else(|| Error::Invalid(format!("Unknown fragment {}", name)))?;
+
+                    if spread.contains(&name) {
+                        return Err(Error::Invalid(format!("Fragment {} spreads itself", name)));
+                    }
+                    spread.push(name);
+                    (
+                        &fragment_spread.directives,
+                        Some(&fragment.type_condition),
+                        &fragment.selection_set,
+                    )
+                }
+                query::Selection::InlineFragment(inline_fragment) => (
+                    &inline_fragment.directives,
+                    inline_fragment.type_condition.as_ref(),
+                    &inline_fragment.selection_set,
+                ),
+            };
+
+            if !directives.is_empty() {
+                return Err(Error::Invalid("Directives are not supported".to_owned()));
+            }
+            if let Some(query::TypeCondition::On(condition)) = type_condition {
+                if condition != type_name {
+                    return Err(Error::Invalid(format!(
+                        "Fragment on {} spread on {}",
+                        condition, type_name
+                    )));
+                }
+            }
+            fields.extend(self.collect_fields(selection_set, type_name, spread)?);
+            if let query::Selection::FragmentSpread(_) = selection {
+                spread.pop();
+            }
+        }
+
+        Ok(fields)
+    }
+
+    /// Argument `name` of `field`, or None when it is not given or null.
+    fn get_argument<T: serde::de::DeserializeOwned>(
+        &self,
+        field: &Field,
+        name: &str,
+    ) -> Result<Option<T>, Error> {
+        let value = match field.arguments.iter().find(|(key, _)| key == name) {
+            Some((_, value)) => to_json(value, &self.variables),
+            None => return Ok(None),
+        };
+
+        match value {
+            serde_json::Value::Null => Ok(None),
+            value => serde_json::from_value(value).map(Some).map_err(|_| {
+                Error::Invalid(format!("Invalid argument {} of {}", name, field.name))
+            }),
+        }
+    }
+
+    fn get_required_argument<T: serde::de::DeserializeOwned>(
+        &self,
+        field: &Field,
+        name: &str,
+    ) -> Result<T, Error> {
+        self.get_argument(field, name)?
+            .ok_or_else(|| Error::Invalid(format!("Missing argument {} of {}", name, field.name)))
+    }
+
+    fn resolve_query(&self, selection_set: &'a SelectionSet) -> Result<Object, Error> {
+        let mut data = Object::new();
+
+        for field in self.collect_fields(selection_set, "Query", &mut Vec::new())? {
+            let value = match field.name.as_str() {
+                "__typename" => serde_json::Value::from("Query"),
+                "stocks" => {
+                    let stock_ids: Vec<String> = self.get_required_argument(field, "ids")?;
+                    let range = (
+                        self.get_argument(field, "start")?,
+                        self.get_argument(field, "end")?,
+                    );
+
+                    serde_json::Value::Array(
+                        stock_ids
+                            .iter()
+                            .map(|stock_id| {
+                                self.resolve_stock(stock_id, range, &field.selection_set)
+                            })
+                            .collect::<Result<_, _>>()?,
+                    )
+                }
+                name => return Err(Error::Invalid(format!("Unknown field {} of Query", name))),
+            };
+
+            data.insert(get_key(field), value);
+        }
+
+        Ok(data)
+    }
+
+    fn resolve_stock(
+        &self,
+        stock_id: &str,
+        range: (Option<chrono::NaiveDate>, Option<chrono::NaiveDate>),
+        selection_set: &'a SelectionSet,
+    ) -> Result<serde_json::Value, Error> {
+        let records = self
+            .back_adjustment
+            .apply(stock_id, self.backend_op.query_all(stock_id)?);
+        let mut stock = Object::new();
+
+        for field in self.collect_fields(selection_set, "Stock", &mut Vec::new())? {
+            let value = match field.name.as_str() {
+                "__typename" => serde_json::Value::from("Stock"),
+                "id" => serde_json::Value::from(stock_id),
+                "records" => {
+                    self.resolve_list(&records, |record| record.date, range, field, "Record")?
+                }
+                "sma" => self.resolve_list(
+                    &view::SmaView::transform_by_period(
+                        &records,
+                        self.get_required_argument(field, "period")?,
+                    )?,
+                    |view| view.date,
+                    range,
+                    field,
+                    "Sma",
+                )?,
+                "rsi" => self.resolve_list(
+                    &view::RsiView::transform_by_period(
+                        &records,
+                        self.get_argument(field, "period")?
+                            .unwrap_or(view::RSI_PERIOD),
+                    )?,
+                    |view| view.date,
+                    range,
+                    field,
+                    "Rsi",
+                )?,
+                "atr" => self.resolve_list(
+                    &view::AtrView::transform_by_period(
+                        &records,
+                        self.get_argument(field, "period")?
+                            .unwrap_or(view::ATR_PERIOD),
+                    )?,
+                    |view| view.date,
+                    range,
+                    field,
+                    "Atr",
+                )?,
+                name => return Err(Error::Invalid(format!("Unknown field {} of Stock", name))),
+            };
+
+            stock.insert(get_key(field), value);
+        }
+
+        Ok(serde_json::Value::Object(stock))
+    }
+
+    /// Items of `items` dated within `range`, each with the subfields `field` selects.
+    fn resolve_list<T: Serialize>(
+        &self,
+        items: &[T],
+        get_date: impl Fn(&T) -> chrono::NaiveDate,
+        range: (Option<chrono::NaiveDate>, Option<chrono::NaiveDate>),
+        field: &'a Field,
+        type_name: &str,
+    ) -> Result<serde_json::Value, Error> {
+        let fields = self.collect_fields(&field.selection_set, type_name, &mut Vec::new())?;
+
+        if fields.is_empty() {
+            return Err(Error::Invalid(format!(
+                "Field {} needs a selection of subfields",
+                field.name
+            )));
+        }
+
+        let mut values = Vec::new();
+
+        for item in items
+            .iter()
+            .filter(|item| is_in_range(get_date(item), range))
+        {
+            let object = match serde_json::to_value(item)? {
+                serde_json::Value::Object(object) => object,
+                _ => return Err(Error::Invalid(format!("{} is not an object", type_name))),
+            };
+            let mut value = Object::new();
+
+            for field in &fields {
+                let subfield = match field.name.as_str() {
+                    "__typename" => serde_json::Value::from(type_name),
+                    name => object.get(name).cloned().ok_or_else(|| {
+                        Error::Invalid(format!("Unknown field {} of {}", name, type_name))
+                    })?,
+                };
+
+                value.insert(get_key(field), subfield);
+            }
+            values.push(serde_json::Value::Object(value));
+        }
+
+        Ok(serde_json::Value::Array(values))
+    }
+}
+
+#[cfg(test)]
+mod graphql_test {
+    use super::{execute, Request};
+    use crate::dataview::adjust;
+    use crate::storage::memory;
+    use crate::testkit::generator;
+
+    fn run(backend_op: &memory::MemoryBackend, query: &str, variables: &str) -> serde_json::Value {
+        let request = Request {
+            query: query.to_owned(),
+            variables: serde_json::from_str(variables).unwrap(),
+            operation_name: None,
+        };
+
+        serde_json::to_value(execute(
+            backend_op,
+            &adjust::BackAdjustment::default(),
+            &request,
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn execute_check() {
+        let backend_op = memory::MemoryBackend::new();
+        let start_date = chrono::NaiveDate::from_ymd_opt(2023, 1, 2).unwrap();
+
+        // Closes 100, 110, 121, 133.1, 146.41, 161.051.
+        generator::SeriesBuilder::new(start_date, 100.0)
+            .flat(1)
+            .trend(5, 10.0)
+            .insert(&backend_op, "2330")
+            .unwrap();
+        generator::SeriesBuilder::new(start_date, 50.0)
+            .flat(6)
+            .insert(&backend_op, "0050")
+            .unwrap();
+
+        let response = run(
+            &backend_op,
+            "query Closes($ids: [String!]!, $start: String = \"2023-01-05\") {
+                stocks(ids: $ids, start: $start) {
+                    id
+                    ...prices
+                    sma3: sma(period: 3) { date sma }
+                }
+            }
+            fragment prices on Stock { records { date close } }",
+            r#"{"ids": ["2330", "0050"]}"#,
+        );
+        let stocks = response["data"]["stocks"].as_array().unwrap();
+
+        assert!(response.get("errors").is_none());
+        assert_eq!(stocks.len(), 2);
+        assert_eq!(stocks[0]["id"], "2330");
+        assert_eq!(stocks[0]["records"][0]["date"], "2023-01-05");
+        assert_eq!(stocks[0]["records"].as_array().unwrap().len(), 3);
+        assert!(stocks[0]["records"][0].get("open").is_none());
+        // Warmed up on the closes before the start: (110 + 121 + 133.1) / 3.
+        assert!((stocks[0]["sma3"][0]["sma"].as_f64().unwrap() - 121.366_666).abs() < 1e-3);
+        assert_eq!(stocks[1]["sma3"][2]["sma"], 50.0);
+
+        let response = run(
+            &backend_op,
+            "{ stocks(ids: [\"2330\"]) { volume } }",
+            "null",
+        );
+
+        assert!(response["data"].is_null());
+        assert_eq!(
+            response["errors"][0]["message"],
+            "Unknown field volume of Stock"
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+
+use serde::Serialize;
+
+/// Longest request line and headers read; longer ones are refused as bad requests.
+pub const MAX_HEAD_SIZE: usize = 8 * 1024;
+/// Longest request body read; longer ones are refused before any of it is read.
+pub const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    /// Malformed or truncated request line, headers or body.
+    BadRequest,
+    /// Body declared longer than `MAX_BODY_SIZE`.
+    PayloadTooLarge,
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct Request {
+    pub method: String,
+    /// Path of the request target, still percent-encoded.
+    pub path: String,
+    /// Query of the request target without the `?`, still percent-encoded.
+    pub query: String,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Reads the request line, the headers and the body of the length the headers declare.
+    pub fn read<R: Read>(stream: R) -> Result<Request, Error> {
+        let mut reader = BufReader::new(stream.take(MAX_HEAD_SIZE as u64));
+        let request_line = read_line(&mut reader)?;
+        let mut content_length = 0;
+
+        loop {
+            let line = read_line(&mut reader)?;
+
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().map_err(|_| Error::BadRequest)?;
+                }
+            }
+        }
+        if content_length > MAX_BODY_SIZE {
+            return Err(Error::PayloadTooLarge);
+        }
+
+        let mut body = vec![0; content_length];
+
+        reader.get_mut().set_limit(content_length as u64);
+        reader
+            .read_exact(&mut body)
+            .map_err(|_| Error::BadRequest)?;
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().ok_or(Error::BadRequest)?;
+        let target = parts.next().ok_or(Error::BadRequest)?;
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+        Ok(Request {
+            method: method.to_owned(),
+            path: path.to_owned(),
+            query: query.to_owned(),
+            body,
+        })
+    }
+
+    /// Percent-decoded segments of the path, empty ones left out. None when a segment does not
+    /// decode to UTF-8.
+    pub fn get_segments(&self) -> Option<Vec<String>> {
+        self.path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(percent_decode)
+            .collect()
+    }
+
+    /// Percent-decoded value of the query parameter `name`, if present.
+    pub fn get_param(&self, name: &str) -> Option<String> {
+        self.query
+            .split('&')
+            .find_map(|param| match param.split_once('=') {
+                Some((key, value)) if key == name => Some(value),
+                _ => None,
+            })
+            .and_then(percent_decode)
+    }
+}
+
+/// Line of the request head without its line break. A line cut off by `MAX_HEAD_SIZE` or the
+/// end of the stream is a bad request.
+fn read_line<R: BufRead>(reader: &mut R) -> Result<String, Error> {
+    let mut line = String::new();
+
+    reader.read_line(&mut line).map_err(|_| Error::BadRequest)?;
+    match line.strip_suffix('\n') {
+        Some(line) => Ok(line.trim_end_matches('\r').to_owned()),
+        None => Err(Error::BadRequest),
+    }
+}
+
+/// Decodes the `%XX` escapes of `value`. None when an escape is malformed or the bytes are not
+/// UTF-8.
+pub fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        match bytes[index] {
+            b'%' => {
+                let hex = std::str::from_utf8(bytes.get(index + 1..index + 3)?).ok()?;
+
+                decoded.push(u8::from_str_radix(hex, 16).ok()?);
+                index += 3;
+            }
+            byte => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+pub struct Response {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: &'static str, content_type: &'static str, body: Vec<u8>) -> Self {
+        Response {
+            status,
+            content_type,
+            body,
+        }
+    }
+
+    pub fn json<T: Serialize>(status: &'static str, body: &T) -> Self {
+        Response::new(
+            status,
+            "application/json",
+            serde_json::to_vec(body).unwrap(),
+        )
+    }
+
+    pub fn text(status: &'static str, body: &str) -> Self {
+        Response::new(
+            status,
+            "text/plain; charset=utf-8",
+            body.as_bytes().to_vec(),
+        )
+    }
+
+    pub fn write<W: Write>(&self, stream: &mut W) {
+        let header = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.status,
+            self.content_type,
+            self.body.len()
+        );
+
+        if let Err(err) = stream
+            .write_all(header.as_bytes())
+            .and_then(|_| stream.write_all(&self.body))
+        {
+            println!("Failed to respond: {}", err);
+        }
+    }
+}
+
+/// Answers the requests to `127.0.0.1:port` with `handler`, one connection at a time. Requests
+/// that cannot be read are answered here, with 413 when the body is too long and 400 otherwise.
+pub fn serve<F: FnMut(&Request) -> Response>(
+    port: u16,
+    mut handler: F,
+) -> Result<(), std::io::Error> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                println!("Connection failed: {}", err);
+                continue;
+            }
+        };
+        let response = match Request::read(&stream) {
+            Ok(request) => handler(&request),
+            Err(Error::PayloadTooLarge) => {
+                Response::text("413 Payload Too Large", "Payload Too Large")
+            }
+            Err(Error::BadRequest) => Response::text("400 Bad Request", "Bad Request"),
+            Err(Error::Io(err)) => {
+                println!("Failed to read the request: {}", err);
+                continue;
+            }
+        };
+
+        response.write(&mut stream);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod http_test {
+    use super::{percent_decode, Error, Request, MAX_BODY_SIZE};
+
+    #[test]
+    fn read_check() {
+        let request = Request::read(
+            "PUT /watchlists/tech%20stocks?after=3&user=%E5%B0%8F HTTP/1.1\r\nHost: localhost\r\n\
+             Content-Length: 4\r\n\r\nbodyextra"
+                .as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(request.method, "PUT");
+        assert_eq!(
+            request.get_segments().unwrap(),
+            vec!["watchlists", "tech stocks"]
+        );
+        assert_eq!(request.get_param("after").as_deref(), Some("3"));
+        assert_eq!(request.get_param("user").as_deref(), Some("小"));
+        assert_eq!(request.get_param("before"), None);
+        assert_eq!(request.body, b"body");
+
+        let oversized = format!(
+            "PUT /watchlists/a HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_SIZE + 1
+        );
+
+        assert!(matches!(
+            Request::read(oversized.as_bytes()),
+            Err(Error::PayloadTooLarge)
+        ));
+        assert!(matches!(
+            Request::read("GET / HTTP/1.1\r\nContent-Length: 10\r\n\r\nshort".as_bytes()),
+            Err(Error::BadRequest)
+        ));
+        assert!(matches!(
+            Request::read("GET / HTTP/1.1\r\nHost: localhost".as_bytes()),
+            Err(Error::BadRequest)
+        ));
+        assert_eq!(percent_decode("%zz"), None);
+        assert_eq!(percent_decode("%FF"), None);
+        assert_eq!(percent_decode("a+b%2Fc").as_deref(), Some("a+b/c"));
+    }
+}
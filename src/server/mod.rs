@@ -0,0 +1,3 @@
+pub mod engine;
+pub mod graphql;
+pub mod http;
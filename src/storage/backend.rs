@@ -1,12 +1,22 @@
 use crate::strategy::schema;
 
+#[cfg(feature = "native")]
+use super::{calendar, watchlist};
+#[cfg(feature = "native")]
+use super::{journal, run};
+
+/// Key of the data generation, outside the `<stock_id>_<date>` keys of the records.
+pub const GENERATION_KEY: &str = "meta/generation";
+
 #[derive(Debug)]
 pub enum Error {
+    #[cfg(feature = "native")]
     Sled(sled::Error),
     Utf8(std::str::Utf8Error),
     Bincode(bincode::Error),
 }
 
+#[cfg(feature = "native")]
 impl From<sled::Error> for Error {
     fn from(err: sled::Error) -> Error {
         Error::Sled(err)
@@ -41,39 +51,82 @@ pub trait BackendOp {
     ) -> Result<Vec<schema::RawData>, Error>;
     fn query_all(&self, stock_id: &str) -> Result<Vec<schema::RawData>, Error>;
     fn batch_delete(&self, records: &Vec<(String, chrono::NaiveDate)>) -> Result<(), Error>;
+    /// Generation of the stored records, bumped by every insert or delete, so a run can tell
+    /// whether it saw the same data as another.
+    fn get_generation(&self) -> Result<u64, Error>;
 }
 
+#[cfg(feature = "native")]
+#[derive(Clone)]
 pub struct SledBackend {
     db_op: sled::Db,
 }
 
+#[cfg(feature = "native")]
 impl SledBackend {
     pub fn new(db_path: &str) -> Result<Self, Error> {
         Ok(SledBackend {
-            db_op: sled::open(db_path).unwrap(),
+            db_op: sled::open(db_path)?,
         })
     }
+
+    fn bump_generation(&self) -> Result<(), Error> {
+        self.db_op.update_and_fetch(GENERATION_KEY, |generation| {
+            let generation = generation
+                .and_then(|value| value.try_into().ok())
+                .map(u64::from_be_bytes)
+                .unwrap_or(0);
+
+            Some((generation + 1).to_be_bytes().to_vec())
+        })?;
+        Ok(())
+    }
+
+    /// Ids of the stocks with records, jumping over the records of each stock once it is found.
+    pub fn get_stock_ids(&self) -> Result<Vec<String>, Error> {
+        let mut stock_ids = Vec::new();
+        let mut start = Vec::new();
+
+        while let Some(item) = self.db_op.range(start.clone()..).next() {
+            let (key, _) = item?;
+            let key = std::str::from_utf8(&key)?;
+
+            match key.rsplit_once('_') {
+                Some((stock_id, date)) if date.parse::<chrono::NaiveDate>().is_ok() => {
+                    stock_ids.push(stock_id.to_owned());
+                    // '`' follows '_', so this is past every `<stock_id>_<date>` key.
+                    start = format!("{}`", stock_id).into_bytes();
+                }
+                _ => {
+                    start = key.as_bytes().to_vec();
+                    start.push(0);
+                }
+            }
+        }
+        Ok(stock_ids)
+    }
 }
 
+#[cfg(feature = "native")]
 impl BackendOp for SledBackend {
     fn batch_insert(&self, records: &Vec<(String, schema::RawData)>) -> Result<(), Error> {
         let mut batch = sled::Batch::default();
 
         for (stock_id, raw_data) in records {
-            let key = stock_id.clone() + "_" + &raw_data.date.to_string();
+            let key = stock_id.clone() + "_" + raw_data.date.to_string().as_str();
             let encoded = bincode::serialize(raw_data)?;
             batch.insert(&key[..], encoded);
         }
 
         self.db_op.apply_batch(batch)?;
-        Ok(())
+        self.bump_generation()
     }
     fn query(
         &self,
         stock_id: &str,
         date: chrono::NaiveDate,
     ) -> Result<Option<schema::RawData>, Error> {
-        let key = stock_id.to_owned() + "_" + &date.to_string();
+        let key = stock_id.to_owned() + "_" + date.to_string().as_str();
 
         match self.db_op.get(key)? {
             Some(val) => Ok(Some(bincode::deserialize(&val)?)),
@@ -86,8 +139,8 @@ impl BackendOp for SledBackend {
         start_date: chrono::NaiveDate,
         end_date: chrono::NaiveDate,
     ) -> Result<Vec<schema::RawData>, Error> {
-        let start = stock_id.to_owned() + "_" + &start_date.to_string();
-        let end = stock_id.to_owned() + "_" + &end_date.succ_opt().unwrap().to_string();
+        let start = stock_id.to_owned() + "_" + start_date.to_string().as_str();
+        let end = stock_id.to_owned() + "_" + end_date.succ_opt().unwrap().to_string().as_str();
         let mut iter = self.db_op.range(start..end);
         let mut records = Vec::new();
 
@@ -115,11 +168,178 @@ impl BackendOp for SledBackend {
         let mut batch = sled::Batch::default();
 
         for (stock_id, date) in records {
-            let key = stock_id.to_owned() + "_" + &date.to_string();
+            let key = stock_id.to_owned() + "_" + date.to_string().as_str();
             batch.remove(&key[..]);
         }
 
+        self.db_op.apply_batch(batch)?;
+        self.bump_generation()
+    }
+    fn get_generation(&self) -> Result<u64, Error> {
+        Ok(self
+            .db_op
+            .get(GENERATION_KEY)?
+            .and_then(|value| value.as_ref().try_into().ok())
+            .map(u64::from_be_bytes)
+            .unwrap_or(0))
+    }
+}
+
+#[cfg(feature = "native")]
+impl run::RunOp for SledBackend {
+    fn insert_run(&self, run: &run::Run) -> Result<(), Error> {
+        let encoded = bincode::serialize(run)?;
+
+        self.db_op.insert(run::get_run_key(&run.run_id), encoded)?;
+        if let Some(cache_key) = &run.manifest.cache_key {
+            self.db_op
+                .insert(run::get_run_cache_key(cache_key), run.run_id.as_bytes())?;
+        }
+        Ok(())
+    }
+    fn get_run(&self, run_id: &str) -> Result<Option<run::Run>, Error> {
+        match self.db_op.get(run::get_run_key(run_id))? {
+            Some(val) => Ok(Some(bincode::deserialize(&val)?)),
+            None => Ok(None),
+        }
+    }
+    fn list_runs(&self, tags: &[String]) -> Result<Vec<run::Run>, Error> {
+        let mut runs = Vec::new();
+
+        for item in self.db_op.scan_prefix(run::RUN_KEY_PREFIX) {
+            let (_, val) = item?;
+            let run: run::Run = bincode::deserialize(&val)?;
+
+            if run.has_tags(tags) {
+                runs.push(run);
+            }
+        }
+
+        Ok(runs)
+    }
+    fn find_cached_run(&self, cache_key: &str) -> Result<Option<run::Run>, Error> {
+        match self.db_op.get(run::get_run_cache_key(cache_key))? {
+            Some(val) => self.get_run(&String::from_utf8_lossy(&val)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl journal::JournalOp for SledBackend {
+    fn insert_entry(&self, entry: &journal::JournalEntry) -> Result<(), Error> {
+        let encoded = bincode::serialize(entry)?;
+
+        self.db_op
+            .insert(journal::get_journal_key(entry.seq), encoded)?;
+        Ok(())
+    }
+    fn get_entry(&self, seq: u64) -> Result<Option<journal::JournalEntry>, Error> {
+        match self.db_op.get(journal::get_journal_key(seq))? {
+            Some(val) => Ok(Some(bincode::deserialize(&val)?)),
+            None => Ok(None),
+        }
+    }
+    fn get_last_entry(&self) -> Result<Option<journal::JournalEntry>, Error> {
+        match self
+            .db_op
+            .scan_prefix(journal::JOURNAL_KEY_PREFIX)
+            .next_back()
+        {
+            Some(item) => Ok(Some(bincode::deserialize(&item?.1)?)),
+            None => Ok(None),
+        }
+    }
+    fn list_entries(&self, after_seq: u64) -> Result<Vec<journal::JournalEntry>, Error> {
+        let mut entries = Vec::new();
+        let end = journal::get_journal_key(u64::MAX);
+
+        for item in self
+            .db_op
+            .range(journal::get_journal_key(after_seq.saturating_add(1))..=end)
+        {
+            let (_, val) = item?;
+
+            entries.push(bincode::deserialize(&val)?);
+        }
+
+        Ok(entries)
+    }
+}
+
+#[cfg(feature = "native")]
+impl watchlist::WatchlistOp for SledBackend {
+    fn insert_watchlist(&self, watchlist: &watchlist::Watchlist) -> Result<(), Error> {
+        let encoded = bincode::serialize(watchlist)?;
+
+        self.db_op
+            .insert(watchlist::get_watchlist_key(&watchlist.name), encoded)?;
+        Ok(())
+    }
+    fn get_watchlist(&self, name: &str) -> Result<Option<watchlist::Watchlist>, Error> {
+        match self.db_op.get(watchlist::get_watchlist_key(name))? {
+            Some(val) => Ok(Some(bincode::deserialize(&val)?)),
+            None => Ok(None),
+        }
+    }
+    fn list_watchlists(&self) -> Result<Vec<watchlist::Watchlist>, Error> {
+        let mut watchlists = Vec::new();
+
+        for item in self.db_op.scan_prefix(watchlist::WATCHLIST_KEY_PREFIX) {
+            let (_, val) = item?;
+
+            watchlists.push(bincode::deserialize(&val)?);
+        }
+
+        Ok(watchlists)
+    }
+    fn delete_watchlist(&self, name: &str) -> Result<bool, Error> {
+        Ok(self
+            .db_op
+            .remove(watchlist::get_watchlist_key(name))?
+            .is_some())
+    }
+}
+
+#[cfg(feature = "native")]
+impl calendar::CalendarOp for SledBackend {
+    fn insert_sessions(&self, sessions: &[calendar::Session]) -> Result<(), Error> {
+        let mut batch = sled::Batch::default();
+
+        for session in sessions {
+            batch.insert(
+                calendar::get_session_key(session.date).as_bytes(),
+                bincode::serialize(session)?,
+            );
+        }
         self.db_op.apply_batch(batch)?;
         Ok(())
     }
+    fn delete_session(&self, date: chrono::NaiveDate) -> Result<bool, Error> {
+        Ok(self
+            .db_op
+            .remove(calendar::get_session_key(date))?
+            .is_some())
+    }
+    fn list_sessions(
+        &self,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> Result<Vec<calendar::Session>, Error> {
+        let mut sessions = Vec::new();
+
+        if start_date > end_date {
+            return Ok(sessions);
+        }
+        for item in self
+            .db_op
+            .range(calendar::get_session_key(start_date)..=calendar::get_session_key(end_date))
+        {
+            let (_, val) = item?;
+
+            sessions.push(bincode::deserialize(&val)?);
+        }
+
+        Ok(sessions)
+    }
 }
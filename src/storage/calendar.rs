@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+use super::backend;
+
+/// Calendar sessions share the backend with the price records; their keys live under this
+/// prefix, followed by the date.
+pub const CALENDAR_KEY_PREFIX: &str = "calendar/";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SessionKind {
+    /// Regular trading, e.g. a make-up Saturday.
+    Full,
+    /// Trading in a shortened session, e.g. Lunar New Year eve.
+    HalfDay,
+    /// Earlier trades are cleared but nothing trades, e.g. the days before Lunar New Year.
+    SettlementOnly,
+    /// No session, e.g. a holiday or a typhoon day.
+    Closed,
+}
+
+/// Day of the exchange departing from the regular week of full weekday sessions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub date: chrono::NaiveDate,
+    pub kind: SessionKind,
+    #[serde(default)]
+    pub note: String,
+}
+
+pub fn get_session_key(date: chrono::NaiveDate) -> String {
+    format!("{}{}", CALENDAR_KEY_PREFIX, date.format("%Y-%m-%d"))
+}
+
+#[mockall::automock]
+pub trait CalendarOp {
+    /// Stores `sessions`, replacing any session on the same dates.
+    fn insert_sessions(&self, sessions: &[Session]) -> Result<(), backend::Error>;
+    /// Deletes the session on `date`, returning whether there was one.
+    fn delete_session(&self, date: chrono::NaiveDate) -> Result<bool, backend::Error>;
+    /// Lists the sessions from `start_date` to `end_date`, oldest first.
+    fn list_sessions(
+        &self,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> Result<Vec<Session>, backend::Error>;
+}
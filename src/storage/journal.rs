@@ -0,0 +1,201 @@
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{clock, decision, order};
+
+use super::{backend, run};
+
+/// Journal entries share the backend with the price records; their keys live under this prefix,
+/// followed by the zero-padded sequence number so that they are stored in order.
+pub const JOURNAL_KEY_PREFIX: &str = "journal/";
+
+/// Something the live operation put out, as it was at the time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum JournalEvent {
+    /// Portfolio decided for a day.
+    Decision(decision::Portfolio),
+    OrderPlan(order::OrderPlan),
+    /// Message sent to a chat, with the path of the file sent along if any.
+    Notification {
+        chat_id: i64,
+        text: String,
+        file: Option<String>,
+    },
+}
+
+impl JournalEvent {
+    pub fn get_kind(&self) -> &'static str {
+        match self {
+            JournalEvent::Decision(_) => "decision",
+            JournalEvent::OrderPlan(_) => "order_plan",
+            JournalEvent::Notification { .. } => "notification",
+        }
+    }
+}
+
+/// Entry of the append-only journal. Each entry is chained to the previous one through its
+/// digest, so that an entry changed or removed afterwards shows in `verify`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub seq: u64,
+    pub recorded_at: chrono::NaiveDateTime,
+    pub profile: String,
+    pub event: JournalEvent,
+    pub digest: String,
+}
+
+impl JournalEntry {
+    fn get_digest(&self, prev_digest: &str) -> String {
+        let content = serde_json::to_vec(&(
+            prev_digest,
+            self.seq,
+            self.recorded_at,
+            &self.profile,
+            &self.event,
+        ))
+        .unwrap();
+
+        run::get_digest(&content)
+    }
+}
+
+pub fn get_journal_key(seq: u64) -> String {
+    format!("{}{:020}", JOURNAL_KEY_PREFIX, seq)
+}
+
+#[mockall::automock]
+pub trait JournalOp {
+    /// Stores `entry` after the last one; entries are never replaced nor deleted.
+    fn insert_entry(&self, entry: &JournalEntry) -> Result<(), backend::Error>;
+    fn get_entry(&self, seq: u64) -> Result<Option<JournalEntry>, backend::Error>;
+    fn get_last_entry(&self) -> Result<Option<JournalEntry>, backend::Error>;
+    /// Lists the entries after sequence number `after_seq`, oldest first.
+    fn list_entries(&self, after_seq: u64) -> Result<Vec<JournalEntry>, backend::Error>;
+}
+
+/// Appends `event` of `profile`, recorded at `recorded_at`, to the journal of `journal_op`.
+pub fn append(
+    journal_op: &dyn JournalOp,
+    profile: &str,
+    event: JournalEvent,
+    recorded_at: chrono::NaiveDateTime,
+) -> Result<JournalEntry, backend::Error> {
+    let last_entry = journal_op.get_last_entry()?;
+    let mut entry = JournalEntry {
+        seq: last_entry.as_ref().map_or(1, |entry| entry.seq + 1),
+        recorded_at,
+        profile: profile.to_owned(),
+        event,
+        digest: String::new(),
+    };
+
+    entry.digest = entry.get_digest(last_entry.as_ref().map_or("", |entry| &entry.digest));
+    journal_op.insert_entry(&entry)?;
+    Ok(entry)
+}
+
+/// Sequence number of the first entry of `entries`, the whole journal oldest first, that does
+/// not follow from the ones before it.
+pub fn verify(entries: &[JournalEntry]) -> Option<u64> {
+    let mut prev_digest = "";
+
+    for (index, entry) in entries.iter().enumerate() {
+        if entry.seq != index as u64 + 1 || entry.get_digest(prev_digest) != entry.digest {
+            return Some(entry.seq);
+        }
+        prev_digest = &entry.digest;
+    }
+    None
+}
+
+/// Journal of one profile.
+#[derive(Clone)]
+pub struct Journal {
+    journal_op: Rc<dyn JournalOp>,
+    profile: String,
+    /// Entries are recorded at its time.
+    pub clock: Rc<dyn clock::Clock>,
+}
+
+impl Journal {
+    pub fn new(journal_op: Rc<dyn JournalOp>, profile: &str) -> Self {
+        Journal {
+            journal_op,
+            profile: profile.to_owned(),
+            clock: Rc::new(clock::SystemClock),
+        }
+    }
+
+    pub fn record(&self, event: JournalEvent) -> Result<JournalEntry, backend::Error> {
+        append(
+            self.journal_op.as_ref(),
+            &self.profile,
+            event,
+            self.clock.now(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod journal_test {
+    use crate::core::order;
+    use crate::storage::memory::MemoryBackend;
+
+    use super::{append, verify, JournalEvent, JournalOp};
+
+    #[test]
+    fn journal_check() {
+        let backend = MemoryBackend::new();
+        let recorded_at = chrono::NaiveDate::from_ymd_opt(2024, 7, 1)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+        let order_plan = order::OrderPlan {
+            date: chrono::NaiveDate::from_ymd_opt(2024, 7, 1).unwrap(),
+            orders: Vec::new(),
+            liquidity: 200000,
+        };
+
+        append(
+            &backend,
+            "default",
+            JournalEvent::OrderPlan(order_plan),
+            recorded_at,
+        )
+        .unwrap();
+        for text in ["first", "second"] {
+            append(
+                &backend,
+                "alice",
+                JournalEvent::Notification {
+                    chat_id: 1,
+                    text: text.to_owned(),
+                    file: None,
+                },
+                recorded_at,
+            )
+            .unwrap();
+        }
+
+        let mut entries = backend.list_entries(0).unwrap();
+
+        assert_eq!(
+            entries.iter().map(|entry| entry.seq).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(entries[0].event.get_kind(), "order_plan");
+        assert_eq!(
+            backend.get_entry(3).unwrap().unwrap().digest,
+            entries[2].digest
+        );
+        assert!(backend.get_entry(4).unwrap().is_none());
+        assert_eq!(backend.list_entries(2).unwrap().len(), 1);
+        assert_eq!(verify(&entries), None);
+
+        entries[1].profile = "bob".to_owned();
+        assert_eq!(verify(&entries), Some(2));
+        entries.remove(1);
+        assert_eq!(verify(&entries), Some(3));
+    }
+}
@@ -0,0 +1,191 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::strategy::schema;
+
+use super::backend::{BackendOp, Error};
+use super::{calendar, watchlist};
+#[cfg(feature = "native")]
+use super::{journal, run};
+
+/// Backend keeping everything in memory, for tests and synthetic data.
+#[derive(Default)]
+pub struct MemoryBackend {
+    records: RefCell<HashMap<String, BTreeMap<chrono::NaiveDate, schema::RawData>>>,
+    #[cfg(feature = "native")]
+    runs: RefCell<BTreeMap<String, run::Run>>,
+    #[cfg(feature = "native")]
+    journal: RefCell<BTreeMap<u64, journal::JournalEntry>>,
+    watchlists: RefCell<BTreeMap<String, watchlist::Watchlist>>,
+    sessions: RefCell<BTreeMap<chrono::NaiveDate, calendar::Session>>,
+    generation: Cell<u64>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        MemoryBackend::default()
+    }
+}
+
+impl BackendOp for MemoryBackend {
+    fn batch_insert(&self, records: &Vec<(String, schema::RawData)>) -> Result<(), Error> {
+        let mut stored = self.records.borrow_mut();
+
+        for (stock_id, raw_data) in records {
+            stored
+                .entry(stock_id.to_owned())
+                .or_default()
+                .insert(raw_data.date, raw_data.clone());
+        }
+        self.generation.set(self.generation.get() + 1);
+        Ok(())
+    }
+    fn query(
+        &self,
+        stock_id: &str,
+        date: chrono::NaiveDate,
+    ) -> Result<Option<schema::RawData>, Error> {
+        Ok(self
+            .records
+            .borrow()
+            .get(stock_id)
+            .and_then(|records| records.get(&date).cloned()))
+    }
+    fn query_by_range(
+        &self,
+        stock_id: &str,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> Result<Vec<schema::RawData>, Error> {
+        if start_date > end_date {
+            return Ok(Vec::new());
+        }
+        Ok(match self.records.borrow().get(stock_id) {
+            Some(records) => records
+                .range(start_date..=end_date)
+                .map(|(_, raw_data)| raw_data.clone())
+                .collect(),
+            None => Vec::new(),
+        })
+    }
+    fn query_all(&self, stock_id: &str) -> Result<Vec<schema::RawData>, Error> {
+        Ok(match self.records.borrow().get(stock_id) {
+            Some(records) => records.values().cloned().collect(),
+            None => Vec::new(),
+        })
+    }
+    fn batch_delete(&self, records: &Vec<(String, chrono::NaiveDate)>) -> Result<(), Error> {
+        let mut stored = self.records.borrow_mut();
+
+        for (stock_id, date) in records {
+            if let Some(records) = stored.get_mut(stock_id) {
+                records.remove(date);
+            }
+        }
+        self.generation.set(self.generation.get() + 1);
+        Ok(())
+    }
+    fn get_generation(&self) -> Result<u64, Error> {
+        Ok(self.generation.get())
+    }
+}
+
+#[cfg(feature = "native")]
+impl run::RunOp for MemoryBackend {
+    fn insert_run(&self, run: &run::Run) -> Result<(), Error> {
+        self.runs
+            .borrow_mut()
+            .insert(run.run_id.to_owned(), run.clone());
+        Ok(())
+    }
+    fn get_run(&self, run_id: &str) -> Result<Option<run::Run>, Error> {
+        Ok(self.runs.borrow().get(run_id).cloned())
+    }
+    fn list_runs(&self, tags: &[String]) -> Result<Vec<run::Run>, Error> {
+        Ok(self
+            .runs
+            .borrow()
+            .values()
+            .filter(|run| run.has_tags(tags))
+            .cloned()
+            .collect())
+    }
+    fn find_cached_run(&self, cache_key: &str) -> Result<Option<run::Run>, Error> {
+        Ok(self
+            .runs
+            .borrow()
+            .values()
+            .filter(|run| run.manifest.cache_key.as_deref() == Some(cache_key))
+            .max_by_key(|run| run.manifest.created_at)
+            .cloned())
+    }
+}
+
+#[cfg(feature = "native")]
+impl journal::JournalOp for MemoryBackend {
+    fn insert_entry(&self, entry: &journal::JournalEntry) -> Result<(), Error> {
+        self.journal.borrow_mut().insert(entry.seq, entry.clone());
+        Ok(())
+    }
+    fn get_entry(&self, seq: u64) -> Result<Option<journal::JournalEntry>, Error> {
+        Ok(self.journal.borrow().get(&seq).cloned())
+    }
+    fn get_last_entry(&self) -> Result<Option<journal::JournalEntry>, Error> {
+        Ok(self.journal.borrow().values().next_back().cloned())
+    }
+    fn list_entries(&self, after_seq: u64) -> Result<Vec<journal::JournalEntry>, Error> {
+        Ok(self
+            .journal
+            .borrow()
+            .range(after_seq.saturating_add(1)..)
+            .map(|(_, entry)| entry.clone())
+            .collect())
+    }
+}
+
+impl watchlist::WatchlistOp for MemoryBackend {
+    fn insert_watchlist(&self, watchlist: &watchlist::Watchlist) -> Result<(), Error> {
+        self.watchlists
+            .borrow_mut()
+            .insert(watchlist.name.to_owned(), watchlist.clone());
+        Ok(())
+    }
+    fn get_watchlist(&self, name: &str) -> Result<Option<watchlist::Watchlist>, Error> {
+        Ok(self.watchlists.borrow().get(name).cloned())
+    }
+    fn list_watchlists(&self) -> Result<Vec<watchlist::Watchlist>, Error> {
+        Ok(self.watchlists.borrow().values().cloned().collect())
+    }
+    fn delete_watchlist(&self, name: &str) -> Result<bool, Error> {
+        Ok(self.watchlists.borrow_mut().remove(name).is_some())
+    }
+}
+
+impl calendar::CalendarOp for MemoryBackend {
+    fn insert_sessions(&self, sessions: &[calendar::Session]) -> Result<(), Error> {
+        let mut stored = self.sessions.borrow_mut();
+
+        for session in sessions {
+            stored.insert(session.date, session.clone());
+        }
+        Ok(())
+    }
+    fn delete_session(&self, date: chrono::NaiveDate) -> Result<bool, Error> {
+        Ok(self.sessions.borrow_mut().remove(&date).is_some())
+    }
+    fn list_sessions(
+        &self,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> Result<Vec<calendar::Session>, Error> {
+        if start_date > end_date {
+            return Ok(Vec::new());
+        }
+        Ok(self
+            .sessions
+            .borrow()
+            .range(start_date..=end_date)
+            .map(|(_, session)| session.clone())
+            .collect())
+    }
+}
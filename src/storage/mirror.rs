@@ -0,0 +1,446 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::strategy::schema;
+
+use super::backend;
+
+/// What was last mirrored is kept in the portfolio path.
+pub const MIRROR_STATE_FILENAME: &str = "mirror_state.json";
+/// Object listing every mirrored object and its hash, uploaded after them, so another
+/// environment can pull what changed without listing the bucket.
+pub const MIRROR_INDEX_KEY: &str = "index.json";
+const PRICES_KEY_PREFIX: &str = "prices/";
+
+#[derive(Debug)]
+pub enum Error {
+    Backend(backend::Error),
+    Reqwest(reqwest::Error),
+    Url(url::ParseError),
+    Json(serde_json::Error),
+    Io(std::io::Error),
+    /// The object store answered with an unexpected status.
+    Status(u16, String),
+    MissingObject(String),
+    Unsupported(String),
+}
+
+impl From<backend::Error> for Error {
+    fn from(err: backend::Error) -> Error {
+        Error::Backend(err)
+    }
+}
+
+impl From<bincode::Error> for Error {
+    fn from(err: bincode::Error) -> Error {
+        Error::Backend(backend::Error::Bincode(err))
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Error {
+        Error::Reqwest(err)
+    }
+}
+
+impl From<url::ParseError> for Error {
+    fn from(err: url::ParseError) -> Error {
+        Error::Url(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::Json(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+/// Bucket of an S3-compatible object store the backend is mirrored to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirrorConfig {
+    /// E.g. `https://s3.ap-northeast-1.amazonaws.com`, or the URL of a MinIO server.
+    pub endpoint: String,
+    pub bucket: String,
+    #[serde(default = "default_region")]
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+    /// Prepended to every object key, so several environments can share a bucket.
+    #[serde(default)]
+    pub prefix: String,
+}
+
+fn default_region() -> String {
+    "us-east-1".to_owned()
+}
+
+#[mockall::automock]
+pub trait ObjectStore {
+    fn put_object(&self, key: &str, body: &[u8]) -> Result<(), Error>;
+    /// Body of the object, or `None` when there is no such object.
+    fn get_object(&self, key: &str) -> Result<Option<Vec<u8>>, Error>;
+}
+
+/// Hash of each object last pushed or pulled, and the backend generation it was in sync with.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MirrorState {
+    pub generation: Option<u64>,
+    pub objects: BTreeMap<String, String>,
+}
+
+impl MirrorState {
+    /// State stored at `path`; empty when there is no such file yet.
+    pub fn load(path: &str) -> Result<Self, Error> {
+        match std::fs::read_to_string(path) {
+            Ok(data) => Ok(serde_json::from_str(&data)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(MirrorState::default()),
+            Err(err) => Err(Error::Io(err)),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Error> {
+        let data = serde_json::to_string(self)?;
+
+        Ok(std::fs::write(path, data)?)
+    }
+}
+
+fn get_prices_key(stock_id: &str, year: i32) -> String {
+    format!("{}{}/{}.bin", PRICES_KEY_PREFIX, stock_id, year)
+}
+
+fn get_stock_id(key: &str) -> Option<&str> {
+    key.strip_prefix(PRICES_KEY_PREFIX)?
+        .split_once('/')
+        .map(|(stock_id, _)| stock_id)
+}
+
+/// Mirrors the price records of a backend to an object store, one object per stock and year,
+/// so a daily update only uploads the current year of the stocks it touched. One environment
+/// pushes and the others pull: a push indexes only what its own state knows of.
+pub struct Mirror<'a> {
+    pub store: &'a dyn ObjectStore,
+}
+
+impl<'a> Mirror<'a> {
+    pub fn new(store: &'a dyn ObjectStore) -> Self {
+        Mirror { store }
+    }
+
+    /// Uploads the objects of `stock_ids` that changed since `state`, then the index, and
+    /// returns how many were uploaded. Nothing is read when the backend generation is the one
+    /// of `state`.
+    pub fn push(
+        &self,
+        backend_op: &dyn backend::BackendOp,
+        stock_ids: &[String],
+        state: &mut MirrorState,
+    ) -> Result<usize, Error> {
+        let generation = backend_op.get_generation()?;
+        let mut uploaded = 0;
+
+        if state.generation == Some(generation) {
+            return Ok(0);
+        }
+        for stock_id in stock_ids {
+            let mut years: BTreeMap<i32, Vec<schema::RawData>> = BTreeMap::new();
+
+            for record in backend_op.query_all(stock_id)? {
+                years
+                    .entry(chrono::Datelike::year(&record.date))
+                    .or_default()
+                    .push(record);
+            }
+            for (year, records) in years {
+                let key = get_prices_key(stock_id, year);
+                let body = bincode::serialize(&records)?;
+                let hash = sha256_hex(&body)?;
+
+                if state.objects.get(&key) == Some(&hash) {
+                    continue;
+                }
+                self.store.put_object(&key, &body)?;
+                state.objects.insert(key, hash);
+                uploaded += 1;
+            }
+        }
+        if uploaded > 0 {
+            self.store
+                .put_object(MIRROR_INDEX_KEY, &serde_json::to_vec(&state.objects)?)?;
+        }
+        state.generation = Some(generation);
+        Ok(uploaded)
+    }
+
+    /// Downloads the objects of the index that differ from `state` into the backend, and
+    /// returns how many were downloaded.
+    pub fn pull(
+        &self,
+        backend_op: &dyn backend::BackendOp,
+        state: &mut MirrorState,
+    ) -> Result<usize, Error> {
+        let objects: BTreeMap<String, String> = match self.store.get_object(MIRROR_INDEX_KEY)? {
+            Some(body) => serde_json::from_slice(&body)?,
+            None => return Ok(0),
+        };
+        let mut downloaded = 0;
+
+        for (key, hash) in objects {
+            let stock_id = match get_stock_id(&key) {
+                Some(stock_id) => stock_id.to_owned(),
+                None => continue,
+            };
+
+            if state.objects.get(&key) == Some(&hash) {
+                continue;
+            }
+
+            let body = self
+                .store
+                .get_object(&key)?
+                .ok_or_else(|| Error::MissingObject(key.to_owned()))?;
+            let records: Vec<schema::RawData> = bincode::deserialize(&body)?;
+
+            backend_op.batch_insert(
+                &records
+                    .into_iter()
+                    .map(|record| (stock_id.to_owned(), record))
+                    .collect(),
+            )?;
+            state.objects.insert(key, hash);
+            downloaded += 1;
+        }
+        state.generation = Some(backend_op.get_generation()?);
+        Ok(downloaded)
+    }
+}
+
+/// Object store speaking the S3 API, with path-style URLs and requests signed with AWS
+/// Signature Version 4, which MinIO, R2 and most other S3-compatible stores accept too.
+pub struct S3Store {
+    pub config: MirrorConfig,
+    client: reqwest::blocking::Client,
+}
+
+impl S3Store {
+    pub fn new(config: &MirrorConfig) -> Self {
+        S3Store {
+            config: config.clone(),
+            client: reqwest::blocking::Client::new(),
+        }
+    }
+
+    fn send(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+        body: Vec<u8>,
+    ) -> Result<reqwest::blocking::Response, Error> {
+        let path = format!(
+            "/{}/{}{}",
+            self.config.bucket,
+            encode_path(&self.config.prefix),
+            encode_path(key)
+        );
+        let url = reqwest::Url::parse(&self.config.endpoint)?.join(&path)?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_owned(),
+            (None, _) => return Err(Error::Url(url::ParseError::EmptyHost)),
+        };
+        let now = chrono::Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = sha256_hex(&body)?;
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method.as_str(),
+            path,
+            host,
+            payload_hash,
+            amz_date,
+            signed_headers,
+            payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.config.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            sha256_hex(canonical_request.as_bytes())?
+        );
+        let mut signing_key = format!("AWS4{}", self.config.secret_key).into_bytes();
+
+        for part in [
+            date.as_str(),
+            self.config.region.as_str(),
+            "s3",
+            "aws4_request",
+        ] {
+            signing_key = hmac_sha256(&signing_key, part.as_bytes())?;
+        }
+
+        let signature = to_hex(&hmac_sha256(&signing_key, string_to_sign.as_bytes())?);
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.config.access_key, scope, signed_headers, signature
+        );
+
+        Ok(self
+            .client
+            .request(method, url)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", amz_date)
+            .header("authorization", authorization)
+            .body(body)
+            .send()?)
+    }
+}
+
+impl ObjectStore for S3Store {
+    fn put_object(&self, key: &str, body: &[u8]) -> Result<(), Error> {
+        let resp = self.send(reqwest::Method::PUT, key, body.to_vec())?;
+
+        match resp.status().as_u16() {
+            200 => Ok(()),
+            status => Err(Error::Status(status, resp.text().unwrap_or_default())),
+        }
+    }
+
+    fn get_object(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        let resp = self.send(reqwest::Method::GET, key, Vec::new())?;
+
+        match resp.status().as_u16() {
+            200 => Ok(Some(resp.bytes()?.to_vec())),
+            404 => Ok(None),
+            status => Err(Error::Status(status, resp.text().unwrap_or_default())),
+        }
+    }
+}
+
+/// Percent-encodes a key as S3 expects in the canonical request, keeping the slashes.
+fn encode_path(key: &str) -> String {
+    key.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            byte => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(feature = "mirror")]
+fn sha256_hex(data: &[u8]) -> Result<String, Error> {
+    use sha2::Digest;
+
+    Ok(to_hex(&sha2::Sha256::digest(data)))
+}
+
+#[cfg(not(feature = "mirror"))]
+fn sha256_hex(_data: &[u8]) -> Result<String, Error> {
+    Err(Error::Unsupported(
+        "built without the mirror feature".to_owned(),
+    ))
+}
+
+#[cfg(feature = "mirror")]
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Result<Vec<u8>, Error> {
+    use hmac::Mac;
+
+    let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(key)
+        .map_err(|err| Error::Unsupported(err.to_string()))?;
+
+    mac.update(data);
+    Ok(mac.finalize().into_bytes().to_vec())
+}
+
+#[cfg(not(feature = "mirror"))]
+fn hmac_sha256(_key: &[u8], _data: &[u8]) -> Result<Vec<u8>, Error> {
+    Err(Error::Unsupported(
+        "built without the mirror feature".to_owned(),
+    ))
+}
+
+#[cfg(all(test, feature = "mirror"))]
+mod mirror_test {
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+
+    use crate::storage::backend::BackendOp;
+    use crate::storage::memory::MemoryBackend;
+    use crate::strategy::schema;
+
+    use super::{Error, Mirror, MirrorState, ObjectStore, MIRROR_INDEX_KEY};
+
+    #[derive(Default)]
+    struct MemoryStore {
+        objects: RefCell<BTreeMap<String, Vec<u8>>>,
+        puts: RefCell<usize>,
+    }
+
+    impl ObjectStore for MemoryStore {
+        fn put_object(&self, key: &str, body: &[u8]) -> Result<(), Error> {
+            *self.puts.borrow_mut() += 1;
+            self.objects
+                .borrow_mut()
+                .insert(key.to_owned(), body.to_vec());
+            Ok(())
+        }
+
+        fn get_object(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+            Ok(self.objects.borrow().get(key).cloned())
+        }
+    }
+
+    #[test]
+    fn mirror_check() {
+        let get_record = |year, month| schema::RawData {
+            date: chrono::NaiveDate::from_ymd_opt(year, month, 1).unwrap(),
+            close: 10.0,
+            ..Default::default()
+        };
+        let store = MemoryStore::default();
+        let mirror = Mirror::new(&store);
+        let backend_op = MemoryBackend::new();
+        let stock_ids = vec!["0050".to_owned()];
+        let mut state = MirrorState::default();
+
+        backend_op
+            .batch_insert(&vec![
+                ("0050".to_owned(), get_record(2020, 1)),
+                ("0050".to_owned(), get_record(2021, 1)),
+            ])
+            .unwrap();
+        assert_eq!(mirror.push(&backend_op, &stock_ids, &mut state).unwrap(), 2);
+        assert!(store.objects.borrow().contains_key(MIRROR_INDEX_KEY));
+        // Unchanged data uploads nothing.
+        assert_eq!(mirror.push(&backend_op, &stock_ids, &mut state).unwrap(), 0);
+
+        // Only the year that changed is uploaded again.
+        backend_op
+            .batch_insert(&vec![("0050".to_owned(), get_record(2021, 2))])
+            .unwrap();
+        assert_eq!(mirror.push(&backend_op, &stock_ids, &mut state).unwrap(), 1);
+        assert_eq!(*store.puts.borrow(), 5);
+
+        let other_backend_op = MemoryBackend::new();
+        let mut other_state = MirrorState::default();
+
+        assert_eq!(mirror.pull(&other_backend_op, &mut other_state).unwrap(), 2);
+        assert_eq!(other_backend_op.query_all("0050").unwrap().len(), 3);
+        assert_eq!(mirror.pull(&other_backend_op, &mut other_state).unwrap(), 0);
+    }
+}
@@ -1,2 +1,12 @@
 pub mod backend;
-
+pub mod calendar;
+#[cfg(feature = "native")]
+pub mod journal;
+pub mod memory;
+#[cfg(feature = "native")]
+pub mod mirror;
+#[cfg(feature = "native")]
+pub mod namespace;
+#[cfg(feature = "native")]
+pub mod run;
+pub mod watchlist;
@@ -0,0 +1,197 @@
+use std::rc::Rc;
+
+use super::{backend, run, watchlist};
+
+/// Separates the namespace from the name of a watchlist or the id of a run.
+pub const NAMESPACE_SEPARATOR: char = '/';
+
+/// Watchlists and runs of one profile over a backend shared with other profiles. Names are
+/// stored prefixed with `<namespace>/`; without a namespace, only the unprefixed ones are seen,
+/// which keeps the state written before there were profiles where it was.
+pub struct Namespaced<B> {
+    backend_op: Rc<B>,
+    namespace: Option<String>,
+}
+
+impl<B> Namespaced<B> {
+    pub fn new(backend_op: Rc<B>, namespace: Option<&str>) -> Self {
+        Namespaced {
+            backend_op,
+            namespace: namespace.map(|namespace| namespace.to_owned()),
+        }
+    }
+
+    fn get_name(&self, name: &str) -> String {
+        match &self.namespace {
+            Some(namespace) => format!("{}{}{}", namespace, NAMESPACE_SEPARATOR, name),
+            None => name.to_owned(),
+        }
+    }
+
+    /// `name` without the namespace, if it is in the namespace.
+    fn strip_name(&self, name: &str) -> Option<String> {
+        match &self.namespace {
+            Some(namespace) => name
+                .strip_prefix(namespace.as_str())
+                .and_then(|name| name.strip_prefix(NAMESPACE_SEPARATOR))
+                .map(|name| name.to_owned()),
+            None if name.contains(NAMESPACE_SEPARATOR) => None,
+            None => Some(name.to_owned()),
+        }
+    }
+
+    fn strip_run(&self, mut run: run::Run) -> Option<run::Run> {
+        run.run_id = self.strip_name(&run.run_id)?;
+        run.manifest.cache_key = match &run.manifest.cache_key {
+            Some(cache_key) => Some(self.strip_name(cache_key)?),
+            None => None,
+        };
+        Some(run)
+    }
+}
+
+impl<B: watchlist::WatchlistOp> watchlist::WatchlistOp for Namespaced<B> {
+    fn insert_watchlist(&self, watchlist: &watchlist::Watchlist) -> Result<(), backend::Error> {
+        self.backend_op.insert_watchlist(&watchlist::Watchlist {
+            name: self.get_name(&watchlist.name),
+            ..watchlist.clone()
+        })
+    }
+    fn get_watchlist(&self, name: &str) -> Result<Option<watchlist::Watchlist>, backend::Error> {
+        Ok(self
+            .backend_op
+            .get_watchlist(&self.get_name(name))?
+            .map(|watchlist| watchlist::Watchlist {
+                name: name.to_owned(),
+                ..watchlist
+            }))
+    }
+    fn list_watchlists(&self) -> Result<Vec<watchlist::Watchlist>, backend::Error> {
+        Ok(self
+            .backend_op
+            .list_watchlists()?
+            .into_iter()
+            .filter_map(|watchlist| {
+                Some(watchlist::Watchlist {
+                    name: self.strip_name(&watchlist.name)?,
+                    ..watchlist
+                })
+            })
+            .collect())
+    }
+    fn delete_watchlist(&self, name: &str) -> Result<bool, backend::Error> {
+        self.backend_op.delete_watchlist(&self.get_name(name))
+    }
+}
+
+impl<B: run::RunOp> run::RunOp for Namespaced<B> {
+    fn insert_run(&self, run: &run::Run) -> Result<(), backend::Error> {
+        let mut run = run.clone();
+
+        run.run_id = self.get_name(&run.run_id);
+        run.manifest.cache_key = run
+            .manifest
+            .cache_key
+            .map(|cache_key| self.get_name(&cache_key));
+        self.backend_op.insert_run(&run)
+    }
+    fn get_run(&self, run_id: &str) -> Result<Option<run::Run>, backend::Error> {
+        Ok(self
+            .backend_op
+            .get_run(&self.get_name(run_id))?
+            .and_then(|run| self.strip_run(run)))
+    }
+    fn list_runs(&self, tags: &[String]) -> Result<Vec<run::Run>, backend::Error> {
+        Ok(self
+            .backend_op
+            .list_runs(tags)?
+            .into_iter()
+            .filter_map(|run| self.strip_run(run))
+            .collect())
+    }
+    fn find_cached_run(&self, cache_key: &str) -> Result<Option<run::Run>, backend::Error> {
+        Ok(self
+            .backend_op
+            .find_cached_run(&self.get_name(cache_key))?
+            .and_then(|run| self.strip_run(run)))
+    }
+}
+
+#[cfg(test)]
+mod namespace_test {
+    use std::rc::Rc;
+
+    use crate::storage::memory::MemoryBackend;
+    use crate::storage::run::{Run, RunManifest, RunMetrics, RunOp};
+    use crate::storage::watchlist::{Watchlist, WatchlistOp};
+    use crate::strategy::strategy;
+
+    use super::Namespaced;
+
+    #[test]
+    fn namespaced_check() {
+        let backend = Rc::new(MemoryBackend::new());
+        let default = Namespaced::new(backend.clone(), None);
+        let alice = Namespaced::new(backend.clone(), Some("alice"));
+        let bob = Namespaced::new(backend.clone(), Some("bob"));
+        let mut semis = Watchlist::new("semis");
+
+        semis.add(&["2330".to_owned()]);
+        default.insert_watchlist(&semis).unwrap();
+        semis.add(&["2454".to_owned()]);
+        alice.insert_watchlist(&semis).unwrap();
+
+        assert_eq!(
+            default.get_watchlist("semis").unwrap().unwrap().stock_ids,
+            vec!["2330"]
+        );
+        assert_eq!(alice.get_watchlist("semis").unwrap(), Some(semis));
+        assert!(bob.get_watchlist("semis").unwrap().is_none());
+        assert_eq!(default.list_watchlists().unwrap().len(), 1);
+        assert_eq!(backend.list_watchlists().unwrap().len(), 2);
+        assert!(!bob.delete_watchlist("semis").unwrap());
+        assert!(alice.delete_watchlist("semis").unwrap());
+        assert!(default.get_watchlist("semis").unwrap().is_some());
+
+        let run = Run {
+            run_id: "20240701000000000".to_owned(),
+            manifest: RunManifest {
+                strategy: strategy::Strategies::BollingerBand,
+                start_date: chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+                end_date: chrono::NaiveDate::from_ymd_opt(2024, 6, 30).unwrap(),
+                effective_start_date: None,
+                data_generation: None,
+                liquidity: 200000,
+                stocks_hold_num: 5,
+                benchmark_id: None,
+                created_at: chrono::NaiveDate::from_ymd_opt(2024, 7, 1)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap(),
+                cache_key: Some("key".to_owned()),
+                parent_run_id: None,
+            },
+            metrics: RunMetrics::default(),
+            trades: Vec::new(),
+            tags: Vec::new(),
+            note: String::new(),
+            end_state: None,
+        };
+
+        alice.insert_run(&run).unwrap();
+        assert_eq!(
+            alice.find_cached_run("key").unwrap().unwrap().run_id,
+            run.run_id
+        );
+        assert!(bob.find_cached_run("key").unwrap().is_none());
+        assert!(default.get_run(&run.run_id).unwrap().is_none());
+        assert!(default.list_runs(&[]).unwrap().is_empty());
+        assert_eq!(
+            alice.list_runs(&[]).unwrap()[0]
+                .manifest
+                .cache_key
+                .as_deref(),
+            Some("key")
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::{backtesting, decision};
+use crate::strategy::strategy;
+
+use super::backend;
+
+/// Runs share the backend with the price records; their keys live under this prefix.
+pub const RUN_KEY_PREFIX: &str = "runs/";
+/// Index from the cache key of a run to its id.
+pub const RUN_CACHE_KEY_PREFIX: &str = "run_cache/";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RunManifest {
+    pub strategy: strategy::Strategies,
+    pub start_date: chrono::NaiveDate,
+    pub end_date: chrono::NaiveDate,
+    /// First day the strategy was scored on, once its warm-up history was available.
+    #[serde(default)]
+    pub effective_start_date: Option<chrono::NaiveDate>,
+    /// Backend generation the run read its data at.
+    #[serde(default)]
+    pub data_generation: Option<u64>,
+    pub liquidity: u32,
+    pub stocks_hold_num: usize,
+    pub benchmark_id: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    /// Digest of every input of the run, see `backtesting::Backtesting::get_cache_key`; runs
+    /// stopped early have none.
+    #[serde(default)]
+    pub cache_key: Option<String>,
+    /// Run whose end state the run started from, if it continued one.
+    #[serde(default)]
+    pub parent_run_id: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct RunMetrics {
+    pub final_equity: u32,
+    /// Return over the run, in percent, external cash flows included.
+    pub total_return: f64,
+    /// Time-weighted return over the run, in percent, which leaves out external cash flows.
+    #[serde(default)]
+    pub time_weighted_return: f64,
+    /// Deposits less withdrawals over the run.
+    #[serde(default)]
+    pub net_cash_flow: i64,
+    /// Maximum drawdown over the run, in percent.
+    pub max_drawdown: f64,
+    pub trade_count: usize,
+    /// Share of trades settled above their hold price, in percent.
+    pub win_rate: f64,
+    /// Fills deferred because the bar was locked at the price limit.
+    #[serde(default)]
+    pub deferred_fills: usize,
+    /// Entries skipped for not fitting the order size constraints.
+    #[serde(default)]
+    pub skipped_orders: usize,
+    /// Orders the volume cap kept from filling in full on the day.
+    #[serde(default)]
+    pub partial_fills: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Run {
+    pub run_id: String,
+    pub manifest: RunManifest,
+    pub metrics: RunMetrics,
+    pub trades: Vec<backtesting::TradeRecord>,
+    pub tags: Vec<String>,
+    pub note: String,
+    /// Holdings and liquidity the run ended with, for a following run to start from.
+    #[serde(default)]
+    pub end_state: Option<decision::EndState>,
+}
+
+impl Run {
+    pub fn has_tags(&self, tags: &[String]) -> bool {
+        tags.iter().all(|tag| self.tags.contains(tag))
+    }
+}
+
+pub fn get_run_key(run_id: &str) -> String {
+    RUN_KEY_PREFIX.to_owned() + run_id
+}
+
+pub fn get_run_cache_key(cache_key: &str) -> String {
+    RUN_CACHE_KEY_PREFIX.to_owned() + cache_key
+}
+
+/// 64-bit FNV-1a digest of `bytes` in hex. Unlike `DefaultHasher`, it is the same across builds,
+/// so stored digests stay valid.
+pub fn get_digest(bytes: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+#[mockall::automock]
+pub trait RunOp {
+    /// Stores `run`, replacing any run with the same id.
+    fn insert_run(&self, run: &Run) -> Result<(), backend::Error>;
+    fn get_run(&self, run_id: &str) -> Result<Option<Run>, backend::Error>;
+    /// Lists the stored runs carrying all of `tags`, oldest first.
+    fn list_runs(&self, tags: &[String]) -> Result<Vec<Run>, backend::Error>;
+    /// Latest run recorded with `cache_key`.
+    fn find_cached_run(&self, cache_key: &str) -> Result<Option<Run>, backend::Error>;
+}
+
+#[cfg(test)]
+mod run_test {
+    use crate::storage::memory::MemoryBackend;
+    use crate::strategy::strategy;
+
+    use super::{get_digest, Run, RunManifest, RunMetrics, RunOp};
+
+    #[test]
+    fn find_cached_run_check() {
+        assert_eq!(get_digest(b""), "cbf29ce484222325");
+        assert_eq!(get_digest(b"a"), "af63dc4c8601ec8c");
+
+        let backend = MemoryBackend::new();
+        let get_run = |run_id: &str, cache_key: Option<&str>, second: u32| Run {
+            run_id: run_id.to_owned(),
+            manifest: RunManifest {
+                strategy: strategy::Strategies::BollingerBand,
+                start_date: chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+                end_date: chrono::NaiveDate::from_ymd_opt(2024, 6, 30).unwrap(),
+                effective_start_date: None,
+                data_generation: Some(3),
+                liquidity: 200000,
+                stocks_hold_num: 5,
+                benchmark_id: None,
+                created_at: chrono::NaiveDate::from_ymd_opt(2024, 7, 1)
+                    .unwrap()
+                    .and_hms_opt(0, 0, second)
+                    .unwrap(),
+                cache_key: cache_key.map(|cache_key| cache_key.to_owned()),
+                parent_run_id: None,
+            },
+            metrics: RunMetrics::default(),
+            trades: Vec::new(),
+            tags: Vec::new(),
+            note: "".to_owned(),
+            end_state: None,
+        };
+
+        backend.insert_run(&get_run("1", Some("key"), 1)).unwrap();
+        backend.insert_run(&get_run("2", Some("key"), 2)).unwrap();
+        backend.insert_run(&get_run("3", None, 3)).unwrap();
+
+        assert_eq!(backend.find_cached_run("key").unwrap().unwrap().run_id, "2");
+        assert!(backend.find_cached_run("other").unwrap().is_none());
+    }
+}
@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+use super::backend;
+
+/// Watchlists share the backend with the price records; their keys live under this prefix.
+pub const WATCHLIST_KEY_PREFIX: &str = "watchlists/";
+
+/// Named list of stocks to screen or backtest on instead of the whole stock list.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Watchlist {
+    pub name: String,
+    pub stock_ids: Vec<String>,
+    #[serde(default)]
+    pub note: String,
+}
+
+impl Watchlist {
+    pub fn new(name: &str) -> Self {
+        Watchlist {
+            name: name.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// Appends the stocks of `stock_ids` not on the list yet, keeping their order.
+    pub fn add(&mut self, stock_ids: &[String]) {
+        for stock_id in stock_ids {
+            if !self.stock_ids.contains(stock_id) {
+                self.stock_ids.push(stock_id.to_owned());
+            }
+        }
+    }
+
+    pub fn remove(&mut self, stock_ids: &[String]) {
+        self.stock_ids
+            .retain(|stock_id| !stock_ids.contains(stock_id));
+    }
+}
+
+pub fn get_watchlist_key(name: &str) -> String {
+    WATCHLIST_KEY_PREFIX.to_owned() + name
+}
+
+#[mockall::automock]
+pub trait WatchlistOp {
+    /// Stores `watchlist`, replacing any watchlist with the same name.
+    fn insert_watchlist(&self, watchlist: &Watchlist) -> Result<(), backend::Error>;
+    fn get_watchlist(&self, name: &str) -> Result<Option<Watchlist>, backend::Error>;
+    /// Lists the stored watchlists by name.
+    fn list_watchlists(&self) -> Result<Vec<Watchlist>, backend::Error>;
+    /// Deletes the watchlist named `name`, returning whether there was one.
+    fn delete_watchlist(&self, name: &str) -> Result<bool, backend::Error>;
+}
+
+#[cfg(test)]
+mod watchlist_test {
+    use crate::storage::memory::MemoryBackend;
+
+    use super::{Watchlist, WatchlistOp};
+
+    #[test]
+    fn watchlist_op_check() {
+        let backend = MemoryBackend::new();
+        let mut semis = Watchlist::new("semis");
+
+        semis.add(&["2330".to_owned(), "2454".to_owned(), "2330".to_owned()]);
+        assert_eq!(semis.stock_ids, vec!["2330", "2454"]);
+        backend.insert_watchlist(&semis).unwrap();
+        backend.insert_watchlist(&Watchlist::new("banks")).unwrap();
+
+        semis.remove(&["2454".to_owned()]);
+        semis.add(&["3711".to_owned()]);
+        backend.insert_watchlist(&semis).unwrap();
+
+        assert_eq!(backend.get_watchlist("semis").unwrap(), Some(semis));
+        assert_eq!(
+            backend
+                .list_watchlists()
+                .unwrap()
+                .iter()
+                .map(|watchlist| watchlist.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["banks", "semis"]
+        );
+        assert!(backend.delete_watchlist("banks").unwrap());
+        assert!(!backend.delete_watchlist("banks").unwrap());
+        assert!(backend.get_watchlist("banks").unwrap().is_none());
+    }
+}
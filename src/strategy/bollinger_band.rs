@@ -1,18 +1,91 @@
 use std::rc::Rc;
 
-use crate::dataview::view::{self, Transform};
+use serde::{Deserialize, Serialize};
+
+use crate::dataview::{adjust, view};
+use crate::diagram::diagram;
+use crate::export::export;
 use crate::storage::backend;
-use crate::strategy::strategy;
+use crate::strategy::{schema, strategy};
 
+/// Default of `BandParams::period`.
 pub const PERIOD: usize = 30;
+/// Default of `BandParams::analyze_range`.
 pub const ANALYZE_RANGE: usize = 8;
-pub const BAND_SIZE: usize = 2;
+/// Default of `BandParams::band_size`.
+pub const BAND_SIZE: f64 = 2.0;
+
+/// How the bands are drawn and which part of them is a buy zone.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BandParams {
+    /// Trading days of the moving average the bands are drawn around.
+    #[serde(default = "default_period")]
+    pub period: usize,
+    /// Trading days before the assess day the bands are scored over.
+    #[serde(default = "default_analyze_range")]
+    pub analyze_range: usize,
+    /// Standard deviations between the middle band and the outer ones.
+    #[serde(default = "default_band_size")]
+    pub band_size: f64,
+    #[serde(default)]
+    pub basis: view::BandBasis,
+    /// %B range of the typical price counted as in the buy zone; by default between one and two
+    /// standard deviations above the middle band.
+    #[serde(default = "default_buy_zone")]
+    pub buy_zone: (f64, f64),
+    /// Bandwidth the bands need on the assess day for a stock to be scored.
+    #[serde(default)]
+    pub min_bandwidth: f64,
+}
+
+fn default_period() -> usize {
+    PERIOD
+}
+
+fn default_analyze_range() -> usize {
+    ANALYZE_RANGE
+}
+
+fn default_band_size() -> f64 {
+    BAND_SIZE
+}
+
+fn default_buy_zone() -> (f64, f64) {
+    (0.75, 1.0)
+}
+
+impl Default for BandParams {
+    fn default() -> Self {
+        BandParams {
+            period: default_period(),
+            analyze_range: default_analyze_range(),
+            band_size: default_band_size(),
+            basis: view::BandBasis::Sma,
+            buy_zone: default_buy_zone(),
+            min_bandwidth: 0.0,
+        }
+    }
+}
 
 pub struct Strategy {
     pub backend_op: Rc<dyn backend::BackendOp>,
+    pub back_adjustment: adjust::BackAdjustment,
+    pub band_params: BandParams,
 }
 
 impl Strategy {
+    fn transform(
+        &self,
+        records: &[schema::RawData],
+    ) -> Result<Vec<view::BollingerBandView>, strategy::Error> {
+        Ok(view::BollingerBandView::transform_by_params(
+            records,
+            self.band_params.period,
+            self.band_params.band_size,
+            self.band_params.basis,
+        )?)
+    }
+
     fn get_views(
         &self,
         stock_id: &str,
@@ -20,14 +93,16 @@ impl Strategy {
         end_date: chrono::NaiveDate,
     ) -> Result<Vec<view::BollingerBandView>, strategy::Error> {
         let calc_date = start_date
-            .checked_sub_signed(chrono::Duration::days(PERIOD as i64 * 2))
+            .checked_sub_signed(chrono::Duration::days(self.band_params.period as i64 * 2))
             .ok_or(strategy::Error::BadOperation)?;
-        let records = self
-            .backend_op
-            .query_by_range(&stock_id, calc_date, end_date)?;
-        let views = view::BollingerBandView::transform(&records)?;
+        let records = self.back_adjustment.apply(
+            stock_id,
+            self.backend_op
+                .query_by_range(stock_id, calc_date, end_date)?,
+        );
+        let views = self.transform(&records)?;
 
-        if records.len() < PERIOD {
+        if records.len() < self.band_params.period {
             return Ok(vec![]);
         }
 
@@ -48,18 +123,20 @@ impl strategy::StrategyAPI for Strategy {
         assess_date: chrono::NaiveDate,
     ) -> Result<strategy::Score, strategy::Error> {
         let analyze_date = assess_date
-            .checked_sub_signed(chrono::Duration::days(ANALYZE_RANGE as i64 * 2))
+            .checked_sub_signed(chrono::Duration::days(
+                self.band_params.analyze_range as i64 * 2,
+            ))
             .ok_or(strategy::Error::BadOperation)?;
         let mut score = strategy::Score::default();
         let views = self.get_views(stock_id, analyze_date, assess_date)?;
 
-        if views.len() < ANALYZE_RANGE {
+        if views.len() < self.band_params.analyze_range {
             return Ok(score);
         }
 
         let last_view = views.last().unwrap();
 
-        if last_view.date != assess_date {
+        if last_view.date != assess_date || last_view.bandwidth < self.band_params.min_bandwidth {
             return Ok(score);
         }
 
@@ -68,6 +145,7 @@ impl strategy::StrategyAPI for Strategy {
         let mut in_buy_zone_ratio = 0.0;
         let mut total_count = 0;
         let mut in_buy_zone_count = 0;
+        let (buy_zone_low, buy_zone_high) = self.band_params.buy_zone;
 
         for view in views.iter().rev() {
             let price = (view.high + view.low + view.close) / 3.0;
@@ -81,11 +159,11 @@ impl strategy::StrategyAPI for Strategy {
 
             tmp_sd = view.sd;
             total_count = total_count + 1;
-            if price >= view.sma + view.sd && price <= view.sma + BAND_SIZE as f64 * view.sd {
+            if view.percent_b >= buy_zone_low && view.percent_b <= buy_zone_high {
                 in_buy_zone_count = in_buy_zone_count + 1;
             }
 
-            if total_count == ANALYZE_RANGE {
+            if total_count == self.band_params.analyze_range {
                 in_buy_zone_ratio = (in_buy_zone_count as f64 / total_count as f64) * 100.0;
                 rise_ratio = (last_view.sma - view.sma) / view.sma * 100.0;
                 break;
@@ -106,14 +184,14 @@ impl strategy::StrategyAPI for Strategy {
         stock_id: &str,
         hold_date: chrono::NaiveDate,
         assess_date: chrono::NaiveDate,
-    ) -> Result<bool, strategy::Error> {
+    ) -> Result<Option<strategy::SettleReason>, strategy::Error> {
         let views = self.get_views(stock_id, hold_date, assess_date)?;
 
         if views.len() == 0 {
-            return Ok(false);
+            return Ok(None);
         }
         if views.last().unwrap().date != assess_date {
-            return Ok(false);
+            return Ok(None);
         }
 
         const CONT_LOW_LIMIT: i32 = 3;
@@ -128,75 +206,133 @@ impl strategy::StrategyAPI for Strategy {
 
             count = count + 1;
             if count == CONT_LOW_LIMIT {
-                return Ok(true);
+                return Ok(Some(strategy::SettleReason::SignalExit));
             }
         }
 
-        Ok(false)
+        Ok(None)
+    }
+
+    fn draw_view(
+        &self,
+        stock_id: &str,
+        style: &diagram::DiagramStyle,
+        output: &diagram::Output,
+    ) -> Result<(), strategy::Error> {
+        let records = self
+            .back_adjustment
+            .apply(stock_id, self.backend_op.query_all(stock_id)?);
+        let views = self.transform(&records)?;
+        let plot = diagram::render_bollinger(
+            &views,
+            self.band_params.period,
+            self.band_params.band_size,
+            style,
+        );
+
+        diagram::render(&plot, style, output);
+
+        Ok(())
     }
 
-    fn draw_view(&self, stock_id: &str) -> Result<(), strategy::Error> {
-        let records = self.backend_op.query_all(stock_id)?;
-        let views = view::BollingerBandView::transform(&records)?;
-        let mut date_series = Vec::new();
-        let mut open_series = Vec::new();
-        let mut high_series = Vec::new();
-        let mut low_series = Vec::new();
-        let mut close_series = Vec::new();
-        let mut sma_series = Vec::new();
-        let mut upper_band_series = Vec::new();
-        let mut upper_one_sd_band_series = Vec::new();
-        let mut lower_band_series = Vec::new();
-        let mut lower_one_sd_band_series = Vec::new();
-        let mut plot = plotly::Plot::new();
-
-        for view in views {
-            date_series.push(view.date.format("%Y-%m-%d").to_string());
-            open_series.push(view.open);
-            high_series.push(view.high);
-            low_series.push(view.low);
-            close_series.push(view.close);
-            sma_series.push(view.sma);
-            upper_band_series.push(view.sma + BAND_SIZE as f64 * view.sd);
-            upper_one_sd_band_series.push(view.sma + view.sd);
-            lower_band_series.push(view.sma - BAND_SIZE as f64 * view.sd);
-            lower_one_sd_band_series.push(view.sma - view.sd);
+    fn export_view(&self, stock_id: &str, file_path: &str) -> Result<(), strategy::Error> {
+        let records = self
+            .back_adjustment
+            .apply(stock_id, self.backend_op.query_all(stock_id)?);
+        let views = self.transform(&records)?;
+
+        export::to_yaml(file_path, &views);
+        Ok(())
+    }
+
+    fn min_history_days(&self) -> usize {
+        self.band_params.period + self.band_params.analyze_range
+    }
+
+    fn data_requirements(&self) -> strategy::DataRequirements {
+        // Mirrors the windows read by `analyze` and `get_views`.
+        strategy::DataRequirements {
+            datasets: vec![strategy::Dataset::StockPrices],
+            lookback_days: (self.band_params.period + self.band_params.analyze_range) as i64 * 2,
         }
+    }
+}
+
+#[cfg(test)]
+mod bollinger_band_test {
+    use std::rc::Rc;
+
+    use crate::dataview::adjust;
+    use crate::dataview::view::{self, Transform};
+    use crate::storage::memory;
+    use crate::strategy::strategy::StrategyAPI;
+    use crate::testkit::{generator, signal};
+
+    use super::{BandParams, Strategy, ANALYZE_RANGE};
+
+    #[test]
+    fn analyze_check() {
+        let backend_op = Rc::new(memory::MemoryBackend::new());
+        let strategy = Strategy {
+            backend_op: backend_op.clone(),
+            back_adjustment: adjust::BackAdjustment::default(),
+            band_params: BandParams::default(),
+        };
+        let start_date = chrono::NaiveDate::from_ymd_opt(2022, 1, 3).unwrap();
+        let flat_records = generator::SeriesBuilder::new(start_date, 100.0)
+            .flat(80)
+            .insert(backend_op.as_ref(), "0050")
+            .unwrap();
+        let trend_records = generator::SeriesBuilder::new(start_date, 100.0)
+            .trend(80, 1.0)
+            .insert(backend_op.as_ref(), "0051")
+            .unwrap();
+
+        signal::assert_no_buy_signal(&strategy, "0050", &flat_records);
+        signal::assert_buy_signal(&strategy, "0051", trend_records.last().unwrap().date);
+    }
+
+    #[test]
+    fn band_params_check() {
+        let backend_op = Rc::new(memory::MemoryBackend::new());
+        let start_date = chrono::NaiveDate::from_ymd_opt(2022, 1, 3).unwrap();
+        let records = generator::SeriesBuilder::new(start_date, 100.0)
+            .trend(80, 1.0)
+            .insert(backend_op.as_ref(), "0051")
+            .unwrap();
+        let last_date = records.last().unwrap().date;
+        let mut strategy = Strategy {
+            backend_op: backend_op.clone(),
+            back_adjustment: adjust::BackAdjustment::default(),
+            band_params: BandParams {
+                basis: view::BandBasis::Ema,
+                ..Default::default()
+            },
+        };
+        let views = strategy.transform(&records).unwrap();
+        let last_view = views.last().unwrap();
 
-        let trace_1 = Box::new(
-            plotly::Candlestick::new(
-                date_series.clone(),
-                open_series.clone(),
-                high_series.clone(),
-                low_series.clone(),
-                close_series.clone(),
-            )
-            .name("Candlestick"),
+        // An EMA basis follows the trend closer than an SMA one.
+        assert!(
+            last_view.sma
+                > view::BollingerBandView::transform(&records).unwrap()[views.len() - 1].sma
         );
-        let trace_2 = plotly::Scatter::new(date_series.clone(), sma_series.clone())
-            .mode(plotly::common::Mode::Lines)
-            .name("20 Period SMA");
-        let trace_3 = plotly::Scatter::new(date_series.clone(), upper_band_series.clone())
-            .mode(plotly::common::Mode::Lines)
-            .name(&("Upper Band (".to_owned() + &BAND_SIZE.to_string() + "sd)"));
-        let trace_4 = plotly::Scatter::new(date_series.clone(), upper_one_sd_band_series.clone())
-            .mode(plotly::common::Mode::Lines)
-            .name("Upper Band (1 sd)");
-        let trace_5 = plotly::Scatter::new(date_series.clone(), lower_band_series.clone())
-            .mode(plotly::common::Mode::Lines)
-            .name(&("Lower Band (".to_owned() + &BAND_SIZE.to_string() + "sd)"));
-        let trace_6 = plotly::Scatter::new(date_series.clone(), lower_one_sd_band_series.clone())
-            .mode(plotly::common::Mode::Lines)
-            .name("Upper Band (1 sd)");
-
-        plot.add_trace(trace_1);
-        plot.add_trace(trace_2);
-        plot.add_trace(trace_3);
-        plot.add_trace(trace_4);
-        plot.add_trace(trace_5);
-        plot.add_trace(trace_6);
-        plot.show();
+        assert!(last_view.bandwidth > 0.0);
+        signal::assert_buy_signal(&strategy, "0051", last_date);
 
-        Ok(())
+        strategy.band_params.min_bandwidth = last_view.bandwidth * 2.0;
+        assert_eq!(strategy.analyze("0051", last_date).unwrap().point, 0);
+
+        // A shorter window scores the stock with less history before it.
+        strategy.band_params = BandParams {
+            period: 10,
+            ..Default::default()
+        };
+        assert_eq!(strategy.min_history_days(), 10 + ANALYZE_RANGE);
+        assert_eq!(
+            strategy.transform(&records).unwrap().len(),
+            records.len() - 9
+        );
+        signal::assert_buy_signal(&strategy, "0051", last_date);
     }
 }
@@ -0,0 +1,275 @@
+use serde::{Deserialize, Serialize};
+
+use crate::dataview::adjust;
+use crate::diagram::diagram;
+use crate::strategy::strategy;
+
+/// Ensemble configured in the config, run by name like the built-in strategies.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompositeConfig {
+    pub name: String,
+    pub members: Vec<Member>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Member {
+    /// Built-in strategy, rule set or model of this name.
+    pub strategy: String,
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+impl CompositeConfig {
+    pub fn is_valid(&self) -> bool {
+        is_valid(
+            &self
+                .members
+                .iter()
+                .map(|member| member.weight)
+                .collect::<Vec<f64>>(),
+        )
+    }
+}
+
+/// Whether an ensemble of members weighted `weights` scores meaningfully: it has members, each
+/// of a finite positive weight.
+pub fn is_valid(weights: &[f64]) -> bool {
+    !weights.is_empty()
+        && weights
+            .iter()
+            .all(|weight| weight.is_finite() && *weight > 0.0)
+}
+
+/// Scores a stock with the weighted sum of the points of its members, and settles a position
+/// only once every member would.
+pub struct CompositeStrategy {
+    pub members: Vec<(strategy::Strategy, f64)>,
+}
+
+impl CompositeStrategy {
+    pub fn set_back_adjustment(&mut self, back_adjustment: adjust::BackAdjustment) {
+        for (member, _) in self.members.iter_mut() {
+            member.set_back_adjustment(back_adjustment.clone());
+        }
+    }
+
+    /// Member of the largest weight, whose views stand for the ensemble.
+    fn get_lead(&self) -> Result<&strategy::Strategy, strategy::Error> {
+        self.members
+            .iter()
+            .max_by(|(_, lhs), (_, rhs)| lhs.total_cmp(rhs))
+            .map(|(member, _)| member)
+            .ok_or(strategy::Error::BadOperation)
+    }
+}
+
+impl strategy::StrategyAPI for CompositeStrategy {
+    fn analyze(
+        &self,
+        stock_id: &str,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<strategy::Score, strategy::Error> {
+        let mut score = strategy::Score::default();
+        let mut point = 0.0;
+
+        for (member, weight) in &self.members {
+            let member_score = member.analyze(stock_id, assess_date)?;
+
+            point += member_score.point as f64 * weight;
+            score.trading_volume = score.trading_volume.max(member_score.trading_volume);
+        }
+
+        if point <= 0.0 {
+            return Ok(strategy::Score::default());
+        }
+
+        score.point = (point.round() as i64).max(1);
+        Ok(score)
+    }
+
+    fn settle_check(
+        &self,
+        stock_id: &str,
+        hold_date: chrono::NaiveDate,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<Option<strategy::SettleReason>, strategy::Error> {
+        let mut settle_reason = None;
+
+        // Settles with the reason of the first member, once all of them agree.
+        for (member, _) in &self.members {
+            match member.settle_check(stock_id, hold_date, assess_date)? {
+                Some(member_reason) => {
+                    settle_reason.get_or_insert(member_reason);
+                }
+                None => return Ok(None),
+            }
+        }
+        Ok(settle_reason)
+    }
+
+    fn draw_view(
+        &self,
+        stock_id: &str,
+        style: &diagram::DiagramStyle,
+        output: &diagram::Output,
+    ) -> Result<(), strategy::Error> {
+        self.get_lead()?.draw_view(stock_id, style, output)
+    }
+
+    fn export_view(&self, stock_id: &str, file_path: &str) -> Result<(), strategy::Error> {
+        self.get_lead()?.export_view(stock_id, file_path)
+    }
+
+    fn min_history_days(&self) -> usize {
+        self.members
+            .iter()
+            .map(|(member, _)| member.min_history_days())
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn data_requirements(&self) -> strategy::DataRequirements {
+        let mut requirements = strategy::DataRequirements {
+            datasets: Vec::new(),
+            lookback_days: 0,
+        };
+
+        for (member, _) in &self.members {
+            let member_requirements = member.data_requirements();
+
+            for dataset in member_requirements.datasets {
+                if !requirements.datasets.contains(&dataset) {
+                    requirements.datasets.push(dataset);
+                }
+            }
+            requirements.lookback_days = requirements
+                .lookback_days
+                .max(member_requirements.lookback_days);
+        }
+        requirements
+    }
+}
+
+#[cfg(test)]
+mod composite_test {
+    use std::rc::Rc;
+
+    use super::CompositeConfig;
+    use crate::storage::memory;
+    use crate::strategy::strategy::{self, StrategyAPI};
+    use crate::testkit::{generator, signal};
+
+    #[test]
+    fn analyze_check() {
+        let backend_op = Rc::new(memory::MemoryBackend::new());
+        let start_date = chrono::NaiveDate::from_ymd_opt(2022, 1, 3).unwrap();
+        let records = generator::SeriesBuilder::new(start_date, 100.0)
+            .trend(80, 1.0)
+            .trend(30, -1.0)
+            .insert(backend_op.as_ref(), "0050")
+            .unwrap();
+        let params = strategy::StrategyParams::default();
+        let members = vec![
+            (strategy::Strategies::BollingerBand, 0.5),
+            (strategy::Strategies::Rsi, 2.0),
+        ];
+        let composite =
+            strategy::StrategyFactory::get_composite(members.clone(), &params, backend_op.clone())
+                .unwrap();
+        let member_strategies: Vec<strategy::Strategy> = members
+            .iter()
+            .map(|(member, _)| {
+                strategy::StrategyFactory::get(member.clone(), &params, backend_op.clone())
+            })
+            .collect();
+        let assess_date = records[79].date;
+        let point = member_strategies[0]
+            .analyze("0050", assess_date)
+            .unwrap()
+            .point;
+
+        assert!(point > 0);
+        assert_eq!(
+            composite.analyze("0050", assess_date).unwrap().point,
+            (point as f64 * 0.5).round() as i64
+        );
+
+        // Settles on the days both members settle on, and on no other.
+        let hold_date = records[60].date;
+        let member_dates: Vec<Vec<chrono::NaiveDate>> = member_strategies
+            .iter()
+            .map(|member| {
+                signal::get_settle_dates(member, "0050", hold_date, &records)
+                    .unwrap()
+                    .into_iter()
+                    .map(|(date, _)| date)
+                    .collect()
+            })
+            .collect();
+        let settle_dates: Vec<chrono::NaiveDate> =
+            signal::get_settle_dates(&composite, "0050", hold_date, &records)
+                .unwrap()
+                .into_iter()
+                .map(|(date, _)| date)
+                .collect();
+
+        assert!(!member_dates[0].is_empty() && !member_dates[1].is_empty());
+        assert_eq!(
+            settle_dates,
+            member_dates[0]
+                .iter()
+                .filter(|date| member_dates[1].contains(date))
+                .cloned()
+                .collect::<Vec<_>>()
+        );
+        assert!(settle_dates.len() < member_dates[0].len().min(member_dates[1].len()));
+        assert_eq!(
+            composite.min_history_days(),
+            member_strategies[0]
+                .min_history_days()
+                .max(member_strategies[1].min_history_days())
+        );
+    }
+
+    #[test]
+    fn get_composite_check() {
+        let backend_op = Rc::new(memory::MemoryBackend::new());
+        let params = strategy::StrategyParams::default();
+        let get_composite = |members| {
+            strategy::StrategyFactory::get_composite(members, &params, backend_op.clone())
+        };
+
+        assert!(matches!(
+            get_composite(vec![]),
+            Err(strategy::Error::InvalidComposite)
+        ));
+        for weight in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                get_composite(vec![
+                    (strategy::Strategies::BollingerBand, 1.0),
+                    (strategy::Strategies::Rsi, weight),
+                ]),
+                Err(strategy::Error::InvalidComposite)
+            ));
+        }
+        assert!(get_composite(vec![(strategy::Strategies::Rsi, 0.5)]).is_ok());
+    }
+
+    #[test]
+    fn is_valid_check() {
+        let parse = |yaml: &str| serde_yaml::from_str::<CompositeConfig>(yaml).unwrap();
+
+        assert!(
+            parse("{name: a, members: [{strategy: rsi}, {strategy: gap, weight: 2}]}").is_valid()
+        );
+        assert!(!parse("{name: a, members: []}").is_valid());
+        assert!(
+            !parse("{name: a, members: [{strategy: rsi}, {strategy: gap, weight: 0}]}").is_valid()
+        );
+        assert!(!parse("{name: a, members: [{strategy: rsi, weight: -1}]}").is_valid());
+    }
+}
@@ -0,0 +1,150 @@
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::dataview::{adjust, view};
+use crate::diagram::diagram;
+use crate::export::export;
+use crate::storage::backend;
+use crate::strategy::strategy;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Frequency {
+    Weekly,
+    Monthly,
+}
+
+impl Frequency {
+    /// Whether `date` falls in a later week or month than `last_date`.
+    pub fn is_new_period(&self, last_date: chrono::NaiveDate, date: chrono::NaiveDate) -> bool {
+        match self {
+            Frequency::Weekly => {
+                chrono::Datelike::iso_week(&last_date) != chrono::Datelike::iso_week(&date)
+            }
+            Frequency::Monthly => {
+                chrono::Datelike::month(&last_date) != chrono::Datelike::month(&date)
+                    || chrono::Datelike::year(&last_date) != chrono::Datelike::year(&date)
+            }
+        }
+    }
+}
+
+/// Instrument and amount of the dollar-cost averaging baseline, set with `dca` in the config.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Plan {
+    #[serde(default = "default_instrument_id")]
+    pub instrument_id: String,
+    /// Cash put into the instrument on the first trading day of every period, or what is left
+    /// when there is less.
+    #[serde(default = "default_amount")]
+    pub amount: u32,
+    #[serde(default = "default_frequency")]
+    pub frequency: Frequency,
+}
+
+fn default_instrument_id() -> String {
+    "0050".to_owned()
+}
+
+fn default_amount() -> u32 {
+    10000
+}
+
+fn default_frequency() -> Frequency {
+    Frequency::Monthly
+}
+
+impl Default for Plan {
+    fn default() -> Self {
+        Plan {
+            instrument_id: default_instrument_id(),
+            amount: default_amount(),
+            frequency: default_frequency(),
+        }
+    }
+}
+
+/// Baseline buying a fixed amount of an instrument on a schedule and never selling. It scores
+/// nothing: the purchases are made by `decision::Decision` on the schedule of the plan, adding
+/// to the position every time, which entries picked on scores never do. Paired with a
+/// `cashflow::CashFlowSchedule` contributing the amount, it invests savings as they come.
+pub struct Strategy {
+    pub backend_op: Rc<dyn backend::BackendOp>,
+    pub back_adjustment: adjust::BackAdjustment,
+    pub plan: Plan,
+}
+
+impl strategy::StrategyAPI for Strategy {
+    fn analyze(
+        &self,
+        _stock_id: &str,
+        _assess_date: chrono::NaiveDate,
+    ) -> Result<strategy::Score, strategy::Error> {
+        Ok(strategy::Score::default())
+    }
+
+    fn settle_check(
+        &self,
+        _stock_id: &str,
+        _hold_date: chrono::NaiveDate,
+        _assess_date: chrono::NaiveDate,
+    ) -> Result<Option<strategy::SettleReason>, strategy::Error> {
+        Ok(None)
+    }
+
+    fn draw_view(
+        &self,
+        stock_id: &str,
+        style: &diagram::DiagramStyle,
+        output: &diagram::Output,
+    ) -> Result<(), strategy::Error> {
+        let records = self
+            .back_adjustment
+            .apply(stock_id, self.backend_op.query_all(stock_id)?);
+
+        diagram::draw_view_diagram(stock_id, &view::Views::None, &records, None, style, output)
+            .map_err(|_| strategy::Error::BadOperation)
+    }
+
+    fn export_view(&self, stock_id: &str, file_path: &str) -> Result<(), strategy::Error> {
+        let records = self
+            .back_adjustment
+            .apply(stock_id, self.backend_op.query_all(stock_id)?);
+
+        export::to_yaml(file_path, &records);
+        Ok(())
+    }
+
+    fn min_history_days(&self) -> usize {
+        0
+    }
+
+    fn data_requirements(&self) -> strategy::DataRequirements {
+        strategy::DataRequirements {
+            datasets: vec![strategy::Dataset::InstrumentPrices(
+                self.plan.instrument_id.to_owned(),
+            )],
+            lookback_days: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod dca_test {
+    use super::Frequency;
+
+    #[test]
+    fn is_new_period_check() {
+        let date = |month, day| chrono::NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+
+        // Friday to the following Monday.
+        assert!(Frequency::Weekly.is_new_period(date(5, 3), date(5, 6)));
+        assert!(!Frequency::Weekly.is_new_period(date(5, 6), date(5, 10)));
+        assert!(!Frequency::Monthly.is_new_period(date(5, 3), date(5, 31)));
+        assert!(Frequency::Monthly.is_new_period(date(5, 31), date(6, 3)));
+        assert!(Frequency::Monthly.is_new_period(
+            date(5, 3),
+            chrono::NaiveDate::from_ymd_opt(2025, 5, 2).unwrap()
+        ));
+    }
+}
@@ -0,0 +1,234 @@
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::dataview::{adjust, view};
+use crate::diagram::diagram;
+use crate::export::export;
+use crate::storage::backend;
+use crate::strategy::{schema, strategy};
+
+/// Default of `GapParams::volume_period`.
+pub const VOLUME_PERIOD: usize = view::GAP_VOLUME_PERIOD;
+/// Default of `GapParams::min_gap`.
+pub const MIN_GAP: f64 = 2.0;
+/// Default of `GapParams::min_volume_ratio`.
+pub const MIN_VOLUME_RATIO: f64 = 1.5;
+
+/// Size and volume a gap needs to be traded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GapParams {
+    /// Trading days the volume of the gap day is averaged against.
+    #[serde(default = "default_volume_period")]
+    pub volume_period: usize,
+    /// Open away from the previous close, in percent, that counts as a gap.
+    #[serde(default = "default_min_gap")]
+    pub min_gap: f64,
+    /// Volume of the gap day over the average volume before it.
+    #[serde(default = "default_min_volume_ratio")]
+    pub min_volume_ratio: f64,
+}
+
+fn default_volume_period() -> usize {
+    VOLUME_PERIOD
+}
+
+fn default_min_gap() -> f64 {
+    MIN_GAP
+}
+
+fn default_min_volume_ratio() -> f64 {
+    MIN_VOLUME_RATIO
+}
+
+impl Default for GapParams {
+    fn default() -> Self {
+        GapParams {
+            volume_period: default_volume_period(),
+            min_gap: default_min_gap(),
+            min_volume_ratio: default_min_volume_ratio(),
+        }
+    }
+}
+
+/// Buys a stock that gaps up on heavy volume and holds above the gap through the day
+/// (gap-and-go), and settles it once the gap fills or the stock gaps down. Meant to fill at the
+/// open, see `decision::FillPolicy::Open`.
+pub struct Strategy {
+    pub backend_op: Rc<dyn backend::BackendOp>,
+    pub back_adjustment: adjust::BackAdjustment,
+    pub gap_params: GapParams,
+}
+
+impl Strategy {
+    /// Views from `start_date` to `assess_date`, or none when the stock has no record on
+    /// `assess_date`.
+    fn get_views(
+        &self,
+        stock_id: &str,
+        start_date: chrono::NaiveDate,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<Vec<view::GapView>, strategy::Error> {
+        let calc_date = start_date
+            .checked_sub_signed(chrono::Duration::days(
+                self.gap_params.volume_period as i64 * 2,
+            ))
+            .ok_or(strategy::Error::BadOperation)?;
+        let records = self.back_adjustment.apply(
+            stock_id,
+            self.backend_op
+                .query_by_range(stock_id, calc_date, assess_date)?,
+        );
+
+        match records.last() {
+            Some(record) if record.date == assess_date => {}
+            _ => return Ok(Vec::new()),
+        }
+        Ok(
+            view::GapView::transform_by_period(&records, self.gap_params.volume_period)?
+                .into_iter()
+                .filter(|view| view.date >= start_date)
+                .collect(),
+        )
+    }
+
+    fn get_records(&self, stock_id: &str) -> Result<Vec<schema::RawData>, strategy::Error> {
+        Ok(self
+            .back_adjustment
+            .apply(stock_id, self.backend_op.query_all(stock_id)?))
+    }
+}
+
+impl strategy::StrategyAPI for Strategy {
+    fn analyze(
+        &self,
+        stock_id: &str,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<strategy::Score, strategy::Error> {
+        let mut score = strategy::Score::default();
+        let views = self.get_views(stock_id, assess_date, assess_date)?;
+        let last_view = match views.last() {
+            Some(last_view) => last_view,
+            None => return Ok(score),
+        };
+
+        if last_view.gap < self.gap_params.min_gap
+            || last_view.volume_ratio < self.gap_params.min_volume_ratio
+            || last_view.is_filled()
+        {
+            return Ok(score);
+        }
+
+        score.point = ((last_view.gap * last_view.volume_ratio) as i64).max(1);
+        score.trading_volume = last_view.volume;
+        Ok(score)
+    }
+
+    fn settle_check(
+        &self,
+        stock_id: &str,
+        hold_date: chrono::NaiveDate,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<Option<strategy::SettleReason>, strategy::Error> {
+        if assess_date <= hold_date {
+            return Ok(None);
+        }
+
+        let views = self.get_views(stock_id, hold_date, assess_date)?;
+        let (entry_view, last_view) = match (views.first(), views.last()) {
+            (Some(entry_view), Some(last_view)) if entry_view.date == hold_date => {
+                (entry_view, last_view)
+            }
+            _ => return Ok(None),
+        };
+
+        // The gap fills once the price trades back to the close before the entry gap.
+        if last_view.low <= entry_view.prev_close || last_view.gap <= -self.gap_params.min_gap {
+            return Ok(Some(strategy::SettleReason::SignalExit));
+        }
+        Ok(None)
+    }
+
+    fn draw_view(
+        &self,
+        stock_id: &str,
+        style: &diagram::DiagramStyle,
+        output: &diagram::Output,
+    ) -> Result<(), strategy::Error> {
+        let records = self.get_records(stock_id)?;
+
+        diagram::draw_view_diagram(stock_id, &view::Views::None, &records, None, style, output)
+            .map_err(|_| strategy::Error::BadOperation)
+    }
+
+    fn export_view(&self, stock_id: &str, file_path: &str) -> Result<(), strategy::Error> {
+        let records = self.get_records(stock_id)?;
+
+        export::to_yaml(
+            file_path,
+            &view::GapView::transform_by_period(&records, self.gap_params.volume_period)?,
+        );
+        Ok(())
+    }
+
+    fn min_history_days(&self) -> usize {
+        self.gap_params.volume_period + 1
+    }
+
+    fn data_requirements(&self) -> strategy::DataRequirements {
+        // Mirrors the window read by `get_views`.
+        strategy::DataRequirements {
+            datasets: vec![strategy::Dataset::StockPrices],
+            lookback_days: self.gap_params.volume_period as i64 * 2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod gap_test {
+    use std::rc::Rc;
+
+    use crate::dataview::adjust;
+    use crate::storage::{backend, memory};
+    use crate::strategy::strategy;
+    use crate::testkit::{generator, signal};
+
+    use super::{GapParams, Strategy};
+
+    #[test]
+    fn analyze_check() {
+        let backend_op = Rc::new(memory::MemoryBackend::new());
+        let strategy = Strategy {
+            backend_op: backend_op.clone(),
+            back_adjustment: adjust::BackAdjustment::default(),
+            gap_params: GapParams::default(),
+        };
+        let start_date = chrono::NaiveDate::from_ymd_opt(2022, 1, 3).unwrap();
+        let mut records = generator::SeriesBuilder::new(start_date, 100.0)
+            .flat(30)
+            .gap(3.0)
+            .trend(5, 3.0)
+            .trend(10, -3.0)
+            .build();
+
+        records[30].trading_volume *= 3;
+        backend::BackendOp::batch_insert(
+            backend_op.as_ref(),
+            &records
+                .iter()
+                .map(|record| ("0050".to_owned(), record.clone()))
+                .collect(),
+        )
+        .unwrap();
+
+        let buy_dates = signal::get_buy_dates(&strategy, "0050", &records).unwrap();
+
+        assert_eq!(buy_dates, vec![records[30].date]);
+
+        let settle_dates =
+            signal::get_settle_dates(&strategy, "0050", buy_dates[0], &records).unwrap();
+
+        assert!(settle_dates[0].0 > records[35].date);
+        assert_eq!(settle_dates[0].1, strategy::SettleReason::SignalExit);
+    }
+}
@@ -0,0 +1,334 @@
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::dataview::{adjust, view};
+use crate::diagram::diagram;
+use crate::export::export;
+use crate::storage::backend;
+use crate::strategy::{schema, strategy};
+
+/// Default of `BreakoutParams::weeks`.
+pub const WEEKS: usize = 52;
+pub const TRADING_DAYS_PER_WEEK: usize = 5;
+/// Trading days of highs cleared with the default `BreakoutParams::weeks`.
+pub const HIGH_PERIOD: usize = WEEKS * TRADING_DAYS_PER_WEEK;
+pub const VOLUME_PERIOD: usize = 20;
+/// Volume of the breakout day over the average volume before it.
+pub const MIN_VOLUME_RATIO: f64 = 1.5;
+/// Default of `BreakoutParams::trailing_stop`.
+pub const TRAILING_STOP: f64 = 10.0;
+
+/// Highs a breakout has to clear and the stop trailing it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BreakoutParams {
+    /// Weeks of highs a close has to clear.
+    #[serde(default = "default_weeks")]
+    pub weeks: usize,
+    /// Drop from the highest close since the entry, in percent, that settles a position.
+    #[serde(default = "default_trailing_stop")]
+    pub trailing_stop: f64,
+}
+
+fn default_weeks() -> usize {
+    WEEKS
+}
+
+fn default_trailing_stop() -> f64 {
+    TRAILING_STOP
+}
+
+impl Default for BreakoutParams {
+    fn default() -> Self {
+        BreakoutParams {
+            weeks: default_weeks(),
+            trailing_stop: default_trailing_stop(),
+        }
+    }
+}
+
+impl BreakoutParams {
+    /// Trading days of highs a close has to clear.
+    pub fn get_high_period(&self) -> usize {
+        self.weeks * TRADING_DAYS_PER_WEEK
+    }
+}
+
+/// Buys a stock closing above its highs of the last `weeks` weeks on expanding volume, and
+/// settles it on a trailing stop below the highest close since the entry.
+pub struct Strategy {
+    pub backend_op: Rc<dyn backend::BackendOp>,
+    pub back_adjustment: adjust::BackAdjustment,
+    pub breakout_params: BreakoutParams,
+}
+
+impl Strategy {
+    fn transform(
+        &self,
+        records: &[schema::RawData],
+    ) -> Result<Vec<view::HighView>, strategy::Error> {
+        Ok(view::HighView::transform_by_periods(
+            records,
+            self.breakout_params.get_high_period(),
+            VOLUME_PERIOD,
+        )?)
+    }
+
+    fn get_lookback_days(&self) -> i64 {
+        // Calendar days spanning the `high_period + 1` records the views start after, with room
+        // for weekends and the holidays of a year.
+        (self.breakout_params.get_high_period() * 2) as i64
+    }
+
+    /// Records from `start_date` to `assess_date`, or none when the stock has no record on
+    /// `assess_date`.
+    fn get_records(
+        &self,
+        stock_id: &str,
+        start_date: chrono::NaiveDate,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<Vec<schema::RawData>, strategy::Error> {
+        let records = self.back_adjustment.apply(
+            stock_id,
+            self.backend_op
+                .query_by_range(stock_id, start_date, assess_date)?,
+        );
+
+        match records.last() {
+            Some(record) if record.date == assess_date => Ok(records),
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+impl strategy::StrategyAPI for Strategy {
+    fn analyze(
+        &self,
+        stock_id: &str,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<strategy::Score, strategy::Error> {
+        let mut score = strategy::Score::default();
+        let calc_date = assess_date
+            .checked_sub_signed(chrono::Duration::days(self.get_lookback_days()))
+            .ok_or(strategy::Error::BadOperation)?;
+        let records = self.get_records(stock_id, calc_date, assess_date)?;
+        let views = self.transform(&records)?;
+        let last_view = match views.last() {
+            Some(last_view) if last_view.date == assess_date => last_view,
+            _ => return Ok(score),
+        };
+
+        if last_view.close <= last_view.prior_high || last_view.volume_ratio < MIN_VOLUME_RATIO {
+            return Ok(score);
+        }
+
+        // Breakout above the prior high in basis points, weighted by the volume behind it.
+        let breakout = (last_view.close / last_view.prior_high - 1.0) * 10000.0;
+
+        score.point = ((breakout * last_view.volume_ratio) as i64).max(1);
+        score.trading_volume = last_view.volume;
+        Ok(score)
+    }
+
+    fn settle_check(
+        &self,
+        stock_id: &str,
+        hold_date: chrono::NaiveDate,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<Option<strategy::SettleReason>, strategy::Error> {
+        if assess_date <= hold_date {
+            return Ok(None);
+        }
+
+        let records = self.get_records(stock_id, hold_date, assess_date)?;
+        let last_record = match records.last() {
+            Some(last_record) => last_record,
+            None => return Ok(None),
+        };
+        let peak = records
+            .iter()
+            .map(|record| record.close)
+            .fold(f64::MIN, f64::max);
+
+        if last_record.close <= peak * (1.0 - self.breakout_params.trailing_stop / 100.0) {
+            return Ok(Some(strategy::SettleReason::StopLoss));
+        }
+        Ok(None)
+    }
+
+    fn draw_view(
+        &self,
+        stock_id: &str,
+        style: &diagram::DiagramStyle,
+        output: &diagram::Output,
+    ) -> Result<(), strategy::Error> {
+        let records = self
+            .back_adjustment
+            .apply(stock_id, self.backend_op.query_all(stock_id)?);
+
+        diagram::draw_view_diagram(stock_id, &view::Views::None, &records, None, style, output)
+            .map_err(|_| strategy::Error::BadOperation)
+    }
+
+    fn export_view(&self, stock_id: &str, file_path: &str) -> Result<(), strategy::Error> {
+        let records = self
+            .back_adjustment
+            .apply(stock_id, self.backend_op.query_all(stock_id)?);
+
+        export::to_yaml(file_path, &self.transform(&records)?);
+        Ok(())
+    }
+
+    fn min_history_days(&self) -> usize {
+        self.breakout_params.get_high_period() + 1
+    }
+
+    fn data_requirements(&self) -> strategy::DataRequirements {
+        // Mirrors the window read by `analyze`.
+        strategy::DataRequirements {
+            datasets: vec![strategy::Dataset::StockPrices],
+            lookback_days: self.get_lookback_days(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod high_breakout_test {
+    use std::rc::Rc;
+
+    use crate::dataview::adjust;
+    use crate::storage::{backend, memory};
+    use crate::strategy::strategy;
+    use crate::testkit::{generator, signal};
+
+    use super::{BreakoutParams, Strategy, HIGH_PERIOD};
+
+    #[test]
+    fn analyze_check() {
+        let backend_op = Rc::new(memory::MemoryBackend::new());
+        let strategy = Strategy {
+            backend_op: backend_op.clone(),
+            back_adjustment: adjust::BackAdjustment::default(),
+            breakout_params: BreakoutParams::default(),
+        };
+        let start_date = chrono::NaiveDate::from_ymd_opt(2022, 1, 3).unwrap();
+        let mut records = generator::SeriesBuilder::new(start_date, 100.0)
+            .mean_reverting(HIGH_PERIOD + 10, 5.0, 40)
+            .trend(5, 3.0)
+            .trend(10, -2.0)
+            .build();
+        let breakout_index = records
+            .iter()
+            .position(|record| record.close > 105.0 * 1.01)
+            .unwrap();
+
+        records[breakout_index].trading_volume *= 3;
+        backend::BackendOp::batch_insert(
+            backend_op.as_ref(),
+            &records
+                .iter()
+                .map(|record| ("0050".to_owned(), record.clone()))
+                .collect(),
+        )
+        .unwrap();
+
+        let buy_dates = signal::get_buy_dates(&strategy, "0050", &records).unwrap();
+
+        assert_eq!(buy_dates, vec![records[breakout_index].date]);
+        signal::assert_settle_signal(
+            &strategy,
+            "0050",
+            buy_dates[0],
+            records.last().unwrap().date,
+            strategy::SettleReason::StopLoss,
+        );
+        assert_eq!(
+            strategy::StrategyAPI::settle_check(
+                &strategy,
+                "0050",
+                buy_dates[0],
+                records[breakout_index + 4].date
+            )
+            .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn holiday_check() {
+        let backend_op = Rc::new(memory::MemoryBackend::new());
+        let strategy = Strategy {
+            backend_op: backend_op.clone(),
+            back_adjustment: adjust::BackAdjustment::default(),
+            breakout_params: BreakoutParams::default(),
+        };
+        let start_date = chrono::NaiveDate::from_ymd_opt(2022, 1, 3).unwrap();
+        // A holiday every three weeks leaves fewer sessions than weekdays over the lookback.
+        let mut records: Vec<_> = generator::SeriesBuilder::new(start_date, 100.0)
+            .mean_reverting(HIGH_PERIOD + 40, 5.0, 40)
+            .trend(1, 10.0)
+            .build()
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| index % 15 != 7)
+            .map(|(_, record)| record)
+            .collect();
+
+        records.last_mut().unwrap().trading_volume *= 3;
+        backend::BackendOp::batch_insert(
+            backend_op.as_ref(),
+            &records
+                .iter()
+                .map(|record| ("0050".to_owned(), record.clone()))
+                .collect(),
+        )
+        .unwrap();
+
+        let score = strategy::StrategyAPI::analyze(&strategy, "0050", records.last().unwrap().date)
+            .unwrap();
+
+        assert!(score.point > 0);
+    }
+
+    #[test]
+    fn params_check() {
+        let backend_op = Rc::new(memory::MemoryBackend::new());
+        let mut strategy = Strategy {
+            backend_op: backend_op.clone(),
+            back_adjustment: adjust::BackAdjustment::default(),
+            breakout_params: BreakoutParams::default(),
+        };
+        let start_date = chrono::NaiveDate::from_ymd_opt(2022, 1, 3).unwrap();
+        let mut records = generator::SeriesBuilder::new(start_date, 100.0)
+            .mean_reverting(30, 5.0, 10)
+            .trend(1, 10.0)
+            .build();
+
+        records.last_mut().unwrap().trading_volume *= 3;
+        backend::BackendOp::batch_insert(
+            backend_op.as_ref(),
+            &records
+                .iter()
+                .map(|record| ("0050".to_owned(), record.clone()))
+                .collect(),
+        )
+        .unwrap();
+
+        let assess_date = records.last().unwrap().date;
+
+        // A month of history is too short for the highs of a year, but not for those of 4 weeks.
+        assert_eq!(
+            strategy::StrategyAPI::analyze(&strategy, "0050", assess_date)
+                .unwrap()
+                .point,
+            0
+        );
+        strategy.breakout_params.weeks = 4;
+        assert!(
+            strategy::StrategyAPI::analyze(&strategy, "0050", assess_date)
+                .unwrap()
+                .point
+                > 0
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::dataview::{adjust, view};
+use crate::diagram::diagram;
+use crate::export::export;
+use crate::storage::backend;
+use crate::strategy::{schema, strategy};
+
+/// Periods of the two moving averages, set with `ma_crossover` in the config.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Periods {
+    #[serde(default = "default_short_period")]
+    pub short_period: usize,
+    #[serde(default = "default_long_period")]
+    pub long_period: usize,
+}
+
+fn default_short_period() -> usize {
+    20
+}
+
+fn default_long_period() -> usize {
+    60
+}
+
+impl Default for Periods {
+    fn default() -> Self {
+        Periods {
+            short_period: default_short_period(),
+            long_period: default_long_period(),
+        }
+    }
+}
+
+/// Buys a stock on a golden cross, when its short SMA crosses above the long one, and settles
+/// it on the death cross that follows.
+pub struct Strategy {
+    pub backend_op: Rc<dyn backend::BackendOp>,
+    pub back_adjustment: adjust::BackAdjustment,
+    pub periods: Periods,
+}
+
+impl Strategy {
+    fn transform(
+        &self,
+        records: &[schema::RawData],
+    ) -> Result<Vec<view::CrossoverView>, strategy::Error> {
+        Ok(view::CrossoverView::transform_by_periods(
+            records,
+            self.periods.short_period,
+            self.periods.long_period,
+        )?)
+    }
+
+    fn get_lookback_days(&self) -> i64 {
+        (self.periods.long_period + 1) as i64 * 2
+    }
+
+    /// Views up to `assess_date`, or none when the stock has no record on that day.
+    fn get_views(
+        &self,
+        stock_id: &str,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<Vec<view::CrossoverView>, strategy::Error> {
+        let calc_date = assess_date
+            .checked_sub_signed(chrono::Duration::days(self.get_lookback_days()))
+            .ok_or(strategy::Error::BadOperation)?;
+        let records = self.back_adjustment.apply(
+            stock_id,
+            self.backend_op
+                .query_by_range(stock_id, calc_date, assess_date)?,
+        );
+
+        match records.last() {
+            Some(record) if record.date == assess_date => self.transform(&records),
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+impl strategy::StrategyAPI for Strategy {
+    fn analyze(
+        &self,
+        stock_id: &str,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<strategy::Score, strategy::Error> {
+        let mut score = strategy::Score::default();
+        let views = self.get_views(stock_id, assess_date)?;
+        let (prev_view, last_view) = match views.as_slice() {
+            [.., prev_view, last_view] => (prev_view, last_view),
+            _ => return Ok(score),
+        };
+
+        if prev_view.short_sma > prev_view.long_sma || last_view.short_sma <= last_view.long_sma {
+            return Ok(score);
+        }
+
+        // Spread between the averages in basis points, so that a decisive cross ranks first.
+        let spread = (last_view.short_sma - last_view.long_sma) / last_view.long_sma * 10000.0;
+
+        score.point = (spread as i64).max(1);
+        score.trading_volume = last_view.volume;
+        Ok(score)
+    }
+
+    fn settle_check(
+        &self,
+        stock_id: &str,
+        hold_date: chrono::NaiveDate,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<Option<strategy::SettleReason>, strategy::Error> {
+        if assess_date <= hold_date {
+            return Ok(None);
+        }
+
+        match self.get_views(stock_id, assess_date)?.last() {
+            Some(last_view) if last_view.short_sma < last_view.long_sma => {
+                Ok(Some(strategy::SettleReason::SignalExit))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn draw_view(
+        &self,
+        stock_id: &str,
+        style: &diagram::DiagramStyle,
+        output: &diagram::Output,
+    ) -> Result<(), strategy::Error> {
+        let records = self
+            .back_adjustment
+            .apply(stock_id, self.backend_op.query_all(stock_id)?);
+
+        diagram::draw_view_diagram(stock_id, &view::Views::None, &records, None, style, output)
+            .map_err(|_| strategy::Error::BadOperation)
+    }
+
+    fn export_view(&self, stock_id: &str, file_path: &str) -> Result<(), strategy::Error> {
+        let records = self
+            .back_adjustment
+            .apply(stock_id, self.backend_op.query_all(stock_id)?);
+
+        export::to_yaml(file_path, &self.transform(&records)?);
+        Ok(())
+    }
+
+    fn min_history_days(&self) -> usize {
+        self.periods.long_period + 1
+    }
+
+    fn data_requirements(&self) -> strategy::DataRequirements {
+        // Mirrors the window read by `get_views`.
+        strategy::DataRequirements {
+            datasets: vec![strategy::Dataset::StockPrices],
+            lookback_days: self.get_lookback_days(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod ma_crossover_test {
+    use std::rc::Rc;
+
+    use crate::dataview::adjust;
+    use crate::storage::memory;
+    use crate::strategy::strategy;
+    use crate::testkit::{generator, signal};
+
+    use super::{Periods, Strategy};
+
+    #[test]
+    fn analyze_check() {
+        let backend_op = Rc::new(memory::MemoryBackend::new());
+        let strategy = Strategy {
+            backend_op: backend_op.clone(),
+            back_adjustment: adjust::BackAdjustment::default(),
+            periods: Periods {
+                short_period: 5,
+                long_period: 20,
+            },
+        };
+        let start_date = chrono::NaiveDate::from_ymd_opt(2022, 1, 3).unwrap();
+        let records = generator::SeriesBuilder::new(start_date, 100.0)
+            .trend(30, -1.0)
+            .trend(30, 1.0)
+            .trend(30, -1.0)
+            .insert(backend_op.as_ref(), "0050")
+            .unwrap();
+        let buy_dates = signal::get_buy_dates(&strategy, "0050", &records).unwrap();
+
+        assert_eq!(buy_dates.len(), 1);
+        assert!(buy_dates[0] > records[30].date);
+        assert!(buy_dates[0] < records[60].date);
+
+        let settle_dates =
+            signal::get_settle_dates(&strategy, "0050", buy_dates[0], &records).unwrap();
+
+        assert!(settle_dates[0].0 > records[60].date);
+        assert_eq!(settle_dates[0].1, strategy::SettleReason::SignalExit);
+    }
+}
@@ -1,4 +1,14 @@
 pub mod bollinger_band;
+pub mod composite;
+pub mod dca;
+pub mod gap;
+pub mod high_breakout;
+pub mod ma_crossover;
+pub mod onnx;
+pub mod rsi;
+pub mod rule;
 pub mod schema;
+pub mod script;
+pub mod squeeze;
 pub mod strategy;
 
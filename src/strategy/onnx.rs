@@ -0,0 +1,293 @@
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::regime;
+use crate::dataview::{adjust, view};
+use crate::diagram::diagram;
+use crate::export::export;
+use crate::storage::backend;
+use crate::strategy::{rule, schema, strategy};
+
+/// Externally trained model scoring stocks from a feature vector, run through the same engine
+/// and cost model as the built-in strategies. Needs the `onnx` feature; without it the strategy
+/// fails on first use.
+///
+/// The model takes a `[1, features.len()]` f32 input and its first output value is the score,
+/// above zero to buy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelConfig {
+    pub name: String,
+    pub model_path: String,
+    /// Inputs of the model in order, as number expressions of the rule language, e.g.
+    /// `close / sma(20)` or `rsi(14)`; see `rule::RuleSet`.
+    pub features: Vec<String>,
+    /// Exits with `SettleReason::SignalExit` once the model output falls below this.
+    #[serde(default)]
+    pub settle_below: Option<f64>,
+    /// Point of a buy signal per unit of model output.
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    /// Calendar days of records the features are computed over.
+    #[serde(default = "default_lookback_days")]
+    pub lookback_days: i64,
+    /// Trading days entries are built over, in equal tranches, instead of at once.
+    #[serde(default)]
+    pub scale_in_days: Option<usize>,
+    /// Regime the market has to be in for entries, instead of the one of the config. Left out
+    /// of the serialized model config when unset, so the cache keys of earlier runs still match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regime_filter: Option<regime::RegimeFilter>,
+}
+
+fn default_scale() -> f64 {
+    100.0
+}
+
+fn default_lookback_days() -> i64 {
+    120
+}
+
+#[cfg(feature = "onnx")]
+type Model = tract_onnx::prelude::TypedRunnableModel<tract_onnx::prelude::TypedModel>;
+#[cfg(not(feature = "onnx"))]
+type Model = ();
+
+#[cfg(feature = "onnx")]
+fn load_model(model_path: &str, feature_count: usize) -> Result<Model, String> {
+    use tract_onnx::prelude::*;
+
+    tract_onnx::onnx()
+        .model_for_path(model_path)
+        .and_then(|model| model.with_input_fact(0, f32::fact([1, feature_count]).into()))
+        .and_then(|model| model.into_optimized())
+        .and_then(|model| model.into_runnable())
+        .map_err(|err| format!("cannot load {}: {}", model_path, err))
+}
+
+#[cfg(not(feature = "onnx"))]
+fn load_model(_model_path: &str, _feature_count: usize) -> Result<Model, String> {
+    Err("built without the onnx feature".to_owned())
+}
+
+#[cfg(feature = "onnx")]
+fn run_model(model: &Model, features: &[f32]) -> Result<f64, String> {
+    use tract_onnx::prelude::*;
+
+    let input =
+        Tensor::from_shape(&[1, features.len()], features).map_err(|err| err.to_string())?;
+    let outputs = model
+        .run(tvec!(input.into()))
+        .map_err(|err| err.to_string())?;
+    let output = outputs[0]
+        .to_array_view::<f32>()
+        .map_err(|err| err.to_string())?;
+
+    output
+        .iter()
+        .next()
+        .map(|value| *value as f64)
+        .ok_or_else(|| "the model has no output".to_owned())
+}
+
+#[cfg(not(feature = "onnx"))]
+fn run_model(_model: &Model, _features: &[f32]) -> Result<f64, String> {
+    Err("built without the onnx feature".to_owned())
+}
+
+pub struct OnnxStrategy {
+    pub backend_op: Rc<dyn backend::BackendOp>,
+    pub back_adjustment: adjust::BackAdjustment,
+    pub model_config: ModelConfig,
+    features: Result<Vec<rule::Expr>, rule::Error>,
+    model: Result<Model, String>,
+}
+
+impl OnnxStrategy {
+    pub fn new(backend_op: Rc<dyn backend::BackendOp>, model_config: ModelConfig) -> Self {
+        let features = model_config
+            .features
+            .iter()
+            .map(|feature| rule::parse(feature, rule::Type::Number, false))
+            .collect();
+        let model = load_model(&model_config.model_path, model_config.features.len());
+
+        OnnxStrategy {
+            backend_op,
+            back_adjustment: adjust::BackAdjustment::default(),
+            model_config,
+            features,
+            model,
+        }
+    }
+
+    /// Feature vector of the last day of `records`, or none while a feature lacks history.
+    pub fn get_features(
+        &self,
+        records: &[schema::RawData],
+    ) -> Result<Option<Vec<f32>>, strategy::Error> {
+        let features = self.features.as_ref().map_err(|err| err.clone())?;
+        let context = rule::Context {
+            records,
+            entry: f64::NAN,
+            hold_days: 0,
+        };
+        let values: Vec<f64> = features
+            .iter()
+            .map(|feature| feature.eval_number(&context))
+            .collect();
+
+        if records.is_empty() || values.iter().any(|value| !value.is_finite()) {
+            return Ok(None);
+        }
+        Ok(Some(values.into_iter().map(|value| value as f32).collect()))
+    }
+
+    /// Model output on `assess_date`, or none when the stock has no record or too little
+    /// history on that day.
+    fn get_output(
+        &self,
+        stock_id: &str,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<Option<(f64, u64)>, strategy::Error> {
+        let model = self
+            .model
+            .as_ref()
+            .map_err(|err| strategy::Error::Model(err.to_owned()))?;
+        let start_date = assess_date
+            .checked_sub_signed(chrono::Duration::days(self.model_config.lookback_days))
+            .ok_or(strategy::Error::BadOperation)?;
+        let records = self.back_adjustment.apply(
+            stock_id,
+            self.backend_op
+                .query_by_range(stock_id, start_date, assess_date)?,
+        );
+
+        match records.last() {
+            Some(record) if record.date == assess_date => {}
+            _ => return Ok(None),
+        }
+
+        let features = match self.get_features(&records)? {
+            Some(features) => features,
+            None => return Ok(None),
+        };
+        let output = run_model(model, &features).map_err(strategy::Error::Model)?;
+
+        Ok(Some((output, records.last().unwrap().trading_volume)))
+    }
+}
+
+impl strategy::StrategyAPI for OnnxStrategy {
+    fn analyze(
+        &self,
+        stock_id: &str,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<strategy::Score, strategy::Error> {
+        let mut score = strategy::Score::default();
+
+        if let Some((output, trading_volume)) = self.get_output(stock_id, assess_date)? {
+            score.point = (output * self.model_config.scale) as i64;
+            score.trading_volume = trading_volume;
+        }
+        Ok(score)
+    }
+
+    fn settle_check(
+        &self,
+        stock_id: &str,
+        _hold_date: chrono::NaiveDate,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<Option<strategy::SettleReason>, strategy::Error> {
+        let settle_below = match self.model_config.settle_below {
+            Some(settle_below) => settle_below,
+            None => return Ok(None),
+        };
+
+        match self.get_output(stock_id, assess_date)? {
+            Some((output, _)) if output < settle_below => {
+                Ok(Some(strategy::SettleReason::SignalExit))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn draw_view(
+        &self,
+        stock_id: &str,
+        style: &diagram::DiagramStyle,
+        output: &diagram::Output,
+    ) -> Result<(), strategy::Error> {
+        let records = self
+            .back_adjustment
+            .apply(stock_id, self.backend_op.query_all(stock_id)?);
+
+        diagram::draw_view_diagram(stock_id, &view::Views::None, &records, None, style, output)
+            .map_err(|_| strategy::Error::BadOperation)
+    }
+
+    fn export_view(&self, stock_id: &str, file_path: &str) -> Result<(), strategy::Error> {
+        let records = self
+            .back_adjustment
+            .apply(stock_id, self.backend_op.query_all(stock_id)?);
+
+        export::to_yaml(file_path, &records);
+        Ok(())
+    }
+
+    fn min_history_days(&self) -> usize {
+        // Roughly the trading days within the lookback window.
+        (self.model_config.lookback_days.max(0) as usize) * 5 / 7
+    }
+
+    fn data_requirements(&self) -> strategy::DataRequirements {
+        strategy::DataRequirements {
+            datasets: vec![strategy::Dataset::StockPrices],
+            lookback_days: self.model_config.lookback_days,
+        }
+    }
+}
+
+#[cfg(test)]
+mod onnx_test {
+    use std::rc::Rc;
+
+    use crate::storage::memory;
+    use crate::strategy::strategy;
+    use crate::testkit::generator;
+
+    use super::{ModelConfig, OnnxStrategy};
+
+    #[test]
+    fn features_check() {
+        let backend_op = Rc::new(memory::MemoryBackend::new());
+        let strategy = OnnxStrategy::new(
+            backend_op.clone(),
+            ModelConfig {
+                name: "model".to_owned(),
+                model_path: "model.onnx".to_owned(),
+                features: vec!["close / sma(5)".to_owned(), "ago(1) - close".to_owned()],
+                settle_below: None,
+                scale: 100.0,
+                lookback_days: 30,
+                scale_in_days: None,
+                regime_filter: None,
+            },
+        );
+        let start_date = chrono::NaiveDate::from_ymd_opt(2022, 1, 3).unwrap();
+        let records = generator::SeriesBuilder::new(start_date, 100.0)
+            .flat(10)
+            .insert(backend_op.as_ref(), "0050")
+            .unwrap();
+
+        assert_eq!(strategy.get_features(&records[..3]).unwrap(), None);
+        assert_eq!(
+            strategy.get_features(&records).unwrap(),
+            Some(vec![1.0, 0.0])
+        );
+        assert!(matches!(
+            strategy::StrategyAPI::analyze(&strategy, "0050", records[9].date),
+            Err(strategy::Error::Model(_))
+        ));
+    }
+}
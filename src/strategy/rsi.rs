@@ -0,0 +1,226 @@
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::dataview::{adjust, view};
+use crate::diagram::diagram;
+use crate::export::export;
+use crate::storage::backend;
+use crate::strategy::{schema, strategy};
+
+/// Default of `RsiParams::period`.
+pub const PERIOD: usize = view::RSI_PERIOD;
+/// Periods of history the RSI is smoothed over before its values are read.
+pub const WARM_UP_PERIODS: usize = 3;
+/// Default of `RsiParams::oversold`.
+pub const OVERSOLD: f64 = 30.0;
+/// Default of `RsiParams::overbought`.
+pub const OVERBOUGHT: f64 = 70.0;
+
+/// Period and thresholds of the RSI.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RsiParams {
+    /// Trading days the RSI is computed over.
+    #[serde(default = "default_period")]
+    pub period: usize,
+    /// RSI below which a stock is oversold.
+    #[serde(default = "default_oversold")]
+    pub oversold: f64,
+    /// RSI above which a stock is overbought.
+    #[serde(default = "default_overbought")]
+    pub overbought: f64,
+}
+
+fn default_period() -> usize {
+    PERIOD
+}
+
+fn default_oversold() -> f64 {
+    OVERSOLD
+}
+
+fn default_overbought() -> f64 {
+    OVERBOUGHT
+}
+
+impl Default for RsiParams {
+    fn default() -> Self {
+        RsiParams {
+            period: default_period(),
+            oversold: default_oversold(),
+            overbought: default_overbought(),
+        }
+    }
+}
+
+/// Buys a stock when its RSI climbs back above the oversold threshold, and settles it once the
+/// RSI reaches the overbought one.
+pub struct Strategy {
+    pub backend_op: Rc<dyn backend::BackendOp>,
+    pub back_adjustment: adjust::BackAdjustment,
+    pub rsi_params: RsiParams,
+}
+
+impl Strategy {
+    /// Records warming up the RSI up to `assess_date`, or none when the stock has no record on
+    /// that day.
+    fn get_records(
+        &self,
+        stock_id: &str,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<Vec<schema::RawData>, strategy::Error> {
+        let calc_date = assess_date
+            .checked_sub_signed(chrono::Duration::days(
+                (self.rsi_params.period * WARM_UP_PERIODS) as i64 * 2,
+            ))
+            .ok_or(strategy::Error::BadOperation)?;
+        let records = self.back_adjustment.apply(
+            stock_id,
+            self.backend_op
+                .query_by_range(stock_id, calc_date, assess_date)?,
+        );
+
+        match records.last() {
+            Some(record) if record.date == assess_date => Ok(records),
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+impl strategy::StrategyAPI for Strategy {
+    fn analyze(
+        &self,
+        stock_id: &str,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<strategy::Score, strategy::Error> {
+        let mut score = strategy::Score::default();
+        let records = self.get_records(stock_id, assess_date)?;
+
+        if records.len() < self.rsi_params.period * WARM_UP_PERIODS {
+            return Ok(score);
+        }
+
+        let views = view::RsiView::transform_by_period(&records, self.rsi_params.period)?;
+        let (prev_view, last_view) = match views.as_slice() {
+            [.., prev_view, last_view] => (prev_view, last_view),
+            _ => return Ok(score),
+        };
+
+        let oversold = self.rsi_params.oversold;
+        let overbought = self.rsi_params.overbought;
+        let is_rebound =
+            prev_view.rsi < oversold && last_view.rsi >= oversold && last_view.rsi < overbought;
+
+        if !is_rebound {
+            return Ok(score);
+        }
+
+        // The further from overbought, the more room the rebound has.
+        score.point = ((overbought - last_view.rsi) as i64).max(1);
+        score.trading_volume = records.last().unwrap().trading_volume;
+        Ok(score)
+    }
+
+    fn settle_check(
+        &self,
+        stock_id: &str,
+        hold_date: chrono::NaiveDate,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<Option<strategy::SettleReason>, strategy::Error> {
+        if assess_date <= hold_date {
+            return Ok(None);
+        }
+
+        let records = self.get_records(stock_id, assess_date)?;
+        let views = view::RsiView::transform_by_period(&records, self.rsi_params.period)?;
+
+        match views.last() {
+            Some(last_view) if last_view.rsi >= self.rsi_params.overbought => {
+                Ok(Some(strategy::SettleReason::SignalExit))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn draw_view(
+        &self,
+        stock_id: &str,
+        style: &diagram::DiagramStyle,
+        output: &diagram::Output,
+    ) -> Result<(), strategy::Error> {
+        let records = self
+            .back_adjustment
+            .apply(stock_id, self.backend_op.query_all(stock_id)?);
+
+        diagram::draw_view_diagram(stock_id, &view::Views::Rsi, &records, None, style, output)
+            .map_err(|_| strategy::Error::BadOperation)
+    }
+
+    fn export_view(&self, stock_id: &str, file_path: &str) -> Result<(), strategy::Error> {
+        let records = self
+            .back_adjustment
+            .apply(stock_id, self.backend_op.query_all(stock_id)?);
+        let views = view::RsiView::transform_by_period(&records, self.rsi_params.period)?;
+
+        export::to_yaml(file_path, &views);
+        Ok(())
+    }
+
+    fn min_history_days(&self) -> usize {
+        self.rsi_params.period * WARM_UP_PERIODS
+    }
+
+    fn data_requirements(&self) -> strategy::DataRequirements {
+        // Mirrors the window read by `get_records`.
+        strategy::DataRequirements {
+            datasets: vec![strategy::Dataset::StockPrices],
+            lookback_days: (self.rsi_params.period * WARM_UP_PERIODS) as i64 * 2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod rsi_test {
+    use std::rc::Rc;
+
+    use crate::dataview::adjust;
+    use crate::storage::memory;
+    use crate::strategy::strategy;
+    use crate::testkit::{generator, signal};
+
+    use super::{RsiParams, Strategy};
+
+    #[test]
+    fn analyze_check() {
+        let backend_op = Rc::new(memory::MemoryBackend::new());
+        let strategy = Strategy {
+            backend_op: backend_op.clone(),
+            back_adjustment: adjust::BackAdjustment::default(),
+            rsi_params: RsiParams::default(),
+        };
+        let start_date = chrono::NaiveDate::from_ymd_opt(2022, 1, 3).unwrap();
+        let flat_records = generator::SeriesBuilder::new(start_date, 100.0)
+            .flat(80)
+            .insert(backend_op.as_ref(), "0050")
+            .unwrap();
+        let records = generator::SeriesBuilder::new(start_date, 100.0)
+            .trend(40, 1.0)
+            .trend(20, -2.0)
+            .trend(20, 2.0)
+            .insert(backend_op.as_ref(), "0051")
+            .unwrap();
+
+        signal::assert_no_buy_signal(&strategy, "0050", &flat_records);
+
+        let buy_dates = signal::get_buy_dates(&strategy, "0051", &records).unwrap();
+
+        assert_eq!(buy_dates.len(), 1);
+        assert!(buy_dates[0] > records[60].date);
+
+        let settle_dates =
+            signal::get_settle_dates(&strategy, "0051", buy_dates[0], &records).unwrap();
+
+        assert!(!settle_dates.is_empty());
+        assert_eq!(settle_dates[0].1, strategy::SettleReason::SignalExit);
+    }
+}
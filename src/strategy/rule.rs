@@ -0,0 +1,850 @@
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use ta::indicators::{
+    ExponentialMovingAverage, RelativeStrengthIndex, SimpleMovingAverage, StandardDeviation,
+};
+use ta::Next;
+
+use crate::core::regime;
+use crate::dataview::{adjust, view};
+use crate::diagram::diagram;
+use crate::export::export;
+use crate::storage::backend;
+use crate::strategy::{schema, strategy};
+
+/// Strategy whose conditions are written as expressions in the config, e.g.
+/// `close > sma(20) + 2 * std(20) and volume > 500000`.
+///
+/// Expressions read the fields `open`, `high`, `low`, `close`, `volume` and `spread` of the
+/// assessed day, numbers, `+ - * /`, comparisons, `and`, `or`, `not` and parentheses, and the
+/// functions below, whose field defaults to `close`:
+///
+/// - `sma(n[, field])`, `ema(n[, field])`, `std(n[, field])` and `rsi(n[, field])` over the
+///   last `n` days up to the assessed one.
+/// - `highest(n[, field])` and `lowest(n[, field])` over the `n` days before the assessed one,
+///   defaulting to `high` and `low`, so that `close > highest(20)` is a breakout.
+/// - `ago(n[, field])`, the field `n` trading days before the assessed one.
+///
+/// Settle conditions may also read `entry`, the close of the day the position was taken, and
+/// `hold_days`, the trading days since. A value needing more history than stored is NaN, which
+/// fails every comparison.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleSet {
+    pub name: String,
+    pub buy: String,
+    /// Exits with `SettleReason::SignalExit` when true.
+    #[serde(default)]
+    pub settle: Option<String>,
+    /// Point of a buy signal, used to rank candidates; 1 when unset.
+    #[serde(default)]
+    pub score: Option<String>,
+    /// Calendar days of records the expressions are evaluated over.
+    #[serde(default = "default_lookback_days")]
+    pub lookback_days: i64,
+    /// Trading days entries are built over, in equal tranches, instead of at once.
+    #[serde(default)]
+    pub scale_in_days: Option<usize>,
+    /// Regime the market has to be in for entries, instead of the one of the config. Left out
+    /// of the serialized rule set when unset, so the cache keys of earlier runs still match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regime_filter: Option<regime::RegimeFilter>,
+}
+
+fn default_lookback_days() -> i64 {
+    120
+}
+
+impl RuleSet {
+    /// Copy with every `$name` placeholder of the expressions replaced by its value in
+    /// `params`, for parameter searches.
+    pub fn bind(&self, params: &BTreeMap<String, f64>) -> RuleSet {
+        let mut names: Vec<&String> = params.keys().collect();
+
+        // Longest first, so that `$period` is not read as `$p` followed by `eriod`.
+        names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+
+        let bind = |expr: &str| {
+            names.iter().fold(expr.to_owned(), |expr, name| {
+                expr.replace(&format!("${}", name), &params[*name].to_string())
+            })
+        };
+
+        RuleSet {
+            name: self.name.clone(),
+            buy: bind(&self.buy),
+            settle: self.settle.as_deref().map(bind),
+            score: self.score.as_deref().map(bind),
+            lookback_days: self.lookback_days,
+            scale_in_days: self.scale_in_days,
+            regime_filter: self.regime_filter.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    Parse(String),
+    Type(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Open,
+    High,
+    Low,
+    Close,
+    Volume,
+    Spread,
+}
+
+impl std::str::FromStr for Field {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(Field::Open),
+            "high" => Ok(Field::High),
+            "low" => Ok(Field::Low),
+            "close" => Ok(Field::Close),
+            "volume" => Ok(Field::Volume),
+            "spread" => Ok(Field::Spread),
+            _ => Err(Error::Parse(format!("unknown field {}", s))),
+        }
+    }
+}
+
+impl Field {
+    fn get(&self, record: &schema::RawData) -> f64 {
+        match self {
+            Field::Open => record.open,
+            Field::High => record.high,
+            Field::Low => record.low,
+            Field::Close => record.close,
+            Field::Volume => record.trading_volume as f64,
+            Field::Spread => record.spread,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    Sma,
+    Ema,
+    Std,
+    Rsi,
+    Highest,
+    Lowest,
+    Ago,
+}
+
+impl std::str::FromStr for Function {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sma" => Ok(Function::Sma),
+            "ema" => Ok(Function::Ema),
+            "std" => Ok(Function::Std),
+            "rsi" => Ok(Function::Rsi),
+            "highest" => Ok(Function::Highest),
+            "lowest" => Ok(Function::Lowest),
+            "ago" => Ok(Function::Ago),
+            _ => Err(Error::Parse(format!("unknown function {}", s))),
+        }
+    }
+}
+
+impl Function {
+    fn get_default_field(&self) -> Field {
+        match self {
+            Function::Highest => Field::High,
+            Function::Lowest => Field::Low,
+            _ => Field::Close,
+        }
+    }
+
+    fn get(&self, period: usize, values: &[f64]) -> f64 {
+        let len = values.len();
+
+        match self {
+            Function::Sma | Function::Ema | Function::Std | Function::Rsi if len < period => {
+                f64::NAN
+            }
+            Function::Sma => get_last(SimpleMovingAverage::new(period).unwrap(), values),
+            Function::Ema => get_last(ExponentialMovingAverage::new(period).unwrap(), values),
+            Function::Std => get_last(StandardDeviation::new(period).unwrap(), values),
+            Function::Rsi => get_last(RelativeStrengthIndex::new(period).unwrap(), values),
+            Function::Highest | Function::Lowest | Function::Ago if len <= period => f64::NAN,
+            Function::Highest => values[len - 1 - period..len - 1]
+                .iter()
+                .cloned()
+                .fold(f64::MIN, f64::max),
+            Function::Lowest => values[len - 1 - period..len - 1]
+                .iter()
+                .cloned()
+                .fold(f64::MAX, f64::min),
+            Function::Ago => values[len - 1 - period],
+        }
+    }
+}
+
+fn get_last<T: Next<f64, Output = f64>>(mut indicator: T, values: &[f64]) -> f64 {
+    values
+        .iter()
+        .fold(f64::NAN, |_, value| indicator.next(*value))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variable {
+    Field(Field),
+    Entry,
+    HoldDays,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Eq,
+    Ne,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Number,
+    Bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Variable(Variable),
+    Call(Function, usize, Field),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+/// Day an expression is evaluated on: `records` ends on it.
+pub struct Context<'a> {
+    pub records: &'a [schema::RawData],
+    /// Close of the day the position was taken, when settling.
+    pub entry: f64,
+    pub hold_days: usize,
+}
+
+impl Expr {
+    pub fn get_type(&self) -> Result<Type, Error> {
+        match self {
+            Expr::Number(_) | Expr::Variable(_) | Expr::Call(..) => Ok(Type::Number),
+            Expr::Neg(expr) => expect_type(expr, Type::Number).map(|_| Type::Number),
+            Expr::Not(expr) => expect_type(expr, Type::Bool).map(|_| Type::Bool),
+            Expr::Binary(op, lhs, rhs) => {
+                let operand_type = match op {
+                    BinaryOp::And | BinaryOp::Or => Type::Bool,
+                    _ => Type::Number,
+                };
+
+                expect_type(lhs, operand_type)?;
+                expect_type(rhs, operand_type)?;
+                match op {
+                    BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div => {
+                        Ok(Type::Number)
+                    }
+                    _ => Ok(Type::Bool),
+                }
+            }
+        }
+    }
+
+    /// Value of a number expression; see `eval_bool` for conditions.
+    pub fn eval_number(&self, context: &Context) -> f64 {
+        match self {
+            Expr::Number(value) => *value,
+            Expr::Variable(Variable::Field(field)) => context
+                .records
+                .last()
+                .map_or(f64::NAN, |record| field.get(record)),
+            Expr::Variable(Variable::Entry) => context.entry,
+            Expr::Variable(Variable::HoldDays) => context.hold_days as f64,
+            Expr::Call(function, period, field) => {
+                let values: Vec<f64> = context
+                    .records
+                    .iter()
+                    .map(|record| field.get(record))
+                    .collect();
+
+                function.get(*period, &values)
+            }
+            Expr::Neg(expr) => -expr.eval_number(context),
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval_number(context), rhs.eval_number(context));
+
+                match op {
+                    BinaryOp::Add => lhs + rhs,
+                    BinaryOp::Sub => lhs - rhs,
+                    BinaryOp::Mul => lhs * rhs,
+                    BinaryOp::Div => lhs / rhs,
+                    _ => f64::NAN,
+                }
+            }
+            Expr::Not(_) => f64::NAN,
+        }
+    }
+
+    pub fn eval_bool(&self, context: &Context) -> bool {
+        match self {
+            Expr::Not(expr) => !expr.eval_bool(context),
+            Expr::Binary(BinaryOp::And, lhs, rhs) => {
+                lhs.eval_bool(context) && rhs.eval_bool(context)
+            }
+            Expr::Binary(BinaryOp::Or, lhs, rhs) => {
+                lhs.eval_bool(context) || rhs.eval_bool(context)
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval_number(context), rhs.eval_number(context));
+
+                match op {
+                    BinaryOp::Gt => lhs > rhs,
+                    BinaryOp::Ge => lhs >= rhs,
+                    BinaryOp::Lt => lhs < rhs,
+                    BinaryOp::Le => lhs <= rhs,
+                    BinaryOp::Eq => lhs == rhs,
+                    BinaryOp::Ne => lhs != rhs,
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+fn expect_type(expr: &Expr, expected: Type) -> Result<(), Error> {
+    let actual = expr.get_type()?;
+
+    if actual != expected {
+        return Err(Error::Type(format!(
+            "expected a {:?} expression, got a {:?} one",
+            expected, actual
+        )));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(&'static str),
+}
+
+const OPS: [&str; 13] = [
+    ">=", "<=", "==", "!=", ">", "<", "+", "-", "*", "/", "(", ")", ",",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = Vec::new();
+    let mut rest = source.trim_start();
+
+    while !rest.is_empty() {
+        let c = rest.chars().next().unwrap();
+
+        if c.is_ascii_digit() || c == '.' {
+            let len = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.' && c != '_')
+                .unwrap_or(rest.len());
+            let number = rest[..len].replace('_', "");
+
+            tokens.push(Token::Number(number.parse().map_err(|_| {
+                Error::Parse(format!("invalid number {}", &rest[..len]))
+            })?));
+            rest = &rest[len..];
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+
+            tokens.push(Token::Ident(rest[..len].to_owned()));
+            rest = &rest[len..];
+        } else if let Some(op) = OPS.iter().find(|op| rest.starts_with(*op)) {
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        } else {
+            return Err(Error::Parse(format!("unexpected character {}", c)));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent over the tokens, loosest binding first: `or`, `and`, `not`, comparisons,
+/// `+ -`, `* /`, unary minus.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    settle: bool,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+
+        self.position += 1;
+        token
+    }
+
+    fn eat_op(&mut self, op: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Op(token)) if *token == op) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(ident)) if ident == keyword) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect_op(&mut self, op: &str) -> Result<(), Error> {
+        if !self.eat_op(op) {
+            return Err(Error::Parse(format!(
+                "expected {} at token {}",
+                op,
+                self.position + 1
+            )));
+        }
+        Ok(())
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.parse_and()?;
+
+        while self.eat_keyword("or") {
+            expr = Expr::Binary(BinaryOp::Or, Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.parse_not()?;
+
+        while self.eat_keyword("and") {
+            expr = Expr::Binary(BinaryOp::And, Box::new(expr), Box::new(self.parse_not()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, Error> {
+        if self.eat_keyword("not") {
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, Error> {
+        let expr = self.parse_sum()?;
+        let ops = [
+            (">=", BinaryOp::Ge),
+            ("<=", BinaryOp::Le),
+            ("==", BinaryOp::Eq),
+            ("!=", BinaryOp::Ne),
+            (">", BinaryOp::Gt),
+            ("<", BinaryOp::Lt),
+        ];
+
+        for (op, binary_op) in ops {
+            if self.eat_op(op) {
+                return Ok(Expr::Binary(
+                    binary_op,
+                    Box::new(expr),
+                    Box::new(self.parse_sum()?),
+                ));
+            }
+        }
+        Ok(expr)
+    }
+
+    fn parse_sum(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.parse_product()?;
+
+        loop {
+            let op = if self.eat_op("+") {
+                BinaryOp::Add
+            } else if self.eat_op("-") {
+                BinaryOp::Sub
+            } else {
+                return Ok(expr);
+            };
+
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.parse_product()?));
+        }
+    }
+
+    fn parse_product(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.parse_unary()?;
+
+        loop {
+            let op = if self.eat_op("*") {
+                BinaryOp::Mul
+            } else if self.eat_op("/") {
+                BinaryOp::Div
+            } else {
+                return Ok(expr);
+            };
+
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.parse_unary()?));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, Error> {
+        if self.eat_op("-") {
+            return Ok(Expr::Neg(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, Error> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Expr::Number(value)),
+            Some(Token::Op("(")) => {
+                let expr = self.parse_or()?;
+
+                self.expect_op(")")?;
+                Ok(expr)
+            }
+            Some(Token::Ident(ident)) if self.peek() == Some(&Token::Op("(")) => {
+                self.parse_call(&ident)
+            }
+            Some(Token::Ident(ident)) => match ident.as_str() {
+                "entry" | "hold_days" if !self.settle => Err(Error::Parse(format!(
+                    "{} is only available in settle conditions",
+                    ident
+                ))),
+                "entry" => Ok(Expr::Variable(Variable::Entry)),
+                "hold_days" => Ok(Expr::Variable(Variable::HoldDays)),
+                _ => Ok(Expr::Variable(Variable::Field(ident.parse()?))),
+            },
+            token => Err(Error::Parse(format!("unexpected token {:?}", token))),
+        }
+    }
+
+    /// `name(period[, field])`; the period has to be a positive whole number.
+    fn parse_call(&mut self, name: &str) -> Result<Expr, Error> {
+        let function: Function = name.parse()?;
+
+        self.expect_op("(")?;
+
+        let period = match self.next() {
+            Some(Token::Number(value)) if value >= 1.0 && value.fract() == 0.0 => value as usize,
+            token => {
+                return Err(Error::Parse(format!(
+                    "{} expects a positive whole period, got {:?}",
+                    name, token
+                )))
+            }
+        };
+        let field = if self.eat_op(",") {
+            match self.next() {
+                Some(Token::Ident(ident)) => ident.parse()?,
+                token => return Err(Error::Parse(format!("expected a field, got {:?}", token))),
+            }
+        } else {
+            function.get_default_field()
+        };
+
+        self.expect_op(")")?;
+        Ok(Expr::Call(function, period, field))
+    }
+}
+
+/// Parses an expression and checks it evaluates to `expected`. `settle` allows the position
+/// variables.
+pub fn parse(source: &str, expected: Type, settle: bool) -> Result<Expr, Error> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        position: 0,
+        settle,
+    };
+    let expr = parser.parse_or()?;
+
+    if let Some(token) = parser.peek() {
+        return Err(Error::Parse(format!("unexpected token {:?}", token)));
+    }
+    expect_type(&expr, expected)?;
+    Ok(expr)
+}
+
+struct Rules {
+    buy: Expr,
+    settle: Option<Expr>,
+    score: Option<Expr>,
+}
+
+impl Rules {
+    fn new(rule_set: &RuleSet) -> Result<Self, Error> {
+        Ok(Rules {
+            buy: parse(&rule_set.buy, Type::Bool, false)?,
+            settle: rule_set
+                .settle
+                .as_ref()
+                .map(|settle| parse(settle, Type::Bool, true))
+                .transpose()?,
+            score: rule_set
+                .score
+                .as_ref()
+                .map(|score| parse(score, Type::Number, false))
+                .transpose()?,
+        })
+    }
+}
+
+pub struct RuleStrategy {
+    pub backend_op: Rc<dyn backend::BackendOp>,
+    pub back_adjustment: adjust::BackAdjustment,
+    pub rule_set: RuleSet,
+    rules: Result<Rules, Error>,
+}
+
+impl RuleStrategy {
+    pub fn new(backend_op: Rc<dyn backend::BackendOp>, rule_set: RuleSet) -> Self {
+        let rules = Rules::new(&rule_set);
+
+        RuleStrategy {
+            backend_op,
+            back_adjustment: adjust::BackAdjustment::default(),
+            rule_set,
+            rules,
+        }
+    }
+
+    fn get_rules(&self) -> Result<&Rules, strategy::Error> {
+        self.rules.as_ref().map_err(|err| err.clone().into())
+    }
+
+    /// Records of the lookback window ending on `assess_date`, or none when the stock has no
+    /// record on that day.
+    fn get_records(
+        &self,
+        stock_id: &str,
+        start_date: chrono::NaiveDate,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<Vec<schema::RawData>, strategy::Error> {
+        let start_date = start_date
+            .checked_sub_signed(chrono::Duration::days(self.rule_set.lookback_days))
+            .ok_or(strategy::Error::BadOperation)?;
+        let records = self.back_adjustment.apply(
+            stock_id,
+            self.backend_op
+                .query_by_range(stock_id, start_date, assess_date)?,
+        );
+
+        match records.last() {
+            Some(record) if record.date == assess_date => Ok(records),
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+impl strategy::StrategyAPI for RuleStrategy {
+    fn analyze(
+        &self,
+        stock_id: &str,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<strategy::Score, strategy::Error> {
+        let rules = self.get_rules()?;
+        let mut score = strategy::Score::default();
+        let records = self.get_records(stock_id, assess_date, assess_date)?;
+        let context = Context {
+            records: &records,
+            entry: f64::NAN,
+            hold_days: 0,
+        };
+
+        if records.is_empty() || !rules.buy.eval_bool(&context) {
+            return Ok(score);
+        }
+
+        score.point = match rules.score {
+            Some(ref expr) => expr.eval_number(&context) as i64,
+            None => 1,
+        };
+        score.trading_volume = records.last().unwrap().trading_volume;
+        Ok(score)
+    }
+
+    fn settle_check(
+        &self,
+        stock_id: &str,
+        hold_date: chrono::NaiveDate,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<Option<strategy::SettleReason>, strategy::Error> {
+        let rules = self.get_rules()?;
+        let settle = match rules.settle {
+            Some(ref settle) => settle,
+            None => return Ok(None),
+        };
+        let records = self.get_records(stock_id, hold_date, assess_date)?;
+        let hold_index = match records.iter().position(|record| record.date >= hold_date) {
+            Some(hold_index) => hold_index,
+            None => return Ok(None),
+        };
+        let context = Context {
+            records: &records,
+            entry: records[hold_index].close,
+            hold_days: records.len() - 1 - hold_index,
+        };
+
+        if settle.eval_bool(&context) {
+            return Ok(Some(strategy::SettleReason::SignalExit));
+        }
+        Ok(None)
+    }
+
+    fn draw_view(
+        &self,
+        stock_id: &str,
+        style: &diagram::DiagramStyle,
+        output: &diagram::Output,
+    ) -> Result<(), strategy::Error> {
+        let records = self
+            .back_adjustment
+            .apply(stock_id, self.backend_op.query_all(stock_id)?);
+
+        diagram::draw_view_diagram(stock_id, &view::Views::None, &records, None, style, output)
+            .map_err(|_| strategy::Error::BadOperation)
+    }
+
+    fn export_view(&self, stock_id: &str, file_path: &str) -> Result<(), strategy::Error> {
+        let records = self
+            .back_adjustment
+            .apply(stock_id, self.backend_op.query_all(stock_id)?);
+
+        export::to_yaml(file_path, &records);
+        Ok(())
+    }
+
+    fn min_history_days(&self) -> usize {
+        // Roughly the trading days within the lookback window.
+        (self.rule_set.lookback_days.max(0) as usize) * 5 / 7
+    }
+
+    fn data_requirements(&self) -> strategy::DataRequirements {
+        strategy::DataRequirements {
+            datasets: vec![strategy::Dataset::StockPrices],
+            lookback_days: self.rule_set.lookback_days,
+        }
+    }
+}
+
+#[cfg(test)]
+mod rule_test {
+    use std::rc::Rc;
+
+    use crate::storage::memory;
+    use crate::strategy::strategy;
+    use crate::testkit::{generator, signal};
+
+    use super::{parse, BinaryOp, Error, Expr, Field, Function, RuleSet, RuleStrategy, Type};
+
+    #[test]
+    fn parse_check() {
+        let expr = parse("close > sma(20) + 2 * std(20, close)", Type::Bool, false).unwrap();
+
+        assert_eq!(
+            expr,
+            Expr::Binary(
+                BinaryOp::Gt,
+                Box::new(Expr::Variable(super::Variable::Field(Field::Close))),
+                Box::new(Expr::Binary(
+                    BinaryOp::Add,
+                    Box::new(Expr::Call(Function::Sma, 20, Field::Close)),
+                    Box::new(Expr::Binary(
+                        BinaryOp::Mul,
+                        Box::new(Expr::Number(2.0)),
+                        Box::new(Expr::Call(Function::Std, 20, Field::Close)),
+                    )),
+                )),
+            )
+        );
+        assert!(parse("volume > 500_000 and not close < ago(1)", Type::Bool, false).is_ok());
+        assert!(matches!(
+            parse("close + 1", Type::Bool, false),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            parse("close > entry", Type::Bool, false),
+            Err(Error::Parse(_))
+        ));
+        assert!(matches!(
+            parse("close > sma(0)", Type::Bool, false),
+            Err(Error::Parse(_))
+        ));
+
+        let rule_set = RuleSet {
+            name: "band".to_owned(),
+            buy: "close > sma($p) + $pb * std($p)".to_owned(),
+            settle: None,
+            score: None,
+            lookback_days: 60,
+            scale_in_days: None,
+            regime_filter: None,
+        }
+        .bind(&[("p".to_owned(), 20.0), ("pb".to_owned(), 1.5)].into());
+
+        assert_eq!(rule_set.buy, "close > sma(20) + 1.5 * std(20)");
+    }
+
+    #[test]
+    fn rule_strategy_check() {
+        let backend_op = Rc::new(memory::MemoryBackend::new());
+        let strategy = RuleStrategy::new(
+            backend_op.clone(),
+            RuleSet {
+                name: "breakout".to_owned(),
+                buy: "close > highest(20, close) and close > sma(20)".to_owned(),
+                settle: Some("close < entry * 0.9 or hold_days >= 30".to_owned()),
+                score: Some("(close - sma(20)) / sma(20) * 100".to_owned()),
+                lookback_days: 60,
+                scale_in_days: None,
+                regime_filter: None,
+            },
+        );
+        let start_date = chrono::NaiveDate::from_ymd_opt(2022, 1, 3).unwrap();
+        let flat_records = generator::SeriesBuilder::new(start_date, 100.0)
+            .flat(40)
+            .insert(backend_op.as_ref(), "0050")
+            .unwrap();
+        let trend_records = generator::SeriesBuilder::new(start_date, 100.0)
+            .trend(40, 1.0)
+            .trend(5, -5.0)
+            .insert(backend_op.as_ref(), "0051")
+            .unwrap();
+
+        signal::assert_no_buy_signal(&strategy, "0050", &flat_records);
+        signal::assert_buy_signal(&strategy, "0051", trend_records[39].date);
+        assert_eq!(
+            signal::get_settle_dates(&strategy, "0051", trend_records[39].date, &trend_records)
+                .unwrap()
+                .first(),
+            Some(&(trend_records[42].date, strategy::SettleReason::SignalExit))
+        );
+    }
+}
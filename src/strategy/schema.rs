@@ -1,7 +1,7 @@
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct RawData {
     pub open: f64,
     pub high: f64,
@@ -46,6 +46,36 @@ impl std::fmt::Display for RawData {
     }
 }
 
+impl ta::Open for RawData {
+    fn open(&self) -> f64 {
+        self.open
+    }
+}
+
+impl ta::High for RawData {
+    fn high(&self) -> f64 {
+        self.high
+    }
+}
+
+impl ta::Low for RawData {
+    fn low(&self) -> f64 {
+        self.low
+    }
+}
+
+impl ta::Close for RawData {
+    fn close(&self) -> f64 {
+        self.close
+    }
+}
+
+impl ta::Volume for RawData {
+    fn volume(&self) -> f64 {
+        self.trading_volume as f64
+    }
+}
+
 impl std::default::Default for RawData {
     fn default() -> Self {
         RawData {
@@ -0,0 +1,384 @@
+use std::rc::Rc;
+
+use ta::indicators::{SimpleMovingAverage, StandardDeviation};
+use ta::Next;
+
+use crate::dataview::{adjust, view};
+use crate::diagram::diagram;
+use crate::export::export;
+use crate::storage::backend;
+use crate::strategy::{schema, strategy};
+
+/// Calendar days of records passed to the script when it does not define `lookback_days()`.
+pub const DEFAULT_LOOKBACK_DAYS: i64 = 120;
+/// Trading days of warm-up when the script does not define `min_history_days()`.
+pub const DEFAULT_MIN_HISTORY_DAYS: usize = 60;
+
+/// Strategy written as a Rhai script, so ideas can be tried without recompiling the crate.
+///
+/// The script defines `analyze(series)`, returning the score of the last day of `series` as an
+/// integer, above zero to buy. It may define `settle_check(series, hold_date)`, returning true to
+/// exit, and `lookback_days()` and `min_history_days()`. `series` is a map of `date`, `open`,
+/// `high`, `low`, `close` and `volume` arrays ending on the assessed day, and the helpers
+/// `sma(values, period)`, `sd(values, period)`, `returns(values)`, `highest(values, period)`
+/// and `lowest(values, period)` are available.
+pub struct ScriptStrategy {
+    pub backend_op: Rc<dyn backend::BackendOp>,
+    pub back_adjustment: adjust::BackAdjustment,
+    engine: rhai::Engine,
+    ast: Result<rhai::AST, String>,
+}
+
+fn get_floats(values: &rhai::Array) -> Vec<f64> {
+    values
+        .iter()
+        .map(|value| {
+            value
+                .as_float()
+                .or_else(|_| value.as_int().map(|value| value as f64))
+                .unwrap_or(f64::NAN)
+        })
+        .collect()
+}
+
+fn get_array(values: Vec<f64>) -> rhai::Array {
+    values.into_iter().map(rhai::Dynamic::from_float).collect()
+}
+
+fn get_period(period: i64) -> Result<usize, Box<rhai::EvalAltResult>> {
+    if period <= 0 {
+        return Err(format!("invalid period {}", period).into());
+    }
+    Ok(period as usize)
+}
+
+fn get_window(values: &rhai::Array, period: i64) -> Result<Vec<f64>, Box<rhai::EvalAltResult>> {
+    let values = get_floats(values);
+    let period = get_period(period)?;
+
+    Ok(values[values.len().saturating_sub(period)..].to_vec())
+}
+
+fn get_engine() -> rhai::Engine {
+    let mut engine = rhai::Engine::new();
+
+    engine.register_fn(
+        "sma",
+        |values: rhai::Array, period: i64| -> Result<rhai::Array, Box<rhai::EvalAltResult>> {
+            let mut sma = SimpleMovingAverage::new(get_period(period)?)
+                .map_err(|err| format!("{:?}", err))?;
+
+            Ok(get_array(
+                get_floats(&values)
+                    .into_iter()
+                    .map(|value| sma.next(value))
+                    .collect(),
+            ))
+        },
+    );
+    engine.register_fn(
+        "sd",
+        |values: rhai::Array, period: i64| -> Result<rhai::Array, Box<rhai::EvalAltResult>> {
+            let mut sd =
+                StandardDeviation::new(get_period(period)?).map_err(|err| format!("{:?}", err))?;
+
+            Ok(get_array(
+                get_floats(&values)
+                    .into_iter()
+                    .map(|value| sd.next(value))
+                    .collect(),
+            ))
+        },
+    );
+    engine.register_fn("returns", |values: rhai::Array| -> rhai::Array {
+        let values = get_floats(&values);
+
+        get_array(
+            (0..values.len())
+                .map(|index| match index {
+                    0 => 0.0,
+                    _ if values[index - 1] == 0.0 => 0.0,
+                    _ => (values[index] - values[index - 1]) / values[index - 1] * 100.0,
+                })
+                .collect(),
+        )
+    });
+    engine.register_fn(
+        "highest",
+        |values: rhai::Array, period: i64| -> Result<f64, Box<rhai::EvalAltResult>> {
+            Ok(get_window(&values, period)?
+                .into_iter()
+                .fold(f64::NAN, f64::max))
+        },
+    );
+    engine.register_fn(
+        "lowest",
+        |values: rhai::Array, period: i64| -> Result<f64, Box<rhai::EvalAltResult>> {
+            Ok(get_window(&values, period)?
+                .into_iter()
+                .fold(f64::NAN, f64::min))
+        },
+    );
+    engine
+}
+
+fn get_series(records: &[schema::RawData]) -> rhai::Map {
+    let mut series = rhai::Map::new();
+
+    series.insert(
+        "date".into(),
+        rhai::Dynamic::from_array(
+            records
+                .iter()
+                .map(|record| rhai::Dynamic::from(record.date.to_string()))
+                .collect(),
+        ),
+    );
+    series.insert(
+        "open".into(),
+        rhai::Dynamic::from_array(get_array(
+            records.iter().map(|record| record.open).collect(),
+        )),
+    );
+    series.insert(
+        "high".into(),
+        rhai::Dynamic::from_array(get_array(
+            records.iter().map(|record| record.high).collect(),
+        )),
+    );
+    series.insert(
+        "low".into(),
+        rhai::Dynamic::from_array(get_array(records.iter().map(|record| record.low).collect())),
+    );
+    series.insert(
+        "close".into(),
+        rhai::Dynamic::from_array(get_array(
+            records.iter().map(|record| record.close).collect(),
+        )),
+    );
+    series.insert(
+        "volume".into(),
+        rhai::Dynamic::from_array(get_array(
+            records
+                .iter()
+                .map(|record| record.trading_volume as f64)
+                .collect(),
+        )),
+    );
+    series
+}
+
+impl ScriptStrategy {
+    pub fn new(backend_op: Rc<dyn backend::BackendOp>, script_path: &str) -> Self {
+        match std::fs::read_to_string(script_path) {
+            Ok(source) => ScriptStrategy::from_source(backend_op, &source),
+            Err(err) => ScriptStrategy {
+                backend_op,
+                back_adjustment: adjust::BackAdjustment::default(),
+                engine: get_engine(),
+                ast: Err(format!("cannot read {}: {}", script_path, err)),
+            },
+        }
+    }
+
+    pub fn from_source(backend_op: Rc<dyn backend::BackendOp>, source: &str) -> Self {
+        let engine = get_engine();
+        let ast = engine.compile(source).map_err(|err| err.to_string());
+
+        ScriptStrategy {
+            backend_op,
+            back_adjustment: adjust::BackAdjustment::default(),
+            engine,
+            ast,
+        }
+    }
+
+    fn get_ast(&self) -> Result<&rhai::AST, strategy::Error> {
+        self.ast
+            .as_ref()
+            .map_err(|err| strategy::Error::Script(err.to_owned()))
+    }
+
+    fn has_fn(&self, name: &str) -> bool {
+        self.ast
+            .as_ref()
+            .is_ok_and(|ast| ast.iter_functions().any(|function| function.name == name))
+    }
+
+    fn call_fn<T: Clone + Send + Sync + 'static>(
+        &self,
+        name: &str,
+        args: impl rhai::FuncArgs,
+    ) -> Result<T, strategy::Error> {
+        self.engine
+            .call_fn::<T>(&mut rhai::Scope::new(), self.get_ast()?, name, args)
+            .map_err(|err| strategy::Error::Script(err.to_string()))
+    }
+
+    fn get_lookback_days(&self) -> i64 {
+        if !self.has_fn("lookback_days") {
+            return DEFAULT_LOOKBACK_DAYS;
+        }
+        self.call_fn::<i64>("lookback_days", ())
+            .unwrap_or(DEFAULT_LOOKBACK_DAYS)
+    }
+
+    /// Records of the lookback window ending on `assess_date`, or none when the stock has no
+    /// record on that day.
+    fn get_records(
+        &self,
+        stock_id: &str,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<Vec<schema::RawData>, strategy::Error> {
+        let start_date = assess_date
+            .checked_sub_signed(chrono::Duration::days(self.get_lookback_days()))
+            .ok_or(strategy::Error::BadOperation)?;
+        let records = self.back_adjustment.apply(
+            stock_id,
+            self.backend_op
+                .query_by_range(stock_id, start_date, assess_date)?,
+        );
+
+        match records.last() {
+            Some(record) if record.date == assess_date => Ok(records),
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+impl strategy::StrategyAPI for ScriptStrategy {
+    fn analyze(
+        &self,
+        stock_id: &str,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<strategy::Score, strategy::Error> {
+        let mut score = strategy::Score::default();
+        let records = self.get_records(stock_id, assess_date)?;
+
+        if let Some(last_record) = records.last() {
+            score.point = self.call_fn::<i64>("analyze", (get_series(&records),))?;
+            score.trading_volume = last_record.trading_volume;
+        }
+        Ok(score)
+    }
+
+    fn settle_check(
+        &self,
+        stock_id: &str,
+        hold_date: chrono::NaiveDate,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<Option<strategy::SettleReason>, strategy::Error> {
+        if !self.has_fn("settle_check") {
+            return Ok(None);
+        }
+
+        let records = self.get_records(stock_id, assess_date)?;
+
+        if records.is_empty() {
+            return Ok(None);
+        }
+        if self.call_fn::<bool>(
+            "settle_check",
+            (get_series(&records), hold_date.to_string()),
+        )? {
+            return Ok(Some(strategy::SettleReason::SignalExit));
+        }
+        Ok(None)
+    }
+
+    fn draw_view(
+        &self,
+        stock_id: &str,
+        style: &diagram::DiagramStyle,
+        output: &diagram::Output,
+    ) -> Result<(), strategy::Error> {
+        let records = self
+            .back_adjustment
+            .apply(stock_id, self.backend_op.query_all(stock_id)?);
+
+        diagram::draw_view_diagram(stock_id, &view::Views::None, &records, None, style, output)
+            .map_err(|_| strategy::Error::BadOperation)
+    }
+
+    fn export_view(&self, stock_id: &str, file_path: &str) -> Result<(), strategy::Error> {
+        let records = self
+            .back_adjustment
+            .apply(stock_id, self.backend_op.query_all(stock_id)?);
+
+        export::to_yaml(file_path, &records);
+        Ok(())
+    }
+
+    fn min_history_days(&self) -> usize {
+        if !self.has_fn("min_history_days") {
+            return DEFAULT_MIN_HISTORY_DAYS;
+        }
+        self.call_fn::<i64>("min_history_days", ())
+            .map(|days| days.max(0) as usize)
+            .unwrap_or(DEFAULT_MIN_HISTORY_DAYS)
+    }
+
+    fn data_requirements(&self) -> strategy::DataRequirements {
+        strategy::DataRequirements {
+            datasets: vec![strategy::Dataset::StockPrices],
+            lookback_days: self.get_lookback_days(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod script_test {
+    use std::rc::Rc;
+
+    use crate::storage::memory;
+    use crate::strategy::strategy;
+    use crate::testkit::{generator, signal};
+
+    use super::ScriptStrategy;
+
+    const SCRIPT: &str = r#"
+        fn lookback_days() { 60 }
+
+        fn analyze(series) {
+            let close = series.close;
+            if close[-1] > sma(close, 20)[-1] + sd(close, 20)[-1] { 1 } else { 0 }
+        }
+
+        fn settle_check(series, hold_date) {
+            series.close[-1] < sma(series.close, 10)[-1]
+        }
+    "#;
+
+    #[test]
+    fn script_check() {
+        let backend_op = Rc::new(memory::MemoryBackend::new());
+        let strategy = ScriptStrategy::from_source(backend_op.clone(), SCRIPT);
+        let start_date = chrono::NaiveDate::from_ymd_opt(2022, 1, 3).unwrap();
+        let flat_records = generator::SeriesBuilder::new(start_date, 100.0)
+            .flat(40)
+            .insert(backend_op.as_ref(), "0050")
+            .unwrap();
+        let trend_records = generator::SeriesBuilder::new(start_date, 100.0)
+            .trend(40, 1.0)
+            .trend(5, -5.0)
+            .insert(backend_op.as_ref(), "0051")
+            .unwrap();
+
+        signal::assert_no_buy_signal(&strategy, "0050", &flat_records);
+        signal::assert_buy_signal(&strategy, "0051", trend_records[39].date);
+        signal::assert_settle_signal(
+            &strategy,
+            "0051",
+            trend_records[39].date,
+            trend_records[44].date,
+            strategy::SettleReason::SignalExit,
+        );
+        assert_eq!(strategy::StrategyAPI::min_history_days(&strategy), 60);
+        assert!(strategy::StrategyAPI::analyze(
+            &ScriptStrategy::from_source(backend_op, "fn analyze("),
+            "0050",
+            flat_records[39].date
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,281 @@
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::dataview::{adjust, view};
+use crate::diagram::diagram;
+use crate::export::export;
+use crate::storage::backend;
+use crate::strategy::{schema, strategy};
+
+/// Default of `SqueezeParams::period`.
+pub const PERIOD: usize = 20;
+/// Default of `SqueezeParams::band_size`.
+pub const BAND_SIZE: f64 = 2.0;
+pub const KELTNER_MULTIPLIER: f64 = view::KELTNER_MULTIPLIER;
+/// Default of `SqueezeParams::analyze_range`.
+pub const ANALYZE_RANGE: usize = 30;
+/// Default of `SqueezeParams::min_squeeze_days`.
+pub const MIN_SQUEEZE_DAYS: usize = 5;
+
+/// How the bands and the channel are drawn and how long a squeeze has to last.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SqueezeParams {
+    /// Trading days of the moving averages the bands and the channel are drawn around.
+    #[serde(default = "default_period")]
+    pub period: usize,
+    /// Standard deviations between the middle band and the outer ones.
+    #[serde(default = "default_band_size")]
+    pub band_size: f64,
+    /// Trading days looked back over for the squeeze before the assess day.
+    #[serde(default = "default_analyze_range")]
+    pub analyze_range: usize,
+    /// Trading days the bands have to stay inside the channel before a release counts.
+    #[serde(default = "default_min_squeeze_days")]
+    pub min_squeeze_days: usize,
+}
+
+fn default_period() -> usize {
+    PERIOD
+}
+
+fn default_band_size() -> f64 {
+    BAND_SIZE
+}
+
+fn default_analyze_range() -> usize {
+    ANALYZE_RANGE
+}
+
+fn default_min_squeeze_days() -> usize {
+    MIN_SQUEEZE_DAYS
+}
+
+impl Default for SqueezeParams {
+    fn default() -> Self {
+        SqueezeParams {
+            period: default_period(),
+            band_size: default_band_size(),
+            analyze_range: default_analyze_range(),
+            min_squeeze_days: default_min_squeeze_days(),
+        }
+    }
+}
+
+/// Bollinger bands and Keltner channel of a day.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SqueezeView {
+    pub bollinger_band: view::BollingerBandView,
+    pub keltner: view::KeltnerView,
+}
+
+impl SqueezeView {
+    /// Whether the Bollinger bands, `band_size` standard deviations wide, lie inside the Keltner
+    /// channel: volatility is low against the usual range of the stock.
+    pub fn is_squeeze(&self, band_size: f64) -> bool {
+        let band_width = band_size * self.bollinger_band.sd;
+
+        self.bollinger_band.sma + band_width < self.keltner.upper
+            && self.bollinger_band.sma - band_width > self.keltner.lower
+    }
+}
+
+/// Buys a stock on the day its Bollinger bands break out of the Keltner channel upwards after a
+/// squeeze, and settles it once the close falls back below the middle of the channel.
+pub struct Strategy {
+    pub backend_op: Rc<dyn backend::BackendOp>,
+    pub back_adjustment: adjust::BackAdjustment,
+    pub squeeze_params: SqueezeParams,
+}
+
+impl Strategy {
+    fn transform(&self, records: &[schema::RawData]) -> Result<Vec<SqueezeView>, strategy::Error> {
+        let bollinger_band_views = view::BollingerBandView::transform_by_params(
+            records,
+            self.squeeze_params.period,
+            self.squeeze_params.band_size,
+            view::BandBasis::Sma,
+        )?;
+        let keltner_views = view::KeltnerView::transform_by_params(
+            records,
+            self.squeeze_params.period,
+            KELTNER_MULTIPLIER,
+        )?;
+
+        // Both views start once `period` records are in, so they line up day by day.
+        Ok(bollinger_band_views
+            .into_iter()
+            .zip(keltner_views)
+            .map(|(bollinger_band, keltner)| SqueezeView {
+                bollinger_band,
+                keltner,
+            })
+            .collect())
+    }
+
+    /// Views up to `assess_date`, or none when the stock has no record on that day.
+    fn get_views(
+        &self,
+        stock_id: &str,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<Vec<SqueezeView>, strategy::Error> {
+        let calc_date = assess_date
+            .checked_sub_signed(chrono::Duration::days(
+                (self.squeeze_params.period + self.squeeze_params.analyze_range) as i64 * 2,
+            ))
+            .ok_or(strategy::Error::BadOperation)?;
+        let records = self.back_adjustment.apply(
+            stock_id,
+            self.backend_op
+                .query_by_range(stock_id, calc_date, assess_date)?,
+        );
+
+        match records.last() {
+            Some(record) if record.date == assess_date => self.transform(&records),
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+impl strategy::StrategyAPI for Strategy {
+    fn analyze(
+        &self,
+        stock_id: &str,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<strategy::Score, strategy::Error> {
+        let mut score = strategy::Score::default();
+        let views = self.get_views(stock_id, assess_date)?;
+        let (last_view, prev_views) = match views.split_last() {
+            Some(split) => split,
+            None => return Ok(score),
+        };
+
+        let band_size = self.squeeze_params.band_size;
+
+        if last_view.is_squeeze(band_size) || last_view.keltner.close <= last_view.keltner.ema {
+            return Ok(score);
+        }
+
+        let squeeze_days = prev_views
+            .iter()
+            .rev()
+            .take_while(|view| view.is_squeeze(band_size))
+            .count();
+
+        if squeeze_days < self.squeeze_params.min_squeeze_days {
+            return Ok(score);
+        }
+
+        // The longer the squeeze, the larger the expansion that tends to follow it.
+        score.point = squeeze_days as i64;
+        score.trading_volume = last_view.bollinger_band.volume;
+        Ok(score)
+    }
+
+    fn settle_check(
+        &self,
+        stock_id: &str,
+        hold_date: chrono::NaiveDate,
+        assess_date: chrono::NaiveDate,
+    ) -> Result<Option<strategy::SettleReason>, strategy::Error> {
+        if assess_date <= hold_date {
+            return Ok(None);
+        }
+
+        match self.get_views(stock_id, assess_date)?.last() {
+            Some(last_view) if last_view.keltner.close < last_view.keltner.ema => {
+                Ok(Some(strategy::SettleReason::SignalExit))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn draw_view(
+        &self,
+        stock_id: &str,
+        style: &diagram::DiagramStyle,
+        output: &diagram::Output,
+    ) -> Result<(), strategy::Error> {
+        let records = self
+            .back_adjustment
+            .apply(stock_id, self.backend_op.query_all(stock_id)?);
+        let (bollinger_band_views, keltner_views): (Vec<_>, Vec<_>) = self
+            .transform(&records)?
+            .into_iter()
+            .map(|view| (view.bollinger_band, view.keltner))
+            .unzip();
+        let plot = diagram::render_squeeze(
+            &bollinger_band_views,
+            &keltner_views,
+            self.squeeze_params.period,
+            self.squeeze_params.band_size,
+            style,
+        );
+
+        diagram::render(&plot, style, output);
+        Ok(())
+    }
+
+    fn export_view(&self, stock_id: &str, file_path: &str) -> Result<(), strategy::Error> {
+        let records = self
+            .back_adjustment
+            .apply(stock_id, self.backend_op.query_all(stock_id)?);
+
+        export::to_yaml(file_path, &self.transform(&records)?);
+        Ok(())
+    }
+
+    fn min_history_days(&self) -> usize {
+        self.squeeze_params.period + self.squeeze_params.min_squeeze_days
+    }
+
+    fn data_requirements(&self) -> strategy::DataRequirements {
+        // Mirrors the window read by `get_views`.
+        strategy::DataRequirements {
+            datasets: vec![strategy::Dataset::StockPrices],
+            lookback_days: (self.squeeze_params.period + self.squeeze_params.analyze_range) as i64
+                * 2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod squeeze_test {
+    use std::rc::Rc;
+
+    use crate::dataview::adjust;
+    use crate::storage::memory;
+    use crate::strategy::strategy;
+    use crate::testkit::{generator, signal};
+
+    use super::{SqueezeParams, Strategy};
+
+    #[test]
+    fn analyze_check() {
+        let backend_op = Rc::new(memory::MemoryBackend::new());
+        let strategy = Strategy {
+            backend_op: backend_op.clone(),
+            back_adjustment: adjust::BackAdjustment::default(),
+            squeeze_params: SqueezeParams::default(),
+        };
+        let start_date = chrono::NaiveDate::from_ymd_opt(2022, 1, 3).unwrap();
+        let records = generator::SeriesBuilder::new(start_date, 100.0)
+            .mean_reverting(30, 3.0, 10)
+            .mean_reverting(20, 0.2, 10)
+            .trend(10, 3.0)
+            .trend(10, -3.0)
+            .insert(backend_op.as_ref(), "0050")
+            .unwrap();
+        let buy_dates = signal::get_buy_dates(&strategy, "0050", &records).unwrap();
+
+        assert!(!buy_dates.is_empty());
+        assert!(buy_dates[0] > records[50].date);
+        assert!(buy_dates[0] < records[60].date);
+
+        let settle_dates =
+            signal::get_settle_dates(&strategy, "0050", buy_dates[0], &records).unwrap();
+
+        assert!(settle_dates[0].0 > records[60].date);
+        assert_eq!(settle_dates[0].1, strategy::SettleReason::SignalExit);
+    }
+}
@@ -2,14 +2,85 @@ use std::cmp::Ordering;
 use std::rc::Rc;
 use std::result::Result;
 
-use crate::dataview::view;
-use crate::storage::backend;
+use serde::{Deserialize, Serialize};
 
-use super::bollinger_band;
+use crate::core::regime;
+use crate::dataview::{adjust, view};
+use crate::diagram::diagram;
+use crate::storage::{backend, memory};
 
-#[derive(Clone)]
+use super::{
+    bollinger_band, composite, dca, gap, high_breakout, ma_crossover, onnx, rsi, rule, schema,
+    script, squeeze,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Strategies {
     BollingerBand,
+    /// Rhai script at the given path, see `script::ScriptStrategy`.
+    Script(String),
+    /// Rule set written in the config, see `rule::RuleSet`.
+    Rule(rule::RuleSet),
+    /// ONNX model configured in the config, see `onnx::ModelConfig`.
+    Onnx(onnx::ModelConfig),
+    Rsi,
+    Squeeze,
+    Gap,
+    /// Golden and death crosses of two moving averages, see `ma_crossover::Periods`.
+    MaCrossover(ma_crossover::Periods),
+    /// Closes above the highs of the last weeks, 52 by default, see `high_breakout::Strategy`.
+    HighBreakout,
+    /// Weighted ensemble of strategies, see `composite::CompositeStrategy`.
+    Composite(Vec<(Strategies, f64)>),
+    /// Dollar-cost averaging baseline, see `dca::Plan`.
+    Dca(dca::Plan),
+}
+
+impl Strategies {
+    /// Every built-in strategy.
+    pub fn all() -> Vec<Strategies> {
+        vec![
+            Strategies::BollingerBand,
+            Strategies::Rsi,
+            Strategies::Squeeze,
+            Strategies::Gap,
+            Strategies::MaCrossover(ma_crossover::Periods::default()),
+            Strategies::HighBreakout,
+            Strategies::Dca(dca::Plan::default()),
+        ]
+    }
+
+    /// Built-in strategy named `name`, with its default parameters.
+    pub fn get_builtin(name: &str) -> Option<Strategies> {
+        match name {
+            "bollinger_band" => Some(Strategies::BollingerBand),
+            "rsi" => Some(Strategies::Rsi),
+            "squeeze" => Some(Strategies::Squeeze),
+            "gap" => Some(Strategies::Gap),
+            "ma_crossover" => Some(Strategies::MaCrossover(ma_crossover::Periods::default())),
+            "high_breakout" => Some(Strategies::HighBreakout),
+            "dca" => Some(Strategies::Dca(dca::Plan::default())),
+            _ => None,
+        }
+    }
+
+    /// Trading days the strategy builds its entries over, if more than one.
+    pub fn get_scale_in_days(&self) -> Option<usize> {
+        match self {
+            Strategies::Rule(rule_set) => rule_set.scale_in_days,
+            Strategies::Onnx(model_config) => model_config.scale_in_days,
+            _ => None,
+        }
+    }
+
+    /// Regime filter the strategy sets for itself, if any.
+    pub fn get_regime_filter(&self) -> Option<regime::RegimeFilter> {
+        match self {
+            Strategies::Rule(rule_set) => rule_set.regime_filter.clone(),
+            Strategies::Onnx(model_config) => model_config.regime_filter.clone(),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Eq)]
@@ -54,12 +125,65 @@ impl Ord for Score {
     }
 }
 
+/// Why a position was settled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SettleReason {
+    /// The strategy's exit signal fired.
+    SignalExit,
+    StopLoss,
+    MaxHoldDays,
+    Delisting,
+    /// Liquidated by the circuit breaker rather than by the strategy.
+    CircuitBreaker,
+    /// Settled on the first trading day after a halt.
+    HaltResumed,
+    /// Sold by hand, as reported by the broker statement.
+    Manual,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Dataset {
+    /// Daily prices of every stock of the stock list.
+    StockPrices,
+    /// Daily prices of a single instrument, e.g. an index or a benchmark.
+    InstrumentPrices(String),
+}
+
+/// Data a strategy reads while scoring and settling over a run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataRequirements {
+    pub datasets: Vec<Dataset>,
+    /// Calendar days of history read before the first assessed day.
+    pub lookback_days: i64,
+}
+
+impl DataRequirements {
+    /// Date range the datasets have to cover for a run from `start_date` to `end_date`.
+    pub fn get_window(
+        &self,
+        start_date: chrono::NaiveDate,
+        end_date: chrono::NaiveDate,
+    ) -> (chrono::NaiveDate, chrono::NaiveDate) {
+        (
+            start_date - chrono::Duration::days(self.lookback_days),
+            end_date,
+        )
+    }
+}
+
 #[derive(Debug)]
 pub enum Error {
     Backend(backend::Error),
     Dataview(view::Error),
     BadOperation,
     RecordNotFound,
+    /// The script failed to compile or to run.
+    Script(String),
+    Rule(rule::Error),
+    /// The model failed to load or to run.
+    Model(String),
+    /// A composite without members, or with a weight that is not a finite positive number.
+    InvalidComposite,
 }
 
 impl From<backend::Error> for Error {
@@ -74,8 +198,66 @@ impl From<view::Error> for Error {
     }
 }
 
+impl From<rule::Error> for Error {
+    fn from(err: rule::Error) -> Error {
+        Error::Rule(err)
+    }
+}
+
+/// Tunable parameters of the built-in strategies, set with `strategy_params` in the config.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StrategyParams {
+    #[serde(default)]
+    pub bollinger_band: bollinger_band::BandParams,
+    #[serde(default)]
+    pub ma_crossover: ma_crossover::Periods,
+    #[serde(default)]
+    pub dca: dca::Plan,
+    #[serde(default)]
+    pub rsi: rsi::RsiParams,
+    #[serde(default)]
+    pub squeeze: squeeze::SqueezeParams,
+    #[serde(default)]
+    pub gap: gap::GapParams,
+    #[serde(default)]
+    pub high_breakout: high_breakout::BreakoutParams,
+}
+
 pub enum Strategy {
     BollingerBand(bollinger_band::Strategy),
+    Script(Box<script::ScriptStrategy>),
+    Rule(rule::RuleStrategy),
+    Onnx(Box<onnx::OnnxStrategy>),
+    Rsi(rsi::Strategy),
+    Squeeze(squeeze::Strategy),
+    Gap(gap::Strategy),
+    MaCrossover(ma_crossover::Strategy),
+    HighBreakout(high_breakout::Strategy),
+    Composite(composite::CompositeStrategy),
+    Dca(dca::Strategy),
+}
+
+impl Strategy {
+    /// Computes the views of the stocks of `back_adjustment` on back-adjusted prices.
+    pub fn set_back_adjustment(&mut self, back_adjustment: adjust::BackAdjustment) {
+        match self {
+            Strategy::BollingerBand(bollinger_band) => {
+                bollinger_band.back_adjustment = back_adjustment
+            }
+            Strategy::Script(script) => script.back_adjustment = back_adjustment,
+            Strategy::Rule(rule) => rule.back_adjustment = back_adjustment,
+            Strategy::Onnx(onnx) => onnx.back_adjustment = back_adjustment,
+            Strategy::Rsi(rsi) => rsi.back_adjustment = back_adjustment,
+            Strategy::Squeeze(squeeze) => squeeze.back_adjustment = back_adjustment,
+            Strategy::Gap(gap) => gap.back_adjustment = back_adjustment,
+            Strategy::MaCrossover(ma_crossover) => ma_crossover.back_adjustment = back_adjustment,
+            Strategy::HighBreakout(high_breakout) => {
+                high_breakout.back_adjustment = back_adjustment
+            }
+            Strategy::Composite(composite) => composite.set_back_adjustment(back_adjustment),
+            Strategy::Dca(dca) => dca.back_adjustment = back_adjustment,
+        }
+    }
 }
 
 #[mockall::automock]
@@ -86,8 +268,18 @@ pub trait StrategyAPI {
         stock_id: &str,
         hold_date: chrono::NaiveDate,
         assess_date: chrono::NaiveDate,
-    ) -> Result<bool, Error>;
-    fn draw_view(&self, stock_id: &str) -> Result<(), Error>;
+    ) -> Result<Option<SettleReason>, Error>;
+    fn draw_view(
+        &self,
+        stock_id: &str,
+        style: &diagram::DiagramStyle,
+        output: &diagram::Output,
+    ) -> Result<(), Error>;
+    /// Writes the indicator view series the strategy trades on to a YAML file.
+    fn export_view(&self, stock_id: &str, file_path: &str) -> Result<(), Error>;
+    /// Trading days of history needed before the strategy can score a stock.
+    fn min_history_days(&self) -> usize;
+    fn data_requirements(&self) -> DataRequirements;
 }
 
 impl StrategyAPI for Strategy {
@@ -96,6 +288,18 @@ impl StrategyAPI for Strategy {
             Strategy::BollingerBand(ref bollinger_band) => {
                 bollinger_band.analyze(stock_id, assess_date)
             }
+            Strategy::Script(ref script) => script.analyze(stock_id, assess_date),
+            Strategy::Rule(ref rule) => rule.analyze(stock_id, assess_date),
+            Strategy::Onnx(ref onnx) => onnx.analyze(stock_id, assess_date),
+            Strategy::Rsi(ref rsi) => rsi.analyze(stock_id, assess_date),
+            Strategy::Squeeze(ref squeeze) => squeeze.analyze(stock_id, assess_date),
+            Strategy::Gap(ref gap) => gap.analyze(stock_id, assess_date),
+            Strategy::MaCrossover(ref ma_crossover) => ma_crossover.analyze(stock_id, assess_date),
+            Strategy::HighBreakout(ref high_breakout) => {
+                high_breakout.analyze(stock_id, assess_date)
+            }
+            Strategy::Composite(ref composite) => composite.analyze(stock_id, assess_date),
+            Strategy::Dca(ref dca) => dca.analyze(stock_id, assess_date),
         }
     }
     fn settle_check(
@@ -103,16 +307,106 @@ impl StrategyAPI for Strategy {
         stock_id: &str,
         hold_date: chrono::NaiveDate,
         assess_date: chrono::NaiveDate,
-    ) -> Result<bool, Error> {
+    ) -> Result<Option<SettleReason>, Error> {
         match *self {
             Strategy::BollingerBand(ref bollinger_band) => {
                 bollinger_band.settle_check(stock_id, hold_date, assess_date)
             }
+            Strategy::Script(ref script) => script.settle_check(stock_id, hold_date, assess_date),
+            Strategy::Rule(ref rule) => rule.settle_check(stock_id, hold_date, assess_date),
+            Strategy::Onnx(ref onnx) => onnx.settle_check(stock_id, hold_date, assess_date),
+            Strategy::Rsi(ref rsi) => rsi.settle_check(stock_id, hold_date, assess_date),
+            Strategy::Squeeze(ref squeeze) => {
+                squeeze.settle_check(stock_id, hold_date, assess_date)
+            }
+            Strategy::Gap(ref gap) => gap.settle_check(stock_id, hold_date, assess_date),
+            Strategy::MaCrossover(ref ma_crossover) => {
+                ma_crossover.settle_check(stock_id, hold_date, assess_date)
+            }
+            Strategy::HighBreakout(ref high_breakout) => {
+                high_breakout.settle_check(stock_id, hold_date, assess_date)
+            }
+            Strategy::Composite(ref composite) => {
+                composite.settle_check(stock_id, hold_date, assess_date)
+            }
+            Strategy::Dca(ref dca) => dca.settle_check(stock_id, hold_date, assess_date),
         }
     }
-    fn draw_view(&self, stock_id: &str) -> Result<(), Error> {
+    fn draw_view(
+        &self,
+        stock_id: &str,
+        style: &diagram::DiagramStyle,
+        output: &diagram::Output,
+    ) -> Result<(), Error> {
         match *self {
-            Strategy::BollingerBand(ref bollinger_band) => bollinger_band.draw_view(stock_id),
+            Strategy::BollingerBand(ref bollinger_band) => {
+                bollinger_band.draw_view(stock_id, style, output)
+            }
+            Strategy::Script(ref script) => script.draw_view(stock_id, style, output),
+            Strategy::Rule(ref rule) => rule.draw_view(stock_id, style, output),
+            Strategy::Onnx(ref onnx) => onnx.draw_view(stock_id, style, output),
+            Strategy::Rsi(ref rsi) => rsi.draw_view(stock_id, style, output),
+            Strategy::Squeeze(ref squeeze) => squeeze.draw_view(stock_id, style, output),
+            Strategy::Gap(ref gap) => gap.draw_view(stock_id, style, output),
+            Strategy::MaCrossover(ref ma_crossover) => {
+                ma_crossover.draw_view(stock_id, style, output)
+            }
+            Strategy::HighBreakout(ref high_breakout) => {
+                high_breakout.draw_view(stock_id, style, output)
+            }
+            Strategy::Composite(ref composite) => composite.draw_view(stock_id, style, output),
+            Strategy::Dca(ref dca) => dca.draw_view(stock_id, style, output),
+        }
+    }
+    fn export_view(&self, stock_id: &str, file_path: &str) -> Result<(), Error> {
+        match *self {
+            Strategy::BollingerBand(ref bollinger_band) => {
+                bollinger_band.export_view(stock_id, file_path)
+            }
+            Strategy::Script(ref script) => script.export_view(stock_id, file_path),
+            Strategy::Rule(ref rule) => rule.export_view(stock_id, file_path),
+            Strategy::Onnx(ref onnx) => onnx.export_view(stock_id, file_path),
+            Strategy::Rsi(ref rsi) => rsi.export_view(stock_id, file_path),
+            Strategy::Squeeze(ref squeeze) => squeeze.export_view(stock_id, file_path),
+            Strategy::Gap(ref gap) => gap.export_view(stock_id, file_path),
+            Strategy::MaCrossover(ref ma_crossover) => {
+                ma_crossover.export_view(stock_id, file_path)
+            }
+            Strategy::HighBreakout(ref high_breakout) => {
+                high_breakout.export_view(stock_id, file_path)
+            }
+            Strategy::Composite(ref composite) => composite.export_view(stock_id, file_path),
+            Strategy::Dca(ref dca) => dca.export_view(stock_id, file_path),
+        }
+    }
+    fn min_history_days(&self) -> usize {
+        match *self {
+            Strategy::BollingerBand(ref bollinger_band) => bollinger_band.min_history_days(),
+            Strategy::Script(ref script) => script.min_history_days(),
+            Strategy::Rule(ref rule) => rule.min_history_days(),
+            Strategy::Onnx(ref onnx) => onnx.min_history_days(),
+            Strategy::Rsi(ref rsi) => rsi.min_history_days(),
+            Strategy::Squeeze(ref squeeze) => squeeze.min_history_days(),
+            Strategy::Gap(ref gap) => gap.min_history_days(),
+            Strategy::MaCrossover(ref ma_crossover) => ma_crossover.min_history_days(),
+            Strategy::HighBreakout(ref high_breakout) => high_breakout.min_history_days(),
+            Strategy::Composite(ref composite) => composite.min_history_days(),
+            Strategy::Dca(ref dca) => dca.min_history_days(),
+        }
+    }
+    fn data_requirements(&self) -> DataRequirements {
+        match *self {
+            Strategy::BollingerBand(ref bollinger_band) => bollinger_band.data_requirements(),
+            Strategy::Script(ref script) => script.data_requirements(),
+            Strategy::Rule(ref rule) => rule.data_requirements(),
+            Strategy::Onnx(ref onnx) => onnx.data_requirements(),
+            Strategy::Rsi(ref rsi) => rsi.data_requirements(),
+            Strategy::Squeeze(ref squeeze) => squeeze.data_requirements(),
+            Strategy::Gap(ref gap) => gap.data_requirements(),
+            Strategy::MaCrossover(ref ma_crossover) => ma_crossover.data_requirements(),
+            Strategy::HighBreakout(ref high_breakout) => high_breakout.data_requirements(),
+            Strategy::Composite(ref composite) => composite.data_requirements(),
+            Strategy::Dca(ref dca) => dca.data_requirements(),
         }
     }
 }
@@ -120,11 +414,128 @@ impl StrategyAPI for Strategy {
 pub struct StrategyFactory {}
 
 impl StrategyFactory {
-    pub fn get(strategy: Strategies, backend_op: Rc<dyn backend::BackendOp>) -> Strategy {
+    /// Builds `strategy` with the parameters of `strategy_params`. Strategies carrying their
+    /// own parameters, such as `Strategies::MaCrossover` and `Strategies::Dca`, keep those.
+    /// Composites are built as given; the config refuses the invalid ones when it loads.
+    pub fn get(
+        strategy: Strategies,
+        strategy_params: &StrategyParams,
+        backend_op: Rc<dyn backend::BackendOp>,
+    ) -> Strategy {
         match strategy {
             Strategies::BollingerBand => Strategy::BollingerBand(bollinger_band::Strategy {
-                backend_op: backend_op,
+                backend_op,
+                back_adjustment: adjust::BackAdjustment::default(),
+                band_params: strategy_params.bollinger_band.clone(),
             }),
+            Strategies::Script(script_path) => Strategy::Script(Box::new(
+                script::ScriptStrategy::new(backend_op, &script_path),
+            )),
+            Strategies::Rule(rule_set) => {
+                Strategy::Rule(rule::RuleStrategy::new(backend_op, rule_set))
+            }
+            Strategies::Onnx(model_config) => {
+                Strategy::Onnx(Box::new(onnx::OnnxStrategy::new(backend_op, model_config)))
+            }
+            Strategies::Rsi => Strategy::Rsi(rsi::Strategy {
+                backend_op,
+                back_adjustment: adjust::BackAdjustment::default(),
+                rsi_params: strategy_params.rsi.clone(),
+            }),
+            Strategies::Squeeze => Strategy::Squeeze(squeeze::Strategy {
+                backend_op,
+                back_adjustment: adjust::BackAdjustment::default(),
+                squeeze_params: strategy_params.squeeze.clone(),
+            }),
+            Strategies::Gap => Strategy::Gap(gap::Strategy {
+                backend_op,
+                back_adjustment: adjust::BackAdjustment::default(),
+                gap_params: strategy_params.gap.clone(),
+            }),
+            Strategies::MaCrossover(periods) => Strategy::MaCrossover(ma_crossover::Strategy {
+                backend_op,
+                back_adjustment: adjust::BackAdjustment::default(),
+                periods,
+            }),
+            Strategies::HighBreakout => Strategy::HighBreakout(high_breakout::Strategy {
+                backend_op,
+                back_adjustment: adjust::BackAdjustment::default(),
+                breakout_params: strategy_params.high_breakout.clone(),
+            }),
+            Strategies::Composite(members) => {
+                StrategyFactory::build_composite(members, strategy_params, backend_op)
+            }
+            Strategies::Dca(plan) => Strategy::Dca(dca::Strategy {
+                backend_op,
+                back_adjustment: adjust::BackAdjustment::default(),
+                plan,
+            }),
+        }
+    }
+
+    /// Builds an ensemble scoring with the weighted sum of the points of `members`, each built
+    /// as `get` would. Refuses an ensemble without members or with a weight that is not a finite
+    /// positive number, which would leave its scores meaningless.
+    pub fn get_composite(
+        members: Vec<(Strategies, f64)>,
+        strategy_params: &StrategyParams,
+        backend_op: Rc<dyn backend::BackendOp>,
+    ) -> Result<Strategy, Error> {
+        let weights: Vec<f64> = members.iter().map(|(_, weight)| *weight).collect();
+
+        match composite::is_valid(&weights) {
+            true => Ok(StrategyFactory::build_composite(
+                members,
+                strategy_params,
+                backend_op,
+            )),
+            false => Err(Error::InvalidComposite),
+        }
+    }
+
+    fn build_composite(
+        members: Vec<(Strategies, f64)>,
+        strategy_params: &StrategyParams,
+        backend_op: Rc<dyn backend::BackendOp>,
+    ) -> Strategy {
+        Strategy::Composite(composite::CompositeStrategy {
+            members: members
+                .into_iter()
+                .map(|(member, weight)| {
+                    (
+                        StrategyFactory::get(member, strategy_params, backend_op.clone()),
+                        weight,
+                    )
+                })
+                .collect(),
+        })
+    }
+
+    /// Builds the strategy over an in-memory backend holding a single series under `stock_id`,
+    /// for callers that bring their own prices instead of a database.
+    pub fn get_by_records(
+        strategy: Strategies,
+        stock_id: &str,
+        records: Vec<schema::RawData>,
+    ) -> Result<Strategy, Error> {
+        let backend_op = Rc::new(memory::MemoryBackend::new());
+
+        backend::BackendOp::batch_insert(
+            backend_op.as_ref(),
+            &records
+                .into_iter()
+                .map(|record| (stock_id.to_owned(), record))
+                .collect(),
+        )?;
+        match strategy {
+            Strategies::Composite(members) => {
+                StrategyFactory::get_composite(members, &StrategyParams::default(), backend_op)
+            }
+            strategy => Ok(StrategyFactory::get(
+                strategy,
+                &StrategyParams::default(),
+                backend_op,
+            )),
         }
     }
 }
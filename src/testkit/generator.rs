@@ -0,0 +1,154 @@
+use crate::storage::backend;
+use crate::strategy::schema;
+
+/// Half of the intraday range around the open and close of a synthetic bar.
+const INTRADAY_RANGE: f64 = 0.005;
+
+enum Segment {
+    Trend {
+        days: usize,
+        daily_return: f64,
+    },
+    MeanReverting {
+        days: usize,
+        amplitude: f64,
+        period: usize,
+    },
+    Gap {
+        ratio: f64,
+    },
+}
+
+/// Builds a synthetic OHLCV series on weekdays, segment after segment, each continuing from the
+/// close of the previous one.
+pub struct SeriesBuilder {
+    pub start_date: chrono::NaiveDate,
+    pub start_price: f64,
+    pub trading_volume: u64,
+    segments: Vec<Segment>,
+}
+
+impl SeriesBuilder {
+    pub fn new(start_date: chrono::NaiveDate, start_price: f64) -> Self {
+        SeriesBuilder {
+            start_date,
+            start_price,
+            trading_volume: 1000,
+            segments: Vec::new(),
+        }
+    }
+
+    /// Moves the close by `daily_return` percent every day.
+    pub fn trend(mut self, days: usize, daily_return: f64) -> Self {
+        self.segments.push(Segment::Trend { days, daily_return });
+        self
+    }
+
+    /// Oscillates the close around its current level by up to `amplitude` percent, with a full
+    /// cycle every `period` days.
+    pub fn mean_reverting(mut self, days: usize, amplitude: f64, period: usize) -> Self {
+        self.segments.push(Segment::MeanReverting {
+            days,
+            amplitude,
+            period,
+        });
+        self
+    }
+
+    pub fn flat(self, days: usize) -> Self {
+        self.trend(days, 0.0)
+    }
+
+    /// Opens the next bar `ratio` percent away from the last close.
+    pub fn gap(mut self, ratio: f64) -> Self {
+        self.segments.push(Segment::Gap { ratio });
+        self
+    }
+
+    pub fn build(&self) -> Vec<schema::RawData> {
+        let mut records = Vec::new();
+        let mut date = self.start_date;
+        let mut close = self.start_price;
+        let mut open = self.start_price;
+
+        for segment in &self.segments {
+            let closes: Vec<f64> = match *segment {
+                Segment::Trend { days, daily_return } => (1..=days)
+                    .map(|day| close * (1.0 + daily_return / 100.0).powi(day as i32))
+                    .collect(),
+                Segment::MeanReverting {
+                    days,
+                    amplitude,
+                    period,
+                } => (1..=days)
+                    .map(|day| {
+                        let phase = 2.0 * std::f64::consts::PI * day as f64 / period.max(1) as f64;
+
+                        close * (1.0 + amplitude / 100.0 * phase.sin())
+                    })
+                    .collect(),
+                Segment::Gap { ratio } => {
+                    open = close * (1.0 + ratio / 100.0);
+                    continue;
+                }
+            };
+
+            for next_close in closes {
+                date = get_weekday(date, !records.is_empty());
+                records.push(get_bar(date, open, next_close, close, self.trading_volume));
+                close = next_close;
+                open = next_close;
+            }
+        }
+        records
+    }
+
+    /// Builds the series and stores it under `stock_id`.
+    pub fn insert(
+        &self,
+        backend_op: &dyn backend::BackendOp,
+        stock_id: &str,
+    ) -> Result<Vec<schema::RawData>, backend::Error> {
+        let records = self.build();
+
+        backend_op.batch_insert(
+            &records
+                .iter()
+                .map(|record| (stock_id.to_owned(), record.clone()))
+                .collect(),
+        )?;
+        Ok(records)
+    }
+}
+
+fn get_weekday(date: chrono::NaiveDate, advance: bool) -> chrono::NaiveDate {
+    let mut date = if advance {
+        date.succ_opt().unwrap()
+    } else {
+        date
+    };
+
+    while chrono::Datelike::weekday(&date).number_from_monday() > 5 {
+        date = date.succ_opt().unwrap();
+    }
+    date
+}
+
+fn get_bar(
+    date: chrono::NaiveDate,
+    open: f64,
+    close: f64,
+    prev_close: f64,
+    trading_volume: u64,
+) -> schema::RawData {
+    schema::RawData {
+        open,
+        high: open.max(close) * (1.0 + INTRADAY_RANGE),
+        low: open.min(close) * (1.0 - INTRADAY_RANGE),
+        close,
+        spread: close - prev_close,
+        date,
+        trading_volume,
+        trading_money: (close * trading_volume as f64) as u64,
+    }
+}
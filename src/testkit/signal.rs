@@ -0,0 +1,85 @@
+use crate::strategy::{schema, strategy};
+
+/// Days of `records` on which the strategy scores the stock above zero.
+pub fn get_buy_dates(
+    strategy: &dyn strategy::StrategyAPI,
+    stock_id: &str,
+    records: &[schema::RawData],
+) -> Result<Vec<chrono::NaiveDate>, strategy::Error> {
+    let mut dates = Vec::new();
+
+    for record in records {
+        if strategy.analyze(stock_id, record.date)?.point > 0 {
+            dates.push(record.date);
+        }
+    }
+    Ok(dates)
+}
+
+/// Days of `records` after `hold_date` on which the strategy settles a position held since then.
+pub fn get_settle_dates(
+    strategy: &dyn strategy::StrategyAPI,
+    stock_id: &str,
+    hold_date: chrono::NaiveDate,
+    records: &[schema::RawData],
+) -> Result<Vec<(chrono::NaiveDate, strategy::SettleReason)>, strategy::Error> {
+    let mut dates = Vec::new();
+
+    for record in records.iter().filter(|record| record.date > hold_date) {
+        if let Some(settle_reason) = strategy.settle_check(stock_id, hold_date, record.date)? {
+            dates.push((record.date, settle_reason));
+        }
+    }
+    Ok(dates)
+}
+
+pub fn assert_buy_signal(
+    strategy: &dyn strategy::StrategyAPI,
+    stock_id: &str,
+    date: chrono::NaiveDate,
+) {
+    let score = strategy.analyze(stock_id, date).unwrap();
+
+    assert!(
+        score.point > 0,
+        "expected a buy signal for {} on {}, got score {}",
+        stock_id,
+        date,
+        score.point
+    );
+}
+
+pub fn assert_no_buy_signal(
+    strategy: &dyn strategy::StrategyAPI,
+    stock_id: &str,
+    records: &[schema::RawData],
+) {
+    let buy_dates = get_buy_dates(strategy, stock_id, records).unwrap();
+
+    assert!(
+        buy_dates.is_empty(),
+        "expected no buy signal for {}, got one on {:?}",
+        stock_id,
+        buy_dates
+    );
+}
+
+pub fn assert_settle_signal(
+    strategy: &dyn strategy::StrategyAPI,
+    stock_id: &str,
+    hold_date: chrono::NaiveDate,
+    date: chrono::NaiveDate,
+    settle_reason: strategy::SettleReason,
+) {
+    let actual = strategy.settle_check(stock_id, hold_date, date).unwrap();
+
+    assert_eq!(
+        actual,
+        Some(settle_reason),
+        "expected {:?} for {} held since {} on {}",
+        settle_reason,
+        stock_id,
+        hold_date,
+        date
+    );
+}
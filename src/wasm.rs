@@ -0,0 +1,98 @@
+use wasm_bindgen::prelude::*;
+
+use crate::dataview::view::{self, Transform};
+use crate::strategy::schema;
+use crate::strategy::strategy::{self, StrategyAPI};
+
+/// Stock id the series passed from JavaScript is stored under.
+const SERIES_ID: &str = "series";
+
+fn get_date(date: &str) -> Result<chrono::NaiveDate, JsError> {
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| JsError::new(&format!("invalid date {}", date)))
+}
+
+fn get_records(records_json: &str) -> Result<Vec<schema::RawData>, JsError> {
+    serde_json::from_str(records_json).map_err(|err| JsError::new(&err.to_string()))
+}
+
+fn get_strategy(strategy: &str, records_json: &str) -> Result<strategy::Strategy, JsError> {
+    let strategies: strategy::Strategies = serde_yaml::from_str(strategy)
+        .map_err(|_| JsError::new(&format!("unknown strategy {}", strategy)))?;
+
+    strategy::StrategyFactory::get_by_records(strategies, SERIES_ID, get_records(records_json)?)
+        .map_err(strategy_error)
+}
+
+fn strategy_error(err: strategy::Error) -> JsError {
+    JsError::new(&format!("{:?}", err))
+}
+
+fn view_error(err: view::Error) -> JsError {
+    JsError::new(&format!("{:?}", err))
+}
+
+/// Score of the series on `assess_date`; above zero is a buy signal. `records_json` is a JSON
+/// array of `RawData`.
+#[wasm_bindgen]
+pub fn analyze(strategy: &str, records_json: &str, assess_date: &str) -> Result<i64, JsError> {
+    let strategy = get_strategy(strategy, records_json)?;
+
+    Ok(strategy
+        .analyze(SERIES_ID, get_date(assess_date)?)
+        .map_err(strategy_error)?
+        .point)
+}
+
+/// Settle reason of a position held since `hold_date`, if the strategy exits it on
+/// `assess_date`.
+#[wasm_bindgen]
+pub fn settle_check(
+    strategy: &str,
+    records_json: &str,
+    hold_date: &str,
+    assess_date: &str,
+) -> Result<Option<String>, JsError> {
+    let strategy = get_strategy(strategy, records_json)?;
+
+    Ok(strategy
+        .settle_check(SERIES_ID, get_date(hold_date)?, get_date(assess_date)?)
+        .map_err(strategy_error)?
+        .map(|settle_reason| format!("{:?}", settle_reason)))
+}
+
+/// The series transformed into a view, named as in `draw_diagram --view`, as a JSON array.
+#[wasm_bindgen]
+pub fn get_view(view: &str, records_json: &str) -> Result<String, JsError> {
+    let records = get_records(records_json)?;
+    let json = match view
+        .parse::<view::Views>()
+        .map_err(|err| JsError::new(&err))?
+    {
+        view::Views::None => serde_json::to_string(&records),
+        view::Views::BollingerBand => serde_json::to_string(
+            &view::BollingerBandView::transform(&records).map_err(view_error)?,
+        ),
+        view::Views::Atr => {
+            serde_json::to_string(&view::AtrView::transform(&records).map_err(view_error)?)
+        }
+        view::Views::EwmaVolatility => serde_json::to_string(
+            &view::EwmaVolatilityView::transform(&records).map_err(view_error)?,
+        ),
+        view::Views::Returns => {
+            serde_json::to_string(&view::ReturnsView::transform(&records).map_err(view_error)?)
+        }
+        view::Views::Rsi => {
+            serde_json::to_string(&view::RsiView::transform(&records).map_err(view_error)?)
+        }
+        view::Views::Macd => {
+            serde_json::to_string(&view::MacdView::transform(&records).map_err(view_error)?)
+        }
+        view::Views::Keltner => {
+            serde_json::to_string(&view::KeltnerView::transform(&records).map_err(view_error)?)
+        }
+        view::Views::Beta => return Err(JsError::new("the beta view needs a benchmark")),
+    };
+
+    json.map_err(|err| JsError::new(&err.to_string()))
+}